@@ -0,0 +1,116 @@
+use futures::{SinkExt, StreamExt};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::dto::{
+    ControlAcknowledgement, ControlCommand, ControlNotification, ControlRequest, ControlResult,
+    Operation,
+};
+use crate::{ZagreusClient, API_KEY_HEADER};
+
+/// Client of the control websocket of an instance (`/ws/control/{instance}`), which sends
+/// operations without the overhead of an HTTP request each, e.g. for clocks and tickers.
+/// Commands are sent one after the other and every command waits for its acknowledgement.
+pub struct ControlClient {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    next_request_id: u64,
+    /// Notifications received while waiting for acknowledgements.
+    notifications: Vec<ControlNotification>,
+}
+
+impl ZagreusClient {
+    /// Connects to the control websocket of the instance with the API key of the client.
+    pub async fn connect_control(&self, instance: &str) -> anyhow::Result<ControlClient> {
+        let mut url = self.get_url(["ws", "control", instance]);
+        let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+        url.set_scheme(scheme)
+            .map_err(|_| anyhow::anyhow!("Could not create websocket URL of {}.", url))?;
+        let mut request = url.as_str().into_client_request()?;
+        if let Some(api_key) = self.get_api_key() {
+            request
+                .headers_mut()
+                .insert(API_KEY_HEADER, HeaderValue::from_str(api_key)?);
+        }
+        let (socket, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(ControlClient {
+            socket,
+            next_request_id: 0,
+            notifications: Vec::new(),
+        })
+    }
+}
+
+impl ControlClient {
+    /// Sends the operations, several operations are applied at once like a batch.
+    pub async fn send(&mut self, operations: Vec<Operation>) -> anyhow::Result<ControlResult> {
+        self.execute(ControlCommand::Send { operations }).await
+    }
+
+    pub async fn approve(&mut self, change: u64) -> anyhow::Result<ControlResult> {
+        self.execute(ControlCommand::Approve { change }).await
+    }
+
+    pub async fn reject(&mut self, change: u64) -> anyhow::Result<ControlResult> {
+        self.execute(ControlCommand::Reject { change }).await
+    }
+
+    /// Adds a note for the other operators of the show day.
+    pub async fn note(
+        &mut self,
+        text: &str,
+        operator: Option<&str>,
+        pinned: bool,
+    ) -> anyhow::Result<ControlResult> {
+        self.execute(ControlCommand::Note {
+            text: text.to_owned(),
+            operator: operator.map(str::to_owned),
+            pinned,
+        })
+        .await
+    }
+
+    pub async fn pin(&mut self, note: u64, pinned: bool) -> anyhow::Result<ControlResult> {
+        self.execute(ControlCommand::Pin { note, pinned }).await
+    }
+
+    /// Returns the notifications (e.g. notes of other operators) which were received since the
+    /// last call. Notifications are only received while a command waits for its acknowledgement.
+    pub fn take_notifications(&mut self) -> Vec<ControlNotification> {
+        std::mem::take(&mut self.notifications)
+    }
+
+    pub async fn close(mut self) -> anyhow::Result<()> {
+        self.socket.close(None).await?;
+        Ok(())
+    }
+
+    async fn execute(&mut self, command: ControlCommand) -> anyhow::Result<ControlResult> {
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let request = ControlRequest {
+            request_id: Some(request_id),
+            command,
+        };
+        self.socket
+            .send(Message::Text(serde_json::to_string(&request)?))
+            .await?;
+        while let Some(message) = self.socket.next().await {
+            let Message::Text(text) = message? else {
+                continue;
+            };
+            let message: serde_json::Value = serde_json::from_str(&text)?;
+            if message.get("event").is_some() {
+                self.notifications.push(serde_json::from_value(message)?);
+                continue;
+            }
+            let acknowledgement: ControlAcknowledgement = serde_json::from_value(message)?;
+            if acknowledgement.request_id == Some(request_id) {
+                return Ok(acknowledgement.result);
+            }
+        }
+        anyhow::bail!("Server closed the control connection.")
+    }
+}
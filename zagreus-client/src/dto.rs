@@ -0,0 +1,414 @@
+//! Types of the bodies of the API and the control protocol, which are shared with the server.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AssetSource {
+    Zagreus,
+    Template,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetReference {
+    pub asset: String,
+    pub asset_source: AssetSource,
+}
+
+/// A manipulation of a template instance as accepted by the API, which can be stored (e.g. while
+/// waiting for approval) and converted to the message sent to the clients.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+#[serde(tag = "operation", rename_all = "camelCase")]
+pub enum Operation {
+    #[serde(rename_all = "camelCase")]
+    SetText {
+        id: String,
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tween_duration: Option<u64>,
+    },
+    AddClass {
+        id: String,
+        class: String,
+    },
+    RemoveClass {
+        id: String,
+        class: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    SetImageSource {
+        id: String,
+        asset: String,
+        asset_source: AssetSource,
+    },
+    #[serde(rename_all = "camelCase")]
+    ExecuteAnimation {
+        animation_sequence: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        execute_at: Option<i64>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum OperationKind {
+    SetText,
+    AddClass,
+    RemoveClass,
+    SetImageSource,
+    ExecuteAnimation,
+}
+
+impl Operation {
+    pub fn kind(&self) -> OperationKind {
+        match self {
+            Operation::SetText { .. } => OperationKind::SetText,
+            Operation::AddClass { .. } => OperationKind::AddClass,
+            Operation::RemoveClass { .. } => OperationKind::RemoveClass,
+            Operation::SetImageSource { .. } => OperationKind::SetImageSource,
+            Operation::ExecuteAnimation { .. } => OperationKind::ExecuteAnimation,
+        }
+    }
+
+    /// Returns the id of the manipulated element, animations do not manipulate a single element.
+    pub fn get_element(&self) -> Option<&str> {
+        match self {
+            Operation::SetText { id, .. }
+            | Operation::AddClass { id, .. }
+            | Operation::RemoveClass { id, .. }
+            | Operation::SetImageSource { id, .. } => Some(id),
+            Operation::ExecuteAnimation { .. } => None,
+        }
+    }
+}
+
+/// Step of a sequence, which is executed after its delay relative to the previous step.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SequenceStep {
+    #[serde(default)]
+    pub delay_ms: u64,
+    #[serde(flatten)]
+    pub operation: Operation,
+}
+
+/// Operations which were held back until a user with approve permission confirms them.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingChange {
+    pub id: u64,
+    pub instance: String,
+    pub operations: Vec<Operation>,
+    /// Channel whose renderers receive the operations once they are approved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub submitted: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetTextDto {
+    pub id: String,
+    pub text: String,
+    /// Duration in milliseconds over which numeric texts are tweened to the new value.
+    pub tween_duration: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct ManipulateClassDto {
+    pub id: String,
+    pub class: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SetImageSourceDto {
+    pub id: String,
+    pub asset: String,
+    pub asset_source: AssetSource,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct ExecuteAnimationQuery {
+    /// Server time (milliseconds since the epoch) at which all clients execute the animation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at: Option<i64>,
+    /// Delay in milliseconds after which all clients execute the animation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delay: Option<i64>,
+    /// Channel whose renderers execute the animation instead of all renderers of the instance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Id of the single client which executes the animation, as listed by the clients endpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<usize>,
+}
+
+/// Output channels to which the data of an instance is routed.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct RouteDto {
+    pub channels: BTreeSet<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct ChannelQuery {
+    /// Channel whose renderers receive the operations instead of all renderers of the instance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Id of the single client which receives the operations, as listed by the clients
+    /// endpoints. The operations do not change the state of the instance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct DateRangeQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LockRequest {
+    pub operator: String,
+    /// Overrides the lock of another operator.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct UploadAssetResponseDto {
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct FetchAssetDto {
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignAssetDto {
+    /// Name of the asset (as uploaded) or file of a specific version.
+    pub asset: String,
+    pub valid_seconds: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedAssetUrl {
+    pub url: String,
+    pub expires: DateTime<Utc>,
+}
+
+/// Metadata of the current version of an asset.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetInfo {
+    pub name: String,
+    pub file: String,
+    pub size: u64,
+    pub content_type: String,
+    /// Time at which the content of the current version was last uploaded.
+    pub uploaded: DateTime<Utc>,
+    /// SHA-256 hash of the content, under which the file is stored.
+    pub sha256: String,
+}
+
+/// Starts a resumable upload of an asset, whose content is sent in chunks.
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUploadDto {
+    /// Name of the asset, e.g. `intro.mp4`.
+    pub name: String,
+    /// Total size of the asset in bytes.
+    pub size: u64,
+    /// Hex encoded SHA-256 hash of the whole content, which is checked on completion.
+    pub sha256: String,
+}
+
+/// Progress of a resumable upload. The next chunk has to start at the offset.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadStatusDto {
+    pub id: String,
+    pub name: String,
+    pub size: u64,
+    pub offset: u64,
+}
+
+/// Requests fake data for the renderers of a preview channel. Elements with data defaults, data
+/// source fields or current data receive fake data if no elements are given.
+#[derive(Serialize, Deserialize, Clone, Default, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FakeDataRequestDto {
+    /// Channel of the renderers which receive the fake data, `preview` if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub texts: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<String>>,
+    /// Generates the same data for the same seed, e.g. to compare layouts before and after a
+    /// change.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+/// Fake data which was sent to the renderers of the preview channel.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FakeDataDto {
+    pub channel: String,
+    pub texts: BTreeMap<String, String>,
+    pub images: BTreeMap<String, AssetReference>,
+    /// Number of renderers which received the data.
+    pub clients: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default, Debug, JsonSchema)]
+pub struct PrecacheAssetsDto {
+    pub assets: Option<Vec<AssetReference>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct PinResourceDto {
+    pub url: String,
+    /// Expected subresource integrity of the resource. The computed integrity is pinned if not set.
+    pub integrity: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
+pub struct RuntimeVersionDto {
+    pub version: String,
+}
+
+/// Command of an external controller. The request id is returned with the acknowledgement, so
+/// controllers can match it to the command.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<u64>,
+    #[serde(flatten)]
+    pub command: ControlCommand,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub enum ControlCommand {
+    /// Sends the operations like the data endpoints, several operations at once like a batch.
+    Send {
+        operations: Vec<Operation>,
+    },
+    Approve {
+        change: u64,
+    },
+    Reject {
+        change: u64,
+    },
+    /// Adds a note for the other operators, which is sent to all controllers.
+    Note {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        operator: Option<String>,
+        #[serde(default)]
+        pinned: bool,
+    },
+    /// Pins a note of the current show day or unpins it.
+    Pin {
+        note: u64,
+        pinned: bool,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishResultDto {
+    /// Number of renderers which subscribed to the topic and received the value.
+    pub clients: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ControlAcknowledgement {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<u64>,
+    #[serde(flatten)]
+    pub result: ControlResult,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ControlResult {
+    Sent,
+    /// The operations require approval and were held back.
+    Pending {
+        change: PendingChange,
+    },
+    Approved {
+        change: PendingChange,
+    },
+    Rejected {
+        change: PendingChange,
+    },
+    /// The note was added or pinned.
+    Noted {
+        note: OperatorNote,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Message which the server sends to all controllers without a request, e.g. when an operator
+/// added a note.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum ControlNotification {
+    /// A note was added or (un)pinned. Pinned notes of the current show day are also sent when
+    /// the controller connects.
+    Note { note: OperatorNote },
+}
+
+/// Note of an operator for the graphics team, e.g. the pinned warning "graphic 12 broken, don't
+/// fire". Notes are stored per show day.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorNote {
+    /// Number of the note within its show day.
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    /// Instance whose controller added the note, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    pub text: String,
+    pub pinned: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteDto {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub operator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+/// Name of a recorded session, e.g. `2024-03-01-final`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionDto {
+    pub name: String,
+}
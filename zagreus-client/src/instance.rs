@@ -0,0 +1,634 @@
+use std::collections::BTreeMap;
+
+use reqwest::{RequestBuilder, StatusCode};
+use serde_json::Value;
+
+use crate::dto::{
+    AssetReference, ChannelQuery, DateRangeQuery, ExecuteAnimationQuery, FakeDataDto,
+    FakeDataRequestDto, LockRequest, ManipulateClassDto, Operation, PendingChange,
+    PrecacheAssetsDto, PublishResultDto, SequenceStep, SetImageSourceDto, SetTextDto,
+};
+use crate::{send, send_empty, send_json, send_text, ZagreusClient};
+
+/// Sends operations and returns the pending change if they were held back for approval.
+async fn send_operations(request: RequestBuilder) -> anyhow::Result<Option<PendingChange>> {
+    let response = send(request).await?;
+    if response.status() == StatusCode::ACCEPTED {
+        Ok(Some(response.json().await?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Endpoints below `/api/instance/{instance}`. Documents such as rules or rundowns are passed as
+/// JSON in the format described in the API documentation.
+impl ZagreusClient {
+    fn instance_request(
+        &self,
+        method: reqwest::Method,
+        instance: &str,
+        segments: &[&str],
+    ) -> RequestBuilder {
+        let segments = ["api", "instance", instance]
+            .into_iter()
+            .chain(segments.iter().copied());
+        self.request(method, segments)
+    }
+
+    fn instance_get(&self, instance: &str, segments: &[&str]) -> RequestBuilder {
+        self.instance_request(reqwest::Method::GET, instance, segments)
+    }
+
+    fn instance_post(&self, instance: &str, segments: &[&str]) -> RequestBuilder {
+        self.instance_request(reqwest::Method::POST, instance, segments)
+    }
+
+    fn instance_put(&self, instance: &str, segments: &[&str]) -> RequestBuilder {
+        self.instance_request(reqwest::Method::PUT, instance, segments)
+    }
+
+    fn instance_delete(&self, instance: &str, segments: &[&str]) -> RequestBuilder {
+        self.instance_request(reqwest::Method::DELETE, instance, segments)
+    }
+
+    pub async fn delete_instance(&self, instance: &str) -> anyhow::Result<()> {
+        send_empty(self.instance_delete(instance, &[])).await
+    }
+
+    pub async fn clone_instance(&self, instance: &str, clone_name: &str) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_post(instance, &["clone"])
+                .query(&[("as", clone_name)]),
+        )
+        .await
+    }
+
+    pub async fn set_text(
+        &self,
+        instance: &str,
+        payload: &SetTextDto,
+        query: &ChannelQuery,
+    ) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(
+            self.instance_post(instance, &["data", "text"])
+                .query(query)
+                .json(payload),
+        )
+        .await
+    }
+
+    pub async fn add_class(
+        &self,
+        instance: &str,
+        payload: &ManipulateClassDto,
+        query: &ChannelQuery,
+    ) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(
+            self.instance_post(instance, &["data", "class", "add"])
+                .query(query)
+                .json(payload),
+        )
+        .await
+    }
+
+    pub async fn remove_class(
+        &self,
+        instance: &str,
+        payload: &ManipulateClassDto,
+        query: &ChannelQuery,
+    ) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(
+            self.instance_post(instance, &["data", "class", "remove"])
+                .query(query)
+                .json(payload),
+        )
+        .await
+    }
+
+    pub async fn execute_animation(
+        &self,
+        instance: &str,
+        animation_sequence: &str,
+        query: &ExecuteAnimationQuery,
+    ) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(
+            self.instance_post(instance, &["data", "animation", animation_sequence])
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn set_image_source(
+        &self,
+        instance: &str,
+        payload: &SetImageSourceDto,
+        query: &ChannelQuery,
+    ) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(
+            self.instance_post(instance, &["data", "image"])
+                .query(query)
+                .json(payload),
+        )
+        .await
+    }
+
+    pub async fn execute_batch(
+        &self,
+        instance: &str,
+        operations: &[Operation],
+        query: &ChannelQuery,
+    ) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(
+            self.instance_post(instance, &["data", "batch"])
+                .query(query)
+                .json(operations),
+        )
+        .await
+    }
+
+    pub async fn execute_sequence(
+        &self,
+        instance: &str,
+        steps: &[SequenceStep],
+        query: &ChannelQuery,
+    ) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_post(instance, &["data", "sequence"])
+                .query(query)
+                .json(steps),
+        )
+        .await
+    }
+
+    pub async fn get_pending_changes(&self, instance: &str) -> anyhow::Result<Vec<PendingChange>> {
+        send_json(self.instance_get(instance, &["pending"])).await
+    }
+
+    pub async fn approve_change(
+        &self,
+        instance: &str,
+        change: u64,
+    ) -> anyhow::Result<PendingChange> {
+        send_json(self.instance_post(instance, &["pending", &change.to_string(), "approve"])).await
+    }
+
+    pub async fn reject_change(
+        &self,
+        instance: &str,
+        change: u64,
+    ) -> anyhow::Result<PendingChange> {
+        send_json(self.instance_delete(instance, &["pending", &change.to_string()])).await
+    }
+
+    pub async fn get_config_document(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["config"])).await
+    }
+
+    pub async fn set_config_document(&self, instance: &str, config: &Value) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["config"]).json(config)).await
+    }
+
+    pub async fn get_instance_feature_flags(
+        &self,
+        instance: &str,
+    ) -> anyhow::Result<BTreeMap<String, bool>> {
+        send_json(self.instance_get(instance, &["feature-flags"])).await
+    }
+
+    pub async fn set_instance_feature_flags(
+        &self,
+        instance: &str,
+        flags: &BTreeMap<String, bool>,
+    ) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["feature-flags"]).json(flags)).await
+    }
+
+    pub async fn set_instance_feature_flag(
+        &self,
+        instance: &str,
+        flag: &str,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_put(instance, &["feature-flags", flag])
+                .json(&enabled),
+        )
+        .await
+    }
+
+    pub async fn get_precache_progress(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["precache"])).await
+    }
+
+    /// Instructs the clients to pre-fetch the assets or, if none are given, all assets.
+    pub async fn precache_assets(
+        &self,
+        instance: &str,
+        assets: Option<Vec<AssetReference>>,
+    ) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_post(instance, &["precache"])
+                .json(&PrecacheAssetsDto { assets }),
+        )
+        .await
+    }
+
+    pub async fn get_prefetch_manifest(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["prefetch-manifest"])).await
+    }
+
+    pub async fn get_instance_clients(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["clients"])).await
+    }
+
+    /// Waits for events after the cursor, at most for the timeout in seconds.
+    pub async fn poll_events(
+        &self,
+        instance: &str,
+        cursor: Option<u64>,
+        timeout: Option<u64>,
+    ) -> anyhow::Result<Value> {
+        let mut query = Vec::new();
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor));
+        }
+        if let Some(timeout) = timeout {
+            query.push(("timeout", timeout));
+        }
+        send_json(
+            self.instance_get(instance, &["events", "poll"])
+                .query(&query),
+        )
+        .await
+    }
+
+    pub async fn get_client_errors(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["errors"])).await
+    }
+
+    /// Returns the HTML of the template when the error occurred.
+    pub async fn get_error_snapshot(
+        &self,
+        instance: &str,
+        error_id: u64,
+    ) -> anyhow::Result<String> {
+        send_text(self.instance_get(instance, &["errors", &error_id.to_string(), "snapshot"])).await
+    }
+
+    pub async fn get_state(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["state"])).await
+    }
+
+    pub async fn set_state(
+        &self,
+        instance: &str,
+        state: &Value,
+    ) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(self.instance_put(instance, &["state"]).json(state)).await
+    }
+
+    pub async fn get_data_defaults(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["defaults"])).await
+    }
+
+    pub async fn set_data_defaults(&self, instance: &str, defaults: &Value) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["defaults"]).json(defaults)).await
+    }
+
+    pub async fn clear_instance(&self, instance: &str) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(self.instance_post(instance, &["clear"])).await
+    }
+
+    pub async fn get_rules(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["rules"])).await
+    }
+
+    pub async fn set_rules(&self, instance: &str, rules: &Value) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["rules"]).json(rules)).await
+    }
+
+    pub async fn get_webhooks(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["webhooks"])).await
+    }
+
+    pub async fn set_webhooks(&self, instance: &str, webhooks: &Value) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["webhooks"]).json(webhooks)).await
+    }
+
+    /// Sends fake data to the renderers of a preview channel without changing the state.
+    pub async fn send_fake_data(
+        &self,
+        instance: &str,
+        request: &FakeDataRequestDto,
+    ) -> anyhow::Result<FakeDataDto> {
+        send_json(
+            self.instance_post(instance, &["preview", "fake-data"])
+                .json(request),
+        )
+        .await
+    }
+
+    /// Returns the data staged on the preview channel, or the channel of the query.
+    pub async fn get_staged_data(
+        &self,
+        instance: &str,
+        query: &ChannelQuery,
+    ) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["preview"]).query(query)).await
+    }
+
+    /// Puts the staged data on all renderers at once and returns it, or returns the pending change
+    /// if the data requires approval.
+    pub async fn take_staged_data(
+        &self,
+        instance: &str,
+        query: &ChannelQuery,
+    ) -> anyhow::Result<Value> {
+        send_json(
+            self.instance_post(instance, &["preview", "take"])
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn get_computed_elements(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["computed"])).await
+    }
+
+    pub async fn set_computed_elements(
+        &self,
+        instance: &str,
+        computed: &Value,
+    ) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["computed"]).json(computed)).await
+    }
+
+    pub async fn run_harness(&self, instance: &str, calls: &Value) -> anyhow::Result<Value> {
+        send_json(self.instance_post(instance, &["harness"]).json(calls)).await
+    }
+
+    pub async fn get_rundown(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["rundown"])).await
+    }
+
+    pub async fn set_rundown(&self, instance: &str, rundown: &Value) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["rundown"]).json(rundown)).await
+    }
+
+    pub async fn take_next_page(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_post(instance, &["rundown", "next"])).await
+    }
+
+    pub async fn take_page_by_index(&self, instance: &str, index: usize) -> anyhow::Result<Value> {
+        send_json(self.instance_post(instance, &["rundown", "take", &index.to_string()])).await
+    }
+
+    pub async fn get_button_feedback(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["feedback"])).await
+    }
+
+    pub async fn get_feedback_buttons(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["feedback", "buttons"])).await
+    }
+
+    pub async fn set_feedback_buttons(
+        &self,
+        instance: &str,
+        buttons: &Value,
+    ) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_put(instance, &["feedback", "buttons"])
+                .json(buttons),
+        )
+        .await
+    }
+
+    pub async fn get_localization(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["localization"])).await
+    }
+
+    pub async fn set_translation_bundle(
+        &self,
+        instance: &str,
+        language: &str,
+        bundle: &Value,
+    ) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_put(instance, &["localization", "bundle", language])
+                .json(bundle),
+        )
+        .await
+    }
+
+    pub async fn delete_translation_bundle(
+        &self,
+        instance: &str,
+        language: &str,
+    ) -> anyhow::Result<()> {
+        send_empty(self.instance_delete(instance, &["localization", "bundle", language])).await
+    }
+
+    pub async fn set_localized_labels(
+        &self,
+        instance: &str,
+        labels: &BTreeMap<String, String>,
+    ) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_put(instance, &["localization", "labels"])
+                .json(labels),
+        )
+        .await
+    }
+
+    /// Switches the language and returns the keys without a translation.
+    pub async fn set_active_language(
+        &self,
+        instance: &str,
+        language: &str,
+    ) -> anyhow::Result<Value> {
+        send_json(self.instance_post(instance, &["localization", "language", language])).await
+    }
+
+    pub async fn get_as_run_log(
+        &self,
+        instance: &str,
+        range: &DateRangeQuery,
+    ) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["as-run"]).query(range)).await
+    }
+
+    pub async fn export_as_run_csv(
+        &self,
+        instance: &str,
+        range: &DateRangeQuery,
+    ) -> anyhow::Result<String> {
+        send_text(self.instance_get(instance, &["as-run", "csv"]).query(range)).await
+    }
+
+    pub async fn get_rotations(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["rotations"])).await
+    }
+
+    pub async fn set_rotations(&self, instance: &str, rotations: &Value) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["rotations"]).json(rotations)).await
+    }
+
+    pub async fn get_rotation_report(
+        &self,
+        instance: &str,
+        range: &DateRangeQuery,
+    ) -> anyhow::Result<Value> {
+        send_json(
+            self.instance_get(instance, &["rotations", "report"])
+                .query(range),
+        )
+        .await
+    }
+
+    pub async fn get_emergency_overrides(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["emergency"])).await
+    }
+
+    pub async fn set_emergency_overrides(
+        &self,
+        instance: &str,
+        overrides: &Value,
+    ) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["emergency"]).json(overrides)).await
+    }
+
+    pub async fn activate_emergency_override(
+        &self,
+        instance: &str,
+        name: &str,
+    ) -> anyhow::Result<()> {
+        send_empty(self.instance_post(instance, &["emergency", "activate", name])).await
+    }
+
+    pub async fn release_emergency_override(&self, instance: &str) -> anyhow::Result<()> {
+        send_empty(self.instance_post(instance, &["emergency", "release"])).await
+    }
+
+    pub async fn get_snapshots(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["snapshot"])).await
+    }
+
+    pub async fn get_snapshot(&self, instance: &str, snapshot: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["snapshot", snapshot])).await
+    }
+
+    /// Saves the current state of the instance as snapshot.
+    pub async fn save_snapshot(&self, instance: &str, snapshot: &str) -> anyhow::Result<()> {
+        send_empty(self.instance_post(instance, &["snapshot", snapshot])).await
+    }
+
+    pub async fn set_snapshot(
+        &self,
+        instance: &str,
+        snapshot: &str,
+        state: &Value,
+    ) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_put(instance, &["snapshot", snapshot])
+                .json(state),
+        )
+        .await
+    }
+
+    pub async fn apply_snapshot(
+        &self,
+        instance: &str,
+        snapshot: &str,
+    ) -> anyhow::Result<Option<PendingChange>> {
+        send_operations(self.instance_post(instance, &["snapshot", snapshot, "apply"])).await
+    }
+
+    pub async fn delete_snapshot(&self, instance: &str, snapshot: &str) -> anyhow::Result<()> {
+        send_empty(self.instance_delete(instance, &["snapshot", snapshot])).await
+    }
+
+    /// Returns the retained value of every topic.
+    pub async fn get_retained_topics(
+        &self,
+        instance: &str,
+    ) -> anyhow::Result<BTreeMap<String, Value>> {
+        send_json(self.instance_get(instance, &["topics"])).await
+    }
+
+    /// Publishes the value to the renderers which subscribed to the topic, e.g. `scores/home`.
+    /// A retained value is also sent to renderers subscribing later.
+    pub async fn publish_topic(
+        &self,
+        instance: &str,
+        topic: &str,
+        value: &Value,
+        retain: bool,
+    ) -> anyhow::Result<PublishResultDto> {
+        let segments: Vec<&str> = std::iter::once("topics").chain(topic.split('/')).collect();
+        send_json(
+            self.instance_post(instance, &segments)
+                .query(&[("retain", retain)])
+                .json(value),
+        )
+        .await
+    }
+
+    pub async fn delete_retained_topic(&self, instance: &str, topic: &str) -> anyhow::Result<()> {
+        let segments: Vec<&str> = std::iter::once("topics").chain(topic.split('/')).collect();
+        send_empty(self.instance_delete(instance, &segments)).await
+    }
+
+    pub async fn get_secret_names(&self, instance: &str) -> anyhow::Result<Vec<String>> {
+        send_json(self.instance_get(instance, &["secret"])).await
+    }
+
+    pub async fn set_secret(&self, instance: &str, name: &str, value: &str) -> anyhow::Result<()> {
+        send_empty(self.instance_put(instance, &["secret", name]).json(value)).await
+    }
+
+    pub async fn delete_secret(&self, instance: &str, name: &str) -> anyhow::Result<()> {
+        send_empty(self.instance_delete(instance, &["secret", name])).await
+    }
+
+    pub async fn create_share_link(
+        &self,
+        instance: &str,
+        valid_seconds: Option<i64>,
+    ) -> anyhow::Result<Value> {
+        let query: Vec<_> = valid_seconds
+            .map(|valid_seconds| ("validSeconds", valid_seconds))
+            .into_iter()
+            .collect();
+        send_json(self.instance_post(instance, &["share"]).query(&query)).await
+    }
+
+    pub async fn get_presence(&self, instance: &str) -> anyhow::Result<Value> {
+        send_json(self.instance_get(instance, &["presence"])).await
+    }
+
+    /// Locks the instance for the operator, `force` overrides the lock of another operator.
+    pub async fn lock_instance(
+        &self,
+        instance: &str,
+        operator: &str,
+        force: bool,
+    ) -> anyhow::Result<Value> {
+        send_json(self.instance_post(instance, &["lock"]).json(&LockRequest {
+            operator: operator.to_owned(),
+            force,
+        }))
+        .await
+    }
+
+    pub async fn release_instance_lock(
+        &self,
+        instance: &str,
+        operator: &str,
+    ) -> anyhow::Result<()> {
+        send_empty(
+            self.instance_delete(instance, &["lock"])
+                .query(&[("operator", operator)]),
+        )
+        .await
+    }
+}
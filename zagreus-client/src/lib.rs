@@ -0,0 +1,158 @@
+//! Client of the zagreus server for Rust automation, e.g. a script which updates a scoreboard
+//! from a timing system. The types of the bodies are shared with the server, so the client
+//! follows changes of the API at compile time.
+
+#![deny(clippy::all)]
+
+#[macro_use]
+extern crate serde_derive;
+
+use reqwest::{Method, RequestBuilder, Response, StatusCode, Url};
+use serde::de::DeserializeOwned;
+
+pub mod control;
+pub mod dto;
+mod instance;
+mod server;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Error response of the server. The message is the one shown in the API documentation, e.g.
+/// `Asset does not exist.`.
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Server responded with {}: {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// Client of the HTTP API of a zagreus server. Errors of the server are returned as [`ApiError`],
+/// which can be retrieved with `downcast_ref`.
+#[derive(Clone)]
+pub struct ZagreusClient {
+    http_client: reqwest::Client,
+    base_url: Url,
+    api_key: Option<String>,
+}
+
+impl ZagreusClient {
+    /// Creates a client of the server at the URL, e.g. `http://localhost:58180`.
+    pub fn new(base_url: &str) -> anyhow::Result<ZagreusClient> {
+        let base_url = Url::parse(base_url)?;
+        if base_url.cannot_be_a_base() {
+            anyhow::bail!("{} is not a valid server URL.", base_url);
+        }
+        Ok(ZagreusClient {
+            http_client: reqwest::Client::new(),
+            base_url,
+            api_key: None,
+        })
+    }
+
+    /// Authenticates all requests with the API key.
+    pub fn with_api_key(mut self, api_key: &str) -> ZagreusClient {
+        self.api_key = Some(api_key.to_owned());
+        self
+    }
+
+    pub fn get_base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    pub fn get_api_key(&self) -> Option<&str> {
+        self.api_key.as_deref()
+    }
+
+    /// Returns the URL of the path segments, which are percent-encoded (e.g. asset names with
+    /// spaces).
+    fn get_url<'a>(&self, segments: impl IntoIterator<Item = &'a str>) -> Url {
+        let mut url = self.base_url.clone();
+        if let Ok(mut path) = url.path_segments_mut() {
+            path.pop_if_empty().extend(segments);
+        }
+        url
+    }
+
+    fn request<'a>(
+        &self,
+        method: Method,
+        segments: impl IntoIterator<Item = &'a str>,
+    ) -> RequestBuilder {
+        let request = self.http_client.request(method, self.get_url(segments));
+        match &self.api_key {
+            Some(api_key) => request.header(API_KEY_HEADER, api_key),
+            None => request,
+        }
+    }
+
+    fn get(&self, segments: &[&str]) -> RequestBuilder {
+        self.request(Method::GET, segments.iter().copied())
+    }
+
+    fn post(&self, segments: &[&str]) -> RequestBuilder {
+        self.request(Method::POST, segments.iter().copied())
+    }
+
+    fn put(&self, segments: &[&str]) -> RequestBuilder {
+        self.request(Method::PUT, segments.iter().copied())
+    }
+
+    fn delete(&self, segments: &[&str]) -> RequestBuilder {
+        self.request(Method::DELETE, segments.iter().copied())
+    }
+}
+
+async fn send(request: RequestBuilder) -> anyhow::Result<Response> {
+    let response = request.send().await?;
+    let status = response.status();
+    if status.is_client_error() || status.is_server_error() {
+        let body = response.text().await.unwrap_or_default();
+        // error messages are sent as JSON strings
+        let message = serde_json::from_str::<String>(&body).unwrap_or(body);
+        return Err(ApiError { status, message }.into());
+    }
+    Ok(response)
+}
+
+async fn send_json<T: DeserializeOwned>(request: RequestBuilder) -> anyhow::Result<T> {
+    Ok(send(request).await?.json().await?)
+}
+
+async fn send_text(request: RequestBuilder) -> anyhow::Result<String> {
+    Ok(send(request).await?.text().await?)
+}
+
+async fn send_empty(request: RequestBuilder) -> anyhow::Result<()> {
+    send(request).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_url() {
+        let client = ZagreusClient::new("http://localhost:58180").unwrap();
+        assert_eq!(
+            "http://localhost:58180/api/asset/versions/team%20logo.png",
+            client
+                .get_url(["api", "asset", "versions", "team logo.png"])
+                .as_str()
+        );
+        // servers behind a reverse proxy
+        let client = ZagreusClient::new("https://graphics.example.com/zagreus/").unwrap();
+        assert_eq!(
+            "https://graphics.example.com/zagreus/api/version",
+            client.get_url(["api", "version"]).as_str()
+        );
+        assert!(ZagreusClient::new("mailto:graphics@example.com").is_err());
+    }
+}
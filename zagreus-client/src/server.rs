@@ -0,0 +1,339 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::NaiveDate;
+use reqwest::multipart::{Form, Part};
+use reqwest::Method;
+use serde_json::Value;
+
+use crate::dto::{
+    AssetInfo, CreateUploadDto, DateRangeQuery, FetchAssetDto, NoteDto, OperatorNote,
+    PinResourceDto, RouteDto, RuntimeVersionDto, SessionDto, SignAssetDto, SignedAssetUrl,
+    UploadAssetResponseDto, UploadStatusDto,
+};
+use crate::{send, send_empty, send_json, send_text, ZagreusClient};
+
+const ASSET_NAME_FIELD: &str = "name";
+const FILE_FIELD: &str = "file";
+const RUNTIME_SOURCE_MAP_FIELD: &str = "sourceMap";
+const UPLOAD_OFFSET_HEADER: &str = "upload-offset";
+
+/// Endpoints which are not specific to an instance. The handover endpoint is left out, it is
+/// only called by a server process replacing another one.
+impl ZagreusClient {
+    pub async fn get_server_version(&self) -> anyhow::Result<String> {
+        send_text(self.get(&["api", "version"])).await
+    }
+
+    pub async fn get_protocol_schema(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "protocol", "schema"])).await
+    }
+
+    pub async fn get_schemas(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "schemas"])).await
+    }
+
+    pub async fn get_runtime_versions(&self) -> anyhow::Result<Vec<RuntimeVersionDto>> {
+        send_json(self.get(&["api", "runtime"])).await
+    }
+
+    pub async fn upload_runtime(
+        &self,
+        version: &str,
+        runtime: Vec<u8>,
+        source_map: Option<Vec<u8>>,
+    ) -> anyhow::Result<RuntimeVersionDto> {
+        let mut form = Form::new().part(FILE_FIELD, Part::bytes(runtime));
+        if let Some(source_map) = source_map {
+            form = form.part(RUNTIME_SOURCE_MAP_FIELD, Part::bytes(source_map));
+        }
+        send_json(self.post(&["api", "runtime", version]).multipart(form)).await
+    }
+
+    pub async fn get_instances(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "instances"])).await
+    }
+
+    pub async fn export_instances(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "instances", "export"])).await
+    }
+
+    /// Imports an archive of [`ZagreusClient::export_instances`] and returns the import report.
+    pub async fn import_instances(&self, archive: &Value) -> anyhow::Result<Value> {
+        send_json(self.post(&["api", "instances", "import"]).json(archive)).await
+    }
+
+    pub async fn replace_texts(&self, request: &Value) -> anyhow::Result<Value> {
+        send_json(self.post(&["api", "instances", "replace"]).json(request)).await
+    }
+
+    pub async fn get_global_feature_flags(&self) -> anyhow::Result<BTreeMap<String, bool>> {
+        send_json(self.get(&["api", "feature-flags"])).await
+    }
+
+    pub async fn set_global_feature_flags(
+        &self,
+        flags: &BTreeMap<String, bool>,
+    ) -> anyhow::Result<()> {
+        send_empty(self.put(&["api", "feature-flags"]).json(flags)).await
+    }
+
+    /// Returns the output channels of every routed instance.
+    pub async fn get_routes(&self) -> anyhow::Result<BTreeMap<String, BTreeSet<String>>> {
+        send_json(self.get(&["api", "routing"])).await
+    }
+
+    pub async fn set_route(&self, instance: &str, route: &RouteDto) -> anyhow::Result<()> {
+        send_empty(self.put(&["api", "routing", instance]).json(route)).await
+    }
+
+    /// Removes the route of the instance, so its data reaches all renderers again.
+    pub async fn delete_route(&self, instance: &str) -> anyhow::Result<()> {
+        send_empty(self.delete(&["api", "routing", instance])).await
+    }
+
+    /// Returns the notes of the show day (e.g. `2024-03-01`), of the current show day if not set.
+    pub async fn get_notes(&self, day: Option<NaiveDate>) -> anyhow::Result<Vec<OperatorNote>> {
+        let query: Vec<_> = day
+            .map(|day| ("day", day.to_string()))
+            .into_iter()
+            .collect();
+        send_json(self.get(&["api", "notes"]).query(&query)).await
+    }
+
+    pub async fn add_note(&self, note: &NoteDto) -> anyhow::Result<OperatorNote> {
+        send_json(self.post(&["api", "notes"]).json(note)).await
+    }
+
+    /// Pins a note of the current show day or unpins it.
+    pub async fn pin_note(&self, id: u64, pinned: bool) -> anyhow::Result<OperatorNote> {
+        let path = ["api", "notes", &id.to_string(), "pin"];
+        if pinned {
+            send_json(self.post(&path)).await
+        } else {
+            send_json(self.delete(&path)).await
+        }
+    }
+
+    /// Starts recording a session, e.g. of a show. Starting an earlier session again continues it.
+    pub async fn start_session(&self, name: &str) -> anyhow::Result<Value> {
+        let session = SessionDto {
+            name: name.to_owned(),
+        };
+        send_json(self.post(&["api", "session", "start"]).json(&session)).await
+    }
+
+    pub async fn stop_session(&self) -> anyhow::Result<Value> {
+        send_json(self.post(&["api", "session", "stop"])).await
+    }
+
+    /// Returns the recorded entries of the session.
+    pub async fn get_session_timeline(&self, name: &str) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "session", name, "timeline"])).await
+    }
+
+    /// Returns the connected clients of all instances, e.g. to find renderers which went silent.
+    pub async fn get_clients(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "clients"])).await
+    }
+
+    pub async fn set_global_feature_flag(&self, flag: &str, enabled: bool) -> anyhow::Result<()> {
+        send_empty(self.put(&["api", "feature-flags", flag]).json(&enabled)).await
+    }
+
+    /// Returns the scheduled items, only those of the instance if one is given.
+    pub async fn get_scheduled_items(&self, instance: Option<&str>) -> anyhow::Result<Value> {
+        let query: Vec<_> = instance
+            .map(|instance| ("instance", instance))
+            .into_iter()
+            .collect();
+        send_json(self.get(&["api", "schedule"]).query(&query)).await
+    }
+
+    pub async fn get_scheduled_item(&self, id: u64) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "schedule", &id.to_string()])).await
+    }
+
+    pub async fn schedule_item(&self, request: &Value) -> anyhow::Result<Value> {
+        send_json(self.post(&["api", "schedule"]).json(request)).await
+    }
+
+    pub async fn replace_scheduled_item(&self, id: u64, request: &Value) -> anyhow::Result<Value> {
+        send_json(
+            self.put(&["api", "schedule", &id.to_string()])
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn delete_scheduled_item(&self, id: u64) -> anyhow::Result<Value> {
+        send_json(self.delete(&["api", "schedule", &id.to_string()])).await
+    }
+
+    /// Returns the animations and elements of the template with the paths which trigger them, as
+    /// used by control surfaces.
+    pub async fn get_action_catalogue(&self, template: &str) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "actions", template])).await
+    }
+
+    pub async fn get_data_sources(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "datasource"])).await
+    }
+
+    pub async fn get_data_source(&self, name: &str) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "datasource", name])).await
+    }
+
+    /// Adds or replaces the data source, which the server polls from then on.
+    pub async fn set_data_source(&self, name: &str, source: &Value) -> anyhow::Result<()> {
+        send_empty(self.put(&["api", "datasource", name]).json(source)).await
+    }
+
+    pub async fn delete_data_source(&self, name: &str) -> anyhow::Result<()> {
+        send_empty(self.delete(&["api", "datasource", name])).await
+    }
+
+    pub async fn get_data_source_status(&self, name: &str) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "datasource", name, "status"])).await
+    }
+
+    /// Polls the data source immediately and returns its status.
+    pub async fn refresh_data_source(&self, name: &str) -> anyhow::Result<Value> {
+        send_json(self.post(&["api", "datasource", name, "refresh"])).await
+    }
+
+    pub async fn get_analytics(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "analytics"])).await
+    }
+
+    pub async fn export_analytics_csv(&self, range: &DateRangeQuery) -> anyhow::Result<String> {
+        send_text(self.get(&["api", "analytics", "csv"]).query(range)).await
+    }
+
+    pub async fn get_audit_log(&self, range: &DateRangeQuery) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "audit"]).query(range)).await
+    }
+
+    pub async fn export_audit_log_csv(&self, range: &DateRangeQuery) -> anyhow::Result<String> {
+        send_text(self.get(&["api", "audit", "csv"]).query(range)).await
+    }
+
+    pub async fn run_self_test(&self) -> anyhow::Result<Value> {
+        send_json(self.post(&["api", "server", "selftest"])).await
+    }
+
+    pub async fn get_stats(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "server", "stats"])).await
+    }
+
+    /// Lists the unreferenced files of the data folder without removing them.
+    pub async fn get_garbage_report(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "server", "gc"])).await
+    }
+
+    /// Removes the unreferenced files of the data folder and returns the removed ones, or only
+    /// lists them if `dry_run` is set.
+    pub async fn collect_garbage(&self, dry_run: bool) -> anyhow::Result<Value> {
+        send_json(
+            self.post(&["api", "server", "gc"])
+                .query(&[("dry_run", dry_run)]),
+        )
+        .await
+    }
+
+    /// Returns the metrics in the Prometheus text format.
+    pub async fn get_metrics(&self) -> anyhow::Result<String> {
+        send_text(self.get(&["metrics"])).await
+    }
+
+    /// Uploads the asset and returns the file of its new version.
+    pub async fn upload_asset(
+        &self,
+        name: &str,
+        data: Vec<u8>,
+    ) -> anyhow::Result<UploadAssetResponseDto> {
+        let form = Form::new()
+            .text(ASSET_NAME_FIELD, name.to_owned())
+            .part(FILE_FIELD, Part::bytes(data).file_name(name.to_owned()));
+        send_json(self.post(&["api", "asset"]).multipart(form)).await
+    }
+
+    /// Lets the server download the asset from the URL.
+    pub async fn fetch_asset(&self, url: &str) -> anyhow::Result<UploadAssetResponseDto> {
+        send_json(self.post(&["api", "asset", "url"]).json(&FetchAssetDto {
+            url: url.to_owned(),
+        }))
+        .await
+    }
+
+    /// Returns the file of the current version of every asset.
+    pub async fn get_asset_versions(&self) -> anyhow::Result<BTreeMap<String, String>> {
+        send_json(self.get(&["api", "asset", "versions"])).await
+    }
+
+    /// Downloads the current version of the asset.
+    pub async fn get_latest_asset(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let response = send(self.get(&["api", "asset", "versions", name])).await?;
+        Ok(response.bytes().await?.to_vec())
+    }
+
+    pub async fn delete_asset(&self, name: &str) -> anyhow::Result<()> {
+        send_empty(self.delete(&["api", "asset", "versions", name])).await
+    }
+
+    pub async fn get_asset_info(&self, name: &str) -> anyhow::Result<AssetInfo> {
+        send_json(self.get(&["api", "asset", "versions", name, "info"])).await
+    }
+
+    /// Starts a resumable upload, see [`ZagreusClient::upload_chunk`].
+    pub async fn create_upload(&self, upload: &CreateUploadDto) -> anyhow::Result<UploadStatusDto> {
+        send_json(self.post(&["api", "asset", "uploads"]).json(upload)).await
+    }
+
+    /// Returns the offset at which the next chunk has to start, e.g. after a chunk failed.
+    pub async fn get_upload(&self, id: &str) -> anyhow::Result<UploadStatusDto> {
+        send_json(self.get(&["api", "asset", "uploads", id])).await
+    }
+
+    /// Appends the chunk at the offset, which has to be the current offset of the upload.
+    pub async fn upload_chunk(
+        &self,
+        id: &str,
+        offset: u64,
+        chunk: Vec<u8>,
+    ) -> anyhow::Result<UploadStatusDto> {
+        send_json(
+            self.request(Method::PATCH, ["api", "asset", "uploads", id])
+                .header(UPLOAD_OFFSET_HEADER, offset)
+                .body(chunk),
+        )
+        .await
+    }
+
+    /// Verifies the checksum of the complete upload and returns the file of the new version.
+    pub async fn complete_upload(&self, id: &str) -> anyhow::Result<UploadAssetResponseDto> {
+        send_json(self.post(&["api", "asset", "uploads", id, "complete"])).await
+    }
+
+    pub async fn delete_upload(&self, id: &str) -> anyhow::Result<()> {
+        send_empty(self.delete(&["api", "asset", "uploads", id])).await
+    }
+
+    pub async fn create_signed_asset_url(
+        &self,
+        payload: &SignAssetDto,
+    ) -> anyhow::Result<SignedAssetUrl> {
+        send_json(self.post(&["api", "asset", "signed-url"]).json(payload)).await
+    }
+
+    pub async fn get_resources(&self) -> anyhow::Result<Value> {
+        send_json(self.get(&["api", "resource"])).await
+    }
+
+    pub async fn pin_resource(&self, payload: &PinResourceDto) -> anyhow::Result<Value> {
+        send_json(self.post(&["api", "resource"]).json(payload)).await
+    }
+
+    pub async fn delete_resource(&self, url: &str) -> anyhow::Result<()> {
+        send_empty(self.delete(&["api", "resource"]).query(&[("url", url)])).await
+    }
+}
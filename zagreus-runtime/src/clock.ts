@@ -0,0 +1,31 @@
+import { WebsocketSender } from "./websocket/websocket-sender";
+import {
+  PongPayload,
+  SetClockOffsetPayload,
+  TaggedEnumType,
+  TemplateMessage,
+} from "./websocket/types";
+import { getInternalZagreusState } from "./runtime";
+
+// answers a ping of the server with the time of this renderer, so the server can measure the
+// latency and the offset of the clocks
+export const answerPing = (
+  serverTime: number,
+  sender: WebsocketSender
+): void => {
+  const message: TaggedEnumType<TemplateMessage, PongPayload> = {
+    tag: "Pong",
+    payload: { serverTime, clientTime: Date.now() },
+  };
+  sender.sendMessage(message);
+};
+
+export const applyClockOffset = (payload: SetClockOffsetPayload): void => {
+  getInternalZagreusState().clockOffset = payload.clockOffsetMs;
+};
+
+// returns the current time of the server clock in milliseconds since the epoch, used to execute
+// animations in sync with other renderers
+export const getServerTime = (): number => {
+  return Date.now() - getInternalZagreusState().clockOffset;
+};
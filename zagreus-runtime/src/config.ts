@@ -0,0 +1,17 @@
+import { getInternalZagreusState } from "./runtime";
+
+export type ConfigChangeListener = (config: Record<string, unknown>) => void;
+
+export const getConfig = (): Record<string, unknown> => {
+  return getInternalZagreusState().config;
+};
+
+export const onConfigChange = (listener: ConfigChangeListener): void => {
+  getInternalZagreusState().configChangeListeners.push(listener);
+};
+
+export const applyConfig = (config: Record<string, unknown>): void => {
+  const state = getInternalZagreusState();
+  state.config = config;
+  state.configChangeListeners.forEach((listener) => listener(config));
+};
@@ -1 +1,4 @@
 export const ZagreusContainerId = "zagreus-container";
+export const RuntimeVersion = "0.0.9";
+// must match the protocol version of the zagreus server
+export const ProtocolVersion = 20;
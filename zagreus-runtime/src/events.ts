@@ -0,0 +1,56 @@
+import { getInternalZagreusState } from "./runtime";
+import {
+  EmitEventPayload,
+  TaggedEnumType,
+  TemplateEventPayload,
+  TemplateMessage,
+} from "./websocket/types";
+
+export type TemplateEventListener = (
+  payload: unknown,
+  event: string,
+  source: string
+) => void;
+
+export interface TemplateEventSubscription {
+  event: string;
+  listener: TemplateEventListener;
+}
+
+// emits an event (e.g. "goal"), which the server forwards to the renderers of the instances
+// configured in its template event routes. Events emitted while disconnected are dropped.
+export const emit = (event: string, payload?: unknown): void => {
+  const websocketSender = getInternalZagreusState().websocketSender;
+  if (!websocketSender?.isOpen()) {
+    console.warn(`Not connected to the server, dropping event ${event}.`);
+    return;
+  }
+  const message: TaggedEnumType<TemplateMessage, EmitEventPayload> = {
+    tag: "EmitEvent",
+    payload: { event, payload: payload ?? null },
+  };
+  websocketSender.sendMessage(message);
+};
+
+// calls the listener with every event of the name emitted by the templates routed to this one,
+// or with all of their events for "*"
+export const onEvent = (
+  event: string,
+  listener: TemplateEventListener
+): void => {
+  getInternalZagreusState().templateEventSubscriptions.push({
+    event,
+    listener,
+  });
+};
+
+export const applyTemplateEvent = (payload: TemplateEventPayload): void => {
+  getInternalZagreusState()
+    .templateEventSubscriptions.filter(
+      (subscription) =>
+        subscription.event === "*" || subscription.event === payload.event
+    )
+    .forEach((subscription) =>
+      subscription.listener(payload.payload, payload.event, payload.source)
+    );
+};
@@ -0,0 +1,21 @@
+import { getInternalZagreusState } from "./runtime";
+
+export type FeatureFlagsChangeListener = (
+  flags: Record<string, boolean>
+) => void;
+
+export const isFeatureEnabled = (flag: string): boolean => {
+  return getInternalZagreusState().featureFlags[flag] === true;
+};
+
+export const onFeatureFlagsChange = (
+  listener: FeatureFlagsChangeListener
+): void => {
+  getInternalZagreusState().featureFlagsChangeListeners.push(listener);
+};
+
+export const applyFeatureFlags = (flags: Record<string, boolean>): void => {
+  const state = getInternalZagreusState();
+  state.featureFlags = flags;
+  state.featureFlagsChangeListeners.forEach((listener) => listener(flags));
+};
@@ -6,11 +6,23 @@ import {
 } from "../websocket/types";
 import { getInternalZagreusState, InternalZagreusState } from "../runtime";
 import { getZagreusElement } from "../utils";
+import { getServerTime } from "../clock";
 
-export const applyAnimation = (sequenceName: string): void => {
+// executes the animation sequence, at the given server time if set so that all renderers start
+// it on the same frame
+export const applyAnimation = (
+  sequenceName: string,
+  executeAt?: number
+): void => {
   const state = getInternalZagreusState();
   const sequence = findAnimationSequence(sequenceName, state);
-  if (sequence) {
+  if (!sequence) {
+    return;
+  }
+  const delay = executeAt !== undefined ? executeAt - getServerTime() : 0;
+  if (delay > 0) {
+    setTimeout(() => scheduleAnimationSequence(sequence.steps), delay);
+  } else {
     scheduleAnimationSequence(sequence.steps);
   }
 };
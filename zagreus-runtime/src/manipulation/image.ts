@@ -1,10 +1,23 @@
-import { AssetSource } from "../websocket/types";
-import { getUrlOnServer } from "../runtime";
+import {
+  AssetSource,
+  ImageDisplayedPayload,
+  TaggedEnumType,
+  TemplateMessage,
+} from "../websocket/types";
+import { WebsocketSender } from "../websocket/websocket-sender";
+import { getInternalZagreusState, getUrlOnServer } from "../runtime";
 import { getZagreusElement } from "../utils";
 
-const getAssetUrl = (asset: string, assetSource: AssetSource): string => {
+const AssetAttribute = "data-zagreus-asset";
+
+export const getAssetUrl = (
+  asset: string,
+  assetSource: AssetSource
+): string => {
   if (assetSource === "zagreus") {
-    return getUrlOnServer(`/assets/${asset}`);
+    // use the current version of named assets so that replaced assets are not loaded from cache
+    const version = getInternalZagreusState().assetVersions[asset] ?? asset;
+    return getUrlOnServer(`/assets/${version}`);
   }
   return asset;
 };
@@ -12,7 +25,8 @@ const getAssetUrl = (asset: string, assetSource: AssetSource): string => {
 export const setImageSource = (
   elementName: string,
   asset: string,
-  assetSource: AssetSource
+  assetSource: AssetSource,
+  sender?: WebsocketSender
 ): void => {
   const url = getAssetUrl(asset, assetSource);
   const element = getZagreusElement<HTMLImageElement>(elementName);
@@ -21,5 +35,57 @@ export const setImageSource = (
       `Cannot set image source on element ${elementName} since it its not an img element.`
     );
   }
+  if (assetSource === "zagreus") {
+    element.setAttribute(AssetAttribute, asset);
+  } else {
+    element.removeAttribute(AssetAttribute);
+  }
+  if (sender) {
+    reportImageDisplayed(element, elementName, asset, url, sender);
+  }
   element.setAttribute("src", url);
 };
+
+// acknowledges to the server that the image is shown once it was loaded, so the server can keep
+// an as-run log of the images that actually aired
+const reportImageDisplayed = (
+  element: HTMLImageElement,
+  elementName: string,
+  asset: string,
+  url: string,
+  sender: WebsocketSender
+): void => {
+  const report = () => {
+    const message: TaggedEnumType<TemplateMessage, ImageDisplayedPayload> = {
+      tag: "ImageDisplayed",
+      payload: { id: elementName, asset },
+    };
+    sender.sendMessage(message);
+  };
+  if (element.getAttribute("src") === url && element.complete) {
+    report();
+    return;
+  }
+  const onLoad = () => {
+    // ignore images that were replaced before they finished loading
+    if (element.getAttribute("src") === url) {
+      report();
+    }
+  };
+  element.addEventListener("load", onLoad, { once: true });
+};
+
+export const applyAssetVersions = (versions: Record<string, string>): void => {
+  getInternalZagreusState().assetVersions = versions;
+  document
+    .querySelectorAll<HTMLImageElement>(`img[${AssetAttribute}]`)
+    .forEach((element) => {
+      const asset = element.getAttribute(AssetAttribute);
+      if (asset) {
+        const url = getAssetUrl(asset, "zagreus");
+        if (element.getAttribute("src") !== url) {
+          element.setAttribute("src", url);
+        }
+      }
+    });
+};
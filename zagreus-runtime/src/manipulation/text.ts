@@ -1,7 +1,61 @@
 import { getZagreusElement } from "../utils";
 
-export const setTextOnElement = (elementName: string, text: string): void => {
+interface NumericText {
+  prefix: string;
+  value: number;
+  decimals: number;
+  suffix: string;
+}
+
+// animation frames of running tweens per element
+const runningTweens: Record<string, number> = {};
+
+// parses texts like "42", "-1.5" or "87 %" into the number and the text around it
+const parseNumericText = (text: string): NumericText | undefined => {
+  const match = /^(\D*?)(-?\d+(?:\.(\d+))?)(\D*)$/.exec(text.trim());
+  if (!match) {
+    return undefined;
+  }
+  return {
+    prefix: match[1],
+    value: parseFloat(match[2]),
+    decimals: match[3]?.length ?? 0,
+    suffix: match[4],
+  };
+};
+
+const easeOut = (progress: number): number => 1 - Math.pow(1 - progress, 3);
+
+export const setTextOnElement = (
+  elementName: string,
+  text: string,
+  tweenDuration?: number
+): void => {
   const element = getZagreusElement(elementName);
-  element.innerText = text;
-  return;
+  if (runningTweens[elementName] !== undefined) {
+    cancelAnimationFrame(runningTweens[elementName]);
+    delete runningTweens[elementName];
+  }
+
+  const from = parseNumericText(element.innerText);
+  const to = parseNumericText(text);
+  if (!tweenDuration || !from || !to || from.value === to.value) {
+    element.innerText = text;
+    return;
+  }
+
+  // count from the current to the new value, e.g. for scores or odometer counters
+  const start = performance.now();
+  const step = (now: number): void => {
+    const progress = Math.min((now - start) / tweenDuration, 1);
+    if (progress >= 1) {
+      delete runningTweens[elementName];
+      element.innerText = text;
+      return;
+    }
+    const value = from.value + (to.value - from.value) * easeOut(progress);
+    element.innerText = `${to.prefix}${value.toFixed(to.decimals)}${to.suffix}`;
+    runningTweens[elementName] = requestAnimationFrame(step);
+  };
+  runningTweens[elementName] = requestAnimationFrame(step);
 };
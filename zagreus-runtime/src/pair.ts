@@ -0,0 +1,19 @@
+import { getInternalZagreusState } from "./runtime";
+import { SetPairRolePayload } from "./websocket/types";
+
+export type ActiveChangeListener = (active: boolean) => void;
+
+// renderers which are not part of a redundant pair (?pair=<name>) are always active
+export const isActive = (): boolean => {
+  return getInternalZagreusState().active;
+};
+
+export const onActiveChange = (listener: ActiveChangeListener): void => {
+  getInternalZagreusState().activeChangeListeners.push(listener);
+};
+
+export const applyPairRole = (payload: SetPairRolePayload): void => {
+  const state = getInternalZagreusState();
+  state.active = payload.active;
+  state.activeChangeListeners.forEach((listener) => listener(payload.active));
+};
@@ -0,0 +1,49 @@
+import { WebsocketSender } from "./websocket/websocket-sender";
+import {
+  AssetReference,
+  PrecacheProgressPayload,
+  TaggedEnumType,
+  TemplateMessage,
+} from "./websocket/types";
+import { getAssetUrl } from "./manipulation/image";
+
+const sendProgress = (
+  sender: WebsocketSender,
+  progress: PrecacheProgressPayload
+): void => {
+  const message: TaggedEnumType<TemplateMessage, PrecacheProgressPayload> = {
+    tag: "PrecacheProgress",
+    payload: { ...progress, failed: [...progress.failed] },
+  };
+  sender.sendMessage(message);
+};
+
+const fetchAsset = async (url: string): Promise<void> => {
+  const response = await fetch(url, { cache: "force-cache" });
+  if (!response.ok) {
+    throw new Error(`Could not fetch ${url}: ${response.status}`);
+  }
+  await response.blob();
+};
+
+// fetch assets one after another to keep the load on the renderer and the server low
+export const precacheAssets = async (
+  assets: AssetReference[],
+  sender: WebsocketSender
+): Promise<void> => {
+  const progress: PrecacheProgressPayload = {
+    total: assets.length,
+    loaded: 0,
+    failed: [],
+  };
+  for (const asset of assets) {
+    try {
+      await fetchAsset(getAssetUrl(asset.asset, asset.assetSource));
+      progress.loaded++;
+    } catch (err) {
+      console.warn(`Could not pre-cache asset ${asset.asset}.`, err);
+      progress.failed.push(asset.asset);
+    }
+    sendProgress(sender, progress);
+  }
+};
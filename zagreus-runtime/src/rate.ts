@@ -0,0 +1,93 @@
+import { getInternalZagreusState } from "./runtime";
+
+// maximum number of updates per second, for all elements or per element id
+export interface UpdateRateLimits {
+  default?: number;
+  elements?: Record<string, number>;
+}
+
+interface PendingUpdate {
+  apply: () => void;
+  timeout?: ReturnType<typeof setTimeout>;
+}
+
+const lastUpdates: Record<string, number> = {};
+const pendingUpdates: Record<string, PendingUpdate> = {};
+let emergencyOverrideActive = false;
+let applyingBatch = false;
+
+// while an emergency override is active, updates are applied immediately. Pending updates are
+// dropped, so they do not replace the emergency graphics
+export const setEmergencyOverrideActive = (active: boolean): void => {
+  emergencyOverrideActive = active;
+  if (active) {
+    for (const key of Object.keys(pendingUpdates)) {
+      clearTimeout(pendingUpdates[key].timeout);
+      delete pendingUpdates[key];
+    }
+  }
+};
+
+// applies the updates of a batch immediately, so all elements of the batch change in the same
+// frame. Pending updates of the elements are dropped, so they do not overwrite the batch
+export const applyBatch = (apply: () => void): void => {
+  applyingBatch = true;
+  try {
+    apply();
+  } finally {
+    applyingBatch = false;
+  }
+};
+
+const getUpdateRateLimit = (id: string): number | undefined => {
+  const limits = getInternalZagreusState().updateRateLimits;
+  return limits.elements?.[id] ?? limits.default;
+};
+
+// applies an update of an element at most as often as configured for the element. Updates that
+// arrive in between replace the pending update, so the latest value is always shown in the end
+export const applyRateLimited = (
+  kind: string,
+  id: string,
+  apply: () => void
+): void => {
+  const key = `${kind}:${id}`;
+  if (applyingBatch) {
+    const pendingUpdate = pendingUpdates[key];
+    if (pendingUpdate) {
+      clearTimeout(pendingUpdate.timeout);
+      delete pendingUpdates[key];
+    }
+    lastUpdates[key] = performance.now();
+    apply();
+    return;
+  }
+
+  const limit = getUpdateRateLimit(id);
+  if (emergencyOverrideActive || !limit || limit <= 0) {
+    apply();
+    return;
+  }
+
+  const pendingUpdate = pendingUpdates[key];
+  if (pendingUpdate) {
+    pendingUpdate.apply = apply;
+    return;
+  }
+
+  const interval = 1000 / limit;
+  const elapsed = performance.now() - (lastUpdates[key] ?? -Infinity);
+  if (elapsed >= interval) {
+    lastUpdates[key] = performance.now();
+    apply();
+    return;
+  }
+
+  const update: PendingUpdate = { apply };
+  pendingUpdates[key] = update;
+  update.timeout = setTimeout(() => {
+    delete pendingUpdates[key];
+    lastUpdates[key] = performance.now();
+    update.apply();
+  }, interval - elapsed);
+};
@@ -0,0 +1,29 @@
+import { getInternalZagreusState } from "./runtime";
+
+export interface RendererParameters {
+  channel?: string;
+  scale?: number;
+  transparent: boolean;
+  // output variant of the template, e.g. "vertical"
+  variant?: string;
+}
+
+// parses the query parameters of the template page, e.g. ?channel=program&scale=0.5&transparent=1
+export const parseRendererParameters = (
+  query: string
+): RendererParameters => {
+  const params = new URLSearchParams(query);
+  const scale = Number(params.get("scale"));
+  const transparent = params.get("transparent");
+  return {
+    channel: params.get("channel") || undefined,
+    scale: Number.isFinite(scale) && scale > 0 ? scale : undefined,
+    transparent:
+      transparent === null || ["", "1", "true", "yes"].includes(transparent),
+    variant: params.get("variant") || undefined,
+  };
+};
+
+export const getRendererParameters = (): RendererParameters => {
+  return getInternalZagreusState().rendererParameters;
+};
@@ -0,0 +1,39 @@
+import { getUrlOnServer } from "./runtime";
+
+export interface ExternalResource {
+  url: string;
+  // derived from the file extension of the URL if not set
+  type?: "stylesheet" | "script";
+}
+
+const getResourceType = (
+  resource: ExternalResource
+): "stylesheet" | "script" => {
+  if (resource.type) {
+    return resource.type;
+  }
+  const path = resource.url.split(/[?#]/)[0];
+  return path.toLowerCase().endsWith(".css") ? "stylesheet" : "script";
+};
+
+// loads external resources from the copy pinned on the server instead of the internet, so
+// renderers in air-gapped networks can use them and they cannot change unnoticed
+export const loadExternalResources = (resources: ExternalResource[]): void => {
+  resources.forEach((resource) => {
+    const url = getUrlOnServer(
+      `/static/resource?url=${encodeURIComponent(resource.url)}`
+    );
+    if (getResourceType(resource) === "stylesheet") {
+      const link = document.createElement("link");
+      link.rel = "stylesheet";
+      link.href = url;
+      document.head.appendChild(link);
+    } else {
+      const script = document.createElement("script");
+      // keep the declared order of scripts which depend on each other
+      script.async = false;
+      script.src = url;
+      document.head.appendChild(script);
+    }
+  });
+};
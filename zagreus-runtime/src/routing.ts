@@ -0,0 +1,7 @@
+import { SetRoutedPayload } from "./websocket/types";
+
+// renderers of channels to which the instance is not routed hide the template, the server sends
+// the current data when it is routed to their channel again
+export const applyRouted = (payload: SetRoutedPayload): void => {
+  document.body.style.visibility = payload.routed ? "" : "hidden";
+};
@@ -1,5 +1,24 @@
 import { AnimationSequence } from "./websocket/types";
 import { registerAnimations, setup } from "./setup";
+import { ConfigChangeListener, getConfig, onConfigChange } from "./config";
+import {
+  FeatureFlagsChangeListener,
+  isFeatureEnabled,
+  onFeatureFlagsChange,
+} from "./feature-flags";
+import { getRendererParameters, RendererParameters } from "./renderer";
+import { onScheduledItemFired, ScheduledItemListener } from "./schedule";
+import { ActiveChangeListener, isActive, onActiveChange } from "./pair";
+import { ExternalResource } from "./resources";
+import { UpdateRateLimits } from "./rate";
+import { subscribe, TopicListener, TopicSubscription } from "./topics";
+import {
+  emit,
+  onEvent,
+  TemplateEventListener,
+  TemplateEventSubscription,
+} from "./events";
+import { WebsocketSender } from "./websocket/websocket-sender";
 
 declare global {
   interface Window {
@@ -15,17 +34,53 @@ export interface ZagreusContainerSetupArguments {
   height: number;
 }
 
+export interface ZagreusSafeArea {
+  top: number;
+  right: number;
+  bottom: number;
+  left: number;
+}
+
+export interface ZagreusVariantSetupArguments {
+  width: number;
+  height: number;
+  // margins in pixels which must not contain essential content, e.g. behind the buttons of
+  // social apps
+  safeArea?: ZagreusSafeArea;
+  // replace the animation sequences with the same name in renderers of the variant
+  animationSequences?: AnimationSequence[];
+}
+
 export interface ZagreusSetupArguments {
   host: string;
   port: string;
+  // connect over HTTPS and WSS, defaults to the protocol of the template page
+  secure?: boolean;
   instance: string;
   container: ZagreusContainerSetupArguments;
   animationSequences?: AnimationSequence[];
+  // output variants per name (e.g. 9:16 for social media as "vertical"), rendered instead of the
+  // container size with ?variant=<name>
+  variants?: Record<string, ZagreusVariantSetupArguments>;
+  requiredProtocolVersion?: number;
+  externalResources?: ExternalResource[];
+  updateRateLimits?: UpdateRateLimits;
 }
 
 export interface ZagreusState {
   setup: (args: ZagreusSetupArguments) => void;
   registerAnimations: (...animation: AnimationSequence[]) => void;
+  getConfig: () => Record<string, unknown>;
+  onConfigChange: (listener: ConfigChangeListener) => void;
+  isFeatureEnabled: (flag: string) => boolean;
+  onFeatureFlagsChange: (listener: FeatureFlagsChangeListener) => void;
+  getRendererParameters: () => RendererParameters;
+  onScheduledItemFired: (listener: ScheduledItemListener) => void;
+  isActive: () => boolean;
+  onActiveChange: (listener: ActiveChangeListener) => void;
+  subscribe: (filter: string, listener: TopicListener) => void;
+  emit: (event: string, payload?: unknown) => void;
+  onEvent: (event: string, listener: TemplateEventListener) => void;
   _internal: InternalZagreusState;
 }
 
@@ -33,20 +88,66 @@ export interface InternalZagreusState {
   instance: string;
   host: string;
   port: string;
+  secure: boolean;
   animationSequences: Record<string, AnimationSequence>;
   errorReporter: ErrorReporter;
+  requiredProtocolVersion: number | undefined;
+  config: Record<string, unknown>;
+  configChangeListeners: ConfigChangeListener[];
+  featureFlags: Record<string, boolean>;
+  featureFlagsChangeListeners: FeatureFlagsChangeListener[];
+  scheduledItemListeners: ScheduledItemListener[];
+  // whether the renderer is the active one of its redundant pair
+  active: boolean;
+  activeChangeListeners: ActiveChangeListener[];
+  assetVersions: Record<string, string>;
+  rendererParameters: RendererParameters;
+  // difference of the renderer clock to the server clock in milliseconds
+  clockOffset: number;
+  updateRateLimits: UpdateRateLimits;
+  topicSubscriptions: TopicSubscription[];
+  templateEventSubscriptions: TemplateEventSubscription[];
+  // undefined until the websocket was started by the setup
+  websocketSender: WebsocketSender | undefined;
 }
 
 if (!window.zagreus) {
   window.zagreus = {
     setup: setup,
     registerAnimations: registerAnimations,
+    getConfig: getConfig,
+    onConfigChange: onConfigChange,
+    isFeatureEnabled: isFeatureEnabled,
+    onFeatureFlagsChange: onFeatureFlagsChange,
+    getRendererParameters: getRendererParameters,
+    onScheduledItemFired: onScheduledItemFired,
+    isActive: isActive,
+    onActiveChange: onActiveChange,
+    subscribe: subscribe,
+    emit: emit,
+    onEvent: onEvent,
     _internal: {
       instance: undefined,
       host: undefined,
       port: undefined,
+      secure: false,
       animationSequences: {},
       errorReporter: undefined,
+      requiredProtocolVersion: undefined,
+      config: {},
+      configChangeListeners: [],
+      featureFlags: {},
+      featureFlagsChangeListeners: [],
+      scheduledItemListeners: [],
+      active: true,
+      activeChangeListeners: [],
+      assetVersions: {},
+      rendererParameters: { transparent: true },
+      clockOffset: 0,
+      updateRateLimits: {},
+      topicSubscriptions: [],
+      templateEventSubscriptions: [],
+      websocketSender: undefined,
     },
   };
 }
@@ -61,7 +162,8 @@ export const getZagreusState = (): ZagreusState => {
 
 export const getUrlOnServer = (path: string): string => {
   const state = getInternalZagreusState();
-  return `http://${state.host}:${state.port}${path}`;
+  const scheme = state.secure ? "https" : "http";
+  return `${scheme}://${state.host}:${state.port}${path}`;
 };
 
 // TODO port animation validation logic from generator to here
@@ -0,0 +1,18 @@
+import { getInternalZagreusState } from "./runtime";
+import { ScheduledItemFiredPayload } from "./websocket/types";
+
+export type ScheduledItemListener = (item: ScheduledItemFiredPayload) => void;
+
+export const onScheduledItemFired = (
+  listener: ScheduledItemListener
+): void => {
+  getInternalZagreusState().scheduledItemListeners.push(listener);
+};
+
+export const notifyScheduledItemFired = (
+  item: ScheduledItemFiredPayload
+): void => {
+  getInternalZagreusState().scheduledItemListeners.forEach((listener) =>
+    listener(item)
+  );
+};
@@ -2,6 +2,7 @@ import {
   getInternalZagreusState,
   ZagreusContainerSetupArguments,
   ZagreusSetupArguments,
+  ZagreusVariantSetupArguments,
 } from "./runtime";
 import { runWebsocket } from "./websocket/run";
 import { getZagreusElement } from "./utils";
@@ -11,13 +12,22 @@ import {
 } from "./manipulation/animation";
 import { removeClassOnElement } from "./manipulation/css";
 import { AnimationSequence } from "./websocket/types";
+import { parseRendererParameters } from "./renderer";
+import { loadExternalResources } from "./resources";
+import { applyVariant, getVariant } from "./variant";
 
 const ZagreusHiddenClassName = "zagreus-hidden";
 
-function setupContainer(args: ZagreusContainerSetupArguments) {
+function setupContainer(
+  args: ZagreusContainerSetupArguments,
+  variant: ZagreusVariantSetupArguments | undefined
+) {
   const container = getZagreusElement(args.name);
+  const rendererParameters = getInternalZagreusState().rendererParameters;
 
-  document.body.style.backgroundColor = "transparent";
+  if (rendererParameters.transparent) {
+    document.body.style.backgroundColor = "transparent";
+  }
 
   // add zagreus hidden class
   const css = `.${ZagreusHiddenClassName} { visibility: hidden }`;
@@ -25,8 +35,15 @@ function setupContainer(args: ZagreusContainerSetupArguments) {
   style.appendChild(document.createTextNode(css));
   document.head.appendChild(style);
 
-  container.style.width = `${args.width}px`;
-  container.style.height = `${args.height}px`;
+  container.style.width = `${variant?.width ?? args.width}px`;
+  container.style.height = `${variant?.height ?? args.height}px`;
+  if (variant) {
+    applyVariant(container, rendererParameters.variant, variant);
+  }
+  if (rendererParameters.scale) {
+    container.style.transform = `scale(${rendererParameters.scale})`;
+    container.style.transformOrigin = "top left";
+  }
 }
 
 export function setup(args: ZagreusSetupArguments) {
@@ -35,11 +52,22 @@ export function setup(args: ZagreusSetupArguments) {
   state.instance = args.instance;
   state.host = args.host;
   state.port = args.port;
+  state.secure = args.secure ?? window.location.protocol === "https:";
+  state.requiredProtocolVersion = args.requiredProtocolVersion;
+  state.rendererParameters = parseRendererParameters(window.location.search);
+  state.updateRateLimits = args.updateRateLimits ?? {};
 
-  setupContainer(args.container);
+  if (args.externalResources) {
+    loadExternalResources(args.externalResources);
+  }
+  const variant = getVariant(args, state.rendererParameters.variant);
+  setupContainer(args.container, variant);
   if (args.animationSequences) {
     registerAnimations(...args.animationSequences);
   }
+  if (variant?.animationSequences) {
+    registerAnimations(...variant.animationSequences);
+  }
 
   // run initial animations after a timeout (to allow time for registering other animations)
   setTimeout(() => {
@@ -0,0 +1,25 @@
+import { WebsocketSender } from "./websocket/websocket-sender";
+import {
+  DomSnapshotPayload,
+  TaggedEnumType,
+  TemplateMessage,
+} from "./websocket/types";
+
+// sends the current DOM to the server, so it can be seen what the template looked like when an
+// error occurred
+export const captureDomSnapshot = (
+  errorId: number,
+  sender: WebsocketSender
+): void => {
+  const message: TaggedEnumType<TemplateMessage, DomSnapshotPayload> = {
+    tag: "DomSnapshot",
+    payload: {
+      errorId,
+      html: document.documentElement.outerHTML,
+      url: window.location.href,
+      width: window.innerWidth,
+      height: window.innerHeight,
+    },
+  };
+  sender.sendMessage(message);
+};
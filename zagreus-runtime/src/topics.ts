@@ -0,0 +1,76 @@
+import { getInternalZagreusState } from "./runtime";
+import { WebsocketSender } from "./websocket/websocket-sender";
+import {
+  PublishPayload,
+  SubscribePayload,
+  TaggedEnumType,
+  TemplateMessage,
+} from "./websocket/types";
+
+export type TopicListener = (value: unknown, topic: string) => void;
+
+export interface TopicSubscription {
+  filter: string;
+  listener: TopicListener;
+}
+
+// subscribes to the topics matching the filter, in which "+" stands for one level and a trailing
+// "#" for all remaining levels (e.g. "scores/#"). The listener is called with the retained value
+// of every matching topic right away and with every value published afterwards.
+export const subscribe = (filter: string, listener: TopicListener): void => {
+  const state = getInternalZagreusState();
+  state.topicSubscriptions.push({ filter, listener });
+  // subscriptions made before the websocket is open are sent once it connects
+  if (state.websocketSender?.isOpen()) {
+    sendSubscribe(state.websocketSender, [filter]);
+  }
+};
+
+// sends all subscriptions, called whenever the websocket (re)connects
+export const sendSubscriptions = (websocketSender: WebsocketSender): void => {
+  const filters = getInternalZagreusState().topicSubscriptions.map(
+    (subscription) => subscription.filter
+  );
+  if (filters.length > 0) {
+    sendSubscribe(websocketSender, filters);
+  }
+};
+
+export const applyPublish = (payload: PublishPayload): void => {
+  getInternalZagreusState()
+    .topicSubscriptions.filter((subscription) =>
+      matchesFilter(payload.topic, subscription.filter)
+    )
+    .forEach((subscription) =>
+      subscription.listener(payload.value, payload.topic)
+    );
+};
+
+const sendSubscribe = (
+  websocketSender: WebsocketSender,
+  topics: string[]
+): void => {
+  const message: TaggedEnumType<TemplateMessage, SubscribePayload> = {
+    tag: "Subscribe",
+    payload: { topics },
+  };
+  websocketSender.sendMessage(message);
+};
+
+// same matching as on the server, which only sends values of subscribed topics
+const matchesFilter = (topic: string, filter: string): boolean => {
+  const topicLevels = topic.split("/");
+  const filterLevels = filter.split("/");
+  for (let i = 0; i < filterLevels.length; i++) {
+    if (filterLevels[i] === "#") {
+      return true;
+    }
+    if (
+      i >= topicLevels.length ||
+      (filterLevels[i] !== "+" && filterLevels[i] !== topicLevels[i])
+    ) {
+      return false;
+    }
+  }
+  return topicLevels.length === filterLevels.length;
+};
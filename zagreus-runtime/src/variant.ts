@@ -0,0 +1,38 @@
+import {
+  ZagreusSetupArguments,
+  ZagreusVariantSetupArguments,
+} from "./runtime";
+
+const SafeAreaSides = ["top", "right", "bottom", "left"] as const;
+
+// returns the output variant the renderer selected with ?variant=<name>, renderers without
+// variant render the container as declared
+export const getVariant = (
+  args: ZagreusSetupArguments,
+  name: string | undefined
+): ZagreusVariantSetupArguments | undefined => {
+  if (!name) {
+    return undefined;
+  }
+  const variant = args.variants?.[name];
+  if (!variant) {
+    console.warn(`Template has no variant ${name}, rendering the container.`);
+  }
+  return variant;
+};
+
+// exposes the variant to the stylesheets of the template, which can select it with
+// [data-zagreus-variant="<name>"] and keep content inside var(--zagreus-safe-area-<side>)
+export const applyVariant = (
+  container: HTMLElement,
+  name: string,
+  variant: ZagreusVariantSetupArguments
+): void => {
+  container.dataset.zagreusVariant = name;
+  SafeAreaSides.forEach((side) =>
+    container.style.setProperty(
+      `--zagreus-safe-area-${side}`,
+      `${variant.safeArea?.[side] ?? 0}px`
+    )
+  );
+};
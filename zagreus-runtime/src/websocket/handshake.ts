@@ -0,0 +1,40 @@
+import { WebsocketSender } from "./websocket-sender";
+import {
+  HandshakePayload,
+  ReportTemplatePayload,
+  TaggedEnumType,
+  TemplateMessage,
+} from "./types";
+import { getInternalZagreusState } from "../runtime";
+import { ProtocolVersion, RuntimeVersion } from "../constants";
+
+export const sendHandshake = (websocketSender: WebsocketSender): void => {
+  const state = getInternalZagreusState();
+  const message: TaggedEnumType<TemplateMessage, HandshakePayload> = {
+    tag: "Handshake",
+    payload: {
+      runtimeVersion: RuntimeVersion,
+      protocolVersion: ProtocolVersion,
+      requiredProtocolVersion: state.requiredProtocolVersion,
+    },
+  };
+  websocketSender.sendMessage(message);
+};
+
+// reports the animations and elements of the template, which the server lists for control
+// surfaces
+export const sendTemplateReport = (websocketSender: WebsocketSender): void => {
+  const elements = Array.from(document.querySelectorAll("[data-zag]")).map(
+    (element) => element.getAttribute("data-zag")
+  );
+  const message: TaggedEnumType<TemplateMessage, ReportTemplatePayload> = {
+    tag: "ReportTemplate",
+    payload: {
+      animationSequences: Object.keys(
+        getInternalZagreusState().animationSequences
+      ),
+      elements,
+    },
+  };
+  websocketSender.sendMessage(message);
+};
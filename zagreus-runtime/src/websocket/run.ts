@@ -3,16 +3,28 @@ import { WebsocketSender } from "./websocket-sender";
 import { installErrorHandler } from "../error";
 import { WebsocketHandler } from "./websocket-handler";
 import { getInternalZagreusState } from "../runtime";
+import { sendHandshake, sendTemplateReport } from "./handshake";
+import { sendSubscriptions } from "../topics";
 
 export function runWebsocket(): void {
   const state = getInternalZagreusState();
-  const url = `ws://${state.host}:${state.port}/ws/instance/${state.instance}`;
+  // forward the query parameters of the template page so that the server knows the role of
+  // this renderer (e.g. its channel)
+  const scheme = state.secure ? "wss" : "ws";
+  const url = `${scheme}://${state.host}:${state.port}/ws/instance/${state.instance}${window.location.search}`;
   const websocket = new TemplateWebsocket(url);
   const websocketSender = new WebsocketSender(websocket);
+  state.websocketSender = websocketSender;
 
   installErrorHandler(websocketSender);
 
   websocket.messageHandler = new WebsocketHandler(websocketSender);
+  websocket.openHandler = () => {
+    sendHandshake(websocketSender);
+    sendTemplateReport(websocketSender);
+    // subscriptions do not survive a reconnect
+    sendSubscriptions(websocketSender);
+  };
 
   websocket.run();
 }
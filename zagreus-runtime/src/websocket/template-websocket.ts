@@ -5,6 +5,7 @@ const reconnectionTimeoutMillis = 2000;
 
 export class TemplateWebsocket {
   messageHandler: WebsocketHandler | undefined = undefined;
+  openHandler: (() => void) | undefined = undefined;
 
   private websocket: WebSocket | undefined;
   private wasOpen = false;
@@ -57,6 +58,9 @@ export class TemplateWebsocket {
 
   private onOpen(): void {
     this.wasOpen = true;
+    if (this.openHandler) {
+      this.openHandler();
+    }
   }
 
   private static onError(): void {
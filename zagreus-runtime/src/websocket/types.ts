@@ -14,19 +14,104 @@ export type TemplateMessage =
   | "AddClass"
   | "RemoveClass"
   | "ExecuteAnimation"
-  | "SetImageSource";
+  | "SetImageSource"
+  | "SetConfig"
+  | "SetFeatureFlags"
+  | "Handshake"
+  | "IncompatibleVersion"
+  | "PrecacheAssets"
+  | "PrecacheProgress"
+  | "SetAssetVersions"
+  | "CaptureDomSnapshot"
+  | "DomSnapshot"
+  | "Ping"
+  | "Pong"
+  | "SetClockOffset"
+  | "SetEmergencyOverride"
+  | "ImageDisplayed"
+  | "Batch"
+  | "InstanceDeleted"
+  | "ScheduledItemFired"
+  | "SetPairRole"
+  | "ServerShuttingDown"
+  | "Subscribe"
+  | "Publish"
+  | "ReportTemplate"
+  | "SetRouted"
+  | "EmitEvent"
+  | "TemplateEvent";
 
 export type AssetSource = "template" | "zagreus";
-export type SetTextPayload = { id: string; text: string };
+export type SetTextPayload = {
+  id: string;
+  text: string;
+  tweenDuration?: number;
+};
 export type OnLoadPayload = { animationSequences: string[] };
 export type ManipulateClassPayload = { id: string; class: string };
-export type ExecuteAnimationPayload = { animationSequence: string };
+export type ExecuteAnimationPayload = {
+  animationSequence: string;
+  executeAt?: number;
+};
 export type SetImageSourcePayload = {
   id: string;
   asset: string;
   assetSource: AssetSource;
 };
+export type AssetReference = { asset: string; assetSource: AssetSource };
+export type PrecacheAssetsPayload = { assets: AssetReference[] };
+export type PrecacheProgressPayload = {
+  total: number;
+  loaded: number;
+  failed: string[];
+};
+export type SetAssetVersionsPayload = { versions: Record<string, string> };
+export type SetConfigPayload = { config: Record<string, unknown> };
+export type SetFeatureFlagsPayload = { flags: Record<string, boolean> };
 export type LogErrorPayload = { message: string; stack: string };
+export type CaptureDomSnapshotPayload = { errorId: number };
+export type DomSnapshotPayload = {
+  errorId: number;
+  html: string;
+  url: string;
+  width: number;
+  height: number;
+};
+export type PingPayload = { serverTime: number };
+export type PongPayload = { serverTime: number; clientTime: number };
+export type SetClockOffsetPayload = {
+  clockOffsetMs: number;
+  roundTripMs: number;
+};
+export type SetEmergencyOverridePayload = { active: boolean };
+export type ImageDisplayedPayload = { id: string; asset: string };
+export type BatchPayload = {
+  operations: TaggedEnumType<TemplateMessage>[];
+};
+export type ScheduledItemFiredPayload = { id: number; name?: string };
+export type SetPairRolePayload = { pair: string; active: boolean };
+export type SubscribePayload = { topics: string[] };
+export type PublishPayload = { topic: string; value: unknown };
+export type SetRoutedPayload = { routed: boolean };
+export type EmitEventPayload = { event: string; payload: unknown };
+export type TemplateEventPayload = {
+  source: string;
+  event: string;
+  payload: unknown;
+};
+export type ReportTemplatePayload = {
+  animationSequences: string[];
+  elements: string[];
+};
+export type HandshakePayload = {
+  runtimeVersion: string;
+  protocolVersion: number;
+  requiredProtocolVersion?: number;
+};
+export type IncompatibleVersionPayload = {
+  serverProtocolVersion: number;
+  message: string;
+};
 
 export interface AnimationSequence {
   name: string;
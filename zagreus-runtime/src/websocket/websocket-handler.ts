@@ -1,24 +1,56 @@
 import { WebsocketSender } from "./websocket-sender";
 import {
+  BatchPayload,
+  CaptureDomSnapshotPayload,
   EnumTypeHandler,
   ExecuteAnimationPayload,
+  IncompatibleVersionPayload,
   ManipulateClassPayload,
+  PingPayload,
+  PrecacheAssetsPayload,
+  SetAssetVersionsPayload,
+  SetClockOffsetPayload,
+  SetConfigPayload,
+  SetEmergencyOverridePayload,
+  SetFeatureFlagsPayload,
   SetImageSourcePayload,
   SetTextPayload,
+  ScheduledItemFiredPayload,
+  SetPairRolePayload,
+  SetRoutedPayload,
+  PublishPayload,
+  TemplateEventPayload,
   TaggedEnumType,
   TemplateMessage,
 } from "./types";
 import { setTextOnElement } from "../manipulation/text";
 import { addClassOnElement, removeClassOnElement } from "../manipulation/css";
 import { applyAnimation } from "../manipulation/animation";
-import { setImageSource } from "../manipulation/image";
+import { applyAssetVersions, setImageSource } from "../manipulation/image";
+import { applyConfig } from "../config";
+import { applyFeatureFlags } from "../feature-flags";
+import { precacheAssets } from "../precache";
+import { captureDomSnapshot } from "../snapshot";
+import { answerPing, applyClockOffset } from "../clock";
+import { notifyScheduledItemFired } from "../schedule";
+import { applyPairRole } from "../pair";
+import { applyPublish } from "../topics";
+import { applyRouted } from "../routing";
+import { applyTemplateEvent } from "../events";
+import {
+  applyBatch,
+  applyRateLimited,
+  setEmergencyOverrideActive,
+} from "../rate";
 
 const templateMessageHandlers: EnumTypeHandler<
   TemplateMessage,
   WebsocketSender
 > = {
   SetText: (payload: SetTextPayload) => {
-    setTextOnElement(payload.id, payload.text);
+    applyRateLimited("text", payload.id, () =>
+      setTextOnElement(payload.id, payload.text, payload.tweenDuration)
+    );
   },
   AddClass: (payload: ManipulateClassPayload) => {
     addClassOnElement(payload.id, payload.class);
@@ -27,13 +59,102 @@ const templateMessageHandlers: EnumTypeHandler<
     removeClassOnElement(payload.id, payload.class);
   },
   ExecuteAnimation: (payload: ExecuteAnimationPayload) => {
-    applyAnimation(payload.animationSequence);
+    applyAnimation(payload.animationSequence, payload.executeAt);
+  },
+  SetImageSource: (payload: SetImageSourcePayload, sender: WebsocketSender) => {
+    applyRateLimited("image", payload.id, () =>
+      setImageSource(payload.id, payload.asset, payload.assetSource, sender)
+    );
+  },
+  SetConfig: (payload: SetConfigPayload) => {
+    applyConfig(payload.config);
+  },
+  SetFeatureFlags: (payload: SetFeatureFlagsPayload) => {
+    applyFeatureFlags(payload.flags);
+  },
+  PrecacheAssets: (payload: PrecacheAssetsPayload, sender: WebsocketSender) => {
+    precacheAssets(payload.assets, sender);
+  },
+  SetAssetVersions: (payload: SetAssetVersionsPayload) => {
+    applyAssetVersions(payload.versions);
+  },
+  IncompatibleVersion: (payload: IncompatibleVersionPayload) => {
+    console.error(payload.message);
+  },
+  CaptureDomSnapshot: (
+    payload: CaptureDomSnapshotPayload,
+    sender: WebsocketSender
+  ) => {
+    captureDomSnapshot(payload.errorId, sender);
+  },
+  Ping: (payload: PingPayload, sender: WebsocketSender) => {
+    answerPing(payload.serverTime, sender);
+  },
+  SetClockOffset: (payload: SetClockOffsetPayload) => {
+    applyClockOffset(payload);
+  },
+  SetEmergencyOverride: (payload: SetEmergencyOverridePayload) => {
+    setEmergencyOverrideActive(payload.active);
+  },
+  Batch: (payload: BatchPayload, sender: WebsocketSender) => {
+    applyBatch(() => {
+      for (const operation of payload.operations) {
+        handleTemplateMessage(operation, sender);
+      }
+    });
+  },
+  InstanceDeleted: () => {
+    console.warn("Instance was deleted, reloading template.");
+    window.location.reload();
+  },
+  ScheduledItemFired: (payload: ScheduledItemFiredPayload) => {
+    notifyScheduledItemFired(payload);
   },
-  SetImageSource: (payload: SetImageSourcePayload) => {
-    setImageSource(payload.id, payload.asset, payload.assetSource);
+  SetPairRole: (payload: SetPairRolePayload) => {
+    applyPairRole(payload);
+  },
+  Publish: (payload: PublishPayload) => {
+    applyPublish(payload);
+  },
+  SetRouted: (payload: SetRoutedPayload) => {
+    applyRouted(payload);
+  },
+  TemplateEvent: (payload: TemplateEventPayload) => {
+    applyTemplateEvent(payload);
+  },
+  ServerShuttingDown: () => {
+    console.info("Server is shutting down, reconnecting once it is back.");
   },
   // eslint-disable-next-line @typescript-eslint/no-empty-function
   LogError: () => {},
+  // eslint-disable-next-line @typescript-eslint/no-empty-function
+  Handshake: () => {},
+  // eslint-disable-next-line @typescript-eslint/no-empty-function
+  PrecacheProgress: () => {},
+  // eslint-disable-next-line @typescript-eslint/no-empty-function
+  DomSnapshot: () => {},
+  // eslint-disable-next-line @typescript-eslint/no-empty-function
+  Subscribe: () => {},
+  // eslint-disable-next-line @typescript-eslint/no-empty-function
+  ReportTemplate: () => {},
+  // eslint-disable-next-line @typescript-eslint/no-empty-function
+  Pong: () => {},
+  // eslint-disable-next-line @typescript-eslint/no-empty-function
+  ImageDisplayed: () => {},
+  // eslint-disable-next-line @typescript-eslint/no-empty-function
+  EmitEvent: () => {},
+};
+
+const handleTemplateMessage = (
+  message: TaggedEnumType<TemplateMessage>,
+  sender: WebsocketSender
+): void => {
+  const handler = templateMessageHandlers[message.tag];
+  if (!handler) {
+    console.warn(`Ignoring unknown message ${message.tag}.`);
+    return;
+  }
+  handler(message.payload, sender);
 };
 
 export class WebsocketHandler {
@@ -41,9 +162,6 @@ export class WebsocketHandler {
 
   handleMessage(message: string): void {
     const parsedMessage: TaggedEnumType<TemplateMessage> = JSON.parse(message);
-    templateMessageHandlers[parsedMessage.tag](
-      parsedMessage.payload,
-      this.sender
-    );
+    handleTemplateMessage(parsedMessage, this.sender);
   }
 }
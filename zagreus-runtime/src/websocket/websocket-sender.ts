@@ -4,6 +4,10 @@ import { TaggedEnumType, TemplateMessage } from "./types";
 export class WebsocketSender {
   constructor(private websocket: TemplateWebsocket) {}
 
+  isOpen(): boolean {
+    return this.websocket.isOpen();
+  }
+
   sendMessage(message: TaggedEnumType<TemplateMessage>): void {
     if (this.websocket.isOpen()) {
       this.websocket.sendMessage(message);
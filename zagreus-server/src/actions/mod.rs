@@ -0,0 +1,132 @@
+use crate::state::InstanceState;
+use crate::websocket::catalogue::ReportedTemplate;
+
+pub const ACTIONS_PATH_PREFIX: &str = "/api/actions/";
+
+/// Animations and elements of a template with the paths which trigger them with a GET request,
+/// so control surfaces such as Bitfocus Companion can be configured by discovery.
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionCatalogue {
+    pub template: String,
+    pub animations: Vec<AnimationAction>,
+    pub elements: Vec<ElementAction>,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationAction {
+    pub name: String,
+    /// Executes the animation.
+    pub path: String,
+}
+
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementAction {
+    pub id: String,
+    /// Current text of the element, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Sets the text passed as `text` query parameter.
+    pub path: String,
+}
+
+/// Returns the catalogue of the animations and elements reported by the renderers of the template
+/// and the elements which have a text in its state, so elements are listed even while no
+/// renderer is connected.
+pub fn get_action_catalogue(
+    template: &str,
+    reported: ReportedTemplate,
+    state: &InstanceState,
+) -> ActionCatalogue {
+    let prefix = format!("{ACTIONS_PATH_PREFIX}{}", encode_path_segment(template));
+    let animations = reported
+        .animation_sequences
+        .into_iter()
+        .map(|name| AnimationAction {
+            path: format!("{prefix}/animation/{}", encode_path_segment(&name)),
+            name,
+        })
+        .collect();
+    let mut element_ids = reported.elements;
+    element_ids.extend(state.texts.keys().cloned());
+    let elements = element_ids
+        .into_iter()
+        .map(|id| ElementAction {
+            text: state.texts.get(&id).cloned(),
+            path: format!("{prefix}/text/{}", encode_path_segment(&id)),
+            id,
+        })
+        .collect();
+    ActionCatalogue {
+        template: template.to_owned(),
+        animations,
+        elements,
+    }
+}
+
+/// Percent-encodes all characters except the unreserved ones of RFC 3986.
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                char::from(byte).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use super::*;
+
+    #[test]
+    fn test_get_action_catalogue() {
+        let reported = ReportedTemplate {
+            animation_sequences: BTreeSet::from([
+                String::from("ScoreboardIn"),
+                String::from("Goal Home"),
+            ]),
+            elements: BTreeSet::from([String::from("ScoreboardHomeScore")]),
+        };
+        let state = InstanceState {
+            texts: BTreeMap::from([(String::from("ScoreboardClock"), String::from("12:01"))]),
+            ..InstanceState::default()
+        };
+        let catalogue = get_action_catalogue("scoreboard", reported, &state);
+        assert_eq!(
+            vec![
+                AnimationAction {
+                    name: String::from("Goal Home"),
+                    path: String::from("/api/actions/scoreboard/animation/Goal%20Home"),
+                },
+                AnimationAction {
+                    name: String::from("ScoreboardIn"),
+                    path: String::from("/api/actions/scoreboard/animation/ScoreboardIn"),
+                },
+            ],
+            catalogue.animations
+        );
+        assert_eq!(
+            vec![
+                ElementAction {
+                    id: String::from("ScoreboardClock"),
+                    text: Some(String::from("12:01")),
+                    path: String::from("/api/actions/scoreboard/text/ScoreboardClock"),
+                },
+                ElementAction {
+                    id: String::from("ScoreboardHomeScore"),
+                    text: None,
+                    path: String::from("/api/actions/scoreboard/text/ScoreboardHomeScore"),
+                },
+            ],
+            catalogue.elements
+        );
+    }
+}
@@ -0,0 +1,499 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+use crate::config::AmcpTemplateConfig;
+use crate::controller::ServerController;
+use crate::data::operation::Operation;
+
+/// CG layer of CasparCG which is used if the command only names the channel.
+const DEFAULT_CG_LAYER: &str = "9999";
+/// Maximum length of a command line in bytes, so a peer cannot make the server buffer without
+/// limit. Template data of CasparCG clients is far smaller.
+const MAX_LINE_LENGTH: usize = 256 * 1024;
+
+/// Command of the subset of AMCP which is understood by zagreus.
+#[derive(PartialEq, Debug)]
+enum AmcpCommand {
+    Version,
+    /// Template command addressed to a layer, e.g. `1-20:1` for CG layer 1 on layer 20 of
+    /// channel 1.
+    Cg {
+        layer: String,
+        command: CgCommand,
+    },
+}
+
+#[derive(PartialEq, Debug)]
+enum CgCommand {
+    Add {
+        template: String,
+        play_on_load: bool,
+        data: Option<String>,
+    },
+    Play,
+    Stop,
+    Update {
+        data: String,
+    },
+    Invoke {
+        method: String,
+    },
+}
+
+/// Command with the id of an `REQ` prefix, which is repeated in the reply as `RES`.
+#[derive(PartialEq, Debug)]
+struct AmcpRequest {
+    request_id: Option<String>,
+    command: AmcpCommand,
+}
+
+/// Executes CasparCG AMCP template commands of playout automation systems on zagreus instances.
+/// Every connection shares the templates which were added to the layers.
+struct AmcpServer {
+    controller: Arc<ServerController>,
+    templates: BTreeMap<String, AmcpTemplateConfig>,
+    /// Name of the template per layer.
+    layers: Mutex<HashMap<String, String>>,
+}
+
+/// Accepts AMCP connections on the address until the server stops.
+pub async fn run_amcp_server(
+    controller: Arc<ServerController>,
+    address: SocketAddr,
+    templates: BTreeMap<String, AmcpTemplateConfig>,
+) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(
+                "Could not listen for AMCP commands on {}: {}.",
+                address, err
+            );
+            return;
+        }
+    };
+    info!("Accepting AMCP commands on {}.", address);
+    let server = Arc::new(AmcpServer {
+        controller,
+        templates,
+        layers: Mutex::new(HashMap::new()),
+    });
+    loop {
+        match listener.accept().await {
+            Ok((stream, peer)) => {
+                tokio::spawn(server.clone().handle_connection(stream, peer));
+            }
+            Err(err) => warn!("Could not accept AMCP connection: {}.", err),
+        }
+    }
+}
+
+impl AmcpServer {
+    async fn handle_connection(self: Arc<Self>, stream: TcpStream, peer: SocketAddr) {
+        info!("AMCP client {} connected.", peer);
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        loop {
+            let line = match read_line(&mut reader).await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(err) => {
+                    warn!("Could not read AMCP command of {}: {}.", peer, err);
+                    break;
+                }
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            debug!("AMCP command of {}: {}.", peer, line);
+            let reply = crate::timing::trace(String::from("AMCP"), self.handle_line(line)).await;
+            if let Err(err) = writer.write_all(format!("{reply}\r\n").as_bytes()).await {
+                debug!("Could not send AMCP reply to {}: {}.", peer, err);
+                break;
+            }
+        }
+        info!("AMCP client {} disconnected.", peer);
+    }
+
+    async fn handle_line(&self, line: &str) -> String {
+        match parse_request(line) {
+            Ok(request) => {
+                let reply = self.execute(request.command).await;
+                format_reply(request.request_id.as_deref(), &reply)
+            }
+            Err((request_id, reply)) => format_reply(request_id.as_deref(), &reply),
+        }
+    }
+
+    async fn execute(&self, command: AmcpCommand) -> String {
+        let (layer, command) = match command {
+            AmcpCommand::Version => return format!("201 VERSION OK\r\n{}", crate::ZAGREUS_VERSION),
+            AmcpCommand::Cg { layer, command } => (layer, command),
+        };
+        let template_name = match &command {
+            CgCommand::Add { template, .. } => Some(template.clone()),
+            _ => self.layers.lock().await.get(&layer).cloned(),
+        };
+        let Some((template_name, template)) = template_name
+            .as_deref()
+            .and_then(|name| self.get_template(name))
+        else {
+            return String::from("404 CG FAILED");
+        };
+
+        let mut operations = Vec::new();
+        match command {
+            CgCommand::Add {
+                play_on_load, data, ..
+            } => {
+                if let Some(data) = data {
+                    match get_text_operations(template, &data) {
+                        Ok(texts) => operations.extend(texts),
+                        Err(err) => {
+                            warn!("Invalid AMCP template data: {}.", err);
+                            return String::from("403 CG FAILED");
+                        }
+                    }
+                }
+                if play_on_load {
+                    operations.extend(get_animation(template.play_animation.as_deref()));
+                }
+                self.layers.lock().await.insert(layer, template_name);
+            }
+            CgCommand::Play => operations.extend(get_animation(template.play_animation.as_deref())),
+            CgCommand::Stop => operations.extend(get_animation(template.stop_animation.as_deref())),
+            CgCommand::Update { data } => match get_text_operations(template, &data) {
+                Ok(texts) => operations.extend(texts),
+                Err(err) => {
+                    warn!("Invalid AMCP template data: {}.", err);
+                    return String::from("403 CG FAILED");
+                }
+            },
+            CgCommand::Invoke { method } => operations.extend(get_animation(Some(&method))),
+        }
+        if !operations.is_empty() {
+            let pending = self
+                .controller
+                .submit_operations(&template.instance, operations)
+                .await;
+            if let Some(change) = pending {
+                info!(
+                    "AMCP command for instance {} is waiting for approval as change {}.",
+                    template.instance, change.id
+                );
+            }
+        }
+        String::from("202 CG OK")
+    }
+
+    /// Returns the configured name and template, ignoring the case like CasparCG does.
+    fn get_template(&self, name: &str) -> Option<(String, &AmcpTemplateConfig)> {
+        self.templates
+            .get_key_value(name)
+            .or_else(|| {
+                self.templates
+                    .iter()
+                    .find(|(template, _)| template.eq_ignore_ascii_case(name))
+            })
+            .map(|(name, template)| (name.clone(), template))
+    }
+}
+
+/// Reads the next line, but at most `MAX_LINE_LENGTH` bytes. Returns `None` at the end of the
+/// stream.
+async fn read_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> std::io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = (&mut *reader)
+        .take(MAX_LINE_LENGTH as u64 + 1)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.len() > MAX_LINE_LENGTH && line.last() != Some(&b'\n') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Line is longer than {MAX_LINE_LENGTH} bytes"),
+        ));
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+fn get_animation(animation_sequence: Option<&str>) -> Option<Operation> {
+    animation_sequence.map(|animation_sequence| Operation::ExecuteAnimation {
+        animation_sequence: animation_sequence.to_owned(),
+        execute_at: None,
+    })
+}
+
+fn get_text_operations(
+    template: &AmcpTemplateConfig,
+    data: &str,
+) -> Result<Vec<Operation>, String> {
+    Ok(parse_template_data(data)?
+        .into_iter()
+        .map(|(field, text)| Operation::SetText {
+            id: template.fields.get(&field).cloned().unwrap_or(field),
+            text,
+            tween_duration: None,
+        })
+        .collect())
+}
+
+fn format_reply(request_id: Option<&str>, reply: &str) -> String {
+    match request_id {
+        Some(request_id) => format!("RES {request_id} {reply}"),
+        None => reply.to_owned(),
+    }
+}
+
+/// Parses a command line. Returns the request id with the reply if the command is invalid.
+fn parse_request(line: &str) -> Result<AmcpRequest, (Option<String>, String)> {
+    let tokens = tokenize(line).map_err(|_| (None, String::from("400 ERROR")))?;
+    let (request_id, tokens) = match tokens.split_first() {
+        Some((keyword, rest)) if keyword.eq_ignore_ascii_case("REQ") => match rest.split_first() {
+            Some((request_id, rest)) => (Some(request_id.clone()), rest),
+            None => return Err((None, String::from("400 ERROR"))),
+        },
+        _ => (None, tokens.as_slice()),
+    };
+    let command = parse_command(tokens).map_err(|reply| (request_id.clone(), reply))?;
+    Ok(AmcpRequest {
+        request_id,
+        command,
+    })
+}
+
+fn parse_command(tokens: &[String]) -> Result<AmcpCommand, String> {
+    let keyword = tokens.first().map(|keyword| keyword.to_ascii_uppercase());
+    match keyword.as_deref() {
+        Some("VERSION") => return Ok(AmcpCommand::Version),
+        Some("CG") => {}
+        _ => return Err(String::from("400 ERROR")),
+    }
+    let missing = || String::from("402 CG FAILED");
+    let illegal = || String::from("403 CG FAILED");
+    let channel_layer = tokens.get(1).ok_or_else(missing)?;
+    let subcommand = tokens.get(2).ok_or_else(missing)?.to_ascii_uppercase();
+    let cg_layer: u32 = tokens
+        .get(3)
+        .ok_or_else(missing)?
+        .parse()
+        .map_err(|_| illegal())?;
+    let layer = if channel_layer.contains('-') {
+        format!("{channel_layer}:{cg_layer}")
+    } else {
+        format!("{channel_layer}-{DEFAULT_CG_LAYER}:{cg_layer}")
+    };
+    let command = match subcommand.as_str() {
+        "ADD" => CgCommand::Add {
+            template: tokens.get(4).ok_or_else(missing)?.clone(),
+            play_on_load: match tokens.get(5).ok_or_else(missing)?.as_str() {
+                "1" => true,
+                "0" => false,
+                _ => return Err(illegal()),
+            },
+            data: tokens.get(6).cloned(),
+        },
+        "PLAY" => CgCommand::Play,
+        "STOP" => CgCommand::Stop,
+        "UPDATE" => CgCommand::Update {
+            data: tokens.get(4).ok_or_else(missing)?.clone(),
+        },
+        "INVOKE" => CgCommand::Invoke {
+            method: tokens.get(4).ok_or_else(missing)?.clone(),
+        },
+        _ => return Err(String::from("400 ERROR")),
+    };
+    Ok(AmcpCommand::Cg { layer, command })
+}
+
+/// Splits the line at whitespace, except within double quotes. Quoted parameters may contain
+/// escaped quotes (`\"`), backslashes (`\\`) and line breaks (`\n`).
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut token = String::new();
+        if c == '"' {
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => token.push('\n'),
+                        Some(escaped) => token.push(escaped),
+                        None => return Err(String::from("Unterminated escape sequence")),
+                    },
+                    Some(c) => token.push(c),
+                    None => return Err(String::from("Unterminated quote")),
+                }
+            }
+        } else {
+            token.push(c);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                token.push(c);
+            }
+        }
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+/// Returns the text per field of the template data, which is either the XML of CasparCG or a
+/// JSON object. Of the XML, only the `text` data of every `componentData` element is read:
+///
+/// ```xml
+/// <templateData>
+///   <componentData id="f0"><data id="text" value="Ada Lovelace"/></componentData>
+/// </templateData>
+/// ```
+///
+/// Other data (e.g. images) and elements are ignored. Documents with a DTD are rejected.
+fn parse_template_data(data: &str) -> Result<BTreeMap<String, String>, String> {
+    let data = data.trim();
+    if data.starts_with('{') {
+        let fields: serde_json::Map<String, Value> =
+            serde_json::from_str(data).map_err(|err| err.to_string())?;
+        return fields
+            .into_iter()
+            .map(|(field, value)| match value {
+                Value::String(text) => Ok((field, text)),
+                Value::Number(number) => Ok((field, number.to_string())),
+                Value::Bool(value) => Ok((field, value.to_string())),
+                _ => Err(format!("Field {field} is not a text")),
+            })
+            .collect();
+    }
+    if !data.starts_with('<') {
+        return Err(String::from("Template data is neither XML nor JSON"));
+    }
+    let document = roxmltree::Document::parse(data).map_err(|err| err.to_string())?;
+    let mut fields = BTreeMap::new();
+    for component in document
+        .descendants()
+        .filter(|node| node.has_tag_name("componentData"))
+    {
+        let field = component
+            .attribute("id")
+            .ok_or("componentData without id")?;
+        let text = component
+            .children()
+            .filter(|node| node.has_tag_name("data"))
+            .find(|node| node.attribute("id") == Some("text"))
+            .and_then(|node| node.attribute("value"));
+        if let Some(text) = text {
+            fields.insert(field.to_owned(), text.to_owned());
+        }
+    }
+    Ok(fields)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let request = parse_request(
+            r#"REQ 12 CG 1-20 ADD 1 "LOWER_THIRD/MAIN" 1 "<templateData><componentData id=\"f0\"><data id=\"text\" value=\"Ada &amp; Grace\"/></componentData></templateData>""#,
+        )
+        .unwrap();
+        assert_eq!(Some(String::from("12")), request.request_id);
+        let AmcpCommand::Cg {
+            layer,
+            command:
+                CgCommand::Add {
+                    template,
+                    play_on_load,
+                    data: Some(data),
+                },
+        } = request.command
+        else {
+            panic!("Command was not parsed as CG ADD.");
+        };
+        assert_eq!("1-20:1", layer);
+        assert_eq!("LOWER_THIRD/MAIN", template);
+        assert!(play_on_load);
+        assert_eq!(
+            BTreeMap::from([(String::from("f0"), String::from("Ada & Grace"))]),
+            parse_template_data(&data).unwrap()
+        );
+
+        assert_eq!(
+            AmcpCommand::Cg {
+                layer: String::from("2-9999:1"),
+                command: CgCommand::Play
+            },
+            parse_request("cg 2 play 1").unwrap().command
+        );
+        assert_eq!(
+            (Some(String::from("a")), String::from("402 CG FAILED")),
+            parse_request("REQ a CG 1-20 UPDATE 1").unwrap_err()
+        );
+        assert_eq!(
+            (None, String::from("403 CG FAILED")),
+            parse_request("CG 1-20 ADD 1 LOWER_THIRD yes").unwrap_err()
+        );
+        assert_eq!(
+            (None, String::from("400 ERROR")),
+            parse_request("PLAY 1-10 AMB").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_template_data() {
+        let xml = r#"<templateData>
+            <componentData id="f0"><data id="text" value="Grace Hopper" /></componentData>
+            <componentData id='f1'><data id='text' value='Rear Admiral &#x26; &#169;'/></componentData>
+            <componentData id="f2"/>
+        </templateData>"#;
+        let fields = parse_template_data(xml).unwrap();
+        assert_eq!(2, fields.len());
+        assert_eq!("Grace Hopper", fields["f0"]);
+        assert_eq!("Rear Admiral & ©", fields["f1"]);
+
+        let fields = parse_template_data(r#"{"f0":"Ada","score":3}"#).unwrap();
+        assert_eq!("Ada", fields["f0"]);
+        assert_eq!("3", fields["score"]);
+        assert!(parse_template_data(r#"{"f0":["Ada"]}"#).is_err());
+        assert!(parse_template_data("Ada").is_err());
+
+        // malformed documents and entity declarations are rejected
+        assert!(parse_template_data(r#"<templateData><componentData id="f0">"#).is_err());
+        assert!(parse_template_data(
+            r#"<!DOCTYPE t [<!ENTITY a "aaaa">]><templateData><componentData id="f0"><data id="text" value="&a;"/></componentData></templateData>"#
+        )
+        .is_err());
+        assert!(parse_template_data(r#"<templateData><componentData/></templateData>"#).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_line_is_bounded() {
+        let mut reader: &[u8] = b"VERSION\r\nCG 1-20 PLAY 1";
+        assert_eq!(
+            Some(String::from("VERSION\r\n")),
+            read_line(&mut reader).await.unwrap()
+        );
+        assert_eq!(
+            Some(String::from("CG 1-20 PLAY 1")),
+            read_line(&mut reader).await.unwrap()
+        );
+        assert_eq!(None, read_line(&mut reader).await.unwrap());
+
+        let long_line = vec![b'A'; MAX_LINE_LENGTH + 10];
+        let mut reader: &[u8] = &long_line;
+        assert!(read_line(&mut reader).await.is_err());
+    }
+}
@@ -0,0 +1,267 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::config::AnalyticsConfig;
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsReport {
+    pub instances: BTreeMap<String, InstanceUsage>,
+}
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceUsage {
+    pub command_count: u64,
+    pub last_used: Option<DateTime<Utc>>,
+    pub animations: BTreeMap<String, AnimationUsage>,
+    pub graphics: BTreeMap<String, GraphicUsage>,
+}
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AnimationUsage {
+    pub execution_count: u64,
+    pub last_executed: Option<DateTime<Utc>>,
+}
+
+/// On-air statistics of a graphic, derived from pairs of in and out animations
+/// (e.g. `ScoreboardShow` and `ScoreboardHide` both belong to the graphic `Scoreboard`).
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphicUsage {
+    pub on_air_count: u64,
+    pub on_air_since: Option<DateTime<Utc>>,
+    pub average_on_air_seconds: Option<f64>,
+    #[serde(skip)]
+    total_on_air_seconds: f64,
+    /// When an in or out animation of the graphic was last executed.
+    #[serde(skip)]
+    last_used: Option<DateTime<Utc>>,
+}
+
+enum AnimationKind<'a> {
+    In(&'a str),
+    Out(&'a str),
+    Other,
+}
+
+pub struct UsageAnalytics {
+    configuration: AnalyticsConfig,
+    instances: RwLock<HashMap<String, InstanceUsage>>,
+}
+
+impl UsageAnalytics {
+    pub fn new(configuration: AnalyticsConfig) -> UsageAnalytics {
+        UsageAnalytics {
+            configuration,
+            instances: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn record_command(&self, instance: &str) {
+        let mut instances = self.instances.write().await;
+        let usage = instances.entry(instance.to_owned()).or_default();
+        usage.command_count += 1;
+        usage.last_used = Some(Utc::now());
+    }
+
+    pub async fn record_animation(&self, instance: &str, animation_sequence: &str) {
+        let now = Utc::now();
+        let mut instances = self.instances.write().await;
+        let usage = instances.entry(instance.to_owned()).or_default();
+
+        let animation_usage = usage
+            .animations
+            .entry(animation_sequence.to_owned())
+            .or_default();
+        animation_usage.execution_count += 1;
+        animation_usage.last_executed = Some(now);
+
+        match self.classify_animation(animation_sequence) {
+            AnimationKind::In(graphic) => {
+                let graphic_usage = usage.graphics.entry(graphic.to_owned()).or_default();
+                graphic_usage.last_used = Some(now);
+                // executing an in animation again on a graphic that is on air does not restart the on-air time
+                if graphic_usage.on_air_since.is_none() {
+                    graphic_usage.on_air_since = Some(now);
+                }
+            }
+            AnimationKind::Out(graphic) => {
+                let graphic_usage = usage.graphics.entry(graphic.to_owned()).or_default();
+                graphic_usage.last_used = Some(now);
+                if let Some(on_air_since) = graphic_usage.on_air_since.take() {
+                    let on_air_seconds = (now - on_air_since).num_milliseconds() as f64 / 1000.0;
+                    graphic_usage.on_air_count += 1;
+                    graphic_usage.total_on_air_seconds += on_air_seconds;
+                    graphic_usage.average_on_air_seconds = Some(
+                        graphic_usage.total_on_air_seconds / graphic_usage.on_air_count as f64,
+                    );
+                }
+            }
+            AnimationKind::Other => {}
+        }
+    }
+
+    pub async fn get_report(&self) -> AnalyticsReport {
+        let instances = self.instances.read().await;
+        AnalyticsReport {
+            instances: instances
+                .iter()
+                .map(|(name, usage)| (name.clone(), usage.clone()))
+                .collect(),
+        }
+    }
+
+    fn classify_animation<'a>(&self, animation_sequence: &'a str) -> AnimationKind<'a> {
+        if let Some(graphic) = strip_any_suffix(
+            animation_sequence,
+            &self.configuration.in_animation_suffixes,
+        ) {
+            return AnimationKind::In(graphic);
+        }
+        if let Some(graphic) = strip_any_suffix(
+            animation_sequence,
+            &self.configuration.out_animation_suffixes,
+        ) {
+            return AnimationKind::Out(graphic);
+        }
+        AnimationKind::Other
+    }
+}
+
+/// Writes the report as CSV with one row per instance, animation and graphic. If a date range
+/// is given, only rows that were last used within the range are included.
+pub fn write_csv(
+    report: &AnalyticsReport,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> anyhow::Result<Vec<u8>> {
+    let in_range = |timestamp: Option<DateTime<Utc>>| match timestamp {
+        Some(timestamp) => {
+            from.is_none_or(|from| timestamp >= from) && to.is_none_or(|to| timestamp <= to)
+        }
+        None => from.is_none() && to.is_none(),
+    };
+    let format_timestamp =
+        |timestamp: Option<DateTime<Utc>>| timestamp.map(|t| t.to_rfc3339()).unwrap_or_default();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "instance",
+        "type",
+        "name",
+        "count",
+        "lastUsed",
+        "averageOnAirSeconds",
+    ])?;
+    for (instance, usage) in &report.instances {
+        if in_range(usage.last_used) {
+            writer.write_record([
+                instance,
+                "instance",
+                instance,
+                &usage.command_count.to_string(),
+                &format_timestamp(usage.last_used),
+                "",
+            ])?;
+        }
+        for (animation, animation_usage) in &usage.animations {
+            if in_range(animation_usage.last_executed) {
+                writer.write_record([
+                    instance,
+                    "animation",
+                    animation,
+                    &animation_usage.execution_count.to_string(),
+                    &format_timestamp(animation_usage.last_executed),
+                    "",
+                ])?;
+            }
+        }
+        for (graphic, graphic_usage) in &usage.graphics {
+            let last_used = graphic_usage.last_used;
+            if in_range(last_used) {
+                writer.write_record([
+                    instance,
+                    "graphic",
+                    graphic,
+                    &graphic_usage.on_air_count.to_string(),
+                    &format_timestamp(last_used),
+                    &graphic_usage
+                        .average_on_air_seconds
+                        .map(|seconds| format!("{seconds:.1}"))
+                        .unwrap_or_default(),
+                ])?;
+            }
+        }
+    }
+    Ok(writer.into_inner()?)
+}
+
+fn strip_any_suffix<'a>(value: &'a str, suffixes: &[String]) -> Option<&'a str> {
+    suffixes
+        .iter()
+        .filter_map(|suffix| value.strip_suffix(suffix.as_str()))
+        .find(|stripped| !stripped.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_animation_pairs() {
+        let analytics = UsageAnalytics::new(AnalyticsConfig::default());
+        analytics
+            .record_animation("scoreboard", "LowerThirdShow")
+            .await;
+        analytics
+            .record_animation("scoreboard", "LowerThirdHide")
+            .await;
+        analytics
+            .record_animation("scoreboard", "LowerThirdHide")
+            .await;
+
+        let report = analytics.get_report().await;
+        let usage = report.instances.get("scoreboard").unwrap();
+        assert_eq!(2, usage.animations["LowerThirdHide"].execution_count);
+
+        let graphic = &usage.graphics["LowerThird"];
+        assert_eq!(1, graphic.on_air_count);
+        assert!(graphic.on_air_since.is_none());
+        assert!(graphic.average_on_air_seconds.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_record_unpaired_animation() {
+        let analytics = UsageAnalytics::new(AnalyticsConfig::default());
+        analytics.record_animation("scoreboard", "Blink").await;
+
+        let report = analytics.get_report().await;
+        let usage = report.instances.get("scoreboard").unwrap();
+        assert_eq!(1, usage.animations["Blink"].execution_count);
+        assert!(usage.graphics.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_csv_separates_graphics_with_common_prefix() {
+        let analytics = UsageAnalytics::new(AnalyticsConfig::default());
+        analytics.record_animation("scoreboard", "ScoreShow").await;
+        let report = analytics.get_report().await;
+        let last_used = report.instances["scoreboard"].graphics["Score"].last_used;
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        analytics
+            .record_animation("scoreboard", "ScoreboardShow")
+            .await;
+        let csv = String::from_utf8(write_csv(&analytics.get_report().await, None, None).unwrap())
+            .unwrap();
+        let score_row = csv
+            .lines()
+            .find(|line| line.starts_with("scoreboard,graphic,Score,"))
+            .unwrap();
+        assert!(score_row.contains(&last_used.unwrap().to_rfc3339()));
+    }
+}
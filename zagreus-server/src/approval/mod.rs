@@ -0,0 +1,153 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::Utc;
+use tokio::sync::RwLock;
+
+use crate::config::ApprovalConfig;
+use crate::data::operation::Operation;
+
+pub use zagreus_client::dto::PendingChange;
+
+struct PendingChanges {
+    next_id: u64,
+    changes: BTreeMap<u64, PendingChange>,
+}
+
+/// Holds back operations of instances which require approval, e.g. legally sensitive strap
+/// lines. Pending changes are kept in memory and are lost when the server restarts.
+pub struct ApprovalQueue {
+    configurations: HashMap<String, ApprovalConfig>,
+    pending: RwLock<PendingChanges>,
+}
+
+impl ApprovalQueue {
+    pub fn new(configurations: HashMap<String, ApprovalConfig>) -> ApprovalQueue {
+        ApprovalQueue {
+            configurations,
+            pending: RwLock::new(PendingChanges {
+                next_id: 1,
+                changes: BTreeMap::new(),
+            }),
+        }
+    }
+
+    /// Returns whether any of the operations requires approval, so all of them are held back.
+    pub fn requires_approval(&self, instance: &str, operations: &[Operation]) -> bool {
+        self.configurations
+            .get(instance)
+            .is_some_and(|configuration| {
+                operations
+                    .iter()
+                    .any(|operation| requires_approval(configuration, operation))
+            })
+    }
+
+    /// Holds back the operations. Returns the pending change with the same operations instead if
+    /// there is one, e.g. when a computed element or rule produces the same change again.
+    pub async fn submit(
+        &self,
+        instance: &str,
+        channel: Option<&str>,
+        operations: Vec<Operation>,
+    ) -> PendingChange {
+        let mut pending = self.pending.write().await;
+        if let Some(change) = pending.changes.values().find(|change| {
+            change.instance == instance
+                && change.channel.as_deref() == channel
+                && change.operations == operations
+        }) {
+            return change.clone();
+        }
+        let change = PendingChange {
+            id: pending.next_id,
+            instance: instance.to_owned(),
+            operations,
+            channel: channel.map(str::to_owned),
+            submitted: Utc::now(),
+        };
+        pending.next_id += 1;
+        pending.changes.insert(change.id, change.clone());
+        change
+    }
+
+    /// Returns the pending changes of the instance, oldest first.
+    pub async fn get_pending(&self, instance: &str) -> Vec<PendingChange> {
+        self.pending
+            .read()
+            .await
+            .changes
+            .values()
+            .filter(|change| change.instance == instance)
+            .cloned()
+            .collect()
+    }
+
+    /// Drops all pending changes of a deleted instance.
+    pub async fn remove_instance(&self, instance: &str) {
+        self.pending
+            .write()
+            .await
+            .changes
+            .retain(|_, change| change.instance != instance);
+    }
+
+    /// Removes the pending change to approve or reject it.
+    pub async fn take(&self, instance: &str, id: u64) -> Option<PendingChange> {
+        let mut pending = self.pending.write().await;
+        match pending.changes.get(&id) {
+            Some(change) if change.instance == instance => pending.changes.remove(&id),
+            _ => None,
+        }
+    }
+}
+
+fn requires_approval(configuration: &ApprovalConfig, operation: &Operation) -> bool {
+    configuration.operations.contains(&operation.kind())
+        && (configuration.elements.is_empty()
+            || operation
+                .get_element()
+                .is_some_and(|element| configuration.elements.iter().any(|id| id == element)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::operation::OperationKind;
+
+    #[tokio::test]
+    async fn test_only_configured_operations_are_held_back() {
+        let queue = ApprovalQueue::new(HashMap::from([(
+            String::from("news"),
+            ApprovalConfig {
+                operations: vec![OperationKind::SetText],
+                elements: vec![String::from("StrapLine")],
+            },
+        )]));
+        let set_text = |id: &str| Operation::SetText {
+            id: id.to_owned(),
+            text: String::from("Breaking news"),
+            tween_duration: None,
+        };
+        let animation = Operation::ExecuteAnimation {
+            animation_sequence: String::from("StrapLineIn"),
+            execute_at: None,
+        };
+
+        assert!(queue.requires_approval("news", &[set_text("StrapLine")]));
+        assert!(queue.requires_approval("news", &[set_text("StrapLine"), animation.clone()]));
+        assert!(!queue.requires_approval("news", &[set_text("Clock"), animation]));
+        assert!(!queue.requires_approval("sports", &[set_text("StrapLine")]));
+
+        let change = queue
+            .submit("news", None, vec![set_text("StrapLine")])
+            .await;
+        assert_eq!(vec![change.clone()], queue.get_pending("news").await);
+        let resubmitted = queue
+            .submit("news", None, vec![set_text("StrapLine")])
+            .await;
+        assert_eq!(change.id, resubmitted.id);
+        assert!(queue.take("sports", change.id).await.is_none());
+        assert_eq!(Some(change.clone()), queue.take("news", change.id).await);
+        assert!(queue.take("news", change.id).await.is_none());
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::controller::ConfigDocument;
+use crate::defaults::DataDefaults;
+use crate::emergency::EmergencyOverrides;
+use crate::expression::ComputedElements;
+use crate::feedback::FeedbackButtons;
+use crate::flags::FeatureFlags;
+use crate::localization::Localization;
+use crate::rotation::Rotations;
+use crate::rules::Rules;
+use crate::rundown::Rundown;
+use crate::snapshot::Snapshots;
+use crate::state::InstanceState;
+use crate::webhooks::Webhooks;
+
+/// All data of a single instance that is managed by the server, except for its secrets.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InstancePackage {
+    #[serde(default)]
+    pub config: ConfigDocument,
+    #[serde(default)]
+    pub defaults: DataDefaults,
+    #[serde(default)]
+    pub rundown: Rundown,
+    #[serde(default)]
+    pub rules: Rules,
+    #[serde(default)]
+    pub webhooks: Webhooks,
+    #[serde(default)]
+    pub computed_elements: ComputedElements,
+    #[serde(default)]
+    pub emergency_overrides: EmergencyOverrides,
+    #[serde(default)]
+    pub feedback_buttons: FeedbackButtons,
+    #[serde(default)]
+    pub localization: Localization,
+    #[serde(default)]
+    pub rotations: Rotations,
+    #[serde(default)]
+    pub feature_flags: FeatureFlags,
+    #[serde(default)]
+    pub state: InstanceState,
+    #[serde(default)]
+    pub snapshots: Snapshots,
+}
+
+/// Packages of multiple instances, e.g. to provision a new server with a full graphics package.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceArchive {
+    pub instances: BTreeMap<String, InstancePackage>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImportStatus {
+    Imported,
+    /// The instance was imported but restored to its previous data because the import of another
+    /// instance failed.
+    RolledBack,
+    Failed,
+    /// The instance was not imported because the import of another instance failed.
+    Skipped,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportResult {
+    pub status: ImportStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub imported: bool,
+    pub instances: BTreeMap<String, ImportResult>,
+}
+
+impl ImportReport {
+    /// Creates a report in which the given instances failed and all others were skipped.
+    pub fn failed(archive: &InstanceArchive, mut errors: BTreeMap<String, String>) -> ImportReport {
+        ImportReport {
+            imported: false,
+            instances: archive
+                .instances
+                .keys()
+                .map(|instance| {
+                    let result = match errors.remove(instance) {
+                        Some(message) => ImportResult {
+                            status: ImportStatus::Failed,
+                            message: Some(message),
+                        },
+                        None => ImportResult {
+                            status: ImportStatus::Skipped,
+                            message: None,
+                        },
+                    };
+                    (instance.clone(), result)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Overview of an instance which has stored data or a current state.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceSummary {
+    pub name: String,
+    /// When the stored data of the instance was last changed.
+    pub last_modified: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires: Option<DateTime<Utc>>,
+    /// Expired instances are no longer served to new clients.
+    pub expired: bool,
+    /// Number of elements in the current state.
+    pub elements: usize,
+    pub connected_clients: usize,
+}
+
+pub enum CloneResult {
+    Cloned,
+    /// The instance to clone has neither stored data nor a current state.
+    NotFound,
+    /// An instance with the new name exists already.
+    AlreadyExists,
+}
+
+/// Checks all names in the archive, so invalid archives are rejected before anything is imported.
+/// Returns the errors per instance.
+pub fn validate_archive(archive: &InstanceArchive) -> BTreeMap<String, String> {
+    archive
+        .instances
+        .iter()
+        .filter_map(|(instance, package)| {
+            if !crate::fs::is_valid_instance_name(instance) {
+                return Some((instance.clone(), String::from("Invalid instance name.")));
+            }
+            package
+                .snapshots
+                .keys()
+                .find(|name| !crate::snapshot::is_valid_snapshot_name(name))
+                .map(|name| format!("Invalid snapshot name: {name}."))
+                .or_else(|| crate::defaults::validate_defaults(&package.defaults))
+                .or_else(|| crate::rundown::validate_rundown(&package.rundown))
+                .or_else(|| crate::rules::validate_rules(&package.rules))
+                .or_else(|| crate::webhooks::validate_webhooks(&package.webhooks))
+                .or_else(|| {
+                    crate::expression::validate_computed_elements(&package.computed_elements)
+                })
+                .or_else(|| crate::feedback::validate_buttons(&package.feedback_buttons))
+                .or_else(|| crate::rotation::validate_rotations(&package.rotations))
+                .map(|message| (instance.clone(), message))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_archive() {
+        let mut archive = InstanceArchive::default();
+        archive
+            .instances
+            .insert(String::from("scoreboard"), InstancePackage::default());
+        assert!(validate_archive(&archive).is_empty());
+
+        archive
+            .instances
+            .insert(String::from("../lower-third"), InstancePackage::default());
+        let errors = validate_archive(&archive);
+        assert_eq!(1, errors.len());
+
+        let report = ImportReport::failed(&archive, errors);
+        assert!(!report.imported);
+        assert_eq!(
+            ImportStatus::Failed,
+            report.instances["../lower-third"].status
+        );
+        assert_eq!(ImportStatus::Skipped, report.instances["scoreboard"].status);
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+const AS_RUN_FILE_NAME: &str = "as-run.jsonl";
+
+/// Time span in which a renderer showed an image, as acknowledged by the renderer.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AsRunRecord {
+    pub client: usize,
+    pub element: String,
+    pub asset: String,
+    pub started: DateTime<Utc>,
+    /// End of the display, not set while the image is still shown.
+    pub ended: Option<DateTime<Utc>>,
+}
+
+struct OpenDisplay {
+    instance: String,
+    asset: String,
+    started: DateTime<Utc>,
+}
+
+/// Log of the images that renderers actually showed, as proof that contracted logos aired. A
+/// display starts when the renderer acknowledges that the image was loaded and ends when another
+/// image is shown in the element or the renderer disconnects. Finished displays are appended to
+/// a log in the instance folder.
+pub struct AsRunLog {
+    data_folder: PathBuf,
+    /// Displays per client and element which are still on air.
+    open_displays: RwLock<HashMap<(usize, String), OpenDisplay>>,
+}
+
+impl AsRunLog {
+    pub fn new(data_folder: PathBuf) -> AsRunLog {
+        AsRunLog {
+            data_folder,
+            open_displays: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn record_display(&self, instance: &str, client: usize, element: &str, asset: &str) {
+        let display = OpenDisplay {
+            instance: instance.to_owned(),
+            asset: asset.to_owned(),
+            started: Utc::now(),
+        };
+        let previous = self
+            .open_displays
+            .write()
+            .await
+            .insert((client, element.to_owned()), display);
+        if let Some(previous) = previous {
+            self.finish_display(client, element, previous).await;
+        }
+    }
+
+    /// Ends all displays of the client.
+    pub async fn client_disconnected(&self, client: usize) {
+        let displays: Vec<((usize, String), OpenDisplay)> = {
+            let mut open_displays = self.open_displays.write().await;
+            let keys: Vec<(usize, String)> = open_displays
+                .keys()
+                .filter(|(display_client, _)| *display_client == client)
+                .cloned()
+                .collect();
+            keys.into_iter()
+                .filter_map(|key| open_displays.remove(&key).map(|display| (key, display)))
+                .collect()
+        };
+        for ((client, element), display) in displays {
+            self.finish_display(client, &element, display).await;
+        }
+    }
+
+    async fn finish_display(&self, client: usize, element: &str, display: OpenDisplay) {
+        let record = AsRunRecord {
+            client,
+            element: element.to_owned(),
+            asset: display.asset,
+            started: display.started,
+            ended: Some(Utc::now()),
+        };
+        if let Err(err) = self.append(&display.instance, &record).await {
+            error!(
+                "Could not write as-run record of instance {}: {}.",
+                display.instance, err
+            );
+        }
+    }
+
+    async fn append(&self, instance: &str, record: &AsRunRecord) -> anyhow::Result<()> {
+        let instance_folder = crate::fs::get_instance_folder(&self.data_folder, instance)?;
+        tokio::fs::create_dir_all(&instance_folder).await?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(instance_folder.join(AS_RUN_FILE_NAME))
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Returns the displays of the instance which overlap with the given time range, including
+    /// the displays that are still on air, ordered by their start.
+    pub async fn get_records(
+        &self,
+        instance: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<AsRunRecord>> {
+        let log_path =
+            crate::fs::get_instance_folder(&self.data_folder, instance)?.join(AS_RUN_FILE_NAME);
+        let contents = if log_path.exists() {
+            tokio::fs::read_to_string(log_path).await?
+        } else {
+            String::new()
+        };
+        let mut records: Vec<AsRunRecord> = contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        records.extend(
+            self.open_displays
+                .read()
+                .await
+                .iter()
+                .filter(|(_, display)| display.instance == instance)
+                .map(|((client, element), display)| AsRunRecord {
+                    client: *client,
+                    element: element.clone(),
+                    asset: display.asset.clone(),
+                    started: display.started,
+                    ended: None,
+                }),
+        );
+        records.retain(|record| is_in_range(record, from, to));
+        records.sort_by_key(|record| record.started);
+        Ok(records)
+    }
+}
+
+fn is_in_range(
+    record: &AsRunRecord,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> bool {
+    let ended_after_from = match (from, record.ended) {
+        (Some(from), Some(ended)) => ended >= from,
+        _ => true,
+    };
+    ended_after_from && to.is_none_or(|to| record.started <= to)
+}
+
+pub fn write_csv(records: &[AsRunRecord]) -> anyhow::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["client", "element", "asset", "started", "ended", "seconds"])?;
+    for record in records {
+        let ended = record.ended.map(|ended| ended.to_rfc3339());
+        let seconds = record
+            .ended
+            .map(|ended| (ended - record.started).num_milliseconds() as f64 / 1000.0);
+        writer.write_record([
+            record.client.to_string().as_str(),
+            record.element.as_str(),
+            record.asset.as_str(),
+            record.started.to_rfc3339().as_str(),
+            ended.as_deref().unwrap_or_default(),
+            seconds
+                .map(|seconds| seconds.to_string())
+                .unwrap_or_default()
+                .as_str(),
+        ])?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_displays_end_with_next_image_or_disconnect() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let as_run = AsRunLog::new(path.clone());
+        as_run
+            .record_display("sponsors", 1, "Logo", "acme.png")
+            .await;
+        as_run
+            .record_display("sponsors", 2, "Logo", "acme.png")
+            .await;
+        as_run
+            .record_display("sponsors", 1, "Logo", "brewery.png")
+            .await;
+
+        let records = as_run.get_records("sponsors", None, None).await.unwrap();
+        assert_eq!(3, records.len());
+        assert_eq!(
+            vec![
+                ("acme.png", true),
+                ("acme.png", false),
+                ("brewery.png", false)
+            ],
+            records
+                .iter()
+                .map(|record| (record.asset.as_str(), record.ended.is_some()))
+                .collect::<Vec<_>>()
+        );
+
+        as_run.client_disconnected(2).await;
+        let records = as_run.get_records("sponsors", None, None).await.unwrap();
+        assert_eq!(
+            1,
+            records
+                .iter()
+                .filter(|record| record.ended.is_none())
+                .count()
+        );
+        assert!(as_run
+            .get_records("other", None, None)
+            .await
+            .unwrap()
+            .is_empty());
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::{Mutex, RwLock};
+
+use crate::store::{load_document, store_document};
+
+pub mod signing;
+pub mod upload;
+
+pub use zagreus_client::dto::AssetInfo;
+
+/// Maps asset names (e.g. `sponsor.png`) to the file of their current version.
+pub type AssetVersions = BTreeMap<String, String>;
+
+const ASSET_VERSIONS_FILE_NAME: &str = "asset-versions.json";
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Returns the metadata of the file storing a version of the asset with the given name.
+pub async fn get_asset_info(
+    assets_folder: &Path,
+    name: &str,
+    file: &str,
+) -> anyhow::Result<AssetInfo> {
+    let metadata = tokio::fs::metadata(assets_folder.join(file)).await?;
+    let (hash, extension) = file.rsplit_once('.').unwrap_or((file, ""));
+    Ok(AssetInfo {
+        name: name.to_owned(),
+        file: file.to_owned(),
+        size: metadata.len(),
+        content_type: get_content_type(extension).to_owned(),
+        uploaded: metadata.modified()?.into(),
+        sha256: hash.to_owned(),
+    })
+}
+
+fn get_content_type(extension: &str) -> &'static str {
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "wav" => "audio/wav",
+        "ogg" => "audio/ogg",
+        "json" => "application/json",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        _ => DEFAULT_CONTENT_TYPE,
+    }
+}
+
+/// Uploaded assets are stored under their content hash so that their URLs can be cached forever.
+/// This store keeps track of which file is the current version of an asset name, so replacing an
+/// asset results in a new URL on every renderer.
+pub struct AssetVersionStore {
+    versions_path: PathBuf,
+    versions: RwLock<Option<AssetVersions>>,
+    /// Serializes changes, so concurrent changes do not overwrite each other.
+    update_lock: Mutex<()>,
+}
+
+impl AssetVersionStore {
+    pub fn new(data_folder: PathBuf) -> AssetVersionStore {
+        AssetVersionStore {
+            versions_path: data_folder.join(ASSET_VERSIONS_FILE_NAME),
+            versions: RwLock::new(None),
+            update_lock: Mutex::new(()),
+        }
+    }
+
+    pub async fn get_versions(&self) -> anyhow::Result<AssetVersions> {
+        if let Some(versions) = self.versions.read().await.as_ref() {
+            return Ok(versions.clone());
+        }
+        // loaded while holding the write lock, so a concurrent change is not overwritten
+        let mut cached = self.versions.write().await;
+        if let Some(versions) = cached.as_ref() {
+            return Ok(versions.clone());
+        }
+        let versions: AssetVersions = load_document(&self.versions_path).await?;
+        *cached = Some(versions.clone());
+        Ok(versions)
+    }
+
+    /// Returns whether the version of the asset has changed.
+    pub async fn set_version(&self, name: &str, file_name: &str) -> anyhow::Result<bool> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut versions = self.get_versions().await?;
+        if versions.get(name).map(String::as_str) == Some(file_name) {
+            return Ok(false);
+        }
+        versions.insert(name.to_owned(), file_name.to_owned());
+        store_document(&self.versions_path, &versions).await?;
+        *self.versions.write().await = Some(versions);
+        Ok(true)
+    }
+
+    /// Removes the asset and returns the file of its current version, if the asset existed.
+    pub async fn remove_version(&self, name: &str) -> anyhow::Result<Option<String>> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut versions = self.get_versions().await?;
+        let Some(file_name) = versions.remove(name) else {
+            return Ok(None);
+        };
+        store_document(&self.versions_path, &versions).await?;
+        *self.versions.write().await = Some(versions);
+        Ok(Some(file_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_version() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let store = AssetVersionStore::new(path.clone());
+        assert!(store.set_version("sponsor.png", "abc.png").await.unwrap());
+        assert!(!store.set_version("sponsor.png", "abc.png").await.unwrap());
+        assert!(store.set_version("sponsor.png", "def.png").await.unwrap());
+
+        let reloaded_store = AssetVersionStore::new(path.clone());
+        assert_eq!(
+            Some(&String::from("def.png")),
+            reloaded_store
+                .get_versions()
+                .await
+                .unwrap()
+                .get("sponsor.png")
+        );
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_remove_version() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let store = AssetVersionStore::new(path.clone());
+        store.set_version("sponsor.png", "abc.png").await.unwrap();
+        store.set_version("logo.png", "def.png").await.unwrap();
+
+        assert_eq!(
+            Some(String::from("def.png")),
+            store.remove_version("logo.png").await.unwrap()
+        );
+        assert_eq!(None, store.remove_version("logo.png").await.unwrap());
+
+        let reloaded_store = AssetVersionStore::new(path.clone());
+        assert_eq!(
+            vec!["sponsor.png"],
+            reloaded_store
+                .get_versions()
+                .await
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>()
+        );
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
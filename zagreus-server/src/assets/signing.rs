@@ -0,0 +1,152 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use base64::Engine;
+use chrono::{TimeZone, Utc};
+
+use crate::config::AssetSigningConfig;
+use crate::secrets::SecretStore;
+
+/// Purpose of the subkey which signs asset URLs.
+const ASSET_URL_SIGNING_PURPOSE: &str = "asset-url";
+/// Path under which assets are served to clients with a signed URL, e.g. through a CDN.
+pub const SIGNED_ASSETS_PATH: &str = "/signed-assets";
+
+pub use zagreus_client::dto::SignedAssetUrl;
+
+#[derive(Deserialize)]
+pub struct AssetSignature {
+    /// Expiry as unix timestamp in seconds.
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// Returns the expiry of a URL which is valid for at least the given seconds. The expiry is
+/// rounded up to the signing window, so all URLs of an asset created within a window are equal
+/// and an edge CDN can serve them from its cache.
+pub fn get_expiry(now: i64, valid_seconds: i64, window_seconds: i64) -> i64 {
+    let expires = now + valid_seconds;
+    if window_seconds <= 0 {
+        return expires;
+    }
+    (expires + window_seconds - 1).div_euclid(window_seconds) * window_seconds
+}
+
+pub async fn sign_url(
+    secrets: &SecretStore,
+    configuration: &AssetSigningConfig,
+    file: &str,
+    expires: i64,
+) -> anyhow::Result<SignedAssetUrl> {
+    let signature = secrets
+        .sign(
+            ASSET_URL_SIGNING_PURPOSE,
+            get_signed_data(file, expires).as_bytes(),
+        )
+        .await?;
+    let base_url = configuration
+        .base_url
+        .as_deref()
+        .unwrap_or_default()
+        .trim_end_matches('/');
+    Ok(SignedAssetUrl {
+        url: format!(
+            "{base_url}{SIGNED_ASSETS_PATH}/{file}?expires={expires}&signature={}",
+            BASE64_URL.encode(signature)
+        ),
+        expires: Utc
+            .timestamp_opt(expires, 0)
+            .single()
+            .unwrap_or_else(Utc::now),
+    })
+}
+
+/// Returns whether the signature of the asset file is valid and did not expire yet.
+pub async fn verify_signature(
+    secrets: &SecretStore,
+    file: &str,
+    signature: &AssetSignature,
+    now: i64,
+) -> anyhow::Result<bool> {
+    if signature.expires <= now {
+        return Ok(false);
+    }
+    let Ok(signature_bytes) = BASE64_URL.decode(&signature.signature) else {
+        return Ok(false);
+    };
+    secrets
+        .verify(
+            ASSET_URL_SIGNING_PURPOSE,
+            get_signed_data(file, signature.expires).as_bytes(),
+            &signature_bytes,
+        )
+        .await
+}
+
+/// Checks that the asset file name is a single path component, e.g. `abc.png`.
+pub fn is_valid_asset_file(file: &str) -> bool {
+    !file.is_empty()
+        && !file.starts_with('.')
+        && file
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+fn get_signed_data(file: &str, expires: i64) -> String {
+    format!("{file}:{expires}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_is_rounded_up_to_window() {
+        assert_eq!(7200, get_expiry(1000, 3600, 3600));
+        assert_eq!(7200, get_expiry(3599, 3600, 3600));
+        assert_eq!(3600, get_expiry(0, 3600, 3600));
+        assert_eq!(4600, get_expiry(1000, 3600, 0));
+
+        assert!(is_valid_asset_file("abc.png"));
+        assert!(!is_valid_asset_file("../secret.key"));
+        assert!(!is_valid_asset_file(".."));
+    }
+
+    #[tokio::test]
+    async fn test_signed_urls_expire_and_are_bound_to_file() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let secrets = SecretStore::new(path.clone(), path.join("secret.key"));
+        let configuration = AssetSigningConfig {
+            base_url: Some(String::from("https://cdn.example.com/")),
+            ..AssetSigningConfig::default()
+        };
+        let now = Utc::now().timestamp();
+        let signed = sign_url(&secrets, &configuration, "abc.png", now + 60)
+            .await
+            .unwrap();
+        assert!(signed
+            .url
+            .starts_with("https://cdn.example.com/signed-assets/abc.png?expires="));
+
+        let signature = AssetSignature {
+            expires: now + 60,
+            signature: signed.url.split("signature=").nth(1).unwrap().to_owned(),
+        };
+        assert!(verify_signature(&secrets, "abc.png", &signature, now)
+            .await
+            .unwrap());
+        assert!(!verify_signature(&secrets, "def.png", &signature, now)
+            .await
+            .unwrap());
+        assert!(!verify_signature(&secrets, "abc.png", &signature, now + 60)
+            .await
+            .unwrap());
+        let extended = AssetSignature {
+            expires: now + 3600,
+            ..signature
+        };
+        assert!(!verify_signature(&secrets, "abc.png", &extended, now)
+            .await
+            .unwrap());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
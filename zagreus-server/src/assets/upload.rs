@@ -0,0 +1,421 @@
+use std::collections::HashSet;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+
+use crate::store::{load_document, store_document};
+use crate::worker::WorkerPool;
+
+pub use zagreus_client::dto::{CreateUploadDto, UploadStatusDto};
+
+/// Header with the offset at which a chunk starts, as in the tus protocol.
+pub const UPLOAD_OFFSET_HEADER: &str = "upload-offset";
+/// Uploads which did not receive a chunk for this long are discarded.
+pub const UPLOAD_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+/// Maximum size of a chunk, which is held in memory until it is written.
+pub const MAX_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+const METADATA_EXTENSION: &str = "json";
+const PART_EXTENSION: &str = "part";
+const RECEIVED_EXTENSION: &str = "received";
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
+
+pub enum AppendResult {
+    Appended(UploadStatusDto),
+    NotFound,
+    /// The chunk does not start at the current offset of the upload, which is returned.
+    OffsetMismatch(u64),
+    /// The chunk exceeds the announced size of the upload.
+    TooLarge,
+    /// Another chunk of the upload is being written or the upload is being completed.
+    Busy,
+}
+
+pub enum UploadCompletion {
+    Completed {
+        name: String,
+        file: String,
+    },
+    NotFound,
+    Incomplete(UploadStatusDto),
+    /// The content does not match the announced hash, the upload was discarded.
+    ChecksumMismatch,
+    Busy,
+}
+
+/// Stores partial uploads of assets, so large assets can be uploaded in chunks over unreliable
+/// networks and resumed after a failed chunk (or a restart of the server). The content of an
+/// upload is kept in a `.part` file whose length is the offset of the next chunk.
+pub struct UploadStore {
+    folder: PathBuf,
+    /// Uploads whose part file is being written.
+    active: Mutex<HashSet<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct UploadMetadata {
+    name: String,
+    size: u64,
+    sha256: String,
+}
+
+/// Marks an upload as active until it is dropped.
+struct ActiveUpload<'a> {
+    store: &'a UploadStore,
+    id: String,
+}
+
+impl Drop for ActiveUpload<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut active) = self.store.active.lock() {
+            active.remove(&self.id);
+        }
+    }
+}
+
+impl UploadStore {
+    pub fn new(data_folder: PathBuf) -> UploadStore {
+        UploadStore {
+            folder: crate::fs::get_uploads_folder(&data_folder),
+            active: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub async fn create(&self, upload: &CreateUploadDto) -> anyhow::Result<UploadStatusDto> {
+        if let Err(err) = self.remove_expired().await {
+            warn!("Could not remove expired uploads: {:#}.", err);
+        }
+        let id = format!("{:032x}", rand::random::<u128>());
+        let metadata = UploadMetadata {
+            name: upload.name.clone(),
+            size: upload.size,
+            sha256: upload.sha256.to_ascii_lowercase(),
+        };
+        store_document(&self.get_metadata_path(&id), &Some(metadata)).await?;
+        tokio::fs::write(self.get_part_path(&id), []).await?;
+        Ok(UploadStatusDto {
+            id,
+            name: upload.name.clone(),
+            size: upload.size,
+            offset: 0,
+        })
+    }
+
+    pub async fn get_status(&self, id: &str) -> anyhow::Result<Option<UploadStatusDto>> {
+        Ok(self
+            .get_upload(id)
+            .await?
+            .map(|(metadata, offset)| UploadStatusDto {
+                id: id.to_owned(),
+                name: metadata.name,
+                size: metadata.size,
+                offset,
+            }))
+    }
+
+    /// Appends the chunk if it starts at the current offset of the upload.
+    pub async fn append(
+        &self,
+        id: &str,
+        offset: u64,
+        chunk: &[u8],
+    ) -> anyhow::Result<AppendResult> {
+        let Some(_active) = self.activate(id) else {
+            return Ok(AppendResult::Busy);
+        };
+        let Some((metadata, current_offset)) = self.get_upload(id).await? else {
+            return Ok(AppendResult::NotFound);
+        };
+        if offset != current_offset {
+            return Ok(AppendResult::OffsetMismatch(current_offset));
+        }
+        let new_offset = offset + chunk.len() as u64;
+        if new_offset > metadata.size {
+            return Ok(AppendResult::TooLarge);
+        }
+        let mut part = tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(self.get_part_path(id))
+            .await?;
+        part.write_all(chunk).await?;
+        part.flush().await?;
+        Ok(AppendResult::Appended(UploadStatusDto {
+            id: id.to_owned(),
+            name: metadata.name,
+            size: metadata.size,
+            offset: new_offset,
+        }))
+    }
+
+    /// Checks the content of the complete upload and moves it to the assets folder, where it is
+    /// stored under its hash like other assets. Hashing runs on the worker pool, since assets can
+    /// be large.
+    pub async fn complete(
+        &self,
+        id: &str,
+        assets_folder: &Path,
+        workers: &WorkerPool,
+    ) -> anyhow::Result<UploadCompletion> {
+        let Some(_active) = self.activate(id) else {
+            return Ok(UploadCompletion::Busy);
+        };
+        let Some((metadata, offset)) = self.get_upload(id).await? else {
+            return Ok(UploadCompletion::NotFound);
+        };
+        if offset != metadata.size {
+            return Ok(UploadCompletion::Incomplete(UploadStatusDto {
+                id: id.to_owned(),
+                name: metadata.name,
+                size: metadata.size,
+                offset,
+            }));
+        }
+        let part_path = self.get_part_path(id);
+        let hashed_path = part_path.clone();
+        let hash = workers.run(move || hash_file(&hashed_path)).await??;
+        if hash != metadata.sha256 {
+            self.remove_files(id).await?;
+            return Ok(UploadCompletion::ChecksumMismatch);
+        }
+        let file_name = match Path::new(&metadata.name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+        {
+            Some(extension) => format!("{hash}.{extension}"),
+            None => hash,
+        };
+        tokio::fs::rename(&part_path, assets_folder.join(&file_name)).await?;
+        self.remove_files(id).await?;
+        Ok(UploadCompletion::Completed {
+            name: metadata.name,
+            file: file_name,
+        })
+    }
+
+    /// Discards the upload, returns `false` if there is no such upload.
+    pub async fn remove(&self, id: &str) -> anyhow::Result<bool> {
+        if self.get_upload(id).await?.is_none() {
+            return Ok(false);
+        }
+        self.remove_files(id).await?;
+        Ok(true)
+    }
+
+    fn activate(&self, id: &str) -> Option<ActiveUpload<'_>> {
+        let mut active = self.active.lock().ok()?;
+        if !active.insert(id.to_owned()) {
+            return None;
+        }
+        Some(ActiveUpload {
+            store: self,
+            id: id.to_owned(),
+        })
+    }
+
+    async fn get_upload(&self, id: &str) -> anyhow::Result<Option<(UploadMetadata, u64)>> {
+        if !is_valid_upload_id(id) {
+            return Ok(None);
+        }
+        let metadata: Option<UploadMetadata> = load_document(&self.get_metadata_path(id)).await?;
+        let Some(metadata) = metadata else {
+            return Ok(None);
+        };
+        match tokio::fs::metadata(self.get_part_path(id)).await {
+            Ok(part) => Ok(Some((metadata, part.len()))),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn remove_files(&self, id: &str) -> anyhow::Result<()> {
+        for path in [self.get_part_path(id), self.get_metadata_path(id)] {
+            match tokio::fs::remove_file(&path).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove_expired(&self) -> anyhow::Result<()> {
+        if !self.folder.exists() {
+            return Ok(());
+        }
+        let now = SystemTime::now();
+        let mut entries = tokio::fs::read_dir(&self.folder).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let extension = path.extension().and_then(|extension| extension.to_str());
+            if extension == Some(RECEIVED_EXTENSION) {
+                let age = now.duration_since(entry.metadata().await?.modified()?);
+                if age.is_ok_and(|age| age > UPLOAD_EXPIRY) {
+                    info!("Removing abandoned upload file {}.", path.display());
+                    tokio::fs::remove_file(&path).await?;
+                }
+                continue;
+            }
+            if extension != Some(METADATA_EXTENSION) {
+                continue;
+            }
+            let Some(id) = path.file_stem().and_then(|id| id.to_str()) else {
+                continue;
+            };
+            let last_modified = match tokio::fs::metadata(self.get_part_path(id)).await {
+                Ok(part) => part.modified()?,
+                Err(_) => entry.metadata().await?.modified()?,
+            };
+            let expired = now
+                .duration_since(last_modified)
+                .is_ok_and(|age| age > UPLOAD_EXPIRY);
+            if expired {
+                if let Some(_active) = self.activate(id) {
+                    info!("Removing expired upload {}.", id);
+                    self.remove_files(id).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the path of a new file for an asset which is uploaded with a single request. Files
+    /// which are left behind (e.g. by a crash) are removed after the upload expiry.
+    pub async fn create_received_file_path(&self) -> anyhow::Result<PathBuf> {
+        tokio::fs::create_dir_all(&self.folder).await?;
+        let id = format!("{:032x}", rand::random::<u128>());
+        Ok(self.folder.join(format!("{id}.{RECEIVED_EXTENSION}")))
+    }
+
+    fn get_metadata_path(&self, id: &str) -> PathBuf {
+        self.folder.join(format!("{id}.{METADATA_EXTENSION}"))
+    }
+
+    fn get_part_path(&self, id: &str) -> PathBuf {
+        self.folder.join(format!("{id}.{PART_EXTENSION}"))
+    }
+}
+
+/// Ids are generated by the server, so anything else (e.g. a path) is rejected.
+fn is_valid_upload_id(id: &str) -> bool {
+    id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub fn is_valid_sha256(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+pub(crate) fn hash_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resumable_upload() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let assets_folder = crate::fs::get_assets_folder(&path).unwrap();
+        let workers = WorkerPool::new(1);
+        let store = UploadStore::new(path.clone());
+        let data = b"frame one, frame two";
+        let upload = store
+            .create(&CreateUploadDto {
+                name: "intro.mp4".to_owned(),
+                size: data.len() as u64,
+                sha256: format!("{:X}", Sha256::digest(data)),
+            })
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            store.append(&upload.id, 0, &data[..10]).await.unwrap(),
+            AppendResult::Appended(UploadStatusDto { offset: 10, .. })
+        ));
+        // a chunk which is sent again after its response was lost
+        assert!(matches!(
+            store.append(&upload.id, 0, &data[..10]).await.unwrap(),
+            AppendResult::OffsetMismatch(10)
+        ));
+        assert!(matches!(
+            store
+                .complete(&upload.id, &assets_folder, &workers)
+                .await
+                .unwrap(),
+            UploadCompletion::Incomplete(UploadStatusDto { offset: 10, .. })
+        ));
+        assert!(matches!(
+            store.append(&upload.id, 10, data).await.unwrap(),
+            AppendResult::TooLarge
+        ));
+
+        // the upload is resumed after a restart
+        let store = UploadStore::new(path.clone());
+        assert_eq!(
+            10,
+            store.get_status(&upload.id).await.unwrap().unwrap().offset
+        );
+        store.append(&upload.id, 10, &data[10..]).await.unwrap();
+        let UploadCompletion::Completed { name, file } = store
+            .complete(&upload.id, &assets_folder, &workers)
+            .await
+            .unwrap()
+        else {
+            panic!("Upload was not completed.");
+        };
+        assert_eq!("intro.mp4", name);
+        assert_eq!(format!("{:x}.mp4", Sha256::digest(data)), file);
+        assert_eq!(
+            data.to_vec(),
+            std::fs::read(assets_folder.join(&file)).unwrap()
+        );
+        assert!(store.get_status(&upload.id).await.unwrap().is_none());
+        assert!(store
+            .get_status("../asset-versions")
+            .await
+            .unwrap()
+            .is_none());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_checksum_mismatch() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let assets_folder = crate::fs::get_assets_folder(&path).unwrap();
+        let store = UploadStore::new(path.clone());
+        let upload = store
+            .create(&CreateUploadDto {
+                name: "intro.mp4".to_owned(),
+                size: 4,
+                sha256: format!("{:x}", Sha256::digest(b"good")),
+            })
+            .await
+            .unwrap();
+        store.append(&upload.id, 0, b"evil").await.unwrap();
+        assert!(matches!(
+            store
+                .complete(&upload.id, &assets_folder, &WorkerPool::new(1))
+                .await
+                .unwrap(),
+            UploadCompletion::ChecksumMismatch
+        ));
+        assert!(store.get_status(&upload.id).await.unwrap().is_none());
+        assert_eq!(0, std::fs::read_dir(&assets_folder).unwrap().count());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
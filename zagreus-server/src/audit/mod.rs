@@ -0,0 +1,119 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+
+use crate::websocket::message::InstanceMessage;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub instance: String,
+    pub operation: String,
+    pub payload: serde_json::Value,
+}
+
+/// Bounded in-memory log of all operations that were sent to template instances.
+pub struct AuditLog {
+    max_entries: usize,
+    entries: RwLock<VecDeque<AuditEntry>>,
+}
+
+impl AuditLog {
+    pub fn new(max_entries: usize) -> AuditLog {
+        AuditLog {
+            max_entries,
+            entries: RwLock::new(VecDeque::new()),
+        }
+    }
+
+    pub async fn record(&self, instance: &str, message: &InstanceMessage<'_>) {
+        let (operation, payload) = match message.to_operation() {
+            Ok(operation) => operation,
+            Err(err) => {
+                error!("Could not serialize message for audit log: {}.", err);
+                return;
+            }
+        };
+
+        let mut entries = self.entries.write().await;
+        if entries.len() >= self.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(AuditEntry {
+            timestamp: Utc::now(),
+            instance: instance.to_owned(),
+            operation,
+            payload,
+        });
+    }
+
+    pub async fn get_entries(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Vec<AuditEntry> {
+        self.entries
+            .read()
+            .await
+            .iter()
+            .filter(|entry| from.is_none_or(|from| entry.timestamp >= from))
+            .filter(|entry| to.is_none_or(|to| entry.timestamp <= to))
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn write_csv(entries: &[AuditEntry]) -> anyhow::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["timestamp", "instance", "operation", "payload"])?;
+    for entry in entries {
+        writer.write_record([
+            entry.timestamp.to_rfc3339().as_str(),
+            entry.instance.as_str(),
+            entry.operation.as_str(),
+            entry.payload.to_string().as_str(),
+        ])?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_is_bounded() {
+        let audit_log = AuditLog::new(2);
+        for text in ["first", "second", "third"] {
+            let message = InstanceMessage::SetText {
+                id: "Title",
+                text,
+                tween_duration: None,
+            };
+            audit_log.record("scoreboard", &message).await;
+        }
+
+        let entries = audit_log.get_entries(None, None).await;
+        assert_eq!(2, entries.len());
+        assert_eq!("SetText", entries[0].operation);
+        assert_eq!("second", entries[0].payload["text"]);
+    }
+
+    #[tokio::test]
+    async fn test_write_csv() {
+        let audit_log = AuditLog::new(10);
+        let message = InstanceMessage::AddClass {
+            id: "Scoreboard",
+            class: "active, highlighted",
+        };
+        audit_log.record("scoreboard", &message).await;
+
+        let csv = write_csv(&audit_log.get_entries(None, None).await).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(Some("timestamp,instance,operation,payload"), lines.next());
+        assert!(lines.next().unwrap().contains(",scoreboard,AddClass,\""));
+    }
+}
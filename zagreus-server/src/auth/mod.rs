@@ -0,0 +1,342 @@
+use axum::http::{header, HeaderMap, Method};
+use sha2::{Digest, Sha256};
+
+use crate::actions::ACTIONS_PATH_PREFIX;
+use crate::config::{ApiKeyConfig, AuthConfig};
+use crate::metrics::METRICS_PATH;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+/// Query parameter for clients which cannot set headers, e.g. websockets in browsers.
+const API_KEY_QUERY_PARAMETER: &str = "apiKey";
+/// Routes below `/api` which stay public, since they only describe the server.
+const PUBLIC_API_ROUTES: [&str; 3] = ["/api/version", "/api/protocol/schema", "/api/schemas"];
+/// Websocket routes below `/ws/instance/{instance}` which stream data to control applications.
+const PROTECTED_WEBSOCKET_SUFFIXES: [&str; 3] = ["/state", "/feedback", "/presence"];
+/// Websocket over which external controllers send commands, which therefore needs write access.
+const CONTROL_WEBSOCKET_PREFIX: &str = "/ws/control/";
+/// Prefix of the routes of a template instance.
+const INSTANCE_API_PREFIX: &str = "/api/instance/";
+/// Path segments of the routes approving and rejecting pending changes.
+const PENDING_CHANGES_SEGMENT: &str = "pending";
+const APPROVE_SEGMENT: &str = "approve";
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum Access {
+    Public,
+    Read,
+    Write,
+    /// Approving or rejecting pending changes.
+    Approve,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum AuthError {
+    /// No API key or an unknown one was presented.
+    Unauthorized,
+    /// The API key may only read.
+    Forbidden,
+    /// The API key may not approve pending changes.
+    CannotApprove,
+}
+
+/// Returns the access a request needs. Renderers, static files, assets, share links and published
+/// data stay public.
+pub fn get_required_access(path: &str, method: &Method) -> Access {
+    if path.starts_with(CONTROL_WEBSOCKET_PREFIX) || is_action_trigger(path) {
+        return Access::Write;
+    }
+    let protected = (path.starts_with("/api/") && !PUBLIC_API_ROUTES.contains(&path))
+        || path == METRICS_PATH
+        || (path.starts_with("/ws/instance/")
+            && PROTECTED_WEBSOCKET_SUFFIXES
+                .iter()
+                .any(|suffix| path.ends_with(suffix)));
+    if !protected {
+        Access::Public
+    } else if method == Method::GET || method == Method::HEAD {
+        Access::Read
+    } else if is_pending_change_decision(path) {
+        Access::Approve
+    } else {
+        Access::Write
+    }
+}
+
+/// Whether the path approves or rejects a pending change, i.e.
+/// `/api/instance/{instance}/pending/{id}/approve` or `/api/instance/{instance}/pending/{id}`.
+fn is_pending_change_decision(path: &str) -> bool {
+    let Some(rest) = path.strip_prefix(INSTANCE_API_PREFIX) else {
+        return false;
+    };
+    let segments: Vec<&str> = rest.split('/').collect();
+    match segments.as_slice() {
+        [instance, PENDING_CHANGES_SEGMENT, id]
+        | [instance, PENDING_CHANGES_SEGMENT, id, APPROVE_SEGMENT] => {
+            !instance.is_empty() && !id.is_empty()
+        }
+        _ => false,
+    }
+}
+
+/// Whether the path triggers an action of a template, which control surfaces do with GET requests,
+/// e.g. `/api/actions/scoreboard/animation/ScoreboardIn` (but not the catalogue itself).
+fn is_action_trigger(path: &str) -> bool {
+    path.strip_prefix(ACTIONS_PATH_PREFIX)
+        .is_some_and(|rest| rest.contains('/'))
+}
+
+/// Checks whether the request may access the route. Authentication is disabled if no API keys
+/// are configured.
+pub fn authorize(
+    configuration: &AuthConfig,
+    path: &str,
+    method: &Method,
+    headers: &HeaderMap,
+    query: Option<&str>,
+) -> Result<(), AuthError> {
+    if configuration.api_keys.is_empty() {
+        return Ok(());
+    }
+    let access = get_required_access(path, method);
+    if access == Access::Public || (access == Access::Read && configuration.anonymous_read) {
+        return Ok(());
+    }
+    let api_key = get_presented_key(headers, query)
+        .and_then(|key| find_api_key(configuration, key))
+        .ok_or(AuthError::Unauthorized)?;
+    match access {
+        Access::Write | Access::Approve if api_key.read_only => Err(AuthError::Forbidden),
+        Access::Approve if !api_key.approve => Err(AuthError::CannotApprove),
+        _ => Ok(()),
+    }
+}
+
+/// Returns whether the request may approve pending changes, for checks after a websocket was
+/// established.
+pub fn may_approve(configuration: &AuthConfig, headers: &HeaderMap, query: Option<&str>) -> bool {
+    configuration.api_keys.is_empty()
+        || get_presented_key(headers, query)
+            .and_then(|key| find_api_key(configuration, key))
+            .is_some_and(|api_key| api_key.approve && !api_key.read_only)
+}
+
+/// Returns the key passed as bearer token, in the `X-Api-Key` header or as query parameter.
+fn get_presented_key<'a>(headers: &'a HeaderMap, query: Option<&'a str>) -> Option<&'a str> {
+    let bearer_token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let header_key = headers
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+    let query_key = query.and_then(|query| {
+        query.split('&').find_map(|pair| {
+            pair.strip_prefix(API_KEY_QUERY_PARAMETER)
+                .and_then(|rest| rest.strip_prefix('='))
+        })
+    });
+    bearer_token.or(header_key).or(query_key)
+}
+
+/// Returns the configured API key matching the presented key. Keys are compared by their hashes
+/// in constant time, so the comparison does not reveal how much of a key matched.
+fn find_api_key<'a>(configuration: &'a AuthConfig, presented: &str) -> Option<&'a ApiKeyConfig> {
+    let presented_hash = Sha256::digest(presented.as_bytes());
+    configuration.api_keys.iter().find(|api_key| {
+        let hash = Sha256::digest(api_key.key.as_bytes());
+        hash.iter()
+            .zip(presented_hash.iter())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_required_access() {
+        assert_eq!(
+            Access::Write,
+            get_required_access("/api/instance/scoreboard/data/text", &Method::POST)
+        );
+        assert_eq!(
+            Access::Read,
+            get_required_access("/api/instance/scoreboard/state", &Method::GET)
+        );
+        assert_eq!(
+            Access::Read,
+            get_required_access("/ws/instance/scoreboard/state", &Method::GET)
+        );
+        assert_eq!(
+            Access::Public,
+            get_required_access("/ws/instance/scoreboard", &Method::GET)
+        );
+        assert_eq!(
+            Access::Public,
+            get_required_access("/static/zagreus-runtime.js", &Method::GET)
+        );
+        assert_eq!(
+            Access::Public,
+            get_required_access("/api/version", &Method::GET)
+        );
+        assert_eq!(Access::Read, get_required_access("/metrics", &Method::GET));
+        assert_eq!(
+            Access::Approve,
+            get_required_access("/api/instance/news/pending/3/approve", &Method::POST)
+        );
+        assert_eq!(
+            Access::Approve,
+            get_required_access("/api/instance/news/pending/3", &Method::DELETE)
+        );
+        // an instance named like the segment only needs write access for ordinary changes
+        assert_eq!(
+            Access::Write,
+            get_required_access("/api/instance/pending/state", &Method::PUT)
+        );
+        assert_eq!(
+            Access::Write,
+            get_required_access("/api/instance/pending/data/text", &Method::POST)
+        );
+        assert_eq!(
+            Access::Approve,
+            get_required_access("/api/instance/pending/pending/3/approve", &Method::POST)
+        );
+        assert_eq!(
+            Access::Write,
+            get_required_access("/ws/control/scoreboard", &Method::GET)
+        );
+        assert_eq!(
+            Access::Read,
+            get_required_access("/api/actions/scoreboard", &Method::GET)
+        );
+        assert_eq!(
+            Access::Write,
+            get_required_access(
+                "/api/actions/scoreboard/animation/ScoreboardIn",
+                &Method::GET
+            )
+        );
+    }
+
+    #[test]
+    fn test_authorize() {
+        let configuration = AuthConfig {
+            api_keys: vec![
+                ApiKeyConfig {
+                    name: String::from("companion"),
+                    key: String::from("secret-key"),
+                    read_only: false,
+                    approve: false,
+                },
+                ApiKeyConfig {
+                    name: String::from("dashboard"),
+                    key: String::from("read-key"),
+                    read_only: true,
+                    approve: false,
+                },
+                ApiKeyConfig {
+                    name: String::from("editor"),
+                    key: String::from("approve-key"),
+                    read_only: false,
+                    approve: true,
+                },
+            ],
+            anonymous_read: false,
+        };
+        let path = "/api/instance/scoreboard/data/text";
+        let headers = |name: header::HeaderName, value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static(value));
+            headers
+        };
+
+        assert_eq!(
+            Err(AuthError::Unauthorized),
+            authorize(&configuration, path, &Method::POST, &HeaderMap::new(), None)
+        );
+        assert_eq!(
+            Ok(()),
+            authorize(
+                &configuration,
+                path,
+                &Method::POST,
+                &headers(header::AUTHORIZATION, "Bearer secret-key"),
+                None
+            )
+        );
+        assert_eq!(
+            Err(AuthError::Unauthorized),
+            authorize(
+                &configuration,
+                path,
+                &Method::POST,
+                &headers(header::AUTHORIZATION, "Bearer secret"),
+                None
+            )
+        );
+        assert_eq!(
+            Err(AuthError::Forbidden),
+            authorize(
+                &configuration,
+                path,
+                &Method::POST,
+                &headers(header::HeaderName::from_static(API_KEY_HEADER), "read-key"),
+                None
+            )
+        );
+        let approve_path = "/api/instance/scoreboard/pending/1/approve";
+        assert_eq!(
+            Err(AuthError::CannotApprove),
+            authorize(
+                &configuration,
+                approve_path,
+                &Method::POST,
+                &headers(header::AUTHORIZATION, "Bearer secret-key"),
+                None
+            )
+        );
+        assert_eq!(
+            Ok(()),
+            authorize(
+                &configuration,
+                approve_path,
+                &Method::POST,
+                &headers(header::AUTHORIZATION, "Bearer approve-key"),
+                None
+            )
+        );
+        assert!(may_approve(
+            &configuration,
+            &HeaderMap::new(),
+            Some("apiKey=approve-key")
+        ));
+        assert!(!may_approve(
+            &configuration,
+            &HeaderMap::new(),
+            Some("apiKey=secret-key")
+        ));
+        assert_eq!(
+            Ok(()),
+            authorize(
+                &configuration,
+                "/ws/instance/scoreboard/state",
+                &Method::GET,
+                &HeaderMap::new(),
+                Some("diff=true&apiKey=read-key")
+            )
+        );
+        assert_eq!(
+            Ok(()),
+            authorize(
+                &AuthConfig::default(),
+                path,
+                &Method::POST,
+                &HeaderMap::new(),
+                None
+            )
+        );
+    }
+}
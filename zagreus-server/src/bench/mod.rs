@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use tokio::sync::{mpsc, watch};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+use crate::tls::{LocalServer, LocalStream};
+
+/// Element whose text the benchmark sets, so it does not interfere with the elements of templates.
+const BENCH_ELEMENT_ID: &str = "ZagreusBench";
+const BENCH_TEXT_PREFIX: &str = "bench-";
+/// Time to wait for messages which are still in flight after the last command.
+const DRAIN_TIME: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    pub instance: String,
+    pub clients: usize,
+    /// Commands per second.
+    pub rate: u32,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct BenchReport {
+    pub commands: u64,
+    pub failed_commands: u64,
+    /// Commands per second that were accepted by the server.
+    pub throughput: f64,
+    pub expected_deliveries: u64,
+    pub deliveries: u64,
+    /// Latencies from sending a command until a client received it.
+    pub p50: Duration,
+    pub p95: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Commands: {} sent, {} failed, {:.1} per second",
+            self.commands, self.failed_commands, self.throughput
+        )?;
+        writeln!(
+            f,
+            "Deliveries: {} of {} received",
+            self.deliveries, self.expected_deliveries
+        )?;
+        write!(
+            f,
+            "Latency: p50 {:.1} ms, p95 {:.1} ms, p99 {:.1} ms, max {:.1} ms",
+            to_millis(self.p50),
+            to_millis(self.p95),
+            to_millis(self.p99),
+            to_millis(self.max)
+        )
+    }
+}
+
+/// Connects simulated renderers to the instance on a running server and sets texts at the given
+/// rate through the API, measuring how long it takes until every renderer received a command.
+/// Simulated renderers count towards the connection limits of the server like any renderer.
+pub async fn run_bench(
+    local_server: &LocalServer,
+    api_key: Option<&str>,
+    options: &BenchOptions,
+) -> anyhow::Result<BenchReport> {
+    anyhow::ensure!(options.clients > 0, "at least one client is required");
+    anyhow::ensure!(
+        options.rate > 0,
+        "the rate must be at least one command per second"
+    );
+
+    let sent_commands: Arc<Mutex<HashMap<u64, Instant>>> = Arc::new(Mutex::new(HashMap::new()));
+    let (latency_sender, mut latency_receiver) = mpsc::unbounded_channel();
+    let (stop_sender, stop_receiver) = watch::channel(false);
+    let path = format!("/ws/instance/{}", options.instance);
+    let mut clients = Vec::with_capacity(options.clients);
+    for _ in 0..options.clients {
+        let socket = local_server.connect_websocket(&path, &[]).await?;
+        clients.push(tokio::spawn(receive_commands(
+            socket,
+            sent_commands.clone(),
+            latency_sender.clone(),
+            stop_receiver.clone(),
+        )));
+    }
+    drop(latency_sender);
+    info!(
+        "Connected {} simulated clients to instance {}.",
+        options.clients, options.instance
+    );
+
+    let http_client = local_server.get_http_client(REQUEST_TIMEOUT)?;
+    let url = local_server.get_url(
+        "http",
+        &format!("/api/instance/{}/data/text", options.instance),
+    );
+    let mut interval = tokio::time::interval(Duration::from_secs(1) / options.rate);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut requests = Vec::new();
+    let started = Instant::now();
+    let mut command = 0;
+    while started.elapsed() < options.duration {
+        interval.tick().await;
+        command += 1;
+        let body = serde_json::json!({
+            "id": BENCH_ELEMENT_ID,
+            "text": format!("{BENCH_TEXT_PREFIX}{command}"),
+        });
+        let mut request = http_client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        if let Some(api_key) = api_key {
+            request = request.header(crate::auth::API_KEY_HEADER, api_key);
+        }
+        lock_commands(&sent_commands).insert(command, Instant::now());
+        // requests are not awaited one after another, so slow responses do not lower the rate
+        requests.push(tokio::spawn(async move {
+            request
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .is_ok()
+        }));
+    }
+    let elapsed = started.elapsed();
+
+    let mut failed_commands = 0;
+    for request in requests {
+        if !request.await.unwrap_or(false) {
+            failed_commands += 1;
+        }
+    }
+    tokio::time::sleep(DRAIN_TIME).await;
+    let _ = stop_sender.send(true);
+    for client in clients {
+        let _ = client.await;
+    }
+
+    let mut latencies = Vec::new();
+    while let Ok(latency) = latency_receiver.try_recv() {
+        latencies.push(latency);
+    }
+    latencies.sort();
+    Ok(BenchReport {
+        commands: command,
+        failed_commands,
+        throughput: (command - failed_commands) as f64 / elapsed.as_secs_f64(),
+        expected_deliveries: (command - failed_commands) * options.clients as u64,
+        deliveries: latencies.len() as u64,
+        p50: crate::timing::get_percentile(&latencies, 50),
+        p95: crate::timing::get_percentile(&latencies, 95),
+        p99: crate::timing::get_percentile(&latencies, 99),
+        max: latencies.last().copied().unwrap_or_default(),
+    })
+}
+
+async fn receive_commands(
+    mut socket: WebSocketStream<Box<dyn LocalStream>>,
+    sent_commands: Arc<Mutex<HashMap<u64, Instant>>>,
+    latencies: mpsc::UnboundedSender<Duration>,
+    mut stop: watch::Receiver<bool>,
+) {
+    loop {
+        let message = tokio::select! {
+            message = socket.next() => message,
+            _ = stop.changed() => {
+                let _ = socket.close(None).await;
+                return;
+            }
+        };
+        let Some(Ok(message)) = message else {
+            return;
+        };
+        let Message::Text(text) = message else {
+            continue;
+        };
+        let Some(command) = parse_bench_command(&text) else {
+            continue;
+        };
+        let sent = lock_commands(&sent_commands).get(&command).copied();
+        if let Some(sent) = sent {
+            let _ = latencies.send(sent.elapsed());
+        }
+    }
+}
+
+/// Returns the number of the benchmark command in the message, if it is one.
+fn parse_bench_command(text: &str) -> Option<u64> {
+    let message: serde_json::Value = serde_json::from_str(text).ok()?;
+    if message["tag"] != "SetText" || message["payload"]["id"] != BENCH_ELEMENT_ID {
+        return None;
+    }
+    message["payload"]["text"]
+        .as_str()?
+        .strip_prefix(BENCH_TEXT_PREFIX)?
+        .parse()
+        .ok()
+}
+
+fn lock_commands(
+    sent_commands: &Mutex<HashMap<u64, Instant>>,
+) -> std::sync::MutexGuard<'_, HashMap<u64, Instant>> {
+    // the map stays consistent even if a holder panicked
+    sent_commands
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn to_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bench_command() {
+        assert_eq!(
+            Some(42),
+            parse_bench_command(
+                r#"{"tag":"SetText","payload":{"id":"ZagreusBench","text":"bench-42"}}"#
+            )
+        );
+        assert_eq!(
+            None,
+            parse_bench_command(r#"{"tag":"SetText","payload":{"id":"Title","text":"bench-42"}}"#)
+        );
+        assert_eq!(
+            None,
+            parse_bench_command(r#"{"tag":"Ping","payload":{"serverTime":0}}"#)
+        );
+    }
+}
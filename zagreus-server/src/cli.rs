@@ -1,4 +1,7 @@
+use std::fmt::Display;
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use structopt::StructOpt;
 
@@ -14,6 +17,8 @@ pub fn get_command() -> ZagreusServerCommand {
 pub struct ZagreusServerCommand {
     #[structopt(short, long, help = "Enables verbose logging")]
     pub verbose: bool,
+    #[structopt(long, help = "The address Zagreus should bind to.")]
+    pub bind_address: Option<IpAddr>,
     #[structopt(long, help = "The server port Zagreus should bind to.")]
     pub server_port: Option<u16>,
     #[structopt(
@@ -21,4 +26,85 @@ pub struct ZagreusServerCommand {
         help = "The data folder where Zagreus should store the template data."
     )]
     pub data_folder: Option<PathBuf>,
+    #[structopt(
+        long,
+        help = "Runs the self-test and exits with a non-zero exit code if a check fails."
+    )]
+    pub self_test: bool,
+    #[structopt(
+        long,
+        help = "Installs Zagreus as Windows service which starts automatically with the given flags."
+    )]
+    pub install_service: bool,
+    #[structopt(
+        long,
+        help = "Runs Zagreus under the control of the Windows service manager."
+    )]
+    pub run_as_service: bool,
+    #[structopt(subcommand)]
+    pub subcommand: Option<ZagreusServerSubcommand>,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ZagreusServerSubcommand {
+    /// Load-tests a running server with simulated clients and reports throughput and latency.
+    Bench {
+        #[structopt(
+            long,
+            default_value = "zagreus-bench",
+            help = "The instance the commands are sent to."
+        )]
+        instance: String,
+        #[structopt(
+            long,
+            default_value = "10",
+            parse(try_from_str = parse_at_least_one),
+            help = "The number of simulated websocket clients."
+        )]
+        clients: usize,
+        #[structopt(
+            long,
+            default_value = "50",
+            parse(try_from_str = parse_at_least_one),
+            help = "The commands per second."
+        )]
+        rate: u32,
+        #[structopt(
+            long,
+            default_value = "30",
+            help = "The duration of the test in seconds."
+        )]
+        duration_seconds: u64,
+    },
+}
+
+/// Parses a count which must be at least one, e.g. of simulated clients.
+fn parse_at_least_one<T>(value: &str) -> Result<T, String>
+where
+    T: FromStr + PartialOrd + From<u8>,
+    T::Err: Display,
+{
+    let count: T = value.parse().map_err(|err: T::Err| err.to_string())?;
+    if count < T::from(1) {
+        return Err(String::from("must be at least 1"));
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bench_counts_are_at_least_one() {
+        let command =
+            ZagreusServerCommand::from_iter_safe(["zagreus-server", "bench", "--rate", "5"]);
+        assert!(command.is_ok());
+        for arguments in [["--rate", "0"], ["--clients", "0"], ["--rate", "-1"]] {
+            let command = ZagreusServerCommand::from_iter_safe(
+                ["zagreus-server", "bench"].into_iter().chain(arguments),
+            );
+            assert!(command.is_err());
+        }
+    }
 }
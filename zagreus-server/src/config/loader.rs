@@ -1,6 +1,11 @@
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
+/// Checks the values of a loaded configuration which can not be expressed by its types.
+pub trait Validate {
+    fn validate(&self) -> anyhow::Result<()>;
+}
+
 pub struct ConfigurationManager<T>
 where
     T: Sized,
@@ -10,7 +15,7 @@ where
 
 impl<T> ConfigurationManager<T>
 where
-    T: Default + Clone + serde::Serialize + serde::de::DeserializeOwned,
+    T: Default + Clone + Validate + serde::Serialize + serde::de::DeserializeOwned,
 {
     pub fn load(
         application_folder: &Path,
@@ -25,9 +30,19 @@ where
             configuration = T::default();
             configuration_loader.store_config(&configuration)?;
         }
+        configuration.validate()?;
         Ok(ConfigurationManager { configuration })
     }
 
+    /// Loads and validates the existing configuration file, e.g. after it was edited while the
+    /// server runs.
+    pub fn reload(application_folder: &Path, config_file_name: &str) -> anyhow::Result<T> {
+        let configuration: T =
+            ConfigurationLoader::new(application_folder, config_file_name).load_config()?;
+        configuration.validate()?;
+        Ok(configuration)
+    }
+
     pub fn get_configuration(self) -> T {
         self.configuration
     }
@@ -90,6 +105,15 @@ mod tests {
         }
     }
 
+    impl Validate for TestConfig {
+        fn validate(&self) -> anyhow::Result<()> {
+            if self.string_value.is_empty() {
+                anyhow::bail!("stringValue must not be empty");
+            }
+            Ok(())
+        }
+    }
+
     #[test]
     fn test_load_config_not_existing() {
         let path = crate::fs::temp::prepare_temp_folder().unwrap();
@@ -147,4 +171,18 @@ mod tests {
 
         crate::fs::temp::delete_temp_folder(&path).unwrap();
     }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let loader = ConfigurationLoader::new(&path, CONFIG_FILE_NAME);
+        let config = TestConfig {
+            string_value: String::new(),
+        };
+        loader.store_config(&config).unwrap();
+
+        assert!(ConfigurationManager::<TestConfig>::load(&path, CONFIG_FILE_NAME).is_err());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
 }
@@ -1,9 +1,44 @@
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::PathBuf;
 
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+use crate::config::loader::Validate;
+use crate::data::operation::OperationKind;
+
 pub mod loader;
+pub mod reload;
 
 const DEFAULT_DATA_FOLDER: &str = "data";
+const ANY_ORIGIN: &str = "*";
+const DEFAULT_SECRET_KEY_FILE_NAME: &str = "secret.key";
 const DEFAULT_SERVER_PORT: u16 = 58180;
+const DEFAULT_MAX_AUDIT_LOG_ENTRIES: usize = 10_000;
+const DEFAULT_FILE_READ_BUFFER_SIZE: usize = 64 * 1024;
+const DEFAULT_MAX_ASSET_UPLOAD_SIZE: usize = 1024 * 1024 * 1024;
+const DEFAULT_AUTO_SNAPSHOT_RETENTION: usize = 12;
+const DEFAULT_STALE_DATA_CLASS: &str = "data-stale";
+const DEFAULT_WATCHDOG_CHANNEL: &str = "program";
+const DEFAULT_WATCHDOG_BACKUP_CHANNEL: &str = "backup";
+const DEFAULT_WATCHDOG_MAX_SILENCE_SECONDS: u64 = 15;
+const DEFAULT_SYNTHETIC_MONITOR_CHECK_INTERVAL_SECONDS: u64 = 30;
+const DEFAULT_SYNTHETIC_MONITOR_MAX_ROUND_TRIP_MS: u64 = 500;
+const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_RETRY_INITIAL_BACKOFF_MS: u64 = 500;
+const DEFAULT_RETRY_MAX_BACKOFF_MS: u64 = 30_000;
+const DEFAULT_CIRCUIT_BREAKER_FAILURE_THRESHOLD: u32 = 5;
+const DEFAULT_CIRCUIT_BREAKER_OPEN_SECONDS: u64 = 60;
+const DEFAULT_PUBLIC_STATE_MAX_AGE_SECONDS: u64 = 2;
+const DEFAULT_ASSET_SIGNING_WINDOW_SECONDS: i64 = 60 * 60;
+const DEFAULT_ASSET_SIGNING_MAX_VALID_SECONDS: i64 = 30 * 24 * 60 * 60;
+const DEFAULT_TLS_RELOAD_INTERVAL_SECONDS: u64 = 60;
+const DEFAULT_PERSIST_STATE: bool = true;
+const DEFAULT_COMMAND_LATENCY_BUDGET_MS: u64 = 20;
+const DEFAULT_SHUTDOWN_TIMEOUT_SECONDS: u64 = 10;
+const DEFAULT_HEARTBEAT_INTERVAL_SECONDS: u64 = 5;
+const DEFAULT_HEARTBEAT_TIMEOUT_SECONDS: u64 = 30;
 
 fn get_default_data_folder() -> PathBuf {
     match crate::fs::get_application_folder(crate::APPLICATION_NAME) {
@@ -32,24 +67,951 @@ fn get_default_data_folder() -> PathBuf {
     }
 }
 
+fn get_default_bind_address() -> IpAddr {
+    IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+}
+
 fn get_default_server_port() -> u16 {
     DEFAULT_SERVER_PORT
 }
 
+fn get_default_max_audit_log_entries() -> usize {
+    DEFAULT_MAX_AUDIT_LOG_ENTRIES
+}
+
+fn get_default_file_read_buffer_size() -> usize {
+    DEFAULT_FILE_READ_BUFFER_SIZE
+}
+
+fn get_default_max_asset_upload_size() -> usize {
+    DEFAULT_MAX_ASSET_UPLOAD_SIZE
+}
+
+fn get_default_auto_snapshot_retention() -> usize {
+    DEFAULT_AUTO_SNAPSHOT_RETENTION
+}
+
+fn get_default_stale_data_class() -> String {
+    DEFAULT_STALE_DATA_CLASS.to_owned()
+}
+
+fn get_default_watchdog_channel() -> String {
+    DEFAULT_WATCHDOG_CHANNEL.to_owned()
+}
+
+fn get_default_watchdog_backup_channel() -> String {
+    DEFAULT_WATCHDOG_BACKUP_CHANNEL.to_owned()
+}
+
+fn get_default_watchdog_max_silence_seconds() -> u64 {
+    DEFAULT_WATCHDOG_MAX_SILENCE_SECONDS
+}
+
+fn get_default_synthetic_monitor_check_interval_seconds() -> u64 {
+    DEFAULT_SYNTHETIC_MONITOR_CHECK_INTERVAL_SECONDS
+}
+
+fn get_default_synthetic_monitor_max_round_trip_ms() -> u64 {
+    DEFAULT_SYNTHETIC_MONITOR_MAX_ROUND_TRIP_MS
+}
+
+fn get_default_retry_max_attempts() -> u32 {
+    DEFAULT_RETRY_MAX_ATTEMPTS
+}
+
+fn get_default_retry_initial_backoff_ms() -> u64 {
+    DEFAULT_RETRY_INITIAL_BACKOFF_MS
+}
+
+fn get_default_retry_max_backoff_ms() -> u64 {
+    DEFAULT_RETRY_MAX_BACKOFF_MS
+}
+
+fn get_default_circuit_breaker_failure_threshold() -> u32 {
+    DEFAULT_CIRCUIT_BREAKER_FAILURE_THRESHOLD
+}
+
+fn get_default_circuit_breaker_open_seconds() -> u64 {
+    DEFAULT_CIRCUIT_BREAKER_OPEN_SECONDS
+}
+
+/// Half of the cores, so the async runtime keeps enough cores for websocket broadcasts.
+fn get_default_max_blocking_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|cores| (cores.get() / 2).max(1))
+        .unwrap_or(1)
+}
+
+fn get_default_public_state_max_age_seconds() -> u64 {
+    DEFAULT_PUBLIC_STATE_MAX_AGE_SECONDS
+}
+
+fn get_default_asset_signing_window_seconds() -> i64 {
+    DEFAULT_ASSET_SIGNING_WINDOW_SECONDS
+}
+
+fn get_default_asset_signing_max_valid_seconds() -> i64 {
+    DEFAULT_ASSET_SIGNING_MAX_VALID_SECONDS
+}
+
+fn get_default_persist_state() -> bool {
+    DEFAULT_PERSIST_STATE
+}
+
+fn get_default_command_latency_budget_ms() -> u64 {
+    DEFAULT_COMMAND_LATENCY_BUDGET_MS
+}
+
+fn get_default_shutdown_timeout_seconds() -> u64 {
+    DEFAULT_SHUTDOWN_TIMEOUT_SECONDS
+}
+
+fn get_default_heartbeat_interval_seconds() -> u64 {
+    DEFAULT_HEARTBEAT_INTERVAL_SECONDS
+}
+
+fn get_default_heartbeat_timeout_seconds() -> u64 {
+    DEFAULT_HEARTBEAT_TIMEOUT_SECONDS
+}
+
+fn get_default_tls_reload_interval_seconds() -> u64 {
+    DEFAULT_TLS_RELOAD_INTERVAL_SECONDS
+}
+
+fn get_default_in_animation_suffixes() -> Vec<String> {
+    vec![String::from("Show"), String::from("In")]
+}
+
+fn get_default_out_animation_suffixes() -> Vec<String> {
+    vec![String::from("Hide"), String::from("Out")]
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ZagreusServerConfig {
+    /// Address the HTTP server binds to, e.g. `127.0.0.1` to only accept local connections.
+    #[serde(default = "get_default_bind_address")]
+    pub bind_address: IpAddr,
     #[serde(default = "get_default_server_port")]
     pub server_port: u16,
+    /// Allows a new server process to listen on the port while the old one still runs, so the
+    /// server can be upgraded without downtime (unix only).
+    #[serde(default)]
+    pub reuse_port: bool,
+    /// Serves the API and the websockets over HTTPS and WSS.
+    #[serde(default)]
+    pub tls: TlsConfig,
     #[serde(default = "get_default_data_folder")]
     pub data_folder: PathBuf,
+    /// API keys protecting the REST API. The API is open if no keys are configured.
+    #[serde(default)]
+    pub auth: AuthConfig,
+    /// Overrides the log level, which is `info` (or `trace` with `--verbose`) otherwise.
+    #[serde(default)]
+    pub log_level: Option<LogLevel>,
+    /// Origins of web pages which may call the API from a browser, e.g.
+    /// `https://panel.example.com`, or `*` for any origin.
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    #[serde(default = "get_default_max_audit_log_entries")]
+    pub max_audit_log_entries: usize,
+    #[serde(default)]
+    pub self_test: SelfTestConfig,
+    #[serde(default)]
+    pub instances: HashMap<String, InstanceConfig>,
+    /// Key file used to encrypt instance secrets. Defaults to a file in the application folder.
+    #[serde(default)]
+    pub secret_key_file: Option<PathBuf>,
+    /// Proxy for all outbound requests (e.g. fetching images, data sources and webhooks).
+    #[serde(default)]
+    pub proxy: Option<ProxyConfig>,
+    /// Allows fetching assets and resources from hosts in private networks (e.g. a graphics
+    /// server in the local network). Such hosts are rejected by default, so users of the API
+    /// cannot reach internal services through the server.
+    #[serde(default)]
+    pub allow_private_network_fetch: bool,
+    /// Folder from which data sources may read local files with `file://` URLs. Data sources
+    /// cannot read local files if not set.
+    #[serde(default)]
+    pub data_source_folder: Option<PathBuf>,
+    /// Retry and circuit breaker policy for external integrations.
+    #[serde(default)]
+    pub resilience: ResilienceConfig,
+    /// Limits protecting the renderers from resource exhaustion, e.g. when an overlay URL leaks.
+    #[serde(default)]
+    pub connection_limits: ConnectionLimitsConfig,
+    #[serde(default)]
+    pub heartbeat: HeartbeatConfig,
+    /// Webhook which receives the crash report (as JSON) if the server panics.
+    #[serde(default)]
+    pub crash_webhook_url: Option<String>,
+    /// Size in bytes of the chunks in which assets and static files are read from disk and
+    /// streamed. Smaller values reduce memory usage, larger values reduce disk reads.
+    #[serde(default = "get_default_file_read_buffer_size")]
+    pub file_read_buffer_size: usize,
+    /// Maximum size in bytes of an uploaded asset (e.g. videos).
+    #[serde(default = "get_default_max_asset_upload_size")]
+    pub max_asset_upload_size: usize,
+    #[serde(default)]
+    pub asset_signing: AssetSigningConfig,
+    /// Maximum number of CPU-heavy tasks (e.g. hashing uploaded assets) running at the same time.
+    /// Defaults to half of the available cores.
+    #[serde(default = "get_default_max_blocking_workers")]
+    pub max_blocking_workers: usize,
+    #[serde(default)]
+    pub auto_snapshot: AutoSnapshotConfig,
+    /// Stores the state of every instance in its folder, so renderers get the graphics that were
+    /// on air back after the server restarted.
+    #[serde(default = "get_default_persist_state")]
+    pub persist_state: bool,
+    /// Time from receiving a request until its first command is written to the websockets above
+    /// which a warning with the timings of the command is logged.
+    #[serde(default = "get_default_command_latency_budget_ms")]
+    pub command_latency_budget_ms: u64,
+    /// Maximum time in which in-flight requests are finished and renderers disconnect when the
+    /// server shuts down.
+    #[serde(default = "get_default_shutdown_timeout_seconds")]
+    pub shutdown_timeout_seconds: u64,
+    /// Bridges forwarding the texts of instances to data map receivers (e.g. virtual set systems).
+    #[serde(default)]
+    pub data_maps: Vec<DataMapConfig>,
+    #[serde(default)]
+    pub synthetic_monitor: SyntheticMonitorConfig,
+    #[serde(default)]
+    pub stale_data: Vec<StaleDataConfig>,
+    /// Routines which run every day at a fixed time, so unattended installations maintain
+    /// themselves.
+    #[serde(default)]
+    pub daily_routines: Vec<DailyRoutineConfig>,
+    /// Watchdogs executing recovery actions when the renderers of a channel stop responding.
+    #[serde(default)]
+    pub watchdogs: Vec<WatchdogConfig>,
+    /// Listener for CasparCG AMCP commands, so playout automation can drive zagreus templates.
+    #[serde(default)]
+    pub amcp: AmcpConfig,
+    /// Listener for OSC messages, so lighting desks and show control software can trigger
+    /// animations and set texts.
+    #[serde(default)]
+    pub osc: OscConfig,
+    /// Bridge to an MQTT broker, so sensors and other IoT devices can drive graphics.
+    #[serde(default)]
+    pub mqtt: MqttConfig,
+    /// Routes of the events emitted by templates to the renderers of other instances, e.g. a
+    /// scorebug announcing a goal to a statistics template.
+    #[serde(default)]
+    pub template_events: Vec<TemplateEventRouteConfig>,
+}
+
+impl ZagreusServerConfig {
+    pub fn is_allowed_origin(&self, origin: &str) -> bool {
+        self.allowed_origins
+            .iter()
+            .any(|allowed| allowed == ANY_ORIGIN || allowed == origin)
+    }
+
+    pub fn get_instance_config(&self, instance: &str) -> Option<&InstanceConfig> {
+        self.instances.get(instance)
+    }
+
+    pub fn get_secret_key_file(&self) -> PathBuf {
+        match &self.secret_key_file {
+            Some(secret_key_file) => secret_key_file.clone(),
+            None => crate::fs::get_application_folder(crate::APPLICATION_NAME)
+                .unwrap_or_default()
+                .join(DEFAULT_SECRET_KEY_FILE_NAME),
+        }
+    }
 }
 
 impl Default for ZagreusServerConfig {
     fn default() -> Self {
         ZagreusServerConfig {
+            bind_address: get_default_bind_address(),
             server_port: get_default_server_port(),
+            reuse_port: false,
+            tls: TlsConfig::default(),
             data_folder: get_default_data_folder(),
+            auth: AuthConfig::default(),
+            log_level: None,
+            allowed_origins: Vec::new(),
+            analytics: AnalyticsConfig::default(),
+            max_audit_log_entries: get_default_max_audit_log_entries(),
+            self_test: SelfTestConfig::default(),
+            instances: HashMap::new(),
+            secret_key_file: None,
+            proxy: None,
+            allow_private_network_fetch: false,
+            data_source_folder: None,
+            resilience: ResilienceConfig::default(),
+            connection_limits: ConnectionLimitsConfig::default(),
+            heartbeat: HeartbeatConfig::default(),
+            crash_webhook_url: None,
+            file_read_buffer_size: get_default_file_read_buffer_size(),
+            max_asset_upload_size: get_default_max_asset_upload_size(),
+            asset_signing: AssetSigningConfig::default(),
+            max_blocking_workers: get_default_max_blocking_workers(),
+            auto_snapshot: AutoSnapshotConfig::default(),
+            persist_state: get_default_persist_state(),
+            command_latency_budget_ms: get_default_command_latency_budget_ms(),
+            shutdown_timeout_seconds: get_default_shutdown_timeout_seconds(),
+            data_maps: Vec::new(),
+            synthetic_monitor: SyntheticMonitorConfig::default(),
+            stale_data: Vec::new(),
+            daily_routines: Vec::new(),
+            watchdogs: Vec::new(),
+            amcp: AmcpConfig::default(),
+            osc: OscConfig::default(),
+            mqtt: MqttConfig::default(),
+            template_events: Vec::new(),
         }
     }
 }
+
+impl ZagreusServerConfig {
+    /// Returns the address under which the server reaches itself, which is the loopback address
+    /// if the server binds to all interfaces.
+    pub fn get_local_address(&self) -> SocketAddr {
+        let ip = match self.bind_address {
+            IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip => ip,
+        };
+        SocketAddr::new(ip, self.server_port)
+    }
+}
+
+impl Validate for ZagreusServerConfig {
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.server_port == 0 {
+            anyhow::bail!("serverPort must be a port between 1 and 65535");
+        }
+        if self.bind_address.is_multicast() {
+            anyhow::bail!(
+                "bindAddress {} is a multicast address and can not be bound to",
+                self.bind_address
+            );
+        }
+        if self.tls.enabled
+            && !self.tls.self_signed
+            && (self.tls.cert_file.is_none() || self.tls.key_file.is_none())
+        {
+            anyhow::bail!("tls requires a certFile and a keyFile unless selfSigned is set");
+        }
+        for origin in &self.allowed_origins {
+            // an origin is a scheme, host and port, so e.g. a trailing slash would never match
+            let is_origin = reqwest::Url::parse(origin)
+                .is_ok_and(|url| url.origin().ascii_serialization() == *origin);
+            if origin != ANY_ORIGIN && !is_origin {
+                anyhow::bail!(
+                    "allowedOrigins contains {origin}, which is not an origin like https://panel.example.com"
+                );
+            }
+        }
+        if self.shutdown_timeout_seconds == 0 {
+            anyhow::bail!("shutdownTimeoutSeconds must be at least 1");
+        }
+        if self.heartbeat.interval_seconds == 0 {
+            anyhow::bail!("heartbeat.intervalSeconds must be at least 1");
+        }
+        if self.heartbeat.timeout_seconds <= self.heartbeat.interval_seconds {
+            anyhow::bail!("heartbeat.timeoutSeconds must be longer than heartbeat.intervalSeconds");
+        }
+        if self.tls.reload_interval_seconds == 0 {
+            anyhow::bail!("tls.reloadIntervalSeconds must be at least 1");
+        }
+        if self.synthetic_monitor.check_interval_seconds == 0 {
+            anyhow::bail!("syntheticMonitor.checkIntervalSeconds must be at least 1");
+        }
+        if self
+            .daily_routines
+            .iter()
+            .any(|routine| routine.actions.is_empty())
+        {
+            anyhow::bail!("dailyRoutines require at least one action");
+        }
+        for watchdog in &self.watchdogs {
+            if watchdog.actions.is_empty() {
+                anyhow::bail!(
+                    "watchdog of instance {} requires at least one action",
+                    watchdog.instance
+                );
+            }
+            if watchdog.max_silence_seconds == 0 {
+                anyhow::bail!(
+                    "watchdog of instance {} requires a maxSilenceSeconds of at least 1",
+                    watchdog.instance
+                );
+            }
+        }
+        for subscription in &self.mqtt.subscriptions {
+            if !rumqttc::valid_filter(&subscription.topic) {
+                anyhow::bail!(
+                    "mqtt subscription {} is not a valid topic filter",
+                    subscription.topic
+                );
+            }
+        }
+        if let Some(topic) = &self.mqtt.event_topic {
+            if topic.is_empty() || !rumqttc::valid_topic(topic) {
+                anyhow::bail!("mqtt eventTopic {topic} is not a valid topic");
+            }
+        }
+        for route in &self.template_events {
+            if route.targets.is_empty() {
+                anyhow::bail!(
+                    "templateEvents route of instance {} requires at least one target",
+                    route.source
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyticsConfig {
+    /// Animation sequence suffixes that bring a graphic on air (e.g. `ScoreboardShow`).
+    #[serde(default = "get_default_in_animation_suffixes")]
+    pub in_animation_suffixes: Vec<String>,
+    /// Animation sequence suffixes that take a graphic off air (e.g. `ScoreboardHide`).
+    #[serde(default = "get_default_out_animation_suffixes")]
+    pub out_animation_suffixes: Vec<String>,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        AnalyticsConfig {
+            in_animation_suffixes: get_default_in_animation_suffixes(),
+            out_animation_suffixes: get_default_out_animation_suffixes(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestConfig {
+    /// Instances that are expected to have at least one renderer connected when the self-test runs.
+    #[serde(default)]
+    pub expected_instances: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProxyConfig {
+    /// Proxy URL, e.g. `http://proxy.local:3128`.
+    pub url: String,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Hosts, domains and IP ranges that are accessed directly (e.g. `localhost` or `10.0.0.0/8`).
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AutoSnapshotConfig {
+    /// Interval in minutes in which the state of every instance is saved as snapshot. Automatic
+    /// snapshots are disabled if not set.
+    #[serde(default)]
+    pub interval_minutes: Option<u64>,
+    /// Number of automatic snapshots kept per instance.
+    #[serde(default = "get_default_auto_snapshot_retention")]
+    pub retention: usize,
+}
+
+impl Default for AutoSnapshotConfig {
+    fn default() -> Self {
+        AutoSnapshotConfig {
+            interval_minutes: None,
+            retention: get_default_auto_snapshot_retention(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DataMapProtocol {
+    Udp,
+    Tcp,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DataMapConfig {
+    /// Instance whose texts are forwarded.
+    pub instance: String,
+    pub protocol: DataMapProtocol,
+    /// Address of the receiver, e.g. `10.0.0.5:6100`.
+    pub address: String,
+    /// Data map key per text element id. If empty, all texts are forwarded with their element id
+    /// as key.
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AmcpConfig {
+    /// TCP port of the AMCP listener, e.g. `5250` like CasparCG. AMCP has no authentication, so
+    /// the listener is disabled unless a port is set.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Template per name as sent with `CG ADD`, e.g. `LOWER_THIRD/MAIN`.
+    #[serde(default)]
+    pub templates: BTreeMap<String, AmcpTemplateConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AmcpTemplateConfig {
+    pub instance: String,
+    /// Animation sequence executed by `CG PLAY` (and `CG ADD` with play on load).
+    #[serde(default)]
+    pub play_animation: Option<String>,
+    /// Animation sequence executed by `CG STOP`.
+    #[serde(default)]
+    pub stop_animation: Option<String>,
+    /// Element id per field of the template data, e.g. `f0`. Other fields are used as element ids.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OscConfig {
+    /// UDP port of the OSC listener, e.g. `8000`. OSC has no authentication, so the listener is
+    /// disabled unless a port is set.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Action per OSC address, e.g. `/lx/cue/5`, in addition to the built-in addresses.
+    #[serde(default)]
+    pub addresses: BTreeMap<String, OscActionConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OscActionConfig {
+    pub instance: String,
+    /// Animation sequence executed by messages on the address.
+    #[serde(default)]
+    pub animation: Option<String>,
+    /// Element whose text is set to the first argument of messages on the address.
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MqttConfig {
+    /// Host name of the broker, e.g. `mqtt.local`. The bridge is disabled unless a host is set.
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Port of the broker, 1883 if not set.
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub subscriptions: Vec<MqttSubscriptionConfig>,
+    /// Topic under which the events of all instances are published, e.g. `zagreus/events`.
+    /// Events are not published unless a topic is set.
+    #[serde(default)]
+    pub event_topic: Option<String>,
+}
+
+/// Maps the messages of a topic to data of an instance.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MqttSubscriptionConfig {
+    /// Topic filter, which may contain the wildcards `+` and `#`.
+    pub topic: String,
+    pub instance: String,
+    /// Element whose text is set to the whole payload.
+    #[serde(default)]
+    pub element: Option<String>,
+    /// JSON pointer of the value per element id for JSON payloads, e.g. `/temperature`.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Animation sequence executed after the data of every message was applied.
+    #[serde(default)]
+    pub animation: Option<String>,
+}
+
+/// Forwards events emitted by the renderers of an instance to the renderers of other instances.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateEventRouteConfig {
+    /// Instance whose renderers emit the events.
+    pub source: String,
+    /// Name of the forwarded event, e.g. `goal`. All events of the source are forwarded if not set.
+    #[serde(default)]
+    pub event: Option<String>,
+    /// Instances whose renderers receive the events.
+    pub targets: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StaleDataConfig {
+    pub instance: String,
+    /// Elements which are bound to the data source, i.e. whose texts or images it refreshes.
+    pub elements: Vec<String>,
+    /// Time after the last refresh of any of the elements after which their data is stale.
+    pub max_age_seconds: u64,
+    /// Class added to the elements while their data is stale.
+    #[serde(default = "get_default_stale_data_class")]
+    pub class: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyRoutineConfig {
+    /// Time of day at which the routine runs, e.g. `05:00`.
+    pub at: NaiveTime,
+    /// IANA time zone of the time, e.g. `Europe/Zurich`. Uses the time zone of the machine if not
+    /// set.
+    #[serde(default)]
+    pub timezone: Option<Tz>,
+    pub actions: Vec<RoutineAction>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum RoutineAction {
+    /// Clears all instances with a state to their defaults.
+    ClearInstances,
+    RotateLogs,
+    /// Polls all data sources, e.g. to load the line-up of the day.
+    RefreshDataSources,
+    /// Runs the self-test and logs failed checks.
+    SelfTest,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchdogConfig {
+    pub instance: String,
+    /// Channel of the watched renderers, as passed with `?channel=` on the template page.
+    #[serde(default = "get_default_watchdog_channel")]
+    pub channel: String,
+    /// Time without an answer to a ping after which a renderer is unresponsive. Renderers are
+    /// pinged every 5 seconds.
+    #[serde(default = "get_default_watchdog_max_silence_seconds")]
+    pub max_silence_seconds: u64,
+    /// Actions executed in order once no renderer of the channel is responsive anymore.
+    pub actions: Vec<WatchdogAction>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum WatchdogAction {
+    /// Posts the instance, channel and silence as JSON to the URL, e.g. to restart the browser
+    /// source through an automation tool.
+    Webhook { url: String },
+    /// Switches a responsive renderer of the backup channel to the watched channel.
+    PromoteBackup {
+        #[serde(default = "get_default_watchdog_backup_channel")]
+        channel: String,
+    },
+    /// Logs an error, in addition to the `rendererUnresponsive` event.
+    Alert,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SyntheticMonitorConfig {
+    /// Instances to which a built-in synthetic client connects like a renderer.
+    #[serde(default)]
+    pub instances: Vec<String>,
+    /// Interval in which the state of the synthetic client is compared to the state of the
+    /// server and the round trip time is measured.
+    #[serde(default = "get_default_synthetic_monitor_check_interval_seconds")]
+    pub check_interval_seconds: u64,
+    /// Round trip time above which the synthetic monitor reports the instance as unhealthy.
+    #[serde(default = "get_default_synthetic_monitor_max_round_trip_ms")]
+    pub max_round_trip_ms: u64,
+}
+
+impl Default for SyntheticMonitorConfig {
+    fn default() -> Self {
+        SyntheticMonitorConfig {
+            instances: Vec::new(),
+            check_interval_seconds: get_default_synthetic_monitor_check_interval_seconds(),
+            max_round_trip_ms: get_default_synthetic_monitor_max_round_trip_ms(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// PEM file with the certificate chain.
+    #[serde(default)]
+    pub cert_file: Option<PathBuf>,
+    /// PEM file with the private key.
+    #[serde(default)]
+    pub key_file: Option<PathBuf>,
+    /// Generates a self-signed certificate for development if no certificate is configured.
+    #[serde(default)]
+    pub self_signed: bool,
+    /// Interval in which the certificate files are checked for changes, so a renewed
+    /// certificate is used without restarting the server.
+    #[serde(default = "get_default_tls_reload_interval_seconds")]
+    pub reload_interval_seconds: u64,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        TlsConfig {
+            enabled: false,
+            cert_file: None,
+            key_file: None,
+            self_signed: false,
+            reload_interval_seconds: get_default_tls_reload_interval_seconds(),
+        }
+    }
+}
+
+/// Signed, expiring asset URLs, e.g. for viewer overlays served through an edge CDN.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetSigningConfig {
+    /// Base URL of the signed URLs, e.g. `https://cdn.example.com`. Relative URLs are created if
+    /// not set.
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Expiries are rounded up to this window, so the URLs of an asset stay equal within the
+    /// window and can be cached.
+    #[serde(default = "get_default_asset_signing_window_seconds")]
+    pub window_seconds: i64,
+    #[serde(default = "get_default_asset_signing_max_valid_seconds")]
+    pub max_valid_seconds: i64,
+}
+
+impl Default for AssetSigningConfig {
+    fn default() -> Self {
+        AssetSigningConfig {
+            base_url: None,
+            window_seconds: get_default_asset_signing_window_seconds(),
+            max_valid_seconds: get_default_asset_signing_max_valid_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// Allows reading (GET requests) without API key.
+    #[serde(default)]
+    pub anonymous_read: bool,
+}
+
+impl AuthConfig {
+    /// Returns a key which may write, used by the server to call its own API (e.g. the handover
+    /// to a new server process).
+    pub fn get_write_key(&self) -> Option<&str> {
+        self.api_keys
+            .iter()
+            .find(|api_key| !api_key.read_only)
+            .map(|api_key| api_key.key.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiKeyConfig {
+    /// Name of the application using the key, e.g. `companion`.
+    pub name: String,
+    pub key: String,
+    /// Restricts the key to reading (GET requests).
+    #[serde(default)]
+    pub read_only: bool,
+    /// Allows approving and rejecting pending changes.
+    #[serde(default)]
+    pub approve: bool,
+}
+
+/// Maximums above which new websocket clients are rejected. Synthetic monitors are not limited.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionLimitsConfig {
+    #[serde(default)]
+    pub max_connections: Option<usize>,
+    #[serde(default)]
+    pub max_connections_per_instance: Option<usize>,
+    /// Maximum number of bytes queued for sending to all clients, e.g. because clients are slow.
+    #[serde(default)]
+    pub max_buffered_bytes: Option<usize>,
+}
+
+/// Pings of the websocket clients, which detect renderers that vanished without closing their
+/// connection, e.g. after a network outage.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HeartbeatConfig {
+    #[serde(default = "get_default_heartbeat_interval_seconds")]
+    pub interval_seconds: u64,
+    /// Time without any message of a client (including answers to pings) after which its
+    /// connection is closed and removed.
+    #[serde(default = "get_default_heartbeat_timeout_seconds")]
+    pub timeout_seconds: u64,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        HeartbeatConfig {
+            interval_seconds: get_default_heartbeat_interval_seconds(),
+            timeout_seconds: get_default_heartbeat_timeout_seconds(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ResilienceConfig {
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryConfig {
+    /// Maximum number of attempts per call, including the first one.
+    #[serde(default = "get_default_retry_max_attempts")]
+    pub max_attempts: u32,
+    /// Backoff before the first retry, doubled for every further retry.
+    #[serde(default = "get_default_retry_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "get_default_retry_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        RetryConfig {
+            max_attempts: get_default_retry_max_attempts(),
+            initial_backoff_ms: get_default_retry_initial_backoff_ms(),
+            max_backoff_ms: get_default_retry_max_backoff_ms(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerConfig {
+    /// Number of consecutive failed calls after which the circuit opens.
+    #[serde(default = "get_default_circuit_breaker_failure_threshold")]
+    pub failure_threshold: u32,
+    /// Time after which an open circuit lets a single call through to probe the integration.
+    #[serde(default = "get_default_circuit_breaker_open_seconds")]
+    pub open_seconds: u64,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            failure_threshold: get_default_circuit_breaker_failure_threshold(),
+            open_seconds: get_default_circuit_breaker_open_seconds(),
+        }
+    }
+}
+
+/// Server side configuration for a single template instance.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceConfig {
+    /// Registered runtime version the instance should load. Uses the bundled runtime if not set.
+    #[serde(default)]
+    pub runtime_version: Option<String>,
+    /// Data of the instance which is published read-only (e.g. for companion apps and websites).
+    /// Nothing is published if not set.
+    #[serde(default)]
+    pub public_state: Option<PublicStateConfig>,
+    /// Operations which are held back as pending changes until they are approved.
+    #[serde(default)]
+    pub approval: Option<ApprovalConfig>,
+    /// Time after which the instance is no longer served to new clients, e.g. for the package of
+    /// a one-off event.
+    #[serde(default)]
+    pub expiry: Option<ExpiryConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalConfig {
+    pub operations: Vec<OperationKind>,
+    /// Elements whose operations require approval. All elements require approval if empty.
+    #[serde(default)]
+    pub elements: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiryConfig {
+    pub expires: DateTime<Utc>,
+    /// Moves the data of the instance to the trash folder once it expired.
+    #[serde(default)]
+    pub move_to_trash: bool,
+}
+
+/// Allowlist of the element ids whose data is published.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicStateConfig {
+    #[serde(default)]
+    pub texts: Vec<String>,
+    #[serde(default)]
+    pub classes: Vec<String>,
+    #[serde(default)]
+    pub images: Vec<String>,
+    /// Time in which clients and proxies may serve the published data from their cache.
+    #[serde(default = "get_default_public_state_max_age_seconds")]
+    pub max_age_seconds: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_intervals() {
+        assert!(ZagreusServerConfig::default().validate().is_ok());
+
+        let mut configuration = ZagreusServerConfig::default();
+        configuration.heartbeat.interval_seconds = 0;
+        assert!(configuration.validate().is_err());
+
+        let mut configuration = ZagreusServerConfig::default();
+        configuration.tls.reload_interval_seconds = 0;
+        assert!(configuration.validate().is_err());
+
+        let mut configuration = ZagreusServerConfig::default();
+        configuration.synthetic_monitor.check_interval_seconds = 0;
+        assert!(configuration.validate().is_err());
+    }
+}
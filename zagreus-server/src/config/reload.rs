@@ -0,0 +1,129 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use serde_json::Value;
+
+use crate::config::loader::ConfigurationManager;
+use crate::config::ZagreusServerConfig;
+use crate::controller::ServerController;
+
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+/// Settings which are applied while the server runs. All other settings only change after a
+/// restart.
+const RELOADABLE_SETTINGS: [&str; 3] = ["logLevel", "auth", "allowedOrigins"];
+
+/// Applies the reloadable settings when the configuration file changed. An invalid file is
+/// reported and the previous configuration stays in use.
+pub async fn run_config_reload(
+    controller: Arc<ServerController>,
+    application_folder: PathBuf,
+    config_file_name: &'static str,
+) {
+    let config_file_path = application_folder.join(config_file_name);
+    let mut last_modified = get_last_modified(&config_file_path);
+    // compared with the file rather than the running configuration, which has the CLI flags
+    let mut loaded =
+        ConfigurationManager::<ZagreusServerConfig>::reload(&application_folder, config_file_name)
+            .map_or_else(
+                |_| serde_json::to_value(&*controller.get_configuration()),
+                serde_json::to_value,
+            )
+            .unwrap_or_default();
+    let mut interval = tokio::time::interval(RELOAD_INTERVAL);
+    loop {
+        interval.tick().await;
+        let modified = get_last_modified(&config_file_path);
+        if modified == last_modified || modified.is_none() {
+            continue;
+        }
+        last_modified = modified;
+        let configuration = match ConfigurationManager::<ZagreusServerConfig>::reload(
+            &application_folder,
+            config_file_name,
+        ) {
+            Ok(configuration) => configuration,
+            Err(err) => {
+                error!(
+                    "Could not reload configuration, keeping the previous one: {:#}.",
+                    err
+                );
+                continue;
+            }
+        };
+        let reloaded = serde_json::to_value(&configuration).unwrap_or_default();
+        let (changed, restart_required) = get_changed_settings(&loaded, &reloaded);
+        loaded = reloaded;
+        if !restart_required.is_empty() {
+            warn!(
+                "Changed settings {} only apply after a restart.",
+                restart_required.join(", ")
+            );
+        }
+        if changed.is_empty() {
+            continue;
+        }
+        info!("Reloading settings {}.", changed.join(", "));
+        if changed.iter().any(|setting| setting == "auth") && configuration.auth.api_keys.is_empty()
+        {
+            warn!("No API keys are configured, the API can be used without authentication.");
+        }
+        apply_reloadable_settings(&controller, configuration);
+    }
+}
+
+fn apply_reloadable_settings(controller: &ServerController, reloaded: ZagreusServerConfig) {
+    let mut configuration = ZagreusServerConfig::clone(&controller.get_configuration());
+    configuration.log_level = reloaded.log_level;
+    configuration.auth = reloaded.auth;
+    configuration.allowed_origins = reloaded.allowed_origins;
+    crate::logger::set_log_level(configuration.log_level);
+    controller.set_configuration(configuration);
+}
+
+/// Returns the names of the changed settings which can be reloaded and of those which require a
+/// restart.
+fn get_changed_settings(previous: &Value, current: &Value) -> (Vec<String>, Vec<String>) {
+    let (Value::Object(previous), Value::Object(current)) = (previous, current) else {
+        return (Vec::new(), Vec::new());
+    };
+    previous
+        .keys()
+        .chain(current.keys().filter(|key| !previous.contains_key(*key)))
+        .filter(|key| previous.get(*key) != current.get(*key))
+        .cloned()
+        .partition(|key| RELOADABLE_SETTINGS.contains(&key.as_str()))
+}
+
+fn get_last_modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_get_changed_settings() {
+        let previous = json!({"serverPort": 58180, "auth": {"apiKeys": []}});
+        let current = json!({
+            "serverPort": 58181,
+            "auth": {"apiKeys": [{"name": "companion", "key": "secret"}]},
+            "logLevel": "debug"
+        });
+        assert_eq!(
+            (
+                vec![String::from("auth"), String::from("logLevel")],
+                vec![String::from("serverPort")]
+            ),
+            get_changed_settings(&previous, &current)
+        );
+        assert_eq!(
+            (Vec::new(), Vec::new()),
+            get_changed_settings(&current, &current)
+        );
+    }
+}
@@ -1,19 +1,2747 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::websocket::server::WebsocketServer;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use tokio::sync::{watch, Mutex, RwLock};
+
+use crate::actions::ActionCatalogue;
+use crate::analytics::{AnalyticsReport, UsageAnalytics};
+use crate::approval::{ApprovalQueue, PendingChange};
+use crate::archive::{
+    CloneResult, ImportReport, ImportResult, ImportStatus, InstanceArchive, InstancePackage,
+    InstanceSummary,
+};
+use crate::asrun::AsRunRecord;
+use crate::assets::signing::{AssetSignature, SignedAssetUrl};
+use crate::assets::upload::{
+    AppendResult, CreateUploadDto, UploadCompletion, UploadStatusDto, UploadStore,
+};
+use crate::assets::{AssetInfo, AssetVersionStore, AssetVersions};
+use crate::audit::{AuditEntry, AuditLog};
+use crate::config::{AssetSigningConfig, ZagreusServerConfig};
+use crate::crash::CrashState;
+use crate::data::asset::{AssetReference, AssetSource};
+use crate::data::operation::Operation;
+use crate::datasource::google::GoogleCredentials;
+use crate::datasource::{DataFormat, DataSource, DataSourceStatus, DataSourceStore, DataSources};
+use crate::defaults::{DataDefaults, DEFAULTS_FILE_NAME};
+use crate::emergency::{ActivationResult, ActiveOverride, EmergencyOverrides, EMERGENCY_FILE_NAME};
+use crate::errors::ClientErrorRecord;
+use crate::events::{EventBatch, EventKind};
+use crate::expiry::InstanceExpiry;
+use crate::expression::{ComputedElements, COMPUTED_ELEMENTS_FILE_NAME};
+use crate::fakedata::{FakeDataDto, FakeDataGenerator, FakeDataRequestDto};
+use crate::feedback::{
+    ButtonCondition, FeedbackButtons, FeedbackContext, FeedbackTracker, FEEDBACK_FILE_NAME,
+};
+use crate::flags::{FeatureFlagStore, FeatureFlags};
+use crate::gc::GarbageReport;
+use crate::handover::HandoverState;
+use crate::localization::{Localization, TranslationBundle, LOCALIZATION_FILE_NAME};
+use crate::metrics::Metrics;
+use crate::monitor::{MonitorStatus, MonitorStatusStore};
+use crate::notes::NoteStore;
+use crate::outbound::FetchedResource;
+use crate::prefetch::{PrefetchEntry, PrefetchManifest};
+use crate::presence::{InstancePresence, LockResult, PresenceTracker, ReleaseResult};
+use crate::replace::{ReplaceReport, ReplaceRequest};
+use crate::resilience::{IntegrationHealth, IntegrationMonitor};
+use crate::resources::{PinnedResource, PinnedResources, ResourceStore};
+use crate::rotation::{DisplayLog, DisplayRecord, DisplayReport, Rotations, ROTATIONS_FILE_NAME};
+use crate::routing::Routes;
+use crate::rules::{Rule, Rules, RULES_FILE_NAME};
+use crate::rundown::{Rundown, TakeResult, RUNDOWN_FILE_NAME};
+use crate::scheduler::{ScheduleRequest, ScheduledItem, Scheduler};
+use crate::secrets::SecretStore;
+use crate::session::{ActiveSession, SessionEntry, SessionRecorder, SessionTimeline};
+use crate::share::{ShareClaims, ShareLink};
+use crate::snapshot::{ApplyResult, Snapshot, SnapshotInfo, SnapshotStore};
+use crate::state::persistence::STATE_FILE_NAME;
+use crate::state::{InstanceState, StagedTakeResult, StateStore};
+use crate::store::InstanceDocumentStore;
+use crate::timing::{CommandLatencyStats, CommandLatencyTracker};
+use crate::webhooks::{DataChange, Webhook, WebhookQueues, Webhooks, WEBHOOKS_FILE_NAME};
+use crate::websocket::message::{InstanceMessage, PrecacheProgress};
+use crate::websocket::parameters::{ClientOrigin, ClientParameters};
+use crate::websocket::server::{ConnectedClient, WebsocketServer};
+use crate::worker::{WorkerPool, WorkerPoolStats};
+use zagreus_client::dto::{NoteDto, OperatorNote};
 
 pub struct ServerController {
+    data_folder: PathBuf,
+    asset_signing: AssetSigningConfig,
     websocket_server: Arc<WebsocketServer>,
+    analytics: UsageAnalytics,
+    audit_log: AuditLog,
+    config_documents: InstanceDocumentStore<ConfigDocument>,
+    data_defaults: InstanceDocumentStore<DataDefaults>,
+    rundowns: InstanceDocumentStore<Rundown>,
+    rules: InstanceDocumentStore<Rules>,
+    webhooks: InstanceDocumentStore<Webhooks>,
+    computed_elements: InstanceDocumentStore<ComputedElements>,
+    /// Serializes takes of rundown pages and staged data, so transitions do not interleave when
+    /// operators press take repeatedly.
+    take_lock: Mutex<()>,
+    emergency_overrides: InstanceDocumentStore<EmergencyOverrides>,
+    /// Emergency overrides which are on air, per instance.
+    active_overrides: RwLock<HashMap<String, ActiveOverride>>,
+    feedback_buttons: InstanceDocumentStore<FeedbackButtons>,
+    feedback: FeedbackTracker,
+    presence: PresenceTracker,
+    notes: NoteStore,
+    sessions: SessionRecorder,
+    approvals: ApprovalQueue,
+    expiry: InstanceExpiry,
+    localizations: InstanceDocumentStore<Localization>,
+    rotations: InstanceDocumentStore<Rotations>,
+    display_log: DisplayLog,
+    scheduler: Scheduler,
+    feature_flags: FeatureFlagStore,
+    data_sources: DataSourceStore,
+    asset_versions: AssetVersionStore,
+    uploads: UploadStore,
+    resources: ResourceStore,
+    states: StateStore,
+    snapshots: SnapshotStore,
+    secrets: SecretStore,
+    http_client: reqwest::Client,
+    integrations: Arc<IntegrationMonitor>,
+    webhook_queues: WebhookQueues,
+    monitors: MonitorStatusStore,
+    workers: WorkerPool,
+    command_latency: CommandLatencyTracker,
+    started_at: Instant,
+    /// Configuration with the settings reloaded while the server runs.
+    configuration: watch::Sender<Arc<ZagreusServerConfig>>,
+}
+
+pub type ConfigDocument = serde_json::Map<String, serde_json::Value>;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerStats {
+    pub uptime_seconds: u64,
+    pub connected_clients: usize,
+    /// Bytes queued for sending to the websocket clients.
+    pub buffered_bytes: usize,
+    pub integrations: BTreeMap<String, IntegrationHealth>,
+    pub synthetic_monitors: BTreeMap<String, MonitorStatus>,
+    pub workers: WorkerPoolStats,
+    pub command_latency: CommandLatencyStats,
 }
 
+const CONFIG_DOCUMENT_FILE_NAME: &str = "config.json";
+
 impl ServerController {
-    pub fn new(websocket_server: Arc<WebsocketServer>) -> ServerController {
-        ServerController { websocket_server }
+    pub fn new(
+        configuration: &ZagreusServerConfig,
+        websocket_server: Arc<WebsocketServer>,
+    ) -> anyhow::Result<ServerController> {
+        let http_client = crate::outbound::build_client(configuration.proxy.as_ref())?;
+        let integrations = Arc::new(IntegrationMonitor::new(configuration.resilience.clone()));
+        Ok(ServerController {
+            data_folder: configuration.data_folder.clone(),
+            asset_signing: configuration.asset_signing.clone(),
+            websocket_server,
+            analytics: UsageAnalytics::new(configuration.analytics.clone()),
+            audit_log: AuditLog::new(configuration.max_audit_log_entries),
+            config_documents: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                CONFIG_DOCUMENT_FILE_NAME,
+            ),
+            data_defaults: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                DEFAULTS_FILE_NAME,
+            ),
+            rundowns: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                RUNDOWN_FILE_NAME,
+            ),
+            rules: InstanceDocumentStore::new(configuration.data_folder.clone(), RULES_FILE_NAME),
+            webhooks: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                WEBHOOKS_FILE_NAME,
+            ),
+            computed_elements: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                COMPUTED_ELEMENTS_FILE_NAME,
+            ),
+            take_lock: Mutex::new(()),
+            emergency_overrides: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                EMERGENCY_FILE_NAME,
+            ),
+            active_overrides: RwLock::new(HashMap::new()),
+            feedback_buttons: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                FEEDBACK_FILE_NAME,
+            ),
+            feedback: FeedbackTracker::new(),
+            presence: PresenceTracker::new(),
+            notes: NoteStore::new(&configuration.data_folder),
+            sessions: SessionRecorder::new(configuration.data_folder.clone()),
+            approvals: ApprovalQueue::new(
+                configuration
+                    .instances
+                    .iter()
+                    .filter_map(|(instance, instance_config)| {
+                        let approval = instance_config.approval.clone()?;
+                        Some((instance.clone(), approval))
+                    })
+                    .collect(),
+            ),
+            expiry: InstanceExpiry::new(
+                configuration
+                    .instances
+                    .iter()
+                    .filter_map(|(instance, instance_config)| {
+                        let expiry = instance_config.expiry.clone()?;
+                        Some((instance.clone(), expiry))
+                    })
+                    .collect(),
+            ),
+            localizations: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                LOCALIZATION_FILE_NAME,
+            ),
+            rotations: InstanceDocumentStore::new(
+                configuration.data_folder.clone(),
+                ROTATIONS_FILE_NAME,
+            ),
+            display_log: DisplayLog::new(configuration.data_folder.clone()),
+            scheduler: Scheduler::new(configuration.data_folder.clone()),
+            feature_flags: FeatureFlagStore::new(configuration.data_folder.clone()),
+            data_sources: DataSourceStore::new(configuration.data_folder.clone()),
+            asset_versions: AssetVersionStore::new(configuration.data_folder.clone()),
+            uploads: UploadStore::new(configuration.data_folder.clone()),
+            resources: ResourceStore::new(configuration.data_folder.clone()),
+            states: StateStore::new(),
+            snapshots: SnapshotStore::new(configuration.data_folder.clone()),
+            secrets: SecretStore::new(
+                configuration.data_folder.clone(),
+                configuration.get_secret_key_file(),
+            ),
+            webhook_queues: WebhookQueues::new(http_client.clone(), integrations.clone()),
+            http_client,
+            integrations,
+            monitors: MonitorStatusStore::new(),
+            workers: WorkerPool::new(configuration.max_blocking_workers),
+            command_latency: CommandLatencyTracker::new(configuration.command_latency_budget_ms),
+            started_at: Instant::now(),
+            configuration: watch::channel(Arc::new(configuration.clone())).0,
+        })
     }
 
-    pub async fn add_websocket_client(&self, socket: axum::extract::ws::WebSocket, instance: &str) {
+    /// Returns the reason why a new client of the instance is rejected, if admitting it would
+    /// exceed a connection limit.
+    pub async fn check_websocket_admission(
+        &self,
+        instance: &str,
+        parameters: &ClientParameters,
+    ) -> Option<String> {
         self.websocket_server
-            .add_client_socket(socket, instance)
+            .check_admission(instance, parameters)
+            .await
+    }
+
+    pub async fn add_websocket_client(
+        &self,
+        socket: axum::extract::ws::WebSocket,
+        instance: &str,
+        parameters: ClientParameters,
+        origin: ClientOrigin,
+    ) {
+        let channel = parameters.channel.clone();
+        let id = self
+            .websocket_server
+            .add_client_socket(socket, instance, parameters, origin)
+            .await;
+        if let Some(id) = id {
+            self.send_initial_messages(id, instance, channel.as_deref())
+                .await;
+        }
+    }
+
+    async fn send_initial_messages(&self, id: usize, instance: &str, channel: Option<&str>) {
+        match self.config_documents.get(instance).await {
+            Ok(config) => {
+                let message = InstanceMessage::SetConfig {
+                    config: Cow::Owned(config),
+                };
+                self.websocket_server
+                    .send_message_to_client(id, &message)
+                    .await
+            }
+            Err(err) => error!(
+                "Could not load config document of instance {}: {}.",
+                instance, err
+            ),
+        }
+
+        match self.feature_flags.get_effective_flags(instance).await {
+            Ok(flags) => {
+                let message = InstanceMessage::SetFeatureFlags {
+                    flags: Cow::Owned(flags),
+                };
+                self.websocket_server
+                    .send_message_to_client(id, &message)
+                    .await
+            }
+            Err(err) => error!(
+                "Could not load feature flags of instance {}: {}.",
+                instance, err
+            ),
+        }
+
+        match self.asset_versions.get_versions().await {
+            Ok(versions) => {
+                let message = InstanceMessage::SetAssetVersions {
+                    versions: Cow::Owned(versions),
+                };
+                self.websocket_server
+                    .send_message_to_client(id, &message)
+                    .await
+            }
+            Err(err) => error!("Could not load asset versions: {}.", err),
+        }
+
+        if self.active_overrides.read().await.contains_key(instance) {
+            let message = InstanceMessage::SetEmergencyOverride { active: true };
+            self.websocket_server
+                .send_message_to_client(id, &message)
+                .await
+        }
+
+        let route = self.websocket_server.get_route(instance).await;
+        if !crate::routing::is_routed(route.as_ref(), channel) {
+            let message = InstanceMessage::SetRouted { routed: false };
+            self.websocket_server
+                .send_message_to_client(id, &message)
+                .await;
+            return;
+        }
+        self.send_current_state(id, instance, channel).await
+    }
+
+    /// Brings a client that connects late (e.g. after a reload) to the current state.
+    async fn send_current_state(&self, id: usize, instance: &str, channel: Option<&str>) {
+        let state = self.states.get_state(instance).await;
+        for message in state.to_messages() {
+            self.websocket_server
+                .send_message_to_client(id, &message)
+                .await
+        }
+        if let Some(channel) = channel {
+            let state = self.states.get_channel_state(instance, channel).await;
+            for message in state.to_messages() {
+                self.websocket_server
+                    .send_message_to_client(id, &message)
+                    .await
+            }
+        }
+    }
+
+    /// Sends the message to all clients of the instance. While an emergency override is active,
+    /// data updates are held back until it is released and animations are skipped.
+    pub async fn send_instance_message(&self, instance: &str, message: &InstanceMessage<'_>) {
+        crate::timing::mark("handler");
+        if self.hold_for_override(instance, message).await {
+            return;
+        }
+        self.deliver_instance_message(instance, message).await
+    }
+
+    pub fn requires_approval(&self, instance: &str, operations: &[Operation]) -> bool {
+        self.approvals.requires_approval(instance, operations)
+    }
+
+    /// Sends the message to the renderers of the channel only. Their data is kept apart from the
+    /// state of the instance, so a channel (e.g. a preview output) can show other data than the
+    /// remaining renderers of the instance.
+    pub async fn send_channel_message(
+        &self,
+        instance: &str,
+        channel: &str,
+        message: &InstanceMessage<'_>,
+    ) {
+        if self.active_overrides.read().await.contains_key(instance) {
+            info!(
+                "Dropping message to channel {} of instance {} during emergency override.",
+                channel, instance
+            );
+            return;
+        }
+        self.record_command(instance, message, Some(format!("channel {channel}")))
+            .await;
+        self.states.record_channel(instance, channel, message).await;
+        let clients = self
+            .websocket_server
+            .send_message_to_channel_clients(instance, channel, message)
+            .await;
+        debug!(
+            "Sent message to {} renderers of channel {} of instance {}.",
+            clients, channel, instance
+        );
+    }
+
+    /// Returns the data which was staged on the channel, e.g. the preview, and is not on all
+    /// renderers yet.
+    pub async fn get_staged_state(&self, instance: &str, channel: &str) -> InstanceState {
+        self.states.get_channel_state(instance, channel).await
+    }
+
+    /// Sends the data staged on the channel to all renderers in a single message, so it goes on
+    /// air at once, unless it requires approval. The data is not staged anymore in either case.
+    pub async fn take_staged_state(&self, instance: &str, channel: &str) -> StagedTakeResult {
+        let _take_guard = self.take_lock.lock().await;
+        let staged = self.states.get_channel_state(instance, channel).await;
+        if staged.count_elements() == 0 {
+            return StagedTakeResult::NothingStaged;
+        }
+        let operations = staged.get_changed_operations(&InstanceState::default());
+        if self.approvals.requires_approval(instance, &operations) {
+            self.states.remove_channel_state(instance, channel).await;
+            let change = self.hold_change(instance, None, operations).await;
+            return StagedTakeResult::Pending(change);
+        }
+        let message = InstanceMessage::Batch {
+            operations: staged.to_messages(),
+        };
+        self.send_instance_message(instance, &message).await;
+        // the data is held back during an emergency override and not staged anymore either
+        self.states.remove_channel_state(instance, channel).await;
+        info!(
+            "Took {} staged elements of channel {} of instance {}.",
+            staged.count_elements(),
+            channel,
+            instance
+        );
+        StagedTakeResult::Taken(staged)
+    }
+
+    /// Sends the operations in a single message, unless they require approval. Returns the pending
+    /// change in that case.
+    pub async fn submit_operations(
+        &self,
+        instance: &str,
+        operations: Vec<Operation>,
+    ) -> Option<PendingChange> {
+        self.submit_channel_operations(instance, None, operations)
             .await
     }
+
+    /// Like [`Self::submit_operations`], but only sends the operations to the renderers of the
+    /// channel if one is given.
+    pub async fn submit_channel_operations(
+        &self,
+        instance: &str,
+        channel: Option<&str>,
+        operations: Vec<Operation>,
+    ) -> Option<PendingChange> {
+        if !self.approvals.requires_approval(instance, &operations) {
+            let message = crate::data::operation::to_message(&operations);
+            self.send_message(instance, channel, &message).await;
+            return None;
+        }
+        Some(self.hold_change(instance, channel, operations).await)
+    }
+
+    async fn hold_change(
+        &self,
+        instance: &str,
+        channel: Option<&str>,
+        operations: Vec<Operation>,
+    ) -> PendingChange {
+        let change = self.approvals.submit(instance, channel, operations).await;
+        info!(
+            "Holding back change {} of instance {} until it is approved.",
+            change.id, instance
+        );
+        let event = EventKind::ChangePending { id: change.id };
+        self.record_event(instance, event).await;
+        change
+    }
+
+    /// Replaces the state of the instance like [`Self::set_state`], unless the changes require
+    /// approval. Returns the pending change with the operations of the changes in that case.
+    pub async fn submit_state(
+        &self,
+        instance: &str,
+        state: InstanceState,
+    ) -> Option<PendingChange> {
+        let current = self.get_regular_state(instance).await;
+        let operations = state.get_changed_operations(&current);
+        if !self.approvals.requires_approval(instance, &operations) {
+            self.set_state(instance, state).await;
+            return None;
+        }
+        Some(self.hold_change(instance, None, operations).await)
+    }
+
+    /// Sends the operations to a single client of the instance, e.g. to fix up a renderer which
+    /// missed an update. The operations are not part of the state of the instance, so the client
+    /// shows the regular data again when it reloads. Returns false if the instance has no such
+    /// client.
+    pub async fn send_client_operations(
+        &self,
+        instance: &str,
+        id: usize,
+        operations: Vec<Operation>,
+    ) -> bool {
+        if !self.websocket_server.is_instance_client(instance, id).await {
+            return false;
+        }
+        if self.active_overrides.read().await.contains_key(instance) {
+            info!(
+                "Dropping message to client {} of instance {} during emergency override.",
+                id, instance
+            );
+            return true;
+        }
+        let message = crate::data::operation::to_message(&operations);
+        self.record_command(instance, &message, Some(format!("client {id}")))
+            .await;
+        self.websocket_server
+            .send_message_to_client(id, &message)
+            .await;
+        true
+    }
+
+    /// Whether the client with the id is connected to the instance.
+    pub async fn is_instance_client(&self, instance: &str, id: usize) -> bool {
+        self.websocket_server.is_instance_client(instance, id).await
+    }
+
+    /// Records the message in the audit log and the session being recorded. `recipients` describes
+    /// the renderers which received the message if not all did.
+    async fn record_command(
+        &self,
+        instance: &str,
+        message: &InstanceMessage<'_>,
+        recipients: Option<String>,
+    ) {
+        self.audit_log.record(instance, message).await;
+        if !self.sessions.is_recording().await {
+            return;
+        }
+        match crate::session::get_command_entry(instance, message, recipients) {
+            Ok(entry) => self.sessions.record(entry).await,
+            Err(err) => error!("Could not serialize message for session: {}.", err),
+        }
+    }
+
+    pub async fn get_pending_changes(&self, instance: &str) -> Vec<PendingChange> {
+        self.approvals.get_pending(instance).await
+    }
+
+    /// Sends the operations of the pending change. Returns `None` if there is no such change.
+    pub async fn approve_change(&self, instance: &str, id: u64) -> Option<PendingChange> {
+        let change = self.approvals.take(instance, id).await?;
+        info!("Change {} of instance {} was approved.", id, instance);
+        self.record_event(instance, EventKind::ChangeApproved { id })
+            .await;
+        let message = crate::data::operation::to_message(&change.operations);
+        self.send_message(instance, change.channel.as_deref(), &message)
+            .await;
+        Some(change)
+    }
+
+    async fn send_message(
+        &self,
+        instance: &str,
+        channel: Option<&str>,
+        message: &InstanceMessage<'_>,
+    ) {
+        match channel {
+            Some(channel) => self.send_channel_message(instance, channel, message).await,
+            None => self.send_instance_message(instance, message).await,
+        }
+    }
+
+    pub async fn reject_change(&self, instance: &str, id: u64) -> Option<PendingChange> {
+        let change = self.approvals.take(instance, id).await?;
+        info!("Change {} of instance {} was rejected.", id, instance);
+        self.record_event(instance, EventKind::ChangeRejected { id })
+            .await;
+        Some(change)
+    }
+
+    async fn hold_for_override(&self, instance: &str, message: &InstanceMessage<'_>) -> bool {
+        let mut active_overrides = self.active_overrides.write().await;
+        let Some(active) = active_overrides.get_mut(instance) else {
+            return false;
+        };
+        Self::hold_message(instance, active, message)
+    }
+
+    fn hold_message(
+        instance: &str,
+        active: &mut ActiveOverride,
+        message: &InstanceMessage<'_>,
+    ) -> bool {
+        match message {
+            InstanceMessage::ExecuteAnimation {
+                animation_sequence, ..
+            } => {
+                info!(
+                    "Skipping animation {} of instance {} while emergency override {} is active.",
+                    animation_sequence, instance, active.name
+                );
+                true
+            }
+            InstanceMessage::Batch { operations } => {
+                let mut held = true;
+                for operation in operations {
+                    held &= Self::hold_message(instance, active, operation);
+                }
+                held
+            }
+            _ => active.held_state.apply(message),
+        }
+    }
+
+    async fn deliver_instance_message(&self, instance: &str, message: &InstanceMessage<'_>) {
+        let rules = match self.rules.get(instance).await {
+            Ok(rules) => rules,
+            Err(err) => {
+                error!("Could not load rules of instance {}: {:#}.", instance, err);
+                Rules::new()
+            }
+        };
+        let webhooks = match self.webhooks.get(instance).await {
+            Ok(webhooks) => webhooks,
+            Err(err) => {
+                error!(
+                    "Could not load webhooks of instance {}: {:#}.",
+                    instance, err
+                );
+                Webhooks::new()
+            }
+        };
+        // the state before the message is only needed to evaluate the rules and webhooks
+        let previous_state = match rules.is_empty() && webhooks.is_empty() {
+            true => None,
+            false => Some(self.states.get_state(instance).await),
+        };
+
+        self.record_command(instance, message, None).await;
+        crate::timing::mark("audit");
+        self.states.record(instance, message).await;
+        crate::timing::mark("state");
+        self.analytics.record_command(instance).await;
+        crate::timing::mark("analytics");
+        for operation in message.operations() {
+            if let InstanceMessage::ExecuteAnimation {
+                animation_sequence, ..
+            } = operation
+            {
+                self.analytics
+                    .record_animation(instance, animation_sequence)
+                    .await;
+                self.feedback
+                    .record_animation(instance, animation_sequence)
+                    .await;
+            }
+        }
+
+        self.websocket_server
+            .send_message_to_routed_clients(instance, message)
+            .await;
+        if let Some(trace) = crate::timing::finish() {
+            self.command_latency.record(instance, trace).await;
+        }
+
+        if let Some(previous_state) = previous_state {
+            let state = self.states.get_state(instance).await;
+            if !webhooks.is_empty() {
+                let changes = crate::webhooks::get_changes(&previous_state, &state);
+                self.call_webhooks(instance, &webhooks, &changes);
+            }
+            let triggered: Vec<Rule> =
+                crate::rules::get_triggered_rules(&rules, &previous_state, &state)
+                    .into_iter()
+                    .cloned()
+                    .collect();
+            if !triggered.is_empty() {
+                self.execute_rules(instance, triggered, state).await;
+            }
+        }
+        self.update_computed_elements(instance).await;
+    }
+
+    /// Sets the texts of computed elements whose inputs changed. The texts are delivered like any
+    /// other message (which updates the computed elements again), so the future is boxed.
+    fn update_computed_elements<'a>(&'a self, instance: &'a str) -> BoxFuture<'a, ()> {
+        async move {
+            let computed = match self.computed_elements.get(instance).await {
+                Ok(computed) => computed,
+                Err(err) => {
+                    error!(
+                        "Could not load computed elements of instance {}: {:#}.",
+                        instance, err
+                    );
+                    return;
+                }
+            };
+            if computed.is_empty() {
+                return;
+            }
+            let state = self.states.get_state(instance).await;
+            let operations: Vec<Operation> = crate::expression::compute_texts(&computed, &state)
+                .into_iter()
+                .map(|(id, text)| Operation::SetText {
+                    id,
+                    text,
+                    tween_duration: None,
+                })
+                .collect();
+            if !operations.is_empty() {
+                self.submit_operations(instance, operations).await;
+            }
+        }
+        .boxed()
+    }
+
+    /// Queues the changes for the webhooks interested in them, without waiting for the responses.
+    fn call_webhooks(&self, instance: &str, webhooks: &[Webhook], changes: &[DataChange]) {
+        if changes.is_empty() {
+            return;
+        }
+        for webhook in webhooks {
+            let Some(payload) = webhook.get_payload(instance, changes) else {
+                continue;
+            };
+            let body = match serde_json::to_value(&payload) {
+                Ok(body) => body,
+                Err(err) => {
+                    error!(
+                        "Could not serialize payload of webhook {}: {}.",
+                        webhook.name, err
+                    );
+                    continue;
+                }
+            };
+            // webhooks must not delay the commands of operators
+            self.webhook_queues.enqueue(
+                &format!("{instance}/{}", webhook.name),
+                &webhook.url,
+                body,
+            );
+        }
+    }
+
+    /// Executes the actions of triggered rules. The operations are delivered like any other
+    /// message (which evaluates the rules again), so the future is boxed.
+    fn execute_rules<'a>(
+        &'a self,
+        instance: &'a str,
+        rules: Vec<Rule>,
+        state: InstanceState,
+    ) -> BoxFuture<'a, ()> {
+        async move {
+            for rule in rules {
+                info!("Rule {} of instance {} was triggered.", rule.name, instance);
+                self.record_event(
+                    instance,
+                    EventKind::RuleTriggered {
+                        rule: rule.name.clone(),
+                    },
+                )
+                .await;
+                let body = serde_json::json!({
+                    "instance": instance,
+                    "rule": rule.name,
+                    "element": rule.element,
+                    "text": state.texts.get(&rule.element),
+                });
+                for url in rule.get_webhooks() {
+                    // webhooks must not delay the commands of operators
+                    self.webhook_queues.enqueue(
+                        &format!("{instance}/rule {}/{url}", rule.name),
+                        url,
+                        body.clone(),
+                    );
+                }
+                let operations = rule.get_operations();
+                if !operations.is_empty() {
+                    self.submit_operations(instance, operations).await;
+                }
+            }
+        }
+        .boxed()
+    }
+
+    /// Returns the current state of the instance including the versions of the used assets.
+    pub async fn get_state(&self, instance: &str) -> anyhow::Result<InstanceState> {
+        let versions = self.asset_versions.get_versions().await?;
+        Ok(self
+            .states
+            .get_state(instance)
+            .await
+            .with_asset_versions(&versions))
+    }
+
+    /// Returns the animations and elements of the template which control surfaces can trigger.
+    pub async fn get_action_catalogue(&self, instance: &str) -> ActionCatalogue {
+        let reported = self.websocket_server.get_reported_template(instance).await;
+        let state = self.states.get_state(instance).await;
+        crate::actions::get_action_catalogue(instance, reported, &state)
+    }
+
+    pub async fn get_routes(&self) -> anyhow::Result<Routes> {
+        self.websocket_server.get_routes().await
+    }
+
+    /// Routes the data of the instance to the channels, or to all renderers without channels, and
+    /// returns the previous route. Renderers which are routed now receive the current state, the
+    /// others hide the template.
+    pub async fn set_route(
+        &self,
+        instance: &str,
+        channels: Option<BTreeSet<String>>,
+    ) -> anyhow::Result<Option<BTreeSet<String>>> {
+        let previous = match &channels {
+            Some(channels) => {
+                self.websocket_server
+                    .set_route(instance, channels.clone())
+                    .await?
+            }
+            None => self.websocket_server.remove_route(instance).await?,
+        };
+        for (id, channel) in self.websocket_server.get_renderer_channels(instance).await {
+            let was_routed = crate::routing::is_routed(previous.as_ref(), channel.as_deref());
+            let routed = crate::routing::is_routed(channels.as_ref(), channel.as_deref());
+            if was_routed == routed {
+                continue;
+            }
+            let message = InstanceMessage::SetRouted { routed };
+            self.websocket_server
+                .send_message_to_client(id, &message)
+                .await;
+            if routed {
+                self.send_current_state(id, instance, channel.as_deref())
+                    .await;
+            }
+        }
+        info!("Routed instance {} to {:?}.", instance, channels);
+        Ok(previous)
+    }
+
+    pub fn subscribe_state_changes(&self) -> tokio::sync::broadcast::Receiver<String> {
+        self.states.subscribe_changes()
+    }
+
+    /// Returns the regular state of the instance, which is the state held back while an
+    /// emergency override is active.
+    async fn get_regular_state(&self, instance: &str) -> InstanceState {
+        match self.active_overrides.read().await.get(instance) {
+            Some(active) => active.held_state.clone(),
+            None => self.states.get_state(instance).await,
+        }
+    }
+
+    /// Replaces the state of the instance and applies it to all of its clients. Elements which are
+    /// not part of the new state are cleared.
+    pub async fn set_state(&self, instance: &str, state: InstanceState) {
+        if let Some(active) = self.active_overrides.write().await.get_mut(instance) {
+            active.held_state = state;
+            return;
+        }
+        self.deliver_state(instance, state).await
+    }
+
+    async fn deliver_state(&self, instance: &str, state: InstanceState) {
+        let current = self.states.get_state(instance).await;
+        let removals = state.get_removal_operations(&current);
+        if !removals.is_empty() {
+            let message = crate::data::operation::to_message(&removals);
+            self.deliver_instance_message(instance, &message).await;
+        }
+        self.states
+            .set_state(instance, InstanceState::default())
+            .await;
+        for message in state.to_messages() {
+            self.deliver_instance_message(instance, &message).await;
+        }
+    }
+
+    pub async fn get_data_defaults(&self, instance: &str) -> anyhow::Result<DataDefaults> {
+        self.data_defaults.get(instance).await
+    }
+
+    pub async fn set_data_defaults(
+        &self,
+        instance: &str,
+        defaults: DataDefaults,
+    ) -> anyhow::Result<()> {
+        self.data_defaults.set(instance, defaults).await
+    }
+
+    pub async fn get_rules(&self, instance: &str) -> anyhow::Result<Rules> {
+        self.rules.get(instance).await
+    }
+
+    pub async fn set_rules(&self, instance: &str, rules: Rules) -> anyhow::Result<()> {
+        self.rules.set(instance, rules).await
+    }
+
+    pub async fn get_webhooks(&self, instance: &str) -> anyhow::Result<Webhooks> {
+        self.webhooks.get(instance).await
+    }
+
+    pub async fn set_webhooks(&self, instance: &str, webhooks: Webhooks) -> anyhow::Result<()> {
+        self.webhooks.set(instance, webhooks).await
+    }
+
+    pub async fn get_computed_elements(&self, instance: &str) -> anyhow::Result<ComputedElements> {
+        self.computed_elements.get(instance).await
+    }
+
+    /// Stores the expressions and sets the texts of the computed elements right away.
+    pub async fn set_computed_elements(
+        &self,
+        instance: &str,
+        computed: ComputedElements,
+    ) -> anyhow::Result<()> {
+        self.computed_elements.set(instance, computed).await?;
+        self.update_computed_elements(instance).await;
+        Ok(())
+    }
+
+    /// Replaces a string in the default texts and snapshots of one or all instances, e.g. the
+    /// name of last season's sponsor. Nothing is changed in a dry run.
+    pub async fn replace_texts(&self, request: &ReplaceRequest) -> anyhow::Result<ReplaceReport> {
+        let instances = match &request.instance {
+            Some(instance) => vec![instance.clone()],
+            None => crate::fs::get_instance_names(&self.data_folder)?,
+        };
+        let mut report = ReplaceReport {
+            dry_run: request.dry_run,
+            matches: Vec::new(),
+        };
+        for instance in instances {
+            let mut defaults = self.data_defaults.get(&instance).await?;
+            let default_matches = crate::replace::replace_in_defaults(
+                &instance,
+                &mut defaults,
+                &request.find,
+                &request.replace,
+            );
+            let mut snapshots = self.snapshots.get_all_snapshots(&instance).await?;
+            let snapshot_matches = crate::replace::replace_in_snapshots(
+                &instance,
+                &mut snapshots,
+                &request.find,
+                &request.replace,
+            );
+            if !request.dry_run {
+                if !default_matches.is_empty() {
+                    self.data_defaults.set(&instance, defaults).await?;
+                }
+                if !snapshot_matches.is_empty() {
+                    self.snapshots
+                        .set_all_snapshots(&instance, snapshots)
+                        .await?;
+                }
+            }
+            report.matches.extend(default_matches);
+            report.matches.extend(snapshot_matches);
+        }
+        if !request.dry_run && !report.matches.is_empty() {
+            info!(
+                "Replaced {:?} with {:?} in {} texts.",
+                request.find,
+                request.replace,
+                report.matches.len()
+            );
+        }
+        Ok(report)
+    }
+
+    /// Returns the data defaults of all instances which declared any.
+    pub async fn get_data_defaults_of_instances(
+        &self,
+    ) -> anyhow::Result<Vec<(String, DataDefaults)>> {
+        let mut instances = Vec::new();
+        for instance in crate::fs::get_instance_names(&self.data_folder)? {
+            let defaults = self.data_defaults.get(&instance).await?;
+            if !defaults.is_empty() {
+                instances.push((instance, defaults));
+            }
+        }
+        Ok(instances)
+    }
+
+    /// Returns the instance to its baseline: all elements except the persisting ones are reset to
+    /// their defaults and all other texts and classes are cleared. Returns the pending change if
+    /// the defaults require approval.
+    pub async fn clear_instance(&self, instance: &str) -> anyhow::Result<Option<PendingChange>> {
+        let defaults = self.data_defaults.get(instance).await?;
+        let state = self.get_regular_state(instance).await;
+        Ok(self
+            .submit_state(instance, crate::defaults::clear_state(&state, &defaults))
+            .await)
+    }
+
+    pub async fn reset_elements(
+        &self,
+        instance: &str,
+        defaults: &DataDefaults,
+        ids: &[&str],
+    ) -> Option<PendingChange> {
+        let state = self.get_regular_state(instance).await;
+        self.submit_state(
+            instance,
+            crate::defaults::reset_elements(&state, defaults, ids),
+        )
+        .await
+    }
+
+    pub async fn get_rundown(&self, instance: &str) -> anyhow::Result<Rundown> {
+        self.rundowns.get(instance).await
+    }
+
+    pub async fn set_rundown(&self, instance: &str, mut rundown: Rundown) -> anyhow::Result<()> {
+        if rundown
+            .current
+            .is_some_and(|current| current >= rundown.pages.len())
+        {
+            rundown.current = None;
+        }
+        self.rundowns.set(instance, rundown).await?;
+        self.feedback.notify_change(instance);
+        Ok(())
+    }
+
+    /// Takes the page with the given index, or the next page if no index is given: runs the out
+    /// animation of the transition profile on the current page, waits for the configured delay,
+    /// applies the snapshot of the page and runs the in animation.
+    pub async fn take_page(
+        &self,
+        instance: &str,
+        index: Option<usize>,
+    ) -> anyhow::Result<TakeResult> {
+        let _take_guard = self.take_lock.lock().await;
+        let mut rundown = self.rundowns.get(instance).await?;
+        let Some(index) = index.or_else(|| rundown.get_next_index()) else {
+            return Ok(TakeResult::PageNotFound);
+        };
+        let Some(page) = rundown.pages.get(index).cloned() else {
+            return Ok(TakeResult::PageNotFound);
+        };
+        let Some(snapshot) = self
+            .snapshots
+            .get_snapshot(instance, &page.snapshot)
+            .await?
+        else {
+            return Ok(TakeResult::SnapshotNotFound(page.snapshot));
+        };
+
+        let transition = rundown.get_transition(&page);
+        // the transition cannot wait for an approval without running its animations out of order
+        let current = self.get_regular_state(instance).await;
+        let mut operations = snapshot.state.get_changed_operations(&current);
+        operations.extend(
+            [&transition.out_animation, &transition.in_animation]
+                .into_iter()
+                .flatten()
+                .map(|animation| Operation::ExecuteAnimation {
+                    animation_sequence: animation.clone(),
+                    execute_at: None,
+                }),
+        );
+        if self.approvals.requires_approval(instance, &operations) {
+            return Ok(TakeResult::RequiresApproval);
+        }
+        // the first page is taken without transitioning out
+        if rundown.current.is_some() {
+            if let Some(out_animation) = &transition.out_animation {
+                let message = InstanceMessage::ExecuteAnimation {
+                    animation_sequence: out_animation,
+                    execute_at: None,
+                };
+                self.send_instance_message(instance, &message).await;
+            }
+            tokio::time::sleep(Duration::from_millis(transition.delay_ms)).await;
+        }
+        self.set_state(instance, snapshot.state).await;
+        if let Some(in_animation) = &transition.in_animation {
+            let message = InstanceMessage::ExecuteAnimation {
+                animation_sequence: in_animation,
+                execute_at: None,
+            };
+            self.send_instance_message(instance, &message).await;
+        }
+
+        rundown.current = Some(index);
+        self.rundowns.set(instance, rundown.clone()).await?;
+        self.feedback.notify_change(instance);
+        Ok(TakeResult::Taken(rundown))
+    }
+
+    pub async fn get_emergency_overrides(
+        &self,
+        instance: &str,
+    ) -> anyhow::Result<EmergencyOverrides> {
+        self.emergency_overrides.get(instance).await
+    }
+
+    pub async fn set_emergency_overrides(
+        &self,
+        instance: &str,
+        overrides: EmergencyOverrides,
+    ) -> anyhow::Result<()> {
+        self.emergency_overrides.set(instance, overrides).await
+    }
+
+    /// Returns the name of the emergency override that is on air.
+    pub async fn get_active_override(&self, instance: &str) -> Option<String> {
+        self.active_overrides
+            .read()
+            .await
+            .get(instance)
+            .map(|active| active.name.clone())
+    }
+
+    /// Immediately replaces everything on air with the emergency override, bypassing takes and
+    /// the update rate limits of the clients. The regular state is held back until the override
+    /// is released. Activating another override while one is active switches the override.
+    pub async fn activate_emergency_override(
+        &self,
+        instance: &str,
+        name: &str,
+    ) -> anyhow::Result<ActivationResult> {
+        let overrides = self.emergency_overrides.get(instance).await?;
+        let Some(emergency) = overrides.get(name) else {
+            return Ok(ActivationResult::OverrideNotFound);
+        };
+        let current = self.states.get_state(instance).await;
+        {
+            let mut active_overrides = self.active_overrides.write().await;
+            let held_state = match active_overrides.remove(instance) {
+                Some(active) => active.held_state,
+                None => current.clone(),
+            };
+            active_overrides.insert(
+                instance.to_owned(),
+                ActiveOverride {
+                    name: name.to_owned(),
+                    held_state,
+                },
+            );
+        }
+        warn!(
+            "Emergency override {} of instance {} is active.",
+            name, instance
+        );
+
+        self.deliver_instance_message(
+            instance,
+            &InstanceMessage::SetEmergencyOverride { active: true },
+        )
+        .await;
+        self.deliver_state(
+            instance,
+            crate::emergency::replace_state(&current, &emergency.state),
+        )
+        .await;
+        if let Some(animation) = &emergency.animation {
+            let message = InstanceMessage::ExecuteAnimation {
+                animation_sequence: animation,
+                execute_at: None,
+            };
+            self.deliver_instance_message(instance, &message).await;
+        }
+        self.feedback.notify_change(instance);
+        Ok(ActivationResult::Activated)
+    }
+
+    /// Releases the emergency override and restores the regular state including all updates that
+    /// were held back. Returns whether an override was active.
+    pub async fn release_emergency_override(&self, instance: &str) -> bool {
+        let Some(active) = self.active_overrides.write().await.remove(instance) else {
+            return false;
+        };
+        info!(
+            "Emergency override {} of instance {} was released.",
+            active.name, instance
+        );
+        let current = self.states.get_state(instance).await;
+        self.deliver_instance_message(
+            instance,
+            &InstanceMessage::SetEmergencyOverride { active: false },
+        )
+        .await;
+        self.deliver_state(
+            instance,
+            crate::emergency::replace_state(&current, &active.held_state),
+        )
+        .await;
+        self.feedback.notify_change(instance);
+        true
+    }
+
+    pub async fn get_feedback_buttons(&self, instance: &str) -> anyhow::Result<FeedbackButtons> {
+        self.feedback_buttons.get(instance).await
+    }
+
+    pub async fn set_feedback_buttons(
+        &self,
+        instance: &str,
+        buttons: FeedbackButtons,
+    ) -> anyhow::Result<()> {
+        self.feedback_buttons.set(instance, buttons).await?;
+        self.feedback.notify_change(instance);
+        Ok(())
+    }
+
+    /// Returns whether each button of the instance is lit, based on what is on air.
+    pub async fn get_button_feedback(
+        &self,
+        instance: &str,
+    ) -> anyhow::Result<BTreeMap<String, bool>> {
+        let buttons = self.feedback_buttons.get(instance).await?;
+        let uses_snapshots = buttons
+            .values()
+            .any(|condition| matches!(condition, ButtonCondition::Snapshot { .. }));
+        let snapshots = if uses_snapshots {
+            self.snapshots
+                .get_all_snapshots(instance)
+                .await?
+                .into_iter()
+                .map(|(name, snapshot)| (name, snapshot.state))
+                .collect()
+        } else {
+            HashMap::new()
+        };
+        let state = self.states.get_state(instance).await;
+        let animations = self.feedback.get_animations(instance).await;
+        let emergency = self.get_active_override(instance).await;
+        let context = FeedbackContext {
+            state: &state,
+            animations: &animations,
+            snapshots: &snapshots,
+            rundown_page: self.rundowns.get(instance).await?.current,
+            emergency: emergency.as_deref(),
+        };
+        Ok(crate::feedback::evaluate_buttons(&buttons, &context))
+    }
+
+    /// Returns a receiver of the names of instances whose button feedback may have changed.
+    pub fn subscribe_feedback_changes(&self) -> tokio::sync::broadcast::Receiver<String> {
+        self.feedback.subscribe_changes()
+    }
+
+    pub async fn get_localization(&self, instance: &str) -> anyhow::Result<Localization> {
+        self.localizations.get(instance).await
+    }
+
+    /// Stores the translation bundle of the language. If the language is active, the labels are
+    /// updated right away.
+    pub async fn set_translation_bundle(
+        &self,
+        instance: &str,
+        language: &str,
+        bundle: TranslationBundle,
+    ) -> anyhow::Result<()> {
+        let mut localization = self.localizations.get(instance).await?;
+        localization.bundles.insert(language.to_owned(), bundle);
+        let is_active = localization.active_language.as_deref() == Some(language);
+        self.localizations
+            .set(instance, localization.clone())
+            .await?;
+        if is_active {
+            self.send_localized_labels(instance, &localization).await;
+        }
+        Ok(())
+    }
+
+    /// Deletes the translation bundle of the language. Returns whether the bundle existed.
+    pub async fn delete_translation_bundle(
+        &self,
+        instance: &str,
+        language: &str,
+    ) -> anyhow::Result<bool> {
+        let mut localization = self.localizations.get(instance).await?;
+        if localization.bundles.remove(language).is_none() {
+            return Ok(false);
+        }
+        if localization.active_language.as_deref() == Some(language) {
+            localization.active_language = None;
+        }
+        self.localizations.set(instance, localization).await?;
+        Ok(true)
+    }
+
+    /// Binds the static labels to translation keys and shows them in the active language.
+    pub async fn set_localized_labels(
+        &self,
+        instance: &str,
+        labels: BTreeMap<String, String>,
+    ) -> anyhow::Result<()> {
+        let mut localization = self.localizations.get(instance).await?;
+        localization.labels = labels;
+        self.localizations
+            .set(instance, localization.clone())
+            .await?;
+        self.send_localized_labels(instance, &localization).await;
+        Ok(())
+    }
+
+    /// Switches the active language and sends all labels in the new language. Returns the keys
+    /// without translation, or `None` if there is no bundle for the language.
+    pub async fn set_active_language(
+        &self,
+        instance: &str,
+        language: &str,
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        let mut localization = self.localizations.get(instance).await?;
+        if !localization.bundles.contains_key(language) {
+            return Ok(None);
+        }
+        let missing_keys: Vec<String> = localization
+            .get_missing_keys(language)
+            .into_iter()
+            .map(str::to_owned)
+            .collect();
+        if !missing_keys.is_empty() {
+            warn!(
+                "Translation bundle {} of instance {} has no translation for {:?}.",
+                language, instance, missing_keys
+            );
+        }
+        localization.active_language = Some(language.to_owned());
+        self.localizations
+            .set(instance, localization.clone())
+            .await?;
+        self.send_localized_labels(instance, &localization).await;
+        Ok(Some(missing_keys))
+    }
+
+    async fn send_localized_labels(&self, instance: &str, localization: &Localization) {
+        let operations: Vec<Operation> = localization
+            .get_label_texts()
+            .into_iter()
+            .map(|(id, text)| Operation::SetText {
+                id: id.to_owned(),
+                text: text.to_owned(),
+                tween_duration: None,
+            })
+            .collect();
+        if !operations.is_empty() {
+            self.submit_operations(instance, operations).await;
+        }
+    }
+
+    pub async fn get_rotations(&self, instance: &str) -> anyhow::Result<Rotations> {
+        self.rotations.get(instance).await
+    }
+
+    pub async fn set_rotations(&self, instance: &str, rotations: Rotations) -> anyhow::Result<()> {
+        self.rotations.set(instance, rotations).await
+    }
+
+    /// Returns the rotations of all instances which defined any.
+    pub async fn get_rotations_of_instances(&self) -> anyhow::Result<Vec<(String, Rotations)>> {
+        let mut instances = Vec::new();
+        for instance in crate::fs::get_instance_names(&self.data_folder)? {
+            let rotations = self.rotations.get(&instance).await?;
+            if !rotations.is_empty() {
+                instances.push((instance, rotations));
+            }
+        }
+        Ok(instances)
+    }
+
+    pub async fn record_rotation_display(
+        &self,
+        instance: &str,
+        record: &DisplayRecord,
+    ) -> anyhow::Result<()> {
+        self.display_log.append(instance, record).await
+    }
+
+    pub async fn get_rotation_report(
+        &self,
+        instance: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<DisplayReport> {
+        self.display_log.get_report(instance, from, to).await
+    }
+
+    pub async fn get_snapshots(&self, instance: &str) -> anyhow::Result<Vec<SnapshotInfo>> {
+        self.snapshots.get_snapshots(instance).await
+    }
+
+    pub async fn get_snapshot(
+        &self,
+        instance: &str,
+        name: &str,
+    ) -> anyhow::Result<Option<Snapshot>> {
+        self.snapshots.get_snapshot(instance, name).await
+    }
+
+    /// Stores the current state of the instance as snapshot.
+    pub async fn save_snapshot(&self, instance: &str, name: &str) -> anyhow::Result<()> {
+        let state = self.get_regular_state(instance).await;
+        self.snapshots.set_snapshot(instance, name, state).await?;
+        self.feedback.notify_change(instance);
+        Ok(())
+    }
+
+    /// Stores the given state as snapshot, e.g. to prepare it before it goes on air.
+    pub async fn set_snapshot(
+        &self,
+        instance: &str,
+        name: &str,
+        state: InstanceState,
+    ) -> anyhow::Result<()> {
+        self.snapshots.set_snapshot(instance, name, state).await?;
+        self.feedback.notify_change(instance);
+        Ok(())
+    }
+
+    /// Applies the snapshot to the instance, unless it requires approval.
+    pub async fn apply_snapshot(&self, instance: &str, name: &str) -> anyhow::Result<ApplyResult> {
+        let Some(snapshot) = self.snapshots.get_snapshot(instance, name).await? else {
+            return Ok(ApplyResult::SnapshotNotFound);
+        };
+        match self.submit_state(instance, snapshot.state).await {
+            Some(change) => Ok(ApplyResult::Pending(change)),
+            None => Ok(ApplyResult::Applied),
+        }
+    }
+
+    /// Returns the regular state of all instances for the process taking over the server.
+    /// Active emergency overrides are not handed over.
+    pub async fn get_handover_state(&self) -> HandoverState {
+        let mut handover = HandoverState {
+            created: Utc::now(),
+            process_id: std::process::id(),
+            ..HandoverState::default()
+        };
+        for instance in self.states.get_instances().await {
+            let state = self.get_regular_state(&instance).await;
+            handover.states.insert(instance, state);
+        }
+        handover
+    }
+
+    pub async fn get_instances_with_state(&self) -> Vec<String> {
+        self.states.get_instances().await
+    }
+
+    /// Stores the regular state of the instance in its folder.
+    pub async fn persist_state(&self, instance: &str) -> anyhow::Result<()> {
+        // the instance was deleted in the meantime
+        if !self.states.has_state(instance).await {
+            return Ok(());
+        }
+        let state = self.get_regular_state(instance).await;
+        let state_path =
+            crate::fs::get_instance_folder(&self.data_folder, instance)?.join(STATE_FILE_NAME);
+        crate::store::store_document(&state_path, &state).await
+    }
+
+    /// Restores the stored states of all instances after the server was started.
+    pub async fn restore_persisted_states(&self) -> anyhow::Result<()> {
+        let mut restored = 0;
+        for instance in crate::fs::get_instance_names(&self.data_folder)? {
+            let state_path =
+                crate::fs::get_instance_folder(&self.data_folder, &instance)?.join(STATE_FILE_NAME);
+            if !state_path.exists() {
+                continue;
+            }
+            match crate::store::load_document::<InstanceState>(&state_path).await {
+                Ok(state) => {
+                    self.set_state(&instance, state).await;
+                    restored += 1;
+                }
+                Err(err) => error!(
+                    "Could not restore stored state of instance {}: {:#}.",
+                    instance, err
+                ),
+            }
+        }
+        if restored > 0 {
+            info!("Restored stored state of {} instance(s).", restored);
+        }
+        Ok(())
+    }
+
+    pub async fn restore_handover_state(&self, handover: HandoverState) {
+        info!(
+            "Restoring state of {} instance(s) handed over from {}.",
+            handover.states.len(),
+            handover.created
+        );
+        for (instance, state) in handover.states {
+            self.set_state(&instance, state).await;
+        }
+    }
+
+    /// Returns the in-memory state for a crash report. Must not wait, since it is called from the
+    /// panic hook.
+    pub fn get_crash_state(&self) -> CrashState {
+        CrashState {
+            clients: self.websocket_server.try_get_clients(),
+            states: self.states.try_get_states(),
+            rundown_positions: self.rundowns.try_get_cached().map(|rundowns| {
+                rundowns
+                    .into_iter()
+                    .filter_map(|(instance, rundown)| {
+                        rundown.current.map(|current| (instance, current))
+                    })
+                    .collect()
+            }),
+        }
+    }
+
+    /// Disconnects all renderers, e.g. so they reconnect to the process that took over.
+    pub async fn disconnect_all_clients(&self) {
+        self.websocket_server.disconnect_all_clients().await
+    }
+
+    pub async fn store_auto_snapshots(&self, retention: usize) {
+        for instance in self.states.get_instances().await {
+            let state = self.get_regular_state(&instance).await;
+            if let Err(err) = self
+                .snapshots
+                .store_auto_snapshot(&instance, state, retention)
+                .await
+            {
+                error!(
+                    "Could not store automatic snapshot of instance {}: {}.",
+                    instance, err
+                );
+            }
+        }
+    }
+
+    pub async fn delete_snapshot(&self, instance: &str, name: &str) -> anyhow::Result<bool> {
+        let deleted = self.snapshots.delete_snapshot(instance, name).await?;
+        self.feedback.notify_change(instance);
+        Ok(deleted)
+    }
+
+    pub async fn export_instance(&self, instance: &str) -> anyhow::Result<InstancePackage> {
+        Ok(InstancePackage {
+            config: self.config_documents.get(instance).await?,
+            defaults: self.data_defaults.get(instance).await?,
+            rundown: self.rundowns.get(instance).await?,
+            rules: self.rules.get(instance).await?,
+            webhooks: self.webhooks.get(instance).await?,
+            computed_elements: self.computed_elements.get(instance).await?,
+            emergency_overrides: self.emergency_overrides.get(instance).await?,
+            feedback_buttons: self.feedback_buttons.get(instance).await?,
+            localization: self.localizations.get(instance).await?,
+            rotations: self.rotations.get(instance).await?,
+            feature_flags: self.feature_flags.get_instance_flags(instance).await?,
+            state: self.get_regular_state(instance).await,
+            snapshots: self.snapshots.get_all_snapshots(instance).await?,
+        })
+    }
+
+    /// Exports all instances which have stored data or a current state.
+    pub async fn export_instances(&self) -> anyhow::Result<InstanceArchive> {
+        let mut archive = InstanceArchive::default();
+        for instance in self.get_instance_names().await? {
+            let package = self.export_instance(&instance).await?;
+            archive.instances.insert(instance, package);
+        }
+        Ok(archive)
+    }
+
+    /// Imports all instances of the archive. If any instance cannot be imported, all instances
+    /// that were already imported are restored to their previous data.
+    pub async fn import_instances(&self, archive: InstanceArchive) -> ImportReport {
+        let errors = crate::archive::validate_archive(&archive);
+        if !errors.is_empty() {
+            return ImportReport::failed(&archive, errors);
+        }
+
+        let mut previous_packages = Vec::new();
+        for instance in archive.instances.keys() {
+            match self.export_instance(instance).await {
+                Ok(package) => previous_packages.push((instance.clone(), package)),
+                Err(err) => {
+                    let errors = BTreeMap::from([(instance.clone(), format!("{err:#}"))]);
+                    return ImportReport::failed(&archive, errors);
+                }
+            }
+        }
+
+        let mut report = ImportReport {
+            imported: true,
+            instances: BTreeMap::new(),
+        };
+        for (instance, package) in &archive.instances {
+            if let Err(err) = self.import_instance(instance, package.clone()).await {
+                error!("Could not import instance {}: {:#}.", instance, err);
+                let errors = BTreeMap::from([(instance.clone(), format!("{err:#}"))]);
+                let mut failed_report = ImportReport::failed(&archive, errors);
+                // the failed instance may be partially imported, so it is restored as well
+                for (previous_instance, previous_package) in &previous_packages {
+                    let imported = report.instances.contains_key(previous_instance);
+                    if !imported && previous_instance != instance {
+                        continue;
+                    }
+                    if let Err(err) = self
+                        .import_instance(previous_instance, previous_package.clone())
+                        .await
+                    {
+                        error!(
+                            "Could not restore instance {}: {:#}.",
+                            previous_instance, err
+                        );
+                    }
+                    if imported {
+                        failed_report.instances.insert(
+                            previous_instance.clone(),
+                            ImportResult {
+                                status: ImportStatus::RolledBack,
+                                message: None,
+                            },
+                        );
+                    }
+                }
+                return failed_report;
+            }
+            report.instances.insert(
+                instance.clone(),
+                ImportResult {
+                    status: ImportStatus::Imported,
+                    message: None,
+                },
+            );
+        }
+        report
+    }
+
+    /// Copies all data of the instance except for its secrets to a new instance, e.g. to create a
+    /// differently branded variant of a graphics package.
+    pub async fn clone_instance(&self, instance: &str, name: &str) -> anyhow::Result<CloneResult> {
+        if !self.instance_exists(instance).await? {
+            return Ok(CloneResult::NotFound);
+        }
+        if self.instance_exists(name).await? {
+            return Ok(CloneResult::AlreadyExists);
+        }
+        let package = self.export_instance(instance).await?;
+        self.import_instance(name, package).await?;
+        info!("Cloned instance {} as {}.", instance, name);
+        Ok(CloneResult::Cloned)
+    }
+
+    /// Lists all instances which have stored data or a current state.
+    pub async fn get_instance_summaries(&self) -> anyhow::Result<Vec<InstanceSummary>> {
+        let mut summaries = Vec::new();
+        for instance in self.get_instance_names().await? {
+            // renderers may connect to instances whose names cannot be used as folder
+            let last_modified = match crate::fs::get_instance_folder(&self.data_folder, &instance) {
+                Ok(folder) if folder.exists() => crate::fs::get_last_modified(&folder)?,
+                _ => None,
+            };
+            summaries.push(InstanceSummary {
+                last_modified: last_modified.map(DateTime::<Utc>::from),
+                expires: self.expiry.get_expires(&instance),
+                expired: self.expiry.is_expired(&instance, Utc::now()),
+                elements: self.states.get_state(&instance).await.count_elements(),
+                connected_clients: self.get_instance_clients(&instance).await.len(),
+                name: instance,
+            });
+        }
+        Ok(summaries)
+    }
+
+    /// Deletes all data of the instance including its secrets and pending changes, and instructs
+    /// its clients to reload. Returns false if the instance does not exist.
+    pub async fn delete_instance(&self, instance: &str) -> anyhow::Result<bool> {
+        self.remove_instance(instance, false).await
+    }
+
+    /// Moves the stored data of the instance to the trash folder and removes the instance like
+    /// when it is deleted. Returns false if the instance does not exist.
+    pub async fn trash_instance(&self, instance: &str) -> anyhow::Result<bool> {
+        self.remove_instance(instance, true).await
+    }
+
+    pub fn is_instance_expired(&self, instance: &str) -> bool {
+        self.expiry.is_expired(instance, Utc::now())
+    }
+
+    pub fn get_instances_to_trash(&self) -> Vec<String> {
+        self.expiry.get_instances_to_trash(Utc::now())
+    }
+
+    pub async fn get_scheduled_items(
+        &self,
+        instance: Option<&str>,
+    ) -> anyhow::Result<Vec<ScheduledItem>> {
+        self.scheduler.get_items(instance).await
+    }
+
+    pub async fn get_scheduled_item(&self, id: u64) -> anyhow::Result<Option<ScheduledItem>> {
+        self.scheduler.get_item(id).await
+    }
+
+    pub async fn schedule_item(
+        &self,
+        request: ScheduleRequest,
+        at: DateTime<Utc>,
+    ) -> anyhow::Result<ScheduledItem> {
+        let item = self.scheduler.add(request, at).await?;
+        info!(
+            "Scheduled item {} of instance {} at {}.",
+            item.id, item.instance, item.at
+        );
+        Ok(item)
+    }
+
+    pub async fn replace_scheduled_item(
+        &self,
+        id: u64,
+        request: ScheduleRequest,
+        at: DateTime<Utc>,
+    ) -> anyhow::Result<Option<ScheduledItem>> {
+        self.scheduler.replace(id, request, at).await
+    }
+
+    pub async fn delete_scheduled_item(&self, id: u64) -> anyhow::Result<Option<ScheduledItem>> {
+        self.scheduler.remove(id).await
+    }
+
+    pub async fn take_due_scheduled_items(
+        &self,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<ScheduledItem>> {
+        self.scheduler.take_due(now).await
+    }
+
+    pub async fn get_next_scheduled_item_due(&self) -> anyhow::Result<Option<DateTime<Utc>>> {
+        self.scheduler.get_next_due().await
+    }
+
+    pub async fn wait_for_schedule_change(&self, timeout: Duration) {
+        self.scheduler.wait_for_change(timeout).await
+    }
+
+    /// Notifies the clients of the instance that the item fired and sends its operations.
+    pub async fn execute_scheduled_item(&self, item: ScheduledItem) {
+        info!(
+            "Executing scheduled item {} of instance {}.",
+            item.id, item.instance
+        );
+        let message = InstanceMessage::ScheduledItemFired {
+            id: item.id,
+            name: item.name.as_deref(),
+        };
+        self.websocket_server
+            .send_message_to_routed_clients(&item.instance, &message)
+            .await;
+        self.record_event(
+            &item.instance,
+            EventKind::ScheduledItemFired { id: item.id },
+        )
+        .await;
+        self.submit_operations(&item.instance, item.operations)
+            .await;
+    }
+
+    async fn remove_instance(&self, instance: &str, trash: bool) -> anyhow::Result<bool> {
+        if !self.instance_exists(instance).await? {
+            return Ok(false);
+        }
+        let instance_folder = crate::fs::get_instance_folder(&self.data_folder, instance)?;
+        if trash && instance_folder.exists() {
+            let trash_folder = crate::fs::get_trash_folder(&self.data_folder);
+            tokio::fs::create_dir_all(&trash_folder).await?;
+            let name = format!("{}-{}", instance, Utc::now().format("%Y%m%d%H%M%S"));
+            tokio::fs::rename(&instance_folder, trash_folder.join(name)).await?;
+        }
+        self.active_overrides.write().await.remove(instance);
+        // renderers of all channels have to receive that the instance was deleted
+        self.websocket_server.remove_route(instance).await?;
+        // resets the cached documents, which are otherwise served after the files are deleted
+        self.import_instance(instance, InstancePackage::default())
+            .await?;
+        if instance_folder.exists() {
+            tokio::fs::remove_dir_all(&instance_folder).await?;
+        }
+        self.states.remove_state(instance).await;
+        self.approvals.remove_instance(instance).await;
+        self.scheduler.remove_instance(instance).await?;
+        self.websocket_server.remove_retained_topics(instance).await;
+        self.websocket_server
+            .remove_reported_template(instance)
+            .await;
+        self.websocket_server
+            .send_message_to_instance_clients(instance, &InstanceMessage::InstanceDeleted)
+            .await;
+        self.record_event(instance, EventKind::InstanceDeleted)
+            .await;
+        info!("Removed instance {}.", instance);
+        Ok(true)
+    }
+
+    async fn get_instance_names(&self) -> anyhow::Result<BTreeSet<String>> {
+        let mut instances: BTreeSet<String> = crate::fs::get_instance_names(&self.data_folder)?
+            .into_iter()
+            .collect();
+        instances.extend(self.states.get_instances().await);
+        Ok(instances)
+    }
+
+    async fn instance_exists(&self, instance: &str) -> anyhow::Result<bool> {
+        Ok(
+            crate::fs::get_instance_folder(&self.data_folder, instance)?.exists()
+                || self
+                    .states
+                    .get_instances()
+                    .await
+                    .iter()
+                    .any(|name| name == instance),
+        )
+    }
+
+    async fn import_instance(
+        &self,
+        instance: &str,
+        package: InstancePackage,
+    ) -> anyhow::Result<()> {
+        self.set_config_document(instance, package.config).await?;
+        self.set_data_defaults(instance, package.defaults).await?;
+        self.set_rundown(instance, package.rundown).await?;
+        self.set_rules(instance, package.rules).await?;
+        self.set_webhooks(instance, package.webhooks).await?;
+        self.computed_elements
+            .set(instance, package.computed_elements)
+            .await?;
+        self.set_emergency_overrides(instance, package.emergency_overrides)
+            .await?;
+        self.set_feedback_buttons(instance, package.feedback_buttons)
+            .await?;
+        self.localizations
+            .set(instance, package.localization)
+            .await?;
+        self.set_rotations(instance, package.rotations).await?;
+        self.set_instance_feature_flags(instance, package.feature_flags)
+            .await?;
+        self.snapshots
+            .set_all_snapshots(instance, package.snapshots)
+            .await?;
+        // data which requires approval is held back like any other change
+        self.submit_state(instance, package.state).await;
+        Ok(())
+    }
+
+    /// Instructs all clients of the instance to pre-fetch the given assets into their cache.
+    pub async fn precache_assets(&self, instance: &str, assets: Vec<AssetReference>) {
+        self.websocket_server
+            .reset_precache_progress(instance, assets.len())
+            .await;
+        let message = InstanceMessage::PrecacheAssets {
+            assets: Cow::Owned(assets),
+        };
+        self.send_instance_message(instance, &message).await
+    }
+
+    pub async fn get_precache_progress(
+        &self,
+        instance: &str,
+    ) -> BTreeMap<usize, Option<PrecacheProgress>> {
+        self.websocket_server.get_precache_progress(instance).await
+    }
+
+    /// Publishes the value to the subscribed clients of the instance and returns their number.
+    pub async fn publish_topic(
+        &self,
+        instance: &str,
+        topic: &str,
+        value: serde_json::Value,
+        retain: bool,
+    ) -> usize {
+        self.websocket_server
+            .publish(instance, topic, value, retain)
+            .await
+    }
+
+    pub async fn get_retained_topics(&self, instance: &str) -> BTreeMap<String, serde_json::Value> {
+        self.websocket_server.get_retained_topics(instance).await
+    }
+
+    /// Removes the retained value of the topic and returns whether there was one.
+    pub async fn delete_retained_topic(&self, instance: &str, topic: &str) -> bool {
+        self.websocket_server
+            .remove_retained_topic(instance, topic)
+            .await
+    }
+
+    pub async fn get_config_document(&self, instance: &str) -> anyhow::Result<ConfigDocument> {
+        self.config_documents.get(instance).await
+    }
+
+    pub async fn set_config_document(
+        &self,
+        instance: &str,
+        config: ConfigDocument,
+    ) -> anyhow::Result<()> {
+        self.config_documents.set(instance, config.clone()).await?;
+        let message = InstanceMessage::SetConfig {
+            config: Cow::Owned(config),
+        };
+        self.websocket_server
+            .send_message_to_instance_clients(instance, &message)
+            .await;
+        Ok(())
+    }
+
+    pub async fn get_data_sources(&self) -> anyhow::Result<DataSources> {
+        self.data_sources.get_sources().await
+    }
+
+    pub async fn set_data_source(&self, name: &str, source: DataSource) -> anyhow::Result<()> {
+        self.data_sources.set_source(name, source).await
+    }
+
+    pub async fn delete_data_source(&self, name: &str) -> anyhow::Result<bool> {
+        self.data_sources.delete_source(name).await
+    }
+
+    pub async fn get_data_source_status(&self, name: &str) -> DataSourceStatus {
+        self.data_sources.get_status(name).await
+    }
+
+    pub fn subscribe_data_source_changes(&self) -> watch::Receiver<()> {
+        self.data_sources.subscribe_changes()
+    }
+
+    /// Polls the data source and sends the texts which changed. Returns `None` if there is no
+    /// such data source.
+    pub async fn poll_data_source(&self, name: &str) -> anyhow::Result<Option<DataSourceStatus>> {
+        let Some(source) = self.data_sources.get_sources().await?.remove(name) else {
+            return Ok(None);
+        };
+        let mut status = self.data_sources.get_status(name).await;
+        status.last_poll = Some(Utc::now());
+        match self.update_from_data_source(name, &source).await {
+            Ok((updated_texts, missing_fields)) => {
+                status.last_success = status.last_poll;
+                status.last_error = None;
+                status.updated_texts = updated_texts;
+                status.missing_fields = missing_fields;
+            }
+            Err(err) => status.last_error = Some(format!("{err:#}")),
+        }
+        self.data_sources.set_status(name, status.clone()).await;
+        Ok(Some(status))
+    }
+
+    /// Returns the number of texts which changed and the fields which could not be resolved.
+    async fn update_from_data_source(
+        &self,
+        name: &str,
+        source: &DataSource,
+    ) -> anyhow::Result<(usize, Vec<String>)> {
+        let (texts, missing_fields) = match source.format {
+            DataFormat::GoogleSheets => (self.load_google_sheet(name, source).await?, Vec::new()),
+            _ => self.load_data_source_document(name, source).await?,
+        };
+        let state = self.get_regular_state(&source.instance).await;
+        let operations: Vec<Operation> = texts
+            .into_iter()
+            .filter(|(element, text)| state.texts.get(element) != Some(text))
+            .map(|(id, text)| Operation::SetText {
+                id,
+                text,
+                tween_duration: None,
+            })
+            .collect();
+        let updated_texts = operations.len();
+        if !operations.is_empty() {
+            self.submit_operations(&source.instance, operations).await;
+        }
+        Ok((updated_texts, missing_fields))
+    }
+
+    /// Returns the text of every field and the fields which could not be resolved.
+    async fn load_data_source_document(
+        &self,
+        name: &str,
+        source: &DataSource,
+    ) -> anyhow::Result<(BTreeMap<String, String>, Vec<String>)> {
+        let mut headers = Vec::with_capacity(source.headers.len());
+        for (header, value) in &source.headers {
+            let value = match crate::datasource::get_secret_reference(value) {
+                Some(secret) => self
+                    .secrets
+                    .get_secret(&source.instance, secret)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Secret {} does not exist", secret))?,
+                None => value.clone(),
+            };
+            headers.push((header.clone(), value));
+        }
+        let configuration = self.get_configuration();
+        let file_folder = configuration.data_source_folder.as_deref();
+        let data = self
+            .integrations
+            .execute(&format!("datasource:{name}"), || {
+                crate::datasource::load(&self.http_client, &source.url, &headers, file_folder)
+            })
+            .await?;
+        let document = crate::datasource::parse_document(source.format, &data)?;
+        Ok(crate::datasource::get_texts(&source.fields, &document))
+    }
+
+    async fn load_google_sheet(
+        &self,
+        name: &str,
+        source: &DataSource,
+    ) -> anyhow::Result<BTreeMap<String, String>> {
+        let spreadsheet_id = crate::datasource::google::get_spreadsheet_id(&source.url)
+            .ok_or_else(|| anyhow::anyhow!("{} is not a Google Sheets URL", source.url))?;
+        let secret = source
+            .credentials
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("The data source has no credentials"))?;
+        let credentials = self
+            .secrets
+            .get_secret(&source.instance, secret)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Secret {} does not exist", secret))?;
+        let credentials = GoogleCredentials::parse(&credentials)?;
+        self.integrations
+            .execute(&format!("datasource:{name}"), || {
+                crate::datasource::google::load_texts(
+                    &self.http_client,
+                    spreadsheet_id,
+                    &source.fields,
+                    &credentials,
+                    self.data_sources.get_google_tokens(),
+                )
+            })
+            .await
+    }
+
+    /// Sends fake data to the renderers of the preview channel, so layouts can be reviewed with
+    /// realistic content. The state of the instance and the renderers of other channels are not
+    /// affected.
+    pub async fn send_fake_data(
+        &self,
+        instance: &str,
+        request: FakeDataRequestDto,
+    ) -> anyhow::Result<FakeDataDto> {
+        let (texts, images) = match (request.texts, request.images) {
+            (None, None) => self.get_bound_elements(instance).await?,
+            (texts, images) => (
+                texts.unwrap_or_default().into_iter().collect(),
+                images.unwrap_or_default().into_iter().collect(),
+            ),
+        };
+        let mut generator = FakeDataGenerator::new(request.seed);
+        let texts = generator.generate_texts(&texts);
+        let placeholders = generator.generate_images(&images);
+
+        let assets_folder = crate::fs::get_assets_folder(&self.data_folder)?;
+        let mut images = BTreeMap::new();
+        for (element, placeholder) in placeholders {
+            let file_name = placeholder.get_file_name();
+            let path = assets_folder.join(&file_name);
+            if !path.exists() {
+                tokio::fs::write(&path, placeholder.content).await?;
+            }
+            images.insert(
+                element,
+                AssetReference {
+                    asset: file_name,
+                    asset_source: AssetSource::Zagreus,
+                },
+            );
+        }
+
+        let operations: Vec<Operation> = texts
+            .iter()
+            .map(|(id, text)| Operation::SetText {
+                id: id.clone(),
+                text: text.clone(),
+                tween_duration: None,
+            })
+            .chain(images.iter().map(|(id, image)| Operation::SetImageSource {
+                id: id.clone(),
+                asset: image.asset.clone(),
+                asset_source: image.asset_source.clone(),
+            }))
+            .collect();
+        let channel = request
+            .channel
+            .unwrap_or_else(|| String::from(crate::fakedata::DEFAULT_PREVIEW_CHANNEL));
+        let clients = if operations.is_empty() {
+            0
+        } else {
+            let message = crate::data::operation::to_message(&operations);
+            self.websocket_server
+                .send_message_to_channel_clients(instance, &channel, &message)
+                .await
+        };
+        Ok(FakeDataDto {
+            channel,
+            texts,
+            images,
+            clients,
+        })
+    }
+
+    /// Returns the text and image elements of the instance which are bound to data, i.e. have a
+    /// default, are filled by a data source or currently have data.
+    async fn get_bound_elements(
+        &self,
+        instance: &str,
+    ) -> anyhow::Result<(BTreeSet<String>, BTreeSet<String>)> {
+        let state = self.get_regular_state(instance).await;
+        let mut texts: BTreeSet<String> = state.texts.into_keys().collect();
+        let mut images: BTreeSet<String> = state.images.into_keys().collect();
+        for (element, default) in self.data_defaults.get(instance).await? {
+            if default.text.is_some() {
+                texts.insert(element.clone());
+            }
+            if default.image.is_some() {
+                images.insert(element);
+            }
+        }
+        for source in self.data_sources.get_sources().await?.into_values() {
+            if source.instance == instance {
+                texts.extend(source.fields.into_keys());
+            }
+        }
+        Ok((texts, images))
+    }
+
+    pub async fn get_global_feature_flags(&self) -> anyhow::Result<FeatureFlags> {
+        self.feature_flags.get_global_flags().await
+    }
+
+    pub async fn set_global_feature_flags(&self, flags: FeatureFlags) -> anyhow::Result<()> {
+        self.feature_flags.set_global_flags(flags).await?;
+        for instance in self.websocket_server.get_connected_instances().await {
+            self.broadcast_feature_flags(&instance).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_global_feature_flag(&self, flag: &str, enabled: bool) -> anyhow::Result<()> {
+        self.feature_flags.set_global_flag(flag, enabled).await?;
+        for instance in self.websocket_server.get_connected_instances().await {
+            self.broadcast_feature_flags(&instance).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn get_instance_feature_flags(&self, instance: &str) -> anyhow::Result<FeatureFlags> {
+        self.feature_flags.get_instance_flags(instance).await
+    }
+
+    pub async fn set_instance_feature_flags(
+        &self,
+        instance: &str,
+        flags: FeatureFlags,
+    ) -> anyhow::Result<()> {
+        self.feature_flags
+            .set_instance_flags(instance, flags)
+            .await?;
+        self.broadcast_feature_flags(instance).await
+    }
+
+    pub async fn set_instance_feature_flag(
+        &self,
+        instance: &str,
+        flag: &str,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        self.feature_flags
+            .set_instance_flag(instance, flag, enabled)
+            .await?;
+        self.broadcast_feature_flags(instance).await
+    }
+
+    async fn broadcast_feature_flags(&self, instance: &str) -> anyhow::Result<()> {
+        let flags = self.feature_flags.get_effective_flags(instance).await?;
+        let message = InstanceMessage::SetFeatureFlags {
+            flags: Cow::Owned(flags),
+        };
+        self.websocket_server
+            .send_message_to_instance_clients(instance, &message)
+            .await;
+        Ok(())
+    }
+
+    pub async fn get_asset_versions(&self) -> anyhow::Result<AssetVersions> {
+        self.asset_versions.get_versions().await
+    }
+
+    /// Sets the current version of an asset and sends the new versions to all clients if it
+    /// changed.
+    pub async fn set_asset_version(&self, name: &str, file_name: &str) -> anyhow::Result<()> {
+        self.websocket_server.get_metrics().record_asset_upload();
+        if self.asset_versions.set_version(name, file_name).await? {
+            self.send_asset_versions().await?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the asset and sends the new versions to all clients, so they stop showing it. The
+    /// file is kept if another asset has the same content. Returns `false` if there is no such
+    /// asset.
+    pub async fn delete_asset(&self, name: &str) -> anyhow::Result<bool> {
+        let Some(file_name) = self.asset_versions.remove_version(name).await? else {
+            return Ok(false);
+        };
+        let versions = self.asset_versions.get_versions().await?;
+        if !versions.values().any(|file| *file == file_name) {
+            let path = crate::fs::get_assets_folder(&self.data_folder)?.join(&file_name);
+            if let Err(err) = tokio::fs::remove_file(&path).await {
+                warn!("Could not delete asset file {:?}: {}.", path, err);
+            }
+        }
+        info!("Deleted asset {}.", name);
+        self.send_asset_versions().await?;
+        Ok(true)
+    }
+
+    /// Reports the files of the data folder which are not referenced by anything and, unless it
+    /// is a dry run, removes them.
+    pub async fn collect_garbage(&self, dry_run: bool) -> anyhow::Result<GarbageReport> {
+        // documents on disk are scanned by the collector, states only live in memory
+        let mut referenced = HashSet::new();
+        for instance in self.states.get_instances().await {
+            let state = self.states.get_state(&instance).await;
+            referenced.extend(state.images.into_values().map(|image| image.asset));
+        }
+        for state in self.states.get_all_channel_states().await {
+            referenced.extend(state.images.into_values().map(|image| image.asset));
+        }
+        for active in self.active_overrides.read().await.values() {
+            referenced.extend(
+                active
+                    .held_state
+                    .images
+                    .values()
+                    .map(|image| image.asset.clone()),
+            );
+        }
+        let data_folder = self.data_folder.clone();
+        let now = SystemTime::now();
+        self.run_blocking(move || {
+            let entries = crate::gc::find_garbage(&data_folder, &referenced, now)?;
+            if dry_run {
+                return Ok(GarbageReport::new(true, entries));
+            }
+            let removed = crate::gc::remove_garbage(&data_folder, entries);
+            let report = GarbageReport::new(false, removed);
+            info!(
+                "Removed {} unreferenced files ({} bytes) from the data folder.",
+                report.entries.len(),
+                report.total_bytes
+            );
+            Ok(report)
+        })
+        .await?
+    }
+
+    pub async fn get_asset_info(&self, name: &str) -> anyhow::Result<Option<AssetInfo>> {
+        let versions = self.asset_versions.get_versions().await?;
+        let Some(file_name) = versions.get(name) else {
+            return Ok(None);
+        };
+        let assets_folder = crate::fs::get_assets_folder(&self.data_folder)?;
+        let info = crate::assets::get_asset_info(&assets_folder, name, file_name).await?;
+        Ok(Some(info))
+    }
+
+    pub async fn create_upload(&self, upload: &CreateUploadDto) -> anyhow::Result<UploadStatusDto> {
+        self.uploads.create(upload).await
+    }
+
+    pub async fn get_upload_status(&self, id: &str) -> anyhow::Result<Option<UploadStatusDto>> {
+        self.uploads.get_status(id).await
+    }
+
+    pub async fn append_upload_chunk(
+        &self,
+        id: &str,
+        offset: u64,
+        chunk: &[u8],
+    ) -> anyhow::Result<AppendResult> {
+        self.uploads.append(id, offset, chunk).await
+    }
+
+    /// Completes the upload and makes it the current version of the asset.
+    pub async fn create_received_upload_path(&self) -> anyhow::Result<PathBuf> {
+        self.uploads.create_received_file_path().await
+    }
+
+    pub async fn complete_upload(&self, id: &str) -> anyhow::Result<UploadCompletion> {
+        let assets_folder = crate::fs::get_assets_folder(&self.data_folder)?;
+        let completion = self
+            .uploads
+            .complete(id, &assets_folder, &self.workers)
+            .await?;
+        if let UploadCompletion::Completed { name, file } = &completion {
+            self.set_asset_version(name, file).await?;
+        }
+        Ok(completion)
+    }
+
+    pub async fn delete_upload(&self, id: &str) -> anyhow::Result<bool> {
+        self.uploads.remove(id).await
+    }
+
+    async fn send_asset_versions(&self) -> anyhow::Result<()> {
+        let message = InstanceMessage::SetAssetVersions {
+            versions: Cow::Owned(self.asset_versions.get_versions().await?),
+        };
+        for instance in self.websocket_server.get_connected_instances().await {
+            self.websocket_server
+                .send_message_to_instance_clients(&instance, &message)
+                .await;
+        }
+        Ok(())
+    }
+
+    pub async fn get_pinned_resources(&self) -> anyhow::Result<PinnedResources> {
+        self.resources.get_resources().await
+    }
+
+    pub async fn set_pinned_resource(
+        &self,
+        url: &str,
+        resource: PinnedResource,
+    ) -> anyhow::Result<()> {
+        self.resources.set_resource(url, resource).await
+    }
+
+    pub async fn delete_pinned_resource(&self, url: &str) -> anyhow::Result<bool> {
+        self.resources.delete_resource(url).await
+    }
+
+    pub async fn get_secret_names(&self, instance: &str) -> anyhow::Result<Vec<String>> {
+        self.secrets.get_secret_names(instance).await
+    }
+
+    pub async fn set_secret(&self, instance: &str, name: &str, value: &str) -> anyhow::Result<()> {
+        self.secrets.set_secret(instance, name, value).await
+    }
+
+    pub async fn delete_secret(&self, instance: &str, name: &str) -> anyhow::Result<bool> {
+        self.secrets.delete_secret(instance, name).await
+    }
+
+    /// Returns a link that grants read-only access to the instance for the given duration.
+    pub async fn create_share_link(
+        &self,
+        instance: &str,
+        valid_for: chrono::Duration,
+    ) -> anyhow::Result<ShareLink> {
+        let claims = ShareClaims {
+            instance: instance.to_owned(),
+            expires: Utc::now() + valid_for,
+        };
+        let token = crate::share::create_token(&self.secrets, &claims).await?;
+        Ok(ShareLink {
+            url: format!("/share/{token}"),
+            token,
+            expires: claims.expires,
+        })
+    }
+
+    /// Returns the claims of the share link if it is valid and did not expire.
+    pub async fn verify_share_link(&self, token: &str) -> anyhow::Result<Option<ShareClaims>> {
+        crate::share::verify_token(&self.secrets, token, Utc::now()).await
+    }
+
+    /// Returns a signed URL of the asset, which is given by its name or file, or `None` if the
+    /// asset does not exist.
+    pub async fn create_signed_asset_url(
+        &self,
+        asset: &str,
+        valid_seconds: i64,
+    ) -> anyhow::Result<Option<SignedAssetUrl>> {
+        let file = match self.get_asset_versions().await?.get(asset) {
+            Some(file) => file.clone(),
+            None if crate::assets::signing::is_valid_asset_file(asset)
+                && crate::fs::get_assets_folder(&self.data_folder)?
+                    .join(asset)
+                    .is_file() =>
+            {
+                asset.to_owned()
+            }
+            None => return Ok(None),
+        };
+        let expires = crate::assets::signing::get_expiry(
+            Utc::now().timestamp(),
+            valid_seconds,
+            self.asset_signing.window_seconds,
+        );
+        crate::assets::signing::sign_url(&self.secrets, &self.asset_signing, &file, expires)
+            .await
+            .map(Some)
+    }
+
+    /// Returns whether the signed URL of the asset file is valid and did not expire.
+    pub async fn verify_signed_asset_url(
+        &self,
+        file: &str,
+        signature: &AssetSignature,
+    ) -> anyhow::Result<bool> {
+        crate::assets::signing::verify_signature(
+            &self.secrets,
+            file,
+            signature,
+            Utc::now().timestamp(),
+        )
+        .await
+    }
+
+    pub async fn fetch_url(&self, url: &str) -> anyhow::Result<FetchedResource> {
+        let configuration = self.get_configuration();
+        let host = reqwest::Url::parse(url)?
+            .host_str()
+            .unwrap_or_default()
+            .to_owned();
+        self.integrations
+            .execute(&format!("fetch:{host}"), || {
+                crate::outbound::fetch(
+                    configuration.proxy.as_ref(),
+                    configuration.allow_private_network_fetch,
+                    url,
+                )
+            })
+            .await
+    }
+
+    /// Posts the JSON body to a webhook, e.g. of a watchdog recovery action.
+    pub async fn post_webhook(&self, url: &str, body: &serde_json::Value) -> anyhow::Result<()> {
+        crate::webhooks::post_webhook(&self.integrations, &self.http_client, url, body).await
+    }
+
+    pub async fn get_stats(&self) -> ServerStats {
+        ServerStats {
+            uptime_seconds: self.started_at.elapsed().as_secs(),
+            connected_clients: self.get_client_count().await,
+            buffered_bytes: self.websocket_server.get_buffered_bytes().await,
+            integrations: self.integrations.get_health().await,
+            synthetic_monitors: self.monitors.get_statuses().await,
+            workers: self.workers.get_stats(),
+            command_latency: self.command_latency.get_stats().await,
+        }
+    }
+
+    pub fn get_configuration(&self) -> Arc<ZagreusServerConfig> {
+        self.configuration.borrow().clone()
+    }
+
+    /// Returns a receiver which is notified when the configuration was reloaded.
+    pub fn subscribe_configuration(&self) -> watch::Receiver<Arc<ZagreusServerConfig>> {
+        self.configuration.subscribe()
+    }
+
+    pub fn set_configuration(&self, configuration: ZagreusServerConfig) {
+        self.configuration.send_replace(Arc::new(configuration));
+    }
+
+    pub fn get_metrics(&self) -> Arc<Metrics> {
+        self.websocket_server.get_metrics()
+    }
+
+    /// Returns the metrics in the Prometheus text format.
+    pub async fn render_metrics(&self) -> String {
+        let clients = self.websocket_server.get_client_counts().await;
+        self.websocket_server.get_metrics().render(&clients)
+    }
+
+    /// Runs CPU-heavy work on the bounded worker pool.
+    /// Returns the files renderers of the instance need with their sizes and hashes, so caches
+    /// can be warmed and checked for completeness before a show.
+    pub async fn get_prefetch_manifest(
+        &self,
+        instance: &str,
+        runtime_version: Option<&str>,
+    ) -> anyhow::Result<PrefetchManifest> {
+        let files = crate::prefetch::get_prefetch_files(
+            &self.data_folder,
+            runtime_version,
+            &self.get_asset_versions().await?,
+            &self.get_pinned_resources().await?,
+        )?;
+        let hashed_files = self
+            .run_blocking(move || {
+                files
+                    .into_iter()
+                    .map(|file| {
+                        let hash = crate::resources::compute_file_integrity(&file.path);
+                        (file, hash)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .await?;
+
+        let mut manifest = PrefetchManifest {
+            instance: instance.to_owned(),
+            generated: Utc::now(),
+            entries: Vec::new(),
+            missing: Vec::new(),
+        };
+        for (file, hash) in hashed_files {
+            match hash {
+                Ok((size, integrity)) => manifest.entries.push(PrefetchEntry {
+                    url: file.url,
+                    kind: file.kind,
+                    source: file.source,
+                    size,
+                    integrity,
+                }),
+                Err(err) => {
+                    debug!(
+                        "Could not hash {:?} for prefetch manifest: {}.",
+                        file.path, err
+                    );
+                    manifest.missing.push(file.url);
+                }
+            }
+        }
+        Ok(manifest)
+    }
+
+    pub async fn run_blocking<T, F>(&self, task: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        self.workers.run(task).await
+    }
+
+    pub fn get_monitor_token(&self) -> &str {
+        self.monitors.get_token()
+    }
+
+    /// Whether the token identifies a synthetic client of this server.
+    pub fn is_monitor_token(&self, token: &str) -> bool {
+        self.monitors.is_token(token)
+    }
+
+    pub async fn set_monitor_status(&self, instance: &str, status: MonitorStatus) {
+        self.monitors.set_status(instance, status).await;
+    }
+
+    pub async fn get_instance_clients(&self, instance: &str) -> Vec<ConnectedClient> {
+        self.websocket_server.get_instance_clients(instance).await
+    }
+
+    pub async fn get_clients(&self) -> Vec<ConnectedClient> {
+        self.websocket_server.get_clients().await
+    }
+
+    pub async fn has_responsive_client(
+        &self,
+        instance: &str,
+        channel: &str,
+        max_silence: Duration,
+    ) -> bool {
+        self.websocket_server
+            .has_responsive_client(instance, channel, max_silence)
+            .await
+    }
+
+    pub async fn switch_client_channel(
+        &self,
+        instance: &str,
+        from_channel: &str,
+        to_channel: &str,
+        max_silence: Duration,
+    ) -> Option<usize> {
+        self.websocket_server
+            .switch_client_channel(instance, from_channel, to_channel, max_silence)
+            .await
+    }
+
+    pub async fn get_client_count(&self) -> usize {
+        self.websocket_server.get_client_count().await
+    }
+
+    pub async fn get_instance_client_count(&self, instance: &str) -> usize {
+        self.websocket_server
+            .get_instance_client_count(instance)
+            .await
+    }
+
+    pub async fn get_as_run_records(
+        &self,
+        instance: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<AsRunRecord>> {
+        self.websocket_server
+            .get_as_run_records(instance, from, to)
+            .await
+    }
+
+    pub async fn get_client_errors(&self, instance: &str) -> Vec<ClientErrorRecord> {
+        self.websocket_server.get_client_errors(instance).await
+    }
+
+    pub async fn get_error_snapshot_html(&self, instance: &str, error_id: u64) -> Option<String> {
+        self.websocket_server
+            .get_error_snapshot_html(instance, error_id)
+            .await
+    }
+
+    pub async fn poll_events(
+        &self,
+        instance: &str,
+        cursor: Option<u64>,
+        timeout: Duration,
+    ) -> EventBatch {
+        self.websocket_server
+            .poll_events(instance, cursor, timeout)
+            .await
+    }
+
+    pub async fn poll_all_events(&self, cursor: Option<u64>, timeout: Duration) -> EventBatch {
+        self.websocket_server.poll_all_events(cursor, timeout).await
+    }
+
+    pub async fn join_presence(&self, instance: &str, operator: &str) {
+        self.presence.join(instance, operator).await;
+    }
+
+    /// Removes a control panel of the operator. The lock of the operator is released when its
+    /// last panel disconnected, so locks of operators who left do not block the instance.
+    pub async fn leave_presence(&self, instance: &str, operator: &str) {
+        if let Some(lock) = self.presence.leave(instance, operator).await {
+            info!(
+                "Released lock of operator {} on instance {} after disconnect.",
+                lock.operator, instance
+            );
+            let event = EventKind::LockReleased {
+                operator: lock.operator,
+            };
+            self.record_event(instance, event).await;
+        }
+    }
+
+    pub async fn get_presence(&self, instance: &str) -> InstancePresence {
+        self.presence.get_presence(instance).await
+    }
+
+    pub fn subscribe_presence_changes(&self) -> tokio::sync::broadcast::Receiver<String> {
+        self.presence.subscribe_changes()
+    }
+
+    pub async fn lock_instance(&self, instance: &str, operator: &str, force: bool) -> LockResult {
+        let result = self.presence.lock(instance, operator, force).await;
+        let event = match &result {
+            LockResult::Acquired(lock) => EventKind::LockAcquired {
+                operator: lock.operator.clone(),
+                overridden: None,
+            },
+            LockResult::Overridden { lock, previous } => {
+                warn!(
+                    "Operator {} overrode the lock of operator {} on instance {}.",
+                    lock.operator, previous.operator, instance
+                );
+                EventKind::LockAcquired {
+                    operator: lock.operator.clone(),
+                    overridden: Some(previous.operator.clone()),
+                }
+            }
+            LockResult::Locked(_) => return result,
+        };
+        self.record_event(instance, event).await;
+        result
+    }
+
+    pub async fn release_instance_lock(&self, instance: &str, operator: &str) -> ReleaseResult {
+        let result = self.presence.release(instance, operator).await;
+        if let ReleaseResult::Released = result {
+            let event = EventKind::LockReleased {
+                operator: operator.to_owned(),
+            };
+            self.record_event(instance, event).await;
+        }
+        result
+    }
+
+    /// Returns the notes of the show day, of the current show day if not set.
+    pub async fn get_notes(&self, day: Option<NaiveDate>) -> anyhow::Result<Vec<OperatorNote>> {
+        self.notes
+            .get_notes(day.unwrap_or_else(crate::notes::get_show_day))
+            .await
+    }
+
+    /// Adds the note to the current show day and sends it to all controllers.
+    pub async fn add_note(&self, note: NoteDto) -> anyhow::Result<OperatorNote> {
+        self.notes
+            .add_note(crate::notes::get_show_day(), note)
+            .await
+    }
+
+    /// Pins or unpins a note of the current show day and sends it to all controllers.
+    pub async fn pin_note(&self, id: u64, pinned: bool) -> anyhow::Result<Option<OperatorNote>> {
+        self.notes
+            .set_pinned(crate::notes::get_show_day(), id, pinned)
+            .await
+    }
+
+    pub fn subscribe_note_changes(&self) -> tokio::sync::broadcast::Receiver<OperatorNote> {
+        self.notes.subscribe_changes()
+    }
+
+    pub async fn record_event(&self, instance: &str, kind: EventKind) {
+        self.websocket_server.record_event(instance, kind).await
+    }
+
+    pub async fn start_session(&self, name: &str) -> anyhow::Result<Option<ActiveSession>> {
+        self.sessions.start(name).await
+    }
+
+    pub async fn stop_session(&self) -> anyhow::Result<Option<ActiveSession>> {
+        self.sessions.stop().await
+    }
+
+    pub async fn get_active_session(&self) -> Option<ActiveSession> {
+        self.sessions.get_active().await
+    }
+
+    pub async fn is_recording_session(&self) -> bool {
+        self.sessions.is_recording().await
+    }
+
+    pub async fn record_session_entry(&self, entry: SessionEntry) {
+        self.sessions.record(entry).await
+    }
+
+    pub async fn get_session_timeline(
+        &self,
+        name: &str,
+    ) -> anyhow::Result<Option<SessionTimeline>> {
+        self.sessions.get_timeline(name).await
+    }
+
+    pub async fn get_analytics(&self) -> AnalyticsReport {
+        self.analytics.get_report().await
+    }
+
+    pub async fn get_audit_entries(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Vec<AuditEntry> {
+        self.audit_log.get_entries(from, to).await
+    }
 }
@@ -0,0 +1,182 @@
+use std::backtrace::Backtrace;
+use std::collections::BTreeMap;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::ZagreusServerConfig;
+use crate::controller::ServerController;
+use crate::state::InstanceState;
+use crate::websocket::server::ConnectedClient;
+
+const CRASH_FOLDER_NAME: &str = "crashes";
+/// Maximum time the panicking thread waits for the crash webhook.
+const CRASH_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub timestamp: DateTime<Utc>,
+    pub version: &'static str,
+    pub thread: String,
+    pub message: String,
+    pub location: Option<String>,
+    pub backtrace: String,
+    pub state: Option<CrashState>,
+}
+
+/// In-memory state of the server at the time of the crash. Parts whose lock was held while the
+/// server panicked are missing, since the panic hook must not wait for them.
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashState {
+    /// Connected clients per instance.
+    pub clients: Option<BTreeMap<String, Vec<ConnectedClient>>>,
+    pub states: Option<BTreeMap<String, InstanceState>>,
+    /// Index of the rundown page on air per instance.
+    pub rundown_positions: Option<BTreeMap<String, usize>>,
+}
+
+/// Replaces the default panic hook, so panics are logged with their backtrace and the state of
+/// the server is dumped to a crash file in the data folder. If a crash webhook is configured, the
+/// crash report is posted to it as well.
+pub fn install_panic_hook(controller: &Arc<ServerController>, configuration: &ZagreusServerConfig) {
+    let controller = Arc::downgrade(controller);
+    let data_folder = configuration.data_folder.clone();
+    let webhook =
+        configuration.crash_webhook_url.as_ref().and_then(
+            |url| match crate::outbound::build_client(configuration.proxy.as_ref()) {
+                Ok(client) => Some((client, url.clone())),
+                Err(err) => {
+                    error!("Could not configure crash webhook: {:#}.", err);
+                    None
+                }
+            },
+        );
+
+    std::panic::set_hook(Box::new(move |info| {
+        let report = create_crash_report(info, &controller);
+        error!(
+            "Server panicked in thread {} at {}: {}\n{}",
+            report.thread,
+            report.location.as_deref().unwrap_or("unknown location"),
+            report.message,
+            report.backtrace
+        );
+        match write_crash_file(&data_folder, &report) {
+            Ok(path) => error!("Stored crash report in {:?}.", path),
+            Err(err) => error!("Could not store crash report: {:#}.", err),
+        }
+        if let Some((client, url)) = &webhook {
+            send_crash_webhook(client.clone(), url.clone(), &report);
+        }
+    }));
+}
+
+fn create_crash_report(info: &PanicHookInfo, controller: &Weak<ServerController>) -> CrashReport {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("unknown panic"));
+    CrashReport {
+        timestamp: Utc::now(),
+        version: crate::ZAGREUS_VERSION,
+        thread: std::thread::current()
+            .name()
+            .unwrap_or("unnamed")
+            .to_owned(),
+        message,
+        location: info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line())),
+        backtrace: Backtrace::force_capture().to_string(),
+        state: controller
+            .upgrade()
+            .map(|controller| controller.get_crash_state()),
+    }
+}
+
+fn write_crash_file(data_folder: &Path, report: &CrashReport) -> anyhow::Result<PathBuf> {
+    let crash_folder = data_folder.join(CRASH_FOLDER_NAME);
+    std::fs::create_dir_all(&crash_folder)?;
+    let file_name = format!(
+        "crash-{}.json",
+        report.timestamp.format("%Y%m%dT%H%M%S%.3fZ")
+    );
+    let path = crash_folder.join(file_name);
+    std::fs::write(&path, serde_json::to_vec_pretty(report)?)?;
+    Ok(path)
+}
+
+/// Posts the crash report from a separate thread with its own runtime, since the runtime of the
+/// panicking thread may be wedged. Waits at most until the webhook timeout.
+fn send_crash_webhook(client: reqwest::Client, url: String, report: &CrashReport) {
+    let body = match serde_json::to_vec(report) {
+        Ok(body) => body,
+        Err(err) => {
+            error!("Could not serialize crash report: {}.", err);
+            return;
+        }
+    };
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let result = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(anyhow::Error::from)
+            .and_then(|runtime| {
+                runtime.block_on(async {
+                    client
+                        .post(&url)
+                        .header(reqwest::header::CONTENT_TYPE, "application/json")
+                        .body(body)
+                        .send()
+                        .await?
+                        .error_for_status()?;
+                    Ok(())
+                })
+            });
+        let _ = sender.send(result);
+    });
+    match receiver.recv_timeout(CRASH_WEBHOOK_TIMEOUT) {
+        Ok(Ok(())) => info!("Sent crash report to webhook."),
+        Ok(Err(err)) => error!("Could not send crash report to webhook: {:#}.", err),
+        Err(_) => error!("Crash webhook did not answer in time."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_file_is_written() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let report = CrashReport {
+            timestamp: Utc::now(),
+            version: crate::ZAGREUS_VERSION,
+            thread: String::from("main"),
+            message: String::from("index out of bounds"),
+            location: Some(String::from("src/rundown/mod.rs:42")),
+            backtrace: String::new(),
+            state: Some(CrashState {
+                rundown_positions: Some(BTreeMap::from([(String::from("scoreboard"), 2)])),
+                ..CrashState::default()
+            }),
+        };
+
+        let crash_file = write_crash_file(&path, &report).unwrap();
+        let stored: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(crash_file).unwrap()).unwrap();
+        assert_eq!("index out of bounds", stored["message"]);
+        assert_eq!(2, stored["state"]["rundownPositions"]["scoreboard"]);
+        assert!(stored["state"]["clients"].is_null());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
@@ -1,3 +1,6 @@
+use schemars::JsonSchema;
+
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AnimationConfig {
@@ -5,20 +8,20 @@ pub struct AnimationConfig {
     pub sequences: Vec<AnimationSequence>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 pub struct AnimationSequence {
     name: String,
     steps: Vec<AnimationStep>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 pub struct AnimationStep {
     start: u16,
     duration: u16,
     animations: Vec<Animation>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Animation {
     id: String,
@@ -27,7 +30,7 @@ pub struct Animation {
     direction: AnimationDirection,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "kebab-case")]
 pub enum AnimationDirection {
     Normal,
@@ -36,6 +39,7 @@ pub enum AnimationDirection {
     AlternateReverse,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OnLoadConfig {
@@ -1,6 +1 @@
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(rename_all = "kebab-case")]
-pub enum AssetSource {
-    Zagreus,
-    Template,
-}
+pub use zagreus_client::dto::{AssetReference, AssetSource};
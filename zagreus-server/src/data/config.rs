@@ -1,3 +1,6 @@
+use schemars::JsonSchema;
+
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TemplateConfig {
@@ -6,31 +9,32 @@ pub struct TemplateConfig {
     pub height: u16,
 }
 
+#[allow(dead_code)]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct TemplateElements {
     pub elements: Vec<TemplateElement>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 pub struct TemplateElement {
     id: String,
     config: Option<ElementConfig>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 pub struct ElementConfig {
     id: String,
     align: AlignmentConfig,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 pub struct AlignmentConfig {
     horizontal: HorizontalAlignment,
     vertical: VerticalAlignment,
     with: String,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum HorizontalAlignment {
     Center,
@@ -38,7 +42,7 @@ pub enum HorizontalAlignment {
     Right,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum VerticalAlignment {
     Center,
@@ -1,3 +1,5 @@
 pub mod animation;
 pub mod asset;
 pub mod config;
+pub mod operation;
+pub mod sequence;
@@ -0,0 +1,46 @@
+use crate::websocket::message::InstanceMessage;
+
+pub use zagreus_client::dto::{Operation, OperationKind};
+
+/// Converts the operation to the message sent to the clients.
+pub fn to_operation_message(operation: &Operation) -> InstanceMessage<'_> {
+    match operation {
+        Operation::SetText {
+            id,
+            text,
+            tween_duration,
+        } => InstanceMessage::SetText {
+            id,
+            text,
+            tween_duration: *tween_duration,
+        },
+        Operation::AddClass { id, class } => InstanceMessage::AddClass { id, class },
+        Operation::RemoveClass { id, class } => InstanceMessage::RemoveClass { id, class },
+        Operation::SetImageSource {
+            id,
+            asset,
+            asset_source,
+        } => InstanceMessage::SetImageSource {
+            id,
+            asset,
+            asset_source: asset_source.clone(),
+        },
+        Operation::ExecuteAnimation {
+            animation_sequence,
+            execute_at,
+        } => InstanceMessage::ExecuteAnimation {
+            animation_sequence,
+            execute_at: *execute_at,
+        },
+    }
+}
+
+/// Returns the message of the operations, a batch if there is more than one operation.
+pub fn to_message(operations: &[Operation]) -> InstanceMessage<'_> {
+    match operations {
+        [operation] => to_operation_message(operation),
+        operations => InstanceMessage::Batch {
+            operations: operations.iter().map(to_operation_message).collect(),
+        },
+    }
+}
@@ -0,0 +1,92 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::controller::ServerController;
+
+pub use zagreus_client::dto::SequenceStep;
+
+/// Returns the offsets of the steps from the start of the sequence.
+fn get_offsets(steps: &[SequenceStep]) -> Vec<Duration> {
+    steps
+        .iter()
+        .scan(Duration::ZERO, |offset, step| {
+            *offset += Duration::from_millis(step.delay_ms);
+            Some(*offset)
+        })
+        .collect()
+}
+
+/// Executes the steps of a sequence (e.g. animate in, update a text, animate out) on the server,
+/// so clients do not have to time the steps themselves. Steps are scheduled relative to the
+/// start, so slow steps do not delay the following ones. A sequence sent to a single client stops
+/// once the client disconnects.
+pub async fn run_sequence(
+    controller: Arc<ServerController>,
+    instance: String,
+    channel: Option<String>,
+    client_id: Option<usize>,
+    steps: Vec<SequenceStep>,
+) {
+    let started = Instant::now();
+    let offsets = get_offsets(&steps);
+    for (step, offset) in steps.into_iter().zip(offsets) {
+        tokio::time::sleep_until(started + offset).await;
+        let operations = vec![step.operation];
+        match client_id {
+            Some(client_id) => {
+                if !controller
+                    .send_client_operations(&instance, client_id, operations)
+                    .await
+                {
+                    debug!(
+                        "Client {} of instance {} disconnected during sequence.",
+                        client_id, instance
+                    );
+                    return;
+                }
+            }
+            None => {
+                controller
+                    .submit_channel_operations(&instance, channel.as_deref(), operations)
+                    .await;
+            }
+        }
+    }
+    debug!("Sequence of instance {} finished.", instance);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::operation::Operation;
+
+    #[test]
+    fn test_parse_sequence() {
+        let steps: Vec<SequenceStep> = serde_json::from_str(
+            r#"[
+                {"operation": "executeAnimation", "animationSequence": "LowerThirdIn"},
+                {"delayMs": 500, "operation": "setText", "id": "Name", "text": "Jane Doe"},
+                {"delayMs": 5000, "operation": "executeAnimation", "animationSequence": "LowerThirdOut"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Operation::SetText {
+                id: String::from("Name"),
+                text: String::from("Jane Doe"),
+                tween_duration: None,
+            },
+            steps[1].operation
+        );
+        assert_eq!(
+            vec![
+                Duration::ZERO,
+                Duration::from_millis(500),
+                Duration::from_millis(5500)
+            ],
+            get_offsets(&steps)
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::{DataMapConfig, DataMapProtocol};
+use crate::controller::ServerController;
+
+/// Interval in which sending entries is retried after the receiver was not reachable.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+enum DataMapConnection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+}
+
+impl DataMapConnection {
+    async fn connect(
+        protocol: DataMapProtocol,
+        address: &str,
+    ) -> std::io::Result<DataMapConnection> {
+        match protocol {
+            DataMapProtocol::Udp => {
+                let socket = UdpSocket::bind("0.0.0.0:0").await?;
+                socket.connect(address).await?;
+                Ok(DataMapConnection::Udp(socket))
+            }
+            DataMapProtocol::Tcp => Ok(DataMapConnection::Tcp(TcpStream::connect(address).await?)),
+        }
+    }
+
+    async fn send(&mut self, data: &[u8]) -> std::io::Result<()> {
+        match self {
+            DataMapConnection::Udp(socket) => socket.send(data).await.map(|_| ()),
+            DataMapConnection::Tcp(stream) => stream.write_all(data).await,
+        }
+    }
+}
+
+/// Forwards the texts of an instance to a data map receiver whenever they change. Every entry is
+/// sent as `key|value` terminated by a null byte, the shared memory format of virtual set
+/// systems. All entries are sent again after the receiver was not reachable.
+pub async fn run_data_map_bridge(controller: Arc<ServerController>, configuration: DataMapConfig) {
+    info!(
+        "Forwarding texts of instance {} to data map at {} ({:?}).",
+        configuration.instance, configuration.address, configuration.protocol
+    );
+    let mut changes = controller.subscribe_state_changes();
+    let mut connection: Option<DataMapConnection> = None;
+    let mut sent_entries: BTreeMap<String, String> = BTreeMap::new();
+    let mut reachable = true;
+
+    loop {
+        match controller.get_state(&configuration.instance).await {
+            Ok(state) => {
+                let entries = get_changed_entries(&configuration, &state.texts, &sent_entries);
+                if !entries.is_empty() {
+                    match send_entries(&configuration, &mut connection, &entries).await {
+                        Ok(()) => {
+                            if !reachable {
+                                info!("Data map at {} is reachable again.", configuration.address);
+                                reachable = true;
+                            }
+                            sent_entries.extend(entries);
+                        }
+                        Err(err) => {
+                            if reachable {
+                                warn!(
+                                    "Could not send data to data map at {}: {}.",
+                                    configuration.address, err
+                                );
+                                reachable = false;
+                            }
+                            connection = None;
+                            sent_entries.clear();
+                        }
+                    }
+                }
+            }
+            Err(err) => error!(
+                "Could not get state of instance {}: {}.",
+                configuration.instance, err
+            ),
+        }
+
+        if !wait_for_change(&mut changes, &configuration.instance, reachable).await {
+            break;
+        }
+    }
+}
+
+/// Waits until the texts of the instance may have changed. Returns false if no more changes
+/// will be notified.
+async fn wait_for_change(
+    changes: &mut tokio::sync::broadcast::Receiver<String>,
+    instance: &str,
+    reachable: bool,
+) -> bool {
+    loop {
+        let change = if reachable {
+            changes.recv().await
+        } else {
+            match tokio::time::timeout(RETRY_INTERVAL, changes.recv()).await {
+                Ok(change) => change,
+                Err(_) => return true,
+            }
+        };
+        match change {
+            Ok(changed_instance) if changed_instance == instance => return true,
+            Ok(_) => {}
+            Err(RecvError::Lagged(_)) => return true,
+            Err(RecvError::Closed) => return false,
+        }
+    }
+}
+
+async fn send_entries(
+    configuration: &DataMapConfig,
+    connection: &mut Option<DataMapConnection>,
+    entries: &BTreeMap<String, String>,
+) -> std::io::Result<()> {
+    if connection.is_none() {
+        *connection =
+            Some(DataMapConnection::connect(configuration.protocol, &configuration.address).await?);
+    }
+    if let Some(connection) = connection {
+        for (key, value) in entries {
+            connection.send(&encode_entry(key, value)).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the mapped entries whose value differs from the last sent value.
+fn get_changed_entries(
+    configuration: &DataMapConfig,
+    texts: &BTreeMap<String, String>,
+    sent_entries: &BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    texts
+        .iter()
+        .filter_map(|(id, text)| {
+            if configuration.keys.is_empty() {
+                Some((id.clone(), text.clone()))
+            } else {
+                configuration
+                    .keys
+                    .get(id)
+                    .map(|key| (key.clone(), text.clone()))
+            }
+        })
+        .filter(|(key, value)| sent_entries.get(key) != Some(value))
+        .collect()
+}
+
+fn encode_entry(key: &str, value: &str) -> Vec<u8> {
+    // the null byte terminates an entry, so it cannot be part of it
+    format!("{}|{}\0", key.replace('\0', ""), value.replace('\0', "")).into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_changed_entries() {
+        let texts = BTreeMap::from([
+            (String::from("HomeScore"), String::from("2")),
+            (String::from("AwayScore"), String::from("0")),
+            (String::from("Clock"), String::from("12:00")),
+        ]);
+        let mut configuration = DataMapConfig {
+            instance: String::from("scoreboard"),
+            protocol: DataMapProtocol::Udp,
+            address: String::from("127.0.0.1:6100"),
+            keys: BTreeMap::from([
+                (String::from("HomeScore"), String::from("Score.Home")),
+                (String::from("AwayScore"), String::from("Score.Away")),
+            ]),
+        };
+        let sent_entries = BTreeMap::from([(String::from("Score.Away"), String::from("0"))]);
+
+        let entries = get_changed_entries(&configuration, &texts, &sent_entries);
+        assert_eq!(
+            BTreeMap::from([(String::from("Score.Home"), String::from("2"))]),
+            entries
+        );
+
+        configuration.keys.clear();
+        assert_eq!(
+            3,
+            get_changed_entries(&configuration, &texts, &BTreeMap::new()).len()
+        );
+        assert_eq!(b"Score.Home|2\0".to_vec(), encode_entry("Score.Home", "2"));
+    }
+}
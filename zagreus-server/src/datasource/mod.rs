@@ -0,0 +1,482 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use tokio::sync::{watch, Mutex, RwLock};
+use tokio::task::JoinHandle;
+
+use crate::controller::ServerController;
+use crate::datasource::google::GoogleTokenCache;
+use crate::store::{load_document, store_document};
+
+pub mod google;
+
+const DATA_SOURCES_FILE_NAME: &str = "datasources.json";
+/// Header values with this prefix reference a secret of the instance, e.g. `secret:feed-key`.
+const SECRET_REFERENCE_PREFIX: &str = "secret:";
+/// Maximum size in bytes of the document of a data source.
+const MAX_DOCUMENT_SIZE: usize = 16 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DataFormat {
+    Json,
+    /// Rows are objects with the values of the header row as keys.
+    Csv,
+    /// Cells of a Google Sheets spreadsheet, read with the Google Sheets API.
+    #[serde(rename = "googleSheets")]
+    GoogleSheets,
+}
+
+/// External source of the texts of an instance, e.g. the live scores of a sports feed, which is
+/// polled by the server.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSource {
+    pub instance: String,
+    pub format: DataFormat,
+    /// URL which is polled over HTTP(S), a `file://` URL of a file in the data source folder or
+    /// the URL of a Google Sheets spreadsheet.
+    pub url: String,
+    /// Headers of the HTTP requests, e.g. an API key. Values can reference a secret of the
+    /// instance with `secret:<name>`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// Name of the instance secret with the API key or service account key of a Google Sheets
+    /// data source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials: Option<String>,
+    #[serde(default = "get_default_interval_seconds")]
+    pub interval_seconds: u64,
+    /// JSON pointer of the value per element id, e.g. `/home/score` or `/0/Name` for the first
+    /// row of a CSV file. For Google Sheets, the cell in A1 notation, e.g. `Guests!B2`.
+    pub fields: BTreeMap<String, String>,
+}
+
+fn get_default_interval_seconds() -> u64 {
+    5
+}
+
+/// Data sources by name.
+pub type DataSources = BTreeMap<String, DataSource>;
+
+/// Result of the last poll of a data source.
+#[derive(Serialize, Clone, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DataSourceStatus {
+    pub last_poll: Option<DateTime<Utc>>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    /// Number of texts which changed with the last successful poll.
+    pub updated_texts: usize,
+    /// Fields whose pointer did not resolve to a text with the last successful poll.
+    pub missing_fields: Vec<String>,
+}
+
+/// Stores the data sources of all instances and the result of their last poll.
+pub struct DataSourceStore {
+    sources_path: PathBuf,
+    sources: RwLock<Option<DataSources>>,
+    /// Serializes changes, so concurrent changes do not overwrite each other.
+    update_lock: Mutex<()>,
+    status: RwLock<HashMap<String, DataSourceStatus>>,
+    changes: watch::Sender<()>,
+    google_tokens: GoogleTokenCache,
+}
+
+impl DataSourceStore {
+    pub fn new(data_folder: PathBuf) -> DataSourceStore {
+        DataSourceStore {
+            sources_path: data_folder.join(DATA_SOURCES_FILE_NAME),
+            sources: RwLock::new(None),
+            update_lock: Mutex::new(()),
+            status: RwLock::new(HashMap::new()),
+            changes: watch::channel(()).0,
+            google_tokens: GoogleTokenCache::default(),
+        }
+    }
+
+    pub async fn get_sources(&self) -> anyhow::Result<DataSources> {
+        if let Some(sources) = self.sources.read().await.as_ref() {
+            return Ok(sources.clone());
+        }
+        let sources: DataSources = load_document(&self.sources_path).await?;
+        *self.sources.write().await = Some(sources.clone());
+        Ok(sources)
+    }
+
+    pub async fn set_source(&self, name: &str, source: DataSource) -> anyhow::Result<()> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut sources = self.get_sources().await?;
+        sources.insert(name.to_owned(), source);
+        self.store_sources(sources).await
+    }
+
+    /// Returns whether the data source existed.
+    pub async fn delete_source(&self, name: &str) -> anyhow::Result<bool> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut sources = self.get_sources().await?;
+        if sources.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.status.write().await.remove(name);
+        self.store_sources(sources).await?;
+        Ok(true)
+    }
+
+    pub async fn get_status(&self, name: &str) -> DataSourceStatus {
+        self.status
+            .read()
+            .await
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub async fn set_status(&self, name: &str, status: DataSourceStatus) {
+        self.status.write().await.insert(name.to_owned(), status);
+    }
+
+    /// Notifies whenever a data source was added, changed or deleted.
+    pub fn subscribe_changes(&self) -> watch::Receiver<()> {
+        self.changes.subscribe()
+    }
+
+    pub fn get_google_tokens(&self) -> &GoogleTokenCache {
+        &self.google_tokens
+    }
+
+    async fn store_sources(&self, sources: DataSources) -> anyhow::Result<()> {
+        store_document(&self.sources_path, &sources).await?;
+        *self.sources.write().await = Some(sources);
+        self.changes.send_replace(());
+        Ok(())
+    }
+}
+
+/// Returns an error message if the data source is invalid. Local files must be inside the data
+/// source folder.
+pub fn validate_data_source(source: &DataSource, file_folder: Option<&Path>) -> Option<String> {
+    if !crate::fs::is_valid_instance_name(&source.instance) {
+        return Some(format!("Invalid instance name: {}.", source.instance));
+    }
+    let valid_url = reqwest::Url::parse(&source.url).is_ok_and(|url| match url.scheme() {
+        "http" | "https" => true,
+        "file" => url
+            .to_file_path()
+            .is_ok_and(|path| file_folder.is_some_and(|folder| path.starts_with(folder))),
+        _ => false,
+    });
+    if !valid_url {
+        return Some(format!("Invalid URL: {}.", source.url));
+    }
+    if source.interval_seconds == 0 {
+        return Some(String::from("The interval must be at least one second."));
+    }
+    if source.fields.is_empty() {
+        return Some(String::from("The data source has no fields."));
+    }
+    if source.format == DataFormat::GoogleSheets {
+        return validate_google_sheet(source);
+    }
+    if let Some((element, _)) = source
+        .fields
+        .iter()
+        .find(|(_, pointer)| !pointer.is_empty() && !pointer.starts_with('/'))
+    {
+        return Some(format!(
+            "The pointer of element {element} must start with a slash."
+        ));
+    }
+    let invalid_secret = source.headers.values().find_map(|value| {
+        get_secret_reference(value).filter(|secret| !crate::secrets::is_valid_secret_name(secret))
+    });
+    if let Some(secret) = invalid_secret {
+        return Some(format!("Invalid secret name: {secret}."));
+    }
+    None
+}
+
+fn validate_google_sheet(source: &DataSource) -> Option<String> {
+    if google::get_spreadsheet_id(&source.url).is_none() {
+        return Some(format!("Invalid Google Sheets URL: {}.", source.url));
+    }
+    match &source.credentials {
+        None => return Some(String::from("Google Sheets require credentials.")),
+        Some(secret) if !crate::secrets::is_valid_secret_name(secret) => {
+            return Some(format!("Invalid secret name: {secret}."));
+        }
+        Some(_) => {}
+    }
+    if let Some((element, _)) = source.fields.iter().find(|(_, range)| range.is_empty()) {
+        return Some(format!("The cell of element {element} is missing."));
+    }
+    None
+}
+
+/// Returns the name of the secret if the header value references one.
+pub fn get_secret_reference(value: &str) -> Option<&str> {
+    value.strip_prefix(SECRET_REFERENCE_PREFIX)
+}
+
+/// Loads the content of the data source with the resolved headers.
+pub async fn load(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &[(String, String)],
+    file_folder: Option<&Path>,
+) -> anyhow::Result<Bytes> {
+    let parsed_url = reqwest::Url::parse(url)?;
+    if parsed_url.scheme() == "file" {
+        let path = parsed_url
+            .to_file_path()
+            .map_err(|_| anyhow!("{} is not a valid file URL", url))?;
+        return load_file(&path, file_folder).await;
+    }
+    let mut request = client.get(parsed_url);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} responded with {}", url, response.status()));
+    }
+    crate::outbound::read_body(response, MAX_DOCUMENT_SIZE).await
+}
+
+/// Reads the file, which must be inside the folder after resolving links.
+async fn load_file(path: &Path, file_folder: Option<&Path>) -> anyhow::Result<Bytes> {
+    let folder = file_folder.ok_or_else(|| anyhow!("No data source folder is configured"))?;
+    let path = tokio::fs::canonicalize(path).await?;
+    if !path.starts_with(tokio::fs::canonicalize(folder).await?) {
+        return Err(anyhow!(
+            "{} is outside of the data source folder",
+            path.display()
+        ));
+    }
+    if tokio::fs::metadata(&path).await?.len() > MAX_DOCUMENT_SIZE as u64 {
+        return Err(anyhow!(
+            "{} is larger than {} bytes",
+            path.display(),
+            MAX_DOCUMENT_SIZE
+        ));
+    }
+    Ok(tokio::fs::read(&path).await?.into())
+}
+
+/// Parses the content of a data source to a JSON document, CSV files to an array of rows.
+pub fn parse_document(format: DataFormat, data: &[u8]) -> anyhow::Result<Value> {
+    match format {
+        DataFormat::Json => Ok(serde_json::from_slice(data)?),
+        DataFormat::Csv => {
+            let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(data);
+            let headers = reader.headers()?.clone();
+            let mut rows = Vec::new();
+            for record in reader.records() {
+                let record = record?;
+                let row: serde_json::Map<String, Value> = headers
+                    .iter()
+                    .zip(record.iter())
+                    .map(|(header, value)| (header.to_owned(), Value::from(value)))
+                    .collect();
+                rows.push(Value::Object(row));
+            }
+            Ok(Value::Array(rows))
+        }
+        DataFormat::GoogleSheets => {
+            Err(anyhow!("Google Sheets are read with the Google Sheets API"))
+        }
+    }
+}
+
+/// Returns the text of every field whose pointer resolves to a string, number or boolean, and
+/// the elements of the fields which do not.
+pub fn get_texts(
+    fields: &BTreeMap<String, String>,
+    document: &Value,
+) -> (BTreeMap<String, String>, Vec<String>) {
+    let mut texts = BTreeMap::new();
+    let mut missing = Vec::new();
+    for (element, pointer) in fields {
+        let text = match document.pointer(pointer) {
+            Some(Value::String(text)) => Some(text.clone()),
+            Some(Value::Number(number)) => Some(number.to_string()),
+            Some(Value::Bool(value)) => Some(value.to_string()),
+            _ => None,
+        };
+        match text {
+            Some(text) => {
+                texts.insert(element.clone(), text);
+            }
+            None => missing.push(element.clone()),
+        }
+    }
+    (texts, missing)
+}
+
+/// Polls every data source in its interval and restarts the pollers whenever the data sources
+/// change.
+pub async fn run_data_sources(controller: Arc<ServerController>) {
+    let mut changes = controller.subscribe_data_source_changes();
+    loop {
+        let sources = match controller.get_data_sources().await {
+            Ok(sources) => sources,
+            Err(err) => {
+                error!("Could not load data sources: {:#}.", err);
+                DataSources::new()
+            }
+        };
+        let pollers: Vec<JoinHandle<()>> = sources
+            .into_iter()
+            .map(|(name, source)| {
+                tokio::spawn(run_poller(
+                    controller.clone(),
+                    name,
+                    Duration::from_secs(source.interval_seconds),
+                ))
+            })
+            .collect();
+        let changed = changes.changed().await;
+        for poller in pollers {
+            poller.abort();
+        }
+        if changed.is_err() {
+            return;
+        }
+    }
+}
+
+async fn run_poller(controller: Arc<ServerController>, name: String, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut failing = false;
+    loop {
+        ticker.tick().await;
+        match controller.poll_data_source(&name).await {
+            Ok(Some(status)) => match status.last_error {
+                Some(err) if !failing => {
+                    warn!("Could not poll data source {}: {}.", name, err);
+                    failing = true;
+                }
+                None if failing => {
+                    info!("Data source {} can be polled again.", name);
+                    failing = false;
+                }
+                _ => {}
+            },
+            Ok(None) => return,
+            Err(err) => error!("Could not poll data source {}: {:#}.", name, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_texts() {
+        let fields = BTreeMap::from([
+            (String::from("HomeScore"), String::from("/home/score")),
+            (String::from("HomeName"), String::from("/home/name")),
+            (String::from("Live"), String::from("/live")),
+            (String::from("Period"), String::from("/period")),
+        ]);
+        let document = serde_json::json!({
+            "home": {"name": "Lions", "score": 3},
+            "live": true,
+            "period": {"number": 2},
+        });
+        let (texts, missing) = get_texts(&fields, &document);
+        assert_eq!(
+            BTreeMap::from([
+                (String::from("HomeName"), String::from("Lions")),
+                (String::from("HomeScore"), String::from("3")),
+                (String::from("Live"), String::from("true")),
+            ]),
+            texts
+        );
+        assert_eq!(vec![String::from("Period")], missing);
+
+        let csv = "Name,Title\nAda Lovelace,Mathematician\n\"Hopper, Grace\",Rear Admiral\n";
+        let document = parse_document(DataFormat::Csv, csv.as_bytes()).unwrap();
+        let fields = BTreeMap::from([
+            (String::from("LowerThirdName"), String::from("/1/Name")),
+            (String::from("LowerThirdTitle"), String::from("/1/Title")),
+        ]);
+        let (texts, missing) = get_texts(&fields, &document);
+        assert_eq!(
+            Some(&String::from("Hopper, Grace")),
+            texts.get("LowerThirdName")
+        );
+        assert_eq!(
+            Some(&String::from("Rear Admiral")),
+            texts.get("LowerThirdTitle")
+        );
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_validate_data_source() {
+        let source: DataSource = serde_json::from_str(
+            r#"{"instance":"scoreboard","format":"json","url":"https://feed.example.com/match/12",
+                "headers":{"x-api-key":"secret:feed-key"},"fields":{"HomeScore":"/home/score"}}"#,
+        )
+        .unwrap();
+        assert_eq!(5, source.interval_seconds);
+        assert_eq!(None, validate_data_source(&source, None));
+        assert!(validate_data_source(
+            &DataSource {
+                url: String::from("ftp://feed.example.com"),
+                ..source.clone()
+            },
+            None
+        )
+        .is_some());
+        assert!(validate_data_source(
+            &DataSource {
+                fields: BTreeMap::from([(String::from("HomeScore"), String::from("home.score"))]),
+                ..source.clone()
+            },
+            None
+        )
+        .is_some());
+        assert!(validate_data_source(
+            &DataSource {
+                headers: BTreeMap::from([(
+                    String::from("x-api-key"),
+                    String::from("secret:../key")
+                )]),
+                ..source.clone()
+            },
+            None
+        )
+        .is_some());
+
+        let file_source = |url: &str| DataSource {
+            url: url.to_owned(),
+            ..source.clone()
+        };
+        let folder = Some(Path::new("/srv/feeds"));
+        assert_eq!(
+            None,
+            validate_data_source(&file_source("file:///srv/feeds/scores.json"), folder)
+        );
+        assert!(
+            validate_data_source(&file_source("file:///srv/feeds/scores.json"), None).is_some()
+        );
+        assert!(
+            validate_data_source(&file_source("file:///etc/zagreus/config.json"), folder).is_some()
+        );
+        assert!(validate_data_source(
+            &file_source("file:///srv/feeds/../zagreus/config.json"),
+            folder
+        )
+        .is_some());
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{Local, NaiveTime};
+
+use crate::controller::ServerController;
+use crate::data::asset::AssetReference;
+use crate::state::InstanceState;
+
+/// Interval in which the daily resets are checked.
+const DAILY_RESET_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResetPolicy {
+    /// The element is reset to its default value when the instance is cleared.
+    #[default]
+    OnClear,
+    /// The element keeps its value when the instance is cleared.
+    Persist,
+    /// The element keeps its value when the instance is cleared and is reset every day at the
+    /// configured time.
+    Daily,
+}
+
+/// Default data of an element, declared by the template.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ElementDefault {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    /// Classes which are added (`true`) or removed (`false`) by default.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub classes: BTreeMap<String, bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<AssetReference>,
+    #[serde(default)]
+    pub reset: ResetPolicy,
+    /// Local time of the daily reset, required for the `daily` policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_at: Option<NaiveTime>,
+}
+
+/// Default data per element id of an instance.
+pub type DataDefaults = BTreeMap<String, ElementDefault>;
+
+pub const DEFAULTS_FILE_NAME: &str = "defaults.json";
+
+/// Returns an error message if a daily reset has no time.
+pub fn validate_defaults(defaults: &DataDefaults) -> Option<String> {
+    defaults
+        .iter()
+        .find(|(_, default)| default.reset == ResetPolicy::Daily && default.reset_at.is_none())
+        .map(|(id, _)| format!("Element {id} is reset daily but has no resetAt time."))
+}
+
+/// Returns the state after clearing the instance: elements which persist keep their data, all
+/// other elements are reset to their defaults. Texts and classes of elements without defaults are
+/// emptied and removed.
+pub fn clear_state(state: &InstanceState, defaults: &DataDefaults) -> InstanceState {
+    reset_state(state, defaults, |default| match default {
+        Some(default) => default.reset == ResetPolicy::OnClear,
+        None => true,
+    })
+}
+
+/// Returns the state after resetting the given elements to their defaults.
+pub fn reset_elements(
+    state: &InstanceState,
+    defaults: &DataDefaults,
+    ids: &[&str],
+) -> InstanceState {
+    let mut reset = state.clone();
+    for id in ids {
+        if let Some(default) = defaults.get(*id) {
+            apply_default(&mut reset, id, default);
+        }
+    }
+    reset
+}
+
+fn reset_state(
+    state: &InstanceState,
+    defaults: &DataDefaults,
+    should_reset: impl Fn(Option<&ElementDefault>) -> bool,
+) -> InstanceState {
+    let mut reset = InstanceState::default();
+    for (id, text) in &state.texts {
+        let text = if should_reset(defaults.get(id)) {
+            String::new()
+        } else {
+            text.clone()
+        };
+        reset.texts.insert(id.clone(), text);
+    }
+    for (id, classes) in &state.classes {
+        let classes = if should_reset(defaults.get(id)) {
+            classes.keys().map(|class| (class.clone(), false)).collect()
+        } else {
+            classes.clone()
+        };
+        reset.classes.insert(id.clone(), classes);
+    }
+    reset.images = state.images.clone();
+
+    for (id, default) in defaults {
+        if should_reset(Some(default)) {
+            apply_default(&mut reset, id, default);
+        }
+    }
+    reset
+}
+
+fn apply_default(state: &mut InstanceState, id: &str, default: &ElementDefault) {
+    if let Some(text) = &default.text {
+        state.texts.insert(id.to_owned(), text.clone());
+    }
+    if !default.classes.is_empty() {
+        state
+            .classes
+            .entry(id.to_owned())
+            .or_default()
+            .extend(default.classes.clone());
+    }
+    if let Some(image) = &default.image {
+        state.images.insert(id.to_owned(), image.clone());
+    }
+}
+
+/// Resets the elements with a daily reset policy of all instances at their configured time.
+pub async fn run_daily_resets(controller: Arc<ServerController>) {
+    let mut interval = tokio::time::interval(DAILY_RESET_CHECK_INTERVAL);
+    let mut last_check = Local::now();
+    loop {
+        interval.tick().await;
+        let now = Local::now();
+        let instances = match controller.get_data_defaults_of_instances().await {
+            Ok(instances) => instances,
+            Err(err) => {
+                error!("Could not load data defaults: {:#}.", err);
+                continue;
+            }
+        };
+
+        for (instance, defaults) in instances {
+            let due_elements: Vec<&str> = defaults
+                .iter()
+                .filter(|(_, default)| default.reset == ResetPolicy::Daily)
+                .filter(|(_, default)| {
+                    default
+                        .reset_at
+                        .is_some_and(|reset_at| crate::routine::is_due(reset_at, last_check, now))
+                })
+                .map(|(id, _)| id.as_str())
+                .collect();
+            if due_elements.is_empty() {
+                continue;
+            }
+            info!(
+                "Resetting elements {:?} of instance {} to their defaults.",
+                due_elements, instance
+            );
+            controller
+                .reset_elements(&instance, &defaults, &due_elements)
+                .await;
+        }
+        last_check = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clear_state() {
+        let mut state = InstanceState::default();
+        state
+            .texts
+            .insert(String::from("HomeScore"), String::from("3"));
+        state
+            .texts
+            .insert(String::from("Title"), String::from("Final"));
+        state
+            .texts
+            .insert(String::from("Sponsor"), String::from("ACME"));
+        state.classes.insert(
+            String::from("Scoreboard"),
+            BTreeMap::from([(String::from("active"), true)]),
+        );
+        let defaults = DataDefaults::from([
+            (
+                String::from("HomeScore"),
+                ElementDefault {
+                    text: Some(String::from("0")),
+                    ..ElementDefault::default()
+                },
+            ),
+            (
+                String::from("Sponsor"),
+                ElementDefault {
+                    text: Some(String::from("Default sponsor")),
+                    reset: ResetPolicy::Persist,
+                    ..ElementDefault::default()
+                },
+            ),
+            (
+                String::from("AwayScore"),
+                ElementDefault {
+                    text: Some(String::from("0")),
+                    ..ElementDefault::default()
+                },
+            ),
+        ]);
+
+        let cleared = clear_state(&state, &defaults);
+        assert_eq!("0", cleared.texts["HomeScore"]);
+        assert_eq!("0", cleared.texts["AwayScore"]);
+        assert_eq!("", cleared.texts["Title"]);
+        assert_eq!("ACME", cleared.texts["Sponsor"]);
+        assert!(!cleared.classes["Scoreboard"]["active"]);
+
+        let reset = reset_elements(&state, &defaults, &["Sponsor"]);
+        assert_eq!("Default sponsor", reset.texts["Sponsor"]);
+        assert_eq!("3", reset.texts["HomeScore"]);
+    }
+
+    #[test]
+    fn test_daily_reset_is_due_once() {
+        let reset_at = NaiveTime::from_hms_opt(6, 0, 0).unwrap();
+        let at = |hour: u32, minute: u32| {
+            Local::now()
+                .date_naive()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap()
+                .and_local_timezone(Local)
+                .earliest()
+                .unwrap()
+        };
+        assert!(crate::routine::is_due(reset_at, at(5, 59), at(6, 0)));
+        assert!(!crate::routine::is_due(reset_at, at(6, 0), at(6, 1)));
+        assert!(!crate::routine::is_due(reset_at, at(5, 0), at(5, 30)));
+
+        let defaults = DataDefaults::from([(
+            String::from("Sponsor"),
+            ElementDefault {
+                reset: ResetPolicy::Daily,
+                ..ElementDefault::default()
+            },
+        )]);
+        assert!(validate_defaults(&defaults).is_some());
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use crate::defaults::DataDefaults;
+use crate::state::InstanceState;
+
+/// Graphics shown instead of the regular graphics of an instance in an emergency, e.g. a weather
+/// warning or an evacuation notice.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EmergencyOverride {
+    /// Data shown while the override is active. All other texts and classes are cleared.
+    #[serde(default)]
+    pub state: InstanceState,
+    /// Animation executed after the data of the override was applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub animation: Option<String>,
+}
+
+/// Emergency overrides of an instance per name.
+pub type EmergencyOverrides = BTreeMap<String, EmergencyOverride>;
+
+pub const EMERGENCY_FILE_NAME: &str = "emergency.json";
+
+/// An override that is on air. Regular updates of the instance are held back in the state which
+/// is restored when the override is released.
+pub struct ActiveOverride {
+    pub name: String,
+    pub held_state: InstanceState,
+}
+
+pub enum ActivationResult {
+    Activated,
+    OverrideNotFound,
+}
+
+/// Returns the state which replaces everything shown in the current state with the given state:
+/// texts that are not part of it are emptied and classes are removed.
+pub fn replace_state(current: &InstanceState, shown: &InstanceState) -> InstanceState {
+    let mut state = crate::defaults::clear_state(current, &DataDefaults::new());
+    state.texts.extend(shown.texts.clone());
+    for (id, classes) in &shown.classes {
+        state
+            .classes
+            .entry(id.clone())
+            .or_default()
+            .extend(classes.clone());
+    }
+    state.images.extend(shown.images.clone());
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_state() {
+        let mut current = InstanceState::default();
+        current
+            .texts
+            .insert(String::from("Title"), String::from("Final"));
+        current.classes.insert(
+            String::from("LowerThird"),
+            BTreeMap::from([(String::from("visible"), true)]),
+        );
+        let mut warning = InstanceState::default();
+        warning.texts.insert(
+            String::from("Warning"),
+            String::from("Severe weather warning"),
+        );
+        warning.classes.insert(
+            String::from("Warning"),
+            BTreeMap::from([(String::from("visible"), true)]),
+        );
+
+        let replaced = replace_state(&current, &warning);
+        assert_eq!("", replaced.texts["Title"]);
+        assert_eq!("Severe weather warning", replaced.texts["Warning"]);
+        assert!(!replaced.classes["LowerThird"]["visible"]);
+        assert!(replaced.classes["Warning"]["visible"]);
+
+        // releasing the override restores the prior state and clears the warning
+        let restored = replace_state(&replaced, &current);
+        assert_eq!("Final", restored.texts["Title"]);
+        assert_eq!("", restored.texts["Warning"]);
+        assert!(restored.classes["LowerThird"]["visible"]);
+        assert!(!restored.classes["Warning"]["visible"]);
+    }
+}
@@ -0,0 +1,58 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::controller::ServerController;
+use crate::data::operation::Operation;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::endpoint::data::submit_operations;
+
+#[derive(Deserialize)]
+pub(crate) struct SetTextQuery {
+    #[serde(default)]
+    text: String,
+}
+
+pub(crate) async fn get_action_catalogue(
+    Path(template): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&template) {
+        return invalid_instance_name_response();
+    }
+    Json(controller.get_action_catalogue(&template).await).into_response()
+}
+
+/// Executes the animation with a GET request, for control surfaces which only send those.
+pub(crate) async fn trigger_animation(
+    Path((template, animation)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&template) {
+        return invalid_instance_name_response();
+    }
+    let operation = Operation::ExecuteAnimation {
+        animation_sequence: animation,
+        execute_at: None,
+    };
+    submit_operations(&template, None, None, controller, vec![operation]).await
+}
+
+/// Sets the text of the element with a GET request, an empty text if none is passed.
+pub(crate) async fn trigger_set_text(
+    Path((template, element)): Path<(String, String)>,
+    Query(query): Query<SetTextQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&template) {
+        return invalid_instance_name_response();
+    }
+    let operation = Operation::SetText {
+        id: element,
+        text: query.text,
+        tween_duration: None,
+    };
+    submit_operations(&template, None, None, controller, vec![operation]).await
+}
@@ -0,0 +1,13 @@
+use std::sync::Arc;
+
+use axum::extract::Extension;
+use axum::response::IntoResponse;
+use axum::Json;
+
+use crate::controller::ServerController;
+
+pub(crate) async fn get_analytics(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> impl IntoResponse {
+    Json(controller.get_analytics().await)
+}
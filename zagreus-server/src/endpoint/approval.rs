@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+
+pub(crate) async fn get_pending_changes(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    Json(controller.get_pending_changes(&instance).await).into_response()
+}
+
+pub(crate) async fn approve_change(
+    Path((instance, id)): Path<(String, u64)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.approve_change(&instance, id).await {
+        Some(change) => Json(change).into_response(),
+        None => change_not_found_response(),
+    }
+}
+
+pub(crate) async fn reject_change(
+    Path((instance, id)): Path<(String, u64)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.reject_change(&instance, id).await {
+        Some(change) => Json(change).into_response(),
+        None => change_not_found_response(),
+    }
+}
+
+fn change_not_found_response() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!("Pending change not found.")),
+    )
+        .into_response()
+}
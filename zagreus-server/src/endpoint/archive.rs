@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::archive::{CloneResult, InstanceArchive};
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+
+#[derive(Deserialize)]
+pub(crate) struct CloneQuery {
+    #[serde(rename = "as")]
+    name: String,
+}
+
+pub(crate) async fn export_instances(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.export_instances().await {
+        Ok(archive) => Json(archive).into_response(),
+        Err(err) => {
+            error!("Could not export instances: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not export instances.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn import_instances(
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(archive): Json<InstanceArchive>,
+) -> Response {
+    let report = controller.import_instances(archive).await;
+    let status = if report.imported {
+        StatusCode::OK
+    } else {
+        StatusCode::UNPROCESSABLE_ENTITY
+    };
+    (status, Json(report)).into_response()
+}
+
+pub(crate) async fn get_instances(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_instance_summaries().await {
+        Ok(instances) => Json(instances).into_response(),
+        Err(err) => {
+            error!("Could not list instances: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not list instances.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn delete_instance(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.delete_instance(&instance).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => (
+            StatusCode::NOT_FOUND,
+            Json(json!("Instance does not exist.")),
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Could not delete instance {}: {:#}.", instance, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not delete instance.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn clone_instance(
+    Path(instance): Path<String>,
+    Query(query): Query<CloneQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance)
+        || !crate::fs::is_valid_instance_name(&query.name)
+    {
+        return invalid_instance_name_response();
+    }
+    match controller.clone_instance(&instance, &query.name).await {
+        Ok(CloneResult::Cloned) => StatusCode::OK.into_response(),
+        Ok(CloneResult::NotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(json!("Instance does not exist.")),
+        )
+            .into_response(),
+        Ok(CloneResult::AlreadyExists) => (
+            StatusCode::CONFLICT,
+            Json(json!("An instance with this name exists already.")),
+        )
+            .into_response(),
+        Err(err) => {
+            error!(
+                "Could not clone instance {} as {}: {:#}.",
+                instance, query.name, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not clone instance.")),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::audit::{csv_response, DateRangeQuery};
+use crate::endpoint::config::invalid_instance_name_response;
+
+pub(crate) async fn get_as_run_log(
+    Path(instance): Path<String>,
+    Query(range): Query<DateRangeQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller
+        .get_as_run_records(&instance, range.from, range.to)
+        .await
+    {
+        Ok(records) => Json(records).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load as-run log of instance {}: {}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load as-run log.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn export_as_run_csv(
+    Path(instance): Path<String>,
+    Query(range): Query<DateRangeQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let csv = controller
+        .get_as_run_records(&instance, range.from, range.to)
+        .await
+        .and_then(|records| crate::asrun::write_csv(&records));
+    csv_response(&format!("as-run-{instance}.csv"), csv)
+}
@@ -1,29 +1,69 @@
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::anyhow;
 use axum::body::Bytes;
-use axum::extract::Extension;
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
+use axum::extract::{Extension, Path as AxumPath, Query, State};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
 use axum::Json;
 use serde_json::json;
 use sha2::Digest;
 use sha2::Sha256;
+use tokio::io::AsyncWriteExt;
 
-#[derive(Deserialize, Serialize)]
-pub(crate) struct UploadAssetResponseDto {
-    name: String,
-}
+use crate::assets::signing::{AssetSignature, SIGNED_ASSETS_PATH};
+use crate::assets::upload::{
+    hash_file, is_valid_sha256, AppendResult, CreateUploadDto, UploadCompletion, UploadStatusDto,
+    UPLOAD_OFFSET_HEADER,
+};
+use crate::config::ZagreusServerConfig;
+use crate::controller::ServerController;
+use zagreus_client::dto::{FetchAssetDto, SignAssetDto, UploadAssetResponseDto};
+
+/// Validity of signed asset URLs if none is requested.
+const DEFAULT_SIGNED_URL_VALIDITY_SECONDS: i64 = 24 * 60 * 60;
 
 const ASSET_NAME_FIELD: &str = "name";
 const ASSET_DATA_FIELD: &str = "file";
 
 pub(crate) async fn upload_asset(
     Extension(assets_folder): Extension<PathBuf>,
+    Extension(controller): Extension<Arc<ServerController>>,
     multipart: axum::extract::Multipart,
 ) -> impl IntoResponse {
-    match get_asset_data(multipart).await {
-        Ok((asset_name, asset_data)) => {
+    let received_path = match controller.create_received_upload_path().await {
+        Ok(path) => path,
+        Err(err) => {
+            error!("Could not prepare upload of asset: {}.", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not upload asset.")),
+            );
+        }
+    };
+    let response = receive_asset(&controller, &assets_folder, multipart, &received_path).await;
+    if let Err(err) = tokio::fs::remove_file(&received_path).await {
+        if err.kind() != std::io::ErrorKind::NotFound {
+            warn!(
+                "Could not remove received upload {}: {}.",
+                received_path.display(),
+                err
+            );
+        }
+    }
+    response
+}
+
+async fn receive_asset(
+    controller: &ServerController,
+    assets_folder: &Path,
+    multipart: axum::extract::Multipart,
+    received_path: &Path,
+) -> (StatusCode, Json<serde_json::Value>) {
+    match receive_asset_data(multipart, received_path).await {
+        Ok(asset_name) => {
             if asset_name.contains(std::path::MAIN_SEPARATOR) || asset_name.contains("..") {
                 return (
                     StatusCode::BAD_REQUEST,
@@ -31,15 +71,34 @@ pub(crate) async fn upload_asset(
                 );
             }
 
-            let path = PathBuf::from(asset_name);
+            let path = PathBuf::from(&asset_name);
 
             match path.extension().and_then(|val| val.to_str()) {
                 Some(extension) => {
-                    match write_asset_file(&assets_folder, extension, asset_data).await {
-                        Ok(asset_name) => (
-                            StatusCode::OK,
-                            Json(json!(UploadAssetResponseDto { name: asset_name })),
-                        ),
+                    match store_received_asset(controller, assets_folder, extension, received_path)
+                        .await
+                    {
+                        Ok(saved_asset_name) => {
+                            if let Err(err) = controller
+                                .set_asset_version(&asset_name, &saved_asset_name)
+                                .await
+                            {
+                                error!(
+                                    "Could not update version of asset {}: {}.",
+                                    asset_name, err
+                                );
+                                return (
+                                    StatusCode::INTERNAL_SERVER_ERROR,
+                                    Json(json!("Could not update asset version.")),
+                                );
+                            }
+                            (
+                                StatusCode::OK,
+                                Json(json!(UploadAssetResponseDto {
+                                    name: saved_asset_name
+                                })),
+                            )
+                        }
                         Err(err) => {
                             error!("Could not upload asset successfully: {}.", err);
                             (
@@ -68,36 +127,476 @@ pub(crate) async fn upload_asset(
     }
 }
 
-async fn get_asset_data(
+pub(crate) async fn get_asset_versions(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_asset_versions().await {
+        Ok(versions) => Json(versions).into_response(),
+        Err(err) => {
+            error!("Could not load asset versions: {}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load asset versions.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Redirects to the current version of the asset with the given name.
+pub(crate) async fn get_latest_asset(
+    AxumPath(name): AxumPath<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_asset_versions().await {
+        Ok(versions) => match versions.get(&name) {
+            Some(file_name) => (
+                [(header::CACHE_CONTROL, "no-cache")],
+                Redirect::temporary(&format!("/assets/{file_name}")),
+            )
+                .into_response(),
+            None => (StatusCode::NOT_FOUND, Json(json!("Asset does not exist."))).into_response(),
+        },
+        Err(err) => {
+            error!("Could not load asset versions: {}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load asset versions.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn delete_asset(
+    AxumPath(name): AxumPath<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.delete_asset(&name).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!("Asset does not exist."))).into_response(),
+        Err(err) => {
+            error!("Could not delete asset {}: {:#}.", name, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not delete asset.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn get_asset_info(
+    AxumPath(name): AxumPath<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_asset_info(&name).await {
+        Ok(Some(info)) => Json(info).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!("Asset does not exist."))).into_response(),
+        Err(err) => {
+            error!("Could not load info of asset {}: {:#}.", name, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load asset info.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Starts a resumable upload, whose content is sent in chunks with [`upload_chunk`].
+pub(crate) async fn create_upload(
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(payload): Json<CreateUploadDto>,
+) -> Response {
+    if payload.name.contains(std::path::MAIN_SEPARATOR)
+        || payload.name.contains("..")
+        || Path::new(&payload.name).extension().is_none()
+    {
+        return (StatusCode::BAD_REQUEST, Json(json!("Invalid asset name."))).into_response();
+    }
+    if !is_valid_sha256(&payload.sha256) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("Checksum must be a hex encoded SHA-256 hash.")),
+        )
+            .into_response();
+    }
+    let max_size = controller.get_configuration().max_asset_upload_size;
+    if payload.size > max_size as u64 {
+        return (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(json!(format!(
+                "Assets must not be larger than {max_size} bytes."
+            ))),
+        )
+            .into_response();
+    }
+    match controller.create_upload(&payload).await {
+        Ok(status) => (StatusCode::CREATED, Json(status)).into_response(),
+        Err(err) => {
+            error!(
+                "Could not create upload of asset {}: {:#}.",
+                payload.name, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not create upload.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn get_upload(
+    AxumPath(id): AxumPath<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_upload_status(&id).await {
+        Ok(Some(status)) => upload_status_response(status),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!("Upload does not exist."))).into_response(),
+        Err(err) => {
+            error!("Could not load upload {}: {:#}.", id, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load upload.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Appends a chunk to the upload. The chunk has to start at the current offset of the upload, so
+/// a chunk which failed can be sent again after the offset was requested.
+pub(crate) async fn upload_chunk(
+    AxumPath(id): AxumPath<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    headers: HeaderMap,
+    chunk: Bytes,
+) -> Response {
+    let offset = headers
+        .get(UPLOAD_OFFSET_HEADER)
+        .and_then(|offset| offset.to_str().ok())
+        .and_then(|offset| offset.parse::<u64>().ok());
+    let Some(offset) = offset else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("Upload-Offset header is missing or invalid.")),
+        )
+            .into_response();
+    };
+    match controller.append_upload_chunk(&id, offset, &chunk).await {
+        Ok(AppendResult::Appended(status)) => upload_status_response(status),
+        Ok(AppendResult::NotFound) => {
+            (StatusCode::NOT_FOUND, Json(json!("Upload does not exist."))).into_response()
+        }
+        Ok(AppendResult::OffsetMismatch(current_offset)) => (
+            StatusCode::CONFLICT,
+            [(UPLOAD_OFFSET_HEADER, current_offset.to_string())],
+            Json(json!(format!(
+                "Chunk must start at offset {current_offset}."
+            ))),
+        )
+            .into_response(),
+        Ok(AppendResult::TooLarge) => (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Json(json!("Chunk exceeds the size of the upload.")),
+        )
+            .into_response(),
+        Ok(AppendResult::Busy) => (
+            StatusCode::CONFLICT,
+            Json(json!("Another chunk of the upload is being written.")),
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Could not write chunk of upload {}: {:#}.", id, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not write chunk.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Verifies the checksum of the complete upload and makes it the current version of the asset.
+pub(crate) async fn complete_upload(
+    AxumPath(id): AxumPath<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.complete_upload(&id).await {
+        Ok(UploadCompletion::Completed { file, .. }) => {
+            Json(UploadAssetResponseDto { name: file }).into_response()
+        }
+        Ok(UploadCompletion::NotFound) => {
+            (StatusCode::NOT_FOUND, Json(json!("Upload does not exist."))).into_response()
+        }
+        Ok(UploadCompletion::Incomplete(status)) => (
+            StatusCode::CONFLICT,
+            [(UPLOAD_OFFSET_HEADER, status.offset.to_string())],
+            Json(json!(format!(
+                "Upload is incomplete, {} of {} bytes were received.",
+                status.offset, status.size
+            ))),
+        )
+            .into_response(),
+        Ok(UploadCompletion::ChecksumMismatch) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!("Checksum does not match, the upload was discarded.")),
+        )
+            .into_response(),
+        Ok(UploadCompletion::Busy) => (
+            StatusCode::CONFLICT,
+            Json(json!("A chunk of the upload is being written.")),
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Could not complete upload {}: {:#}.", id, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not complete upload.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn delete_upload(
+    AxumPath(id): AxumPath<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.delete_upload(&id).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!("Upload does not exist."))).into_response(),
+        Err(err) => {
+            error!("Could not delete upload {}: {:#}.", id, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not delete upload.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Returns the status with the offset also in the header, as expected by tus clients.
+fn upload_status_response(status: UploadStatusDto) -> Response {
+    (
+        [(UPLOAD_OFFSET_HEADER, status.offset.to_string())],
+        Json(status),
+    )
+        .into_response()
+}
+
+/// Creates an expiring signed URL of the asset, which can be cached by an edge CDN without
+/// giving access to the API.
+pub(crate) async fn create_signed_asset_url(
+    Extension(configuration): Extension<Arc<ZagreusServerConfig>>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(payload): Json<SignAssetDto>,
+) -> Response {
+    let max_valid_seconds = configuration.asset_signing.max_valid_seconds;
+    let valid_seconds = payload
+        .valid_seconds
+        .unwrap_or(DEFAULT_SIGNED_URL_VALIDITY_SECONDS);
+    if !(1..=max_valid_seconds).contains(&valid_seconds) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!(format!(
+                "Signed URLs must be valid for 1 to {max_valid_seconds} seconds."
+            ))),
+        )
+            .into_response();
+    }
+    match controller
+        .create_signed_asset_url(&payload.asset, valid_seconds)
+        .await
+    {
+        Ok(Some(signed_url)) => Json(signed_url).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!("Asset does not exist."))).into_response(),
+        Err(err) => {
+            error!("Could not sign URL of asset {}: {:#}.", payload.asset, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not sign asset URL.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Only passes requests for signed assets whose signature is valid and did not expire. Responses
+/// may be cached until the signature expires.
+pub(crate) async fn verify_signed_asset<B>(
+    State(controller): State<Arc<ServerController>>,
+    signature: Option<Query<AssetSignature>>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let file = req
+        .uri()
+        .path()
+        .strip_prefix(SIGNED_ASSETS_PATH)
+        .and_then(|path| path.strip_prefix('/'))
+        .unwrap_or_default();
+    let valid_until = match signature {
+        Some(Query(signature)) if crate::assets::signing::is_valid_asset_file(file) => {
+            match controller.verify_signed_asset_url(file, &signature).await {
+                Ok(true) => Some(signature.expires),
+                Ok(false) => None,
+                Err(err) => {
+                    error!("Could not verify signed asset URL: {:#}.", err);
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let Some(valid_until) = valid_until else {
+        return (
+            StatusCode::FORBIDDEN,
+            Json(json!("Asset URL is not signed or expired.")),
+        )
+            .into_response();
+    };
+    let mut response = next.run(req).await;
+    let max_age = valid_until - chrono::Utc::now().timestamp();
+    if let Ok(cache_control) = format!("public, max-age={max_age}").parse() {
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control);
+    }
+    response
+}
+
+/// Downloads an image from the given URL (through the configured proxy) and stores it as an asset.
+pub(crate) async fn fetch_asset(
+    Extension(assets_folder): Extension<PathBuf>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(payload): Json<FetchAssetDto>,
+) -> impl IntoResponse {
+    let resource = match controller.fetch_url(&payload.url).await {
+        Ok(resource) => resource,
+        Err(err) => {
+            error!("Could not fetch asset from {}: {}.", payload.url, err);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(json!("Could not fetch asset.")),
+            );
+        }
+    };
+
+    let extension = resource
+        .content_type
+        .as_deref()
+        .and_then(get_image_extension)
+        .or_else(|| get_url_extension(&payload.url));
+    let extension = match extension {
+        Some(extension) => extension,
+        None => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!("Could not determine asset type.")),
+            )
+        }
+    };
+
+    match write_asset_file(&controller, &assets_folder, &extension, resource.data).await {
+        Ok(asset_name) => (
+            StatusCode::OK,
+            Json(json!(UploadAssetResponseDto { name: asset_name })),
+        ),
+        Err(err) => {
+            error!("Could not store fetched asset: {}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store asset.")),
+            )
+        }
+    }
+}
+
+fn get_image_extension(content_type: &str) -> Option<String> {
+    let mime_type = content_type.split(';').next()?.trim();
+    let extension = match mime_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        _ => return None,
+    };
+    Some(extension.to_owned())
+}
+
+pub(crate) fn get_url_extension(url: &str) -> Option<String> {
+    let path = url.split(['?', '#']).next()?;
+    let file_name = path.rsplit('/').next()?;
+    let (_, extension) = file_name.rsplit_once('.')?;
+    if !extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Some(extension.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Streams the asset data of a multipart request to the given file, so large assets are not held
+/// in memory, and returns the asset name.
+async fn receive_asset_data(
     mut multipart: axum::extract::Multipart,
-) -> anyhow::Result<(String, Bytes)> {
+    received_path: &Path,
+) -> anyhow::Result<String> {
     let mut asset_name: Option<String> = None;
-    let mut asset_data: Option<Bytes> = None;
-    while let Some(field) = multipart.next_field().await? {
+    let mut has_data = false;
+    while let Some(mut field) = multipart.next_field().await? {
         if let Some(name) = field.name() {
             if name.eq(ASSET_NAME_FIELD) {
                 let asset_name_text = field.text().await?;
                 asset_name = Some(asset_name_text);
             } else if name.eq(ASSET_DATA_FIELD) {
-                let data = field.bytes().await?;
-                asset_data = Some(data);
+                let mut file = tokio::fs::File::create(received_path).await?;
+                while let Some(chunk) = field.chunk().await? {
+                    file.write_all(&chunk).await?;
+                }
+                file.flush().await?;
+                has_data = true;
             }
         }
     }
-    if let Some(asset_name) = asset_name {
-        if let Some(asset_data) = asset_data {
-            return Ok((asset_name, asset_data));
-        }
+    match asset_name {
+        Some(asset_name) if has_data => Ok(asset_name),
+        _ => Err(anyhow!("Multipart request did not have expected format.")),
     }
-    Err(anyhow!("Multipart request did not have expected format."))
 }
 
-async fn write_asset_file(
+/// Moves a received asset file into the assets folder under the hash of its content.
+async fn store_received_asset(
+    controller: &ServerController,
+    assets_folder: &Path,
+    extension: &str,
+    received_path: &Path,
+) -> anyhow::Result<String> {
+    let hashed_path = received_path.to_owned();
+    let hash = controller
+        .run_blocking(move || hash_file(&hashed_path))
+        .await??;
+    let saved_asset_name = format!("{hash}.{extension}");
+    tokio::fs::rename(received_path, assets_folder.join(&saved_asset_name)).await?;
+    Ok(saved_asset_name)
+}
+
+/// Stores the asset under the hash of its content. Hashing runs on the worker pool, since large
+/// assets would block the async runtime.
+pub(crate) async fn write_asset_file(
+    controller: &ServerController,
     assets_folder: &Path,
     extension: &str,
     asset_bytes: Bytes,
 ) -> anyhow::Result<String> {
-    let hash = Sha256::digest(&asset_bytes);
+    let hashed_bytes = asset_bytes.clone();
+    let hash = controller
+        .run_blocking(move || Sha256::digest(&hashed_bytes))
+        .await?;
     let saved_asset_name = format!("{:x}.{extension}", hash);
 
     let mut asset_file_path = assets_folder.to_owned();
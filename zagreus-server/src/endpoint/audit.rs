@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Query};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+
+pub(crate) use zagreus_client::dto::DateRangeQuery;
+
+pub(crate) async fn get_audit_log(
+    Query(range): Query<DateRangeQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> impl IntoResponse {
+    Json(controller.get_audit_entries(range.from, range.to).await)
+}
+
+pub(crate) async fn export_audit_log_csv(
+    Query(range): Query<DateRangeQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    let entries = controller.get_audit_entries(range.from, range.to).await;
+    csv_response("audit.csv", crate::audit::write_csv(&entries))
+}
+
+pub(crate) async fn export_analytics_csv(
+    Query(range): Query<DateRangeQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    let report = controller.get_analytics().await;
+    csv_response(
+        "analytics.csv",
+        crate::analytics::write_csv(&report, range.from, range.to),
+    )
+}
+
+pub(crate) fn csv_response(file_name: &str, csv: anyhow::Result<Vec<u8>>) -> Response {
+    match csv {
+        Ok(csv) => (
+            [
+                (header::CONTENT_TYPE, String::from("text/csv")),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{file_name}\""),
+                ),
+            ],
+            csv,
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Could not export {} as CSV: {}.", file_name, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not export CSV.")),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+
+pub(crate) async fn get_clients(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    Json(controller.get_clients().await).into_response()
+}
+
+pub(crate) async fn get_instance_clients(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    Json(controller.get_instance_clients(&instance).await).into_response()
+}
@@ -0,0 +1,65 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+
+pub(crate) async fn get_computed_elements(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_computed_elements(&instance).await {
+        Ok(computed) => Json(computed).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load computed elements of instance {}: {}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load computed elements.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn set_computed_elements(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(sources): Json<BTreeMap<String, String>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let computed = match crate::expression::parse_computed_elements(sources) {
+        Ok(computed) => computed,
+        Err(message) => return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response(),
+    };
+    if let Some(message) = crate::expression::validate_computed_elements(&computed) {
+        return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response();
+    }
+    match controller.set_computed_elements(&instance, computed).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store computed elements of instance {}: {}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store computed elements.")),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::{ConfigDocument, ServerController};
+
+pub(crate) async fn get_config_document(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_config_document(&instance).await {
+        Ok(config) => Json(config).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load config document of instance {}: {}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load config document.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn set_config_document(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(config): Json<ConfigDocument>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.set_config_document(&instance, config).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store config document of instance {}: {}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store config document.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) fn invalid_instance_name_response() -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!("Instance name contains invalid character")),
+    )
+        .into_response()
+}
@@ -1,96 +1,257 @@
-use crate::data::asset::AssetSource;
-use crate::websocket::message::InstanceMessage;
-use crate::WebsocketServer;
-use axum::extract::{Extension, Path};
-use axum::response::IntoResponse;
+use crate::controller::ServerController;
+use crate::data::operation::Operation;
+use crate::data::sequence::SequenceStep;
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
+use chrono::Utc;
+use serde_json::json;
 use std::sync::Arc;
-
-#[derive(Deserialize, Serialize)]
-pub(crate) struct SetTextDto {
-    id: String,
-    text: String,
-}
-
-#[derive(Deserialize, Serialize)]
-pub(crate) struct ManipulateClassDto {
-    id: String,
-    class: String,
-}
-
-#[derive(Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub(crate) struct SetImageSourceDto {
-    id: String,
-    asset: String,
-    asset_source: AssetSource,
-}
+use zagreus_client::dto::{
+    ChannelQuery, ExecuteAnimationQuery, ManipulateClassDto, SetImageSourceDto, SetTextDto,
+};
 
 pub(crate) async fn set_text(
     Path(instance): Path<String>,
-    Extension(server): Extension<Arc<WebsocketServer>>,
+    Query(query): Query<ChannelQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
     Json(payload): Json<SetTextDto>,
-) -> impl IntoResponse {
-    let message = InstanceMessage::SetText {
-        id: &payload.id,
-        text: &payload.text,
+) -> Response {
+    let operation = Operation::SetText {
+        id: payload.id,
+        text: payload.text,
+        tween_duration: payload.tween_duration,
     };
-    send_instance_message(&instance, server, message).await
+    submit_operations(
+        &instance,
+        query.channel,
+        query.client_id,
+        controller,
+        vec![operation],
+    )
+    .await
 }
 
 pub(crate) async fn add_class(
     Path(instance): Path<String>,
-    Extension(server): Extension<Arc<WebsocketServer>>,
+    Query(query): Query<ChannelQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
     Json(payload): Json<ManipulateClassDto>,
-) -> impl IntoResponse {
-    let message = InstanceMessage::AddClass {
-        id: &payload.id,
-        class: &payload.class,
+) -> Response {
+    let operation = Operation::AddClass {
+        id: payload.id,
+        class: payload.class,
     };
-    send_instance_message(&instance, server, message).await
+    submit_operations(
+        &instance,
+        query.channel,
+        query.client_id,
+        controller,
+        vec![operation],
+    )
+    .await
 }
 
 pub(crate) async fn remove_class(
     Path(instance): Path<String>,
-    Extension(server): Extension<Arc<WebsocketServer>>,
+    Query(query): Query<ChannelQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
     Json(payload): Json<ManipulateClassDto>,
-) -> impl IntoResponse {
-    let message = InstanceMessage::RemoveClass {
-        id: &payload.id,
-        class: &payload.class,
+) -> Response {
+    let operation = Operation::RemoveClass {
+        id: payload.id,
+        class: payload.class,
     };
-    send_instance_message(&instance, server, message).await
+    submit_operations(
+        &instance,
+        query.channel,
+        query.client_id,
+        controller,
+        vec![operation],
+    )
+    .await
 }
 
 pub(crate) async fn execute_animation(
     Path((instance, animation_name)): Path<(String, String)>,
-    Extension(server): Extension<Arc<WebsocketServer>>,
-) -> impl IntoResponse {
-    let message = InstanceMessage::ExecuteAnimation {
-        animation_sequence: &animation_name,
+    Query(query): Query<ExecuteAnimationQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    let execute_at = match (query.at, query.delay) {
+        (Some(_), Some(_)) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!("Only one of at and delay can be set.")),
+            )
+                .into_response()
+        }
+        (Some(at), None) => Some(at),
+        (None, Some(delay)) => Some(Utc::now().timestamp_millis() + delay.max(0)),
+        (None, None) => None,
     };
-    send_instance_message(&instance, server, message).await
+    let operation = Operation::ExecuteAnimation {
+        animation_sequence: animation_name,
+        execute_at,
+    };
+    submit_operations(
+        &instance,
+        query.channel,
+        query.client_id,
+        controller,
+        vec![operation],
+    )
+    .await
 }
 
 pub(crate) async fn set_image_source(
     Path(instance): Path<String>,
-    Extension(server): Extension<Arc<WebsocketServer>>,
+    Query(query): Query<ChannelQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
     Json(payload): Json<SetImageSourceDto>,
-) -> impl IntoResponse {
-    let message = InstanceMessage::SetImageSource {
-        id: &payload.id,
-        asset: &payload.asset,
+) -> Response {
+    let operation = Operation::SetImageSource {
+        id: payload.id,
+        asset: payload.asset,
         asset_source: payload.asset_source,
     };
-    send_instance_message(&instance, server, message).await
+    submit_operations(
+        &instance,
+        query.channel,
+        query.client_id,
+        controller,
+        vec![operation],
+    )
+    .await
+}
+
+/// Sends the operations to the clients in a single message, so they are applied at once.
+pub(crate) async fn execute_batch(
+    Path(instance): Path<String>,
+    Query(query): Query<ChannelQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(payload): Json<Vec<Operation>>,
+) -> Response {
+    if payload.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("The batch contains no operations.")),
+        )
+            .into_response();
+    }
+    submit_operations(
+        &instance,
+        query.channel,
+        query.client_id,
+        controller,
+        payload,
+    )
+    .await
+}
+
+/// Starts executing the steps of the sequence in the background and responds right away.
+pub(crate) async fn execute_sequence(
+    Path(instance): Path<String>,
+    Query(query): Query<ChannelQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(payload): Json<Vec<SequenceStep>>,
+) -> Response {
+    if payload.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("The sequence contains no steps.")),
+        )
+            .into_response();
+    }
+    if let Some(response) =
+        check_client_target(&instance, &query.channel, query.client_id, &controller).await
+    {
+        return response;
+    }
+    let operations: Vec<Operation> = payload.iter().map(|step| step.operation.clone()).collect();
+    // steps cannot wait for an approval without delaying the following steps
+    if controller.requires_approval(&instance, &operations) {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!(
+                "The sequence contains operations which require approval."
+            )),
+        )
+            .into_response();
+    }
+    tokio::spawn(crate::data::sequence::run_sequence(
+        controller,
+        instance,
+        query.channel,
+        query.client_id,
+        payload,
+    ));
+    StatusCode::OK.into_response()
 }
 
-async fn send_instance_message(
+/// Sends the operations to all renderers of the instance, only those of the channel or a single
+/// client or, if they require approval, responds with the pending change.
+pub(crate) async fn submit_operations(
     instance: &str,
-    server: Arc<WebsocketServer>,
-    message: InstanceMessage<'_>,
-) {
-    server
-        .send_message_to_instance_clients(instance, &message)
+    channel: Option<String>,
+    client_id: Option<usize>,
+    controller: Arc<ServerController>,
+    operations: Vec<Operation>,
+) -> Response {
+    if let Some(response) = check_client_target(instance, &channel, client_id, &controller).await {
+        return response;
+    }
+    if let Some(client_id) = client_id {
+        // approvals are meant for what goes on air, not for fixing up a single renderer
+        if controller.requires_approval(instance, &operations) {
+            return (
+                StatusCode::CONFLICT,
+                Json(json!(
+                    "Operations which require approval cannot be sent to a single client."
+                )),
+            )
+                .into_response();
+        }
+        controller
+            .send_client_operations(instance, client_id, operations)
+            .await;
+        return StatusCode::OK.into_response();
+    }
+    match controller
+        .submit_channel_operations(instance, channel.as_deref(), operations)
         .await
+    {
+        Some(change) => (StatusCode::ACCEPTED, Json(change)).into_response(),
+        None => StatusCode::OK.into_response(),
+    }
+}
+
+/// Responds with an error if the operations target a client which is not connected to the
+/// instance, or a channel and a client at once.
+async fn check_client_target(
+    instance: &str,
+    channel: &Option<String>,
+    client_id: Option<usize>,
+    controller: &ServerController,
+) -> Option<Response> {
+    let client_id = client_id?;
+    if channel.is_some() {
+        return Some(
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!("Only one of channel and client_id can be set.")),
+            )
+                .into_response(),
+        );
+    }
+    if !controller.is_instance_client(instance, client_id).await {
+        return Some(
+            (
+                StatusCode::NOT_FOUND,
+                Json(json!("The client is not connected to the instance.")),
+            )
+                .into_response(),
+        );
+    }
+    None
 }
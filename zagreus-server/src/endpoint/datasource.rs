@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::datasource::DataSource;
+
+pub(crate) async fn get_data_sources(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_data_sources().await {
+        Ok(sources) => Json(sources).into_response(),
+        Err(err) => {
+            error!("Could not load data sources: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load data sources.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn get_data_source(
+    Path(name): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_data_sources().await {
+        Ok(sources) => match sources.get(&name) {
+            Some(source) => Json(source).into_response(),
+            None => not_found_response(),
+        },
+        Err(err) => {
+            error!("Could not load data sources: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load data sources.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Adds or replaces the data source, which is polled from then on.
+pub(crate) async fn set_data_source(
+    Path(name): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(source): Json<DataSource>,
+) -> Response {
+    if !crate::fs::is_valid_name(&name) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("Invalid data source name.")),
+        )
+            .into_response();
+    }
+    let configuration = controller.get_configuration();
+    let file_folder = configuration.data_source_folder.as_deref();
+    if let Some(message) = crate::datasource::validate_data_source(&source, file_folder) {
+        return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response();
+    }
+    match controller.set_data_source(&name, source).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!("Could not store data source {}: {:#}.", name, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store data source.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn delete_data_source(
+    Path(name): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.delete_data_source(&name).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => not_found_response(),
+        Err(err) => {
+            error!("Could not delete data source {}: {:#}.", name, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not delete data source.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn get_data_source_status(
+    Path(name): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_data_sources().await {
+        Ok(sources) if sources.contains_key(&name) => {
+            Json(controller.get_data_source_status(&name).await).into_response()
+        }
+        Ok(_) => not_found_response(),
+        Err(err) => {
+            error!("Could not load data sources: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load data sources.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Polls the data source immediately, e.g. after the feed was corrected.
+pub(crate) async fn refresh_data_source(
+    Path(name): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.poll_data_source(&name).await {
+        Ok(Some(status)) => Json(status).into_response(),
+        Ok(None) => not_found_response(),
+        Err(err) => {
+            error!("Could not poll data source {}: {:#}.", name, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not poll data source.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+fn not_found_response() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!("Data source does not exist.")),
+    )
+        .into_response()
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::defaults::DataDefaults;
+use crate::endpoint::config::invalid_instance_name_response;
+
+pub(crate) async fn get_data_defaults(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_data_defaults(&instance).await {
+        Ok(defaults) => Json(defaults).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load data defaults of instance {}: {}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load data defaults.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn set_data_defaults(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(defaults): Json<DataDefaults>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if let Some(message) = crate::defaults::validate_defaults(&defaults) {
+        return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response();
+    }
+    match controller.set_data_defaults(&instance, defaults).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store data defaults of instance {}: {}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store data defaults.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn clear_instance(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.clear_instance(&instance).await {
+        Ok(Some(change)) => (StatusCode::ACCEPTED, Json(change)).into_response(),
+        Ok(None) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!("Could not clear instance {}: {}.", instance, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not clear instance.")),
+            )
+                .into_response()
+        }
+    }
+}
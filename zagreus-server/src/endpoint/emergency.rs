@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::emergency::{ActivationResult, EmergencyOverrides};
+use crate::endpoint::config::invalid_instance_name_response;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EmergencyStatus {
+    overrides: EmergencyOverrides,
+    /// Name of the override that is on air.
+    active: Option<String>,
+}
+
+pub(crate) async fn get_emergency_overrides(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_emergency_overrides(&instance).await {
+        Ok(overrides) => Json(EmergencyStatus {
+            overrides,
+            active: controller.get_active_override(&instance).await,
+        })
+        .into_response(),
+        Err(err) => {
+            error!(
+                "Could not load emergency overrides of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not load emergency overrides.")
+        }
+    }
+}
+
+pub(crate) async fn set_emergency_overrides(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(overrides): Json<EmergencyOverrides>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller
+        .set_emergency_overrides(&instance, overrides)
+        .await
+    {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store emergency overrides of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not store emergency overrides.")
+        }
+    }
+}
+
+pub(crate) async fn activate_emergency_override(
+    Path((instance, name)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller
+        .activate_emergency_override(&instance, &name)
+        .await
+    {
+        Ok(ActivationResult::Activated) => StatusCode::OK.into_response(),
+        Ok(ActivationResult::OverrideNotFound) => (
+            StatusCode::NOT_FOUND,
+            Json(json!("Emergency override does not exist.")),
+        )
+            .into_response(),
+        Err(err) => {
+            error!(
+                "Could not activate emergency override {} of instance {}: {}.",
+                name, instance, err
+            );
+            internal_error_response("Could not activate emergency override.")
+        }
+    }
+}
+
+pub(crate) async fn release_emergency_override(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if controller.release_emergency_override(&instance).await {
+        StatusCode::OK.into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!("No emergency override is active.")),
+        )
+            .into_response()
+    }
+}
+
+fn internal_error_response(message: &str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(message))).into_response()
+}
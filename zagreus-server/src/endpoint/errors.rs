@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+
+pub(crate) async fn get_client_errors(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    Json(controller.get_client_errors(&instance).await).into_response()
+}
+
+pub(crate) async fn get_error_snapshot(
+    Path((instance, error_id)): Path<(String, u64)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller
+        .get_error_snapshot_html(&instance, error_id)
+        .await
+    {
+        // the snapshot is served as plain text, so scripts of the template are not executed
+        // on the origin of the server
+        Some(html) => ([(header::CONTENT_TYPE, "text/plain; charset=utf-8")], html).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(json!("No snapshot exists for this error.")),
+        )
+            .into_response(),
+    }
+}
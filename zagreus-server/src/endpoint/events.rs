@@ -0,0 +1,38 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::extract::{Extension, Path, Query};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+
+const DEFAULT_POLL_TIMEOUT_SECONDS: u64 = 30;
+const MAX_POLL_TIMEOUT_SECONDS: u64 = 60;
+
+#[derive(Deserialize)]
+pub(crate) struct PollQuery {
+    cursor: Option<u64>,
+    timeout: Option<u64>,
+}
+
+pub(crate) async fn poll_events(
+    Path(instance): Path<String>,
+    Query(query): Query<PollQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let timeout = query
+        .timeout
+        .unwrap_or(DEFAULT_POLL_TIMEOUT_SECONDS)
+        .min(MAX_POLL_TIMEOUT_SECONDS);
+    Json(
+        controller
+            .poll_events(&instance, query.cursor, Duration::from_secs(timeout))
+            .await,
+    )
+    .into_response()
+}
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::feedback::FeedbackButtons;
+
+pub(crate) async fn get_button_feedback(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_button_feedback(&instance).await {
+        Ok(feedback) => Json(feedback).into_response(),
+        Err(err) => {
+            error!(
+                "Could not get button feedback of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not get button feedback.")
+        }
+    }
+}
+
+pub(crate) async fn get_feedback_buttons(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_feedback_buttons(&instance).await {
+        Ok(buttons) => Json(buttons).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load feedback buttons of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not load feedback buttons.")
+        }
+    }
+}
+
+pub(crate) async fn set_feedback_buttons(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(buttons): Json<FeedbackButtons>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if let Some(message) = crate::feedback::validate_buttons(&buttons) {
+        return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response();
+    }
+    match controller.set_feedback_buttons(&instance, buttons).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store feedback buttons of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not store feedback buttons.")
+        }
+    }
+}
+
+fn internal_error_response(message: &str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(message))).into_response()
+}
@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::flags::FeatureFlags;
+
+pub(crate) async fn get_global_feature_flags(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    flags_response(controller.get_global_feature_flags().await)
+}
+
+pub(crate) async fn set_global_feature_flags(
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(flags): Json<FeatureFlags>,
+) -> Response {
+    update_response(controller.set_global_feature_flags(flags).await)
+}
+
+pub(crate) async fn set_global_feature_flag(
+    Path(flag): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(enabled): Json<bool>,
+) -> Response {
+    update_response(controller.set_global_feature_flag(&flag, enabled).await)
+}
+
+pub(crate) async fn get_instance_feature_flags(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    flags_response(controller.get_instance_feature_flags(&instance).await)
+}
+
+pub(crate) async fn set_instance_feature_flags(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(flags): Json<FeatureFlags>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    update_response(
+        controller
+            .set_instance_feature_flags(&instance, flags)
+            .await,
+    )
+}
+
+pub(crate) async fn set_instance_feature_flag(
+    Path((instance, flag)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(enabled): Json<bool>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    update_response(
+        controller
+            .set_instance_feature_flag(&instance, &flag, enabled)
+            .await,
+    )
+}
+
+fn flags_response(flags: anyhow::Result<FeatureFlags>) -> Response {
+    match flags {
+        Ok(flags) => Json(flags).into_response(),
+        Err(err) => {
+            error!("Could not load feature flags: {}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load feature flags.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+fn update_response(result: anyhow::Result<()>) -> Response {
+    match result {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!("Could not update feature flags: {}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not update feature flags.")),
+            )
+                .into_response()
+        }
+    }
+}
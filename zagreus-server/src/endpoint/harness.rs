@@ -0,0 +1,39 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::harness::HarnessCall;
+
+pub(crate) async fn run_harness(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(calls): Json<Vec<HarnessCall>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if let Some(message) = crate::harness::validate_calls(&calls) {
+        return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response();
+    }
+    let configuration = controller.get_configuration();
+    match crate::harness::run_harness(&configuration, &instance, calls).await {
+        Ok(responses) => Json(responses).into_response(),
+        Err(err) => {
+            error!(
+                "Could not run harness for instance {}: {:#}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not run harness.")),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::localization::TranslationBundle;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LanguageSwitch {
+    /// Translation keys of labels which have no translation in the language.
+    missing_keys: Vec<String>,
+}
+
+pub(crate) async fn get_localization(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_localization(&instance).await {
+        Ok(localization) => Json(localization).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load localization of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not load localization.")
+        }
+    }
+}
+
+pub(crate) async fn set_translation_bundle(
+    Path((instance, language)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(bundle): Json<TranslationBundle>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller
+        .set_translation_bundle(&instance, &language, bundle)
+        .await
+    {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store translation bundle {} of instance {}: {}.",
+                language, instance, err
+            );
+            internal_error_response("Could not store translation bundle.")
+        }
+    }
+}
+
+pub(crate) async fn delete_translation_bundle(
+    Path((instance, language)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller
+        .delete_translation_bundle(&instance, &language)
+        .await
+    {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => bundle_not_found_response(),
+        Err(err) => {
+            error!(
+                "Could not delete translation bundle {} of instance {}: {}.",
+                language, instance, err
+            );
+            internal_error_response("Could not delete translation bundle.")
+        }
+    }
+}
+
+pub(crate) async fn set_localized_labels(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(labels): Json<BTreeMap<String, String>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.set_localized_labels(&instance, labels).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store localized labels of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not store localized labels.")
+        }
+    }
+}
+
+pub(crate) async fn set_active_language(
+    Path((instance, language)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.set_active_language(&instance, &language).await {
+        Ok(Some(missing_keys)) => Json(LanguageSwitch { missing_keys }).into_response(),
+        Ok(None) => bundle_not_found_response(),
+        Err(err) => {
+            error!(
+                "Could not switch language of instance {} to {}: {}.",
+                instance, language, err
+            );
+            internal_error_response("Could not switch language.")
+        }
+    }
+}
+
+fn bundle_not_found_response() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!("Translation bundle does not exist.")),
+    )
+        .into_response()
+}
+
+fn internal_error_response(message: &str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(message))).into_response()
+}
@@ -1,9 +1,49 @@
 use crate::ZAGREUS_VERSION;
 use axum::response::IntoResponse;
 
+pub mod action;
+pub mod analytics;
+pub mod approval;
+pub mod archive;
+pub mod asrun;
 pub mod asset;
+pub mod audit;
+pub mod client;
+pub mod computed;
+pub mod config;
 pub mod data;
+pub mod datasource;
+pub mod defaults;
+pub mod emergency;
+pub mod errors;
+pub mod events;
+pub mod feedback;
+pub mod flags;
+pub mod harness;
+pub mod localization;
+pub mod notes;
+pub mod precache;
+pub mod presence;
+pub mod preview;
+pub mod protocol;
+pub mod public;
+pub mod replace;
+pub mod resource;
+pub mod rotation;
 pub mod routes;
+pub mod routing;
+pub mod rules;
+pub mod rundown;
+pub mod runtime;
+pub mod schedule;
+pub mod secret;
+pub mod server;
+pub mod session;
+pub mod share;
+pub mod snapshot;
+pub mod state;
+pub mod topic;
+pub mod webhooks;
 pub mod websocket;
 
 async fn get_server_version() -> impl IntoResponse {
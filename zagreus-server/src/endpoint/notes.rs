@@ -0,0 +1,98 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::NaiveDate;
+use serde_json::json;
+use zagreus_client::dto::{NoteDto, OperatorNote};
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::notes::is_valid_note_text;
+use crate::presence::is_valid_operator_name;
+
+#[derive(Deserialize)]
+pub(crate) struct NotesQuery {
+    /// Show day, e.g. `2024-03-01`, the current one if not set.
+    day: Option<NaiveDate>,
+}
+
+pub(crate) async fn get_notes(
+    Query(query): Query<NotesQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_notes(query.day).await {
+        Ok(notes) => Json(notes).into_response(),
+        Err(err) => {
+            error!("Could not load notes: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load notes.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn add_note(
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(note): Json<NoteDto>,
+) -> Response {
+    if !is_valid_note_text(&note.text) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("The note is empty or too long.")),
+        )
+            .into_response();
+    }
+    if note
+        .operator
+        .as_deref()
+        .is_some_and(|operator| !is_valid_operator_name(operator))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("Invalid operator name.")),
+        )
+            .into_response();
+    }
+    if note
+        .instance
+        .as_deref()
+        .is_some_and(|instance| !crate::fs::is_valid_instance_name(instance))
+    {
+        return invalid_instance_name_response();
+    }
+    note_response(controller.add_note(note).await.map(Some))
+}
+
+pub(crate) async fn pin_note(
+    Path(id): Path<u64>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    note_response(controller.pin_note(id, true).await)
+}
+
+pub(crate) async fn unpin_note(
+    Path(id): Path<u64>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    note_response(controller.pin_note(id, false).await)
+}
+
+fn note_response(result: anyhow::Result<Option<OperatorNote>>) -> Response {
+    match result {
+        Ok(Some(note)) => Json(note).into_response(),
+        Ok(None) => (StatusCode::NOT_FOUND, Json(json!("Note not found."))).into_response(),
+        Err(err) => {
+            error!("Could not store note: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store note.")),
+            )
+                .into_response()
+        }
+    }
+}
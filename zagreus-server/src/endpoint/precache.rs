@@ -0,0 +1,98 @@
+use std::path::{Path as FsPath, PathBuf};
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::config::ZagreusServerConfig;
+use crate::controller::ServerController;
+use crate::data::asset::{AssetReference, AssetSource};
+use crate::endpoint::config::invalid_instance_name_response;
+use zagreus_client::dto::PrecacheAssetsDto;
+
+/// Instructs the clients of an instance to pre-fetch the given assets or, if none are given, all
+/// assets uploaded to the server.
+pub(crate) async fn precache_assets(
+    Path(instance): Path<String>,
+    Extension(assets_folder): Extension<PathBuf>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    payload: Option<Json<PrecacheAssetsDto>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+
+    let assets = match payload.and_then(|Json(payload)| payload.assets) {
+        Some(assets) => assets,
+        None => match get_uploaded_assets(&assets_folder).await {
+            Ok(assets) => assets,
+            Err(err) => {
+                error!("Could not list uploaded assets: {}.", err);
+                return (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!("Could not list uploaded assets.")),
+                )
+                    .into_response();
+            }
+        },
+    };
+    controller.precache_assets(&instance, assets).await;
+    StatusCode::OK.into_response()
+}
+
+pub(crate) async fn get_precache_progress(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    Json(controller.get_precache_progress(&instance).await).into_response()
+}
+
+/// Lists all files renderers of the instance need with their sizes and hashes, e.g. for warming
+/// caches of a CDN or proxy before the show.
+pub(crate) async fn get_prefetch_manifest(
+    Path(instance): Path<String>,
+    Extension(configuration): Extension<Arc<ZagreusServerConfig>>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let runtime_version = configuration
+        .get_instance_config(&instance)
+        .and_then(|instance_config| instance_config.runtime_version.as_deref());
+    match controller
+        .get_prefetch_manifest(&instance, runtime_version)
+        .await
+    {
+        Ok(manifest) => Json(manifest).into_response(),
+        Err(err) => {
+            error!("Could not create prefetch manifest: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not create prefetch manifest.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn get_uploaded_assets(assets_folder: &FsPath) -> anyhow::Result<Vec<AssetReference>> {
+    let mut assets = Vec::new();
+    let mut entries = tokio::fs::read_dir(assets_folder).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_file() {
+            assets.push(AssetReference {
+                asset: entry.file_name().to_string_lossy().into_owned(),
+                asset_source: AssetSource::Zagreus,
+            });
+        }
+    }
+    assets.sort_by(|a, b| a.asset.cmp(&b.asset));
+    Ok(assets)
+}
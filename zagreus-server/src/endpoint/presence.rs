@@ -0,0 +1,95 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::presence::{is_valid_operator_name, InstanceLock, LockResult, ReleaseResult};
+use zagreus_client::dto::LockRequest;
+
+#[derive(Deserialize)]
+pub(crate) struct OperatorQuery {
+    pub(crate) operator: String,
+}
+
+pub(crate) async fn get_presence(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    Json(controller.get_presence(&instance).await).into_response()
+}
+
+pub(crate) async fn lock_instance(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(request): Json<LockRequest>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &request.operator) {
+        return response;
+    }
+    match controller
+        .lock_instance(&instance, &request.operator, request.force)
+        .await
+    {
+        LockResult::Acquired(lock) | LockResult::Overridden { lock, .. } => {
+            Json(lock).into_response()
+        }
+        LockResult::Locked(lock) => locked_response(&lock),
+    }
+}
+
+pub(crate) async fn release_instance_lock(
+    Path(instance): Path<String>,
+    Query(query): Query<OperatorQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &query.operator) {
+        return response;
+    }
+    match controller
+        .release_instance_lock(&instance, &query.operator)
+        .await
+    {
+        ReleaseResult::Released => StatusCode::OK.into_response(),
+        ReleaseResult::NotLocked => (
+            StatusCode::NOT_FOUND,
+            Json(json!("Instance is not locked.")),
+        )
+            .into_response(),
+        ReleaseResult::Locked(lock) => locked_response(&lock),
+    }
+}
+
+pub(crate) fn validate_names(instance: &str, operator: &str) -> Option<Response> {
+    if !crate::fs::is_valid_instance_name(instance) {
+        return Some(invalid_instance_name_response());
+    }
+    if !is_valid_operator_name(operator) {
+        return Some(
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!("Invalid operator name.")),
+            )
+                .into_response(),
+        );
+    }
+    None
+}
+
+fn locked_response(lock: &InstanceLock) -> Response {
+    (
+        StatusCode::CONFLICT,
+        Json(json!(format!(
+            "Instance is locked by operator {}.",
+            lock.operator
+        ))),
+    )
+        .into_response()
+}
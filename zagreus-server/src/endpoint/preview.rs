@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::fakedata::{FakeDataRequestDto, DEFAULT_PREVIEW_CHANNEL};
+use crate::state::StagedTakeResult;
+use zagreus_client::dto::ChannelQuery;
+
+/// Sends fake data to the renderers of the preview channel without changing the state.
+pub(crate) async fn send_fake_data(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    request: Option<Json<FakeDataRequestDto>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    if request.channel.as_deref() == Some("") {
+        return (StatusCode::BAD_REQUEST, Json(json!("Invalid channel."))).into_response();
+    }
+    match controller.send_fake_data(&instance, request).await {
+        Ok(data) => Json(data).into_response(),
+        Err(err) => {
+            error!(
+                "Could not send fake data to instance {}: {:#}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not send fake data.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Returns the data staged on the preview channel, or the channel of the query.
+pub(crate) async fn get_staged_data(
+    Path(instance): Path<String>,
+    Query(query): Query<ChannelQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let channel = query.channel.as_deref().unwrap_or(DEFAULT_PREVIEW_CHANNEL);
+    Json(controller.get_staged_state(&instance, channel).await).into_response()
+}
+
+/// Puts the data staged on the preview channel, or the channel of the query, on all renderers.
+pub(crate) async fn take_staged_data(
+    Path(instance): Path<String>,
+    Query(query): Query<ChannelQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let channel = query.channel.as_deref().unwrap_or(DEFAULT_PREVIEW_CHANNEL);
+    match controller.take_staged_state(&instance, channel).await {
+        StagedTakeResult::Taken(taken) => Json(taken).into_response(),
+        StagedTakeResult::Pending(change) => (StatusCode::ACCEPTED, Json(change)).into_response(),
+        StagedTakeResult::NothingStaged => (
+            StatusCode::NOT_FOUND,
+            Json(json!("No data is staged on the channel.")),
+        )
+            .into_response(),
+    }
+}
@@ -0,0 +1,71 @@
+use axum::response::IntoResponse;
+use axum::Json;
+use schemars::gen::SchemaSettings;
+use serde_json::json;
+
+use crate::websocket::message::{get_protocol_schema, InstanceMessage, PROTOCOL_VERSION};
+use zagreus_client::dto::{
+    FetchAssetDto, ManipulateClassDto, Operation, PinResourceDto, PrecacheAssetsDto,
+    RuntimeVersionDto, SequenceStep, SetImageSourceDto, SetTextDto, SignAssetDto,
+    UploadAssetResponseDto,
+};
+
+pub(crate) async fn get_schema() -> impl IntoResponse {
+    Json(json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "schema": get_protocol_schema(),
+    }))
+}
+
+pub(crate) async fn get_schemas() -> impl IntoResponse {
+    Json(json!({
+        "protocolVersion": PROTOCOL_VERSION,
+        "components": get_api_components(),
+    }))
+}
+
+/// Returns the schemas of the request and response bodies of the API and of the websocket
+/// messages as OpenAPI components, so client SDKs can be generated from them. The schemas are
+/// derived from the types at build time, so they cannot diverge from the server.
+fn get_api_components() -> serde_json::Value {
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    generator.subschema_for::<SetTextDto>();
+    generator.subschema_for::<ManipulateClassDto>();
+    generator.subschema_for::<SetImageSourceDto>();
+    generator.subschema_for::<Operation>();
+    generator.subschema_for::<SequenceStep>();
+    generator.subschema_for::<PrecacheAssetsDto>();
+    generator.subschema_for::<PinResourceDto>();
+    generator.subschema_for::<FetchAssetDto>();
+    generator.subschema_for::<SignAssetDto>();
+    generator.subschema_for::<UploadAssetResponseDto>();
+    generator.subschema_for::<RuntimeVersionDto>();
+    generator.subschema_for::<InstanceMessage>();
+    json!({ "schemas": generator.take_definitions() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_components() {
+        let components = get_api_components();
+        let schemas = components["schemas"].as_object().unwrap();
+        assert!(schemas.contains_key("SetTextDto"));
+        assert!(schemas.contains_key("InstanceMessage"));
+        assert_eq!(json!(["id", "text"]), schemas["SetTextDto"]["required"]);
+
+        // every reference can be resolved within the components
+        let serialized = components.to_string();
+        for reference in serialized.split("\"$ref\":\"").skip(1) {
+            let name = reference
+                .split('"')
+                .next()
+                .unwrap()
+                .strip_prefix("#/components/schemas/")
+                .unwrap();
+            assert!(schemas.contains_key(name), "{name} is missing");
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::config::ZagreusServerConfig;
+use crate::controller::ServerController;
+
+/// Returns the allowlisted data of the instance. Responses can be cached for the configured time
+/// and carry an entity tag, so clients polling frequently mostly get `304 Not Modified`.
+pub(crate) async fn get_public_state(
+    Path(instance): Path<String>,
+    headers: HeaderMap,
+    Extension(configuration): Extension<Arc<ZagreusServerConfig>>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    // instances without allowlist are not distinguishable from instances that do not exist
+    let Some(allowlist) = configuration
+        .get_instance_config(&instance)
+        .and_then(|instance_config| instance_config.public_state.as_ref())
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!("Instance does not publish any data.")),
+        )
+            .into_response();
+    };
+
+    let state = match controller.get_state(&instance).await {
+        Ok(state) => state.to_public(allowlist),
+        Err(err) => {
+            error!("Could not load state of instance {}: {}.", instance, err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load state.")),
+            )
+                .into_response();
+        }
+    };
+    let body = match serde_json::to_vec(&state) {
+        Ok(body) => body,
+        Err(err) => {
+            error!(
+                "Could not serialize public state of instance {}: {}.",
+                instance, err
+            );
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = format!("\"{:x}\"", Sha256::digest(&body));
+    let cache_headers = [
+        (header::ETAG, etag.clone()),
+        (
+            header::CACHE_CONTROL,
+            format!("public, max-age={}", allowlist.max_age_seconds),
+        ),
+        (header::ACCESS_CONTROL_ALLOW_ORIGIN, String::from("*")),
+    ];
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, cache_headers).into_response();
+    }
+    (
+        cache_headers,
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json"),
+        )],
+        body,
+    )
+        .into_response()
+}
@@ -0,0 +1,40 @@
+use std::sync::Arc;
+
+use axum::extract::Extension;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::replace::ReplaceRequest;
+
+pub(crate) async fn replace_texts(
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(request): Json<ReplaceRequest>,
+) -> Response {
+    if let Some(instance) = &request.instance {
+        if !crate::fs::is_valid_instance_name(instance) {
+            return invalid_instance_name_response();
+        }
+    }
+    if request.find.is_empty() {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("The text to find must not be empty.")),
+        )
+            .into_response();
+    }
+    match controller.replace_texts(&request).await {
+        Ok(report) => Json(report).into_response(),
+        Err(err) => {
+            error!("Could not replace texts: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not replace texts.")),
+            )
+                .into_response()
+        }
+    }
+}
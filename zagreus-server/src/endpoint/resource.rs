@@ -0,0 +1,208 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::{Extension, Query};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::asset::{get_url_extension, write_asset_file};
+use crate::resources::PinnedResource;
+use zagreus_client::dto::PinResourceDto;
+
+/// Extensions of the files which can be pinned.
+const RESOURCE_EXTENSIONS: &[&str] = &["css", "js", "json", "woff2", "woff", "ttf", "otf"];
+
+#[derive(Deserialize)]
+pub(crate) struct ResourceQuery {
+    url: String,
+}
+
+pub(crate) async fn get_resources(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_pinned_resources().await {
+        Ok(resources) => Json(resources).into_response(),
+        Err(err) => {
+            error!("Could not load pinned resources: {}.", err);
+            load_error_response()
+        }
+    }
+}
+
+/// Downloads an external resource (through the configured proxy), verifies its integrity and
+/// stores it in the assets folder.
+pub(crate) async fn pin_resource(
+    Extension(assets_folder): Extension<PathBuf>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(payload): Json<PinResourceDto>,
+) -> Response {
+    let resource = match controller.fetch_url(&payload.url).await {
+        Ok(resource) => resource,
+        Err(err) => {
+            error!("Could not fetch resource from {}: {}.", payload.url, err);
+            return (
+                StatusCode::BAD_GATEWAY,
+                Json(json!("Could not fetch resource.")),
+            )
+                .into_response();
+        }
+    };
+
+    let data = resource.data.clone();
+    let expected_integrity = payload.integrity.clone();
+    let integrity = controller
+        .run_blocking(move || match expected_integrity {
+            Some(integrity) => {
+                crate::resources::verify_integrity(&data, &integrity).then_some(integrity)
+            }
+            None => Some(crate::resources::compute_integrity(&data)),
+        })
+        .await;
+    let integrity = match integrity {
+        Ok(Some(integrity)) => integrity,
+        Ok(None) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!("Resource does not match the expected integrity.")),
+            )
+                .into_response();
+        }
+        Err(err) => {
+            error!(
+                "Could not verify integrity of resource {}: {}.",
+                payload.url, err
+            );
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not verify resource.")),
+            )
+                .into_response();
+        }
+    };
+
+    // pinned resources are served from the origin of the server, so only types which cannot
+    // run as a page (e.g. HTML) are accepted
+    let extension = resource
+        .content_type
+        .as_deref()
+        .and_then(get_resource_extension)
+        .or_else(|| {
+            get_url_extension(&payload.url)
+                .filter(|extension| RESOURCE_EXTENSIONS.contains(&extension.as_str()))
+        });
+    let Some(extension) = extension else {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!(
+                "Could not determine resource type. Only CSS, JS, JSON and font files can be pinned."
+            )),
+        )
+            .into_response();
+    };
+
+    let file = match write_asset_file(&controller, &assets_folder, &extension, resource.data).await
+    {
+        Ok(file) => file,
+        Err(err) => {
+            error!("Could not store resource: {}.", err);
+            return (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store resource.")),
+            )
+                .into_response();
+        }
+    };
+
+    let pinned_resource = PinnedResource { file, integrity };
+    match controller
+        .set_pinned_resource(&payload.url, pinned_resource.clone())
+        .await
+    {
+        Ok(()) => Json(pinned_resource).into_response(),
+        Err(err) => {
+            error!("Could not pin resource {}: {}.", payload.url, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not pin resource.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn delete_resource(
+    Query(query): Query<ResourceQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.delete_pinned_resource(&query.url).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => resource_not_found_response(),
+        Err(err) => {
+            error!("Could not delete pinned resource {}: {}.", query.url, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not delete resource.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Redirects to the pinned local copy of an external resource. Resources which were not pinned are
+/// never fetched, so renderers do not depend on the internet.
+pub(crate) async fn get_pinned_resource(
+    Query(query): Query<ResourceQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_pinned_resources().await {
+        Ok(resources) => match resources.get(&query.url) {
+            Some(resource) => (
+                [(header::CACHE_CONTROL, "no-cache")],
+                Redirect::temporary(&format!("/assets/{}", resource.file)),
+            )
+                .into_response(),
+            None => {
+                warn!("Resource {} was requested but is not pinned.", query.url);
+                resource_not_found_response()
+            }
+        },
+        Err(err) => {
+            error!("Could not load pinned resources: {}.", err);
+            load_error_response()
+        }
+    }
+}
+
+fn get_resource_extension(content_type: &str) -> Option<String> {
+    let mime_type = content_type.split(';').next()?.trim();
+    let extension = match mime_type {
+        "text/css" => "css",
+        "text/javascript" | "application/javascript" => "js",
+        "application/json" => "json",
+        "font/woff2" => "woff2",
+        "font/woff" => "woff",
+        "font/ttf" => "ttf",
+        "font/otf" => "otf",
+        _ => return None,
+    };
+    Some(extension.to_owned())
+}
+
+fn resource_not_found_response() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!("Resource is not pinned.")),
+    )
+        .into_response()
+}
+
+fn load_error_response() -> Response {
+    (
+        StatusCode::INTERNAL_SERVER_ERROR,
+        Json(json!("Could not load pinned resources.")),
+    )
+        .into_response()
+}
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::audit::DateRangeQuery;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::rotation::Rotations;
+
+pub(crate) async fn get_rotations(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_rotations(&instance).await {
+        Ok(rotations) => Json(rotations).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load rotations of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not load rotations.")
+        }
+    }
+}
+
+pub(crate) async fn set_rotations(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(rotations): Json<Rotations>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if let Some(message) = crate::rotation::validate_rotations(&rotations) {
+        return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response();
+    }
+    match controller.set_rotations(&instance, rotations).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store rotations of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not store rotations.")
+        }
+    }
+}
+
+pub(crate) async fn get_rotation_report(
+    Path(instance): Path<String>,
+    Query(range): Query<DateRangeQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller
+        .get_rotation_report(&instance, range.from, range.to)
+        .await
+    {
+        Ok(report) => Json(report).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load rotation report of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not load rotation report.")
+        }
+    }
+}
+
+fn internal_error_response(message: &str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(message))).into_response()
+}
@@ -1,37 +1,42 @@
 use axum::error_handling::HandleErrorLayer;
+use axum::extract::{DefaultBodyLimit, State};
 use axum::http::uri::InvalidUri;
-use axum::http::{Request, StatusCode, Uri};
-use axum::Router;
+use axum::http::{header, HeaderValue, Request, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::{Json, Router};
 use hyper::Body;
+use serde_json::json;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
 use tower::ServiceBuilder;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
 use tower_http::services::ServeDir;
+use tower_http::set_header::SetResponseHeaderLayer;
 
+use crate::assets::signing::SIGNED_ASSETS_PATH;
+use crate::assets::upload::MAX_CHUNK_SIZE;
+use crate::auth::AuthError;
 use crate::config::ZagreusServerConfig;
 use crate::controller::ServerController;
 use crate::endpoint;
-use crate::endpoint::websocket::ws_handler;
-use crate::endpoint::{data, get_server_version};
-use crate::fs::get_assets_folder;
-use crate::websocket::server::WebsocketServer;
-
-#[derive(Deserialize, Serialize)]
-struct SetTextDto {
-    id: String,
-    text: String,
-}
-
-#[derive(Deserialize, Serialize)]
-struct ManipulateClassDto {
-    id: String,
-    class: String,
-}
+use crate::endpoint::websocket::{
+    control_handler, feedback_stream_handler, presence_stream_handler, public_state_stream_handler,
+    state_stream_handler, ws_handler,
+};
+use crate::endpoint::{
+    action, analytics, approval, archive, asrun, audit, client, computed, config, data, datasource,
+    defaults, emergency, errors, events, feedback, flags, get_server_version, harness,
+    localization, notes, precache, presence, preview, protocol, public, replace, resource,
+    rotation, routing, rules, rundown, runtime, schedule, secret, server, session, share, snapshot,
+    state, topic, webhooks,
+};
+use crate::fs::{get_assets_folder, get_runtimes_folder};
+use crate::metrics::METRICS_PATH;
 
-#[derive(Deserialize, Serialize)]
-struct SetImageSourceDto {
-    id: String,
-    asset: String,
-}
+const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Time for which browsers may cache the answer to a preflight request.
+const CORS_MAX_AGE: Duration = Duration::from_secs(600);
 
 // e.g. rewrite /static/template/my-template to /static/template/my-template/
 // TODO parse url better (what if there are multiple dots in the asset name?)
@@ -39,7 +44,7 @@ struct SetImageSourceDto {
 async fn map_rewrite_template_url(req: Request<Body>) -> Result<Request<Body>, StatusCode> {
     let uri = req.uri().to_string();
     if uri.starts_with("/static/template/") && !uri.ends_with('/') {
-        let last_part = uri.split('/').last();
+        let last_part = uri.split('/').next_back();
 
         if let Some(last_part) = last_part {
             if !last_part.contains('.') {
@@ -61,39 +66,124 @@ async fn map_rewrite_template_url(req: Request<Body>) -> Result<Request<Body>, S
     Ok(req)
 }
 
+/// Rejects requests to protected routes without a valid API key. The keys are taken from the
+/// current configuration, so reloaded keys apply to the next request.
+async fn authenticate(
+    State(configuration): State<watch::Receiver<Arc<ZagreusServerConfig>>>,
+    req: Request<Body>,
+) -> Result<Request<Body>, Response> {
+    let configuration = configuration.borrow().clone();
+    let result = crate::auth::authorize(
+        &configuration.auth,
+        req.uri().path(),
+        req.method(),
+        req.headers(),
+        req.uri().query(),
+    );
+    match result {
+        Ok(()) => Ok(req),
+        Err(AuthError::Unauthorized) => {
+            debug!("Rejected unauthenticated request to {}.", req.uri().path());
+            Err((
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                Json(json!("Missing or invalid API key.")),
+            )
+                .into_response())
+        }
+        Err(AuthError::Forbidden) => {
+            Err((StatusCode::FORBIDDEN, Json(json!("API key may only read."))).into_response())
+        }
+        Err(AuthError::CannotApprove) => Err((
+            StatusCode::FORBIDDEN,
+            Json(json!("API key may not approve changes.")),
+        )
+            .into_response()),
+    }
+}
+
+/// Allows web pages on the configured origins (e.g. a control panel served by another host) to
+/// call the API. The origins are taken from the current configuration, so reloaded origins apply
+/// to the next request.
+fn get_cors_layer(configuration: watch::Receiver<Arc<ZagreusServerConfig>>) -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::predicate(move |origin, _| {
+            origin
+                .to_str()
+                .is_ok_and(|origin| configuration.borrow().is_allowed_origin(origin))
+        }))
+        .allow_methods(AllowMethods::mirror_request())
+        .allow_headers(AllowHeaders::mirror_request())
+        .max_age(CORS_MAX_AGE)
+}
+
 pub fn get_router(
     configuration: &ZagreusServerConfig,
-    ws_server: Arc<WebsocketServer>,
     server_controller: Arc<ServerController>,
 ) -> anyhow::Result<Router> {
-    let mut router = Router::new().route("/api/version", axum::routing::get(get_server_version));
+    let mut router = Router::new()
+        .route("/api/version", axum::routing::get(get_server_version))
+        .route(
+            "/api/protocol/schema",
+            axum::routing::get(protocol::get_schema),
+        )
+        .route("/api/schemas", axum::routing::get(protocol::get_schemas));
+    // all files are streamed in chunks of this size and support range requests (e.g. for seeking
+    // in videos)
+    let buffer_size = configuration.file_read_buffer_size;
 
     let assets_folder = get_assets_folder(&configuration.data_folder)?;
+    // assets are stored under their content hash, so they never change and can be cached forever
     let assets_router = Router::new().nest_service(
         "/assets",
-        axum::routing::get_service(ServeDir::new(&assets_folder)).handle_error(|err| async move {
-            error!("error occurred when serving assets: {}.", err)
-        }),
+        axum::routing::get_service(
+            ServiceBuilder::new()
+                .layer(SetResponseHeaderLayer::overriding(
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_static(IMMUTABLE_CACHE_CONTROL),
+                ))
+                .service(ServeDir::new(&assets_folder).with_buf_chunk_size(buffer_size)),
+        )
+        .handle_error(|err| async move { error!("error occurred when serving assets: {}.", err) }),
     );
     router = router.merge(assets_router);
 
+    // the same assets for clients with a signed URL, e.g. an edge CDN serving viewer overlays
+    let signed_assets_router = Router::new()
+        .nest_service(
+            SIGNED_ASSETS_PATH,
+            axum::routing::get_service(
+                ServeDir::new(&assets_folder).with_buf_chunk_size(buffer_size),
+            )
+            .handle_error(|err| async move {
+                error!("error occurred when serving signed assets: {}.", err)
+            }),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            server_controller.clone(),
+            endpoint::asset::verify_signed_asset,
+        ));
+    router = router.merge(signed_assets_router);
+
     let static_router = Router::new().nest(
         "/static",
         Router::new()
             .route(
                 "/zagreus-runtime.js",
-                axum::routing::get_service(tower_http::services::ServeFile::new(
-                    "zagreus-runtime.js",
-                ))
+                axum::routing::get_service(
+                    tower_http::services::ServeFile::new("zagreus-runtime.js")
+                        .with_buf_chunk_size(buffer_size),
+                )
                 .handle_error(|err| async move {
                     error!("error occurred when serving zagreus runtime: {}.", err)
                 }),
             )
             .route(
                 "/zagreus-runtime.js.map",
-                axum::routing::get_service(tower_http::services::ServeFile::new(
-                    "zagreus-runtime.js.map",
-                ))
+                axum::routing::get_service(
+                    tower_http::services::ServeFile::new("zagreus-runtime.js.map")
+                        .with_buf_chunk_size(buffer_size),
+                )
                 .handle_error(|err| async move {
                     error!(
                         "error occurred when serving zagreus runtime source map: {}.",
@@ -103,24 +193,73 @@ pub fn get_router(
             )
             .nest_service(
                 "/swagger-docs",
-                axum::routing::get_service(tower_http::services::ServeDir::new("swagger-docs"))
-                    .handle_error(|err| async move {
-                        error!("error occurred when serving swagger docs: {}.", err)
-                    }),
+                axum::routing::get_service(
+                    tower_http::services::ServeDir::new("swagger-docs")
+                        .with_buf_chunk_size(buffer_size),
+                )
+                .handle_error(|err| async move {
+                    error!("error occurred when serving swagger docs: {}.", err)
+                }),
             ),
     );
     router = router.merge(static_router);
 
+    // routes for serving and registering runtime versions
+    let runtimes_folder = get_runtimes_folder(&configuration.data_folder)?;
+    let runtime_router = Router::new()
+        .nest_service(
+            "/static/runtime",
+            axum::routing::get_service(
+                ServeDir::new(&runtimes_folder).with_buf_chunk_size(buffer_size),
+            )
+            .handle_error(|err| async move {
+                error!("error occurred when serving runtime: {}.", err)
+            }),
+        )
+        .route(
+            "/static/instance/:instance/zagreus-runtime.js",
+            axum::routing::get(runtime::get_instance_runtime),
+        )
+        .route(
+            "/api/runtime",
+            axum::routing::get(runtime::get_runtime_versions),
+        )
+        .route(
+            "/api/runtime/:version",
+            axum::routing::post(runtime::upload_runtime),
+        )
+        .layer(axum::extract::Extension(runtimes_folder))
+        .layer(axum::extract::Extension(Arc::new(configuration.clone())));
+    router = router.merge(runtime_router);
+
     // route for websocket router
     let websocket_router = Router::new()
         .route("/ws/instance/:instance", axum::routing::get(ws_handler))
-        .layer(axum::extract::Extension(server_controller));
+        .route(
+            "/ws/instance/:instance/state",
+            axum::routing::get(state_stream_handler),
+        )
+        .route(
+            "/ws/instance/:instance/feedback",
+            axum::routing::get(feedback_stream_handler),
+        )
+        .route(
+            "/ws/instance/:instance/presence",
+            axum::routing::get(presence_stream_handler),
+        )
+        .route(
+            "/ws/control/:instance",
+            axum::routing::get(control_handler)
+                .layer(axum::extract::Extension(Arc::new(configuration.clone()))),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
     router = router.merge(websocket_router);
 
     // routes for manipulating template instances
     let manipulate_templates_router = Router::new().nest(
         "/api/instance/:instance",
         Router::new()
+            .route("/", axum::routing::delete(archive::delete_instance))
             .route("/data/text", axum::routing::post(data::set_text))
             .route("/data/class/add", axum::routing::post(data::add_class))
             .route(
@@ -132,17 +271,442 @@ pub fn get_router(
                 axum::routing::post(data::execute_animation),
             )
             .route("/data/image", axum::routing::post(data::set_image_source))
-            .layer(axum::extract::Extension(ws_server)),
+            .route("/data/batch", axum::routing::post(data::execute_batch))
+            .route(
+                "/data/sequence",
+                axum::routing::post(data::execute_sequence),
+            )
+            .route(
+                "/pending",
+                axum::routing::get(approval::get_pending_changes),
+            )
+            .route(
+                "/pending/:id",
+                axum::routing::delete(approval::reject_change),
+            )
+            .route(
+                "/pending/:id/approve",
+                axum::routing::post(approval::approve_change),
+            )
+            .route(
+                "/config",
+                axum::routing::get(config::get_config_document).put(config::set_config_document),
+            )
+            .route(
+                "/feature-flags",
+                axum::routing::get(flags::get_instance_feature_flags)
+                    .put(flags::set_instance_feature_flags),
+            )
+            .route(
+                "/feature-flags/:flag",
+                axum::routing::put(flags::set_instance_feature_flag),
+            )
+            .route(
+                "/precache",
+                axum::routing::get(precache::get_precache_progress).post(precache::precache_assets),
+            )
+            .route(
+                "/prefetch-manifest",
+                axum::routing::get(precache::get_prefetch_manifest)
+                    .layer(axum::extract::Extension(Arc::new(configuration.clone()))),
+            )
+            .route("/clients", axum::routing::get(client::get_instance_clients))
+            .route("/events/poll", axum::routing::get(events::poll_events))
+            .route("/errors", axum::routing::get(errors::get_client_errors))
+            .route(
+                "/errors/:error_id/snapshot",
+                axum::routing::get(errors::get_error_snapshot),
+            )
+            .route(
+                "/state",
+                axum::routing::get(state::get_state).put(state::set_state),
+            )
+            .route(
+                "/defaults",
+                axum::routing::get(defaults::get_data_defaults).put(defaults::set_data_defaults),
+            )
+            .route("/clear", axum::routing::post(defaults::clear_instance))
+            .route(
+                "/rules",
+                axum::routing::get(rules::get_rules).put(rules::set_rules),
+            )
+            .route(
+                "/webhooks",
+                axum::routing::get(webhooks::get_webhooks).put(webhooks::set_webhooks),
+            )
+            .route(
+                "/preview/fake-data",
+                axum::routing::post(preview::send_fake_data),
+            )
+            .route("/preview", axum::routing::get(preview::get_staged_data))
+            .route(
+                "/preview/take",
+                axum::routing::post(preview::take_staged_data),
+            )
+            .route(
+                "/computed",
+                axum::routing::get(computed::get_computed_elements)
+                    .put(computed::set_computed_elements),
+            )
+            .route("/harness", axum::routing::post(harness::run_harness))
+            .route(
+                "/rundown",
+                axum::routing::get(rundown::get_rundown).put(rundown::set_rundown),
+            )
+            .route(
+                "/rundown/next",
+                axum::routing::post(rundown::take_next_page),
+            )
+            .route(
+                "/rundown/take/:index",
+                axum::routing::post(rundown::take_page_by_index),
+            )
+            .route(
+                "/feedback",
+                axum::routing::get(feedback::get_button_feedback),
+            )
+            .route(
+                "/feedback/buttons",
+                axum::routing::get(feedback::get_feedback_buttons)
+                    .put(feedback::set_feedback_buttons),
+            )
+            .route(
+                "/localization",
+                axum::routing::get(localization::get_localization),
+            )
+            .route(
+                "/localization/bundle/:language",
+                axum::routing::put(localization::set_translation_bundle)
+                    .delete(localization::delete_translation_bundle),
+            )
+            .route(
+                "/localization/labels",
+                axum::routing::put(localization::set_localized_labels),
+            )
+            .route(
+                "/localization/language/:language",
+                axum::routing::post(localization::set_active_language),
+            )
+            .route("/as-run", axum::routing::get(asrun::get_as_run_log))
+            .route("/as-run/csv", axum::routing::get(asrun::export_as_run_csv))
+            .route(
+                "/rotations",
+                axum::routing::get(rotation::get_rotations).put(rotation::set_rotations),
+            )
+            .route(
+                "/rotations/report",
+                axum::routing::get(rotation::get_rotation_report),
+            )
+            .route(
+                "/emergency",
+                axum::routing::get(emergency::get_emergency_overrides)
+                    .put(emergency::set_emergency_overrides),
+            )
+            .route(
+                "/emergency/release",
+                axum::routing::post(emergency::release_emergency_override),
+            )
+            .route(
+                "/emergency/activate/:name",
+                axum::routing::post(emergency::activate_emergency_override),
+            )
+            .route("/snapshot", axum::routing::get(snapshot::get_snapshots))
+            .route(
+                "/snapshot/:snapshot",
+                axum::routing::get(snapshot::get_snapshot)
+                    .post(snapshot::save_snapshot)
+                    .put(snapshot::set_snapshot)
+                    .delete(snapshot::delete_snapshot),
+            )
+            .route(
+                "/snapshot/:snapshot/apply",
+                axum::routing::post(snapshot::apply_snapshot),
+            )
+            .route("/secret", axum::routing::get(secret::get_secret_names))
+            .route(
+                "/secret/:name",
+                axum::routing::put(secret::set_secret).delete(secret::delete_secret),
+            )
+            .route("/topics", axum::routing::get(topic::get_retained_topics))
+            .route(
+                "/topics/*topic",
+                axum::routing::post(topic::publish_topic).delete(topic::delete_retained_topic),
+            )
+            .route("/share", axum::routing::post(share::create_share_link))
+            .route("/clone", axum::routing::post(archive::clone_instance))
+            .route("/presence", axum::routing::get(presence::get_presence))
+            .route(
+                "/lock",
+                axum::routing::post(presence::lock_instance)
+                    .delete(presence::release_instance_lock),
+            )
+            .layer(axum::extract::Extension(assets_folder.clone()))
+            .layer(axum::extract::Extension(server_controller.clone())),
     );
     router = router.merge(manipulate_templates_router);
 
+    // routes for share links, which grant read-only access to a single instance
+    let share_router = Router::new()
+        .route(
+            "/share/:token",
+            axum::routing::get(share::get_shared_preview),
+        )
+        .route(
+            "/share/:token/state",
+            axum::routing::get(share::get_shared_state),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(share_router);
+
+    // route for the read-only data which instances publish
+    let public_router = Router::new()
+        .route(
+            "/public/instance/:instance/state",
+            axum::routing::get(public::get_public_state),
+        )
+        .route(
+            "/public/instance/:instance/ws",
+            axum::routing::get(public_state_stream_handler),
+        )
+        .layer(axum::extract::Extension(Arc::new(configuration.clone())))
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(public_router);
+
+    // routes for control surfaces which discover and trigger actions with GET requests
+    let actions_router = Router::new()
+        .route(
+            "/api/actions/:template_name",
+            axum::routing::get(action::get_action_catalogue),
+        )
+        .route(
+            "/api/actions/:template_name/animation/:animation",
+            axum::routing::get(action::trigger_animation),
+        )
+        .route(
+            "/api/actions/:template_name/text/:element",
+            axum::routing::get(action::trigger_set_text),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(actions_router);
+
+    // routes for global feature flags
+    let feature_flags_router = Router::new()
+        .route(
+            "/api/feature-flags",
+            axum::routing::get(flags::get_global_feature_flags)
+                .put(flags::set_global_feature_flags),
+        )
+        .route(
+            "/api/feature-flags/:flag",
+            axum::routing::put(flags::set_global_feature_flag),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(feature_flags_router);
+
+    // routes for routing instances to output channels
+    let routing_router = Router::new()
+        .route("/api/routing", axum::routing::get(routing::get_routes))
+        .route(
+            "/api/routing/:instance",
+            axum::routing::put(routing::set_route).delete(routing::delete_route),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(routing_router);
+
+    // routes for the notes of the operators
+    let notes_router = Router::new()
+        .route(
+            "/api/notes",
+            axum::routing::get(notes::get_notes).post(notes::add_note),
+        )
+        .route(
+            "/api/notes/:id/pin",
+            axum::routing::post(notes::pin_note).delete(notes::unpin_note),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(notes_router);
+
+    // routes for recording sessions of a show
+    let session_router = Router::new()
+        .route(
+            "/api/session",
+            axum::routing::get(session::get_active_session),
+        )
+        .route(
+            "/api/session/start",
+            axum::routing::post(session::start_session),
+        )
+        .route(
+            "/api/session/stop",
+            axum::routing::post(session::stop_session),
+        )
+        .route(
+            "/api/session/:name/timeline",
+            axum::routing::get(session::get_session_timeline),
+        )
+        .route(
+            "/api/session/:name/timeline/html",
+            axum::routing::get(session::export_session_timeline_html),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(session_router);
+
+    // route for the connected clients of all instances
+    let clients_router = Router::new()
+        .route("/api/clients", axum::routing::get(client::get_clients))
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(clients_router);
+
+    // routes for exporting, importing and editing the data of multiple instances
+    let archive_router = Router::new()
+        .route("/api/instances", axum::routing::get(archive::get_instances))
+        .route(
+            "/api/instances/export",
+            axum::routing::get(archive::export_instances),
+        )
+        .route(
+            "/api/instances/import",
+            axum::routing::post(archive::import_instances),
+        )
+        .route(
+            "/api/instances/replace",
+            axum::routing::post(replace::replace_texts),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(archive_router);
+
+    // routes for scheduling operations on instances
+    let schedule_router = Router::new()
+        .route(
+            "/api/schedule",
+            axum::routing::get(schedule::get_scheduled_items).post(schedule::schedule_item),
+        )
+        .route(
+            "/api/schedule/:id",
+            axum::routing::get(schedule::get_scheduled_item)
+                .put(schedule::replace_scheduled_item)
+                .delete(schedule::delete_scheduled_item),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(schedule_router);
+
+    // routes for polling external data sources
+    let data_source_router = Router::new()
+        .route(
+            "/api/datasource",
+            axum::routing::get(datasource::get_data_sources),
+        )
+        .route(
+            "/api/datasource/:name",
+            axum::routing::get(datasource::get_data_source)
+                .put(datasource::set_data_source)
+                .delete(datasource::delete_data_source),
+        )
+        .route(
+            "/api/datasource/:name/status",
+            axum::routing::get(datasource::get_data_source_status),
+        )
+        .route(
+            "/api/datasource/:name/refresh",
+            axum::routing::post(datasource::refresh_data_source),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(data_source_router);
+
+    // routes for usage analytics and audit log
+    let analytics_router = Router::new()
+        .route(
+            "/api/analytics",
+            axum::routing::get(analytics::get_analytics),
+        )
+        .route(
+            "/api/analytics/csv",
+            axum::routing::get(audit::export_analytics_csv),
+        )
+        .route("/api/audit", axum::routing::get(audit::get_audit_log))
+        .route(
+            "/api/audit/csv",
+            axum::routing::get(audit::export_audit_log_csv),
+        )
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(analytics_router);
+
+    // routes for server management
+    let server_router = Router::new()
+        .route(
+            "/api/server/selftest",
+            axum::routing::post(server::run_self_test),
+        )
+        .route("/api/server/stats", axum::routing::get(server::get_stats))
+        .route(
+            "/api/server/gc",
+            axum::routing::get(server::get_garbage_report).post(server::collect_garbage),
+        )
+        .route(METRICS_PATH, axum::routing::get(server::get_metrics))
+        .route(
+            "/api/server/handover",
+            axum::routing::put(server::receive_handover),
+        )
+        .layer(axum::extract::Extension(Arc::new(configuration.clone())))
+        .layer(axum::extract::Extension(server_controller.clone()));
+    router = router.merge(server_router);
+
     // route for manipulating assets
     let assets_router = Router::new()
         .route(
             "/api/asset",
-            axum::routing::post(endpoint::asset::upload_asset),
+            axum::routing::post(endpoint::asset::upload_asset)
+                .layer(DefaultBodyLimit::max(configuration.max_asset_upload_size)),
+        )
+        .route(
+            "/api/asset/url",
+            axum::routing::post(endpoint::asset::fetch_asset),
+        )
+        .route(
+            "/api/asset/versions",
+            axum::routing::get(endpoint::asset::get_asset_versions),
+        )
+        .route(
+            "/api/asset/signed-url",
+            axum::routing::post(endpoint::asset::create_signed_asset_url)
+                .layer(axum::extract::Extension(Arc::new(configuration.clone()))),
+        )
+        .route(
+            "/api/asset/versions/:name",
+            axum::routing::get(endpoint::asset::get_latest_asset)
+                .delete(endpoint::asset::delete_asset),
+        )
+        .route(
+            "/api/asset/versions/:name/info",
+            axum::routing::get(endpoint::asset::get_asset_info),
+        )
+        .route(
+            "/api/asset/uploads",
+            axum::routing::post(endpoint::asset::create_upload),
+        )
+        .route(
+            "/api/asset/uploads/:id",
+            axum::routing::get(endpoint::asset::get_upload)
+                .patch(endpoint::asset::upload_chunk)
+                .delete(endpoint::asset::delete_upload)
+                .layer(DefaultBodyLimit::max(MAX_CHUNK_SIZE)),
+        )
+        .route(
+            "/api/asset/uploads/:id/complete",
+            axum::routing::post(endpoint::asset::complete_upload),
+        )
+        .route(
+            "/api/resource",
+            axum::routing::get(resource::get_resources)
+                .post(resource::pin_resource)
+                .delete(resource::delete_resource),
         )
-        .layer(axum::extract::Extension(assets_folder));
+        .route(
+            "/static/resource",
+            axum::routing::get(resource::get_pinned_resource),
+        )
+        .layer(axum::extract::Extension(assets_folder))
+        .layer(axum::extract::Extension(server_controller.clone()));
     router = router.merge(assets_router);
 
     let middleware_stack = ServiceBuilder::new()
@@ -152,7 +716,271 @@ pub fn get_router(
                 format!("Unhandled internal error: {error}"),
             )
         }))
-        .layer(axum::middleware::map_request(map_rewrite_template_url));
-
+        .layer(axum::middleware::from_fn(crate::timing::trace_commands))
+        .layer(axum::middleware::from_fn_with_state(
+            server_controller.get_metrics(),
+            crate::metrics::record_requests,
+        ))
+        // preflight requests carry no API key, so they are answered before authentication
+        .layer(get_cors_layer(server_controller.subscribe_configuration()))
+        .layer(axum::middleware::map_request(map_rewrite_template_url))
+        .layer(axum::middleware::map_request_with_state(
+            server_controller.subscribe_configuration(),
+            authenticate,
+        ));
     Ok(router.layer(middleware_stack))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::operation::Operation;
+    use crate::websocket::server::WebsocketServer;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_asset_range_request() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let configuration = ZagreusServerConfig {
+            data_folder: path.clone(),
+            file_read_buffer_size: 16,
+            ..ZagreusServerConfig::default()
+        };
+        let data: Vec<u8> = (0..=255).collect();
+        std::fs::write(get_assets_folder(&path).unwrap().join("clip.mp4"), &data).unwrap();
+
+        let controller = Arc::new(
+            ServerController::new(
+                &configuration,
+                Arc::new(WebsocketServer::new(
+                    path.clone(),
+                    configuration.connection_limits.clone(),
+                    configuration.heartbeat.clone(),
+                    configuration.template_events.clone(),
+                )),
+            )
+            .unwrap(),
+        );
+        let router = get_router(&configuration, controller).unwrap();
+        let request = Request::builder()
+            .uri("/assets/clip.mp4")
+            .header(header::RANGE, "bytes=100-199")
+            .body(Body::empty())
+            .unwrap();
+        let response = router.oneshot(request).await.unwrap();
+
+        assert_eq!(StatusCode::PARTIAL_CONTENT, response.status());
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&data[100..200], &body[..]);
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let configuration = ZagreusServerConfig {
+            data_folder: path.clone(),
+            allowed_origins: vec![String::from("https://panel.example.com")],
+            auth: serde_json::from_value(json!({"apiKeys": [{"name": "panel", "key": "key"}]}))
+                .unwrap(),
+            ..ZagreusServerConfig::default()
+        };
+        let controller = Arc::new(
+            ServerController::new(
+                &configuration,
+                Arc::new(WebsocketServer::new(
+                    path.clone(),
+                    configuration.connection_limits.clone(),
+                    configuration.heartbeat.clone(),
+                    configuration.template_events.clone(),
+                )),
+            )
+            .unwrap(),
+        );
+        let router = get_router(&configuration, controller).unwrap();
+        let preflight = |origin: &str| {
+            Request::builder()
+                .method("OPTIONS")
+                .uri("/api/instance/scoreboard/data/text")
+                .header(header::ORIGIN, origin)
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "authorization")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(preflight("https://panel.example.com"))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        let headers = response.headers();
+        assert_eq!(
+            "https://panel.example.com",
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN]
+        );
+        assert_eq!("POST", headers[header::ACCESS_CONTROL_ALLOW_METHODS]);
+        assert_eq!(
+            "authorization",
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS]
+        );
+
+        let response = router
+            .oneshot(preflight("https://other.example.com"))
+            .await
+            .unwrap();
+        assert!(!response
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_state_requiring_approval_is_held() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let configuration = ZagreusServerConfig {
+            data_folder: path.clone(),
+            instances: serde_json::from_value(json!({
+                "news": {"approval": {"operations": ["setText"], "elements": ["StrapLine"]}}
+            }))
+            .unwrap(),
+            ..ZagreusServerConfig::default()
+        };
+        let controller = Arc::new(
+            ServerController::new(
+                &configuration,
+                Arc::new(WebsocketServer::new(
+                    path.clone(),
+                    configuration.connection_limits.clone(),
+                    configuration.heartbeat.clone(),
+                    configuration.template_events.clone(),
+                )),
+            )
+            .unwrap(),
+        );
+        let router = get_router(&configuration, controller.clone()).unwrap();
+        let put_state = |state: serde_json::Value| {
+            Request::builder()
+                .method("PUT")
+                .uri("/api/instance/news/state")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(state.to_string()))
+                .unwrap()
+        };
+
+        let response = router
+            .clone()
+            .oneshot(put_state(json!({"texts": {"Clock": "12:00"}})))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = router
+            .oneshot(put_state(
+                json!({"texts": {"Clock": "12:00", "StrapLine": "Breaking news"}}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(StatusCode::ACCEPTED, response.status());
+        let state = controller.get_state("news").await.unwrap();
+        assert_eq!(None, state.texts.get("StrapLine"));
+
+        // only the changed element is held back
+        let pending = controller.get_pending_changes("news").await;
+        assert_eq!(1, pending.len());
+        assert_eq!(
+            vec![Operation::SetText {
+                id: String::from("StrapLine"),
+                text: String::from("Breaking news"),
+                tween_duration: None,
+            }],
+            pending[0].operations
+        );
+        controller.approve_change("news", pending[0].id).await;
+        let state = controller.get_state("news").await.unwrap();
+        assert_eq!(
+            Some(&String::from("Breaking news")),
+            state.texts.get("StrapLine")
+        );
+        assert_eq!(Some(&String::from("12:00")), state.texts.get("Clock"));
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let configuration = ZagreusServerConfig {
+            data_folder: path.clone(),
+            auth: serde_json::from_value(json!({"apiKeys": [{"name": "script", "key": "key"}]}))
+                .unwrap(),
+            ..ZagreusServerConfig::default()
+        };
+        let controller = Arc::new(
+            ServerController::new(
+                &configuration,
+                Arc::new(WebsocketServer::new(
+                    path.clone(),
+                    configuration.connection_limits.clone(),
+                    configuration.heartbeat.clone(),
+                    configuration.template_events.clone(),
+                )),
+            )
+            .unwrap(),
+        );
+        let router = get_router(&configuration, controller).unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service()),
+        );
+
+        let client = zagreus_client::ZagreusClient::new(&format!("http://{address}")).unwrap();
+        let err = client.get_state("scoreboard").await.unwrap_err();
+        let err = err.downcast_ref::<zagreus_client::ApiError>().unwrap();
+        assert_eq!(StatusCode::UNAUTHORIZED, err.status);
+
+        let client = client.with_api_key("key");
+        let text = zagreus_client::dto::SetTextDto {
+            id: String::from("HomeScore"),
+            text: String::from("1"),
+            tween_duration: None,
+        };
+        let query = zagreus_client::dto::ChannelQuery::default();
+        assert_eq!(
+            None,
+            client.set_text("scoreboard", &text, &query).await.unwrap()
+        );
+        let preview = zagreus_client::dto::ChannelQuery {
+            channel: Some(String::from("preview")),
+            ..Default::default()
+        };
+        let preview_text = zagreus_client::dto::SetTextDto {
+            text: String::from("3"),
+            ..text
+        };
+        client
+            .set_text("scoreboard", &preview_text, &preview)
+            .await
+            .unwrap();
+        let mut control = client.connect_control("scoreboard").await.unwrap();
+        let result = control
+            .send(vec![Operation::SetText {
+                id: String::from("AwayScore"),
+                text: String::from("2"),
+                tween_duration: None,
+            }])
+            .await
+            .unwrap();
+        assert!(matches!(result, zagreus_client::dto::ControlResult::Sent));
+        let state = client.get_state("scoreboard").await.unwrap();
+        assert_eq!(json!({"HomeScore": "1", "AwayScore": "2"}), state["texts"]);
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
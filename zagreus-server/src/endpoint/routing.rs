@@ -0,0 +1,86 @@
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use zagreus_client::dto::RouteDto;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+
+pub(crate) async fn get_routes(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_routes().await {
+        Ok(routes) => Json(routes).into_response(),
+        Err(err) => {
+            error!("Could not load routes: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load routes.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn set_route(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(payload): Json<RouteDto>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if !payload
+        .channels
+        .iter()
+        .all(|channel| crate::fs::is_valid_name(channel))
+    {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("Channel name contains invalid character")),
+        )
+            .into_response();
+    }
+    route_response(
+        &instance,
+        controller
+            .set_route(&instance, Some(payload.channels))
+            .await,
+    )
+}
+
+pub(crate) async fn delete_route(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.set_route(&instance, None).await {
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!("The instance is not routed.")),
+        )
+            .into_response(),
+        result => route_response(&instance, result),
+    }
+}
+
+fn route_response(instance: &str, result: anyhow::Result<Option<BTreeSet<String>>>) -> Response {
+    match result {
+        Ok(_) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!("Could not route instance {}: {:#}.", instance, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not route instance.")),
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::rules::Rules;
+
+pub(crate) async fn get_rules(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_rules(&instance).await {
+        Ok(rules) => Json(rules).into_response(),
+        Err(err) => {
+            error!("Could not load rules of instance {}: {}.", instance, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load rules.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn set_rules(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(rules): Json<Rules>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if let Some(message) = crate::rules::validate_rules(&rules) {
+        return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response();
+    }
+    match controller.set_rules(&instance, rules).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!("Could not store rules of instance {}: {}.", instance, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store rules.")),
+            )
+                .into_response()
+        }
+    }
+}
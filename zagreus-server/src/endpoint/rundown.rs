@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::rundown::{Rundown, TakeResult};
+
+pub(crate) async fn get_rundown(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_rundown(&instance).await {
+        Ok(rundown) => Json(rundown).into_response(),
+        Err(err) => {
+            error!("Could not load rundown of instance {}: {}.", instance, err);
+            internal_error_response("Could not load rundown.")
+        }
+    }
+}
+
+pub(crate) async fn set_rundown(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(rundown): Json<Rundown>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if let Some(message) = crate::rundown::validate_rundown(&rundown) {
+        return (StatusCode::BAD_REQUEST, Json(json!(message))).into_response();
+    }
+    match controller.set_rundown(&instance, rundown).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!("Could not store rundown of instance {}: {}.", instance, err);
+            internal_error_response("Could not store rundown.")
+        }
+    }
+}
+
+pub(crate) async fn take_next_page(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    take_page(&instance, None, controller).await
+}
+
+pub(crate) async fn take_page_by_index(
+    Path((instance, index)): Path<(String, usize)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    take_page(&instance, Some(index), controller).await
+}
+
+async fn take_page(
+    instance: &str,
+    index: Option<usize>,
+    controller: Arc<ServerController>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.take_page(instance, index).await {
+        Ok(TakeResult::Taken(rundown)) => Json(rundown).into_response(),
+        Ok(TakeResult::PageNotFound) => {
+            (StatusCode::NOT_FOUND, Json(json!("Page does not exist."))).into_response()
+        }
+        Ok(TakeResult::SnapshotNotFound(snapshot)) => (
+            StatusCode::NOT_FOUND,
+            Json(json!(format!("Snapshot {snapshot} does not exist."))),
+        )
+            .into_response(),
+        Ok(TakeResult::RequiresApproval) => (
+            StatusCode::CONFLICT,
+            Json(json!(
+                "The page contains operations which require approval."
+            )),
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Could not take page of instance {}: {}.", instance, err);
+            internal_error_response("Could not take page.")
+        }
+    }
+}
+
+fn internal_error_response(message: &str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(message))).into_response()
+}
@@ -0,0 +1,176 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use axum::body::Bytes;
+use axum::extract::{Extension, Path as UrlPath};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::config::ZagreusServerConfig;
+use zagreus_client::dto::RuntimeVersionDto;
+
+pub(crate) const RUNTIME_FILE_NAME: &str = "zagreus-runtime.js";
+const RUNTIME_SOURCE_MAP_FILE_NAME: &str = "zagreus-runtime.js.map";
+const RUNTIME_DATA_FIELD: &str = "file";
+const RUNTIME_SOURCE_MAP_FIELD: &str = "sourceMap";
+
+/// Redirects to the runtime bundle that is configured for the instance, so that templates can
+/// load their runtime from a stable URL. Expired instances get no runtime.
+pub(crate) async fn get_instance_runtime(
+    UrlPath(instance): UrlPath<String>,
+    Extension(configuration): Extension<Arc<ZagreusServerConfig>>,
+) -> Response {
+    let instance_config = configuration.get_instance_config(&instance);
+    let expired = instance_config
+        .and_then(|instance_config| instance_config.expiry.as_ref())
+        .is_some_and(|expiry| crate::expiry::is_expired(expiry, chrono::Utc::now()));
+    if expired {
+        return (StatusCode::GONE, Json(json!("Instance has expired."))).into_response();
+    }
+    let runtime_version =
+        instance_config.and_then(|instance_config| instance_config.runtime_version.as_ref());
+    match runtime_version {
+        Some(version) => {
+            Redirect::temporary(&format!("/static/runtime/{version}/{RUNTIME_FILE_NAME}"))
+                .into_response()
+        }
+        None => Redirect::temporary(&format!("/static/{RUNTIME_FILE_NAME}")).into_response(),
+    }
+}
+
+pub(crate) async fn get_runtime_versions(
+    Extension(runtimes_folder): Extension<PathBuf>,
+) -> Response {
+    match list_runtime_versions(&runtimes_folder).await {
+        Ok(versions) => Json(versions).into_response(),
+        Err(err) => {
+            error!("Could not list runtime versions: {}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not list runtime versions.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn upload_runtime(
+    UrlPath(version): UrlPath<String>,
+    Extension(runtimes_folder): Extension<PathBuf>,
+    multipart: axum::extract::Multipart,
+) -> impl IntoResponse {
+    if !is_valid_version(&version) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("Runtime version contains invalid character")),
+        );
+    }
+
+    match get_runtime_data(multipart).await {
+        Ok((runtime_data, source_map_data)) => {
+            match write_runtime_files(
+                &runtimes_folder.join(&version),
+                runtime_data,
+                source_map_data,
+            )
+            .await
+            {
+                Ok(()) => (StatusCode::OK, Json(json!(RuntimeVersionDto { version }))),
+                Err(err) => {
+                    error!("Could not store runtime version {}: {}.", version, err);
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!("Could not store runtime.")),
+                    )
+                }
+            }
+        }
+        Err(err) => {
+            error!("Could not upload runtime to server: {}.", err);
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!("Could not parse upload request.")),
+            )
+        }
+    }
+}
+
+fn is_valid_version(version: &str) -> bool {
+    !version.is_empty()
+        && !version.contains("..")
+        && version
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_')
+}
+
+async fn list_runtime_versions(runtimes_folder: &Path) -> anyhow::Result<Vec<RuntimeVersionDto>> {
+    let mut versions = Vec::new();
+    let mut entries = tokio::fs::read_dir(runtimes_folder).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.path().join(RUNTIME_FILE_NAME).is_file() {
+            versions.push(RuntimeVersionDto {
+                version: entry.file_name().to_string_lossy().into_owned(),
+            });
+        }
+    }
+    versions.sort_by(|a, b| a.version.cmp(&b.version));
+    Ok(versions)
+}
+
+async fn get_runtime_data(
+    mut multipart: axum::extract::Multipart,
+) -> anyhow::Result<(Bytes, Option<Bytes>)> {
+    let mut runtime_data: Option<Bytes> = None;
+    let mut source_map_data: Option<Bytes> = None;
+    while let Some(field) = multipart.next_field().await? {
+        if let Some(name) = field.name() {
+            if name.eq(RUNTIME_DATA_FIELD) {
+                runtime_data = Some(field.bytes().await?);
+            } else if name.eq(RUNTIME_SOURCE_MAP_FIELD) {
+                source_map_data = Some(field.bytes().await?);
+            }
+        }
+    }
+    match runtime_data {
+        Some(runtime_data) => Ok((runtime_data, source_map_data)),
+        None => Err(anyhow!("Multipart request did not have expected format.")),
+    }
+}
+
+async fn write_runtime_files(
+    runtime_folder: &Path,
+    runtime_data: Bytes,
+    source_map_data: Option<Bytes>,
+) -> anyhow::Result<()> {
+    tokio::fs::create_dir_all(runtime_folder).await?;
+    tokio::fs::write(runtime_folder.join(RUNTIME_FILE_NAME), runtime_data).await?;
+    if let Some(source_map_data) = source_map_data {
+        tokio::fs::write(
+            runtime_folder.join(RUNTIME_SOURCE_MAP_FILE_NAME),
+            source_map_data,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_valid_version() {
+        assert!(is_valid_version("0.1.0"));
+        assert!(is_valid_version("1.2.3-beta_1"));
+
+        assert!(!is_valid_version(""));
+        assert!(!is_valid_version(".."));
+        assert!(!is_valid_version("1..2"));
+        assert!(!is_valid_version("../0.1.0"));
+        assert!(!is_valid_version("0.1.0/evil"));
+        assert!(!is_valid_version("0.1.0 beta"));
+    }
+}
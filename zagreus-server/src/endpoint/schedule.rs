@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::scheduler::{ScheduleRequest, ScheduledItem};
+
+#[derive(Deserialize)]
+pub(crate) struct ScheduleQuery {
+    instance: Option<String>,
+}
+
+pub(crate) async fn get_scheduled_items(
+    Query(query): Query<ScheduleQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller
+        .get_scheduled_items(query.instance.as_deref())
+        .await
+    {
+        Ok(items) => Json(items).into_response(),
+        Err(err) => {
+            error!("Could not load schedule: {:#}.", err);
+            internal_error_response("Could not load schedule.")
+        }
+    }
+}
+
+pub(crate) async fn get_scheduled_item(
+    Path(id): Path<u64>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    item_response(controller.get_scheduled_item(id).await)
+}
+
+pub(crate) async fn schedule_item(
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(request): Json<ScheduleRequest>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&request.instance) {
+        return invalid_instance_name_response();
+    }
+    let at = match validate_request(&controller, &request) {
+        Ok(at) => at,
+        Err((status, message)) => return (status, Json(json!(message))).into_response(),
+    };
+    match controller.schedule_item(request, at).await {
+        Ok(item) => Json(item).into_response(),
+        Err(err) => {
+            error!("Could not store schedule: {:#}.", err);
+            internal_error_response("Could not store schedule.")
+        }
+    }
+}
+
+pub(crate) async fn replace_scheduled_item(
+    Path(id): Path<u64>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(request): Json<ScheduleRequest>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&request.instance) {
+        return invalid_instance_name_response();
+    }
+    let at = match validate_request(&controller, &request) {
+        Ok(at) => at,
+        Err((status, message)) => return (status, Json(json!(message))).into_response(),
+    };
+    item_response(controller.replace_scheduled_item(id, request, at).await)
+}
+
+pub(crate) async fn delete_scheduled_item(
+    Path(id): Path<u64>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    item_response(controller.delete_scheduled_item(id).await)
+}
+
+/// Returns the time at which the item is due or the status and message rejecting it.
+fn validate_request(
+    controller: &ServerController,
+    request: &ScheduleRequest,
+) -> Result<DateTime<Utc>, (StatusCode, String)> {
+    let now = Utc::now();
+    let at = request
+        .get_due(now)
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    if crate::scheduler::is_missed(at, now) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            String::from("The time is in the past."),
+        ));
+    }
+    // scheduled operations cannot wait for an approval without going on air late
+    if controller.requires_approval(&request.instance, &request.operations) {
+        return Err((
+            StatusCode::CONFLICT,
+            String::from("The scheduled item contains operations which require approval."),
+        ));
+    }
+    Ok(at)
+}
+
+fn item_response(item: anyhow::Result<Option<ScheduledItem>>) -> Response {
+    match item {
+        Ok(Some(item)) => Json(item).into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(json!("Scheduled item not found.")),
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Could not update schedule: {:#}.", err);
+            internal_error_response("Could not update schedule.")
+        }
+    }
+}
+
+fn internal_error_response(message: &str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(message))).into_response()
+}
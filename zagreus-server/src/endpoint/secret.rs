@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::secrets::is_valid_secret_name;
+
+pub(crate) async fn get_secret_names(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_secret_names(&instance).await {
+        Ok(names) => Json(names).into_response(),
+        Err(err) => {
+            error!("Could not load secrets of instance {}: {}.", instance, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load secrets.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn set_secret(
+    Path((instance, name)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(value): Json<String>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if !is_valid_secret_name(&name) {
+        return invalid_secret_name_response();
+    }
+    match controller.set_secret(&instance, &name, &value).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store secret {} of instance {}: {}.",
+                name, instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not store secret.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn delete_secret(
+    Path((instance, name)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    if !is_valid_secret_name(&name) {
+        return invalid_secret_name_response();
+    }
+    match controller.delete_secret(&instance, &name).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, Json(json!("Secret does not exist."))).into_response(),
+        Err(err) => {
+            error!(
+                "Could not delete secret {} of instance {}: {}.",
+                name, instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not delete secret.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+fn invalid_secret_name_response() -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!("Secret name contains invalid character")),
+    )
+        .into_response()
+}
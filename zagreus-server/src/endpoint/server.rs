@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Query};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::config::ZagreusServerConfig;
+use crate::controller::ServerController;
+use crate::handover::HandoverState;
+
+pub(crate) async fn run_self_test(
+    Extension(configuration): Extension<Arc<ZagreusServerConfig>>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> impl IntoResponse {
+    Json(crate::selftest::run_self_test(&configuration, Some(&controller)).await)
+}
+
+#[derive(Deserialize)]
+pub(crate) struct GarbageQuery {
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Reports the unreferenced files of the data folder without removing them.
+pub(crate) async fn get_garbage_report(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    garbage_response(controller.collect_garbage(true).await)
+}
+
+/// Removes the unreferenced files of the data folder, or only reports them with `dry_run=true`.
+pub(crate) async fn collect_garbage(
+    Query(query): Query<GarbageQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    garbage_response(controller.collect_garbage(query.dry_run).await)
+}
+
+fn garbage_response(report: anyhow::Result<crate::gc::GarbageReport>) -> Response {
+    match report {
+        Ok(report) => Json(report).into_response(),
+        Err(err) => {
+            error!("Could not collect unreferenced files: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not collect unreferenced files.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Receives the state of a draining server process which was replaced by this one.
+pub(crate) async fn receive_handover(
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(handover): Json<HandoverState>,
+) -> Response {
+    // a draining process may still accept the request itself if it could not stop listening
+    if handover.process_id == std::process::id() {
+        return (
+            StatusCode::CONFLICT,
+            Json(json!("State cannot be handed over to the same process.")),
+        )
+            .into_response();
+    }
+    controller.restore_handover_state(handover).await;
+    StatusCode::OK.into_response()
+}
+
+/// Returns the metrics in the Prometheus text exposition format.
+pub(crate) async fn get_metrics(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, crate::metrics::CONTENT_TYPE)],
+        controller.render_metrics().await,
+    )
+}
+
+pub(crate) async fn get_stats(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> impl IntoResponse {
+    Json(controller.get_stats().await)
+}
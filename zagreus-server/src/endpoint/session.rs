@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use zagreus_client::dto::SessionDto;
+
+use crate::controller::ServerController;
+use crate::session::SessionTimeline;
+
+pub(crate) async fn get_active_session(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.get_active_session().await {
+        Some(session) => Json(session).into_response(),
+        None => no_session_response(),
+    }
+}
+
+pub(crate) async fn start_session(
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(session): Json<SessionDto>,
+) -> Response {
+    if !crate::fs::is_valid_name(&session.name) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!("Invalid session name.")),
+        )
+            .into_response();
+    }
+    match controller.start_session(&session.name).await {
+        Ok(Some(session)) => {
+            info!("Started recording session {}.", session.name);
+            Json(session).into_response()
+        }
+        Ok(None) => (
+            StatusCode::CONFLICT,
+            Json(json!("Another session is being recorded.")),
+        )
+            .into_response(),
+        Err(err) => {
+            error!("Could not start session {}: {:#}.", session.name, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not start session.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn stop_session(
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match controller.stop_session().await {
+        Ok(Some(session)) => {
+            info!(
+                "Stopped recording session {} with {} entries.",
+                session.name, session.entries
+            );
+            Json(session).into_response()
+        }
+        Ok(None) => no_session_response(),
+        Err(err) => {
+            error!("Could not stop session: {:#}.", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not stop session.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn get_session_timeline(
+    Path(name): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match load_timeline(&controller, &name).await {
+        Ok(timeline) => Json(timeline).into_response(),
+        Err(response) => response,
+    }
+}
+
+/// Exports the timeline as a standalone HTML page.
+pub(crate) async fn export_session_timeline_html(
+    Path(name): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    match load_timeline(&controller, &name).await {
+        Ok(timeline) => (
+            [(header::CONTENT_TYPE, "text/html; charset=utf-8")],
+            crate::session::write_html(&timeline),
+        )
+            .into_response(),
+        Err(response) => response,
+    }
+}
+
+async fn load_timeline(
+    controller: &ServerController,
+    name: &str,
+) -> Result<SessionTimeline, Response> {
+    if !crate::fs::is_valid_name(name) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!("Invalid session name.")),
+        )
+            .into_response());
+    }
+    match controller.get_session_timeline(name).await {
+        Ok(Some(timeline)) => Ok(timeline),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!("The session was not recorded.")),
+        )
+            .into_response()),
+        Err(err) => {
+            error!("Could not load session {}: {:#}.", name, err);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load session.")),
+            )
+                .into_response())
+        }
+    }
+}
+
+fn no_session_response() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!("No session is being recorded.")),
+    )
+        .into_response()
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::share::{ShareClaims, MAX_SHARE_LINK_VALIDITY_SECONDS};
+
+/// Validity of share links if none is requested.
+const DEFAULT_SHARE_LINK_VALIDITY_SECONDS: i64 = 12 * 60 * 60;
+
+const PREVIEW_PAGE: &str = include_str!("../share/preview.html");
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ShareLinkQuery {
+    valid_seconds: Option<i64>,
+}
+
+pub(crate) async fn create_share_link(
+    Path(instance): Path<String>,
+    Query(query): Query<ShareLinkQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let valid_seconds = query
+        .valid_seconds
+        .unwrap_or(DEFAULT_SHARE_LINK_VALIDITY_SECONDS);
+    if !(1..=MAX_SHARE_LINK_VALIDITY_SECONDS).contains(&valid_seconds) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(json!(format!(
+                "Share links must be valid for 1 to {MAX_SHARE_LINK_VALIDITY_SECONDS} seconds."
+            ))),
+        )
+            .into_response();
+    }
+    match controller
+        .create_share_link(&instance, chrono::Duration::seconds(valid_seconds))
+        .await
+    {
+        Ok(link) => {
+            info!(
+                "Created share link for instance {} valid until {}.",
+                instance, link.expires
+            );
+            Json(link).into_response()
+        }
+        Err(err) => {
+            error!(
+                "Could not create share link for instance {}: {}.",
+                instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not create share link.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn get_shared_preview(
+    Path(token): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if let Err(response) = verify_share_link(&controller, &token).await {
+        return response;
+    }
+    (
+        [
+            (header::CONTENT_TYPE, "text/html; charset=utf-8"),
+            (header::REFERRER_POLICY, "no-referrer"),
+        ],
+        PREVIEW_PAGE,
+    )
+        .into_response()
+}
+
+pub(crate) async fn get_shared_state(
+    Path(token): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    let claims = match verify_share_link(&controller, &token).await {
+        Ok(claims) => claims,
+        Err(response) => return response,
+    };
+    match controller.get_state(&claims.instance).await {
+        Ok(state) => Json(json!({
+            "instance": claims.instance,
+            "expires": claims.expires,
+            "state": state,
+        }))
+        .into_response(),
+        Err(err) => {
+            error!(
+                "Could not load state of instance {}: {}.",
+                claims.instance, err
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not load state.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+async fn verify_share_link(
+    controller: &ServerController,
+    token: &str,
+) -> Result<ShareClaims, Response> {
+    match controller.verify_share_link(token).await {
+        Ok(Some(claims)) => Ok(claims),
+        Ok(None) => Err((
+            StatusCode::FORBIDDEN,
+            Json(json!("Share link is invalid or expired.")),
+        )
+            .into_response()),
+        Err(err) => {
+            error!("Could not verify share link: {}.", err);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not verify share link.")),
+            )
+                .into_response())
+        }
+    }
+}
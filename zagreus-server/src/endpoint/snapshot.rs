@@ -0,0 +1,159 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::snapshot::{is_valid_snapshot_name, ApplyResult};
+use crate::state::InstanceState;
+
+pub(crate) async fn get_snapshots(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_snapshots(&instance).await {
+        Ok(snapshots) => Json(snapshots).into_response(),
+        Err(err) => {
+            error!(
+                "Could not load snapshots of instance {}: {}.",
+                instance, err
+            );
+            internal_error_response("Could not load snapshots.")
+        }
+    }
+}
+
+pub(crate) async fn get_snapshot(
+    Path((instance, name)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &name) {
+        return response;
+    }
+    match controller.get_snapshot(&instance, &name).await {
+        Ok(Some(snapshot)) => Json(snapshot).into_response(),
+        Ok(None) => snapshot_not_found_response(),
+        Err(err) => {
+            error!(
+                "Could not load snapshot {} of instance {}: {}.",
+                name, instance, err
+            );
+            internal_error_response("Could not load snapshot.")
+        }
+    }
+}
+
+pub(crate) async fn save_snapshot(
+    Path((instance, name)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &name) {
+        return response;
+    }
+    match controller.save_snapshot(&instance, &name).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not save snapshot {} of instance {}: {}.",
+                name, instance, err
+            );
+            internal_error_response("Could not save snapshot.")
+        }
+    }
+}
+
+pub(crate) async fn set_snapshot(
+    Path((instance, name)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(state): Json<InstanceState>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &name) {
+        return response;
+    }
+    match controller.set_snapshot(&instance, &name, state).await {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(err) => {
+            error!(
+                "Could not store snapshot {} of instance {}: {}.",
+                name, instance, err
+            );
+            internal_error_response("Could not store snapshot.")
+        }
+    }
+}
+
+pub(crate) async fn apply_snapshot(
+    Path((instance, name)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &name) {
+        return response;
+    }
+    match controller.apply_snapshot(&instance, &name).await {
+        Ok(ApplyResult::Applied) => StatusCode::OK.into_response(),
+        Ok(ApplyResult::Pending(change)) => (StatusCode::ACCEPTED, Json(change)).into_response(),
+        Ok(ApplyResult::SnapshotNotFound) => snapshot_not_found_response(),
+        Err(err) => {
+            error!(
+                "Could not apply snapshot {} of instance {}: {}.",
+                name, instance, err
+            );
+            internal_error_response("Could not apply snapshot.")
+        }
+    }
+}
+
+pub(crate) async fn delete_snapshot(
+    Path((instance, name)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &name) {
+        return response;
+    }
+    match controller.delete_snapshot(&instance, &name).await {
+        Ok(true) => StatusCode::OK.into_response(),
+        Ok(false) => snapshot_not_found_response(),
+        Err(err) => {
+            error!(
+                "Could not delete snapshot {} of instance {}: {}.",
+                name, instance, err
+            );
+            internal_error_response("Could not delete snapshot.")
+        }
+    }
+}
+
+fn validate_names(instance: &str, name: &str) -> Option<Response> {
+    if !crate::fs::is_valid_instance_name(instance) {
+        return Some(invalid_instance_name_response());
+    }
+    if !is_valid_snapshot_name(name) {
+        return Some(
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!("Snapshot name contains invalid character")),
+            )
+                .into_response(),
+        );
+    }
+    None
+}
+
+fn snapshot_not_found_response() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!("Snapshot does not exist.")),
+    )
+        .into_response()
+}
+
+fn internal_error_response(message: &str) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(json!(message))).into_response()
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::state::InstanceState;
+
+pub(crate) async fn get_state(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.get_state(&instance).await {
+        Ok(state) => Json(state).into_response(),
+        Err(err) => {
+            error!("Could not get state of instance {}: {}.", instance, err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!("Could not get state.")),
+            )
+                .into_response()
+        }
+    }
+}
+
+pub(crate) async fn set_state(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(state): Json<InstanceState>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    match controller.submit_state(&instance, state).await {
+        Some(change) => (StatusCode::ACCEPTED, Json(change)).into_response(),
+        None => StatusCode::OK.into_response(),
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use axum::extract::{Extension, Path, Query};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+
+use crate::controller::ServerController;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::websocket::topics::is_valid_topic;
+
+#[derive(Deserialize)]
+pub(crate) struct PublishQuery {
+    #[serde(default)]
+    retain: bool,
+}
+
+pub(crate) async fn get_retained_topics(
+    Path(instance): Path<String>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    Json(controller.get_retained_topics(&instance).await).into_response()
+}
+
+/// Publishes the body to the renderers which subscribed to the topic and responds with their
+/// number.
+pub(crate) async fn publish_topic(
+    Path((instance, topic)): Path<(String, String)>,
+    Query(query): Query<PublishQuery>,
+    Extension(controller): Extension<Arc<ServerController>>,
+    Json(value): Json<serde_json::Value>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &topic) {
+        return response;
+    }
+    let clients = controller
+        .publish_topic(&instance, &topic, value, query.retain)
+        .await;
+    Json(json!({ "clients": clients })).into_response()
+}
+
+pub(crate) async fn delete_retained_topic(
+    Path((instance, topic)): Path<(String, String)>,
+    Extension(controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &topic) {
+        return response;
+    }
+    if controller.delete_retained_topic(&instance, &topic).await {
+        StatusCode::OK.into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!("Topic has no retained value.")),
+        )
+            .into_response()
+    }
+}
+
+fn validate_names(instance: &str, topic: &str) -> Option<Response> {
+    if !crate::fs::is_valid_instance_name(instance) {
+        return Some(invalid_instance_name_response());
+    }
+    if !is_valid_topic(topic) {
+        return Some(
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!("Topic must not be empty or contain wildcards.")),
+            )
+                .into_response(),
+        );
+    }
+    None
+}
@@ -1,23 +1,195 @@
+use crate::config::ZagreusServerConfig;
+use crate::endpoint::config::invalid_instance_name_response;
+use crate::endpoint::presence::{validate_names, OperatorQuery};
+use crate::monitor::MONITOR_TOKEN_HEADER;
+use crate::websocket::parameters::{ClientOrigin, ClientParameters};
 use crate::ServerController;
 use axum::extract::ws::WebSocket;
-use axum::extract::{Extension, Path, WebSocketUpgrade};
-use axum::response::IntoResponse;
+use axum::extract::{ConnectInfo, Extension, Path, Query, RawQuery, WebSocketUpgrade};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     Path(instance): Path<String>,
+    Query(query): Query<BTreeMap<String, String>>,
+    // missing if the server is not started with connection information, e.g. in tests
+    remote_address: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     Extension(server_controller): Extension<Arc<ServerController>>,
-) -> impl IntoResponse {
-    ws.on_upgrade(|websocket| handle_socket(websocket, server_controller, instance))
+) -> Response {
+    if server_controller.is_instance_expired(&instance) {
+        warn!(
+            "Rejected websocket client of expired instance {}.",
+            instance
+        );
+        return (StatusCode::GONE, Json(json!("Instance has expired."))).into_response();
+    }
+    let synthetic = headers
+        .get(MONITOR_TOKEN_HEADER)
+        .and_then(|token| token.to_str().ok())
+        .is_some_and(|token| server_controller.is_monitor_token(token));
+    let parameters = ClientParameters::from_query(query, synthetic);
+    if let Some(reason) = server_controller
+        .check_websocket_admission(&instance, &parameters)
+        .await
+    {
+        warn!(
+            "Rejected websocket client of instance {}: {}",
+            instance, reason
+        );
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(json!(reason))).into_response();
+    }
+    let origin = ClientOrigin {
+        remote_address: remote_address.map(|ConnectInfo(address)| address),
+        user_agent: headers
+            .get(header::USER_AGENT)
+            .and_then(|user_agent| user_agent.to_str().ok())
+            .map(str::to_owned),
+    };
+    ws.on_upgrade(|websocket| {
+        handle_socket(websocket, server_controller, instance, parameters, origin)
+    })
 }
 
 pub async fn handle_socket(
     socket: WebSocket,
     server_controller: Arc<ServerController>,
     instance: String,
+    parameters: ClientParameters,
+    origin: ClientOrigin,
 ) {
     server_controller
-        .add_websocket_client(socket, &instance)
+        .add_websocket_client(socket, &instance, parameters, origin)
         .await;
 }
+
+#[derive(Deserialize)]
+pub struct StateStreamQuery {
+    #[serde(default)]
+    diff: bool,
+}
+
+pub async fn state_stream_handler(
+    ws: WebSocketUpgrade,
+    Path(instance): Path<String>,
+    Query(query): Query<StateStreamQuery>,
+    Extension(server_controller): Extension<Arc<ServerController>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |websocket| {
+        crate::websocket::stream::stream_state(
+            websocket,
+            server_controller,
+            instance,
+            query.diff,
+            None,
+        )
+    })
+}
+
+#[derive(Deserialize)]
+pub struct PublicStateStreamQuery {
+    #[serde(default)]
+    diff: bool,
+    /// Comma separated element ids to which the stream is narrowed down.
+    keys: Option<String>,
+}
+
+/// Streams the published data of the instance to untrusted clients (e.g. venue screens or
+/// second-screen apps), optionally narrowed down to some of the allowlisted elements.
+pub async fn public_state_stream_handler(
+    ws: WebSocketUpgrade,
+    Path(instance): Path<String>,
+    Query(query): Query<PublicStateStreamQuery>,
+    Extension(configuration): Extension<Arc<ZagreusServerConfig>>,
+    Extension(server_controller): Extension<Arc<ServerController>>,
+) -> Response {
+    let Some(mut allowlist) = configuration
+        .get_instance_config(&instance)
+        .and_then(|instance_config| instance_config.public_state.clone())
+    else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(json!("Instance does not publish any data.")),
+        )
+            .into_response();
+    };
+    if let Some(keys) = &query.keys {
+        let keys: Vec<&str> = keys.split(',').map(str::trim).collect();
+        for ids in [
+            &mut allowlist.texts,
+            &mut allowlist.classes,
+            &mut allowlist.images,
+        ] {
+            ids.retain(|id| keys.contains(&id.as_str()));
+        }
+    }
+    ws.on_upgrade(move |websocket| {
+        crate::websocket::stream::stream_state(
+            websocket,
+            server_controller,
+            instance,
+            query.diff,
+            Some(allowlist),
+        )
+    })
+    .into_response()
+}
+
+pub async fn presence_stream_handler(
+    ws: WebSocketUpgrade,
+    Path(instance): Path<String>,
+    Query(query): Query<OperatorQuery>,
+    Extension(server_controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if let Some(response) = validate_names(&instance, &query.operator) {
+        return response;
+    }
+    ws.on_upgrade(move |websocket| {
+        crate::websocket::presence::stream_presence(
+            websocket,
+            server_controller,
+            instance,
+            query.operator,
+        )
+    })
+    .into_response()
+}
+
+pub async fn control_handler(
+    ws: WebSocketUpgrade,
+    Path(instance): Path<String>,
+    headers: HeaderMap,
+    RawQuery(query): RawQuery,
+    Extension(server_controller): Extension<Arc<ServerController>>,
+) -> Response {
+    if !crate::fs::is_valid_instance_name(&instance) {
+        return invalid_instance_name_response();
+    }
+    let configuration = server_controller.get_configuration();
+    let may_approve = crate::auth::may_approve(&configuration.auth, &headers, query.as_deref());
+    ws.on_upgrade(move |websocket| {
+        crate::websocket::control::handle_control_commands(
+            websocket,
+            server_controller,
+            instance,
+            may_approve,
+        )
+    })
+    .into_response()
+}
+
+pub async fn feedback_stream_handler(
+    ws: WebSocketUpgrade,
+    Path(instance): Path<String>,
+    Extension(server_controller): Extension<Arc<ServerController>>,
+) -> impl IntoResponse {
+    ws.on_upgrade(move |websocket| {
+        crate::websocket::feedback::stream_feedback(websocket, server_controller, instance)
+    })
+}
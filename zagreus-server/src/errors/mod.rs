@@ -0,0 +1,176 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::RwLock;
+
+use crate::websocket::message::DomSnapshot;
+
+/// Minimum time between two DOM snapshots of the same client, so a template which fails
+/// continuously does not flood the server with snapshots.
+const MIN_SNAPSHOT_INTERVAL_SECONDS: i64 = 10;
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorSnapshot {
+    pub captured: DateTime<Utc>,
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(skip)]
+    pub html: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientErrorRecord {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip)]
+    pub instance: String,
+    pub client: usize,
+    pub message: String,
+    pub stack: String,
+    /// Whether a DOM snapshot was requested from the client for this error.
+    pub snapshot_requested: bool,
+    pub snapshot: Option<ErrorSnapshot>,
+}
+
+struct ErrorRecords {
+    next_id: u64,
+    records: VecDeque<ClientErrorRecord>,
+}
+
+/// Bounded in-memory log of the errors reported by runtime clients together with the DOM
+/// snapshots the clients captured when the error occurred.
+pub struct ErrorLog {
+    max_entries: usize,
+    errors: RwLock<ErrorRecords>,
+}
+
+impl ErrorLog {
+    pub fn new(max_entries: usize) -> ErrorLog {
+        ErrorLog {
+            max_entries,
+            errors: RwLock::new(ErrorRecords {
+                next_id: 1,
+                records: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Records the error and returns its id if a DOM snapshot should be requested for it.
+    pub async fn record(
+        &self,
+        instance: &str,
+        client: usize,
+        message: &str,
+        stack: &str,
+    ) -> Option<u64> {
+        let now = Utc::now();
+        let mut errors = self.errors.write().await;
+        let id = errors.next_id;
+        errors.next_id += 1;
+
+        let snapshot_requested = !errors.records.iter().any(|record| {
+            record.client == client
+                && record.snapshot_requested
+                && now - record.timestamp < Duration::seconds(MIN_SNAPSHOT_INTERVAL_SECONDS)
+        });
+        if errors.records.len() >= self.max_entries {
+            errors.records.pop_front();
+        }
+        errors.records.push_back(ClientErrorRecord {
+            id,
+            timestamp: now,
+            instance: instance.to_owned(),
+            client,
+            message: message.to_owned(),
+            stack: stack.to_owned(),
+            snapshot_requested,
+            snapshot: None,
+        });
+        snapshot_requested.then_some(id)
+    }
+
+    /// Attaches the snapshot to the error if it was requested from the client. Returns whether
+    /// the snapshot was attached.
+    pub async fn attach_snapshot(&self, client: usize, snapshot: DomSnapshot) -> bool {
+        let mut errors = self.errors.write().await;
+        let record = errors.records.iter_mut().find(|record| {
+            record.id == snapshot.error_id && record.client == client && record.snapshot_requested
+        });
+        match record {
+            Some(record) => {
+                record.snapshot = Some(ErrorSnapshot {
+                    captured: Utc::now(),
+                    url: snapshot.url,
+                    width: snapshot.width,
+                    height: snapshot.height,
+                    html: snapshot.html,
+                });
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub async fn get_errors(&self, instance: &str) -> Vec<ClientErrorRecord> {
+        self.errors
+            .read()
+            .await
+            .records
+            .iter()
+            .filter(|record| record.instance == instance)
+            .cloned()
+            .collect()
+    }
+
+    pub async fn get_snapshot_html(&self, instance: &str, id: u64) -> Option<String> {
+        self.errors
+            .read()
+            .await
+            .records
+            .iter()
+            .find(|record| record.id == id && record.instance == instance)
+            .and_then(|record| record.snapshot.as_ref())
+            .map(|snapshot| snapshot.html.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_snapshots_are_throttled_per_client() {
+        let error_log = ErrorLog::new(10);
+        let first_id = error_log
+            .record("scoreboard", 1, "first", "")
+            .await
+            .unwrap();
+        assert!(error_log
+            .record("scoreboard", 1, "second", "")
+            .await
+            .is_none());
+        assert!(error_log
+            .record("scoreboard", 2, "other client", "")
+            .await
+            .is_some());
+
+        let snapshot = DomSnapshot {
+            error_id: first_id,
+            html: String::from("<html></html>"),
+            url: String::from("http://localhost/index.html"),
+            width: 1920,
+            height: 1080,
+        };
+        // only the client that reported the error can attach a snapshot
+        assert!(!error_log.attach_snapshot(2, snapshot.clone()).await);
+        assert!(error_log.attach_snapshot(1, snapshot).await);
+        assert_eq!(
+            Some(String::from("<html></html>")),
+            error_log.get_snapshot_html("scoreboard", first_id).await
+        );
+        assert_eq!(3, error_log.get_errors("scoreboard").await.len());
+    }
+}
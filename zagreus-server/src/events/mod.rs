@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{Notify, RwLock};
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum EventKind {
+    /// An operation was sent to the given number of connected clients.
+    Operation {
+        operation: String,
+        payload: serde_json::Value,
+        clients: usize,
+    },
+    /// A client reported an error in the template.
+    ClientError {
+        client: usize,
+        message: String,
+    },
+    ClientConnected {
+        client: usize,
+    },
+    ClientDisconnected {
+        client: usize,
+    },
+    /// A data source missed its refresh, so the data of its elements cannot be trusted.
+    DataStale {
+        elements: Vec<String>,
+    },
+    /// A stale data source was refreshed again.
+    DataRefreshed {
+        elements: Vec<String>,
+    },
+    /// An operator locked the instance, possibly overriding the lock of another operator.
+    LockAcquired {
+        operator: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        overridden: Option<String>,
+    },
+    LockReleased {
+        operator: String,
+    },
+    /// Operations were held back until they are approved.
+    ChangePending {
+        id: u64,
+    },
+    ChangeApproved {
+        id: u64,
+    },
+    ChangeRejected {
+        id: u64,
+    },
+    /// All data of the instance was deleted.
+    InstanceDeleted,
+    /// A scheduled item fired and its operations were sent.
+    ScheduledItemFired {
+        id: u64,
+    },
+    /// No renderer of the channel answered a ping within the maximum silence of the watchdog.
+    RendererUnresponsive {
+        channel: String,
+    },
+    RendererRecovered {
+        channel: String,
+    },
+    /// The watchdog switched a backup renderer to the channel.
+    ClientPromoted {
+        client: usize,
+        channel: String,
+    },
+    /// The condition of a rule was met and its actions were executed.
+    RuleTriggered {
+        rule: String,
+    },
+    /// The active renderer of a redundant pair disconnected, so its standby became active.
+    StandbyPromoted {
+        client: usize,
+        pair: String,
+    },
+    /// A renderer emitted an event, which was forwarded to the given number of clients of the
+    /// target instances.
+    TemplateEvent {
+        client: usize,
+        event: String,
+        payload: serde_json::Value,
+        clients: usize,
+    },
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceEvent {
+    pub cursor: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(skip)]
+    pub instance: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventBatch {
+    /// Cursor to pass to the next poll.
+    pub cursor: u64,
+    pub events: Vec<InstanceEvent>,
+}
+
+struct Events {
+    next_cursor: u64,
+    entries: VecDeque<InstanceEvent>,
+}
+
+/// Bounded in-memory log of the events of all instances which can be polled by cursor, for
+/// integrations that cannot hold a websocket connection.
+pub struct EventLog {
+    max_entries: usize,
+    events: RwLock<Events>,
+    notify: Notify,
+}
+
+impl EventLog {
+    pub fn new(max_entries: usize) -> EventLog {
+        EventLog {
+            max_entries,
+            events: RwLock::new(Events {
+                next_cursor: 1,
+                entries: VecDeque::new(),
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    pub async fn record(&self, instance: &str, kind: EventKind) {
+        let mut events = self.events.write().await;
+        let cursor = events.next_cursor;
+        events.next_cursor += 1;
+        if events.entries.len() >= self.max_entries {
+            events.entries.pop_front();
+        }
+        events.entries.push_back(InstanceEvent {
+            cursor,
+            timestamp: Utc::now(),
+            instance: instance.to_owned(),
+            kind,
+        });
+        drop(events);
+        self.notify.notify_waiters();
+    }
+
+    /// Returns the events of the instance after the cursor, waiting up to the timeout for new
+    /// events if there are none. Without a cursor only events recorded from now on are returned.
+    pub async fn poll(&self, instance: &str, cursor: Option<u64>, timeout: Duration) -> EventBatch {
+        self.poll_matching(Some(instance), cursor, timeout).await
+    }
+
+    /// Returns the events of all instances after the cursor like `poll`, e.g. to forward them to
+    /// a message broker.
+    pub async fn poll_all(&self, cursor: Option<u64>, timeout: Duration) -> EventBatch {
+        self.poll_matching(None, cursor, timeout).await
+    }
+
+    async fn poll_matching(
+        &self,
+        instance: Option<&str>,
+        cursor: Option<u64>,
+        timeout: Duration,
+    ) -> EventBatch {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let mut cursor = match cursor {
+            Some(cursor) => cursor,
+            None => self.events.read().await.next_cursor - 1,
+        };
+
+        loop {
+            // register for notifications before checking, so no event is missed in between
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let batch = self.get_events(instance, cursor).await;
+            if !batch.events.is_empty() {
+                return batch;
+            }
+            // events of other instances advance the cursor as well
+            cursor = batch.cursor;
+            if tokio::time::timeout_at(deadline, notified).await.is_err() {
+                return batch;
+            }
+        }
+    }
+
+    async fn get_events(&self, instance: Option<&str>, cursor: u64) -> EventBatch {
+        let events = self.events.read().await;
+        EventBatch {
+            cursor: (events.next_cursor - 1).max(cursor),
+            events: events
+                .entries
+                .iter()
+                .filter(|event| {
+                    event.cursor > cursor
+                        && instance.is_none_or(|instance| event.instance == instance)
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_poll_events() {
+        let event_log = EventLog::new(2);
+        event_log
+            .record("scoreboard", EventKind::ClientConnected { client: 1 })
+            .await;
+        event_log
+            .record("lower-third", EventKind::ClientConnected { client: 2 })
+            .await;
+        event_log
+            .record("scoreboard", EventKind::ClientDisconnected { client: 1 })
+            .await;
+
+        // the first event was dropped because the log is bounded
+        let batch = event_log
+            .poll("scoreboard", Some(0), Duration::from_millis(10))
+            .await;
+        assert_eq!(3, batch.cursor);
+        assert_eq!(1, batch.events.len());
+        assert_eq!(
+            EventKind::ClientDisconnected { client: 1 },
+            batch.events[0].kind
+        );
+
+        let batch = event_log
+            .poll("scoreboard", Some(batch.cursor), Duration::from_millis(10))
+            .await;
+        assert_eq!(3, batch.cursor);
+        assert!(batch.events.is_empty());
+    }
+}
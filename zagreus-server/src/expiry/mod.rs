@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::ExpiryConfig;
+use crate::controller::ServerController;
+
+const EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Expiry dates of instances which are only used for a limited time (e.g. the package of a
+/// one-off event). Expired instances are no longer served to new clients.
+pub struct InstanceExpiry {
+    configurations: HashMap<String, ExpiryConfig>,
+}
+
+impl InstanceExpiry {
+    pub fn new(configurations: HashMap<String, ExpiryConfig>) -> InstanceExpiry {
+        InstanceExpiry { configurations }
+    }
+
+    pub fn get_expires(&self, instance: &str) -> Option<DateTime<Utc>> {
+        self.configurations
+            .get(instance)
+            .map(|configuration| configuration.expires)
+    }
+
+    pub fn is_expired(&self, instance: &str, now: DateTime<Utc>) -> bool {
+        self.configurations
+            .get(instance)
+            .is_some_and(|configuration| is_expired(configuration, now))
+    }
+
+    /// Returns the expired instances whose data should be moved to the trash.
+    pub fn get_instances_to_trash(&self, now: DateTime<Utc>) -> Vec<String> {
+        self.configurations
+            .iter()
+            .filter(|(_, configuration)| configuration.move_to_trash)
+            .filter(|(_, configuration)| is_expired(configuration, now))
+            .map(|(instance, _)| instance.clone())
+            .collect()
+    }
+}
+
+pub fn is_expired(configuration: &ExpiryConfig, now: DateTime<Utc>) -> bool {
+    now >= configuration.expires
+}
+
+/// Moves the data of expired instances to the trash folder, if configured.
+pub async fn run_expiry(controller: Arc<ServerController>) {
+    let mut interval = tokio::time::interval(EXPIRY_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        for instance in controller.get_instances_to_trash() {
+            match controller.trash_instance(&instance).await {
+                Ok(true) => info!("Moved expired instance {} to the trash.", instance),
+                // the instance was moved before or has no data
+                Ok(false) => {}
+                Err(err) => error!(
+                    "Could not move expired instance {} to the trash: {:#}.",
+                    instance, err
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expired_instances() {
+        let expires = "2024-06-30T22:00:00Z".parse().unwrap();
+        let expiry = InstanceExpiry::new(HashMap::from([
+            (
+                String::from("final"),
+                ExpiryConfig {
+                    expires,
+                    move_to_trash: true,
+                },
+            ),
+            (
+                String::from("gala"),
+                ExpiryConfig {
+                    expires,
+                    move_to_trash: false,
+                },
+            ),
+        ]));
+        let before = "2024-06-30T21:59:59Z".parse().unwrap();
+
+        assert!(!expiry.is_expired("final", before));
+        assert!(expiry.is_expired("final", expires));
+        assert!(!expiry.is_expired("scoreboard", expires));
+        assert!(expiry.get_instances_to_trash(before).is_empty());
+        assert_eq!(
+            vec![String::from("final")],
+            expiry.get_instances_to_trash(expires)
+        );
+    }
+}
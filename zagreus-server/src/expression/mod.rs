@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+use crate::state::InstanceState;
+
+mod parser;
+
+pub use parser::Expression;
+
+pub const COMPUTED_ELEMENTS_FILE_NAME: &str = "computed.json";
+
+/// Expressions per element id, whose results are set as texts of the elements, e.g.
+/// `{"TotalGoals": "HomeScore + AwayScore"}`.
+pub type ComputedElements = BTreeMap<String, ComputedExpression>;
+
+/// Expression of a computed element, which is parsed once when it is set or loaded instead of
+/// for every message. It is stored as its source.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(try_from = "String", into = "String")]
+pub struct ComputedExpression {
+    source: String,
+    expression: Expression,
+}
+
+impl TryFrom<String> for ComputedExpression {
+    type Error = String;
+
+    fn try_from(source: String) -> Result<ComputedExpression, String> {
+        let expression = Expression::parse(&source)?;
+        Ok(ComputedExpression { source, expression })
+    }
+}
+
+impl From<ComputedExpression> for String {
+    fn from(computed: ComputedExpression) -> String {
+        computed.source
+    }
+}
+
+/// Parses the expressions per element id. Returns an error message for the first invalid one.
+pub fn parse_computed_elements(
+    sources: BTreeMap<String, String>,
+) -> Result<ComputedElements, String> {
+    sources
+        .into_iter()
+        .map(|(id, source)| match ComputedExpression::try_from(source) {
+            Ok(expression) => Ok((id, expression)),
+            Err(err) => Err(format!("Invalid expression of element {id}: {err}")),
+        })
+        .collect()
+}
+
+/// Returns an error message if an expression depends on another computed element.
+pub fn validate_computed_elements(computed: &ComputedElements) -> Option<String> {
+    for (id, computed_expression) in computed {
+        let inputs = computed_expression.expression.get_elements();
+        if let Some(input) = inputs.iter().find(|input| computed.contains_key(**input)) {
+            return Some(format!(
+                "Element {id} depends on the computed element {input}, which is not supported."
+            ));
+        }
+    }
+    None
+}
+
+/// Returns the texts of the computed elements which differ from their texts in the state.
+/// Expressions which cannot be evaluated (e.g. while an input is still empty) are skipped.
+pub fn compute_texts(computed: &ComputedElements, state: &InstanceState) -> Vec<(String, String)> {
+    let get_text = |id: &str| state.texts.get(id).map(String::as_str);
+    computed
+        .iter()
+        .filter_map(|(id, computed_expression)| {
+            match computed_expression.expression.evaluate(&get_text) {
+                Ok(value) => Some((id.clone(), value.to_string())),
+                Err(err) => {
+                    debug!("Could not compute text of element {}: {}.", id, err);
+                    None
+                }
+            }
+        })
+        .filter(|(id, text)| state.texts.get(id) != Some(text))
+        .collect()
+}
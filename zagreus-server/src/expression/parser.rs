@@ -0,0 +1,474 @@
+use std::collections::BTreeSet;
+
+/// Longest source of an expression, which also bounds the depth of operator chains.
+pub const MAX_EXPRESSION_LENGTH: usize = 1_000;
+/// Deepest nesting of parentheses, calls and negations, so parsing and evaluating an expression
+/// cannot overflow the stack.
+pub const MAX_NESTING_DEPTH: usize = 32;
+
+/// Value of an expression. Texts of elements which are numbers are treated as numbers.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    fn from_text(text: &str) -> Value {
+        match text.trim().parse::<f64>() {
+            Ok(number) if !text.trim().is_empty() => Value::Number(number),
+            _ => Value::Text(text.to_owned()),
+        }
+    }
+
+    fn as_number(&self) -> Result<f64, String> {
+        match self {
+            Value::Number(number) => Ok(*number),
+            Value::Text(text) => Err(format!("\"{text}\" is not a number")),
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            // whole numbers are shown without decimals, e.g. scores
+            Value::Number(number) if number.fract() == 0.0 && number.abs() < 1e15 => {
+                write!(f, "{}", *number as i64)
+            }
+            Value::Number(number) => write!(f, "{number}"),
+            Value::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Function {
+    Round,
+    Floor,
+    Ceil,
+    Abs,
+    Min,
+    Max,
+    Format,
+}
+
+impl Function {
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "round" => Some(Function::Round),
+            "floor" => Some(Function::Floor),
+            "ceil" => Some(Function::Ceil),
+            "abs" => Some(Function::Abs),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            "format" => Some(Function::Format),
+            _ => None,
+        }
+    }
+}
+
+/// Expression over the texts of elements, e.g. `HomeScore + AwayScore` or
+/// `format("{}%", round(Share * 100))`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Expression {
+    Number(f64),
+    Text(String),
+    Element(String),
+    Negate(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Identifier(String),
+    Operator(Operator),
+    OpenParenthesis,
+    CloseParenthesis,
+    Comma,
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '0'..='9' | '.' => {
+                let mut number = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                    number.push(c);
+                    chars.next();
+                }
+                let number = number
+                    .parse()
+                    .map_err(|_| format!("Invalid number {number}."))?;
+                tokens.push(Token::Number(number));
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) => text.push(c),
+                            None => return Err(String::from("Unterminated text.")),
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err(String::from("Unterminated text.")),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut identifier = String::new();
+                while let Some(&c) = chars
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || **c == '_')
+                {
+                    identifier.push(c);
+                    chars.next();
+                }
+                // format!(...) is accepted like format(...)
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                }
+                tokens.push(Token::Identifier(identifier));
+            }
+            _ => {
+                chars.next();
+                tokens.push(match c {
+                    '+' => Token::Operator(Operator::Add),
+                    '-' => Token::Operator(Operator::Subtract),
+                    '*' => Token::Operator(Operator::Multiply),
+                    '/' => Token::Operator(Operator::Divide),
+                    '%' => Token::Operator(Operator::Remainder),
+                    '(' => Token::OpenParenthesis,
+                    ')' => Token::CloseParenthesis,
+                    ',' => Token::Comma,
+                    _ => return Err(format!("Unexpected character {c}.")),
+                });
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn enter(&mut self) -> Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING_DEPTH {
+            return Err(format!(
+                "The expression is nested deeper than {MAX_NESTING_DEPTH} levels."
+            ));
+        }
+        Ok(())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(format!("Expected {expected:?}.")),
+        }
+    }
+
+    fn parse_sum(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_product()?;
+        while let Some(Token::Operator(operator @ (Operator::Add | Operator::Subtract))) =
+            self.peek().cloned()
+        {
+            self.next();
+            let right = self.parse_product()?;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn parse_product(&mut self) -> Result<Expression, String> {
+        let mut expression = self.parse_unary()?;
+        while let Some(Token::Operator(
+            operator @ (Operator::Multiply | Operator::Divide | Operator::Remainder),
+        )) = self.peek().cloned()
+        {
+            self.next();
+            let right = self.parse_unary()?;
+            expression = Expression::Binary(operator, Box::new(expression), Box::new(right));
+        }
+        Ok(expression)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        if self.peek() == Some(&Token::Operator(Operator::Subtract)) {
+            self.next();
+            self.enter()?;
+            let operand = self.parse_unary()?;
+            self.depth -= 1;
+            return Ok(Expression::Negate(Box::new(operand)));
+        }
+        self.enter()?;
+        let expression = self.parse_primary()?;
+        self.depth -= 1;
+        Ok(expression)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expression::Number(number)),
+            Some(Token::Text(text)) => Ok(Expression::Text(text)),
+            Some(Token::OpenParenthesis) => {
+                let expression = self.parse_sum()?;
+                self.expect(Token::CloseParenthesis)?;
+                Ok(expression)
+            }
+            Some(Token::Identifier(name)) if self.peek() == Some(&Token::OpenParenthesis) => {
+                let function = Function::from_name(&name)
+                    .ok_or_else(|| format!("Unknown function {name}."))?;
+                self.next();
+                let mut arguments = Vec::new();
+                if self.peek() == Some(&Token::CloseParenthesis) {
+                    self.next();
+                } else {
+                    loop {
+                        arguments.push(self.parse_sum()?);
+                        match self.next() {
+                            Some(Token::Comma) => {}
+                            Some(Token::CloseParenthesis) => break,
+                            _ => return Err(format!("Expected , or ) in call of {name}.")),
+                        }
+                    }
+                }
+                Ok(Expression::Call(function, arguments))
+            }
+            Some(Token::Identifier(name)) => Ok(Expression::Element(name)),
+            _ => Err(String::from("Unexpected end of expression.")),
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> Result<Expression, String> {
+        if source.len() > MAX_EXPRESSION_LENGTH {
+            return Err(format!(
+                "The expression is longer than {MAX_EXPRESSION_LENGTH} characters."
+            ));
+        }
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            depth: 0,
+        };
+        let expression = parser.parse_sum()?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(token) => Err(format!("Unexpected {token:?}.")),
+        }
+    }
+
+    /// Returns the ids of the elements the expression reads.
+    pub fn get_elements(&self) -> BTreeSet<&str> {
+        let mut elements = BTreeSet::new();
+        self.collect_elements(&mut elements);
+        elements
+    }
+
+    fn collect_elements<'a>(&'a self, elements: &mut BTreeSet<&'a str>) {
+        match self {
+            Expression::Number(_) | Expression::Text(_) => {}
+            Expression::Element(id) => {
+                elements.insert(id);
+            }
+            Expression::Negate(operand) => operand.collect_elements(elements),
+            Expression::Binary(_, left, right) => {
+                left.collect_elements(elements);
+                right.collect_elements(elements);
+            }
+            Expression::Call(_, arguments) => {
+                for argument in arguments {
+                    argument.collect_elements(elements);
+                }
+            }
+        }
+    }
+
+    /// Evaluates the expression with the texts of the elements. Elements without text are empty.
+    pub fn evaluate<'a>(
+        &self,
+        get_text: &impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<Value, String> {
+        match self {
+            Expression::Number(number) => Ok(Value::Number(*number)),
+            Expression::Text(text) => Ok(Value::Text(text.clone())),
+            Expression::Element(id) => Ok(Value::from_text(get_text(id).unwrap_or_default())),
+            Expression::Negate(operand) => {
+                Ok(Value::Number(-operand.evaluate(get_text)?.as_number()?))
+            }
+            Expression::Binary(operator, left, right) => {
+                let left = left.evaluate(get_text)?;
+                let right = right.evaluate(get_text)?;
+                if let (Operator::Add, Value::Text(_), _) | (Operator::Add, _, Value::Text(_)) =
+                    (operator, &left, &right)
+                {
+                    return Ok(Value::Text(format!("{left}{right}")));
+                }
+                let (left, right) = (left.as_number()?, right.as_number()?);
+                let result = match operator {
+                    Operator::Add => left + right,
+                    Operator::Subtract => left - right,
+                    Operator::Multiply => left * right,
+                    Operator::Divide | Operator::Remainder if right == 0.0 => {
+                        return Err(String::from("Division by zero"))
+                    }
+                    Operator::Divide => left / right,
+                    Operator::Remainder => left % right,
+                };
+                Ok(Value::Number(result))
+            }
+            Expression::Call(function, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(get_text))
+                    .collect::<Result<Vec<Value>, String>>()?;
+                call(*function, &arguments)
+            }
+        }
+    }
+}
+
+fn call(function: Function, arguments: &[Value]) -> Result<Value, String> {
+    let numbers = || {
+        arguments
+            .iter()
+            .map(Value::as_number)
+            .collect::<Result<Vec<f64>, String>>()
+    };
+    let single = || match numbers()?.as_slice() {
+        [number] => Ok(*number),
+        _ => Err(format!("{function:?} takes one argument")),
+    };
+    let number = match function {
+        Function::Round => match numbers()?.as_slice() {
+            [number] => number.round(),
+            [number, digits] => {
+                let factor = 10f64.powi(*digits as i32);
+                (number * factor).round() / factor
+            }
+            _ => return Err(String::from("Round takes one or two arguments")),
+        },
+        Function::Floor => single()?.floor(),
+        Function::Ceil => single()?.ceil(),
+        Function::Abs => single()?.abs(),
+        Function::Min | Function::Max => {
+            let numbers = numbers()?;
+            let fold = if function == Function::Min {
+                f64::min
+            } else {
+                f64::max
+            };
+            numbers
+                .into_iter()
+                .reduce(fold)
+                .ok_or_else(|| format!("{function:?} takes at least one argument"))?
+        }
+        Function::Format => return format(arguments),
+    };
+    Ok(Value::Number(number))
+}
+
+/// Replaces every `{}` in the first argument with the following arguments in order.
+fn format(arguments: &[Value]) -> Result<Value, String> {
+    let Some((Value::Text(template), values)) = arguments.split_first() else {
+        return Err(String::from("Format takes a text as first argument"));
+    };
+    let mut values = values.iter();
+    let mut parts = template.split("{}");
+    let mut formatted = parts.next().unwrap_or_default().to_owned();
+    for part in parts {
+        let value = values
+            .next()
+            .ok_or_else(|| String::from("Format has more placeholders than arguments"))?;
+        formatted.push_str(&value.to_string());
+        formatted.push_str(part);
+    }
+    Ok(Value::Text(formatted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(source: &str) -> Result<String, String> {
+        let texts = [("HomeScore", "2"), ("AwayScore", "1"), ("Share", "0.4567")];
+        let get_text = |id: &str| {
+            texts
+                .iter()
+                .find(|(element, _)| *element == id)
+                .map(|(_, text)| *text)
+        };
+        Ok(Expression::parse(source)?.evaluate(&get_text)?.to_string())
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(Ok(String::from("3")), evaluate("HomeScore + AwayScore"));
+        assert_eq!(
+            Ok(String::from("46%")),
+            evaluate("format!(\"{}%\", round(Share*100))")
+        );
+        assert_eq!(Ok(String::from("45.67")), evaluate("round(Share * 100, 2)"));
+        assert_eq!(Ok(String::from("-7")), evaluate("-(1 + 2) * 2 - 1"));
+        assert_eq!(
+            Ok(String::from("2 - 1")),
+            evaluate("HomeScore + \" - \" + AwayScore")
+        );
+        assert_eq!(Ok(String::from("2")), evaluate("max(HomeScore, AwayScore)"));
+        // elements without text are empty
+        assert_eq!(Ok(String::from("")), evaluate("Missing"));
+        assert!(evaluate("HomeScore / (AwayScore - 1)").is_err());
+        assert!(evaluate("sqrt(HomeScore)").is_err());
+        assert!(evaluate("HomeScore +").is_err());
+        assert_eq!(
+            Ok(String::from("1")),
+            evaluate(&format!("{}1{}", "(".repeat(30), ")".repeat(30)))
+        );
+        assert!(Expression::parse(&"(".repeat(100_000)).is_err());
+        assert!(Expression::parse(&format!("{}1{}", "(".repeat(40), ")".repeat(40))).is_err());
+        assert!(Expression::parse(&"-".repeat(40)).is_err());
+        assert_eq!(
+            BTreeSet::from(["AwayScore", "HomeScore"]),
+            Expression::parse("max(HomeScore, AwayScore) + 1")
+                .unwrap()
+                .get_elements()
+        );
+    }
+}
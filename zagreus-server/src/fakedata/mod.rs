@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{Duration, Local};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+
+pub use zagreus_client::dto::{FakeDataDto, FakeDataRequestDto};
+
+/// Channel of the renderers which receive fake data if the request names none.
+pub const DEFAULT_PREVIEW_CHANNEL: &str = "preview";
+
+const FIRST_NAMES: &[&str] = &[
+    "Anna", "Luca", "Sofia", "Noah", "Mia", "Elias", "Lena", "Jonas", "Emma", "David", "Laura",
+    "Nico",
+];
+const LAST_NAMES: &[&str] = &[
+    "Meier", "Rossi", "Schmid", "Keller", "Weber", "Bianchi", "Huber", "Fischer", "Brunner",
+    "Moreau",
+];
+const TEAMS: &[&str] = &[
+    "FC Lakeside",
+    "Riverside United",
+    "Mountain Lions",
+    "Harbor City",
+    "Northern Stars",
+    "Valley Rovers",
+];
+const ROLES: &[&str] = &[
+    "Head Coach",
+    "Team Captain",
+    "Political Correspondent",
+    "Chief Executive Officer",
+    "Weather Presenter",
+    "Mayor",
+];
+const CITIES: &[&str] = &[
+    "Zurich", "Geneva", "Milan", "Lyon", "Munich", "Vienna", "Basel", "Turin",
+];
+const HEADLINES: &[&str] = &[
+    "Record crowd expected for the final",
+    "Council approves new tram line",
+    "Heavy snowfall closes mountain pass",
+    "Local startup wins innovation award",
+    "Late goal secures promotion",
+    "Festival returns after two years",
+];
+/// Colors of the placeholder images, each image gets its own.
+const PLACEHOLDER_COLORS: &[&str] = &[
+    "#d64545", "#2f80ed", "#27ae60", "#f2994a", "#9b51e0", "#2d9cdb",
+];
+
+/// Kind of content an element is assumed to show, guessed from its id.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum TextKind {
+    Score,
+    Time,
+    Date,
+    Percentage,
+    Number,
+    Team,
+    Role,
+    City,
+    Name,
+    Headline,
+}
+
+/// Keywords in element ids per kind. They are checked in this order, so e.g. `HomeTeamScore` is a
+/// score and `TeamName` is a team.
+const KEYWORDS: &[(TextKind, &[&str])] = &[
+    (TextKind::Score, &["score", "goals", "points"]),
+    (TextKind::Time, &["time", "clock", "countdown"]),
+    (TextKind::Date, &["date", "day"]),
+    (TextKind::Percentage, &["percent", "share", "turnout"]),
+    (
+        TextKind::Number,
+        &[
+            "number", "count", "minute", "period", "round", "rank", "votes",
+        ],
+    ),
+    (TextKind::Team, &["team", "club", "party"]),
+    (
+        TextKind::Role,
+        &["title", "role", "position", "function", "job"],
+    ),
+    (TextKind::City, &["city", "location", "venue", "place"]),
+    (TextKind::Name, &["name", "player", "speaker", "guest"]),
+];
+
+fn get_text_kind(element: &str) -> TextKind {
+    let element = element.to_lowercase();
+    KEYWORDS
+        .iter()
+        .find(|(_, keywords)| keywords.iter().any(|keyword| element.contains(keyword)))
+        .map_or(TextKind::Headline, |(kind, _)| *kind)
+}
+
+/// Generates plausible content for templates under review. Texts are guessed from the element
+/// ids, images are colored placeholders. The same seed always generates the same data.
+pub struct FakeDataGenerator {
+    rng: StdRng,
+}
+
+impl FakeDataGenerator {
+    pub fn new(seed: Option<u64>) -> FakeDataGenerator {
+        FakeDataGenerator {
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+        }
+    }
+
+    pub fn generate_texts(&mut self, elements: &BTreeSet<String>) -> BTreeMap<String, String> {
+        elements
+            .iter()
+            .map(|element| (element.clone(), self.generate_text(get_text_kind(element))))
+            .collect()
+    }
+
+    /// Returns the placeholder image per element, the same placeholder if there are more
+    /// elements than colors.
+    pub fn generate_images(
+        &mut self,
+        elements: &BTreeSet<String>,
+    ) -> BTreeMap<String, Placeholder> {
+        let offset = self.rng.gen_range(0..PLACEHOLDER_COLORS.len());
+        elements
+            .iter()
+            .enumerate()
+            .map(|(index, element)| {
+                let color = PLACEHOLDER_COLORS[(offset + index) % PLACEHOLDER_COLORS.len()];
+                (element.clone(), Placeholder::new(element, color))
+            })
+            .collect()
+    }
+
+    fn generate_text(&mut self, kind: TextKind) -> String {
+        match kind {
+            TextKind::Score => self.rng.gen_range(0..=5).to_string(),
+            TextKind::Time => format!(
+                "{:02}:{:02}",
+                self.rng.gen_range(0..90),
+                self.rng.gen_range(0..60)
+            ),
+            TextKind::Date => (Local::now() + Duration::days(self.rng.gen_range(0..60)))
+                .format("%-d %B %Y")
+                .to_string(),
+            TextKind::Percentage => format!("{}%", self.rng.gen_range(1..100)),
+            TextKind::Number => self.rng.gen_range(1..100).to_string(),
+            TextKind::Team => self.choose(TEAMS),
+            TextKind::Role => self.choose(ROLES),
+            TextKind::City => self.choose(CITIES),
+            TextKind::Name => format!("{} {}", self.choose(FIRST_NAMES), self.choose(LAST_NAMES)),
+            TextKind::Headline => self.choose(HEADLINES),
+        }
+    }
+
+    fn choose(&mut self, values: &[&str]) -> String {
+        values
+            .choose(&mut self.rng)
+            .copied()
+            .unwrap_or_default()
+            .to_owned()
+    }
+}
+
+/// Placeholder image labeled with the id of its element.
+pub struct Placeholder {
+    pub content: String,
+}
+
+impl Placeholder {
+    fn new(element: &str, color: &str) -> Placeholder {
+        let label: String = element
+            .chars()
+            .filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+            .collect();
+        Placeholder {
+            content: format!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="400" height="400" viewBox="0 0 400 400"><rect width="400" height="400" fill="{color}"/><path d="M0 0L400 400M400 0L0 400" stroke="#ffffff" stroke-opacity="0.3" stroke-width="4"/><text x="200" y="210" fill="#ffffff" font-family="sans-serif" font-size="28" text-anchor="middle">{label}</text></svg>"##
+            ),
+        }
+    }
+
+    /// Returns the file name under which the placeholder is stored in the assets folder, which is
+    /// its hash like the file names of uploaded assets.
+    pub fn get_file_name(&self) -> String {
+        format!("{:x}.svg", Sha256::digest(self.content.as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_text_kind() {
+        assert_eq!(TextKind::Score, get_text_kind("HomeTeamScore"));
+        assert_eq!(TextKind::Team, get_text_kind("AwayTeamName"));
+        assert_eq!(TextKind::Name, get_text_kind("LowerThirdName"));
+        assert_eq!(TextKind::Role, get_text_kind("LowerThirdTitle"));
+        assert_eq!(TextKind::Time, get_text_kind("MatchClock"));
+        assert_eq!(TextKind::Headline, get_text_kind("Ticker"));
+    }
+
+    #[test]
+    fn test_generate() {
+        let elements = BTreeSet::from([
+            String::from("HomeScore"),
+            String::from("MatchClock"),
+            String::from("LowerThirdName"),
+        ]);
+        let texts = FakeDataGenerator::new(Some(7)).generate_texts(&elements);
+        assert_eq!(
+            texts,
+            FakeDataGenerator::new(Some(7)).generate_texts(&elements)
+        );
+        assert!(texts["HomeScore"].parse::<u32>().unwrap() <= 5);
+        assert_eq!(5, texts["MatchClock"].len());
+        assert!(texts["LowerThirdName"].contains(' '));
+
+        let images = BTreeSet::from([String::from("HomeLogo"), String::from("AwayLogo")]);
+        let placeholders = FakeDataGenerator::new(Some(7)).generate_images(&images);
+        assert!(placeholders["HomeLogo"]
+            .content
+            .contains(">HomeLogo</text>"));
+        assert_ne!(
+            placeholders["HomeLogo"].get_file_name(),
+            placeholders["AwayLogo"].get_file_name()
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::{broadcast, RwLock};
+
+use crate::state::InstanceState;
+
+/// Capacity of the channel notifying about feedback changes which are not state changes.
+const FEEDBACK_CHANGES_CAPACITY: usize = 256;
+
+/// Condition under which a button of an external control surface is lit.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ButtonCondition {
+    /// The animation `on` was executed more recently than the animation `off`, e.g. an in and an
+    /// out animation.
+    Animation {
+        on: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        off: Option<String>,
+    },
+    /// The class is added to the element.
+    Class { id: String, class: String },
+    /// All data of the snapshot is on air.
+    Snapshot { name: String },
+    /// The page of the rundown is the current page.
+    RundownPage { index: usize },
+    /// The emergency override is active.
+    Emergency { name: String },
+}
+
+/// Buttons of an instance per name.
+pub type FeedbackButtons = BTreeMap<String, ButtonCondition>;
+
+pub const FEEDBACK_FILE_NAME: &str = "feedback.json";
+
+/// Everything on air that the buttons of an instance can reflect.
+pub struct FeedbackContext<'a> {
+    pub state: &'a InstanceState,
+    /// Sequence number of the last execution per animation.
+    pub animations: &'a HashMap<String, u64>,
+    pub snapshots: &'a HashMap<String, InstanceState>,
+    pub rundown_page: Option<usize>,
+    pub emergency: Option<&'a str>,
+}
+
+/// Returns an error message if a button references an invalid snapshot name.
+pub fn validate_buttons(buttons: &FeedbackButtons) -> Option<String> {
+    buttons.values().find_map(|condition| match condition {
+        ButtonCondition::Snapshot { name } if !crate::snapshot::is_valid_snapshot_name(name) => {
+            Some(format!("Invalid snapshot name: {name}."))
+        }
+        _ => None,
+    })
+}
+
+/// Returns whether each button is lit.
+pub fn evaluate_buttons(
+    buttons: &FeedbackButtons,
+    context: &FeedbackContext,
+) -> BTreeMap<String, bool> {
+    buttons
+        .iter()
+        .map(|(name, condition)| (name.clone(), is_lit(condition, context)))
+        .collect()
+}
+
+fn is_lit(condition: &ButtonCondition, context: &FeedbackContext) -> bool {
+    match condition {
+        ButtonCondition::Animation { on, off } => {
+            let last_on = context.animations.get(on);
+            let last_off = off.as_ref().and_then(|off| context.animations.get(off));
+            match (last_on, last_off) {
+                (Some(last_on), Some(last_off)) => last_on > last_off,
+                (Some(_), None) => true,
+                (None, _) => false,
+            }
+        }
+        ButtonCondition::Class { id, class } => context
+            .state
+            .classes
+            .get(id)
+            .and_then(|classes| classes.get(class))
+            .copied()
+            .unwrap_or(false),
+        ButtonCondition::Snapshot { name } => context
+            .snapshots
+            .get(name)
+            .is_some_and(|snapshot| is_on_air(snapshot, context.state)),
+        ButtonCondition::RundownPage { index } => context.rundown_page == Some(*index),
+        ButtonCondition::Emergency { name } => context.emergency == Some(name.as_str()),
+    }
+}
+
+/// Returns whether every text, class and image of the snapshot is part of the state.
+fn is_on_air(snapshot: &InstanceState, state: &InstanceState) -> bool {
+    let texts = snapshot
+        .texts
+        .iter()
+        .all(|(id, text)| state.texts.get(id) == Some(text));
+    let classes = snapshot.classes.iter().all(|(id, classes)| {
+        classes.iter().all(|(class, added)| {
+            state
+                .classes
+                .get(id)
+                .and_then(|classes| classes.get(class))
+                .unwrap_or(&false)
+                == added
+        })
+    });
+    let images = snapshot
+        .images
+        .iter()
+        .all(|(id, image)| state.images.get(id) == Some(image));
+    texts && classes && images
+}
+
+/// Keeps track of the executed animations and notifies about changes of the button feedback
+/// that are not state changes.
+pub struct FeedbackTracker {
+    animations: RwLock<HashMap<String, HashMap<String, u64>>>,
+    next_sequence: AtomicU64,
+    changes: broadcast::Sender<String>,
+}
+
+impl FeedbackTracker {
+    pub fn new() -> FeedbackTracker {
+        FeedbackTracker {
+            animations: RwLock::new(HashMap::new()),
+            next_sequence: AtomicU64::new(1),
+            changes: broadcast::channel(FEEDBACK_CHANGES_CAPACITY).0,
+        }
+    }
+
+    pub async fn record_animation(&self, instance: &str, animation: &str) {
+        let sequence = self.next_sequence.fetch_add(1, Ordering::Relaxed);
+        self.animations
+            .write()
+            .await
+            .entry(instance.to_owned())
+            .or_default()
+            .insert(animation.to_owned(), sequence);
+        self.notify_change(instance);
+    }
+
+    pub async fn get_animations(&self, instance: &str) -> HashMap<String, u64> {
+        self.animations
+            .read()
+            .await
+            .get(instance)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns a receiver of the names of instances whose feedback may have changed.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<String> {
+        self.changes.subscribe()
+    }
+
+    pub fn notify_change(&self, instance: &str) {
+        // sending only fails if nobody is subscribed
+        let _ = self.changes.send(instance.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_buttons() {
+        let mut state = InstanceState::default();
+        state
+            .texts
+            .insert(String::from("Name"), String::from("Jane Doe"));
+        state.classes.insert(
+            String::from("LowerThird"),
+            BTreeMap::from([(String::from("visible"), true)]),
+        );
+        let mut speaker = InstanceState::default();
+        speaker
+            .texts
+            .insert(String::from("Name"), String::from("Jane Doe"));
+        let mut other_speaker = InstanceState::default();
+        other_speaker
+            .texts
+            .insert(String::from("Name"), String::from("John Doe"));
+        let animations = HashMap::from([
+            (String::from("LowerThirdIn"), 2),
+            (String::from("LowerThirdOut"), 1),
+            (String::from("BugOut"), 3),
+        ]);
+        let snapshots = HashMap::from([
+            (String::from("jane"), speaker),
+            (String::from("john"), other_speaker),
+        ]);
+        let context = FeedbackContext {
+            state: &state,
+            animations: &animations,
+            snapshots: &snapshots,
+            rundown_page: Some(1),
+            emergency: None,
+        };
+        let buttons = FeedbackButtons::from([
+            (
+                String::from("lowerThird"),
+                ButtonCondition::Animation {
+                    on: String::from("LowerThirdIn"),
+                    off: Some(String::from("LowerThirdOut")),
+                },
+            ),
+            (
+                String::from("bug"),
+                ButtonCondition::Animation {
+                    on: String::from("BugIn"),
+                    off: Some(String::from("BugOut")),
+                },
+            ),
+            (
+                String::from("visible"),
+                ButtonCondition::Class {
+                    id: String::from("LowerThird"),
+                    class: String::from("visible"),
+                },
+            ),
+            (
+                String::from("jane"),
+                ButtonCondition::Snapshot {
+                    name: String::from("jane"),
+                },
+            ),
+            (
+                String::from("john"),
+                ButtonCondition::Snapshot {
+                    name: String::from("john"),
+                },
+            ),
+            (
+                String::from("page2"),
+                ButtonCondition::RundownPage { index: 1 },
+            ),
+            (
+                String::from("weather"),
+                ButtonCondition::Emergency {
+                    name: String::from("weather"),
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            BTreeMap::from([
+                (String::from("bug"), false),
+                (String::from("jane"), true),
+                (String::from("john"), false),
+                (String::from("lowerThird"), true),
+                (String::from("page2"), true),
+                (String::from("visible"), true),
+                (String::from("weather"), false),
+            ]),
+            evaluate_buttons(&buttons, &context)
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use tokio::sync::{Mutex, RwLock};
+
+use crate::store::{load_document, store_document, InstanceDocumentStore};
+
+pub type FeatureFlags = BTreeMap<String, bool>;
+
+const FEATURE_FLAGS_FILE_NAME: &str = "feature-flags.json";
+
+/// Feature flags can be set globally and overridden per instance. Runtime clients always receive
+/// the effective flags of their instance.
+pub struct FeatureFlagStore {
+    global_flags_path: PathBuf,
+    global_flags: RwLock<Option<FeatureFlags>>,
+    instance_flags: InstanceDocumentStore<FeatureFlags>,
+    /// Serializes changes, so concurrent changes do not overwrite each other.
+    update_lock: Mutex<()>,
+}
+
+impl FeatureFlagStore {
+    pub fn new(data_folder: PathBuf) -> FeatureFlagStore {
+        FeatureFlagStore {
+            global_flags_path: data_folder.join(FEATURE_FLAGS_FILE_NAME),
+            global_flags: RwLock::new(None),
+            instance_flags: InstanceDocumentStore::new(data_folder, FEATURE_FLAGS_FILE_NAME),
+            update_lock: Mutex::new(()),
+        }
+    }
+
+    pub async fn get_global_flags(&self) -> anyhow::Result<FeatureFlags> {
+        if let Some(flags) = self.global_flags.read().await.as_ref() {
+            return Ok(flags.clone());
+        }
+        // loaded while holding the write lock, so a concurrent change is not overwritten
+        let mut cached = self.global_flags.write().await;
+        if let Some(flags) = cached.as_ref() {
+            return Ok(flags.clone());
+        }
+        let flags: FeatureFlags = load_document(&self.global_flags_path).await?;
+        *cached = Some(flags.clone());
+        Ok(flags)
+    }
+
+    pub async fn set_global_flags(&self, flags: FeatureFlags) -> anyhow::Result<()> {
+        let _update_guard = self.update_lock.lock().await;
+        self.store_global_flags(flags).await
+    }
+
+    pub async fn set_global_flag(&self, flag: &str, enabled: bool) -> anyhow::Result<()> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut flags = self.get_global_flags().await?;
+        flags.insert(flag.to_owned(), enabled);
+        self.store_global_flags(flags).await
+    }
+
+    async fn store_global_flags(&self, flags: FeatureFlags) -> anyhow::Result<()> {
+        store_document(&self.global_flags_path, &flags).await?;
+        *self.global_flags.write().await = Some(flags);
+        Ok(())
+    }
+
+    pub async fn get_instance_flags(&self, instance: &str) -> anyhow::Result<FeatureFlags> {
+        self.instance_flags.get(instance).await
+    }
+
+    pub async fn set_instance_flags(
+        &self,
+        instance: &str,
+        flags: FeatureFlags,
+    ) -> anyhow::Result<()> {
+        let _update_guard = self.update_lock.lock().await;
+        self.instance_flags.set(instance, flags).await
+    }
+
+    pub async fn set_instance_flag(
+        &self,
+        instance: &str,
+        flag: &str,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut flags = self.instance_flags.get(instance).await?;
+        flags.insert(flag.to_owned(), enabled);
+        self.instance_flags.set(instance, flags).await
+    }
+
+    pub async fn get_effective_flags(&self, instance: &str) -> anyhow::Result<FeatureFlags> {
+        let mut flags = self.get_global_flags().await?;
+        flags.extend(self.get_instance_flags(instance).await?);
+        Ok(flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_instance_flags_override_global_flags() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let store = FeatureFlagStore::new(path.clone());
+        store
+            .set_global_flags(FeatureFlags::from([
+                (String::from("newClock"), false),
+                (String::from("sponsorLoop"), true),
+            ]))
+            .await
+            .unwrap();
+        store
+            .set_instance_flags(
+                "scoreboard",
+                FeatureFlags::from([(String::from("newClock"), true)]),
+            )
+            .await
+            .unwrap();
+
+        let flags = store.get_effective_flags("scoreboard").await.unwrap();
+        assert_eq!(Some(&true), flags.get("newClock"));
+        assert_eq!(Some(&true), flags.get("sponsorLoop"));
+
+        let flags = store.get_effective_flags("lower-third").await.unwrap();
+        assert_eq!(Some(&false), flags.get("newClock"));
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
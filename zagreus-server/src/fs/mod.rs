@@ -1,13 +1,19 @@
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 
 #[cfg(test)]
 pub mod temp;
 
 pub const ASSETS_SUBFOLDER_NAME: &str = "assets";
+const RUNTIMES_SUBFOLDER_NAME: &str = "runtimes";
+const INSTANCES_SUBFOLDER_NAME: &str = "instances";
 const ORGANIZATION_SUBFOLDER_NAME: &str = "zagreus";
 const LOGS_SUBFOLDER_NAME: &str = "logs";
+const TRASH_SUBFOLDER_NAME: &str = "trash";
+const UPLOADS_SUBFOLDER_NAME: &str = "uploads";
+const TEMP_SUBFOLDER_NAME: &str = "tmp";
 
 pub fn get_log_folder_path(application_name: &str) -> anyhow::Result<PathBuf> {
     let mut folder = get_application_folder(application_name)?;
@@ -32,6 +38,127 @@ pub fn get_assets_folder(data_folder_path: &Path) -> anyhow::Result<PathBuf> {
     Ok(folder)
 }
 
+pub fn get_runtimes_folder(data_folder_path: &Path) -> anyhow::Result<PathBuf> {
+    let folder = data_folder_path.join(RUNTIMES_SUBFOLDER_NAME);
+    create_if_necessary(&folder)?;
+    Ok(folder)
+}
+
+/// Returns the folder in which the data of the given instance is stored (without creating it).
+pub fn get_instance_folder(data_folder_path: &Path, instance: &str) -> anyhow::Result<PathBuf> {
+    if !is_valid_instance_name(instance) {
+        return Err(anyhow!("Invalid instance name: {}", instance));
+    }
+    Ok(data_folder_path
+        .join(INSTANCES_SUBFOLDER_NAME)
+        .join(instance))
+}
+
+/// Returns the folder to which the data of expired instances is moved (without creating it).
+pub fn get_trash_folder(data_folder_path: &Path) -> PathBuf {
+    data_folder_path.join(TRASH_SUBFOLDER_NAME)
+}
+
+/// Returns the folder in which partial uploads of assets are stored (without creating it).
+pub fn get_uploads_folder(data_folder_path: &Path) -> PathBuf {
+    data_folder_path.join(UPLOADS_SUBFOLDER_NAME)
+}
+
+/// Creates a new, empty folder for temporary files in the data folder. The caller removes it when
+/// it is no longer needed.
+pub fn create_temp_folder(data_folder_path: &Path) -> anyhow::Result<PathBuf> {
+    let folder = data_folder_path
+        .join(TEMP_SUBFOLDER_NAME)
+        .join(format!("{:032x}", rand::random::<u128>()));
+    std::fs::create_dir_all(&folder)
+        .with_context(|| format!("Could not create temporary folder {folder:?}"))?;
+    Ok(folder)
+}
+
+/// Removes the temporary folders left behind by a previous run of the server.
+pub fn clear_temp_folders(data_folder_path: &Path) -> anyhow::Result<()> {
+    let folder = data_folder_path.join(TEMP_SUBFOLDER_NAME);
+    if folder.exists() {
+        std::fs::remove_dir_all(&folder)
+            .with_context(|| format!("Could not remove temporary folder {folder:?}"))?;
+    }
+    Ok(())
+}
+
+/// Returns the names of all instances that have a folder in the data folder.
+pub fn get_instance_names(data_folder_path: &Path) -> anyhow::Result<Vec<String>> {
+    let instances_folder = data_folder_path.join(INSTANCES_SUBFOLDER_NAME);
+    if !instances_folder.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names = Vec::new();
+    for entry in std::fs::read_dir(&instances_folder)
+        .with_context(|| format!("Could not read folder {instances_folder:?}"))?
+    {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            if let Some(name) = entry.file_name().to_str() {
+                if is_valid_instance_name(name) {
+                    names.push(name.to_owned());
+                }
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+pub fn is_valid_instance_name(instance: &str) -> bool {
+    is_valid_name(instance)
+}
+
+/// Checks that a name given by the user (e.g. of an instance or a secret) only contains
+/// alphanumeric characters, `-` and `_`, so it can be safely used in paths.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Returns when a file in the folder or one of its subfolders was last modified, `None` if the
+/// folder contains no files.
+pub fn get_last_modified(folder: &Path) -> anyhow::Result<Option<SystemTime>> {
+    let mut last_modified = None;
+    for entry in
+        std::fs::read_dir(folder).with_context(|| format!("Could not read folder {folder:?}"))?
+    {
+        let entry = entry?;
+        let modified = if entry.file_type()?.is_dir() {
+            get_last_modified(&entry.path())?
+        } else {
+            Some(entry.metadata()?.modified()?)
+        };
+        last_modified = last_modified.max(modified);
+    }
+    Ok(last_modified)
+}
+
+/// Writes a file which only the user running the server may read, e.g. a private key. The file
+/// is created with restricted permissions, so its content is never readable by others.
+pub fn write_private_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(path)?;
+    // the mode only applies to new files, existing files are restricted before they are written
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
 fn create_if_necessary(path: &Path) -> anyhow::Result<()> {
     if !path.exists() {
         std::fs::create_dir_all(path)
@@ -66,4 +193,22 @@ mod tests {
         let path = get_profile_folder().unwrap();
         assert!(path.exists());
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_file_is_only_readable_by_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let folder = temp::prepare_temp_folder().unwrap();
+        let path = folder.join("key.pem");
+        std::fs::write(&path, "public").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private_file(&path, b"private").unwrap();
+        let metadata = std::fs::metadata(&path).unwrap();
+        assert_eq!(0o600, metadata.permissions().mode() & 0o777);
+        assert_eq!("private", std::fs::read_to_string(&path).unwrap());
+
+        temp::delete_temp_folder(&folder).unwrap();
+    }
 }
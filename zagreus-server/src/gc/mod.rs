@@ -0,0 +1,309 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Context;
+
+use crate::assets::upload::UPLOAD_EXPIRY;
+use crate::endpoint::runtime::RUNTIME_FILE_NAME;
+
+/// Files which were modified more recently are never collected, so files which are just being
+/// written (e.g. an asset whose version is not stored yet) are safe.
+const GRACE_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum GarbageKind {
+    /// Content of an asset which was replaced or deleted and is not shown anywhere.
+    AssetVersion,
+    /// Part or metadata of an upload which was abandoned or is missing its counterpart.
+    Upload,
+    /// Folder of a runtime version whose upload failed, i.e. without a runtime bundle.
+    Runtime,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GarbageEntry {
+    /// Path relative to the data folder.
+    pub path: String,
+    pub kind: GarbageKind,
+    pub bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GarbageReport {
+    pub dry_run: bool,
+    pub entries: Vec<GarbageEntry>,
+    pub total_bytes: u64,
+}
+
+impl GarbageReport {
+    pub fn new(dry_run: bool, entries: Vec<GarbageEntry>) -> GarbageReport {
+        GarbageReport {
+            dry_run,
+            total_bytes: entries.iter().map(|entry| entry.bytes).sum(),
+            entries,
+        }
+    }
+}
+
+/// Scans the data folder for files which are not referenced by anything: asset files which are
+/// neither mentioned in a document of the data folder (e.g. the asset versions, pinned resources,
+/// snapshots or rotations) nor in `referenced_assets` (e.g. the states in memory), abandoned
+/// uploads and folders of failed runtime uploads.
+pub fn find_garbage(
+    data_folder: &Path,
+    referenced_assets: &HashSet<String>,
+    now: SystemTime,
+) -> anyhow::Result<Vec<GarbageEntry>> {
+    let mut entries = Vec::new();
+
+    let assets_folder = crate::fs::get_assets_folder(data_folder)?;
+    let mut referenced_assets = referenced_assets.clone();
+    collect_document_references(data_folder, &assets_folder, &mut referenced_assets)?;
+    for entry in read_folder(&assets_folder)? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let metadata = entry.metadata()?;
+        if metadata.is_file()
+            && is_content_addressed(&name)
+            && !referenced_assets.contains(&name)
+            && is_older(metadata.modified()?, now, GRACE_PERIOD)
+        {
+            entries.push(GarbageEntry {
+                path: format!("{}/{name}", crate::fs::ASSETS_SUBFOLDER_NAME),
+                kind: GarbageKind::AssetVersion,
+                bytes: metadata.len(),
+            });
+        }
+    }
+
+    // the part and the metadata file of an upload are collected together once the upload expired
+    let uploads_folder = crate::fs::get_uploads_folder(data_folder);
+    let mut uploads: BTreeMap<String, Vec<(String, std::fs::Metadata)>> = BTreeMap::new();
+    for entry in read_folder(&uploads_folder)? {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let id = name.split_once('.').map_or(name.as_str(), |(id, _)| id);
+        uploads
+            .entry(id.to_owned())
+            .or_default()
+            .push((name.clone(), entry.metadata()?));
+    }
+    for files in uploads.values() {
+        let last_modified = files
+            .iter()
+            .filter_map(|(_, metadata)| metadata.modified().ok())
+            .max();
+        if last_modified.is_some_and(|modified| is_older(modified, now, UPLOAD_EXPIRY)) {
+            entries.extend(files.iter().map(|(name, metadata)| GarbageEntry {
+                path: format!("{}/{name}", get_folder_name(&uploads_folder)),
+                kind: GarbageKind::Upload,
+                bytes: metadata.len(),
+            }));
+        }
+    }
+
+    let runtimes_folder = crate::fs::get_runtimes_folder(data_folder)?;
+    for entry in read_folder(&runtimes_folder)? {
+        let path = entry.path();
+        if !entry.file_type()?.is_dir() || path.join(RUNTIME_FILE_NAME).is_file() {
+            continue;
+        }
+        let last_modified =
+            crate::fs::get_last_modified(&path)?.unwrap_or(entry.metadata()?.modified()?);
+        if is_older(last_modified, now, GRACE_PERIOD) {
+            entries.push(GarbageEntry {
+                path: format!(
+                    "{}/{}",
+                    get_folder_name(&runtimes_folder),
+                    entry.file_name().to_string_lossy()
+                ),
+                kind: GarbageKind::Runtime,
+                bytes: get_size(&path)?,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// Collects the asset files mentioned anywhere in the JSON documents of the folder, so no
+/// document type referencing assets can be overlooked. Documents which cannot be parsed keep
+/// everything, since their references are unknown.
+fn collect_document_references(
+    folder: &Path,
+    assets_folder: &Path,
+    references: &mut HashSet<String>,
+) -> anyhow::Result<()> {
+    for entry in read_folder(folder)? {
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if path != assets_folder {
+                collect_document_references(&path, assets_folder, references)?;
+            }
+            continue;
+        }
+        if path.extension().and_then(|extension| extension.to_str()) != Some("json") {
+            continue;
+        }
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read document {path:?}"))?;
+        let document: serde_json::Value = serde_json::from_str(&contents)
+            .with_context(|| format!("Could not parse document {path:?}"))?;
+        collect_strings(&document, references);
+    }
+    Ok(())
+}
+
+fn collect_strings(value: &serde_json::Value, references: &mut HashSet<String>) {
+    match value {
+        serde_json::Value::String(text) if is_content_addressed(text) => {
+            references.insert(text.clone());
+        }
+        serde_json::Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_strings(value, references)),
+        serde_json::Value::Object(values) => values
+            .values()
+            .for_each(|value| collect_strings(value, references)),
+        _ => {}
+    }
+}
+
+/// Removes the entries from the data folder and returns the ones which were removed.
+pub fn remove_garbage(data_folder: &Path, entries: Vec<GarbageEntry>) -> Vec<GarbageEntry> {
+    entries
+        .into_iter()
+        .filter(|entry| {
+            let path = data_folder.join(&entry.path);
+            let result = if path.is_dir() {
+                std::fs::remove_dir_all(&path)
+            } else {
+                std::fs::remove_file(&path)
+            };
+            match result {
+                Ok(()) => true,
+                Err(err) => {
+                    warn!("Could not remove unreferenced file {:?}: {}.", path, err);
+                    false
+                }
+            }
+        })
+        .collect()
+}
+
+/// Whether the file name is a content hash, optionally with an extension, as the server stores
+/// assets. Other files in the assets folder were put there by hand and are left alone.
+fn is_content_addressed(name: &str) -> bool {
+    let hash = name.split_once('.').map_or(name, |(hash, _)| hash);
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_older(modified: SystemTime, now: SystemTime, age: Duration) -> bool {
+    now.duration_since(modified)
+        .is_ok_and(|duration| duration > age)
+}
+
+fn read_folder(folder: &Path) -> anyhow::Result<Vec<std::fs::DirEntry>> {
+    if !folder.exists() {
+        return Ok(Vec::new());
+    }
+    std::fs::read_dir(folder)
+        .with_context(|| format!("Could not read folder {folder:?}"))?
+        .map(|entry| entry.map_err(anyhow::Error::from))
+        .collect()
+}
+
+fn get_folder_name(folder: &Path) -> String {
+    folder
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn get_size(folder: &Path) -> anyhow::Result<u64> {
+    let mut size = 0;
+    for entry in read_folder(folder)? {
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            get_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_and_remove_garbage() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let hash = "a".repeat(64);
+        let old_hash = "b".repeat(64);
+        let assets_folder = crate::fs::get_assets_folder(&path).unwrap();
+        std::fs::write(assets_folder.join(format!("{hash}.png")), "current").unwrap();
+        std::fs::write(assets_folder.join(format!("{old_hash}.png")), "replaced").unwrap();
+        std::fs::write(assets_folder.join("logo.png"), "by hand").unwrap();
+        let rotated_hash = "c".repeat(64);
+        std::fs::write(assets_folder.join(format!("{rotated_hash}.png")), "sponsor").unwrap();
+        let instance_folder = crate::fs::get_instance_folder(&path, "scoreboard").unwrap();
+        std::fs::create_dir_all(&instance_folder).unwrap();
+        std::fs::write(
+            instance_folder.join("rotations.json"),
+            format!(r#"{{"sponsors":{{"items":[{{"image":{{"asset":"{rotated_hash}.png"}}}}]}}}}"#),
+        )
+        .unwrap();
+        let uploads_folder = crate::fs::get_uploads_folder(&path);
+        std::fs::create_dir_all(&uploads_folder).unwrap();
+        std::fs::write(uploads_folder.join("0123.part"), "chunk").unwrap();
+        let runtimes_folder = crate::fs::get_runtimes_folder(&path).unwrap();
+        std::fs::create_dir_all(runtimes_folder.join("1.0.0")).unwrap();
+        std::fs::write(runtimes_folder.join("1.0.0").join(RUNTIME_FILE_NAME), "").unwrap();
+        std::fs::create_dir_all(runtimes_folder.join("1.1.0")).unwrap();
+        std::fs::write(
+            runtimes_folder.join("1.1.0").join("zagreus-runtime.js.map"),
+            "{}",
+        )
+        .unwrap();
+        let referenced = HashSet::from([format!("{hash}.png")]);
+
+        // everything is within the grace period
+        assert!(find_garbage(&path, &referenced, SystemTime::now())
+            .unwrap()
+            .is_empty());
+
+        let later = SystemTime::now() + GRACE_PERIOD + Duration::from_secs(60);
+        let entries = find_garbage(&path, &referenced, later).unwrap();
+        assert_eq!(
+            vec![
+                GarbageEntry {
+                    path: format!("assets/{old_hash}.png"),
+                    kind: GarbageKind::AssetVersion,
+                    bytes: 8,
+                },
+                GarbageEntry {
+                    path: String::from("runtimes/1.1.0"),
+                    kind: GarbageKind::Runtime,
+                    bytes: 2,
+                },
+                GarbageEntry {
+                    path: String::from("uploads/0123.part"),
+                    kind: GarbageKind::Upload,
+                    bytes: 5,
+                },
+            ],
+            entries
+        );
+        assert_eq!(15, GarbageReport::new(true, entries.clone()).total_bytes);
+
+        assert_eq!(3, remove_garbage(&path, entries).len());
+        assert!(find_garbage(&path, &referenced, later).unwrap().is_empty());
+        assert!(assets_folder.join("logo.png").exists());
+        assert!(runtimes_folder.join("1.0.0").exists());
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
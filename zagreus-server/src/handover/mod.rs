@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::net::{Shutdown, SocketAddr};
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+
+use crate::config::ZagreusServerConfig;
+use crate::controller::ServerController;
+use crate::state::InstanceState;
+use crate::tls::LocalServer;
+
+/// Environment variables set by systemd for socket activation.
+const LISTEN_PID_VARIABLE: &str = "LISTEN_PID";
+const LISTEN_FDS_VARIABLE: &str = "LISTEN_FDS";
+/// First file descriptor passed by systemd.
+#[cfg(unix)]
+const LISTEN_FDS_START: i32 = 3;
+const LISTEN_BACKLOG: u32 = 1024;
+/// Timeout for handing over the state to the new process directly.
+const HANDOVER_REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const ACCEPT_LOOP_STOP_DELAY: Duration = Duration::from_millis(50);
+/// Interval in which the draining server checks whether all renderers disconnected.
+const CLIENT_DISCONNECT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const HANDOVER_FILE_NAME: &str = "handover.json";
+/// Handover files which are older are not restored, so a regular start does not resurrect an
+/// outdated state.
+const MAX_HANDOVER_FILE_AGE_SECONDS: i64 = 60;
+
+/// State handed over from a draining process to the process replacing it.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct HandoverState {
+    pub created: DateTime<Utc>,
+    /// Process which handed over the state.
+    #[serde(default)]
+    pub process_id: u32,
+    pub states: BTreeMap<String, InstanceState>,
+}
+
+pub struct ServerListener {
+    pub listener: std::net::TcpListener,
+    /// Handle to stop listening when draining, while the listener is owned by the server.
+    pub drain_handle: std::net::TcpListener,
+    /// Whether the listener was passed by systemd socket activation.
+    pub socket_activated: bool,
+}
+
+/// Returns the listener passed by systemd socket activation or binds a new one. With
+/// `reuse_port` a new process can bind the port while the old process still listens on it.
+pub fn create_listener(address: SocketAddr, reuse_port: bool) -> anyhow::Result<ServerListener> {
+    if let Some(listener) = take_activated_listener()? {
+        info!("Using listener passed by socket activation.");
+        return Ok(ServerListener {
+            drain_handle: listener.try_clone()?,
+            listener,
+            socket_activated: true,
+        });
+    }
+
+    let socket = if address.is_ipv4() {
+        tokio::net::TcpSocket::new_v4()?
+    } else {
+        tokio::net::TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    if reuse_port {
+        #[cfg(unix)]
+        socket.set_reuseport(true)?;
+        #[cfg(not(unix))]
+        warn!("Reusing the server port is only supported on unix systems.");
+    }
+    socket
+        .bind(address)
+        .with_context(|| format!("Could not bind to {address}"))?;
+    let listener = socket.listen(LISTEN_BACKLOG)?.into_std()?;
+    Ok(ServerListener {
+        drain_handle: listener.try_clone()?,
+        listener,
+        socket_activated: false,
+    })
+}
+
+#[cfg(unix)]
+fn take_activated_listener() -> anyhow::Result<Option<std::net::TcpListener>> {
+    use std::os::unix::io::FromRawFd;
+
+    let for_this_process = std::env::var(LISTEN_PID_VARIABLE)
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        == Some(std::process::id());
+    let fds = std::env::var(LISTEN_FDS_VARIABLE)
+        .ok()
+        .and_then(|fds| fds.parse::<i32>().ok())
+        .unwrap_or(0);
+    if !for_this_process || fds < 1 {
+        return Ok(None);
+    }
+    if fds > 1 {
+        warn!("{} sockets were passed, only the first one is used.", fds);
+    }
+    // SAFETY: systemd passes the listening sockets to this process starting at descriptor 3 and
+    // nothing else in the process owns them
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(LISTEN_FDS_START) };
+    listener.set_nonblocking(true)?;
+    Ok(Some(listener))
+}
+
+#[cfg(not(unix))]
+fn take_activated_listener() -> anyhow::Result<Option<std::net::TcpListener>> {
+    if std::env::var_os(LISTEN_FDS_VARIABLE).is_some() {
+        warn!("Socket activation is only supported on unix systems.");
+    }
+    Ok(None)
+}
+
+/// Waits for SIGTERM (or Ctrl+C) and drains the server: it stops listening, finishes in-flight
+/// requests, hands over and stores the state of all instances and tells the renderers that it
+/// shuts down before disconnecting them, so they reconnect to the process that took over the
+/// port. The state is sent to the new process directly if it already listens on the port,
+/// otherwise it is stored in a file which the new process loads on start.
+pub async fn drain_on_signal(
+    controller: Arc<ServerController>,
+    handle: axum_server::Handle,
+    drain_handle: std::net::TcpListener,
+    socket_activated: bool,
+    configuration: ZagreusServerConfig,
+    local_server: LocalServer,
+) {
+    wait_for_signal().await;
+    info!("Draining server...");
+    crate::service::notify("STOPPING=1");
+    let timeout = Duration::from_secs(configuration.shutdown_timeout_seconds);
+    handle.graceful_shutdown(Some(timeout));
+    // give the accept loop time to stop before its listener becomes invalid
+    tokio::time::sleep(ACCEPT_LOOP_STOP_DELAY).await;
+    // the listener is closed for the whole system, so new connections only reach the new process
+    if let Err(err) = socket2::Socket::from(drain_handle).shutdown(Shutdown::Read) {
+        warn!("Could not stop listening: {}.", err);
+    }
+
+    let handover = controller.get_handover_state().await;
+    // with socket activation the new process is only started after this one exited
+    let handed_over = !socket_activated
+        && match send_handover_state(&local_server, configuration.auth.get_write_key(), &handover)
+            .await
+        {
+            Ok(()) => true,
+            Err(err) => {
+                debug!("Could not hand over state directly: {:#}.", err);
+                false
+            }
+        };
+    if handed_over {
+        info!("Handed over state to the new server process.");
+    } else if let Err(err) = write_handover_file(&configuration.data_folder, &handover).await {
+        error!("Could not store state for handover: {:#}.", err);
+    }
+    // changes of the last moments may still wait to be stored
+    if configuration.persist_state {
+        crate::state::persistence::persist_all_states(&controller).await;
+    }
+
+    controller.disconnect_all_clients().await;
+    let deadline = Instant::now() + timeout;
+    while controller.get_client_count().await > 0 && Instant::now() < deadline {
+        tokio::time::sleep(CLIENT_DISCONNECT_POLL_INTERVAL).await;
+    }
+}
+
+/// Shutdown requested from within the process, e.g. when the Windows service is stopped.
+static SHUTDOWN_REQUEST: OnceLock<tokio::sync::Notify> = OnceLock::new();
+
+/// Drains the server as if it received SIGTERM.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn request_shutdown() {
+    get_shutdown_request().notify_one();
+}
+
+fn get_shutdown_request() -> &'static tokio::sync::Notify {
+    SHUTDOWN_REQUEST.get_or_init(tokio::sync::Notify::new)
+}
+
+async fn wait_for_signal() {
+    tokio::select! {
+        _ = wait_for_os_signal() => {},
+        _ = get_shutdown_request().notified() => {},
+    }
+}
+
+async fn wait_for_os_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {},
+                    _ = tokio::signal::ctrl_c() => {},
+                }
+                return;
+            }
+            Err(err) => error!("Could not listen for SIGTERM: {}.", err),
+        }
+    }
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        error!("Could not listen for Ctrl+C: {}.", err);
+        std::future::pending::<()>().await;
+    }
+}
+
+async fn send_handover_state(
+    local_server: &LocalServer,
+    api_key: Option<&str>,
+    handover: &HandoverState,
+) -> anyhow::Result<()> {
+    // the listener of this process is closed, so only a new process can answer
+    let mut request = local_server
+        .get_http_client(HANDOVER_REQUEST_TIMEOUT)?
+        .put(local_server.get_url("http", "/api/server/handover"))
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    if let Some(api_key) = api_key {
+        request = request.header(crate::auth::API_KEY_HEADER, api_key);
+    }
+    request
+        .body(serde_json::to_vec(handover)?)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+async fn write_handover_file(data_folder: &Path, handover: &HandoverState) -> anyhow::Result<()> {
+    let path = data_folder.join(HANDOVER_FILE_NAME);
+    tokio::fs::write(&path, serde_json::to_vec(handover)?).await?;
+    info!("Stored state for handover in {:?}.", path);
+    Ok(())
+}
+
+/// Loads and removes the handover file of a previous process, if it was stored recently.
+pub async fn take_handover_file(data_folder: &Path) -> anyhow::Result<Option<HandoverState>> {
+    let path = data_folder.join(HANDOVER_FILE_NAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let data = tokio::fs::read(&path).await?;
+    tokio::fs::remove_file(&path).await?;
+    let handover: HandoverState = serde_json::from_slice(&data)?;
+    if (Utc::now() - handover.created).num_seconds() > MAX_HANDOVER_FILE_AGE_SECONDS {
+        info!("Ignoring outdated handover state of {}.", handover.created);
+        return Ok(None);
+    }
+    Ok(Some(handover))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handover_file_is_taken_once() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let mut handover = HandoverState {
+            created: Utc::now(),
+            ..HandoverState::default()
+        };
+        handover
+            .states
+            .insert(String::from("scoreboard"), InstanceState::default());
+        write_handover_file(&path, &handover).await.unwrap();
+
+        let taken = take_handover_file(&path).await.unwrap().unwrap();
+        assert!(taken.states.contains_key("scoreboard"));
+        assert!(take_handover_file(&path).await.unwrap().is_none());
+
+        handover.created = Utc::now() - chrono::Duration::minutes(5);
+        write_handover_file(&path, &handover).await.unwrap();
+        assert!(take_handover_file(&path).await.unwrap().is_none());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
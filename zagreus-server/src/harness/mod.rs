@@ -0,0 +1,273 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::ws::Message;
+use axum::http::{header, Method, Request};
+use serde_json::Value;
+use tower::ServiceExt;
+
+use crate::config::{AuthConfig, ZagreusServerConfig};
+use crate::controller::ServerController;
+use crate::rules::RuleAction;
+use crate::webhooks::Webhooks;
+use crate::websocket::server::WebsocketServer;
+
+/// Maximum number of API calls in a single harness run.
+pub const MAX_HARNESS_CALLS: usize = 1_000;
+
+/// API call of a harness run, e.g. `{"path": "/api/instance/scoreboard/data/text", "body": ...}`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HarnessCall {
+    #[serde(default = "get_default_method")]
+    pub method: String,
+    pub path: String,
+    #[serde(default)]
+    pub body: Option<Value>,
+}
+
+fn get_default_method() -> String {
+    Method::POST.to_string()
+}
+
+/// Response to an API call of a harness run with the messages the renderers of the instance
+/// would have received.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HarnessResponse {
+    pub status: u16,
+    pub body: Value,
+    pub messages: Vec<Value>,
+}
+
+/// Returns an error message if a call is not a request to the API.
+pub fn validate_calls(calls: &[HarnessCall]) -> Option<String> {
+    if calls.len() > MAX_HARNESS_CALLS {
+        return Some(format!(
+            "A harness run may contain at most {MAX_HARNESS_CALLS} calls."
+        ));
+    }
+    for call in calls {
+        if Method::from_bytes(call.method.as_bytes()).is_err() {
+            return Some(format!("Invalid method {}.", call.method));
+        }
+        if !call.path.starts_with("/api/") {
+            return Some(format!("{} is not an API path.", call.path));
+        }
+    }
+    None
+}
+
+/// Executes the calls against a sandbox server which has the documents (e.g. rules and computed
+/// elements) of the instance but no clients, and records the messages sent to the instance for
+/// every call. Webhooks (including those of rules) are not called and the state starts empty, so
+/// runs are repeatable.
+pub async fn run_harness(
+    configuration: &ZagreusServerConfig,
+    instance: &str,
+    calls: Vec<HarnessCall>,
+) -> anyhow::Result<Vec<HarnessResponse>> {
+    let sandbox_folder = crate::fs::create_temp_folder(&configuration.data_folder)?;
+    let result = run_in_sandbox(configuration, &sandbox_folder, instance, calls).await;
+    if let Err(err) = tokio::fs::remove_dir_all(&sandbox_folder).await {
+        warn!(
+            "Could not delete harness folder {:?}: {}.",
+            sandbox_folder, err
+        );
+    }
+    result
+}
+
+async fn run_in_sandbox(
+    configuration: &ZagreusServerConfig,
+    sandbox_folder: &Path,
+    instance: &str,
+    calls: Vec<HarnessCall>,
+) -> anyhow::Result<Vec<HarnessResponse>> {
+    copy_instance_documents(
+        &crate::fs::get_instance_folder(&configuration.data_folder, instance)?,
+        &crate::fs::get_instance_folder(sandbox_folder, instance)?,
+    )?;
+    let sandbox_configuration = ZagreusServerConfig {
+        data_folder: sandbox_folder.to_owned(),
+        // the run itself was authorized
+        auth: AuthConfig::default(),
+        persist_state: false,
+        ..configuration.clone()
+    };
+    let websocket_server = Arc::new(WebsocketServer::new(
+        sandbox_folder.to_owned(),
+        sandbox_configuration.connection_limits.clone(),
+        sandbox_configuration.heartbeat.clone(),
+        sandbox_configuration.template_events.clone(),
+    ));
+    let controller = Arc::new(ServerController::new(
+        &sandbox_configuration,
+        websocket_server.clone(),
+    )?);
+    remove_webhooks(&controller, instance).await?;
+    let router = crate::endpoint::routes::get_router(&sandbox_configuration, controller)?;
+    let mut receiver = websocket_server.add_capturing_client(instance).await;
+
+    let mut responses = Vec::with_capacity(calls.len());
+    for call in calls {
+        let mut request = Request::builder()
+            .method(call.method.as_str())
+            .uri(&call.path);
+        let body = match call.body {
+            Some(body) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(serde_json::to_vec(&body)?)
+            }
+            None => Body::empty(),
+        };
+        let response = router.clone().oneshot(request.body(body)?).await?;
+        let status = response.status().as_u16();
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+
+        let mut messages = Vec::new();
+        while let Ok(Ok(Message::Text(text))) = receiver.try_recv() {
+            messages.push(serde_json::from_str(&text)?);
+        }
+        responses.push(HarnessResponse {
+            status,
+            body: parse_body(&body),
+            messages,
+        });
+    }
+    Ok(responses)
+}
+
+/// Copies the documents of the instance, leaving out its stored state.
+fn copy_instance_documents(source: &Path, target: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(target)?;
+    if !source.exists() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(source)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        if path.is_file() && file_name != crate::state::persistence::STATE_FILE_NAME {
+            std::fs::copy(&path, target.join(file_name))?;
+        }
+    }
+    Ok(())
+}
+
+async fn remove_webhooks(controller: &ServerController, instance: &str) -> anyhow::Result<()> {
+    controller.set_webhooks(instance, Webhooks::new()).await?;
+    let mut rules = controller.get_rules(instance).await?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+    for rule in &mut rules {
+        rule.actions
+            .retain(|action| !matches!(action, RuleAction::Webhook { .. }));
+    }
+    rules.retain(|rule| !rule.actions.is_empty());
+    controller.set_rules(instance, rules).await
+}
+
+fn parse_body(body: &[u8]) -> Value {
+    if body.is_empty() {
+        return Value::Null;
+    }
+    serde_json::from_slice(body)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(body).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_run_harness() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let configuration = ZagreusServerConfig {
+            data_folder: path.clone(),
+            ..ZagreusServerConfig::default()
+        };
+        let instance_folder = crate::fs::get_instance_folder(&path, "scoreboard").unwrap();
+        std::fs::create_dir_all(&instance_folder).unwrap();
+        let rules = json!([{
+            "name": "goal",
+            "element": "HomeScore",
+            "condition": "changed",
+            "actions": [
+                {"action": "executeAnimation", "animationSequence": "GoalIn"},
+                {"action": "webhook", "url": "http://automation.local/goal"}
+            ]
+        }]);
+        std::fs::write(
+            instance_folder.join(crate::rules::RULES_FILE_NAME),
+            rules.to_string(),
+        )
+        .unwrap();
+
+        let calls: Vec<HarnessCall> = serde_json::from_value(json!([
+            {"path": "/api/instance/scoreboard/data/text", "body": {"id": "HomeScore", "text": "1"}},
+            {"method": "GET", "path": "/api/instance/scoreboard/rules"}
+        ]))
+        .unwrap();
+        assert_eq!(None, validate_calls(&calls));
+        let responses = run_harness(&configuration, "scoreboard", calls)
+            .await
+            .unwrap();
+
+        assert_eq!(200, responses[0].status);
+        assert_eq!(
+            vec![
+                json!({"tag": "SetText", "payload": {"id": "HomeScore", "text": "1"}}),
+                json!({"tag": "ExecuteAnimation", "payload": {"animationSequence": "GoalIn"}}),
+            ],
+            responses[0].messages
+        );
+        // the rules of the instance are used without their webhooks
+        assert_eq!(1, responses[1].body[0]["actions"].as_array().unwrap().len());
+        assert!(responses[1].messages.is_empty());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replaced_state_clears_missing_elements() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let configuration = ZagreusServerConfig {
+            data_folder: path.clone(),
+            ..ZagreusServerConfig::default()
+        };
+
+        let calls: Vec<HarnessCall> = serde_json::from_value(json!([
+            {"method": "PUT", "path": "/api/instance/news/state", "body": {
+                "texts": {"Title": "Breaking", "Subtitle": "Live"},
+                "classes": {"LowerThird": {"visible": true}}
+            }},
+            {"method": "PUT", "path": "/api/instance/news/state", "body": {
+                "texts": {"Title": "Weather"}
+            }},
+            {"method": "GET", "path": "/api/instance/news/state"}
+        ]))
+        .unwrap();
+        let responses = run_harness(&configuration, "news", calls).await.unwrap();
+
+        assert_eq!(200, responses[1].status);
+        assert_eq!(
+            vec![
+                json!({"tag": "Batch", "payload": {"operations": [
+                    {"tag": "SetText", "payload": {"id": "Subtitle", "text": ""}},
+                    {"tag": "RemoveClass", "payload": {"id": "LowerThird", "class": "visible"}}
+                ]}}),
+                json!({"tag": "SetText", "payload": {"id": "Title", "text": "Weather"}}),
+            ],
+            responses[1].messages
+        );
+        assert_eq!(json!({"Title": "Weather"}), responses[2].body["texts"]);
+        assert_eq!(json!({}), responses[2].body["classes"]);
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
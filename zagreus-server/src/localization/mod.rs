@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+/// Texts per translation key of a language.
+pub type TranslationBundle = BTreeMap<String, String>;
+
+/// Translation bundles of an instance and the static labels bound to their keys, so one design
+/// can be played out in multiple languages.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Localization {
+    /// Translation bundles per language.
+    #[serde(default)]
+    pub bundles: BTreeMap<String, TranslationBundle>,
+    /// Translation key per element id of a static label.
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_language: Option<String>,
+}
+
+impl Localization {
+    /// Returns the text of every label in the active language. Labels whose key is missing in the
+    /// bundle are left out, so they keep their previous text.
+    pub fn get_label_texts(&self) -> Vec<(&str, &str)> {
+        let Some(bundle) = self
+            .active_language
+            .as_ref()
+            .and_then(|language| self.bundles.get(language))
+        else {
+            return Vec::new();
+        };
+        self.labels
+            .iter()
+            .filter_map(|(id, key)| bundle.get(key).map(|text| (id.as_str(), text.as_str())))
+            .collect()
+    }
+
+    /// Returns the keys of labels which have no translation in the given language.
+    pub fn get_missing_keys(&self, language: &str) -> Vec<&str> {
+        let bundle = self.bundles.get(language);
+        self.labels
+            .values()
+            .filter(|key| !bundle.is_some_and(|bundle| bundle.contains_key(*key)))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+pub const LOCALIZATION_FILE_NAME: &str = "localization.json";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_texts_of_active_language() {
+        let mut localization = Localization {
+            bundles: BTreeMap::from([
+                (
+                    String::from("en"),
+                    TranslationBundle::from([
+                        (String::from("score"), String::from("Score")),
+                        (String::from("halfTime"), String::from("Half time")),
+                    ]),
+                ),
+                (
+                    String::from("de"),
+                    TranslationBundle::from([(String::from("score"), String::from("Spielstand"))]),
+                ),
+            ]),
+            labels: BTreeMap::from([
+                (String::from("ScoreLabel"), String::from("score")),
+                (String::from("PeriodLabel"), String::from("halfTime")),
+            ]),
+            active_language: None,
+        };
+        assert!(localization.get_label_texts().is_empty());
+
+        localization.active_language = Some(String::from("en"));
+        assert_eq!(
+            vec![("PeriodLabel", "Half time"), ("ScoreLabel", "Score")],
+            localization.get_label_texts()
+        );
+
+        localization.active_language = Some(String::from("de"));
+        assert_eq!(
+            vec![("ScoreLabel", "Spielstand")],
+            localization.get_label_texts()
+        );
+        assert_eq!(vec!["halfTime"], localization.get_missing_keys("de"));
+    }
+}
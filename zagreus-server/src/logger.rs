@@ -1,12 +1,16 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use log::LevelFilter;
 use log4rs::append::console::ConsoleAppender;
 use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
 use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
+use log4rs::append::rolling_file::policy::compound::trigger::Trigger;
 use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
-use log4rs::append::rolling_file::RollingFileAppender;
+use log4rs::append::rolling_file::{LogFile, RollingFileAppender};
 use log4rs::config::{Appender, Config, Root};
 use log4rs::encode::json::JsonEncoder;
 
+use crate::config::LogLevel;
 use crate::APPLICATION_NAME;
 
 const LOG_FILE_NAME: &str = "log.log";
@@ -14,6 +18,26 @@ const ROTATED_LOG_FILE_NAME: &str = "log.{}.log";
 const MAX_LOG_SIZE_BYTES: u64 = 5_000_000;
 const LOG_FILE_COUNT: u32 = 5;
 
+static ROTATION_REQUESTED: AtomicBool = AtomicBool::new(false);
+static VERBOSE: AtomicBool = AtomicBool::new(false);
+
+/// Rolls the log file over when it exceeds its maximum size or when a rotation was requested.
+#[derive(Debug)]
+struct RotationTrigger {
+    size_trigger: SizeTrigger,
+}
+
+impl Trigger for RotationTrigger {
+    fn trigger(&self, file: &LogFile) -> anyhow::Result<bool> {
+        Ok(ROTATION_REQUESTED.swap(false, Ordering::Relaxed) || self.size_trigger.trigger(file)?)
+    }
+}
+
+/// Rolls the log file over after the next message, e.g. in a daily routine.
+pub fn request_rotation() {
+    ROTATION_REQUESTED.store(true, Ordering::Relaxed);
+}
+
 /// init logger configuration or panic if something fails (since we cannot log yet)
 pub fn init_logger(is_verbose: bool) {
     let log_folder_path = crate::fs::get_log_folder_path(APPLICATION_NAME).unwrap_or_else(|err| {
@@ -34,7 +58,9 @@ pub fn init_logger(is_verbose: bool) {
             panic!("Could not setup fixed window roller: {err}.");
         });
     let rolling_file_policy = CompoundPolicy::new(
-        Box::new(SizeTrigger::new(MAX_LOG_SIZE_BYTES)),
+        Box::new(RotationTrigger {
+            size_trigger: SizeTrigger::new(MAX_LOG_SIZE_BYTES),
+        }),
         Box::new(roller),
     );
     let file_appender = RollingFileAppender::builder()
@@ -50,12 +76,28 @@ pub fn init_logger(is_verbose: bool) {
             Root::builder()
                 .appender(CONSOLE_LOGGER_NAME)
                 .appender(FILE_LOGGER_NAME)
-                .build(level_filter(is_verbose)),
+                .build(LevelFilter::Trace),
         )
         .unwrap_or_else(|err| {
             panic!("Could not construct logging config: {err}");
         });
     log4rs::init_config(config).unwrap();
+    // the appenders accept everything, so the level can be changed without rebuilding them
+    VERBOSE.store(is_verbose, Ordering::Relaxed);
+    set_log_level(None);
+}
+
+/// Sets the level of all loggers, falling back to the level given by `--verbose`.
+pub fn set_log_level(level: Option<LogLevel>) {
+    let level_filter = match level {
+        Some(LogLevel::Error) => LevelFilter::Error,
+        Some(LogLevel::Warn) => LevelFilter::Warn,
+        Some(LogLevel::Info) => LevelFilter::Info,
+        Some(LogLevel::Debug) => LevelFilter::Debug,
+        Some(LogLevel::Trace) => LevelFilter::Trace,
+        None => level_filter(VERBOSE.load(Ordering::Relaxed)),
+    };
+    log::set_max_level(level_filter);
 }
 
 fn level_filter(is_verbose: bool) -> LevelFilter {
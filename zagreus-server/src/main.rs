@@ -7,21 +7,81 @@ extern crate serde_derive;
 
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::cli::{get_command, ZagreusServerCommand};
+use crate::cli::{get_command, ZagreusServerCommand, ZagreusServerSubcommand};
 use crate::config::loader::ConfigurationManager;
 use crate::config::ZagreusServerConfig;
 use crate::controller::ServerController;
+use crate::tls::LocalServer;
 use crate::websocket::server::WebsocketServer;
 
+mod actions;
+mod amcp;
+mod analytics;
+mod approval;
+mod archive;
+mod asrun;
+mod assets;
+mod audit;
+mod auth;
+mod bench;
 mod cli;
 mod config;
 mod controller;
+mod crash;
 mod data;
+mod datamap;
+mod datasource;
+mod defaults;
+mod emergency;
 mod endpoint;
+mod errors;
+mod events;
+mod expiry;
+mod expression;
+mod fakedata;
+mod feedback;
+mod flags;
 mod fs;
+mod gc;
+mod handover;
+mod harness;
+mod localization;
 mod logger;
+mod metrics;
+mod migration;
+mod monitor;
+mod mqtt;
+mod notes;
+mod osc;
+mod outbound;
+mod prefetch;
+mod presence;
+mod replace;
+mod resilience;
+mod resources;
+mod rotation;
+mod routine;
+mod routing;
+mod rules;
+mod rundown;
+mod scheduler;
+mod secrets;
+mod selftest;
+mod service;
+mod session;
+mod share;
+mod snapshot;
+mod stale;
+mod state;
+mod store;
+mod timing;
+mod tls;
+mod watchdog;
+mod webhooks;
 mod websocket;
+mod worker;
 
 const ZAGREUS_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -30,7 +90,7 @@ const CONFIG_FILE_NAME: &str = "config.json";
 
 #[tokio::main]
 async fn main() {
-    let command = get_command();
+    let mut command = get_command();
     let application_folder = fs::get_application_folder(APPLICATION_NAME).unwrap_or_else(|err| {
         panic!("Could not get application folder: {err}");
     });
@@ -39,8 +99,42 @@ async fn main() {
     match ConfigurationManager::<ZagreusServerConfig>::load(&application_folder, CONFIG_FILE_NAME) {
         Ok(manager) => {
             let mut configuration = manager.get_configuration();
+            logger::set_log_level(configuration.log_level);
+            let self_test = command.self_test;
+            let install_service = command.install_service;
+            let run_as_service = command.run_as_service;
+            let subcommand = command.subcommand.take();
+            let service_arguments = get_service_arguments(&command);
             override_configuration_with_cli_flags(&mut configuration, command);
-            start_with_config(configuration).await
+            if let Some(ZagreusServerSubcommand::Bench {
+                instance,
+                clients,
+                rate,
+                duration_seconds,
+            }) = subcommand
+            {
+                let options = bench::BenchOptions {
+                    instance,
+                    clients,
+                    rate,
+                    duration: Duration::from_secs(duration_seconds),
+                };
+                run_bench(&configuration, &options).await
+            } else if self_test {
+                run_self_test(&configuration).await
+            } else if install_service {
+                if let Err(err) = service::install_service(service_arguments) {
+                    error!("Could not install service: {:#}.", err);
+                    std::process::exit(1);
+                }
+            } else if run_as_service {
+                if let Err(err) = service::run_as_service(configuration) {
+                    error!("Could not run as service: {:#}.", err);
+                    std::process::exit(1);
+                }
+            } else {
+                start_with_config(configuration).await
+            }
         }
         Err(err) => error!("Could not load configuration: {}.", err),
     }
@@ -48,28 +142,273 @@ async fn main() {
 
 async fn start_with_config(configuration: ZagreusServerConfig) {
     info!("Starting zagreus server...");
-    let server_port = configuration.server_port;
+    let certificate_files = if configuration.tls.enabled {
+        match tls::get_certificate_files(&configuration) {
+            Ok(certificate_files) => Some(certificate_files),
+            Err(err) => {
+                error!("Could not prepare certificate: {:#}", err);
+                return;
+            }
+        }
+    } else {
+        None
+    };
+    let local_server = LocalServer::new(&configuration, certificate_files.as_ref());
     info!(
-        "API docs are available at http://localhost:{}/static/swagger-docs/?url=spec.yaml",
-        server_port
+        "API docs are available at {}",
+        local_server.get_url("http", "/static/swagger-docs/?url=spec.yaml")
     );
-    let ws_server = Arc::new(WebsocketServer::new());
+    if let Err(err) = fs::clear_temp_folders(&configuration.data_folder) {
+        warn!("Could not clear temporary folders: {:#}.", err);
+    }
+    if let Err(err) = migration::migrate_data_folder(&configuration.data_folder) {
+        error!("Could not migrate data folder: {:#}.", err);
+        return;
+    }
+    let ws_server = Arc::new(WebsocketServer::new(
+        configuration.data_folder.clone(),
+        configuration.connection_limits.clone(),
+        configuration.heartbeat.clone(),
+        configuration.template_events.clone(),
+    ));
+
+    let server_controller = match ServerController::new(&configuration, ws_server) {
+        Ok(server_controller) => Arc::new(server_controller),
+        Err(err) => {
+            error!("Could not initialize server: {:#}", err);
+            return;
+        }
+    };
+
+    crash::install_panic_hook(&server_controller, &configuration);
+
+    if configuration.persist_state {
+        if let Err(err) = server_controller.restore_persisted_states().await {
+            error!("Could not restore stored state: {:#}.", err);
+        }
+        tokio::spawn(state::persistence::run_state_persistence(
+            server_controller.clone(),
+        ));
+    }
+    // handed over state is more recent than the stored state
+    match handover::take_handover_file(&configuration.data_folder).await {
+        Ok(Some(handover)) => server_controller.restore_handover_state(handover).await,
+        Ok(None) => {}
+        Err(err) => error!("Could not restore handed over state: {:#}.", err),
+    }
+
+    tokio::spawn(snapshot::run_auto_snapshots(
+        server_controller.clone(),
+        configuration.auto_snapshot.clone(),
+    ));
+    tokio::spawn(defaults::run_daily_resets(server_controller.clone()));
+    tokio::spawn(rotation::run_rotations(server_controller.clone()));
+    tokio::spawn(expiry::run_expiry(server_controller.clone()));
+    tokio::spawn(scheduler::run_scheduler(server_controller.clone()));
+    match fs::get_application_folder(APPLICATION_NAME) {
+        Ok(application_folder) => {
+            tokio::spawn(config::reload::run_config_reload(
+                server_controller.clone(),
+                application_folder,
+                CONFIG_FILE_NAME,
+            ));
+        }
+        Err(err) => error!("Could not watch configuration: {:#}.", err),
+    }
+    if !configuration.daily_routines.is_empty() {
+        tokio::spawn(routine::run_daily_routines(
+            server_controller.clone(),
+            configuration.clone(),
+        ));
+    }
+    tokio::spawn(datasource::run_data_sources(server_controller.clone()));
+    if let Some(port) = configuration.amcp.port {
+        tokio::spawn(amcp::run_amcp_server(
+            server_controller.clone(),
+            SocketAddr::new(configuration.bind_address, port),
+            configuration.amcp.templates.clone(),
+        ));
+    }
+    if let Some(port) = configuration.osc.port {
+        tokio::spawn(osc::run_osc_server(
+            server_controller.clone(),
+            SocketAddr::new(configuration.bind_address, port),
+            configuration.osc.addresses.clone(),
+        ));
+    }
+    tokio::spawn(session::run_session_recording(server_controller.clone()));
+    if configuration.mqtt.host.is_some() {
+        tokio::spawn(mqtt::run_mqtt_bridge(
+            server_controller.clone(),
+            configuration.mqtt.clone(),
+        ));
+    }
+    for data_map in &configuration.data_maps {
+        tokio::spawn(datamap::run_data_map_bridge(
+            server_controller.clone(),
+            data_map.clone(),
+        ));
+    }
+    for stale_data in &configuration.stale_data {
+        tokio::spawn(stale::run_stale_data_watch(
+            server_controller.clone(),
+            stale_data.clone(),
+        ));
+    }
+    for watchdog in &configuration.watchdogs {
+        tokio::spawn(watchdog::run_renderer_watchdog(
+            server_controller.clone(),
+            watchdog.clone(),
+        ));
+    }
+    for instance in &configuration.synthetic_monitor.instances {
+        tokio::spawn(monitor::run_synthetic_monitor(
+            server_controller.clone(),
+            local_server.clone(),
+            instance.clone(),
+            configuration.synthetic_monitor.clone(),
+        ));
+    }
 
-    let server_controller = Arc::new(ServerController::new(ws_server.clone()));
+    let router = match endpoint::routes::get_router(&configuration, server_controller.clone()) {
+        Ok(router) => router,
+        Err(err) => {
+            error!("Could not configure server routes: {}", err);
+            return;
+        }
+    };
+    if configuration.auth.api_keys.is_empty() {
+        warn!("No API keys are configured, the API can be used without authentication.");
+    }
+    let rustls_config = match &certificate_files {
+        Some(certificate_files) => match tls::load_server_config(certificate_files).await {
+            Ok(rustls_config) => {
+                tokio::spawn(tls::run_certificate_reload(
+                    rustls_config.clone(),
+                    certificate_files.clone(),
+                    Duration::from_secs(configuration.tls.reload_interval_seconds),
+                ));
+                Some(rustls_config)
+            }
+            Err(err) => {
+                error!("Could not start server: {:#}", err);
+                return;
+            }
+        },
+        None => None,
+    };
+    let addr = SocketAddr::new(configuration.bind_address, configuration.server_port);
+    let server_listener = match handover::create_listener(addr, configuration.reuse_port) {
+        Ok(server_listener) => server_listener,
+        Err(err) => {
+            error!("Could not start server: {:#}", err);
+            return;
+        }
+    };
+    service::notify("READY=1");
+    tokio::spawn(service::run_watchdog());
 
-    match endpoint::routes::get_router(&configuration, ws_server.clone(), server_controller.clone())
-    {
-        Ok(router) => {
-            let addr = SocketAddr::from(([0, 0, 0, 0], server_port));
-            if let Err(err) = axum_server::bind(addr)
-                .serve(router.into_make_service())
+    let handle = axum_server::Handle::new();
+    let drain = tokio::spawn(handover::drain_on_signal(
+        server_controller,
+        handle.clone(),
+        server_listener.drain_handle,
+        server_listener.socket_activated,
+        configuration.clone(),
+        local_server,
+    ));
+    let make_service = router.into_make_service_with_connect_info::<SocketAddr>();
+    let result = match rustls_config {
+        Some(rustls_config) => {
+            axum_server::from_tcp_rustls(server_listener.listener, rustls_config)
+                .handle(handle)
+                .serve(make_service)
                 .await
-            {
-                error!("Could not start server: {}", err);
+        }
+        None => {
+            axum_server::from_tcp(server_listener.listener)
+                .handle(handle)
+                .serve(make_service)
+                .await
+        }
+    };
+    if let Err(err) = result {
+        error!("Could not start server: {}", err);
+        return;
+    }
+    // the process exits once the state was handed over and the renderers disconnected
+    if let Err(err) = drain.await {
+        error!("Could not drain server: {}.", err);
+    }
+    info!("Server stopped.");
+}
+
+async fn run_self_test(configuration: &ZagreusServerConfig) {
+    let report = selftest::run_self_test(configuration, None).await;
+    for check in &report.checks {
+        match check.status {
+            selftest::CheckStatus::Failed => error!("[FAILED] {}: {}", check.name, check.message),
+            selftest::CheckStatus::Passed => info!("[PASSED] {}: {}", check.name, check.message),
+            selftest::CheckStatus::Skipped => info!("[SKIPPED] {}: {}", check.name, check.message),
+        }
+    }
+    if !report.passed {
+        std::process::exit(1);
+    }
+}
+
+async fn run_bench(configuration: &ZagreusServerConfig, options: &bench::BenchOptions) {
+    let certificate_files = if configuration.tls.enabled {
+        match tls::get_certificate_files(configuration) {
+            Ok(certificate_files) => Some(certificate_files),
+            Err(err) => {
+                error!("Could not prepare certificate: {:#}", err);
+                std::process::exit(1);
             }
         }
-        Err(err) => error!("Could not configure server routes: {}", err),
+    } else {
+        None
+    };
+    let local_server = LocalServer::new(configuration, certificate_files.as_ref());
+    info!(
+        "Running benchmark against {} with {} clients and {} commands per second for {} seconds.",
+        local_server.get_url("http", ""),
+        options.clients,
+        options.rate,
+        options.duration.as_secs()
+    );
+    match bench::run_bench(&local_server, configuration.auth.get_write_key(), options).await {
+        Ok(report) => {
+            for line in report.to_string().lines() {
+                info!("{}", line);
+            }
+        }
+        Err(err) => {
+            error!("Benchmark failed: {:#}.", err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Returns the flags which are passed to the server when it is started as service.
+fn get_service_arguments(command: &ZagreusServerCommand) -> Vec<std::ffi::OsString> {
+    let mut arguments = Vec::new();
+    if command.verbose {
+        arguments.push("--verbose".into());
+    }
+    if let Some(bind_address) = command.bind_address {
+        arguments.push("--bind-address".into());
+        arguments.push(bind_address.to_string().into());
+    }
+    if let Some(server_port) = command.server_port {
+        arguments.push("--server-port".into());
+        arguments.push(server_port.to_string().into());
     }
+    if let Some(data_folder) = &command.data_folder {
+        arguments.push("--data-folder".into());
+        arguments.push(data_folder.clone().into_os_string());
+    }
+    arguments
 }
 
 fn override_configuration_with_cli_flags(
@@ -80,6 +419,10 @@ fn override_configuration_with_cli_flags(
         configuration.data_folder = data_folder;
     }
 
+    if let Some(bind_address) = command.bind_address {
+        configuration.bind_address = bind_address;
+    }
+
     if let Some(server_port) = command.server_port {
         configuration.server_port = server_port;
     }
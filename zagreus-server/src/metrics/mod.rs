@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::extract::{MatchedPath, State};
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+
+pub const METRICS_PATH: &str = "/metrics";
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+/// Upper bounds in seconds of the buckets of the request latency histogram.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+/// Route label of requests which did not match a route, so unknown paths do not create series.
+const UNMATCHED_ROUTE: &str = "unmatched";
+/// Prefix of the path axum matches for the fallback of a router.
+const FALLBACK_ROUTE_PREFIX: &str = "/*__private__";
+
+#[derive(Default)]
+struct Histogram {
+    /// Number of observations per bucket, not cumulative.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, value: f64) {
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| value <= *bound) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Counters of the server which are exposed in the Prometheus text format, e.g. to alert when
+/// renderers disconnect during a show.
+#[derive(Default)]
+pub struct Metrics {
+    messages_sent: AtomicU64,
+    asset_uploads: AtomicU64,
+    client_errors: AtomicU64,
+    api_errors: AtomicU64,
+    /// Latencies per method and route.
+    request_latencies: Mutex<BTreeMap<(String, String), Histogram>>,
+}
+
+impl Metrics {
+    pub fn record_message_sent(&self) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_asset_upload(&self) {
+        self.asset_uploads.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records an error reported by a client, e.g. an exception in a template.
+    pub fn record_client_error(&self) {
+        self.client_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_request(&self, method: &str, route: &str, failed: bool, latency: Duration) {
+        if failed {
+            self.api_errors.fetch_add(1, Ordering::Relaxed);
+        }
+        self.lock_latencies()
+            .entry((method.to_owned(), route.to_owned()))
+            .or_default()
+            .observe(latency.as_secs_f64());
+    }
+
+    fn lock_latencies(&self) -> std::sync::MutexGuard<'_, BTreeMap<(String, String), Histogram>> {
+        // the histograms stay consistent even if a holder panicked
+        self.request_latencies
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Renders all metrics with the given number of connected clients per instance.
+    pub fn render(&self, clients: &BTreeMap<String, usize>) -> String {
+        let mut output = String::new();
+        write_header(
+            &mut output,
+            "zagreus_websocket_clients",
+            "gauge",
+            "Connected websocket clients per template instance.",
+        );
+        for (instance, count) in clients {
+            let _ = writeln!(
+                output,
+                "zagreus_websocket_clients{{instance=\"{}\"}} {}",
+                escape_label(instance),
+                count
+            );
+        }
+
+        write_counter(
+            &mut output,
+            "zagreus_websocket_messages_sent_total",
+            "Messages sent to websocket clients.",
+            self.messages_sent.load(Ordering::Relaxed),
+        );
+        write_counter(
+            &mut output,
+            "zagreus_asset_uploads_total",
+            "Assets uploaded to the server.",
+            self.asset_uploads.load(Ordering::Relaxed),
+        );
+        write_header(
+            &mut output,
+            "zagreus_errors_total",
+            "counter",
+            "Errors reported by clients and API requests which failed with a server error.",
+        );
+        let _ = writeln!(
+            output,
+            "zagreus_errors_total{{source=\"client\"}} {}",
+            self.client_errors.load(Ordering::Relaxed)
+        );
+        let _ = writeln!(
+            output,
+            "zagreus_errors_total{{source=\"api\"}} {}",
+            self.api_errors.load(Ordering::Relaxed)
+        );
+
+        write_header(
+            &mut output,
+            "zagreus_http_request_duration_seconds",
+            "histogram",
+            "Latencies of HTTP requests per method and route.",
+        );
+        for ((method, route), histogram) in self.lock_latencies().iter() {
+            let labels = format!(
+                "method=\"{}\",route=\"{}\"",
+                escape_label(method),
+                escape_label(route)
+            );
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    output,
+                    "zagreus_http_request_duration_seconds_bucket{{{labels},le=\"{bound}\"}} {cumulative}"
+                );
+            }
+            let _ = writeln!(
+                output,
+                "zagreus_http_request_duration_seconds_bucket{{{labels},le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(
+                output,
+                "zagreus_http_request_duration_seconds_sum{{{labels}}} {}",
+                histogram.sum
+            );
+            let _ = writeln!(
+                output,
+                "zagreus_http_request_duration_seconds_count{{{labels}}} {}",
+                histogram.count
+            );
+        }
+        output
+    }
+}
+
+/// Measures the latency of every request by its route, so the number of series does not grow
+/// with the instances.
+pub async fn record_requests(
+    State(metrics): State<Arc<Metrics>>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str())
+        .filter(|path| !path.starts_with(FALLBACK_ROUTE_PREFIX))
+        .unwrap_or(UNMATCHED_ROUTE)
+        .to_owned();
+    let started = Instant::now();
+    let response = next.run(request).await;
+    metrics.record_request(
+        &method,
+        &route,
+        response.status().is_server_error(),
+        started.elapsed(),
+    );
+    response
+}
+
+fn write_header(output: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {name} {help}");
+    let _ = writeln!(output, "# TYPE {name} {kind}");
+}
+
+fn write_counter(output: &mut String, name: &str, help: &str, value: u64) {
+    write_header(output, name, "counter", help);
+    let _ = writeln!(output, "{name} {value}");
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let metrics = Metrics::default();
+        metrics.record_message_sent();
+        metrics.record_message_sent();
+        metrics.record_request(
+            "POST",
+            "/api/instance/:instance/data/text",
+            false,
+            Duration::from_millis(20),
+        );
+        metrics.record_request(
+            "POST",
+            "/api/instance/:instance/data/text",
+            true,
+            Duration::from_millis(200),
+        );
+        let output = metrics.render(&BTreeMap::from([(String::from("scoreboard"), 2)]));
+
+        assert!(output.contains("zagreus_websocket_clients{instance=\"scoreboard\"} 2\n"));
+        assert!(output.contains("zagreus_websocket_messages_sent_total 2\n"));
+        assert!(output.contains("zagreus_errors_total{source=\"api\"} 1\n"));
+        let labels = "method=\"POST\",route=\"/api/instance/:instance/data/text\"";
+        assert!(output.contains(&format!(
+            "zagreus_http_request_duration_seconds_bucket{{{labels},le=\"0.025\"}} 1\n"
+        )));
+        assert!(output.contains(&format!(
+            "zagreus_http_request_duration_seconds_bucket{{{labels},le=\"0.25\"}} 2\n"
+        )));
+        assert!(output.contains(&format!(
+            "zagreus_http_request_duration_seconds_count{{{labels}}} 2\n"
+        )));
+        assert_eq!("say \\\"hi\\\"", escape_label("say \"hi\""));
+    }
+}
@@ -0,0 +1,211 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use chrono::Utc;
+
+/// File in the data folder storing the version of its layout.
+const VERSION_FILE_NAME: &str = "data-version.json";
+/// Version of data folders which were written before the layout was versioned.
+const INITIAL_VERSION: u32 = 1;
+
+/// Changes the layout of a data folder from the previous version to `version`, e.g. moves or
+/// converts the documents of every instance.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub run: fn(&Path) -> anyhow::Result<()>,
+}
+
+/// Migrations in the order of their versions. A migration is added for every change to how data
+/// is stored, so existing installations are migrated when they are upgraded.
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 2,
+    description: "flag automatic snapshots",
+    run: crate::snapshot::flag_automatic_snapshots,
+}];
+
+#[derive(Serialize, Deserialize)]
+struct DataVersion {
+    version: u32,
+}
+
+fn get_latest_version(migrations: &[Migration]) -> u32 {
+    migrations
+        .last()
+        .map_or(INITIAL_VERSION, |migration| migration.version)
+}
+
+/// Brings the data folder to the current layout before the server uses it. The folder is backed
+/// up next to it before the first migration runs. Fails for data folders of newer servers, which
+/// this server cannot read.
+pub fn migrate_data_folder(data_folder: &Path) -> anyhow::Result<()> {
+    run_migrations(data_folder, MIGRATIONS)
+}
+
+fn run_migrations(data_folder: &Path, migrations: &[Migration]) -> anyhow::Result<()> {
+    let latest_version = get_latest_version(migrations);
+    let Some(mut version) = read_version(data_folder)? else {
+        // nothing to migrate in a new data folder
+        std::fs::create_dir_all(data_folder)
+            .with_context(|| format!("Could not create data folder {data_folder:?}"))?;
+        return write_version(data_folder, latest_version);
+    };
+    if version > latest_version {
+        return Err(anyhow!(
+            "The data folder has version {}, but this server only supports up to version {}. \
+             Upgrade the server or restore a backup of the data folder",
+            version,
+            latest_version
+        ));
+    }
+    let pending: Vec<&Migration> = migrations
+        .iter()
+        .filter(|migration| migration.version > version)
+        .collect();
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    let backup_folder = get_backup_folder(data_folder, version)?;
+    info!(
+        "Backing up data folder to {:?} before migrating it from version {} to {}.",
+        backup_folder, version, latest_version
+    );
+    copy_folder(data_folder, &backup_folder).with_context(|| {
+        format!("Could not back up data folder {data_folder:?} to {backup_folder:?}")
+    })?;
+    for migration in pending {
+        info!(
+            "Migrating data folder to version {}: {}.",
+            migration.version, migration.description
+        );
+        (migration.run)(data_folder).with_context(|| {
+            format!(
+                "Migration to version {} failed, the data folder before the migration is at \
+                 {backup_folder:?}",
+                migration.version
+            )
+        })?;
+        // a failing migration is retried on the next start, not the ones before it
+        version = migration.version;
+        write_version(data_folder, version)?;
+    }
+    Ok(())
+}
+
+/// Describes whether the data folder can be used by this server, e.g. for the self-test.
+pub fn check_data_folder_version(data_folder: &Path) -> anyhow::Result<String> {
+    let latest_version = get_latest_version(MIGRATIONS);
+    match read_version(data_folder)? {
+        None => Ok(String::from("Data folder is empty.")),
+        Some(version) if version > latest_version => Err(anyhow!(
+            "Data folder has version {}, but this server only supports up to version {}",
+            version,
+            latest_version
+        )),
+        Some(version) if version < latest_version => Ok(format!(
+            "Data folder has version {version} and is migrated to version {latest_version} when \
+             the server starts."
+        )),
+        Some(version) => Ok(format!("Data folder has the current version {version}.")),
+    }
+}
+
+/// Returns the version of the data folder, `None` if it contains nothing yet.
+fn read_version(data_folder: &Path) -> anyhow::Result<Option<u32>> {
+    let path = data_folder.join(VERSION_FILE_NAME);
+    if path.exists() {
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read data folder version {path:?}"))?;
+        let data_version: DataVersion = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid data folder version {path:?}"))?;
+        return Ok(Some(data_version.version));
+    }
+    let is_empty = !data_folder.exists()
+        || std::fs::read_dir(data_folder)
+            .with_context(|| format!("Could not read data folder {data_folder:?}"))?
+            .next()
+            .is_none();
+    Ok((!is_empty).then_some(INITIAL_VERSION))
+}
+
+fn write_version(data_folder: &Path, version: u32) -> anyhow::Result<()> {
+    let path = data_folder.join(VERSION_FILE_NAME);
+    std::fs::write(&path, serde_json::to_vec(&DataVersion { version })?)
+        .with_context(|| format!("Could not write data folder version {path:?}"))
+}
+
+/// Returns a folder next to the data folder, e.g. `data.backup-v1-20240301120000`.
+fn get_backup_folder(data_folder: &Path, version: u32) -> anyhow::Result<PathBuf> {
+    let name = data_folder
+        .file_name()
+        .ok_or_else(|| anyhow!("The data folder {:?} has no name", data_folder))?
+        .to_string_lossy();
+    let timestamp = Utc::now().format("%Y%m%d%H%M%S");
+    Ok(data_folder.with_file_name(format!("{name}.backup-v{version}-{timestamp}")))
+}
+
+fn copy_folder(source: &Path, target: &Path) -> anyhow::Result<()> {
+    std::fs::create_dir_all(target)?;
+    for entry in std::fs::read_dir(source)? {
+        let entry = entry?;
+        let target_path = target.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_folder(&entry.path(), &target_path)?;
+        } else {
+            std::fs::copy(entry.path(), &target_path)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rename_state_files(data_folder: &Path) -> anyhow::Result<()> {
+        for instance in crate::fs::get_instance_names(data_folder)? {
+            let folder = crate::fs::get_instance_folder(data_folder, &instance)?;
+            std::fs::rename(folder.join("state.json"), folder.join("data.json"))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_migrations() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let migrations = [Migration {
+            version: 2,
+            description: "Rename state files",
+            run: rename_state_files,
+        }];
+
+        // new data folders start with the latest version
+        let new_folder = path.join("new");
+        run_migrations(&new_folder, &migrations).unwrap();
+        assert_eq!(Some(2), read_version(&new_folder).unwrap());
+
+        let data_folder = path.join("data");
+        let instance_folder = crate::fs::get_instance_folder(&data_folder, "scoreboard").unwrap();
+        std::fs::create_dir_all(&instance_folder).unwrap();
+        std::fs::write(instance_folder.join("state.json"), "{}").unwrap();
+        run_migrations(&data_folder, &migrations).unwrap();
+        assert!(instance_folder.join("data.json").exists());
+        assert_eq!(Some(2), read_version(&data_folder).unwrap());
+        let backups: Vec<_> = std::fs::read_dir(&path)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("data.backup-v1-"))
+            .collect();
+        assert_eq!(1, backups.len());
+        assert!(path
+            .join(&backups[0])
+            .join("instances/scoreboard/state.json")
+            .exists());
+
+        // migrations only run once
+        run_migrations(&data_folder, &migrations).unwrap();
+        assert!(run_migrations(&data_folder, &[]).is_err());
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::config::SyntheticMonitorConfig;
+use crate::controller::ServerController;
+use crate::state::InstanceState;
+use crate::tls::LocalServer;
+use crate::websocket::message::{InstanceMessage, PROTOCOL_VERSION};
+
+/// Header with which the synthetic client identifies itself to the server it runs in.
+pub const MONITOR_TOKEN_HEADER: &str = "x-zagreus-monitor-token";
+/// Interval in which the synthetic client reconnects after the connection was lost.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+/// Delay before the first connection, so the server is listening already.
+const STARTUP_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorStatus {
+    pub connected: bool,
+    pub healthy: bool,
+    pub last_check: Option<DateTime<Utc>>,
+    /// Round trip time of the last ping, if the synthetic client received an answer.
+    pub round_trip_ms: Option<u64>,
+    /// Differences between the state of the synthetic client and the state of the server.
+    pub divergences: Vec<String>,
+}
+
+/// Latest status of the synthetic clients per instance.
+pub struct MonitorStatusStore {
+    statuses: RwLock<BTreeMap<String, MonitorStatus>>,
+    /// Random token of this process, so only its own synthetic clients are treated as such.
+    token: String,
+}
+
+impl MonitorStatusStore {
+    pub fn new() -> MonitorStatusStore {
+        MonitorStatusStore {
+            statuses: RwLock::new(BTreeMap::new()),
+            token: format!("{:032x}", rand::random::<u128>()),
+        }
+    }
+
+    pub fn get_token(&self) -> &str {
+        &self.token
+    }
+
+    /// Whether the presented token is the token of this process, compared in constant time.
+    pub fn is_token(&self, presented: &str) -> bool {
+        let presented_hash = Sha256::digest(presented.as_bytes());
+        let hash = Sha256::digest(self.token.as_bytes());
+        hash.iter()
+            .zip(presented_hash.iter())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+    }
+
+    pub async fn get_statuses(&self) -> BTreeMap<String, MonitorStatus> {
+        self.statuses.read().await.clone()
+    }
+
+    pub async fn set_status(&self, instance: &str, status: MonitorStatus) {
+        self.statuses
+            .write()
+            .await
+            .insert(instance.to_owned(), status);
+    }
+}
+
+/// Connects a headless client to the websocket of the instance like a renderer, applies all
+/// messages to an in-memory model of the DOM and periodically compares it to the state of the
+/// server. Divergences and slow round trips are logged and exposed in the server stats, even when
+/// no real renderer is attached.
+pub async fn run_synthetic_monitor(
+    controller: Arc<ServerController>,
+    local_server: LocalServer,
+    instance: String,
+    configuration: SyntheticMonitorConfig,
+) {
+    info!("Starting synthetic monitor for instance {}.", instance);
+    let path = format!("/ws/instance/{instance}");
+    let mut healthy = true;
+    tokio::time::sleep(STARTUP_DELAY).await;
+
+    loop {
+        if let Err(err) = monitor_connection(
+            &controller,
+            &local_server,
+            &path,
+            &instance,
+            &configuration,
+            &mut healthy,
+        )
+        .await
+        {
+            if healthy {
+                warn!(
+                    "Synthetic monitor lost connection to instance {}: {}.",
+                    instance, err
+                );
+                healthy = false;
+            }
+        }
+        let status = MonitorStatus {
+            last_check: Some(Utc::now()),
+            ..MonitorStatus::default()
+        };
+        controller.set_monitor_status(&instance, status).await;
+        tokio::time::sleep(RECONNECT_INTERVAL).await;
+    }
+}
+
+async fn monitor_connection(
+    controller: &ServerController,
+    local_server: &LocalServer,
+    path: &str,
+    instance: &str,
+    configuration: &SyntheticMonitorConfig,
+    healthy: &mut bool,
+) -> anyhow::Result<()> {
+    let mut socket = local_server
+        .connect_websocket(
+            path,
+            &[(MONITOR_TOKEN_HEADER, controller.get_monitor_token())],
+        )
+        .await?;
+    let handshake = InstanceMessage::Handshake {
+        runtime_version: format!("synthetic-monitor/{}", env!("CARGO_PKG_VERSION")),
+        protocol_version: PROTOCOL_VERSION,
+        required_protocol_version: None,
+    };
+    socket
+        .send(Message::Text(serde_json::to_string(&handshake)?))
+        .await?;
+
+    let mut model = InstanceState::default();
+    let mut previous_divergences = Vec::new();
+    let mut round_trip: Option<Duration> = None;
+    let mut ping_sent: Option<Instant> = None;
+    let mut interval =
+        tokio::time::interval(Duration::from_secs(configuration.check_interval_seconds));
+    // the first check happens after the initial messages were received
+    interval.tick().await;
+
+    loop {
+        tokio::select! {
+            message = socket.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    match serde_json::from_str::<InstanceMessage>(&text) {
+                        Ok(InstanceMessage::Ping { server_time }) => {
+                            // answer like a renderer, so the synthetic client has a latency as well
+                            let pong = InstanceMessage::Pong {
+                                server_time,
+                                client_time: Utc::now().timestamp_millis(),
+                            };
+                            socket.send(Message::Text(serde_json::to_string(&pong)?)).await?;
+                        }
+                        Ok(message) => {
+                            model.apply(&message);
+                        }
+                        Err(err) => warn!(
+                            "Synthetic monitor could not parse message of instance {}: {}.",
+                            instance, err
+                        ),
+                    }
+                }
+                Some(Ok(Message::Pong(_))) => {
+                    round_trip = ping_sent.take().map(|sent| sent.elapsed());
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    anyhow::bail!("connection was closed")
+                }
+                Some(Ok(_)) => {}
+                Some(Err(err)) => return Err(err.into()),
+            },
+            _ = interval.tick() => {
+                // a ping without answer until the next check counts as timed out
+                if ping_sent.is_some() {
+                    round_trip = None;
+                }
+                let state = controller.get_state(instance).await?;
+                let divergences: Vec<String> = find_divergences(&state, &model);
+                // messages may be in flight during a check, so only divergences that persist
+                // over two checks are reported
+                let persistent: Vec<String> = divergences
+                    .iter()
+                    .filter(|divergence| previous_divergences.contains(*divergence))
+                    .cloned()
+                    .collect();
+                previous_divergences = divergences;
+
+                let round_trip_ms = round_trip.map(|round_trip| round_trip.as_millis() as u64);
+                let slow = round_trip_ms.map_or(ping_sent.is_some(), |round_trip_ms| {
+                    round_trip_ms > configuration.max_round_trip_ms
+                });
+                let is_healthy = persistent.is_empty() && !slow;
+                if is_healthy != *healthy {
+                    if is_healthy {
+                        info!("Synthetic monitor of instance {} is healthy again.", instance);
+                    } else {
+                        warn!(
+                            "Synthetic monitor of instance {} is unhealthy (round trip: {:?} ms, divergences: {}).",
+                            instance,
+                            round_trip_ms,
+                            persistent.join("; ")
+                        );
+                    }
+                    *healthy = is_healthy;
+                }
+                let status = MonitorStatus {
+                    connected: true,
+                    healthy: is_healthy,
+                    last_check: Some(Utc::now()),
+                    round_trip_ms,
+                    divergences: persistent,
+                };
+                controller.set_monitor_status(instance, status).await;
+
+                socket.send(Message::Ping(Vec::new())).await?;
+                ping_sent = Some(Instant::now());
+            }
+        }
+    }
+}
+
+/// Returns a description of every entry in which the state of a client differs from the expected
+/// state of the server.
+pub fn find_divergences(expected: &InstanceState, actual: &InstanceState) -> Vec<String> {
+    let flatten_classes = |state: &InstanceState| -> BTreeMap<String, bool> {
+        state
+            .classes
+            .iter()
+            .flat_map(|(id, classes)| {
+                classes
+                    .iter()
+                    .map(move |(class, added)| (format!("{id}.{class}"), *added))
+            })
+            .collect()
+    };
+
+    let mut divergences = Vec::new();
+    find_entry_divergences("text", &expected.texts, &actual.texts, &mut divergences);
+    find_entry_divergences(
+        "class",
+        &flatten_classes(expected),
+        &flatten_classes(actual),
+        &mut divergences,
+    );
+    find_entry_divergences("image", &expected.images, &actual.images, &mut divergences);
+    divergences
+}
+
+fn find_entry_divergences<V: PartialEq + Debug>(
+    kind: &str,
+    expected: &BTreeMap<String, V>,
+    actual: &BTreeMap<String, V>,
+    divergences: &mut Vec<String>,
+) {
+    let format_value = |value: Option<&V>| match value {
+        Some(value) => format!("{value:?}"),
+        None => String::from("nothing"),
+    };
+    let keys = expected
+        .keys()
+        .chain(actual.keys().filter(|key| !expected.contains_key(*key)));
+    for key in keys {
+        let expected_value = expected.get(key);
+        let actual_value = actual.get(key);
+        if expected_value != actual_value {
+            divergences.push(format!(
+                "{kind} {key}: expected {}, got {}",
+                format_value(expected_value),
+                format_value(actual_value)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_divergences() {
+        let mut expected = InstanceState::default();
+        expected.apply(&InstanceMessage::SetText {
+            id: "title",
+            text: "Final",
+            tween_duration: None,
+        });
+        expected.apply(&InstanceMessage::AddClass {
+            id: "banner",
+            class: "visible",
+        });
+        let mut actual = expected.clone();
+        assert!(find_divergences(&expected, &actual).is_empty());
+
+        actual.apply(&InstanceMessage::SetText {
+            id: "title",
+            text: "Half time",
+            tween_duration: None,
+        });
+        actual.apply(&InstanceMessage::RemoveClass {
+            id: "banner",
+            class: "visible",
+        });
+        actual.apply(&InstanceMessage::SetText {
+            id: "subtitle",
+            text: "",
+            tween_duration: None,
+        });
+        assert_eq!(
+            vec![
+                String::from("text title: expected \"Final\", got \"Half time\""),
+                String::from("text subtitle: expected nothing, got \"\""),
+                String::from("class banner.visible: expected true, got false"),
+            ],
+            find_divergences(&expected, &actual)
+        );
+    }
+}
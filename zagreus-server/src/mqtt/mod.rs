@@ -0,0 +1,256 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, MqttOptions, Packet, QoS};
+
+use crate::config::{MqttConfig, MqttSubscriptionConfig};
+use crate::controller::ServerController;
+use crate::data::operation::Operation;
+use crate::events::InstanceEvent;
+
+const DEFAULT_PORT: u16 = 1883;
+const DEFAULT_CLIENT_ID: &str = "zagreus-server";
+const KEEP_ALIVE: Duration = Duration::from_secs(30);
+/// Capacity of the queue of requests to the broker, e.g. events to publish.
+const REQUEST_CAPACITY: usize = 100;
+/// Interval in which connecting to the broker is retried after it was not reachable.
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+/// Timeout of polling the event log, after which polling starts again.
+const EVENT_POLL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Connects to the MQTT broker, applies the messages of the subscribed topics to the instances
+/// and publishes the events of all instances if an event topic is configured. Reconnects until
+/// the server stops.
+pub async fn run_mqtt_bridge(controller: Arc<ServerController>, configuration: MqttConfig) {
+    let host = configuration.host.clone().unwrap_or_default();
+    let port = configuration.port.unwrap_or(DEFAULT_PORT);
+    let client_id = configuration
+        .client_id
+        .clone()
+        .unwrap_or_else(|| String::from(DEFAULT_CLIENT_ID));
+    let mut options = MqttOptions::new(client_id, &host, port);
+    options.set_keep_alive(KEEP_ALIVE);
+    if let Some(username) = &configuration.username {
+        options.set_credentials(
+            username,
+            configuration.password.as_deref().unwrap_or_default(),
+        );
+    }
+    let (client, mut event_loop) = AsyncClient::new(options, REQUEST_CAPACITY);
+    if let Some(topic) = &configuration.event_topic {
+        tokio::spawn(publish_events(
+            controller.clone(),
+            client.clone(),
+            topic.clone(),
+        ));
+    }
+
+    let mut reachable = true;
+    loop {
+        match event_loop.poll().await {
+            Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                info!("Connected to MQTT broker {}:{}.", host, port);
+                reachable = true;
+                // subscriptions do not survive a clean session, so subscribe on every connect
+                for subscription in &configuration.subscriptions {
+                    // the event loop handles the requests, so waiting for them here would block it
+                    if let Err(err) = client.try_subscribe(&subscription.topic, QoS::AtLeastOnce) {
+                        error!(
+                            "Could not subscribe to MQTT topic {}: {}.",
+                            subscription.topic, err
+                        );
+                    }
+                }
+            }
+            Ok(Event::Incoming(Packet::Publish(publish))) => {
+                for subscription in &configuration.subscriptions {
+                    if rumqttc::matches(&publish.topic, &subscription.topic) {
+                        apply_message(&controller, subscription, &publish.topic, &publish.payload)
+                            .await;
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(err) => {
+                if reachable {
+                    warn!(
+                        "Could not connect to MQTT broker {}:{}: {}.",
+                        host, port, err
+                    );
+                    reachable = false;
+                }
+                tokio::time::sleep(RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
+
+async fn apply_message(
+    controller: &ServerController,
+    subscription: &MqttSubscriptionConfig,
+    topic: &str,
+    payload: &[u8],
+) {
+    let operations = match get_operations(subscription, payload) {
+        Ok(operations) => operations,
+        Err(err) => {
+            warn!("Ignoring MQTT message on topic {}: {}.", topic, err);
+            return;
+        }
+    };
+    if operations.is_empty() {
+        return;
+    }
+    let pending = crate::timing::trace(
+        format!("MQTT {topic}"),
+        controller.submit_operations(&subscription.instance, operations),
+    )
+    .await;
+    if let Some(change) = pending {
+        info!(
+            "MQTT message for instance {} is waiting for approval as change {}.",
+            subscription.instance, change.id
+        );
+    }
+}
+
+/// Returns the operations of a message: the payload as text of the element, the values of the
+/// fields of a JSON payload and the animation.
+fn get_operations(
+    subscription: &MqttSubscriptionConfig,
+    payload: &[u8],
+) -> Result<Vec<Operation>, String> {
+    let mut operations = Vec::new();
+    if let Some(element) = &subscription.element {
+        let text =
+            std::str::from_utf8(payload).map_err(|_| String::from("Payload is not UTF-8"))?;
+        operations.push(Operation::SetText {
+            id: element.clone(),
+            text: text.trim().to_owned(),
+            tween_duration: None,
+        });
+    }
+    if !subscription.fields.is_empty() {
+        let document: serde_json::Value =
+            serde_json::from_slice(payload).map_err(|err| format!("Invalid JSON: {err}"))?;
+        let (texts, _) = crate::datasource::get_texts(&subscription.fields, &document);
+        operations.extend(texts.into_iter().map(|(id, text)| Operation::SetText {
+            id,
+            text,
+            tween_duration: None,
+        }));
+    }
+    if let Some(animation_sequence) = &subscription.animation {
+        operations.push(Operation::ExecuteAnimation {
+            animation_sequence: animation_sequence.clone(),
+            execute_at: None,
+        });
+    }
+    Ok(operations)
+}
+
+/// Publishes every event of the event log as JSON.
+async fn publish_events(controller: Arc<ServerController>, client: AsyncClient, topic: String) {
+    let mut cursor = None;
+    loop {
+        let batch = controller.poll_all_events(cursor, EVENT_POLL_TIMEOUT).await;
+        cursor = Some(batch.cursor);
+        for event in batch.events {
+            let payload = match serde_json::to_vec(&event) {
+                Ok(payload) => payload,
+                Err(err) => {
+                    error!("Could not serialize event for MQTT: {}.", err);
+                    continue;
+                }
+            };
+            // events are dropped while the broker is not reachable and the queue is full
+            if let Err(err) = client.try_publish(
+                get_event_topic(&topic, &event),
+                QoS::AtMostOnce,
+                false,
+                payload,
+            ) {
+                debug!("Could not publish event to MQTT: {}.", err);
+            }
+        }
+    }
+}
+
+/// Returns the topic of the event, `<topic>/<instance>/<type>` and for operations the operation
+/// as well, e.g. `zagreus/events/scoreboard/operation/ExecuteAnimation`.
+fn get_event_topic(topic: &str, event: &InstanceEvent) -> String {
+    let value = serde_json::to_value(&event.kind).unwrap_or_default();
+    let kind = value["type"].as_str().unwrap_or_default();
+    match value["operation"].as_str() {
+        Some(operation) => format!("{topic}/{}/{kind}/{operation}", event.instance),
+        None => format!("{topic}/{}/{kind}", event.instance),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::events::EventKind;
+
+    #[test]
+    fn test_get_operations() {
+        let mut subscription = MqttSubscriptionConfig {
+            topic: String::from("stadium/+/temperature"),
+            instance: String::from("weather"),
+            element: Some(String::from("Temperature")),
+            fields: BTreeMap::new(),
+            animation: Some(String::from("TemperatureUpdate")),
+        };
+        let operations = get_operations(&subscription, b" 21.5\n").unwrap();
+        assert!(matches!(
+            &operations[..],
+            [Operation::SetText { id, text, .. }, Operation::ExecuteAnimation { .. }]
+                if id == "Temperature" && text == "21.5"
+        ));
+
+        subscription.element = None;
+        subscription.animation = None;
+        subscription.fields = BTreeMap::from([
+            (String::from("Temperature"), String::from("/celsius")),
+            (String::from("Humidity"), String::from("/humidity")),
+        ]);
+        let operations = get_operations(&subscription, br#"{"celsius": 21.5}"#).unwrap();
+        assert!(matches!(
+            &operations[..],
+            [Operation::SetText { id, text, .. }] if id == "Temperature" && text == "21.5"
+        ));
+        assert!(get_operations(&subscription, b"21.5 degrees").is_err());
+    }
+
+    #[test]
+    fn test_get_event_topic() {
+        let event = |kind| InstanceEvent {
+            cursor: 1,
+            timestamp: Utc::now(),
+            instance: String::from("scoreboard"),
+            kind,
+        };
+        assert_eq!(
+            "zagreus/events/scoreboard/clientConnected",
+            get_event_topic(
+                "zagreus/events",
+                &event(EventKind::ClientConnected { client: 3 })
+            )
+        );
+        assert_eq!(
+            "zagreus/events/scoreboard/operation/ExecuteAnimation",
+            get_event_topic(
+                "zagreus/events",
+                &event(EventKind::Operation {
+                    operation: String::from("ExecuteAnimation"),
+                    payload: serde_json::json!({"animationSequence": "Goal"}),
+                    clients: 2,
+                })
+            )
+        );
+    }
+}
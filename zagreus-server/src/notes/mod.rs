@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, NaiveDate, Utc};
+use tokio::sync::{broadcast, Mutex};
+use zagreus_client::dto::{NoteDto, OperatorNote};
+
+use crate::store::{load_document, store_document};
+
+const NOTES_FOLDER_NAME: &str = "notes";
+const NOTE_CHANGES_CAPACITY: usize = 256;
+pub const MAX_NOTE_LENGTH: usize = 2_000;
+
+/// Notes of the operators, stored in one document per show day (e.g. `notes/2024-03-01.json`),
+/// so the coordination of a show can be looked up afterwards. Every added or (un)pinned note is
+/// broadcast to the controllers.
+pub struct NoteStore {
+    notes_folder: PathBuf,
+    /// Serializes changes, since every change rewrites the document of the day.
+    write_lock: Mutex<()>,
+    changes: broadcast::Sender<OperatorNote>,
+}
+
+impl NoteStore {
+    pub fn new(data_folder: &Path) -> NoteStore {
+        NoteStore {
+            notes_folder: data_folder.join(NOTES_FOLDER_NAME),
+            write_lock: Mutex::new(()),
+            changes: broadcast::channel(NOTE_CHANGES_CAPACITY).0,
+        }
+    }
+
+    pub async fn get_notes(&self, day: NaiveDate) -> anyhow::Result<Vec<OperatorNote>> {
+        load_document(&self.get_notes_path(day)).await
+    }
+
+    pub async fn add_note(&self, day: NaiveDate, note: NoteDto) -> anyhow::Result<OperatorNote> {
+        let _guard = self.write_lock.lock().await;
+        let mut notes = self.get_notes(day).await?;
+        let note = OperatorNote {
+            id: notes.iter().map(|note| note.id).max().unwrap_or_default() + 1,
+            timestamp: Utc::now(),
+            operator: note.operator,
+            instance: note.instance,
+            text: note.text,
+            pinned: note.pinned,
+        };
+        notes.push(note.clone());
+        store_document(&self.get_notes_path(day), &notes).await?;
+        self.changes.send(note.clone()).ok();
+        Ok(note)
+    }
+
+    /// Pins or unpins the note and returns it, or `None` if the day has no such note.
+    pub async fn set_pinned(
+        &self,
+        day: NaiveDate,
+        id: u64,
+        pinned: bool,
+    ) -> anyhow::Result<Option<OperatorNote>> {
+        let _guard = self.write_lock.lock().await;
+        let mut notes = self.get_notes(day).await?;
+        let Some(note) = notes.iter_mut().find(|note| note.id == id) else {
+            return Ok(None);
+        };
+        note.pinned = pinned;
+        let note = note.clone();
+        store_document(&self.get_notes_path(day), &notes).await?;
+        self.changes.send(note.clone()).ok();
+        Ok(Some(note))
+    }
+
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<OperatorNote> {
+        self.changes.subscribe()
+    }
+
+    fn get_notes_path(&self, day: NaiveDate) -> PathBuf {
+        self.notes_folder.join(format!("{day}.json"))
+    }
+}
+
+/// Returns the show day, which is the date in the time zone of the server.
+pub fn get_show_day() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+pub fn is_valid_note_text(text: &str) -> bool {
+    !text.trim().is_empty() && text.len() <= MAX_NOTE_LENGTH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_pin_notes() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let store = NoteStore::new(&path);
+        let mut changes = store.subscribe_changes();
+        let day = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let note = |text: &str| NoteDto {
+            text: text.to_owned(),
+            operator: Some(String::from("Jane")),
+            instance: None,
+            pinned: false,
+        };
+
+        store
+            .add_note(day, note("Kick-off at 20:45"))
+            .await
+            .unwrap();
+        let warning = store
+            .add_note(day, note("Graphic 12 broken, don't fire"))
+            .await
+            .unwrap();
+        assert_eq!(2, warning.id);
+        assert_eq!(2, store.set_pinned(day, 2, true).await.unwrap().unwrap().id);
+        assert!(store.set_pinned(day, 3, true).await.unwrap().is_none());
+        assert_eq!(1, changes.recv().await.unwrap().id);
+
+        let notes = NoteStore::new(&path).get_notes(day).await.unwrap();
+        assert_eq!(2, notes.len());
+        assert!(!notes[0].pinned && notes[1].pinned);
+        let next_day = day.succ_opt().unwrap();
+        assert!(store.get_notes(next_day).await.unwrap().is_empty());
+
+        assert!(is_valid_note_text("Replay ready"));
+        assert!(!is_valid_note_text("  "));
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use tokio::net::UdpSocket;
+
+use crate::config::OscActionConfig;
+use crate::controller::ServerController;
+use crate::data::operation::Operation;
+
+/// Prefix of the built-in addresses, e.g. `/zagreus/template/scoreboard/animation/Goal`.
+const ADDRESS_PREFIX: &str = "/zagreus/template/";
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+/// Largest payload of a UDP datagram.
+const MAX_PACKET_SIZE: usize = 65_507;
+/// Bundles may contain bundles, but not deeper than this.
+const MAX_BUNDLE_DEPTH: usize = 8;
+
+#[derive(PartialEq, Debug)]
+enum OscArgument {
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Bool(bool),
+    Nil,
+    Blob,
+}
+
+impl OscArgument {
+    fn to_text(&self) -> Option<String> {
+        match self {
+            OscArgument::Int(value) => Some(value.to_string()),
+            OscArgument::Long(value) => Some(value.to_string()),
+            OscArgument::Float(value) => Some(value.to_string()),
+            OscArgument::Double(value) => Some(value.to_string()),
+            OscArgument::String(value) => Some(value.clone()),
+            OscArgument::Bool(value) => Some(value.to_string()),
+            OscArgument::Nil | OscArgument::Blob => None,
+        }
+    }
+
+    /// Whether the argument is sent when a button is released, e.g. `0.0` by TouchOSC.
+    fn is_release(&self) -> bool {
+        match self {
+            OscArgument::Int(value) => *value == 0,
+            OscArgument::Long(value) => *value == 0,
+            OscArgument::Float(value) => *value == 0.0,
+            OscArgument::Double(value) => *value == 0.0,
+            OscArgument::Bool(value) => !value,
+            _ => false,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct OscMessage {
+    address: String,
+    arguments: Vec<OscArgument>,
+}
+
+/// Receives OSC messages on the address until the server stops. The messages of a packet are
+/// applied together per instance, so a bundle updates all of its texts at once.
+pub async fn run_osc_server(
+    controller: Arc<ServerController>,
+    address: SocketAddr,
+    addresses: BTreeMap<String, OscActionConfig>,
+) {
+    let socket = match UdpSocket::bind(address).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!("Could not listen for OSC messages on {}: {}.", address, err);
+            return;
+        }
+    };
+    info!("Accepting OSC messages on {}.", address);
+    let mut buffer = vec![0; MAX_PACKET_SIZE];
+    loop {
+        let (length, peer) = match socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(err) => {
+                warn!("Could not receive OSC packet: {}.", err);
+                continue;
+            }
+        };
+        let messages = match parse_packet(&buffer[..length], 0) {
+            Ok(messages) => messages,
+            Err(err) => {
+                debug!("Invalid OSC packet of {}: {}.", peer, err);
+                continue;
+            }
+        };
+        let mut operations: BTreeMap<String, Vec<Operation>> = BTreeMap::new();
+        for message in messages {
+            debug!("OSC message of {}: {}.", peer, message.address);
+            match get_operations(&addresses, &message) {
+                Some((instance, message_operations)) => operations
+                    .entry(instance)
+                    .or_default()
+                    .extend(message_operations),
+                None => debug!("Ignoring OSC message on address {}.", message.address),
+            }
+        }
+        for (instance, operations) in operations {
+            if operations.is_empty() {
+                continue;
+            }
+            let pending = crate::timing::trace(
+                String::from("OSC"),
+                controller.submit_operations(&instance, operations),
+            )
+            .await;
+            if let Some(change) = pending {
+                info!(
+                    "OSC message for instance {} is waiting for approval as change {}.",
+                    instance, change.id
+                );
+            }
+        }
+    }
+}
+
+/// Returns the instance and operations of the message, or `None` if the address is unknown.
+/// Configured addresses take precedence over the built-in ones.
+fn get_operations(
+    addresses: &BTreeMap<String, OscActionConfig>,
+    message: &OscMessage,
+) -> Option<(String, Vec<Operation>)> {
+    let first_argument = message.arguments.first();
+    if let Some(action) = addresses.get(&message.address) {
+        let mut operations = Vec::new();
+        if let (Some(id), Some(text)) =
+            (&action.text, first_argument.and_then(OscArgument::to_text))
+        {
+            operations.push(Operation::SetText {
+                id: id.clone(),
+                text,
+                tween_duration: None,
+            });
+        }
+        if let Some(animation_sequence) = &action.animation {
+            if !first_argument.is_some_and(OscArgument::is_release) {
+                operations.push(Operation::ExecuteAnimation {
+                    animation_sequence: animation_sequence.clone(),
+                    execute_at: None,
+                });
+            }
+        }
+        return Some((action.instance.clone(), operations));
+    }
+
+    let mut segments = message.address.strip_prefix(ADDRESS_PREFIX)?.split('/');
+    let (instance, kind, name) = (segments.next()?, segments.next()?, segments.next()?);
+    if segments.next().is_some() || !crate::fs::is_valid_instance_name(instance) || name.is_empty()
+    {
+        return None;
+    }
+    let operation = match kind {
+        "animation" if first_argument.is_some_and(OscArgument::is_release) => None,
+        "animation" => Some(Operation::ExecuteAnimation {
+            animation_sequence: name.to_owned(),
+            execute_at: None,
+        }),
+        "text" => first_argument
+            .and_then(OscArgument::to_text)
+            .map(|text| Operation::SetText {
+                id: name.to_owned(),
+                text,
+                tween_duration: None,
+            }),
+        _ => return None,
+    };
+    Some((instance.to_owned(), operation.into_iter().collect()))
+}
+
+/// Returns the messages of the packet, which is a single message or a bundle. The time tags of
+/// bundles are ignored, their messages are applied immediately.
+fn parse_packet(packet: &[u8], depth: usize) -> Result<Vec<OscMessage>, String> {
+    let mut reader = PacketReader { packet, offset: 0 };
+    if !packet.starts_with(BUNDLE_TAG) {
+        return Ok(vec![reader.read_message()?]);
+    }
+    if depth >= MAX_BUNDLE_DEPTH {
+        return Err(String::from("Bundles are nested too deeply"));
+    }
+    reader.read_bytes(BUNDLE_TAG.len() + 8)?;
+    let mut messages = Vec::new();
+    while reader.offset < packet.len() {
+        let size = usize::try_from(reader.read_i32()?)
+            .map_err(|_| String::from("Negative size of bundle element"))?;
+        messages.extend(parse_packet(reader.read_bytes(size)?, depth + 1)?);
+    }
+    Ok(messages)
+}
+
+struct PacketReader<'a> {
+    packet: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> PacketReader<'a> {
+    fn read_message(&mut self) -> Result<OscMessage, String> {
+        let address = self.read_string()?;
+        if !address.starts_with('/') {
+            return Err(format!("Invalid address {address}"));
+        }
+        // very old implementations send no type tags and thus no arguments
+        if self.offset == self.packet.len() {
+            return Ok(OscMessage {
+                address,
+                arguments: Vec::new(),
+            });
+        }
+        let type_tags = self.read_string()?;
+        let type_tags = type_tags
+            .strip_prefix(',')
+            .ok_or_else(|| format!("Invalid type tags {type_tags}"))?;
+        let mut arguments = Vec::new();
+        for tag in type_tags.chars() {
+            arguments.push(match tag {
+                'i' => OscArgument::Int(self.read_i32()?),
+                'h' => OscArgument::Long(i64::from_be_bytes(self.read_array()?)),
+                'f' => OscArgument::Float(f32::from_be_bytes(self.read_array()?)),
+                'd' => OscArgument::Double(f64::from_be_bytes(self.read_array()?)),
+                's' | 'S' => OscArgument::String(self.read_string()?),
+                'T' => OscArgument::Bool(true),
+                'F' => OscArgument::Bool(false),
+                'N' | 'I' => OscArgument::Nil,
+                'b' => {
+                    let size = usize::try_from(self.read_i32()?)
+                        .map_err(|_| String::from("Negative size of blob"))?;
+                    self.read_bytes(get_padded_length(size))?;
+                    OscArgument::Blob
+                }
+                _ => return Err(format!("Unsupported argument type {tag}")),
+            });
+        }
+        Ok(OscMessage { address, arguments })
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .packet
+            .get(self.offset..self.offset + length)
+            .ok_or_else(|| String::from("Packet is truncated"))?;
+        self.offset += length;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.read_bytes(N)?);
+        Ok(array)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, String> {
+        Ok(i32::from_be_bytes(self.read_array()?))
+    }
+
+    /// Reads a string, which is terminated by a null byte and padded to a multiple of four bytes.
+    fn read_string(&mut self) -> Result<String, String> {
+        let rest = &self.packet[self.offset..];
+        let length = rest
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| String::from("Unterminated string"))?;
+        let string = String::from_utf8(rest[..length].to_vec())
+            .map_err(|_| String::from("String is not UTF-8"))?;
+        self.read_bytes(get_padded_length(length + 1))?;
+        Ok(string)
+    }
+}
+
+fn get_padded_length(length: usize) -> usize {
+    length.div_ceil(4) * 4
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_string(string: &str) -> Vec<u8> {
+        let mut bytes = string.as_bytes().to_vec();
+        bytes.resize(get_padded_length(string.len() + 1), 0);
+        bytes
+    }
+
+    #[test]
+    fn test_parse_packet() {
+        let mut message = encode_string("/zagreus/template/scoreboard/text/HomeScore");
+        message.extend(encode_string(",if"));
+        message.extend(2_i32.to_be_bytes());
+        message.extend(0.5_f32.to_be_bytes());
+        assert_eq!(
+            OscMessage {
+                address: String::from("/zagreus/template/scoreboard/text/HomeScore"),
+                arguments: vec![OscArgument::Int(2), OscArgument::Float(0.5)],
+            },
+            parse_packet(&message, 0).unwrap().remove(0)
+        );
+
+        let mut bundle = BUNDLE_TAG.to_vec();
+        bundle.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+        bundle.extend((message.len() as i32).to_be_bytes());
+        bundle.extend(&message);
+        let mut release = encode_string("/lx/cue/5");
+        release.extend(encode_string(",F"));
+        bundle.extend((release.len() as i32).to_be_bytes());
+        bundle.extend(&release);
+        let messages = parse_packet(&bundle, 0).unwrap();
+        assert_eq!(2, messages.len());
+        assert_eq!(vec![OscArgument::Bool(false)], messages[1].arguments);
+
+        assert!(parse_packet(&message[..message.len() - 2], 0).is_err());
+        assert!(parse_packet(b"zagreus\0", 0).is_err());
+    }
+
+    #[test]
+    fn test_get_operations() {
+        let addresses = BTreeMap::from([(
+            String::from("/lx/cue/5"),
+            OscActionConfig {
+                instance: String::from("scoreboard"),
+                animation: Some(String::from("Goal")),
+                text: None,
+            },
+        )]);
+        let message = |address: &str, arguments| OscMessage {
+            address: address.to_owned(),
+            arguments,
+        };
+
+        let (instance, operations) = get_operations(
+            &addresses,
+            &message("/lx/cue/5", vec![OscArgument::Float(1.0)]),
+        )
+        .unwrap();
+        assert_eq!("scoreboard", instance);
+        assert!(matches!(
+            &operations[..],
+            [Operation::ExecuteAnimation { animation_sequence, .. }] if animation_sequence == "Goal"
+        ));
+        let (_, operations) = get_operations(
+            &addresses,
+            &message("/lx/cue/5", vec![OscArgument::Float(0.0)]),
+        )
+        .unwrap();
+        assert!(operations.is_empty());
+
+        let (instance, operations) = get_operations(
+            &addresses,
+            &message(
+                "/zagreus/template/lower-third/text/LowerThirdName",
+                vec![OscArgument::String(String::from("Ada Lovelace"))],
+            ),
+        )
+        .unwrap();
+        assert_eq!("lower-third", instance);
+        assert!(matches!(
+            &operations[..],
+            [Operation::SetText { id, text, .. }] if id == "LowerThirdName" && text == "Ada Lovelace"
+        ));
+        assert!(get_operations(
+            &addresses,
+            &message("/zagreus/template/scoreboard/animation", Vec::new())
+        )
+        .is_none());
+        assert!(get_operations(
+            &addresses,
+            &message("/zagreus/template/../animation/Goal", Vec::new())
+        )
+        .is_none());
+        assert!(get_operations(&addresses, &message("/lx/cue/6", Vec::new())).is_none());
+    }
+}
@@ -0,0 +1,276 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use axum::body::Bytes;
+use reqwest::Url;
+
+use crate::config::ProxyConfig;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum size of a resource fetched on behalf of a user (e.g. an image or a pinned resource).
+pub const MAX_FETCH_SIZE: usize = 64 * 1024 * 1024;
+const MAX_REDIRECTS: usize = 10;
+
+pub struct FetchedResource {
+    pub content_type: Option<String>,
+    pub data: Bytes,
+}
+
+/// Builds the client used for all outbound requests of the server (e.g. fetching images,
+/// polling data sources and sending webhooks), routed through the configured proxy.
+pub fn build_client(proxy_config: Option<&ProxyConfig>) -> anyhow::Result<reqwest::Client> {
+    get_client_builder(proxy_config)?
+        .build()
+        .context("Could not build outbound HTTP client")
+}
+
+fn get_client_builder(
+    proxy_config: Option<&ProxyConfig>,
+) -> anyhow::Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .user_agent(format!("zagreus-server/{}", crate::ZAGREUS_VERSION));
+    if let Some(proxy_config) = proxy_config {
+        builder = builder.proxy(build_proxy(proxy_config)?);
+    }
+    Ok(builder)
+}
+
+fn build_proxy(proxy_config: &ProxyConfig) -> anyhow::Result<reqwest::Proxy> {
+    let mut proxy = reqwest::Proxy::all(&proxy_config.url)
+        .with_context(|| format!("Invalid proxy URL {}", proxy_config.url))?;
+    if let Some(username) = &proxy_config.username {
+        proxy = proxy.basic_auth(
+            username,
+            proxy_config.password.as_deref().unwrap_or_default(),
+        );
+    }
+    if !proxy_config.no_proxy.is_empty() {
+        proxy = proxy.no_proxy(reqwest::NoProxy::from_string(
+            &proxy_config.no_proxy.join(","),
+        ));
+    }
+    Ok(proxy)
+}
+
+/// Fetches a resource from a URL given by a user. Only http and https URLs are accepted, and
+/// unless `allow_private_networks` is set, hosts which resolve to a private, loopback or
+/// link-local address are rejected, so the server cannot be used to reach into the internal
+/// network. The addresses are checked for every redirect and the request is sent to the checked
+/// addresses.
+pub async fn fetch(
+    proxy_config: Option<&ProxyConfig>,
+    allow_private_networks: bool,
+    url: &str,
+) -> anyhow::Result<FetchedResource> {
+    let mut url = Url::parse(url)?;
+    for _ in 0..=MAX_REDIRECTS {
+        let addresses = resolve_fetch_target(&url, allow_private_networks).await?;
+        let mut builder =
+            get_client_builder(proxy_config)?.redirect(reqwest::redirect::Policy::none());
+        if let Some(domain) = url.domain() {
+            builder = builder.resolve_to_addrs(domain, &addresses);
+        }
+        let response = builder.build()?.get(url.clone()).send().await?;
+        if response.status().is_redirection() {
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .ok_or_else(|| anyhow!("{} redirected without a location", url))?;
+            url = url.join(location)?;
+            continue;
+        }
+        if !response.status().is_success() {
+            return Err(anyhow!("{} responded with {}", url, response.status()));
+        }
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_owned());
+        return Ok(FetchedResource {
+            content_type,
+            data: read_body(response, MAX_FETCH_SIZE).await?,
+        });
+    }
+    Err(anyhow!(
+        "{} redirected more than {} times",
+        url,
+        MAX_REDIRECTS
+    ))
+}
+
+/// Returns the addresses of the host of the URL, if it may be fetched.
+async fn resolve_fetch_target(
+    url: &Url,
+    allow_private_networks: bool,
+) -> anyhow::Result<Vec<SocketAddr>> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(anyhow!(
+            "Only http and https URLs can be fetched, not {}",
+            url
+        ));
+    }
+    let port = url.port_or_known_default().unwrap_or_default();
+    let addresses: Vec<SocketAddr> = match (url.domain(), url.host_str()) {
+        (Some(domain), _) => tokio::net::lookup_host((domain, port))
+            .await
+            .with_context(|| format!("Could not resolve {domain}"))?
+            .collect(),
+        // IPv6 hosts are enclosed in brackets
+        (None, Some(host)) => vec![SocketAddr::new(
+            host.trim_start_matches('[').trim_end_matches(']').parse()?,
+            port,
+        )],
+        (None, None) => return Err(anyhow!("{} has no host", url)),
+    };
+    if addresses.is_empty() {
+        return Err(anyhow!("{} does not resolve to an address", url));
+    }
+    if !allow_private_networks {
+        if let Some(address) = addresses
+            .iter()
+            .find(|address| !is_public_address(address.ip()))
+        {
+            return Err(anyhow!(
+                "{} resolves to the non-public address {}",
+                url,
+                address.ip()
+            ));
+        }
+    }
+    Ok(addresses)
+}
+
+/// Returns whether the address is reachable on the internet, i.e. not private, loopback,
+/// link-local or otherwise reserved.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4_address(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_ipv4_address(ip),
+            None => is_public_ipv6_address(ip),
+        },
+    }
+}
+
+fn is_public_ipv4_address(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    // shared address space of carrier-grade NAT (100.64.0.0/10)
+    let is_shared = first == 100 && (second & 0b1100_0000) == 64;
+    !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || is_shared
+        || first == 0)
+}
+
+fn is_public_ipv6_address(ip: Ipv6Addr) -> bool {
+    let first_segment = ip.segments()[0];
+    // unique local (fc00::/7) and link-local (fe80::/10) addresses
+    let is_unique_local = (first_segment & 0xfe00) == 0xfc00;
+    let is_link_local = (first_segment & 0xffc0) == 0xfe80;
+    !(ip.is_loopback()
+        || ip.is_unspecified()
+        || ip.is_multicast()
+        || is_unique_local
+        || is_link_local)
+}
+
+/// Reads the body of the response, but at most `max_size` bytes.
+pub async fn read_body(mut response: reqwest::Response, max_size: usize) -> anyhow::Result<Bytes> {
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_size as u64)
+    {
+        return Err(anyhow!("The response is larger than {} bytes", max_size));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_size {
+            return Err(anyhow!("The response is larger than {} bytes", max_size));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body.into())
+}
+
+pub async fn post_json(
+    client: &reqwest::Client,
+    url: &str,
+    body: &serde_json::Value,
+) -> anyhow::Result<()> {
+    let response = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("{} responded with {}", url, response.status()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_client_with_proxy() {
+        let proxy_config = ProxyConfig {
+            url: String::from("http://proxy.broadcast.local:3128"),
+            username: Some(String::from("zagreus")),
+            password: Some(String::from("secret")),
+            no_proxy: vec![String::from("localhost"), String::from("10.0.0.0/8")],
+        };
+        assert!(build_client(Some(&proxy_config)).is_ok());
+
+        let invalid_proxy_config = ProxyConfig {
+            url: String::from("not a url"),
+            ..proxy_config
+        };
+        assert!(build_client(Some(&invalid_proxy_config)).is_err());
+    }
+
+    #[test]
+    fn test_is_public_address() {
+        for address in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_address(address.parse().unwrap()), "{address}");
+        }
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.10",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:192.168.1.10",
+        ] {
+            assert!(!is_public_address(address.parse().unwrap()), "{address}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_rejects_internal_targets() {
+        for url in [
+            "http://127.0.0.1:8080/api/state",
+            "http://localhost/",
+            "http://[::1]/",
+            "file:///etc/passwd",
+            "ftp://example.com/image.png",
+        ] {
+            assert!(fetch(None, false, url).await.is_err(), "{url}");
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+
+use crate::assets::AssetVersions;
+use crate::resources::PinnedResources;
+
+const RUNTIME_FILE_NAME: &str = "zagreus-runtime.js";
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrefetchKind {
+    Runtime,
+    Asset,
+    /// Local copy of an external resource, e.g. a font from a CDN.
+    Resource,
+}
+
+/// A file served by the server which renderers of the instance need.
+#[derive(Clone, PartialEq, Debug)]
+pub struct PrefetchFile {
+    pub url: String,
+    pub path: PathBuf,
+    pub kind: PrefetchKind,
+    /// Asset name or URL of the external resource the file belongs to.
+    pub source: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchEntry {
+    pub url: String,
+    pub kind: PrefetchKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    pub size: u64,
+    /// Subresource integrity hash of the content, e.g. `sha384-...`.
+    pub integrity: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrefetchManifest {
+    pub instance: String,
+    pub generated: DateTime<Utc>,
+    pub entries: Vec<PrefetchEntry>,
+    /// URLs of files which are referenced but do not exist on the server.
+    pub missing: Vec<String>,
+}
+
+/// Returns all files a renderer of the instance may load from the server: the runtime, all
+/// uploaded assets and the local copies of pinned resources. Templates themselves are not served
+/// by the server and therefore not included.
+pub fn get_prefetch_files(
+    data_folder: &Path,
+    runtime_version: Option<&str>,
+    asset_versions: &AssetVersions,
+    pinned_resources: &PinnedResources,
+) -> anyhow::Result<Vec<PrefetchFile>> {
+    let runtime = match runtime_version {
+        Some(version) => PrefetchFile {
+            url: format!("/static/runtime/{version}/{RUNTIME_FILE_NAME}"),
+            path: crate::fs::get_runtimes_folder(data_folder)?
+                .join(version)
+                .join(RUNTIME_FILE_NAME),
+            kind: PrefetchKind::Runtime,
+            source: Some(version.to_owned()),
+        },
+        None => PrefetchFile {
+            url: format!("/static/{RUNTIME_FILE_NAME}"),
+            path: PathBuf::from(RUNTIME_FILE_NAME),
+            kind: PrefetchKind::Runtime,
+            source: None,
+        },
+    };
+    let mut files = vec![runtime];
+
+    let assets_folder = crate::fs::get_assets_folder(data_folder)?;
+    let asset_file = |file: &str, kind: PrefetchKind, source: Option<&str>| PrefetchFile {
+        url: format!("/assets/{file}"),
+        path: assets_folder.join(file),
+        kind,
+        source: source.map(str::to_owned),
+    };
+    for (url, resource) in pinned_resources {
+        files.push(asset_file(
+            &resource.file,
+            PrefetchKind::Resource,
+            Some(url),
+        ));
+    }
+    for (name, file) in asset_versions {
+        files.push(asset_file(file, PrefetchKind::Asset, Some(name)));
+    }
+
+    // older versions of assets are listed as well, since snapshots and rundowns may use them
+    let mut listed: BTreeSet<PathBuf> = files.iter().map(|file| file.path.clone()).collect();
+    let mut uploaded: Vec<String> = std::fs::read_dir(&assets_folder)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    uploaded.sort();
+    for file in uploaded {
+        let file = asset_file(&file, PrefetchKind::Asset, None);
+        if listed.insert(file.path.clone()) {
+            files.push(file);
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resources::PinnedResource;
+
+    #[test]
+    fn test_get_prefetch_files() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let assets_folder = crate::fs::get_assets_folder(&path).unwrap();
+        std::fs::write(assets_folder.join("abc.png"), b"new").unwrap();
+        std::fs::write(assets_folder.join("def.png"), b"old").unwrap();
+        std::fs::write(assets_folder.join("font.woff2"), b"font").unwrap();
+        let asset_versions = AssetVersions::from([
+            (String::from("sponsor.png"), String::from("abc.png")),
+            (String::from("logo.png"), String::from("missing.png")),
+        ]);
+        let pinned_resources = PinnedResources::from([(
+            String::from("https://cdn.example.com/font.woff2"),
+            PinnedResource {
+                file: String::from("font.woff2"),
+                integrity: String::new(),
+            },
+        )]);
+
+        let files =
+            get_prefetch_files(&path, Some("1.2.0"), &asset_versions, &pinned_resources).unwrap();
+        let urls: Vec<(&str, PrefetchKind)> = files
+            .iter()
+            .map(|file| (file.url.as_str(), file.kind))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    "/static/runtime/1.2.0/zagreus-runtime.js",
+                    PrefetchKind::Runtime
+                ),
+                ("/assets/font.woff2", PrefetchKind::Resource),
+                ("/assets/missing.png", PrefetchKind::Asset),
+                ("/assets/abc.png", PrefetchKind::Asset),
+                ("/assets/def.png", PrefetchKind::Asset),
+            ],
+            urls
+        );
+        assert_eq!(
+            (3, crate::resources::compute_integrity(b"new")),
+            crate::resources::compute_file_integrity(&files[3].path).unwrap()
+        );
+        assert!(crate::resources::compute_file_integrity(&files[2].path).is_err());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{broadcast, RwLock};
+
+const PRESENCE_CHANGES_CAPACITY: usize = 256;
+const MAX_OPERATOR_NAME_LENGTH: usize = 64;
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorPresence {
+    pub operator: String,
+    pub since: DateTime<Utc>,
+    /// Number of control panels of the operator which are connected to the instance.
+    pub connections: usize,
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceLock {
+    pub operator: String,
+    pub acquired: DateTime<Utc>,
+}
+
+/// Operators which are currently operating an instance and the operator who locked it.
+#[derive(Serialize, Clone, PartialEq, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstancePresence {
+    pub operators: Vec<OperatorPresence>,
+    pub lock: Option<InstanceLock>,
+}
+
+pub enum LockResult {
+    Acquired(InstanceLock),
+    /// The lock of another operator was overridden.
+    Overridden {
+        lock: InstanceLock,
+        previous: InstanceLock,
+    },
+    /// The instance is locked by another operator.
+    Locked(InstanceLock),
+}
+
+pub enum ReleaseResult {
+    Released,
+    NotLocked,
+    /// The instance is locked by another operator.
+    Locked(InstanceLock),
+}
+
+#[derive(Default)]
+struct InstanceEntry {
+    operators: BTreeMap<String, OperatorPresence>,
+    lock: Option<InstanceLock>,
+}
+
+/// Keeps track of the operators whose control panels are connected to an instance and of
+/// advisory locks, so operators do not unknowingly drive the same graphic from different panels.
+/// Locks are not enforced by the other endpoints.
+pub struct PresenceTracker {
+    instances: RwLock<HashMap<String, InstanceEntry>>,
+    changes: broadcast::Sender<String>,
+}
+
+impl PresenceTracker {
+    pub fn new() -> PresenceTracker {
+        PresenceTracker {
+            instances: RwLock::new(HashMap::new()),
+            changes: broadcast::channel(PRESENCE_CHANGES_CAPACITY).0,
+        }
+    }
+
+    pub async fn join(&self, instance: &str, operator: &str) {
+        self.instances
+            .write()
+            .await
+            .entry(instance.to_owned())
+            .or_default()
+            .operators
+            .entry(operator.to_owned())
+            .or_insert_with(|| OperatorPresence {
+                operator: operator.to_owned(),
+                since: Utc::now(),
+                connections: 0,
+            })
+            .connections += 1;
+        self.notify_change(instance);
+    }
+
+    /// Removes a connection of the operator. Returns the lock of the operator if it was released
+    /// because the operator has no connection left.
+    pub async fn leave(&self, instance: &str, operator: &str) -> Option<InstanceLock> {
+        let mut instances = self.instances.write().await;
+        let entry = instances.get_mut(instance)?;
+        let presence = entry.operators.get_mut(operator)?;
+        presence.connections -= 1;
+        let mut released = None;
+        if presence.connections == 0 {
+            entry.operators.remove(operator);
+            if entry
+                .lock
+                .as_ref()
+                .is_some_and(|lock| lock.operator == operator)
+            {
+                released = entry.lock.take();
+            }
+        }
+        if entry.operators.is_empty() && entry.lock.is_none() {
+            instances.remove(instance);
+        }
+        drop(instances);
+        self.notify_change(instance);
+        released
+    }
+
+    pub async fn get_presence(&self, instance: &str) -> InstancePresence {
+        match self.instances.read().await.get(instance) {
+            Some(entry) => InstancePresence {
+                operators: entry.operators.values().cloned().collect(),
+                lock: entry.lock.clone(),
+            },
+            None => InstancePresence::default(),
+        }
+    }
+
+    /// Locks the instance for the operator. The lock of another operator is only overridden if
+    /// forced.
+    pub async fn lock(&self, instance: &str, operator: &str, force: bool) -> LockResult {
+        let mut instances = self.instances.write().await;
+        let entry = instances.entry(instance.to_owned()).or_default();
+        let lock = InstanceLock {
+            operator: operator.to_owned(),
+            acquired: Utc::now(),
+        };
+        let result = match entry.lock.take() {
+            Some(previous) if previous.operator == operator => {
+                entry.lock = Some(previous.clone());
+                return LockResult::Acquired(previous);
+            }
+            Some(previous) if !force => {
+                entry.lock = Some(previous.clone());
+                return LockResult::Locked(previous);
+            }
+            Some(previous) => LockResult::Overridden {
+                lock: lock.clone(),
+                previous,
+            },
+            None => LockResult::Acquired(lock.clone()),
+        };
+        entry.lock = Some(lock);
+        drop(instances);
+        self.notify_change(instance);
+        result
+    }
+
+    pub async fn release(&self, instance: &str, operator: &str) -> ReleaseResult {
+        let mut instances = self.instances.write().await;
+        let Some(entry) = instances.get_mut(instance) else {
+            return ReleaseResult::NotLocked;
+        };
+        match &entry.lock {
+            None => return ReleaseResult::NotLocked,
+            Some(lock) if lock.operator != operator => return ReleaseResult::Locked(lock.clone()),
+            Some(_) => entry.lock = None,
+        }
+        if entry.operators.is_empty() {
+            instances.remove(instance);
+        }
+        drop(instances);
+        self.notify_change(instance);
+        ReleaseResult::Released
+    }
+
+    /// Returns a receiver of the names of instances whose presence or lock changed.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<String> {
+        self.changes.subscribe()
+    }
+
+    fn notify_change(&self, instance: &str) {
+        // sending only fails if nobody is subscribed
+        let _ = self.changes.send(instance.to_owned());
+    }
+}
+
+pub fn is_valid_operator_name(operator: &str) -> bool {
+    !operator.trim().is_empty()
+        && operator.chars().count() <= MAX_OPERATOR_NAME_LENGTH
+        && !operator.chars().any(char::is_control)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_locks_are_advisory_and_released_on_leave() {
+        let tracker = PresenceTracker::new();
+        tracker.join("lower-third", "jane").await;
+        tracker.join("lower-third", "jane").await;
+        tracker.join("lower-third", "john").await;
+
+        assert!(matches!(
+            tracker.lock("lower-third", "jane", false).await,
+            LockResult::Acquired(_)
+        ));
+        assert!(matches!(
+            tracker.lock("lower-third", "john", false).await,
+            LockResult::Locked(lock) if lock.operator == "jane"
+        ));
+        assert!(matches!(
+            tracker.release("lower-third", "john").await,
+            ReleaseResult::Locked(_)
+        ));
+
+        // the lock is kept until the last panel of the operator disconnects
+        assert!(tracker.leave("lower-third", "jane").await.is_none());
+        let released = tracker.leave("lower-third", "jane").await;
+        assert_eq!(
+            Some(String::from("jane")),
+            released.map(|lock| lock.operator)
+        );
+
+        let presence = tracker.get_presence("lower-third").await;
+        assert_eq!(1, presence.operators.len());
+        assert!(presence.lock.is_none());
+
+        tracker.lock("lower-third", "john", false).await;
+        assert!(matches!(
+            tracker.lock("lower-third", "jane", true).await,
+            LockResult::Overridden { previous, .. } if previous.operator == "john"
+        ));
+    }
+
+    #[test]
+    fn test_operator_names() {
+        assert!(is_valid_operator_name("Jane Doe"));
+        assert!(!is_valid_operator_name(" "));
+        assert!(!is_valid_operator_name("jane\n"));
+    }
+}
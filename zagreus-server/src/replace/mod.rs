@@ -0,0 +1,153 @@
+use crate::defaults::DataDefaults;
+use crate::snapshot::Snapshots;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceRequest {
+    pub find: String,
+    pub replace: String,
+    /// Instance to search, all instances are searched if not set.
+    #[serde(default)]
+    pub instance: Option<String>,
+    /// Only returns the matches without changing any text.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReplaceLocation {
+    Default,
+    Snapshot,
+}
+
+/// A text containing the searched string, with the text after the replacement.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceMatch {
+    pub instance: String,
+    pub location: ReplaceLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<String>,
+    pub element: String,
+    pub text: String,
+    pub replaced: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceReport {
+    pub dry_run: bool,
+    pub matches: Vec<ReplaceMatch>,
+}
+
+/// Replaces the string in the default texts of the instance and returns the matches.
+pub fn replace_in_defaults(
+    instance: &str,
+    defaults: &mut DataDefaults,
+    find: &str,
+    replace: &str,
+) -> Vec<ReplaceMatch> {
+    let mut matches = Vec::new();
+    for (element, default) in defaults.iter_mut() {
+        if let Some(text) = &mut default.text {
+            if let Some(replaced) = replace_text(text, find, replace) {
+                matches.push(ReplaceMatch {
+                    instance: instance.to_owned(),
+                    location: ReplaceLocation::Default,
+                    snapshot: None,
+                    element: element.clone(),
+                    text: std::mem::replace(text, replaced.clone()),
+                    replaced,
+                });
+            }
+        }
+    }
+    matches
+}
+
+/// Replaces the string in the texts of all snapshots of the instance and returns the matches.
+pub fn replace_in_snapshots(
+    instance: &str,
+    snapshots: &mut Snapshots,
+    find: &str,
+    replace: &str,
+) -> Vec<ReplaceMatch> {
+    let mut matches = Vec::new();
+    for (name, snapshot) in snapshots.iter_mut() {
+        for (element, text) in snapshot.state.texts.iter_mut() {
+            if let Some(replaced) = replace_text(text, find, replace) {
+                matches.push(ReplaceMatch {
+                    instance: instance.to_owned(),
+                    location: ReplaceLocation::Snapshot,
+                    snapshot: Some(name.clone()),
+                    element: element.clone(),
+                    text: std::mem::replace(text, replaced.clone()),
+                    replaced,
+                });
+            }
+        }
+    }
+    matches
+}
+
+fn replace_text(text: &str, find: &str, replace: &str) -> Option<String> {
+    text.contains(find).then(|| text.replace(find, replace))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::defaults::ElementDefault;
+    use crate::snapshot::Snapshot;
+    use crate::state::InstanceState;
+
+    #[test]
+    fn test_replace_texts() {
+        let mut defaults = DataDefaults::from([
+            (
+                String::from("Sponsor"),
+                ElementDefault {
+                    text: Some(String::from("Presented by ACME")),
+                    ..ElementDefault::default()
+                },
+            ),
+            (
+                String::from("Title"),
+                ElementDefault {
+                    text: Some(String::from("Final")),
+                    ..ElementDefault::default()
+                },
+            ),
+        ]);
+        let matches = replace_in_defaults("scoreboard", &mut defaults, "ACME", "Globex");
+        assert_eq!(1, matches.len());
+        assert_eq!("Presented by ACME", matches[0].text);
+        assert_eq!(
+            Some(String::from("Presented by Globex")),
+            defaults["Sponsor"].text
+        );
+        assert_eq!(Some(String::from("Final")), defaults["Title"].text);
+
+        let mut state = InstanceState::default();
+        state
+            .texts
+            .insert(String::from("Sponsor"), String::from("ACME & ACME"));
+        let mut snapshots = Snapshots::from([(
+            String::from("kickoff"),
+            Snapshot {
+                created: Utc::now(),
+                state,
+                automatic: false,
+            },
+        )]);
+        let matches = replace_in_snapshots("scoreboard", &mut snapshots, "ACME", "Globex");
+        assert_eq!(Some(String::from("kickoff")), matches[0].snapshot);
+        assert_eq!(
+            "Globex & Globex",
+            snapshots["kickoff"].state.texts["Sponsor"]
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use tokio::sync::RwLock;
+
+use crate::config::ResilienceConfig;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrationHealth {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub success_count: u64,
+    pub failure_count: u64,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_failure: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    #[serde(skip)]
+    opened_at: Option<Instant>,
+    /// Start of the single call which probes the integration while the circuit is half-open.
+    #[serde(skip)]
+    probe_started: Option<Instant>,
+}
+
+impl Default for IntegrationHealth {
+    fn default() -> Self {
+        IntegrationHealth {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            success_count: 0,
+            failure_count: 0,
+            last_success: None,
+            last_failure: None,
+            last_error: None,
+            opened_at: None,
+            probe_started: None,
+        }
+    }
+}
+
+/// Executes calls to external integrations (e.g. data feeds or webhooks) with retries and a
+/// circuit breaker per integration. While a circuit is open, calls fail immediately so that a
+/// failing provider is neither hammered nor flooding the log.
+pub struct IntegrationMonitor {
+    configuration: ResilienceConfig,
+    integrations: RwLock<HashMap<String, IntegrationHealth>>,
+}
+
+impl IntegrationMonitor {
+    pub fn new(configuration: ResilienceConfig) -> IntegrationMonitor {
+        IntegrationMonitor {
+            configuration,
+            integrations: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn execute<T, F, Fut>(&self, integration: &str, mut operation: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        if !self.try_acquire(integration).await {
+            return Err(anyhow!("Circuit of integration {} is open", integration));
+        }
+
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Ok(value) => {
+                    self.record_success(integration).await;
+                    return Ok(value);
+                }
+                Err(err) if attempt < self.configuration.retry.max_attempts => {
+                    debug!(
+                        "Attempt {} of integration {} failed: {}.",
+                        attempt, integration, err
+                    );
+                    tokio::time::sleep(self.get_backoff(attempt)).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    self.record_failure(integration, &err).await;
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    pub async fn get_health(&self) -> BTreeMap<String, IntegrationHealth> {
+        self.integrations
+            .read()
+            .await
+            .iter()
+            .map(|(name, health)| (name.clone(), health.clone()))
+            .collect()
+    }
+
+    async fn try_acquire(&self, integration: &str) -> bool {
+        let open_duration = Duration::from_secs(self.configuration.circuit_breaker.open_seconds);
+        let mut integrations = self.integrations.write().await;
+        let health = integrations.entry(integration.to_owned()).or_default();
+        match health.state {
+            CircuitState::Closed => true,
+            // only a single call probes the integration, unless the probe was abandoned without
+            // a result (e.g. the caller was dropped)
+            CircuitState::HalfOpen => {
+                let elapsed = health.probe_started.map(|started| started.elapsed());
+                if elapsed.is_none_or(|elapsed| elapsed >= open_duration) {
+                    health.probe_started = Some(Instant::now());
+                    true
+                } else {
+                    false
+                }
+            }
+            CircuitState::Open => {
+                let elapsed = health.opened_at.map(|opened_at| opened_at.elapsed());
+                if elapsed.is_none_or(|elapsed| elapsed >= open_duration) {
+                    health.state = CircuitState::HalfOpen;
+                    health.probe_started = Some(Instant::now());
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    async fn record_success(&self, integration: &str) {
+        let mut integrations = self.integrations.write().await;
+        let health = integrations.entry(integration.to_owned()).or_default();
+        if health.state != CircuitState::Closed {
+            info!("Circuit of integration {} is closed again.", integration);
+        }
+        health.state = CircuitState::Closed;
+        health.opened_at = None;
+        health.probe_started = None;
+        health.consecutive_failures = 0;
+        health.success_count += 1;
+        health.last_success = Some(Utc::now());
+    }
+
+    async fn record_failure(&self, integration: &str, err: &anyhow::Error) {
+        let failure_threshold = self.configuration.circuit_breaker.failure_threshold;
+        let mut integrations = self.integrations.write().await;
+        let health = integrations.entry(integration.to_owned()).or_default();
+        health.consecutive_failures += 1;
+        health.failure_count += 1;
+        health.last_failure = Some(Utc::now());
+        health.last_error = Some(err.to_string());
+
+        let should_open = health.state == CircuitState::HalfOpen
+            || (health.state == CircuitState::Closed
+                && health.consecutive_failures >= failure_threshold);
+        if should_open {
+            warn!(
+                "Circuit of integration {} is open after {} consecutive failures: {}.",
+                integration, health.consecutive_failures, err
+            );
+            health.state = CircuitState::Open;
+            health.opened_at = Some(Instant::now());
+            health.probe_started = None;
+        }
+    }
+
+    /// Exponential backoff with jitter, i.e. a random delay between half and the full backoff.
+    fn get_backoff(&self, attempt: u32) -> Duration {
+        let retry = &self.configuration.retry;
+        let backoff_ms = retry
+            .initial_backoff_ms
+            .saturating_mul(2u64.saturating_pow(attempt - 1))
+            .min(retry.max_backoff_ms);
+        let jittered_backoff_ms = rand::thread_rng().gen_range(backoff_ms / 2..=backoff_ms);
+        Duration::from_millis(jittered_backoff_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{CircuitBreakerConfig, RetryConfig};
+
+    fn get_monitor() -> IntegrationMonitor {
+        IntegrationMonitor::new(ResilienceConfig {
+            retry: RetryConfig {
+                max_attempts: 2,
+                initial_backoff_ms: 0,
+                max_backoff_ms: 0,
+            },
+            circuit_breaker: CircuitBreakerConfig {
+                failure_threshold: 2,
+                open_seconds: 60,
+            },
+        })
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_consecutive_failures() {
+        let monitor = get_monitor();
+        let mut attempts = 0;
+        for _ in 0..3 {
+            let result: anyhow::Result<()> = monitor
+                .execute("sports-feed", || {
+                    attempts += 1;
+                    async { Err(anyhow!("feed unavailable")) }
+                })
+                .await;
+            assert!(result.is_err());
+        }
+
+        // two calls with two attempts each, the third call fails without an attempt
+        assert_eq!(4, attempts);
+        let health = &monitor.get_health().await["sports-feed"];
+        assert_eq!(CircuitState::Open, health.state);
+        assert_eq!(2, health.failure_count);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_circuit_allows_single_probe() {
+        let monitor = get_monitor();
+        for _ in 0..2 {
+            let result: anyhow::Result<()> = monitor
+                .execute("sports-feed", || async { Err(anyhow!("feed unavailable")) })
+                .await;
+            assert!(result.is_err());
+        }
+        // the circuit was opened long enough ago to be probed
+        monitor
+            .integrations
+            .write()
+            .await
+            .get_mut("sports-feed")
+            .unwrap()
+            .opened_at = Instant::now().checked_sub(Duration::from_secs(61));
+
+        let (release_probe, probe_released) = tokio::sync::oneshot::channel::<()>();
+        let mut probe_released = Some(probe_released);
+        let probe = monitor.execute("sports-feed", || {
+            let released = probe_released.take().unwrap();
+            async move {
+                released.await?;
+                Ok(())
+            }
+        });
+        tokio::pin!(probe);
+        // start the probe, which waits until it is released
+        assert!(futures::poll!(probe.as_mut()).is_pending());
+        assert_eq!(
+            CircuitState::HalfOpen,
+            monitor.get_health().await["sports-feed"].state
+        );
+
+        let mut attempts = 0;
+        let result: anyhow::Result<()> = monitor
+            .execute("sports-feed", || {
+                attempts += 1;
+                async { Ok(()) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(0, attempts);
+
+        release_probe.send(()).unwrap();
+        assert!(probe.await.is_ok());
+        assert_eq!(
+            CircuitState::Closed,
+            monitor.get_health().await["sports-feed"].state
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_succeeds() {
+        let monitor = get_monitor();
+        let mut attempts = 0;
+        let result = monitor
+            .execute("sports-feed", || {
+                attempts += 1;
+                let attempt = attempts;
+                async move {
+                    if attempt == 1 {
+                        Err(anyhow!("feed unavailable"))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+
+        assert_eq!(2, result.unwrap());
+        let health = &monitor.get_health().await["sports-feed"];
+        assert_eq!(CircuitState::Closed, health.state);
+        assert_eq!(0, health.failure_count);
+    }
+
+    #[test]
+    fn test_backoff_is_bounded() {
+        let monitor = IntegrationMonitor::new(ResilienceConfig::default());
+        for attempt in 1..20 {
+            let backoff = monitor.get_backoff(attempt);
+            assert!(backoff <= Duration::from_millis(monitor.configuration.retry.max_backoff_ms));
+        }
+    }
+}
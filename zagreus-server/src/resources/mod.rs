@@ -0,0 +1,129 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::store::{load_document, store_document};
+
+/// An external resource (e.g. a CSS or JS file from a CDN) which was downloaded and is served
+/// from the assets folder.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PinnedResource {
+    /// File of the resource in the assets folder.
+    pub file: String,
+    /// Subresource integrity hash of the content, e.g. `sha384-...`.
+    pub integrity: String,
+}
+
+/// Maps the URLs of external resources to their pinned local copy.
+pub type PinnedResources = BTreeMap<String, PinnedResource>;
+
+const RESOURCES_FILE_NAME: &str = "resources.json";
+
+pub struct ResourceStore {
+    resources_path: PathBuf,
+    resources: RwLock<Option<PinnedResources>>,
+    /// Serializes changes, so concurrent changes do not overwrite each other.
+    update_lock: Mutex<()>,
+}
+
+impl ResourceStore {
+    pub fn new(data_folder: PathBuf) -> ResourceStore {
+        ResourceStore {
+            resources_path: data_folder.join(RESOURCES_FILE_NAME),
+            resources: RwLock::new(None),
+            update_lock: Mutex::new(()),
+        }
+    }
+
+    pub async fn get_resources(&self) -> anyhow::Result<PinnedResources> {
+        if let Some(resources) = self.resources.read().await.as_ref() {
+            return Ok(resources.clone());
+        }
+        // loaded while holding the write lock, so a concurrent change is not overwritten
+        let mut cached = self.resources.write().await;
+        if let Some(resources) = cached.as_ref() {
+            return Ok(resources.clone());
+        }
+        let resources: PinnedResources = load_document(&self.resources_path).await?;
+        *cached = Some(resources.clone());
+        Ok(resources)
+    }
+
+    pub async fn set_resource(&self, url: &str, resource: PinnedResource) -> anyhow::Result<()> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut resources = self.get_resources().await?;
+        resources.insert(url.to_owned(), resource);
+        self.store_resources(resources).await
+    }
+
+    /// Returns whether the resource was pinned. The file is kept in the assets folder.
+    pub async fn delete_resource(&self, url: &str) -> anyhow::Result<bool> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut resources = self.get_resources().await?;
+        if resources.remove(url).is_none() {
+            return Ok(false);
+        }
+        self.store_resources(resources).await?;
+        Ok(true)
+    }
+
+    async fn store_resources(&self, resources: PinnedResources) -> anyhow::Result<()> {
+        store_document(&self.resources_path, &resources).await?;
+        *self.resources.write().await = Some(resources);
+        Ok(())
+    }
+}
+
+/// Returns the subresource integrity hash (SHA-384) of the data.
+pub fn compute_integrity(data: &[u8]) -> String {
+    format!("sha384-{}", BASE64.encode(Sha384::digest(data)))
+}
+
+/// Returns the size and subresource integrity hash (SHA-384) of the file, which is streamed
+/// instead of being loaded into memory.
+pub fn compute_file_integrity(path: &Path) -> std::io::Result<(u64, String)> {
+    let mut hasher = Sha384::new();
+    let size = std::io::copy(&mut std::fs::File::open(path)?, &mut hasher)?;
+    Ok((size, format!("sha384-{}", BASE64.encode(hasher.finalize()))))
+}
+
+/// Checks the data against a subresource integrity value. Like in browsers, the value may contain
+/// multiple hashes separated by whitespace, of which one has to match.
+pub fn verify_integrity(data: &[u8], integrity: &str) -> bool {
+    integrity.split_whitespace().any(|hash| {
+        // options (e.g. `sha384-...?foo`) are ignored
+        let hash = hash.split('?').next().unwrap_or_default();
+        let Some((algorithm, expected)) = hash.split_once('-') else {
+            return false;
+        };
+        let actual = match algorithm {
+            "sha256" => BASE64.encode(Sha256::digest(data)),
+            "sha384" => BASE64.encode(Sha384::digest(data)),
+            "sha512" => BASE64.encode(Sha512::digest(data)),
+            _ => return false,
+        };
+        actual == expected
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_integrity() {
+        let data = b"body { background: transparent; }";
+        let integrity = compute_integrity(data);
+        assert!(verify_integrity(data, &integrity));
+        assert!(!verify_integrity(b"body {}", &integrity));
+
+        let sha256 = format!("sha256-{}", BASE64.encode(Sha256::digest(data)));
+        assert!(verify_integrity(data, &format!("sha512-invalid {sha256}")));
+        assert!(!verify_integrity(data, "md5-abc"));
+    }
+}
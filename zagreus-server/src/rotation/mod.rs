@@ -0,0 +1,388 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Local, NaiveTime, Utc, Weekday};
+use tokio::io::AsyncWriteExt;
+
+use crate::controller::ServerController;
+use crate::data::asset::AssetReference;
+use crate::data::operation::Operation;
+
+/// Interval in which the rotations are advanced.
+const ROTATION_TICK: Duration = Duration::from_secs(1);
+
+/// Time window in which an item of a rotation may be shown. The window wraps around midnight if
+/// `to` is before `from`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Daypart {
+    /// Days on which the window applies, every day if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Weekday>,
+    pub from: NaiveTime,
+    pub to: NaiveTime,
+}
+
+impl Daypart {
+    fn contains(&self, now: DateTime<Local>) -> bool {
+        let time = now.time();
+        let (day, in_window) = if self.from <= self.to {
+            (now.weekday(), self.from <= time && time < self.to)
+        } else if time >= self.from {
+            (now.weekday(), true)
+        } else {
+            // after midnight the window belongs to the day it started on
+            (now.weekday().pred(), time < self.to)
+        };
+        in_window && (self.days.is_empty() || self.days.contains(&day))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationItem {
+    pub image: AssetReference,
+    pub duration_seconds: u64,
+    /// Windows in which the item is shown, always if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dayparts: Vec<Daypart>,
+}
+
+impl RotationItem {
+    fn is_scheduled(&self, now: DateTime<Local>) -> bool {
+        self.dayparts.is_empty() || self.dayparts.iter().any(|daypart| daypart.contains(now))
+    }
+}
+
+/// Playlist of images which are shown one after another in an image element, e.g. sponsor logos.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Rotation {
+    pub element: String,
+    pub items: Vec<RotationItem>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Rotation {
+    /// Returns the index of the next item after the given one which is scheduled now.
+    fn get_next_item(&self, current: Option<usize>, now: DateTime<Local>) -> Option<usize> {
+        let start = current.map_or(0, |current| current + 1);
+        (0..self.items.len())
+            .map(|offset| (start + offset) % self.items.len())
+            .find(|index| self.items[*index].is_scheduled(now))
+    }
+}
+
+/// Rotations of an instance per name.
+pub type Rotations = BTreeMap<String, Rotation>;
+
+pub const ROTATIONS_FILE_NAME: &str = "rotations.json";
+const DISPLAY_LOG_FILE_NAME: &str = "rotation-log.jsonl";
+
+/// Returns an error message if a rotation has no element or an item is shown for no time.
+pub fn validate_rotations(rotations: &Rotations) -> Option<String> {
+    rotations.iter().find_map(|(name, rotation)| {
+        if rotation.element.is_empty() {
+            Some(format!("Rotation {name} has no element."))
+        } else if rotation.items.iter().any(|item| item.duration_seconds == 0) {
+            Some(format!("Rotation {name} has an item without duration."))
+        } else {
+            None
+        }
+    })
+}
+
+/// Time span in which an item of a rotation was shown.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayRecord {
+    pub rotation: String,
+    pub element: String,
+    pub image: AssetReference,
+    pub started: DateTime<Utc>,
+    pub ended: DateTime<Utc>,
+    /// Number of renderers connected when the item was shown.
+    pub clients: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayReport {
+    pub records: Vec<DisplayRecord>,
+    /// Seconds per asset in which the asset was shown while a renderer was connected.
+    pub display_seconds: BTreeMap<String, f64>,
+}
+
+/// Append-only log of the display times of rotation items per instance, for sponsorship
+/// reporting.
+pub struct DisplayLog {
+    data_folder: PathBuf,
+}
+
+impl DisplayLog {
+    pub fn new(data_folder: PathBuf) -> DisplayLog {
+        DisplayLog { data_folder }
+    }
+
+    pub async fn append(&self, instance: &str, record: &DisplayRecord) -> anyhow::Result<()> {
+        let instance_folder = crate::fs::get_instance_folder(&self.data_folder, instance)?;
+        tokio::fs::create_dir_all(&instance_folder).await?;
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(instance_folder.join(DISPLAY_LOG_FILE_NAME))
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Returns the records which overlap with the given time range.
+    pub async fn get_report(
+        &self,
+        instance: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<DisplayReport> {
+        let log_path = crate::fs::get_instance_folder(&self.data_folder, instance)?
+            .join(DISPLAY_LOG_FILE_NAME);
+        let contents = if log_path.exists() {
+            tokio::fs::read_to_string(log_path).await?
+        } else {
+            String::new()
+        };
+        let records: Vec<DisplayRecord> = contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .filter(|record: &DisplayRecord| from.is_none_or(|from| record.ended > from))
+            .filter(|record: &DisplayRecord| to.is_none_or(|to| record.started < to))
+            .collect();
+        Ok(DisplayReport {
+            display_seconds: sum_display_seconds(&records),
+            records,
+        })
+    }
+}
+
+fn sum_display_seconds(records: &[DisplayRecord]) -> BTreeMap<String, f64> {
+    let mut display_milliseconds: BTreeMap<String, i64> = BTreeMap::new();
+    for record in records.iter().filter(|record| record.clients > 0) {
+        *display_milliseconds
+            .entry(record.image.asset.clone())
+            .or_default() += (record.ended - record.started).num_milliseconds();
+    }
+    display_milliseconds
+        .into_iter()
+        .map(|(asset, milliseconds)| (asset, milliseconds as f64 / 1000.0))
+        .collect()
+}
+
+struct PlayingItem {
+    index: usize,
+    element: String,
+    image: AssetReference,
+    started: DateTime<Utc>,
+    until: Instant,
+    clients: usize,
+}
+
+/// Advances the rotations of all instances: every item is shown in the bound image element for
+/// its duration, skipping items outside of their dayparts. The actual display times are logged.
+pub async fn run_rotations(controller: Arc<ServerController>) {
+    let mut interval = tokio::time::interval(ROTATION_TICK);
+    let mut playing: HashMap<(String, String), PlayingItem> = HashMap::new();
+    loop {
+        // durations are measured in ticks, so items are not shown a tick longer than configured
+        let tick = interval.tick().await.into_std();
+        let instances = match controller.get_rotations_of_instances().await {
+            Ok(instances) => instances,
+            Err(err) => {
+                error!("Could not load rotations: {:#}.", err);
+                continue;
+            }
+        };
+        let now = Local::now();
+
+        // rotations which were deleted or disabled stop with their current item
+        let enabled: HashSet<(String, String)> = instances
+            .iter()
+            .flat_map(|(instance, rotations)| {
+                rotations
+                    .iter()
+                    .filter(|(_, rotation)| rotation.enabled)
+                    .map(move |(name, _)| (instance.clone(), name.clone()))
+            })
+            .collect();
+        let stopped: Vec<(String, String)> = playing
+            .keys()
+            .filter(|key| !enabled.contains(*key))
+            .cloned()
+            .collect();
+        for key in stopped {
+            if let Some(item) = playing.remove(&key) {
+                record_display(&controller, &key, item).await;
+            }
+        }
+
+        for (instance, rotations) in &instances {
+            for (name, rotation) in rotations.iter().filter(|(_, rotation)| rotation.enabled) {
+                let key = (instance.clone(), name.clone());
+                let current = playing.get(&key);
+                let is_due = current.is_none_or(|current| {
+                    tick >= current.until
+                        || current.element != rotation.element
+                        || rotation
+                            .items
+                            .get(current.index)
+                            .is_none_or(|item| !item.is_scheduled(now))
+                });
+                if !is_due {
+                    continue;
+                }
+
+                let next = rotation.get_next_item(current.map(|current| current.index), now);
+                let Some(index) = next else {
+                    if let Some(item) = playing.remove(&key) {
+                        record_display(&controller, &key, item).await;
+                    }
+                    continue;
+                };
+                let item = &rotation.items[index];
+                if let Some(current) = playing.get_mut(&key) {
+                    // the only scheduled item stays on air without being sent again
+                    if current.index == index
+                        && current.image == item.image
+                        && current.element == rotation.element
+                    {
+                        current.until = tick + Duration::from_secs(item.duration_seconds);
+                        continue;
+                    }
+                }
+                if let Some(previous) = playing.remove(&key) {
+                    record_display(&controller, &key, previous).await;
+                }
+
+                let operation = Operation::SetImageSource {
+                    id: rotation.element.clone(),
+                    asset: item.image.asset.clone(),
+                    asset_source: item.image.asset_source.clone(),
+                };
+                controller
+                    .submit_operations(instance, vec![operation])
+                    .await;
+                playing.insert(
+                    key,
+                    PlayingItem {
+                        index,
+                        element: rotation.element.clone(),
+                        image: item.image.clone(),
+                        started: Utc::now(),
+                        until: tick + Duration::from_secs(item.duration_seconds),
+                        clients: controller.get_instance_client_count(instance).await,
+                    },
+                );
+            }
+        }
+    }
+}
+
+async fn record_display(
+    controller: &ServerController,
+    (instance, rotation): &(String, String),
+    item: PlayingItem,
+) {
+    let record = DisplayRecord {
+        rotation: rotation.clone(),
+        element: item.element,
+        image: item.image,
+        started: item.started,
+        ended: Utc::now(),
+        clients: item.clients,
+    };
+    if let Err(err) = controller.record_rotation_display(instance, &record).await {
+        error!(
+            "Could not log display of rotation {} of instance {}: {}.",
+            rotation, instance, err
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::data::asset::AssetSource;
+
+    fn item(asset: &str, dayparts: Vec<Daypart>) -> RotationItem {
+        RotationItem {
+            image: AssetReference {
+                asset: asset.to_owned(),
+                asset_source: AssetSource::Zagreus,
+            },
+            duration_seconds: 10,
+            dayparts,
+        }
+    }
+
+    #[test]
+    fn test_dayparts() {
+        let time = |hour: u32| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+        let night = Daypart {
+            days: vec![Weekday::Fri],
+            from: time(22),
+            to: time(2),
+        };
+        // 2023-06-02 is a Friday
+        let friday_night = Local.with_ymd_and_hms(2023, 6, 2, 23, 0, 0).unwrap();
+        let saturday_morning = Local.with_ymd_and_hms(2023, 6, 3, 1, 0, 0).unwrap();
+        let saturday_night = Local.with_ymd_and_hms(2023, 6, 3, 23, 0, 0).unwrap();
+        assert!(night.contains(friday_night));
+        assert!(night.contains(saturday_morning));
+        assert!(!night.contains(saturday_night));
+
+        let rotation = Rotation {
+            element: String::from("Sponsor"),
+            items: vec![
+                item("acme.png", Vec::new()),
+                item("nightclub.png", vec![night]),
+                item("brewery.png", Vec::new()),
+            ],
+            enabled: true,
+        };
+        assert_eq!(Some(0), rotation.get_next_item(None, saturday_night));
+        assert_eq!(Some(1), rotation.get_next_item(Some(0), friday_night));
+        assert_eq!(Some(2), rotation.get_next_item(Some(0), saturday_night));
+        assert_eq!(Some(0), rotation.get_next_item(Some(2), saturday_night));
+    }
+
+    #[test]
+    fn test_only_displays_with_renderers_are_counted() {
+        let started = Utc.with_ymd_and_hms(2023, 6, 2, 20, 0, 0).unwrap();
+        let record = |asset: &str, seconds: i64, clients: usize| DisplayRecord {
+            rotation: String::from("sponsors"),
+            element: String::from("Sponsor"),
+            image: item(asset, Vec::new()).image,
+            started,
+            ended: started + chrono::Duration::seconds(seconds),
+            clients,
+        };
+        let records = vec![
+            record("acme.png", 10, 1),
+            record("brewery.png", 10, 0),
+            record("acme.png", 5, 2),
+        ];
+        assert_eq!(
+            BTreeMap::from([(String::from("acme.png"), 15.0)]),
+            sum_display_seconds(&records)
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Utc};
+
+use crate::config::{DailyRoutineConfig, RoutineAction, ZagreusServerConfig};
+use crate::controller::ServerController;
+
+/// Interval in which the daily routines are checked.
+const ROUTINE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Returns whether the time of day passed after `last_check` until `now`, in the time zone of
+/// `now`. If the time does not exist on a day because the clocks are set forward, it is due an
+/// hour later.
+pub fn is_due<Tz: TimeZone>(at: NaiveTime, last_check: DateTime<Tz>, now: DateTime<Tz>) -> bool {
+    let timezone = now.timezone();
+    let today = now.date_naive();
+    [today.pred_opt(), Some(today)]
+        .into_iter()
+        .flatten()
+        .filter_map(|date| {
+            let time = date.and_time(at);
+            timezone.from_local_datetime(&time).earliest().or_else(|| {
+                let shifted = time + chrono::Duration::hours(1);
+                timezone.from_local_datetime(&shifted).earliest()
+            })
+        })
+        .any(|due_time| last_check < due_time && due_time <= now)
+}
+
+fn is_routine_due(
+    routine: &DailyRoutineConfig,
+    last_check: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> bool {
+    match routine.timezone {
+        Some(timezone) => is_due(
+            routine.at,
+            last_check.with_timezone(&timezone),
+            now.with_timezone(&timezone),
+        ),
+        None => is_due(
+            routine.at,
+            last_check.with_timezone(&Local),
+            now.with_timezone(&Local),
+        ),
+    }
+}
+
+/// Runs the configured routines every day at their time, so unattended installations (e.g. a
+/// channel in a box) maintain themselves.
+pub async fn run_daily_routines(
+    controller: Arc<ServerController>,
+    configuration: ZagreusServerConfig,
+) {
+    let mut interval = tokio::time::interval(ROUTINE_CHECK_INTERVAL);
+    let mut last_check = Utc::now();
+    loop {
+        interval.tick().await;
+        let now = Utc::now();
+        for routine in &configuration.daily_routines {
+            if is_routine_due(routine, last_check, now) {
+                run_routine(&controller, &configuration, routine).await;
+            }
+        }
+        last_check = now;
+    }
+}
+
+async fn run_routine(
+    controller: &ServerController,
+    configuration: &ZagreusServerConfig,
+    routine: &DailyRoutineConfig,
+) {
+    info!(
+        "Running daily routine scheduled at {} with actions {:?}.",
+        routine.at, routine.actions
+    );
+    for action in &routine.actions {
+        match action {
+            RoutineAction::ClearInstances => {
+                for instance in controller.get_instances_with_state().await {
+                    // renderers may connect to instances whose names cannot be used as folder
+                    if !crate::fs::is_valid_instance_name(&instance) {
+                        continue;
+                    }
+                    if let Err(err) = controller.clear_instance(&instance).await {
+                        error!("Could not clear instance {}: {:#}.", instance, err);
+                    }
+                }
+            }
+            RoutineAction::RotateLogs => {
+                crate::logger::request_rotation();
+                info!("Rotating log files.");
+            }
+            RoutineAction::RefreshDataSources => match controller.get_data_sources().await {
+                Ok(sources) => {
+                    for name in sources.keys() {
+                        if let Err(err) = controller.poll_data_source(name).await {
+                            error!("Could not poll data source {}: {:#}.", name, err);
+                        }
+                    }
+                    info!("Refreshed {} data sources.", sources.len());
+                }
+                Err(err) => error!("Could not load data sources: {:#}.", err),
+            },
+            RoutineAction::SelfTest => {
+                let report = crate::selftest::run_self_test(configuration, Some(controller)).await;
+                for check in &report.checks {
+                    if check.status == crate::selftest::CheckStatus::Failed {
+                        error!("Self-test check {} failed: {}", check.name, check.message);
+                    }
+                }
+                if report.passed {
+                    info!("Self-test passed.");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_routine_is_due_in_its_time_zone() {
+        let routine: DailyRoutineConfig = serde_json::from_str(
+            r#"{"at": "05:00", "timezone": "Europe/Zurich", "actions": ["clearInstances"]}"#,
+        )
+        .unwrap();
+        let at = |time: &str| time.parse::<DateTime<Utc>>().unwrap();
+
+        // 05:00 in Zurich is 03:00 UTC in summer and 04:00 UTC in winter
+        assert!(is_routine_due(
+            &routine,
+            at("2024-07-01T02:59:50Z"),
+            at("2024-07-01T03:00:10Z")
+        ));
+        assert!(!is_routine_due(
+            &routine,
+            at("2024-07-01T03:00:10Z"),
+            at("2024-07-01T03:00:40Z")
+        ));
+        assert!(!is_routine_due(
+            &routine,
+            at("2024-12-01T03:59:30Z"),
+            at("2024-12-01T03:59:59Z")
+        ));
+        assert!(is_routine_due(
+            &routine,
+            at("2024-12-01T03:59:59Z"),
+            at("2024-12-01T04:00:20Z")
+        ));
+
+        // 02:30 does not exist when the clocks are set forward, so the routine runs at 03:30
+        let routine = DailyRoutineConfig {
+            at: NaiveTime::from_hms_opt(2, 30, 0).unwrap(),
+            ..routine
+        };
+        assert!(is_routine_due(
+            &routine,
+            at("2024-03-31T01:29:50Z"),
+            at("2024-03-31T01:30:10Z")
+        ));
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use tokio::sync::RwLock;
+
+use crate::store::{load_document, store_document};
+
+/// Output channels (e.g. `program-a`) to which the data of the instances is routed. Instances
+/// without a route reach all of their renderers.
+pub type Routes = BTreeMap<String, BTreeSet<String>>;
+
+const ROUTING_FILE_NAME: &str = "routing.json";
+
+pub struct RoutingMatrix {
+    routes_path: PathBuf,
+    routes: RwLock<Option<Routes>>,
+}
+
+impl RoutingMatrix {
+    pub fn new(data_folder: PathBuf) -> RoutingMatrix {
+        RoutingMatrix {
+            routes_path: data_folder.join(ROUTING_FILE_NAME),
+            routes: RwLock::new(None),
+        }
+    }
+
+    pub async fn get_routes(&self) -> anyhow::Result<Routes> {
+        if let Some(routes) = self.routes.read().await.as_ref() {
+            return Ok(routes.clone());
+        }
+        let routes: Routes = load_document(&self.routes_path).await?;
+        *self.routes.write().await = Some(routes.clone());
+        Ok(routes)
+    }
+
+    pub async fn get_route(&self, instance: &str) -> anyhow::Result<Option<BTreeSet<String>>> {
+        Ok(self.get_routes().await?.remove(instance))
+    }
+
+    /// Routes the instance to the channels and returns its previous route.
+    pub async fn set_route(
+        &self,
+        instance: &str,
+        channels: BTreeSet<String>,
+    ) -> anyhow::Result<Option<BTreeSet<String>>> {
+        let mut routes = self.get_routes().await?;
+        let previous = routes.insert(instance.to_owned(), channels);
+        self.store_routes(routes).await?;
+        Ok(previous)
+    }
+
+    /// Removes the route of the instance, so it reaches all renderers again, and returns it.
+    pub async fn remove_route(&self, instance: &str) -> anyhow::Result<Option<BTreeSet<String>>> {
+        let mut routes = self.get_routes().await?;
+        let previous = routes.remove(instance);
+        if previous.is_some() {
+            self.store_routes(routes).await?;
+        }
+        Ok(previous)
+    }
+
+    async fn store_routes(&self, routes: Routes) -> anyhow::Result<()> {
+        store_document(&self.routes_path, &routes).await?;
+        *self.routes.write().await = Some(routes);
+        Ok(())
+    }
+}
+
+/// Whether a renderer of the channel receives the data of an instance with the route.
+pub fn is_routed(route: Option<&BTreeSet<String>>, channel: Option<&str>) -> bool {
+    match route {
+        Some(channels) => channel.is_some_and(|channel| channels.contains(channel)),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_route_instances() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let matrix = RoutingMatrix::new(path.clone());
+        assert_eq!(None, matrix.get_route("lowerthird").await.unwrap());
+        assert!(is_routed(None, Some("program-a")));
+        assert!(is_routed(None, None));
+
+        let program_b = BTreeSet::from([String::from("program-b")]);
+        matrix
+            .set_route("lowerthird", BTreeSet::from([String::from("program-a")]))
+            .await
+            .unwrap();
+        let previous = matrix
+            .set_route("lowerthird", program_b.clone())
+            .await
+            .unwrap();
+        assert!(previous.unwrap().contains("program-a"));
+        let route = RoutingMatrix::new(path.clone())
+            .get_route("lowerthird")
+            .await
+            .unwrap();
+        assert_eq!(Some(program_b), route);
+        assert!(is_routed(route.as_ref(), Some("program-b")));
+        assert!(!is_routed(route.as_ref(), Some("program-a")));
+        assert!(!is_routed(route.as_ref(), None));
+
+        assert!(matrix.remove_route("lowerthird").await.unwrap().is_some());
+        assert!(matrix.remove_route("lowerthird").await.unwrap().is_none());
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
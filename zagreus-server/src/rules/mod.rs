@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+
+use crate::data::operation::Operation;
+use crate::state::InstanceState;
+
+pub const RULES_FILE_NAME: &str = "rules.json";
+
+/// Condition on the text of an element. Comparisons only trigger when they become true, so a
+/// rule fires once when e.g. the remaining time drops below a minute and not on every update.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "condition", rename_all = "camelCase")]
+pub enum RuleCondition {
+    Changed,
+    LessThan { value: f64 },
+    GreaterThan { value: f64 },
+    Equals { value: String },
+}
+
+impl RuleCondition {
+    fn is_met(&self, previous: Option<&str>, current: Option<&str>) -> bool {
+        match self {
+            RuleCondition::Changed => current.is_some() && previous != current,
+            _ => self.holds(current) && !self.holds(previous),
+        }
+    }
+
+    fn holds(&self, text: Option<&str>) -> bool {
+        let Some(text) = text else {
+            return false;
+        };
+        let number = || text.trim().parse::<f64>().ok();
+        match self {
+            RuleCondition::Changed => false,
+            RuleCondition::LessThan { value } => number().is_some_and(|number| number < *value),
+            RuleCondition::GreaterThan { value } => number().is_some_and(|number| number > *value),
+            RuleCondition::Equals { value } => text == value,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum RuleAction {
+    #[serde(rename_all = "camelCase")]
+    ExecuteAnimation {
+        animation_sequence: String,
+    },
+    AddClass {
+        id: String,
+        class: String,
+    },
+    RemoveClass {
+        id: String,
+        class: String,
+    },
+    /// Posts the instance, rule, element and text as JSON to the URL.
+    Webhook {
+        url: String,
+    },
+}
+
+/// Executes actions when the text of an element changes or meets a condition, e.g. to show a
+/// goal animation when the score of the home team changes.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub name: String,
+    pub element: String,
+    #[serde(flatten)]
+    pub condition: RuleCondition,
+    pub actions: Vec<RuleAction>,
+}
+
+impl Rule {
+    /// Returns the operations of the actions which manipulate the instance.
+    pub fn get_operations(&self) -> Vec<Operation> {
+        self.actions
+            .iter()
+            .filter_map(|action| match action {
+                RuleAction::ExecuteAnimation { animation_sequence } => {
+                    Some(Operation::ExecuteAnimation {
+                        animation_sequence: animation_sequence.clone(),
+                        execute_at: None,
+                    })
+                }
+                RuleAction::AddClass { id, class } => Some(Operation::AddClass {
+                    id: id.clone(),
+                    class: class.clone(),
+                }),
+                RuleAction::RemoveClass { id, class } => Some(Operation::RemoveClass {
+                    id: id.clone(),
+                    class: class.clone(),
+                }),
+                RuleAction::Webhook { .. } => None,
+            })
+            .collect()
+    }
+
+    pub fn get_webhooks(&self) -> impl Iterator<Item = &str> {
+        self.actions.iter().filter_map(|action| match action {
+            RuleAction::Webhook { url } => Some(url.as_str()),
+            _ => None,
+        })
+    }
+}
+
+/// Rules of an instance, evaluated in order.
+pub type Rules = Vec<Rule>;
+
+/// Returns an error message if a rule is incomplete or its name is not unique.
+pub fn validate_rules(rules: &[Rule]) -> Option<String> {
+    let mut names = HashSet::new();
+    for rule in rules {
+        if rule.name.is_empty() || rule.element.is_empty() {
+            return Some(String::from("Every rule needs a name and an element."));
+        }
+        if !names.insert(&rule.name) {
+            return Some(format!("Rule {} is declared more than once.", rule.name));
+        }
+        if rule.actions.is_empty() {
+            return Some(format!("Rule {} has no actions.", rule.name));
+        }
+        let invalid_url = rule
+            .get_webhooks()
+            .find(|url| reqwest::Url::parse(url).is_err());
+        if let Some(url) = invalid_url {
+            return Some(format!(
+                "Rule {} has an invalid webhook URL: {url}.",
+                rule.name
+            ));
+        }
+    }
+    None
+}
+
+/// Returns the rules whose condition was met by the change from the previous to the current
+/// state.
+pub fn get_triggered_rules<'a>(
+    rules: &'a [Rule],
+    previous: &InstanceState,
+    current: &InstanceState,
+) -> Vec<&'a Rule> {
+    rules
+        .iter()
+        .filter(|rule| {
+            rule.condition.is_met(
+                previous.texts.get(&rule.element).map(String::as_str),
+                current.texts.get(&rule.element).map(String::as_str),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(texts: &[(&str, &str)]) -> InstanceState {
+        InstanceState {
+            texts: texts
+                .iter()
+                .map(|(id, text)| (id.to_string(), text.to_string()))
+                .collect(),
+            ..InstanceState::default()
+        }
+    }
+
+    #[test]
+    fn test_triggered_rules() {
+        let rules: Rules = serde_json::from_str(
+            r#"[
+                {"name":"goal","element":"HomeScore","condition":"changed","actions":[{"action":"executeAnimation","animationSequence":"GoalIn"}]},
+                {"name":"last-minute","element":"TimeRemaining","condition":"lessThan","value":60,"actions":[{"action":"addClass","id":"Clock","class":"warning"}]}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(None, validate_rules(&rules));
+
+        let names = |previous: &InstanceState, current: &InstanceState| {
+            get_triggered_rules(&rules, previous, current)
+                .iter()
+                .map(|rule| rule.name.as_str())
+                .collect::<Vec<_>>()
+        };
+        let kickoff = state(&[("HomeScore", "0"), ("TimeRemaining", "90")]);
+        let goal = state(&[("HomeScore", "1"), ("TimeRemaining", "61")]);
+        let last_minute = state(&[("HomeScore", "1"), ("TimeRemaining", "59")]);
+        let later = state(&[("HomeScore", "1"), ("TimeRemaining", "30")]);
+        assert_eq!(vec!["goal"], names(&kickoff, &goal));
+        assert_eq!(vec!["last-minute"], names(&goal, &last_minute));
+        // comparisons only trigger when they become true
+        assert!(names(&last_minute, &later).is_empty());
+        assert_eq!(
+            vec![Operation::AddClass {
+                id: String::from("Clock"),
+                class: String::from("warning"),
+            }],
+            rules[1].get_operations()
+        );
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let rule = Rule {
+            name: String::from("goal"),
+            element: String::from("HomeScore"),
+            condition: RuleCondition::Changed,
+            actions: vec![RuleAction::Webhook {
+                url: String::from("not a url"),
+            }],
+        };
+        assert!(validate_rules(std::slice::from_ref(&rule)).is_some());
+        let rule = Rule {
+            actions: vec![RuleAction::Webhook {
+                url: String::from("http://automation.local/goal"),
+            }],
+            ..rule
+        };
+        assert_eq!(None, validate_rules(std::slice::from_ref(&rule)));
+        assert!(validate_rules(&[rule.clone(), rule]).is_some());
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+
+/// How to transition from the current page to the next one.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TransitionProfile {
+    /// Animation executed on the current page before the next page is applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_animation: Option<String>,
+    /// Time between the start of the out animation and applying the next page.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Animation executed after the next page was applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_animation: Option<String>,
+}
+
+/// A page of the rundown, whose data is stored in a snapshot.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RundownPage {
+    pub snapshot: String,
+    /// Transition profile used when this page is taken. The default profile is used if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// Ordered pages of an instance which are taken one after another.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Rundown {
+    #[serde(default)]
+    pub pages: Vec<RundownPage>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, TransitionProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    /// Index of the page that is currently on air.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current: Option<usize>,
+}
+
+impl Rundown {
+    /// Returns the transition profile used to take the page. Pages without profile snap to the
+    /// next page if there is no default profile.
+    pub fn get_transition(&self, page: &RundownPage) -> TransitionProfile {
+        page.profile
+            .as_ref()
+            .or(self.default_profile.as_ref())
+            .and_then(|profile| self.profiles.get(profile))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the index of the page after the current one, which is the first page if no page
+    /// was taken yet.
+    pub fn get_next_index(&self) -> Option<usize> {
+        let next = self.current.map_or(0, |current| current + 1);
+        (next < self.pages.len()).then_some(next)
+    }
+}
+
+pub const RUNDOWN_FILE_NAME: &str = "rundown.json";
+
+pub enum TakeResult {
+    /// The page was taken, with the updated rundown.
+    Taken(Rundown),
+    PageNotFound,
+    SnapshotNotFound(String),
+    /// The page was not taken, since its data or animations require approval.
+    RequiresApproval,
+}
+
+/// Returns an error message if the rundown references invalid snapshots or unknown profiles.
+pub fn validate_rundown(rundown: &Rundown) -> Option<String> {
+    if let Some(page) = rundown
+        .pages
+        .iter()
+        .find(|page| !crate::snapshot::is_valid_snapshot_name(&page.snapshot))
+    {
+        return Some(format!("Invalid snapshot name: {}.", page.snapshot));
+    }
+    rundown
+        .pages
+        .iter()
+        .filter_map(|page| page.profile.as_ref())
+        .chain(rundown.default_profile.as_ref())
+        .find(|profile| !rundown.profiles.contains_key(*profile))
+        .map(|profile| format!("Unknown transition profile: {profile}."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions_and_validation() {
+        let fade = TransitionProfile {
+            out_animation: Some(String::from("LowerThirdOut")),
+            delay_ms: 500,
+            in_animation: Some(String::from("LowerThirdIn")),
+        };
+        let mut rundown = Rundown {
+            pages: vec![
+                RundownPage {
+                    snapshot: String::from("speaker-1"),
+                    profile: None,
+                },
+                RundownPage {
+                    snapshot: String::from("speaker-2"),
+                    profile: Some(String::from("cut")),
+                },
+            ],
+            profiles: BTreeMap::from([
+                (String::from("fade"), fade.clone()),
+                (String::from("cut"), TransitionProfile::default()),
+            ]),
+            default_profile: Some(String::from("fade")),
+            current: None,
+        };
+        assert!(validate_rundown(&rundown).is_none());
+        assert_eq!(fade, rundown.get_transition(&rundown.pages[0]));
+        assert_eq!(
+            TransitionProfile::default(),
+            rundown.get_transition(&rundown.pages[1])
+        );
+
+        assert_eq!(Some(0), rundown.get_next_index());
+        rundown.current = Some(1);
+        assert_eq!(None, rundown.get_next_index());
+
+        rundown.default_profile = Some(String::from("wipe"));
+        assert!(validate_rundown(&rundown).is_some());
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::{Mutex, Notify};
+
+use crate::controller::ServerController;
+use crate::data::operation::Operation;
+use crate::store::{load_document, store_document};
+
+const SCHEDULE_FILE_NAME: &str = "schedule.json";
+/// Maximum time the scheduler sleeps without checking the schedule, e.g. after the clock of the
+/// machine was adjusted.
+const MAX_SCHEDULER_SLEEP: Duration = Duration::from_secs(1);
+/// Items which are overdue by more than this (e.g. because the server was not running) are
+/// discarded instead of being executed late.
+const MAX_LATENESS_SECONDS: i64 = 60;
+
+/// Request to schedule operations at a wall-clock time or after a delay.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleRequest {
+    pub instance: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+    pub operations: Vec<Operation>,
+}
+
+impl ScheduleRequest {
+    /// Returns the time at which the operations are executed, or an error message if the request
+    /// is invalid.
+    pub fn get_due(&self, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+        if self.operations.is_empty() {
+            return Err(String::from("The scheduled item contains no operations."));
+        }
+        match (self.at, self.delay_ms) {
+            (Some(at), None) => Ok(at),
+            (None, Some(delay_ms)) => Ok(now + chrono::Duration::milliseconds(delay_ms as i64)),
+            _ => Err(String::from(
+                "Either a time (at) or a delay (delayMs) is required.",
+            )),
+        }
+    }
+}
+
+/// Operations which are executed on an instance at a given time, e.g. an animation at the start
+/// of a segment.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledItem {
+    pub id: u64,
+    pub instance: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub at: DateTime<Utc>,
+    pub operations: Vec<Operation>,
+    pub created: DateTime<Utc>,
+}
+
+impl ScheduledItem {
+    fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.at <= now
+    }
+}
+
+/// Returns whether an item due at the given time is overdue for too long to be executed.
+pub fn is_missed(at: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now - at > chrono::Duration::seconds(MAX_LATENESS_SECONDS)
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Schedule {
+    last_id: u64,
+    items: BTreeMap<u64, ScheduledItem>,
+}
+
+/// Items scheduled on any instance, stored in the data folder so they survive restarts.
+pub struct Scheduler {
+    schedule_path: PathBuf,
+    schedule: Mutex<Option<Schedule>>,
+    changed: Notify,
+}
+
+impl Scheduler {
+    pub fn new(data_folder: PathBuf) -> Scheduler {
+        Scheduler {
+            schedule_path: data_folder.join(SCHEDULE_FILE_NAME),
+            schedule: Mutex::new(None),
+            changed: Notify::new(),
+        }
+    }
+
+    async fn load<'a>(
+        &self,
+        schedule: &'a mut Option<Schedule>,
+    ) -> anyhow::Result<&'a mut Schedule> {
+        if schedule.is_none() {
+            *schedule = Some(load_document(&self.schedule_path).await?);
+        }
+        Ok(schedule.get_or_insert_with(Schedule::default))
+    }
+
+    /// Returns the scheduled items, optionally of a single instance, in the order they are due.
+    pub async fn get_items(&self, instance: Option<&str>) -> anyhow::Result<Vec<ScheduledItem>> {
+        let mut schedule = self.schedule.lock().await;
+        let mut items: Vec<ScheduledItem> = self
+            .load(&mut schedule)
+            .await?
+            .items
+            .values()
+            .filter(|item| instance.is_none_or(|instance| item.instance == instance))
+            .cloned()
+            .collect();
+        items.sort_by_key(|item| (item.at, item.id));
+        Ok(items)
+    }
+
+    pub async fn get_item(&self, id: u64) -> anyhow::Result<Option<ScheduledItem>> {
+        let mut schedule = self.schedule.lock().await;
+        Ok(self.load(&mut schedule).await?.items.get(&id).cloned())
+    }
+
+    pub async fn add(
+        &self,
+        request: ScheduleRequest,
+        at: DateTime<Utc>,
+    ) -> anyhow::Result<ScheduledItem> {
+        let mut schedule = self.schedule.lock().await;
+        let schedule = self.load(&mut schedule).await?;
+        let item = ScheduledItem {
+            id: schedule.last_id + 1,
+            instance: request.instance,
+            name: request.name,
+            at,
+            operations: request.operations,
+            created: Utc::now(),
+        };
+        schedule.last_id = item.id;
+        schedule.items.insert(item.id, item.clone());
+        self.store(schedule).await?;
+        Ok(item)
+    }
+
+    /// Replaces the item, keeping its id. Returns `None` if there is no such item, e.g. because it
+    /// was executed already.
+    pub async fn replace(
+        &self,
+        id: u64,
+        request: ScheduleRequest,
+        at: DateTime<Utc>,
+    ) -> anyhow::Result<Option<ScheduledItem>> {
+        let mut schedule = self.schedule.lock().await;
+        let schedule = self.load(&mut schedule).await?;
+        let Some(item) = schedule.items.get_mut(&id) else {
+            return Ok(None);
+        };
+        item.instance = request.instance;
+        item.name = request.name;
+        item.at = at;
+        item.operations = request.operations;
+        let item = item.clone();
+        self.store(schedule).await?;
+        Ok(Some(item))
+    }
+
+    pub async fn remove(&self, id: u64) -> anyhow::Result<Option<ScheduledItem>> {
+        let mut schedule = self.schedule.lock().await;
+        let schedule = self.load(&mut schedule).await?;
+        let item = schedule.items.remove(&id);
+        if item.is_some() {
+            self.store(schedule).await?;
+        }
+        Ok(item)
+    }
+
+    /// Removes the items of a deleted instance.
+    pub async fn remove_instance(&self, instance: &str) -> anyhow::Result<()> {
+        let mut schedule = self.schedule.lock().await;
+        let schedule = self.load(&mut schedule).await?;
+        let count = schedule.items.len();
+        schedule.items.retain(|_, item| item.instance != instance);
+        if schedule.items.len() != count {
+            self.store(schedule).await?;
+        }
+        Ok(())
+    }
+
+    /// Removes and returns the items which are due, in the order they are due.
+    pub async fn take_due(&self, now: DateTime<Utc>) -> anyhow::Result<Vec<ScheduledItem>> {
+        let mut schedule = self.schedule.lock().await;
+        let schedule = self.load(&mut schedule).await?;
+        let (mut due, pending): (Vec<ScheduledItem>, Vec<ScheduledItem>) =
+            std::mem::take(&mut schedule.items)
+                .into_values()
+                .partition(|item| item.is_due(now));
+        schedule.items = pending.into_iter().map(|item| (item.id, item)).collect();
+        if !due.is_empty() {
+            self.store(schedule).await?;
+        }
+        due.sort_by_key(|item| (item.at, item.id));
+        Ok(due)
+    }
+
+    /// Returns the time at which the next item is due.
+    pub async fn get_next_due(&self) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let mut schedule = self.schedule.lock().await;
+        Ok(self
+            .load(&mut schedule)
+            .await?
+            .items
+            .values()
+            .map(|item| item.at)
+            .min())
+    }
+
+    /// Waits until the schedule changed or the timeout elapsed.
+    pub async fn wait_for_change(&self, timeout: Duration) {
+        let _ = tokio::time::timeout(timeout, self.changed.notified()).await;
+    }
+
+    async fn store(&self, schedule: &Schedule) -> anyhow::Result<()> {
+        store_document(&self.schedule_path, schedule).await?;
+        self.changed.notify_one();
+        Ok(())
+    }
+}
+
+/// Executes the scheduled items when they are due. Items which were missed by more than a minute
+/// (e.g. while the server was not running) are discarded, so graphics do not go on air late.
+pub async fn run_scheduler(controller: Arc<ServerController>) {
+    loop {
+        let now = Utc::now();
+        match controller.take_due_scheduled_items(now).await {
+            Ok(items) => {
+                for item in items {
+                    if is_missed(item.at, now) {
+                        warn!(
+                            "Discarding scheduled item {} of instance {} which was due at {}.",
+                            item.id, item.instance, item.at
+                        );
+                        continue;
+                    }
+                    controller.execute_scheduled_item(item).await;
+                }
+            }
+            Err(err) => error!("Could not load schedule: {:#}.", err),
+        }
+
+        let sleep = match controller.get_next_scheduled_item_due().await {
+            Ok(Some(at)) => (at - Utc::now())
+                .to_std()
+                .unwrap_or_default()
+                .min(MAX_SCHEDULER_SLEEP),
+            _ => MAX_SCHEDULER_SLEEP,
+        };
+        controller.wait_for_schedule_change(sleep).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(at: &str) -> ScheduleRequest {
+        ScheduleRequest {
+            instance: String::from("scoreboard"),
+            name: None,
+            at: Some(at.parse().unwrap()),
+            delay_ms: None,
+            operations: vec![Operation::ExecuteAnimation {
+                animation_sequence: String::from("ScoreboardShow"),
+                execute_at: None,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_due_items_are_taken_in_order() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let scheduler = Scheduler::new(path.clone());
+        let now: DateTime<Utc> = "2024-05-01T18:00:00Z".parse().unwrap();
+        let later = request("2024-05-01T18:30:00Z");
+        let later_at = later.get_due(now).unwrap();
+        let later = scheduler.add(later, later_at).await.unwrap();
+        let first = request("2024-05-01T17:59:00Z");
+        let first_at = first.get_due(now).unwrap();
+        let first = scheduler.add(first, first_at).await.unwrap();
+        assert_eq!(2, first.id);
+
+        // the schedule survives restarts
+        let scheduler = Scheduler::new(path.clone());
+        assert_eq!(
+            vec![first.clone(), later.clone()],
+            scheduler.get_items(Some("scoreboard")).await.unwrap()
+        );
+        assert_eq!(vec![first], scheduler.take_due(now).await.unwrap());
+        assert_eq!(Some(later.at), scheduler.get_next_due().await.unwrap());
+        assert!(scheduler.take_due(now).await.unwrap().is_empty());
+        assert!(!is_missed(
+            later.at,
+            later.at + chrono::Duration::seconds(60)
+        ));
+        assert!(is_missed(
+            later.at,
+            later.at + chrono::Duration::seconds(61)
+        ));
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[test]
+    fn test_time_or_delay_is_required() {
+        let now: DateTime<Utc> = "2024-05-01T18:00:00Z".parse().unwrap();
+        let mut delayed = request("2024-05-01T18:00:00Z");
+        delayed.at = None;
+        delayed.delay_ms = Some(1500);
+        assert_eq!(
+            Ok("2024-05-01T18:00:01.500Z".parse().unwrap()),
+            delayed.get_due(now)
+        );
+        delayed.delay_ms = None;
+        assert!(delayed.get_due(now).is_err());
+    }
+}
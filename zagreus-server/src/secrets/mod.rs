@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::store::InstanceDocumentStore;
+
+/// Encrypted secrets of an instance, mapping the secret name to the base64 encoded nonce and
+/// ciphertext.
+type EncryptedSecrets = BTreeMap<String, String>;
+type HmacSha256 = Hmac<Sha256>;
+
+const SECRETS_FILE_NAME: &str = "secrets.json";
+const KEY_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 12;
+
+/// Stores secrets (e.g. API keys of data feeds) per instance. Secrets are encrypted at rest with
+/// a key that is kept outside of the data folder and are never returned by the API. Integrations
+/// reference them by name with `secret:<name>`. The same key signs data handed out by the server
+/// (e.g. share links), with a separate subkey per purpose.
+pub struct SecretStore {
+    key_file_path: PathBuf,
+    key: OnceCell<Key>,
+    secrets: InstanceDocumentStore<EncryptedSecrets>,
+    /// Serializes changes, so concurrent changes do not overwrite each other.
+    update_lock: Mutex<()>,
+}
+
+impl SecretStore {
+    pub fn new(data_folder: PathBuf, key_file_path: PathBuf) -> SecretStore {
+        SecretStore {
+            key_file_path,
+            key: OnceCell::new(),
+            secrets: InstanceDocumentStore::new(data_folder, SECRETS_FILE_NAME),
+            update_lock: Mutex::new(()),
+        }
+    }
+
+    pub async fn get_secret_names(&self, instance: &str) -> anyhow::Result<Vec<String>> {
+        Ok(self.secrets.get(instance).await?.into_keys().collect())
+    }
+
+    pub async fn set_secret(&self, instance: &str, name: &str, value: &str) -> anyhow::Result<()> {
+        if !is_valid_secret_name(name) {
+            return Err(anyhow!("Invalid secret name: {}", name));
+        }
+        let cipher = self.get_cipher().await?;
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, value.as_bytes())
+            .map_err(|err| anyhow!("Could not encrypt secret {}: {}", name, err))?;
+        let mut encrypted = nonce.to_vec();
+        encrypted.extend(ciphertext);
+
+        let _update_guard = self.update_lock.lock().await;
+        let mut secrets = self.secrets.get(instance).await?;
+        secrets.insert(name.to_owned(), BASE64.encode(encrypted));
+        self.secrets.set(instance, secrets).await
+    }
+
+    /// Returns whether the secret existed.
+    pub async fn delete_secret(&self, instance: &str, name: &str) -> anyhow::Result<bool> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut secrets = self.secrets.get(instance).await?;
+        if secrets.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.secrets.set(instance, secrets).await?;
+        Ok(true)
+    }
+
+    pub async fn get_secret(&self, instance: &str, name: &str) -> anyhow::Result<Option<String>> {
+        let secrets = self.secrets.get(instance).await?;
+        let encrypted = match secrets.get(name) {
+            Some(encrypted) => BASE64.decode(encrypted)?,
+            None => return Ok(None),
+        };
+        if encrypted.len() < NONCE_LENGTH {
+            return Err(anyhow!("Secret {} is corrupt", name));
+        }
+
+        let (nonce, ciphertext) = encrypted.split_at(NONCE_LENGTH);
+        let cipher = self.get_cipher().await?;
+        let value = cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| anyhow!("Could not decrypt secret {}", name))?;
+        Ok(Some(String::from_utf8(value)?))
+    }
+
+    /// Returns the HMAC-SHA256 signature of the data.
+    pub async fn sign(&self, purpose: &str, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let mut mac = self.get_signer(purpose).await?;
+        mac.update(data);
+        Ok(mac.finalize().into_bytes().to_vec())
+    }
+
+    /// Returns whether the signature of the data is valid, comparing in constant time.
+    pub async fn verify(
+        &self,
+        purpose: &str,
+        data: &[u8],
+        signature: &[u8],
+    ) -> anyhow::Result<bool> {
+        let mut mac = self.get_signer(purpose).await?;
+        mac.update(data);
+        Ok(mac.verify_slice(signature).is_ok())
+    }
+
+    async fn get_signer(&self, purpose: &str) -> anyhow::Result<HmacSha256> {
+        let mut subkey = <HmacSha256 as Mac>::new_from_slice(self.get_key().await?)?;
+        subkey.update(purpose.as_bytes());
+        Ok(<HmacSha256 as Mac>::new_from_slice(
+            &subkey.finalize().into_bytes(),
+        )?)
+    }
+
+    async fn get_cipher(&self) -> anyhow::Result<ChaCha20Poly1305> {
+        Ok(ChaCha20Poly1305::new(self.get_key().await?))
+    }
+
+    async fn get_key(&self) -> anyhow::Result<&Key> {
+        self.key
+            .get_or_try_init(|| load_or_create_key(&self.key_file_path))
+            .await
+    }
+}
+
+pub fn is_valid_secret_name(name: &str) -> bool {
+    crate::fs::is_valid_name(name)
+}
+
+async fn load_or_create_key(key_file_path: &PathBuf) -> anyhow::Result<Key> {
+    if key_file_path.exists() {
+        let key = tokio::fs::read(key_file_path)
+            .await
+            .with_context(|| format!("Could not read secret key {key_file_path:?}"))?;
+        if key.len() != KEY_LENGTH {
+            return Err(anyhow!("Secret key {:?} is invalid", key_file_path));
+        }
+        return Ok(*Key::from_slice(&key));
+    }
+
+    info!("Creating new secret key at {:?}.", key_file_path);
+    let key = ChaCha20Poly1305::generate_key(&mut OsRng);
+    if let Some(parent) = key_file_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let written_path = key_file_path.clone();
+    tokio::task::spawn_blocking(move || {
+        crate::fs::write_private_file(&written_path, key.as_slice())
+    })
+    .await?
+    .with_context(|| format!("Could not write secret key {key_file_path:?}"))?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_secrets_are_encrypted_at_rest() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let key_file_path = path.join("secret.key");
+        let store = SecretStore::new(path.clone(), key_file_path.clone());
+        store
+            .set_secret("scoreboard", "feedApiKey", "abc123")
+            .await
+            .unwrap();
+
+        let stored = std::fs::read_to_string(
+            crate::fs::get_instance_folder(&path, "scoreboard")
+                .unwrap()
+                .join(SECRETS_FILE_NAME),
+        )
+        .unwrap();
+        assert!(stored.contains("feedApiKey"));
+        assert!(!stored.contains("abc123"));
+
+        let reloaded_store = SecretStore::new(path.clone(), key_file_path);
+        assert_eq!(
+            Some(String::from("abc123")),
+            reloaded_store
+                .get_secret("scoreboard", "feedApiKey")
+                .await
+                .unwrap()
+        );
+        assert_eq!(
+            vec![String::from("feedApiKey")],
+            reloaded_store.get_secret_names("scoreboard").await.unwrap()
+        );
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
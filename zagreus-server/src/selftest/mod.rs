@@ -0,0 +1,207 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use sha2::{Digest, Sha256};
+
+use crate::config::ZagreusServerConfig;
+use crate::controller::ServerController;
+
+const PROBE_FILE_NAME: &str = ".zagreus-selftest";
+const RUNTIME_FILE_NAME: &str = "zagreus-runtime.js";
+
+#[derive(Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+    pub passed: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+impl CheckResult {
+    fn from_result(name: impl Into<String>, result: anyhow::Result<String>) -> CheckResult {
+        match result {
+            Ok(message) => CheckResult {
+                name: name.into(),
+                status: CheckStatus::Passed,
+                message,
+            },
+            Err(err) => CheckResult {
+                name: name.into(),
+                status: CheckStatus::Failed,
+                message: format!("{err:#}"),
+            },
+        }
+    }
+
+    fn skipped(name: impl Into<String>, message: impl Into<String>) -> CheckResult {
+        CheckResult {
+            name: name.into(),
+            status: CheckStatus::Skipped,
+            message: message.into(),
+        }
+    }
+}
+
+/// Runs all self-test checks. Renderer connectivity can only be checked when the server is
+/// running, i.e. when a controller is passed.
+pub async fn run_self_test(
+    configuration: &ZagreusServerConfig,
+    controller: Option<&ServerController>,
+) -> SelfTestReport {
+    let data_folder = configuration.data_folder.clone();
+    let data_folder_result =
+        run_blocking_check(controller, move || check_data_folder_writable(&data_folder)).await;
+    let data_folder = configuration.data_folder.clone();
+    let data_version_result = run_blocking_check(controller, move || {
+        crate::migration::check_data_folder_version(&data_folder)
+    })
+    .await;
+    let runtime_result = run_blocking_check(controller, check_runtime_available).await;
+    let assets_folder = configuration
+        .data_folder
+        .join(crate::fs::ASSETS_SUBFOLDER_NAME);
+    let assets_result = run_blocking_check(controller, move || check_assets(&assets_folder)).await;
+
+    let mut checks = vec![
+        CheckResult::from_result("data-folder", data_folder_result),
+        CheckResult::from_result("data-version", data_version_result),
+        CheckResult::from_result("runtime", runtime_result),
+        CheckResult::from_result("assets", assets_result),
+    ];
+
+    for instance in &configuration.self_test.expected_instances {
+        let name = format!("renderer:{instance}");
+        match controller {
+            Some(controller) => {
+                let client_count = controller.get_instance_client_count(instance).await;
+                let result = if client_count > 0 {
+                    Ok(format!("{client_count} renderer client(s) connected."))
+                } else {
+                    Err(anyhow!("No renderer client is connected."))
+                };
+                checks.push(CheckResult::from_result(name, result));
+            }
+            None => checks.push(CheckResult::skipped(
+                name,
+                "Renderer connectivity can only be checked on a running server.",
+            )),
+        }
+    }
+
+    SelfTestReport {
+        passed: checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Failed),
+        checks,
+    }
+}
+
+/// Checks which access the file system are run on the worker pool of the controller (or a
+/// blocking thread without a running server), so they do not block the runtime.
+async fn run_blocking_check<F>(
+    controller: Option<&ServerController>,
+    check: F,
+) -> anyhow::Result<String>
+where
+    F: FnOnce() -> anyhow::Result<String> + Send + 'static,
+{
+    match controller {
+        Some(controller) => controller
+            .run_blocking(check)
+            .await
+            .and_then(|result| result),
+        None => tokio::task::spawn_blocking(check)
+            .await
+            .unwrap_or_else(|err| Err(anyhow!("Check did not complete: {}", err))),
+    }
+}
+
+fn check_data_folder_writable(data_folder: &Path) -> anyhow::Result<String> {
+    let probe_file_path = data_folder.join(PROBE_FILE_NAME);
+    std::fs::write(&probe_file_path, b"zagreus")
+        .with_context(|| format!("Data folder {data_folder:?} is not writable"))?;
+    std::fs::remove_file(&probe_file_path)?;
+    Ok(format!("Data folder {data_folder:?} is writable."))
+}
+
+fn check_runtime_available() -> anyhow::Result<String> {
+    let runtime_path = PathBuf::from(RUNTIME_FILE_NAME);
+    if runtime_path.is_file() {
+        Ok(String::from("Runtime bundle is available."))
+    } else {
+        Err(anyhow!(
+            "Runtime bundle {} was not found in the working directory.",
+            RUNTIME_FILE_NAME
+        ))
+    }
+}
+
+/// Assets are stored under the hash of their content, so every asset can be verified to be
+/// readable and intact.
+fn check_assets(assets_folder: &Path) -> anyhow::Result<String> {
+    if !assets_folder.exists() {
+        return Ok(String::from("No assets have been uploaded."));
+    }
+
+    let mut asset_count = 0;
+    let mut corrupt_assets = Vec::new();
+    for entry in std::fs::read_dir(assets_folder)? {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        asset_count += 1;
+        let data = std::fs::read(&path).with_context(|| format!("Could not read {path:?}"))?;
+        let hash = format!("{:x}", Sha256::digest(&data));
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        if stem != hash {
+            corrupt_assets.push(path.file_name().unwrap_or_default().to_owned());
+        }
+    }
+
+    if corrupt_assets.is_empty() {
+        Ok(format!("All {asset_count} assets are intact."))
+    } else {
+        Err(anyhow!(
+            "Assets do not match their content hash: {:?}",
+            corrupt_assets
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_assets() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let data = b"asset content";
+        let hash = format!("{:x}", Sha256::digest(data));
+        std::fs::write(path.join(format!("{hash}.png")), data).unwrap();
+        assert!(check_assets(&path).is_ok());
+
+        std::fs::write(path.join("sponsor.png"), data).unwrap();
+        assert!(check_assets(&path).is_err());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+#[cfg(windows)]
+mod windows;
+
+#[cfg(windows)]
+pub use self::windows::{install_service, run_as_service};
+
+/// Environment variables set by systemd for services of `Type=notify`.
+#[cfg(target_os = "linux")]
+const NOTIFY_SOCKET_VARIABLE: &str = "NOTIFY_SOCKET";
+const WATCHDOG_USEC_VARIABLE: &str = "WATCHDOG_USEC";
+const WATCHDOG_PID_VARIABLE: &str = "WATCHDOG_PID";
+
+#[cfg(not(windows))]
+pub fn install_service(_arguments: Vec<std::ffi::OsString>) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!(
+        "Services can only be installed on Windows, use a systemd unit on Linux"
+    ))
+}
+
+#[cfg(not(windows))]
+pub fn run_as_service(_configuration: crate::config::ZagreusServerConfig) -> anyhow::Result<()> {
+    Err(anyhow::anyhow!(
+        "Running as service is only supported on Windows"
+    ))
+}
+
+/// Notifies systemd about the state of the server (e.g. `READY=1`) if it was started as a
+/// service of `Type=notify`. Does nothing otherwise.
+pub fn notify(state: &str) {
+    #[cfg(target_os = "linux")]
+    if let Some(socket) = std::env::var_os(NOTIFY_SOCKET_VARIABLE) {
+        if let Err(err) = send_notification(&socket.to_string_lossy(), state) {
+            warn!("Could not notify service manager: {}.", err);
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = state;
+}
+
+#[cfg(target_os = "linux")]
+fn send_notification(socket: &str, state: &str) -> std::io::Result<()> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    // sockets starting with @ are in the abstract namespace
+    let address = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes())?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+/// Sends keep-alive notifications to the systemd watchdog from the async runtime. If the runtime
+/// wedges, the notifications stop and systemd restarts the server.
+pub async fn run_watchdog() {
+    let interval = parse_watchdog_interval(
+        std::env::var(WATCHDOG_USEC_VARIABLE).ok().as_deref(),
+        std::env::var(WATCHDOG_PID_VARIABLE).ok().as_deref(),
+        std::process::id(),
+    );
+    let Some(interval) = interval else {
+        return;
+    };
+    // notify twice per interval, as recommended by systemd
+    let mut ticker = tokio::time::interval(interval / 2);
+    info!(
+        "Sending watchdog notifications every {} ms.",
+        (interval / 2).as_millis()
+    );
+    loop {
+        ticker.tick().await;
+        notify("WATCHDOG=1");
+    }
+}
+
+/// Returns the watchdog interval if the watchdog is enabled for this process.
+fn parse_watchdog_interval(
+    usec: Option<&str>,
+    pid: Option<&str>,
+    process_id: u32,
+) -> Option<Duration> {
+    if pid.is_some_and(|pid| pid.parse::<u32>().ok() != Some(process_id)) {
+        return None;
+    }
+    usec.and_then(|usec| usec.parse::<u64>().ok())
+        .filter(|usec| *usec > 0)
+        .map(Duration::from_micros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_watchdog_interval() {
+        assert_eq!(
+            Some(Duration::from_secs(30)),
+            parse_watchdog_interval(Some("30000000"), None, 42)
+        );
+        assert_eq!(
+            Some(Duration::from_secs(30)),
+            parse_watchdog_interval(Some("30000000"), Some("42"), 42)
+        );
+        // the watchdog is meant for another process
+        assert_eq!(
+            None,
+            parse_watchdog_interval(Some("30000000"), Some("7"), 42)
+        );
+        assert_eq!(None, parse_watchdog_interval(Some("0"), None, 42));
+        assert_eq!(None, parse_watchdog_interval(None, None, 42));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_notifications_are_sent_to_socket() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let socket_path = path.join("notify.sock");
+        let receiver = std::os::unix::net::UnixDatagram::bind(&socket_path).unwrap();
+
+        send_notification(&socket_path.to_string_lossy(), "READY=1").unwrap();
+        let mut buffer = [0; 16];
+        let length = receiver.recv(&mut buffer).unwrap();
+        assert_eq!(b"READY=1", &buffer[..length]);
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
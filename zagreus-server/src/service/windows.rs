@@ -0,0 +1,120 @@
+use std::ffi::OsString;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use windows_service::service::{
+    ServiceAccess, ServiceAction, ServiceActionType, ServiceControl, ServiceControlAccept,
+    ServiceErrorControl, ServiceExitCode, ServiceFailureActions, ServiceFailureResetPeriod,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+
+use crate::config::ZagreusServerConfig;
+
+const SERVICE_NAME: &str = "zagreus-server";
+const SERVICE_DISPLAY_NAME: &str = "Zagreus Server";
+const SERVICE_DESCRIPTION: &str = "Management and playout server for Zagreus templates.";
+const SERVICE_TYPE: ServiceType = ServiceType::OWN_PROCESS;
+const RUN_AS_SERVICE_ARGUMENT: &str = "--run-as-service";
+/// Delay before the service is restarted after it failed.
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+/// Period without failures after which the failure count is reset.
+const FAILURE_RESET_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Configuration and runtime of the server, which the service main function runs on.
+static SERVICE_CONTEXT: OnceLock<(ZagreusServerConfig, tokio::runtime::Handle)> = OnceLock::new();
+
+/// Installs the server as Windows service which starts automatically and is restarted if it
+/// fails. The arguments are passed to the server when the service starts.
+pub fn install_service(arguments: Vec<OsString>) -> anyhow::Result<()> {
+    let service_manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )?;
+    let mut launch_arguments = vec![OsString::from(RUN_AS_SERVICE_ARGUMENT)];
+    launch_arguments.extend(arguments);
+    let service_info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(SERVICE_DISPLAY_NAME),
+        service_type: SERVICE_TYPE,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments,
+        dependencies: Vec::new(),
+        // run as LocalSystem
+        account_name: None,
+        account_password: None,
+    };
+    let service = service_manager.create_service(
+        &service_info,
+        ServiceAccess::CHANGE_CONFIG | ServiceAccess::START,
+    )?;
+    service.set_description(SERVICE_DESCRIPTION)?;
+    service.update_failure_actions(ServiceFailureActions {
+        reset_period: ServiceFailureResetPeriod::After(FAILURE_RESET_PERIOD),
+        reboot_msg: None,
+        command: None,
+        actions: Some(vec![ServiceAction {
+            action_type: ServiceActionType::Restart,
+            delay: RESTART_DELAY,
+        }]),
+    })?;
+    service.set_failure_actions_on_non_crash_failures(true)?;
+    info!("Installed service {}.", SERVICE_NAME);
+    Ok(())
+}
+
+/// Runs the server under the control of the Windows service manager until the service is stopped.
+pub fn run_as_service(configuration: ZagreusServerConfig) -> anyhow::Result<()> {
+    let context = (configuration, tokio::runtime::Handle::current());
+    if SERVICE_CONTEXT.set(context).is_err() {
+        return Err(anyhow::anyhow!("Service is already running"));
+    }
+    // blocks until the service is stopped, the server runs on the runtime of this process
+    tokio::task::block_in_place(|| service_dispatcher::start(SERVICE_NAME, ffi_service_main))?;
+    Ok(())
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(err) = run_service() {
+        error!("Could not run service: {}.", err);
+    }
+}
+
+fn run_service() -> windows_service::Result<()> {
+    let status_handle = service_control_handler::register(SERVICE_NAME, |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            crate::handover::request_shutdown();
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    })?;
+    let set_state = |current_state: ServiceState| {
+        let controls_accepted = if current_state == ServiceState::Running {
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+        } else {
+            ServiceControlAccept::empty()
+        };
+        status_handle.set_service_status(ServiceStatus {
+            service_type: SERVICE_TYPE,
+            current_state,
+            controls_accepted,
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            wait_hint: Duration::default(),
+            process_id: None,
+        })
+    };
+
+    set_state(ServiceState::Running)?;
+    if let Some((configuration, runtime)) = SERVICE_CONTEXT.get() {
+        runtime.block_on(crate::start_with_config(configuration.clone()));
+    }
+    set_state(ServiceState::Stopped)
+}
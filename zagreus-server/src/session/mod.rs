@@ -0,0 +1,392 @@
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::controller::ServerController;
+use crate::events::InstanceEvent;
+use crate::websocket::message::InstanceMessage;
+use zagreus_client::dto::{ControlAcknowledgement, ControlResult};
+
+const SESSIONS_FOLDER_NAME: &str = "sessions";
+const EVENT_POLL_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum EntryCategory {
+    /// The recording of the session started or stopped.
+    Session,
+    /// Operations which were sent to renderers.
+    Command,
+    Event,
+    /// A command of a controller was executed.
+    Ack,
+    /// A renderer reported an error or a command of a controller failed.
+    Error,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionEntry {
+    pub timestamp: DateTime<Utc>,
+    pub category: EntryCategory,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+    /// Short description for the timeline, e.g. the operation and its recipients.
+    pub summary: String,
+    pub details: serde_json::Value,
+}
+
+impl SessionEntry {
+    fn new(
+        category: EntryCategory,
+        instance: Option<&str>,
+        summary: String,
+        details: serde_json::Value,
+    ) -> SessionEntry {
+        SessionEntry {
+            timestamp: Utc::now(),
+            category,
+            instance: instance.map(str::to_owned),
+            summary,
+            details,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveSession {
+    pub name: String,
+    pub started: DateTime<Utc>,
+    /// Entries recorded since the session started.
+    pub entries: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionTimeline {
+    pub name: String,
+    pub entries: Vec<SessionEntry>,
+}
+
+/// Records everything that happens during a show (the commands sent to the renderers, the events
+/// and errors of the instances and the acknowledged commands of controllers) into
+/// `sessions/<name>.jsonl`, so production gets a complete as-run record and bugs can be
+/// reproduced from the timeline. Entries are appended as they happen, so a recording survives a
+/// crash of the server and is continued by starting a session with the same name.
+pub struct SessionRecorder {
+    sessions_folder: PathBuf,
+    /// The session being recorded, locked while appending so entries keep their order.
+    active: Mutex<Option<ActiveSession>>,
+}
+
+impl SessionRecorder {
+    pub fn new(data_folder: PathBuf) -> SessionRecorder {
+        SessionRecorder {
+            sessions_folder: data_folder.join(SESSIONS_FOLDER_NAME),
+            active: Mutex::new(None),
+        }
+    }
+
+    /// Starts recording the session, or returns `None` if another session is being recorded.
+    pub async fn start(&self, name: &str) -> anyhow::Result<Option<ActiveSession>> {
+        let mut active = self.active.lock().await;
+        if active.is_some() {
+            return Ok(None);
+        }
+        tokio::fs::create_dir_all(&self.sessions_folder).await?;
+        let mut session = ActiveSession {
+            name: name.to_owned(),
+            started: Utc::now(),
+            entries: 0,
+        };
+        let entry = SessionEntry::new(
+            EntryCategory::Session,
+            None,
+            format!("Session {name} started"),
+            serde_json::Value::Null,
+        );
+        self.append(&mut session, &entry).await?;
+        *active = Some(session.clone());
+        Ok(Some(session))
+    }
+
+    /// Stops recording and returns the session, `None` if no session is being recorded.
+    pub async fn stop(&self) -> anyhow::Result<Option<ActiveSession>> {
+        let mut active = self.active.lock().await;
+        let Some(mut session) = active.take() else {
+            return Ok(None);
+        };
+        let entry = SessionEntry::new(
+            EntryCategory::Session,
+            None,
+            format!("Session {} stopped", session.name),
+            serde_json::Value::Null,
+        );
+        self.append(&mut session, &entry).await?;
+        Ok(Some(session))
+    }
+
+    pub async fn get_active(&self) -> Option<ActiveSession> {
+        self.active.lock().await.clone()
+    }
+
+    pub async fn is_recording(&self) -> bool {
+        self.active.lock().await.is_some()
+    }
+
+    /// Appends the entry to the session being recorded, if any.
+    pub async fn record(&self, entry: SessionEntry) {
+        let mut active = self.active.lock().await;
+        let Some(session) = active.as_mut() else {
+            return;
+        };
+        if let Err(err) = self.append(session, &entry).await {
+            error!(
+                "Could not record entry of session {}: {}.",
+                session.name, err
+            );
+        }
+    }
+
+    async fn append(
+        &self,
+        session: &mut ActiveSession,
+        entry: &SessionEntry,
+    ) -> anyhow::Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.get_session_path(&session.name))
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        session.entries += 1;
+        Ok(())
+    }
+
+    /// Returns the recorded entries of the session, `None` if it was never recorded.
+    pub async fn get_timeline(&self, name: &str) -> anyhow::Result<Option<SessionTimeline>> {
+        let path = self.get_session_path(name);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = tokio::fs::read_to_string(path).await?;
+        Ok(Some(SessionTimeline {
+            name: name.to_owned(),
+            // a line cut off by a crash is skipped
+            entries: contents
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+        }))
+    }
+
+    fn get_session_path(&self, name: &str) -> PathBuf {
+        self.sessions_folder.join(format!("{name}.jsonl"))
+    }
+}
+
+/// Returns the entry of a message sent to the renderers of the instance, which are described by
+/// `recipients` if not all of them received it.
+pub fn get_command_entry(
+    instance: &str,
+    message: &InstanceMessage<'_>,
+    recipients: Option<String>,
+) -> anyhow::Result<SessionEntry> {
+    let (operation, payload) = message.to_operation()?;
+    let summary = match recipients {
+        Some(recipients) => format!("{operation} to {recipients}"),
+        None => operation.clone(),
+    };
+    let details = json!({ "operation": operation, "payload": payload });
+    Ok(SessionEntry::new(
+        EntryCategory::Command,
+        Some(instance),
+        summary,
+        details,
+    ))
+}
+
+/// Returns the entry of the acknowledgement of a command of a controller.
+pub fn get_acknowledgement_entry(
+    instance: &str,
+    command: &str,
+    acknowledgement: &ControlAcknowledgement,
+) -> SessionEntry {
+    let category = match acknowledgement.result {
+        ControlResult::Error { .. } => EntryCategory::Error,
+        _ => EntryCategory::Ack,
+    };
+    let acknowledgement = serde_json::to_value(acknowledgement).unwrap_or_default();
+    let summary = format!(
+        "Control command {}",
+        acknowledgement["status"].as_str().unwrap_or_default()
+    );
+    // invalid commands are kept as they were sent
+    let command = serde_json::from_str(command)
+        .unwrap_or_else(|_| serde_json::Value::String(command.to_owned()));
+    let details = json!({ "command": command, "acknowledgement": acknowledgement });
+    SessionEntry::new(category, Some(instance), summary, details)
+}
+
+/// Returns the entry of an event, `None` for operations, which are recorded as commands with
+/// their recipients.
+pub fn get_event_entry(event: &InstanceEvent) -> Option<SessionEntry> {
+    let details = serde_json::to_value(&event.kind).ok()?;
+    let kind = details["type"].as_str().unwrap_or_default();
+    let (category, summary) = match kind {
+        "operation" => return None,
+        "clientError" => (
+            EntryCategory::Error,
+            format!(
+                "Client {} reported: {}",
+                details["client"],
+                details["message"].as_str().unwrap_or_default()
+            ),
+        ),
+        kind => (EntryCategory::Event, kind.to_owned()),
+    };
+    Some(SessionEntry {
+        timestamp: event.timestamp,
+        category,
+        instance: Some(event.instance.clone()),
+        summary,
+        details,
+    })
+}
+
+/// Records the events of all instances while a session is being recorded.
+pub async fn run_session_recording(controller: Arc<ServerController>) {
+    let mut cursor = None;
+    loop {
+        let batch = controller.poll_all_events(cursor, EVENT_POLL_TIMEOUT).await;
+        cursor = Some(batch.cursor);
+        if batch.events.is_empty() || !controller.is_recording_session().await {
+            continue;
+        }
+        for entry in batch.events.iter().filter_map(get_event_entry) {
+            controller.record_session_entry(entry).await;
+        }
+    }
+}
+
+/// Renders the timeline as a standalone HTML page, e.g. to attach it to a show report.
+pub fn write_html(timeline: &SessionTimeline) -> String {
+    let mut rows = String::new();
+    for entry in &timeline.entries {
+        let details = match &entry.details {
+            serde_json::Value::Null => String::new(),
+            details => serde_json::to_string_pretty(details).unwrap_or_default(),
+        };
+        let category = serde_json::to_value(entry.category).unwrap_or_default();
+        let category = category.as_str().unwrap_or_default();
+        writeln!(
+            rows,
+            "<tr class=\"{category}\"><td>{}</td><td>{category}</td><td>{}</td><td>{}</td><td><pre>{}</pre></td></tr>",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S%.3f"),
+            escape_html(entry.instance.as_deref().unwrap_or_default()),
+            escape_html(&entry.summary),
+            escape_html(&details),
+        )
+        .ok();
+    }
+    let name = escape_html(&timeline.name);
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <title>Session {name}</title>
+    <style>
+        body {{ font-family: sans-serif; margin: 2em; color: #222; }}
+        table {{ border-collapse: collapse; }}
+        th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }}
+        td:first-child {{ white-space: nowrap; font-variant-numeric: tabular-nums; }}
+        pre {{ margin: 0; font-size: 0.85em; }}
+        tr.session {{ background: #eef; }}
+        tr.error {{ background: #fdd; }}
+        tr.ack {{ color: #555; }}
+    </style>
+</head>
+<body>
+<h1>Session {name}</h1>
+<p>{} entries, times in UTC.</p>
+<table>
+<tr><th>Time</th><th>Category</th><th>Instance</th><th>Summary</th><th>Details</th></tr>
+{rows}</table>
+</body>
+</html>
+"#,
+        timeline.entries.len()
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EventKind;
+
+    #[tokio::test]
+    async fn test_record_session() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let recorder = SessionRecorder::new(path.clone());
+        let message = InstanceMessage::SetText {
+            id: "Home",
+            text: "<b>1</b>",
+            tween_duration: None,
+        };
+        let entry = get_command_entry("scoreboard", &message, None).unwrap();
+        // nothing is recorded before the session starts
+        recorder.record(entry.clone()).await;
+        assert!(recorder.start("final").await.unwrap().is_some());
+        assert!(recorder.start("other").await.unwrap().is_none());
+        recorder.record(entry).await;
+        let event = InstanceEvent {
+            cursor: 1,
+            timestamp: Utc::now(),
+            instance: String::from("scoreboard"),
+            kind: EventKind::ClientError {
+                client: 2,
+                message: String::from("Asset not found"),
+            },
+        };
+        recorder.record(get_event_entry(&event).unwrap()).await;
+        assert_eq!(4, recorder.stop().await.unwrap().unwrap().entries);
+        assert!(recorder.stop().await.unwrap().is_none());
+
+        let timeline = recorder.get_timeline("final").await.unwrap().unwrap();
+        let categories: Vec<EntryCategory> = timeline
+            .entries
+            .iter()
+            .map(|entry| entry.category)
+            .collect();
+        assert_eq!(
+            vec![
+                EntryCategory::Session,
+                EntryCategory::Command,
+                EntryCategory::Error,
+                EntryCategory::Session
+            ],
+            categories
+        );
+        assert_eq!("SetText", timeline.entries[1].summary);
+        assert!(write_html(&timeline).contains("&lt;b&gt;1&lt;/b&gt;"));
+        assert!(recorder.get_timeline("other").await.unwrap().is_none());
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
@@ -0,0 +1,109 @@
+use anyhow::anyhow;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+
+use crate::secrets::SecretStore;
+
+/// Purpose of the subkey which signs share links.
+const SHARE_LINK_SIGNING_PURPOSE: &str = "share-link";
+/// Maximum validity of a share link.
+pub const MAX_SHARE_LINK_VALIDITY_SECONDS: i64 = 7 * 24 * 60 * 60;
+
+/// Content of a share link, which grants read-only access to the state and preview of an
+/// instance until it expires.
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareClaims {
+    pub instance: String,
+    pub expires: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    /// Path of the preview page, relative to the server.
+    pub url: String,
+    pub token: String,
+    pub expires: DateTime<Utc>,
+}
+
+/// Returns the token of the claims, consisting of the encoded claims and their signature.
+pub async fn create_token(secrets: &SecretStore, claims: &ShareClaims) -> anyhow::Result<String> {
+    let payload = BASE64_URL.encode(serde_json::to_vec(claims)?);
+    let signature = secrets
+        .sign(SHARE_LINK_SIGNING_PURPOSE, payload.as_bytes())
+        .await?;
+    Ok(format!("{payload}.{}", BASE64_URL.encode(signature)))
+}
+
+/// Returns the claims of the token if its signature is valid and it did not expire yet.
+pub async fn verify_token(
+    secrets: &SecretStore,
+    token: &str,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Option<ShareClaims>> {
+    let Some((payload, signature)) = token.split_once('.') else {
+        return Ok(None);
+    };
+    let Ok(signature) = BASE64_URL.decode(signature) else {
+        return Ok(None);
+    };
+    if !secrets
+        .verify(SHARE_LINK_SIGNING_PURPOSE, payload.as_bytes(), &signature)
+        .await?
+    {
+        return Ok(None);
+    }
+    let claims: ShareClaims = serde_json::from_slice(&BASE64_URL.decode(payload)?)
+        .map_err(|err| anyhow!("Signed share link is invalid: {}", err))?;
+    Ok((now < claims.expires).then_some(claims))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tokens_are_signed_and_expire() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let secrets = SecretStore::new(path.clone(), path.join("secret.key"));
+        let now = Utc::now();
+        let claims = ShareClaims {
+            instance: String::from("scoreboard"),
+            expires: now + Duration::hours(1),
+        };
+        let token = create_token(&secrets, &claims).await.unwrap();
+        assert_eq!(
+            Some(claims),
+            verify_token(&secrets, &token, now).await.unwrap()
+        );
+        assert!(verify_token(&secrets, &token, now + Duration::hours(2))
+            .await
+            .unwrap()
+            .is_none());
+
+        // a token for another instance cannot be forged from a valid signature
+        let (_, signature) = token.split_once('.').unwrap();
+        let forged_payload = BASE64_URL.encode(
+            serde_json::to_vec(&ShareClaims {
+                instance: String::from("ticker"),
+                expires: now + Duration::hours(1),
+            })
+            .unwrap(),
+        );
+        let forged = format!("{forged_payload}.{signature}");
+        assert!(verify_token(&secrets, &forged, now)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(verify_token(&secrets, "invalid", now)
+            .await
+            .unwrap()
+            .is_none());
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use tokio::sync::Mutex;
+
+use crate::approval::PendingChange;
+use crate::config::AutoSnapshotConfig;
+use crate::controller::ServerController;
+use crate::state::InstanceState;
+use crate::store::InstanceDocumentStore;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    pub created: DateTime<Utc>,
+    pub state: InstanceState,
+    /// Whether the snapshot was stored by the automatic snapshots. Only automatic snapshots are
+    /// pruned, whatever their name.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub automatic: bool,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotInfo {
+    pub name: String,
+    pub created: DateTime<Utc>,
+    pub automatic: bool,
+}
+
+pub type Snapshots = BTreeMap<String, Snapshot>;
+
+pub enum ApplyResult {
+    Applied,
+    /// The snapshot was held back, since its changes require approval.
+    Pending(PendingChange),
+    SnapshotNotFound,
+}
+
+const SNAPSHOTS_FILE_NAME: &str = "snapshots.json";
+const AUTO_SNAPSHOT_PREFIX: &str = "auto-";
+const AUTO_SNAPSHOT_TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// Named state snapshots ("savepoints") of an instance, stored in the instance folder.
+pub struct SnapshotStore {
+    snapshots: InstanceDocumentStore<Snapshots>,
+    /// Serializes changes, so concurrent changes do not overwrite each other.
+    update_lock: Mutex<()>,
+}
+
+impl SnapshotStore {
+    pub fn new(data_folder: PathBuf) -> SnapshotStore {
+        SnapshotStore {
+            snapshots: InstanceDocumentStore::new(data_folder, SNAPSHOTS_FILE_NAME),
+            update_lock: Mutex::new(()),
+        }
+    }
+
+    pub async fn get_snapshots(&self, instance: &str) -> anyhow::Result<Vec<SnapshotInfo>> {
+        Ok(self
+            .snapshots
+            .get(instance)
+            .await?
+            .into_iter()
+            .map(|(name, snapshot)| SnapshotInfo {
+                name,
+                created: snapshot.created,
+                automatic: snapshot.automatic,
+            })
+            .collect())
+    }
+
+    pub async fn get_all_snapshots(&self, instance: &str) -> anyhow::Result<Snapshots> {
+        self.snapshots.get(instance).await
+    }
+
+    /// Replaces all snapshots of the instance.
+    pub async fn set_all_snapshots(
+        &self,
+        instance: &str,
+        snapshots: Snapshots,
+    ) -> anyhow::Result<()> {
+        if let Some(name) = snapshots.keys().find(|name| !is_valid_snapshot_name(name)) {
+            return Err(anyhow!("Invalid snapshot name: {}", name));
+        }
+        let _update_guard = self.update_lock.lock().await;
+        self.snapshots.set(instance, snapshots).await
+    }
+
+    pub async fn get_snapshot(
+        &self,
+        instance: &str,
+        name: &str,
+    ) -> anyhow::Result<Option<Snapshot>> {
+        Ok(self.snapshots.get(instance).await?.remove(name))
+    }
+
+    pub async fn set_snapshot(
+        &self,
+        instance: &str,
+        name: &str,
+        state: InstanceState,
+    ) -> anyhow::Result<()> {
+        if !is_valid_snapshot_name(name) {
+            return Err(anyhow!("Invalid snapshot name: {}", name));
+        }
+        let _update_guard = self.update_lock.lock().await;
+        let mut snapshots = self.snapshots.get(instance).await?;
+        snapshots.insert(
+            name.to_owned(),
+            Snapshot {
+                created: Utc::now(),
+                state,
+                automatic: false,
+            },
+        );
+        self.snapshots.set(instance, snapshots).await
+    }
+
+    /// Stores the state as automatic snapshot named after the current time unless it did not
+    /// change since the last automatic snapshot. Only the latest automatic snapshots are kept.
+    /// Returns whether a snapshot was stored.
+    pub async fn store_auto_snapshot(
+        &self,
+        instance: &str,
+        state: InstanceState,
+        retention: usize,
+    ) -> anyhow::Result<bool> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut snapshots = self.snapshots.get(instance).await?;
+        let mut auto_snapshot_names: Vec<String> = snapshots
+            .iter()
+            .filter(|(_, snapshot)| snapshot.automatic)
+            .map(|(name, _)| name.clone())
+            .collect();
+        let latest_state = auto_snapshot_names
+            .last()
+            .and_then(|name| snapshots.get(name))
+            .map(|snapshot| &snapshot.state);
+        if latest_state == Some(&state) {
+            return Ok(false);
+        }
+
+        let now = Utc::now();
+        let name = format!(
+            "{AUTO_SNAPSHOT_PREFIX}{}",
+            now.format(AUTO_SNAPSHOT_TIMESTAMP_FORMAT)
+        );
+        if snapshots
+            .get(&name)
+            .is_some_and(|snapshot| !snapshot.automatic)
+        {
+            warn!(
+                "Not storing automatic snapshot {} of instance {}, since a snapshot with that name exists.",
+                name, instance
+            );
+            return Ok(false);
+        }
+        snapshots.insert(
+            name.clone(),
+            Snapshot {
+                created: now,
+                state,
+                automatic: true,
+            },
+        );
+        auto_snapshot_names.push(name);
+        auto_snapshot_names.sort();
+        auto_snapshot_names.dedup();
+        let excess = auto_snapshot_names.len().saturating_sub(retention);
+        for name in &auto_snapshot_names[..excess] {
+            snapshots.remove(name);
+        }
+        self.snapshots.set(instance, snapshots).await?;
+        Ok(true)
+    }
+
+    /// Returns whether the snapshot existed.
+    pub async fn delete_snapshot(&self, instance: &str, name: &str) -> anyhow::Result<bool> {
+        let _update_guard = self.update_lock.lock().await;
+        let mut snapshots = self.snapshots.get(instance).await?;
+        if snapshots.remove(name).is_none() {
+            return Ok(false);
+        }
+        self.snapshots.set(instance, snapshots).await?;
+        Ok(true)
+    }
+}
+
+pub fn is_valid_snapshot_name(name: &str) -> bool {
+    crate::fs::is_valid_name(name)
+}
+
+/// Migration flagging the automatic snapshots stored before snapshots had the flag, recognized by
+/// their name `auto-<timestamp>`.
+pub fn flag_automatic_snapshots(data_folder: &Path) -> anyhow::Result<()> {
+    for instance in crate::fs::get_instance_names(data_folder)? {
+        let path =
+            crate::fs::get_instance_folder(data_folder, &instance)?.join(SNAPSHOTS_FILE_NAME);
+        if !path.exists() {
+            continue;
+        }
+        let mut snapshots: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_slice(&std::fs::read(&path)?)
+                .with_context(|| format!("Could not parse {path:?}"))?;
+        for (name, snapshot) in snapshots.iter_mut() {
+            let is_automatic = name
+                .strip_prefix(AUTO_SNAPSHOT_PREFIX)
+                .is_some_and(|timestamp| {
+                    NaiveDateTime::parse_from_str(timestamp, AUTO_SNAPSHOT_TIMESTAMP_FORMAT).is_ok()
+                });
+            if let (true, Some(snapshot)) = (is_automatic, snapshot.as_object_mut()) {
+                snapshot.insert(String::from("automatic"), serde_json::Value::Bool(true));
+            }
+        }
+        std::fs::write(&path, serde_json::to_vec_pretty(&snapshots)?)?;
+    }
+    Ok(())
+}
+
+/// Periodically stores automatic snapshots of all instances, so the state can be restored after
+/// a crash or an accidental change.
+pub async fn run_auto_snapshots(
+    controller: Arc<ServerController>,
+    configuration: AutoSnapshotConfig,
+) {
+    let Some(interval_minutes) = configuration
+        .interval_minutes
+        .filter(|minutes| *minutes > 0)
+    else {
+        return;
+    };
+    info!(
+        "Storing automatic snapshots every {} minutes (keeping {}).",
+        interval_minutes, configuration.retention
+    );
+    let mut interval = tokio::time::interval(Duration::from_secs(interval_minutes * 60));
+    // the first tick completes immediately
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        controller
+            .store_auto_snapshots(configuration.retention)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_auto_snapshot_retention() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let store = SnapshotStore::new(path.clone());
+        store
+            .set_snapshot("scoreboard", "halftime", InstanceState::default())
+            .await
+            .unwrap();
+        // snapshots named like automatic snapshots by the user are kept
+        store
+            .set_snapshot("scoreboard", "auto-kickoff", InstanceState::default())
+            .await
+            .unwrap();
+
+        let mut state = InstanceState::default();
+        for score in ["1", "2", "3"] {
+            state
+                .texts
+                .insert(String::from("HomeScore"), score.to_owned());
+            assert!(store
+                .store_auto_snapshot("scoreboard", state.clone(), 1)
+                .await
+                .unwrap());
+        }
+        assert!(!store
+            .store_auto_snapshot("scoreboard", state.clone(), 1)
+            .await
+            .unwrap());
+
+        let snapshots = store.get_snapshots("scoreboard").await.unwrap();
+        assert_eq!(3, snapshots.len());
+        assert!(snapshots[0].name.starts_with(AUTO_SNAPSHOT_PREFIX));
+        assert!(snapshots[0].automatic);
+        assert_eq!("auto-kickoff", snapshots[1].name);
+        assert!(!snapshots[1].automatic);
+        assert_eq!("halftime", snapshots[2].name);
+        let auto_snapshot = store
+            .get_snapshot("scoreboard", &snapshots[0].name)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state, auto_snapshot.state);
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_flag_automatic_snapshots() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let instance_folder = crate::fs::get_instance_folder(&path, "scoreboard").unwrap();
+        std::fs::create_dir_all(&instance_folder).unwrap();
+        let snapshot = serde_json::json!({"created": "2024-03-01T12:00:00Z", "state": {}});
+        std::fs::write(
+            instance_folder.join(SNAPSHOTS_FILE_NAME),
+            serde_json::json!({
+                "auto-20240301-120000": snapshot,
+                "auto-kickoff": snapshot,
+                "halftime": snapshot
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        flag_automatic_snapshots(&path).unwrap();
+        let snapshots = SnapshotStore::new(path.clone())
+            .get_snapshots("scoreboard")
+            .await
+            .unwrap();
+        let automatic: Vec<bool> = snapshots
+            .iter()
+            .map(|snapshot| snapshot.automatic)
+            .collect();
+        assert_eq!(vec![true, false, false], automatic);
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
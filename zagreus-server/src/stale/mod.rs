@@ -0,0 +1,143 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::config::StaleDataConfig;
+use crate::controller::ServerController;
+use crate::data::operation::Operation;
+use crate::events::EventKind;
+
+/// Maximum time to wait for events while the data is stale.
+const STALE_POLL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Watches the elements bound to a data source. If none of them was refreshed within the maximum
+/// age, the configured class is added to all of them and a `dataStale` event is emitted, so data
+/// that can no longer be trusted is visibly flagged. The class is removed with the next refresh.
+pub async fn run_stale_data_watch(
+    controller: Arc<ServerController>,
+    configuration: StaleDataConfig,
+) {
+    info!(
+        "Watching data of elements {:?} of instance {} (maximum age: {} s).",
+        configuration.elements, configuration.instance, configuration.max_age_seconds
+    );
+    let max_age = Duration::from_secs(configuration.max_age_seconds);
+    let mut cursor = None;
+    let mut last_refresh = Instant::now();
+    let mut stale = false;
+
+    loop {
+        let timeout = if stale {
+            STALE_POLL_TIMEOUT
+        } else {
+            max_age.saturating_sub(last_refresh.elapsed())
+        };
+        let batch = controller
+            .poll_events(&configuration.instance, cursor, timeout)
+            .await;
+        cursor = Some(batch.cursor);
+
+        if batch
+            .events
+            .iter()
+            .any(|event| is_refresh(&configuration, &event.kind))
+        {
+            last_refresh = Instant::now();
+            if stale {
+                info!(
+                    "Data of elements {:?} of instance {} was refreshed.",
+                    configuration.elements, configuration.instance
+                );
+                set_stale(&controller, &configuration, false).await;
+                stale = false;
+            }
+        } else if !stale && last_refresh.elapsed() >= max_age {
+            warn!(
+                "Data of elements {:?} of instance {} was not refreshed for {} s and is stale.",
+                configuration.elements, configuration.instance, configuration.max_age_seconds
+            );
+            set_stale(&controller, &configuration, true).await;
+            stale = true;
+        }
+    }
+}
+
+/// Returns whether the event is an update of the text or image of a bound element. Setting the
+/// same value again counts as a refresh as well.
+fn is_refresh(configuration: &StaleDataConfig, event: &EventKind) -> bool {
+    match event {
+        EventKind::Operation {
+            operation, payload, ..
+        } if operation == "SetText" || operation == "SetImageSource" => payload["id"]
+            .as_str()
+            .is_some_and(|id| configuration.elements.iter().any(|element| element == id)),
+        _ => false,
+    }
+}
+
+async fn set_stale(controller: &ServerController, configuration: &StaleDataConfig, stale: bool) {
+    let operations = configuration
+        .elements
+        .iter()
+        .map(|element| {
+            let (id, class) = (element.clone(), configuration.class.clone());
+            if stale {
+                Operation::AddClass { id, class }
+            } else {
+                Operation::RemoveClass { id, class }
+            }
+        })
+        .collect();
+    controller
+        .submit_operations(&configuration.instance, operations)
+        .await;
+
+    let elements = configuration.elements.clone();
+    let event = if stale {
+        EventKind::DataStale { elements }
+    } else {
+        EventKind::DataRefreshed { elements }
+    };
+    controller
+        .record_event(&configuration.instance, event)
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_only_updates_of_bound_elements_are_refreshes() {
+        let configuration = StaleDataConfig {
+            instance: String::from("scoreboard"),
+            elements: vec![String::from("HomeScore")],
+            max_age_seconds: 10,
+            class: String::from("data-stale"),
+        };
+        let operation = |operation: &str, id: &str| EventKind::Operation {
+            operation: operation.to_owned(),
+            payload: json!({ "id": id, "text": "1", "class": "data-stale" }),
+            clients: 1,
+        };
+
+        assert!(is_refresh(
+            &configuration,
+            &operation("SetText", "HomeScore")
+        ));
+        assert!(!is_refresh(
+            &configuration,
+            &operation("SetText", "AwayScore")
+        ));
+        // adding the stale class itself does not refresh the data
+        assert!(!is_refresh(
+            &configuration,
+            &operation("AddClass", "HomeScore")
+        ));
+        assert!(!is_refresh(
+            &configuration,
+            &EventKind::ClientConnected { client: 1 }
+        ));
+    }
+}
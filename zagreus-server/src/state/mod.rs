@@ -0,0 +1,559 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use tokio::sync::{broadcast, RwLock};
+
+use crate::approval::PendingChange;
+use crate::assets::AssetVersions;
+use crate::config::PublicStateConfig;
+use crate::data::asset::{AssetReference, AssetSource};
+use crate::data::operation::Operation;
+use crate::websocket::message::InstanceMessage;
+
+pub mod persistence;
+
+/// Current data state of an instance, derived from the messages that were sent to it.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceState {
+    #[serde(default)]
+    pub texts: BTreeMap<String, String>,
+    /// Classes per element which were added (`true`) or removed (`false`).
+    #[serde(default)]
+    pub classes: BTreeMap<String, BTreeMap<String, bool>>,
+    #[serde(default)]
+    pub images: BTreeMap<String, AssetReference>,
+    /// Versions of the zagreus assets used by images at the time of the export. Only informative,
+    /// images always show the current version of an asset when the state is applied.
+    #[serde(default, skip_deserializing)]
+    pub asset_versions: AssetVersions,
+}
+
+pub enum StagedTakeResult {
+    /// The staged data was sent to all renderers.
+    Taken(InstanceState),
+    /// The staged data was held back, since it requires approval.
+    Pending(PendingChange),
+    NothingStaged,
+}
+
+/// Data of an instance which is published read-only, limited to the allowlisted elements.
+#[derive(Serialize, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PublicState {
+    pub texts: BTreeMap<String, String>,
+    pub classes: BTreeMap<String, BTreeMap<String, bool>>,
+    pub images: BTreeMap<String, AssetReference>,
+}
+
+/// Capacity of the channel notifying about state changes. Subscribers which lag behind only miss
+/// notifications, not state.
+const STATE_CHANGES_CAPACITY: usize = 256;
+
+impl InstanceState {
+    /// Applies the message to the state and returns whether it is part of the state.
+    pub fn apply(&mut self, message: &InstanceMessage<'_>) -> bool {
+        match message {
+            InstanceMessage::SetText { id, text, .. } => {
+                self.texts.insert((*id).to_owned(), (*text).to_owned());
+            }
+            InstanceMessage::AddClass { id, class } => {
+                self.set_class(id, class, true);
+            }
+            InstanceMessage::RemoveClass { id, class } => {
+                self.set_class(id, class, false);
+            }
+            InstanceMessage::SetImageSource {
+                id,
+                asset,
+                asset_source,
+            } => {
+                self.images.insert(
+                    (*id).to_owned(),
+                    AssetReference {
+                        asset: (*asset).to_owned(),
+                        asset_source: asset_source.clone(),
+                    },
+                );
+            }
+            InstanceMessage::Batch { operations } => {
+                let mut changed = false;
+                for operation in operations {
+                    changed |= self.apply(operation);
+                }
+                return changed;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Removes the data which the message replaces, e.g. when it is sent to all channels after a
+    /// single channel received different data.
+    pub fn forget(&mut self, message: &InstanceMessage<'_>) {
+        match message {
+            InstanceMessage::SetText { id, .. } => {
+                self.texts.remove(*id);
+            }
+            InstanceMessage::AddClass { id, class }
+            | InstanceMessage::RemoveClass { id, class } => {
+                if let Some(classes) = self.classes.get_mut(*id) {
+                    classes.remove(*class);
+                    if classes.is_empty() {
+                        self.classes.remove(*id);
+                    }
+                }
+            }
+            InstanceMessage::SetImageSource { id, .. } => {
+                self.images.remove(*id);
+            }
+            InstanceMessage::Batch { operations } => {
+                for operation in operations {
+                    self.forget(operation);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn set_class(&mut self, id: &str, class: &str, added: bool) {
+        self.classes
+            .entry(id.to_owned())
+            .or_default()
+            .insert(class.to_owned(), added);
+    }
+
+    /// Returns the messages which bring a client to this state.
+    pub fn to_messages(&self) -> Vec<InstanceMessage<'_>> {
+        let texts = self
+            .texts
+            .iter()
+            .map(|(id, text)| InstanceMessage::SetText {
+                id,
+                text,
+                tween_duration: None,
+            });
+        let classes = self.classes.iter().flat_map(|(id, classes)| {
+            classes.iter().map(move |(class, added)| {
+                if *added {
+                    InstanceMessage::AddClass { id, class }
+                } else {
+                    InstanceMessage::RemoveClass { id, class }
+                }
+            })
+        });
+        let images = self
+            .images
+            .iter()
+            .map(|(id, image)| InstanceMessage::SetImageSource {
+                id,
+                asset: &image.asset,
+                asset_source: image.asset_source.clone(),
+            });
+        texts.chain(classes).chain(images).collect()
+    }
+
+    /// Returns the operations which bring a client from the current state to this state, without
+    /// the data both states have in common. Elements which are missing from this state are
+    /// cleared.
+    pub fn get_changed_operations(&self, current: &InstanceState) -> Vec<Operation> {
+        let texts = self
+            .texts
+            .iter()
+            .filter(|(id, text)| current.texts.get(*id) != Some(text))
+            .map(|(id, text)| Operation::SetText {
+                id: id.clone(),
+                text: text.clone(),
+                tween_duration: None,
+            });
+        let classes = self.classes.iter().flat_map(|(id, classes)| {
+            let current_classes = current.classes.get(id);
+            classes
+                .iter()
+                .filter(move |(class, added)| {
+                    current_classes.and_then(|classes| classes.get(*class)) != Some(added)
+                })
+                .map(|(class, added)| {
+                    let (id, class) = (id.clone(), class.clone());
+                    if *added {
+                        Operation::AddClass { id, class }
+                    } else {
+                        Operation::RemoveClass { id, class }
+                    }
+                })
+        });
+        let images = self
+            .images
+            .iter()
+            .filter(|(id, image)| current.images.get(*id) != Some(image))
+            .map(|(id, image)| Operation::SetImageSource {
+                id: id.clone(),
+                asset: image.asset.clone(),
+                asset_source: image.asset_source.clone(),
+            });
+        texts
+            .chain(classes)
+            .chain(images)
+            .chain(self.get_removal_operations(current))
+            .collect()
+    }
+
+    /// Returns the operations which clear the elements of the current state that are not part of
+    /// this state: their texts are emptied and their classes are removed. Images stay, since they
+    /// cannot be removed from an element.
+    pub fn get_removal_operations(&self, current: &InstanceState) -> Vec<Operation> {
+        let texts = current
+            .texts
+            .iter()
+            .filter(|(id, text)| !text.is_empty() && !self.texts.contains_key(*id))
+            .map(|(id, _)| Operation::SetText {
+                id: id.clone(),
+                text: String::new(),
+                tween_duration: None,
+            });
+        let classes = current.classes.iter().flat_map(|(id, classes)| {
+            let new_classes = self.classes.get(id);
+            classes
+                .iter()
+                .filter(move |(class, added)| {
+                    **added && new_classes.is_none_or(|classes| !classes.contains_key(*class))
+                })
+                .map(|(class, _)| Operation::RemoveClass {
+                    id: id.clone(),
+                    class: class.clone(),
+                })
+        });
+        texts.chain(classes).collect()
+    }
+
+    /// Returns the number of elements which have a text, classes or an image.
+    pub fn count_elements(&self) -> usize {
+        self.texts
+            .keys()
+            .chain(self.classes.keys())
+            .chain(self.images.keys())
+            .collect::<BTreeSet<_>>()
+            .len()
+    }
+
+    /// Returns the data of the allowlisted elements.
+    pub fn to_public(&self, allowlist: &PublicStateConfig) -> PublicState {
+        fn filter<V: Clone>(entries: &BTreeMap<String, V>, ids: &[String]) -> BTreeMap<String, V> {
+            entries
+                .iter()
+                .filter(|(id, _)| ids.contains(id))
+                .map(|(id, value)| (id.clone(), value.clone()))
+                .collect()
+        }
+
+        PublicState {
+            texts: filter(&self.texts, &allowlist.texts),
+            classes: filter(&self.classes, &allowlist.classes),
+            images: filter(&self.images, &allowlist.images),
+        }
+    }
+
+    /// Adds the current versions of the zagreus assets used by images.
+    pub fn with_asset_versions(mut self, versions: &AssetVersions) -> InstanceState {
+        self.asset_versions = self
+            .images
+            .values()
+            .filter(|image| image.asset_source == AssetSource::Zagreus)
+            .filter_map(|image| {
+                versions
+                    .get(&image.asset)
+                    .map(|version| (image.asset.clone(), version.clone()))
+            })
+            .collect();
+        self
+    }
+}
+
+/// Keeps track of the current state of all instances.
+pub struct StateStore {
+    states: RwLock<HashMap<String, InstanceState>>,
+    /// Data which was only sent to a channel of an instance, per instance and channel. Renderers
+    /// of the channel show it on top of the state of the instance.
+    channel_states: RwLock<HashMap<(String, String), InstanceState>>,
+    changes: broadcast::Sender<String>,
+}
+
+impl StateStore {
+    pub fn new() -> StateStore {
+        StateStore {
+            states: RwLock::new(HashMap::new()),
+            channel_states: RwLock::new(HashMap::new()),
+            changes: broadcast::channel(STATE_CHANGES_CAPACITY).0,
+        }
+    }
+
+    pub async fn record(&self, instance: &str, message: &InstanceMessage<'_>) {
+        let changed = self
+            .states
+            .write()
+            .await
+            .entry(instance.to_owned())
+            .or_default()
+            .apply(message);
+        if changed {
+            // data sent to all channels replaces the data of single channels
+            for ((state_instance, _), state) in self.channel_states.write().await.iter_mut() {
+                if state_instance == instance {
+                    state.forget(message);
+                }
+            }
+            self.notify_change(instance);
+        }
+    }
+
+    /// Records a message which was only sent to the renderers of the channel. The state of the
+    /// instance is not changed, so subscribers are not notified.
+    pub async fn record_channel(
+        &self,
+        instance: &str,
+        channel: &str,
+        message: &InstanceMessage<'_>,
+    ) {
+        self.channel_states
+            .write()
+            .await
+            .entry((instance.to_owned(), channel.to_owned()))
+            .or_default()
+            .apply(message);
+    }
+
+    /// Returns the data which was only sent to the channel, without the state of the instance.
+    pub async fn get_channel_state(&self, instance: &str, channel: &str) -> InstanceState {
+        self.channel_states
+            .read()
+            .await
+            .get(&(instance.to_owned(), channel.to_owned()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub async fn remove_channel_state(&self, instance: &str, channel: &str) {
+        self.channel_states
+            .write()
+            .await
+            .remove(&(instance.to_owned(), channel.to_owned()));
+    }
+
+    /// Returns the states of the channels of all instances.
+    pub async fn get_all_channel_states(&self) -> Vec<InstanceState> {
+        self.channel_states.read().await.values().cloned().collect()
+    }
+
+    pub async fn get_state(&self, instance: &str) -> InstanceState {
+        self.states
+            .read()
+            .await
+            .get(instance)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the states of all instances without waiting, if they are not locked.
+    pub fn try_get_states(&self) -> Option<BTreeMap<String, InstanceState>> {
+        self.states.try_read().ok().map(|states| {
+            states
+                .iter()
+                .map(|(instance, state)| (instance.clone(), state.clone()))
+                .collect()
+        })
+    }
+
+    pub async fn get_instances(&self) -> Vec<String> {
+        self.states.read().await.keys().cloned().collect()
+    }
+
+    pub async fn set_state(&self, instance: &str, state: InstanceState) {
+        self.states.write().await.insert(instance.to_owned(), state);
+        self.notify_change(instance);
+    }
+
+    pub async fn has_state(&self, instance: &str) -> bool {
+        self.states.read().await.contains_key(instance)
+    }
+
+    /// Forgets the state of a deleted instance. Subscribers are not notified, as there is nothing
+    /// left to store.
+    pub async fn remove_state(&self, instance: &str) {
+        self.states.write().await.remove(instance);
+        self.channel_states
+            .write()
+            .await
+            .retain(|(state_instance, _), _| state_instance != instance);
+    }
+
+    /// Returns a receiver of the names of instances whose state changed.
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<String> {
+        self.changes.subscribe()
+    }
+
+    fn notify_change(&self, instance: &str) {
+        // sending only fails if nobody is subscribed
+        let _ = self.changes.send(instance.to_owned());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_and_replay_state() {
+        let store = StateStore::new();
+        let messages = [
+            InstanceMessage::SetText {
+                id: "HomeScore",
+                text: "1",
+                tween_duration: None,
+            },
+            InstanceMessage::SetText {
+                id: "HomeScore",
+                text: "2",
+                tween_duration: None,
+            },
+            InstanceMessage::AddClass {
+                id: "Scoreboard",
+                class: "active",
+            },
+            InstanceMessage::RemoveClass {
+                id: "Scoreboard",
+                class: "active",
+            },
+            InstanceMessage::SetImageSource {
+                id: "Sponsor",
+                asset: "sponsor.png",
+                asset_source: AssetSource::Zagreus,
+            },
+        ];
+        for message in &messages {
+            store.record("scoreboard", message).await;
+        }
+
+        let state = store.get_state("scoreboard").await;
+        assert_eq!("2", state.texts["HomeScore"]);
+        assert_eq!(Some(&false), state.classes["Scoreboard"].get("active"));
+        assert_eq!(3, state.count_elements());
+
+        let replayed_store = StateStore::new();
+        for message in &state.to_messages() {
+            replayed_store.record("scoreboard", message).await;
+        }
+        assert_eq!(state, replayed_store.get_state("scoreboard").await);
+
+        store.remove_state("scoreboard").await;
+        assert!(!store.has_state("scoreboard").await);
+    }
+
+    #[tokio::test]
+    async fn test_channel_state() {
+        let store = StateStore::new();
+        let preview_text = InstanceMessage::SetText {
+            id: "Name",
+            text: "Jane Doe",
+            tween_duration: None,
+        };
+        store
+            .record_channel("lowerthird", "preview", &preview_text)
+            .await;
+        store
+            .record_channel(
+                "lowerthird",
+                "preview",
+                &InstanceMessage::AddClass {
+                    id: "Name",
+                    class: "highlighted",
+                },
+            )
+            .await;
+        assert!(!store.has_state("lowerthird").await);
+        let state = store.get_channel_state("lowerthird", "preview").await;
+        assert_eq!("Jane Doe", state.texts["Name"]);
+        assert!(store
+            .get_channel_state("lowerthird", "program")
+            .await
+            .texts
+            .is_empty());
+
+        store
+            .record(
+                "lowerthird",
+                &InstanceMessage::SetText {
+                    id: "Name",
+                    text: "John Doe",
+                    tween_duration: None,
+                },
+            )
+            .await;
+        let state = store.get_channel_state("lowerthird", "preview").await;
+        assert!(state.texts.is_empty());
+        assert_eq!(Some(&true), state.classes["Name"].get("highlighted"));
+
+        store.remove_state("lowerthird").await;
+        assert!(store.get_all_channel_states().await.is_empty());
+    }
+
+    #[test]
+    fn test_apply_batch() {
+        let mut state = InstanceState::default();
+        let batch = InstanceMessage::Batch {
+            operations: vec![
+                InstanceMessage::SetText {
+                    id: "Name",
+                    text: "Jane Doe",
+                    tween_duration: None,
+                },
+                InstanceMessage::SetText {
+                    id: "Role",
+                    text: "Reporter",
+                    tween_duration: None,
+                },
+                InstanceMessage::ExecuteAnimation {
+                    animation_sequence: "LowerThirdIn",
+                    execute_at: None,
+                },
+            ],
+        };
+        assert!(state.apply(&batch));
+        assert_eq!("Jane Doe", state.texts["Name"]);
+        assert_eq!("Reporter", state.texts["Role"]);
+
+        let animations_only = InstanceMessage::Batch {
+            operations: vec![InstanceMessage::ExecuteAnimation {
+                animation_sequence: "LowerThirdOut",
+                execute_at: None,
+            }],
+        };
+        assert!(!state.apply(&animations_only));
+    }
+
+    #[test]
+    fn test_only_allowlisted_data_is_public() {
+        let mut state = InstanceState::default();
+        state
+            .texts
+            .insert(String::from("HomeScore"), String::from("2"));
+        state
+            .texts
+            .insert(String::from("DirectorNote"), String::from("cut to cam 3"));
+        state.classes.insert(
+            String::from("Scoreboard"),
+            BTreeMap::from([(String::from("visible"), true)]),
+        );
+        let allowlist = PublicStateConfig {
+            texts: vec![String::from("HomeScore"), String::from("AwayScore")],
+            classes: Vec::new(),
+            images: Vec::new(),
+            max_age_seconds: 2,
+        };
+
+        let public = state.to_public(&allowlist);
+        assert_eq!(
+            BTreeMap::from([(String::from("HomeScore"), String::from("2"))]),
+            public.texts
+        );
+        assert!(public.classes.is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::controller::ServerController;
+
+pub const STATE_FILE_NAME: &str = "state.json";
+
+/// Delay before changed states are stored, so a burst of updates (e.g. of a running clock) is
+/// written only once.
+const PERSIST_DELAY: Duration = Duration::from_secs(1);
+
+/// Stores the state of instances whenever it changed, so it can be restored after a restart.
+pub async fn run_state_persistence(controller: Arc<ServerController>) {
+    let mut changes = controller.subscribe_state_changes();
+    let mut changed = HashSet::new();
+    loop {
+        match changes.recv().await {
+            Ok(instance) => {
+                changed.insert(instance);
+            }
+            Err(RecvError::Lagged(_)) => {
+                changed.extend(controller.get_instances_with_state().await)
+            }
+            Err(RecvError::Closed) => break,
+        }
+
+        let delay = tokio::time::sleep(PERSIST_DELAY);
+        tokio::pin!(delay);
+        loop {
+            tokio::select! {
+                _ = &mut delay => break,
+                change = changes.recv() => match change {
+                    Ok(instance) => {
+                        changed.insert(instance);
+                    }
+                    Err(RecvError::Lagged(_)) => {
+                        changed.extend(controller.get_instances_with_state().await)
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+
+        persist_states(&controller, changed.drain()).await;
+    }
+}
+
+/// Stores the state of all instances right away, e.g. when the server shuts down before the
+/// delay of the last changes elapsed.
+pub async fn persist_all_states(controller: &ServerController) {
+    let instances = controller.get_instances_with_state().await;
+    persist_states(controller, instances).await;
+}
+
+async fn persist_states(
+    controller: &ServerController,
+    instances: impl IntoIterator<Item = String>,
+) {
+    for instance in instances {
+        // renderers may connect to instances whose names cannot be used as folder
+        if !crate::fs::is_valid_instance_name(&instance) {
+            continue;
+        }
+        if let Err(err) = controller.persist_state(&instance).await {
+            error!("Could not store state of instance {}: {:#}.", instance, err);
+        }
+    }
+}
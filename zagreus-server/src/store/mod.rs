@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+/// Extension of documents which are being written.
+const TEMP_EXTENSION: &str = "tmp";
+
+/// Stores one JSON document per instance in the instance's folder within the data folder.
+/// Documents are loaded lazily and cached in memory.
+pub struct InstanceDocumentStore<T> {
+    data_folder: PathBuf,
+    file_name: &'static str,
+    documents: RwLock<HashMap<String, T>>,
+}
+
+impl<T> InstanceDocumentStore<T>
+where
+    T: Default + Clone + serde::Serialize + serde::de::DeserializeOwned,
+{
+    pub fn new(data_folder: PathBuf, file_name: &'static str) -> InstanceDocumentStore<T> {
+        InstanceDocumentStore {
+            data_folder,
+            file_name,
+            documents: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub async fn get(&self, instance: &str) -> anyhow::Result<T> {
+        if let Some(document) = self.documents.read().await.get(instance) {
+            return Ok(document.clone());
+        }
+
+        // loaded while holding the write lock, so a concurrent change is not overwritten
+        let mut documents = self.documents.write().await;
+        if let Some(document) = documents.get(instance) {
+            return Ok(document.clone());
+        }
+        let document_path =
+            crate::fs::get_instance_folder(&self.data_folder, instance)?.join(self.file_name);
+        let document: T = load_document(&document_path).await?;
+        documents.insert(instance.to_owned(), document.clone());
+        Ok(document)
+    }
+
+    /// Returns the cached documents without waiting, if they are not locked.
+    pub fn try_get_cached(&self) -> Option<HashMap<String, T>> {
+        self.documents
+            .try_read()
+            .ok()
+            .map(|documents| documents.clone())
+    }
+
+    pub async fn set(&self, instance: &str, document: T) -> anyhow::Result<()> {
+        let instance_folder = crate::fs::get_instance_folder(&self.data_folder, instance)?;
+        // stored while holding the write lock, so the file and the cache contain the same change
+        let mut documents = self.documents.write().await;
+        store_document(&instance_folder.join(self.file_name), &document).await?;
+        documents.insert(instance.to_owned(), document);
+        Ok(())
+    }
+}
+
+/// Loads a JSON document from the given path or returns the default document if it does not exist.
+pub async fn load_document<T>(document_path: &Path) -> anyhow::Result<T>
+where
+    T: Default + serde::de::DeserializeOwned,
+{
+    if !document_path.exists() {
+        return Ok(T::default());
+    }
+    let contents = tokio::fs::read_to_string(document_path).await?;
+    serde_json::from_str(&contents)
+        .map_err(|err| anyhow!("Could not parse {:?}: {}", document_path, err))
+}
+
+/// Stores a JSON document at the given path, creating parent folders if necessary. The document
+/// is written to a temporary file next to it and renamed into place, so a crash while writing
+/// never leaves a truncated document.
+pub async fn store_document<T>(document_path: &Path, document: &T) -> anyhow::Result<()>
+where
+    T: serde::Serialize,
+{
+    if let Some(parent) = document_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let serialized_document = serde_json::to_string_pretty(document)?;
+    let file_name = document_path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid document path {:?}", document_path))?;
+    let temp_path = document_path.with_file_name(format!(
+        "{}.{:032x}.{TEMP_EXTENSION}",
+        file_name.to_string_lossy(),
+        rand::random::<u128>()
+    ));
+    let result = async {
+        let mut file = tokio::fs::File::create(&temp_path).await?;
+        file.write_all(serialized_document.as_bytes()).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&temp_path, document_path).await
+    }
+    .await;
+    if result.is_err() {
+        if let Err(err) = tokio::fs::remove_file(&temp_path).await {
+            debug!("Could not remove temporary file {:?}: {}.", temp_path, err);
+        }
+    }
+    Ok(result?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_store_and_load_document() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let store = InstanceDocumentStore::<Vec<String>>::new(path.clone(), "test.json");
+        assert!(store.get("scoreboard").await.unwrap().is_empty());
+
+        store
+            .set("scoreboard", vec![String::from("value")])
+            .await
+            .unwrap();
+
+        let reloaded_store = InstanceDocumentStore::<Vec<String>>::new(path.clone(), "test.json");
+        assert_eq!(
+            vec![String::from("value")],
+            reloaded_store.get("scoreboard").await.unwrap()
+        );
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sets_are_consistent() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let store = InstanceDocumentStore::<Vec<String>>::new(path.clone(), "test.json");
+        futures::future::join_all(
+            (0..10).map(|value| store.set("scoreboard", vec![value.to_string()])),
+        )
+        .await
+        .into_iter()
+        .collect::<anyhow::Result<Vec<()>>>()
+        .unwrap();
+
+        let reloaded_store = InstanceDocumentStore::<Vec<String>>::new(path.clone(), "test.json");
+        assert_eq!(
+            store.get("scoreboard").await.unwrap(),
+            reloaded_store.get("scoreboard").await.unwrap()
+        );
+        // no temporary files are left behind
+        let file_names: Vec<_> =
+            std::fs::read_dir(crate::fs::get_instance_folder(&path, "scoreboard").unwrap())
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name())
+                .collect();
+        assert_eq!(vec![std::ffi::OsString::from("test.json")], file_names);
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_invalid_instance_name() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let store = InstanceDocumentStore::<Vec<String>>::new(path.clone(), "test.json");
+        assert!(store.get("../scoreboard").await.is_err());
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
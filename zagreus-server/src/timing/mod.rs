@@ -0,0 +1,240 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use tokio::sync::RwLock;
+
+/// Number of recent commands the percentiles are computed from.
+const MAX_SAMPLES: usize = 1024;
+
+tokio::task_local! {
+    static COMMAND_TRACE: RefCell<CommandTrace>;
+}
+
+struct CommandTrace {
+    request: String,
+    received: Instant,
+    stages: Vec<(&'static str, Instant)>,
+    /// Only the first command of a request is measured, later ones may be delayed on purpose
+    /// (e.g. by the transition of a take).
+    finished: bool,
+}
+
+/// Timings of a command from receiving its request until it was written to the websockets.
+pub struct FinishedTrace {
+    pub request: String,
+    pub latency: Duration,
+    /// Time spent in every stage since the previous one.
+    pub stages: Vec<(&'static str, Duration)>,
+}
+
+impl Display for FinishedTrace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (index, (stage, duration)) in self.stages.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} {:.1} ms", stage, to_millis(*duration))?;
+        }
+        Ok(())
+    }
+}
+
+/// Traces the commands sent while handling the request, starting when it was received.
+pub async fn trace_commands(request: Request<Body>, next: Next<Body>) -> Response {
+    let name = format!("{} {}", request.method(), request.uri().path());
+    trace(name, next.run(request)).await
+}
+
+/// Traces the commands sent by the future, e.g. while handling a command of a websocket.
+pub async fn trace<F: Future>(request: String, future: F) -> F::Output {
+    let trace = CommandTrace {
+        request,
+        received: Instant::now(),
+        stages: Vec::new(),
+        finished: false,
+    };
+    COMMAND_TRACE.scope(RefCell::new(trace), future).await
+}
+
+/// Records that the command of the current request passed the stage. Does nothing outside of
+/// requests, e.g. for scheduled commands.
+pub fn mark(stage: &'static str) {
+    let _ = COMMAND_TRACE.try_with(|trace| {
+        let mut trace = trace.borrow_mut();
+        if !trace.finished {
+            trace.stages.push((stage, Instant::now()));
+        }
+    });
+}
+
+/// Ends the trace of the current request and returns it, if this was its first command.
+pub fn finish() -> Option<FinishedTrace> {
+    COMMAND_TRACE
+        .try_with(|trace| {
+            let mut trace = trace.borrow_mut();
+            if trace.finished {
+                return None;
+            }
+            trace.finished = true;
+            let mut previous = trace.received;
+            let stages = trace
+                .stages
+                .iter()
+                .map(|(stage, at)| {
+                    let duration = at.duration_since(previous);
+                    previous = *at;
+                    (*stage, duration)
+                })
+                .collect();
+            Some(FinishedTrace {
+                request: trace.request.clone(),
+                latency: trace.received.elapsed(),
+                stages,
+            })
+        })
+        .ok()
+        .flatten()
+}
+
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandLatencyStats {
+    pub budget_ms: u64,
+    /// Number of measured commands since the server started.
+    pub commands: u64,
+    pub over_budget: u64,
+    /// Percentiles of the recent commands.
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+#[derive(Default)]
+struct LatencySamples {
+    samples: VecDeque<Duration>,
+    commands: u64,
+    over_budget: u64,
+}
+
+/// Measures the time from receiving a request until its command was written to the websockets
+/// and warns if it exceeds the budget, so performance regressions are noticed early.
+pub struct CommandLatencyTracker {
+    budget: Duration,
+    samples: RwLock<LatencySamples>,
+}
+
+impl CommandLatencyTracker {
+    pub fn new(budget_ms: u64) -> CommandLatencyTracker {
+        CommandLatencyTracker {
+            budget: Duration::from_millis(budget_ms),
+            samples: RwLock::new(LatencySamples::default()),
+        }
+    }
+
+    pub async fn record(&self, instance: &str, trace: FinishedTrace) {
+        if trace.latency > self.budget {
+            warn!(
+                "Command to instance {} ({}) took {:.1} ms, exceeding the budget of {} ms: {}.",
+                instance,
+                trace.request,
+                to_millis(trace.latency),
+                self.budget.as_millis(),
+                trace
+            );
+        }
+
+        let mut samples = self.samples.write().await;
+        if samples.samples.len() >= MAX_SAMPLES {
+            samples.samples.pop_front();
+        }
+        samples.samples.push_back(trace.latency);
+        samples.commands += 1;
+        if trace.latency > self.budget {
+            samples.over_budget += 1;
+        }
+    }
+
+    pub async fn get_stats(&self) -> CommandLatencyStats {
+        let samples = self.samples.read().await;
+        let mut sorted: Vec<Duration> = samples.samples.iter().copied().collect();
+        sorted.sort();
+        CommandLatencyStats {
+            budget_ms: self.budget.as_millis() as u64,
+            commands: samples.commands,
+            over_budget: samples.over_budget,
+            p50_ms: to_millis(get_percentile(&sorted, 50)),
+            p95_ms: to_millis(get_percentile(&sorted, 95)),
+            p99_ms: to_millis(get_percentile(&sorted, 99)),
+        }
+    }
+}
+
+/// Returns the percentile of the sorted durations with the nearest-rank method.
+pub fn get_percentile(sorted: &[Duration], percentile: usize) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (percentile * sorted.len()).div_ceil(100);
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn to_millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles() {
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(Duration::from_millis(50), get_percentile(&sorted, 50));
+        assert_eq!(Duration::from_millis(99), get_percentile(&sorted, 99));
+        assert_eq!(
+            Duration::from_millis(7),
+            get_percentile(&[Duration::from_millis(7)], 95)
+        );
+        assert_eq!(Duration::ZERO, get_percentile(&[], 50));
+    }
+
+    #[tokio::test]
+    async fn test_only_first_command_of_request_is_measured() {
+        let request = String::from("POST /api/instance/scoreboard/snapshot/kickoff/apply");
+        let (first, second) = trace(request, async {
+            mark("state");
+            mark("websocket");
+            let first = finish();
+            mark("websocket");
+            (first, finish())
+        })
+        .await;
+        let first = first.unwrap();
+        assert_eq!(
+            vec!["state", "websocket"],
+            first
+                .stages
+                .iter()
+                .map(|(stage, _)| *stage)
+                .collect::<Vec<_>>()
+        );
+        assert!(second.is_none());
+
+        // outside of requests, nothing is traced
+        mark("state");
+        assert!(finish().is_none());
+
+        let tracker = CommandLatencyTracker::new(20);
+        tracker.record("scoreboard", first).await;
+        let stats = tracker.get_stats().await;
+        assert_eq!(1, stats.commands);
+        assert_eq!(0, stats.over_budget);
+    }
+}
@@ -0,0 +1,262 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context};
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::WebSocketStream;
+
+use crate::config::ZagreusServerConfig;
+
+const TLS_SUBFOLDER_NAME: &str = "tls";
+const SELF_SIGNED_CERT_FILE_NAME: &str = "self-signed-cert.pem";
+const SELF_SIGNED_KEY_FILE_NAME: &str = "self-signed-key.pem";
+
+#[derive(Clone)]
+pub struct CertificateFiles {
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+}
+
+/// Returns the configured certificate files. If none are configured, a self-signed certificate
+/// for localhost and the bind address is generated in the data folder, unless it exists already.
+pub fn get_certificate_files(
+    configuration: &ZagreusServerConfig,
+) -> anyhow::Result<CertificateFiles> {
+    if let (Some(cert_file), Some(key_file)) =
+        (&configuration.tls.cert_file, &configuration.tls.key_file)
+    {
+        return Ok(CertificateFiles {
+            cert_file: cert_file.clone(),
+            key_file: key_file.clone(),
+        });
+    }
+
+    let tls_folder = configuration.data_folder.join(TLS_SUBFOLDER_NAME);
+    let files = CertificateFiles {
+        cert_file: tls_folder.join(SELF_SIGNED_CERT_FILE_NAME),
+        key_file: tls_folder.join(SELF_SIGNED_KEY_FILE_NAME),
+    };
+    if !files.cert_file.exists() || !files.key_file.exists() {
+        std::fs::create_dir_all(&tls_folder)
+            .with_context(|| format!("Could not create folder {tls_folder:?}"))?;
+        generate_self_signed_certificate(&files, configuration.bind_address)?;
+        info!(
+            "Generated self-signed certificate {:?} for development.",
+            files.cert_file
+        );
+    }
+    warn!("Using a self-signed certificate, which clients have to trust explicitly.");
+    Ok(files)
+}
+
+fn generate_self_signed_certificate(
+    files: &CertificateFiles,
+    bind_address: IpAddr,
+) -> anyhow::Result<()> {
+    let mut params = rcgen::CertificateParams::default();
+    params.subject_alt_names = vec![
+        rcgen::SanType::DnsName(String::from("localhost")),
+        rcgen::SanType::IpAddress(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        rcgen::SanType::IpAddress(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+    ];
+    if !bind_address.is_unspecified() && !bind_address.is_loopback() {
+        params
+            .subject_alt_names
+            .push(rcgen::SanType::IpAddress(bind_address));
+    }
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "Zagreus development certificate");
+    let certificate = rcgen::Certificate::from_params(params)?;
+    std::fs::write(&files.cert_file, certificate.serialize_pem()?)?;
+    crate::fs::write_private_file(
+        &files.key_file,
+        certificate.serialize_private_key_pem().as_bytes(),
+    )
+    .with_context(|| format!("Could not write private key {:?}", files.key_file))?;
+    Ok(())
+}
+
+pub async fn load_server_config(files: &CertificateFiles) -> anyhow::Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(&files.cert_file, &files.key_file)
+        .await
+        .with_context(|| format!("Could not load certificate {:?}", files.cert_file))
+}
+
+/// Reloads the certificate when one of its files changed, e.g. after it was renewed. The
+/// previous certificate stays in use if the new one cannot be loaded.
+pub async fn run_certificate_reload(
+    rustls_config: RustlsConfig,
+    files: CertificateFiles,
+    interval: Duration,
+) {
+    let mut last_modified = get_last_modified(&files);
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        let modified = get_last_modified(&files);
+        if modified == last_modified {
+            continue;
+        }
+        match rustls_config
+            .reload_from_pem_file(&files.cert_file, &files.key_file)
+            .await
+        {
+            Ok(()) => {
+                info!("Reloaded certificate {:?}.", files.cert_file);
+                last_modified = modified;
+            }
+            // the files may be written partially, so loading is retried with the next check
+            Err(err) => warn!(
+                "Could not reload certificate {:?}: {}.",
+                files.cert_file, err
+            ),
+        }
+    }
+}
+
+fn get_last_modified(files: &CertificateFiles) -> Option<(SystemTime, SystemTime)> {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified());
+    Some((
+        modified(&files.cert_file).ok()?,
+        modified(&files.key_file).ok()?,
+    ))
+}
+
+pub trait LocalStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> LocalStream for T {}
+
+/// The server as seen by connections to itself, e.g. of the synthetic monitor or of the handover
+/// to a new process. With TLS, only the certificate of the server is accepted, regardless of the
+/// names it was issued for.
+#[derive(Clone)]
+pub struct LocalServer {
+    pub address: SocketAddr,
+    pub cert_file: Option<PathBuf>,
+}
+
+impl LocalServer {
+    pub fn new(
+        configuration: &ZagreusServerConfig,
+        files: Option<&CertificateFiles>,
+    ) -> LocalServer {
+        LocalServer {
+            address: configuration.get_local_address(),
+            cert_file: files.map(|files| files.cert_file.clone()),
+        }
+    }
+
+    /// Returns the URL of the path, with a secure scheme if TLS is enabled.
+    pub fn get_url(&self, scheme: &str, path: &str) -> String {
+        let secure = if self.cert_file.is_some() { "s" } else { "" };
+        format!("{scheme}{secure}://{}{path}", self.address)
+    }
+
+    pub fn get_http_client(&self, timeout: Duration) -> anyhow::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder().no_proxy().timeout(timeout);
+        if let Some(cert_file) = &self.cert_file {
+            builder = builder.use_preconfigured_tls(get_pinned_client_config(cert_file)?);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Connects to the websocket at the path, sending the headers with the upgrade request.
+    pub async fn connect_websocket(
+        &self,
+        path: &str,
+        headers: &[(&str, &str)],
+    ) -> anyhow::Result<WebSocketStream<Box<dyn LocalStream>>> {
+        let mut request = self.get_url("ws", path).into_client_request()?;
+        for (name, value) in headers {
+            request.headers_mut().insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        let stream = tokio::net::TcpStream::connect(self.address).await?;
+        let stream: Box<dyn LocalStream> = match &self.cert_file {
+            Some(cert_file) => {
+                let connector = tokio_rustls::TlsConnector::from(Arc::new(
+                    get_pinned_client_config(cert_file)?,
+                ));
+                let server_name = rustls::ServerName::IpAddress(self.address.ip());
+                Box::new(connector.connect(server_name, stream).await?)
+            }
+            None => Box::new(stream),
+        };
+        let (socket, _) = tokio_tungstenite::client_async(request, stream).await?;
+        Ok(socket)
+    }
+}
+
+fn get_pinned_client_config(cert_file: &Path) -> anyhow::Result<rustls::ClientConfig> {
+    let pem = std::fs::read(cert_file)
+        .with_context(|| format!("Could not read certificate {cert_file:?}"))?;
+    let certificate = rustls_pemfile::certs(&mut pem.as_slice())?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("No certificate found in {:?}", cert_file))?;
+    Ok(rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(PinnedCertificateVerifier { certificate }))
+        .with_no_client_auth())
+}
+
+struct PinnedCertificateVerifier {
+    certificate: Vec<u8>,
+}
+
+impl rustls::client::ServerCertVerifier for PinnedCertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        _intermediates: &[rustls::Certificate],
+        _server_name: &rustls::ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<rustls::client::ServerCertVerified, rustls::Error> {
+        if end_entity.0 == self.certificate {
+            Ok(rustls::client::ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::General(String::from(
+                "certificate is not the certificate of this server",
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_self_signed_certificate() {
+        let path = crate::fs::temp::prepare_temp_folder().unwrap();
+        let configuration = ZagreusServerConfig {
+            data_folder: path.clone(),
+            ..ZagreusServerConfig::default()
+        };
+        let files = get_certificate_files(&configuration).unwrap();
+        assert!(files.cert_file.starts_with(&path));
+        assert!(load_server_config(&files).await.is_ok());
+        let local_server = LocalServer::new(&configuration, Some(&files));
+        assert!(local_server
+            .get_url("ws", "/ws/instance/scoreboard")
+            .starts_with("wss://127.0.0.1:"));
+        assert!(local_server.get_http_client(Duration::from_secs(1)).is_ok());
+
+        // an existing certificate is not replaced
+        let last_modified = get_last_modified(&files);
+        get_certificate_files(&configuration).unwrap();
+        assert_eq!(last_modified, get_last_modified(&files));
+
+        crate::fs::temp::delete_temp_folder(&path).unwrap();
+    }
+}
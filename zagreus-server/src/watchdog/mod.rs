@@ -0,0 +1,195 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+
+use crate::config::{WatchdogAction, WatchdogConfig};
+use crate::controller::ServerController;
+use crate::events::EventKind;
+
+const WATCHDOG_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Transition {
+    Unresponsive,
+    Recovered,
+}
+
+/// Tracks whether the renderers of a channel are responsive. The watch is only armed once a
+/// renderer was responsive, so instances which are not played out do not raise alerts.
+#[derive(Default)]
+struct RendererWatch {
+    armed: bool,
+    unresponsive: bool,
+}
+
+impl RendererWatch {
+    fn update(&mut self, responsive: bool) -> Option<Transition> {
+        if responsive {
+            self.armed = true;
+            if self.unresponsive {
+                self.unresponsive = false;
+                return Some(Transition::Recovered);
+            }
+        } else if self.armed && !self.unresponsive {
+            self.unresponsive = true;
+            return Some(Transition::Unresponsive);
+        }
+        None
+    }
+}
+
+/// Watches the renderers of a channel of the instance (e.g. the program renderer). If none of
+/// them answered a ping within the maximum silence, or the last one disconnected, the configured
+/// recovery actions are executed once, until a renderer of the channel is responsive again.
+pub async fn run_renderer_watchdog(
+    controller: Arc<ServerController>,
+    configuration: WatchdogConfig,
+) {
+    info!(
+        "Watching {} renderers of instance {} (maximum silence: {} s).",
+        configuration.channel, configuration.instance, configuration.max_silence_seconds
+    );
+    let max_silence = Duration::from_secs(configuration.max_silence_seconds);
+    let mut watch = RendererWatch::default();
+    let mut interval = tokio::time::interval(WATCHDOG_CHECK_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        let responsive = controller
+            .has_responsive_client(&configuration.instance, &configuration.channel, max_silence)
+            .await;
+        match watch.update(responsive) {
+            Some(Transition::Unresponsive) => {
+                warn!(
+                    "No {} renderer of instance {} responded within {} s.",
+                    configuration.channel,
+                    configuration.instance,
+                    configuration.max_silence_seconds
+                );
+                controller
+                    .record_event(
+                        &configuration.instance,
+                        EventKind::RendererUnresponsive {
+                            channel: configuration.channel.clone(),
+                        },
+                    )
+                    .await;
+                for action in &configuration.actions {
+                    execute_action(&controller, &configuration, action, max_silence).await;
+                }
+            }
+            Some(Transition::Recovered) => {
+                info!(
+                    "A {} renderer of instance {} is responsive again.",
+                    configuration.channel, configuration.instance
+                );
+                controller
+                    .record_event(
+                        &configuration.instance,
+                        EventKind::RendererRecovered {
+                            channel: configuration.channel.clone(),
+                        },
+                    )
+                    .await;
+            }
+            None => {}
+        }
+    }
+}
+
+async fn execute_action(
+    controller: &ServerController,
+    configuration: &WatchdogConfig,
+    action: &WatchdogAction,
+    max_silence: Duration,
+) {
+    match action {
+        WatchdogAction::Webhook { url } => {
+            let body = json!({
+                "instance": configuration.instance,
+                "channel": configuration.channel,
+                "maxSilenceSeconds": configuration.max_silence_seconds,
+            });
+            if let Err(err) = controller.post_webhook(url, &body).await {
+                error!(
+                    "Could not call recovery webhook of instance {}: {:#}.",
+                    configuration.instance, err
+                );
+            }
+        }
+        WatchdogAction::PromoteBackup { channel } => {
+            match controller
+                .switch_client_channel(
+                    &configuration.instance,
+                    channel,
+                    &configuration.channel,
+                    max_silence,
+                )
+                .await
+            {
+                Some(client) => {
+                    info!(
+                        "Switched {} renderer {} of instance {} to {}.",
+                        channel, client, configuration.instance, configuration.channel
+                    );
+                    controller
+                        .record_event(
+                            &configuration.instance,
+                            EventKind::ClientPromoted {
+                                client,
+                                channel: configuration.channel.clone(),
+                            },
+                        )
+                        .await;
+                }
+                None => error!(
+                    "No responsive {} renderer of instance {} to switch to {}.",
+                    channel, configuration.instance, configuration.channel
+                ),
+            }
+        }
+        WatchdogAction::Alert => error!(
+            "Renderer alert: the {} renderer of instance {} is unresponsive.",
+            configuration.channel, configuration.instance
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_actions_are_triggered_once_per_outage() {
+        let mut watch = RendererWatch::default();
+        // not armed before a renderer was responsive
+        assert_eq!(None, watch.update(false));
+        assert_eq!(None, watch.update(true));
+        assert_eq!(Some(Transition::Unresponsive), watch.update(false));
+        assert_eq!(None, watch.update(false));
+        assert_eq!(Some(Transition::Recovered), watch.update(true));
+        assert_eq!(None, watch.update(true));
+        assert_eq!(Some(Transition::Unresponsive), watch.update(false));
+    }
+
+    #[test]
+    fn test_parse_actions() {
+        let actions: Vec<WatchdogAction> = serde_json::from_str(
+            r#"[{"action":"webhook","url":"http://obs.local/restart"},{"action":"promoteBackup"},{"action":"alert"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                WatchdogAction::Webhook {
+                    url: String::from("http://obs.local/restart")
+                },
+                WatchdogAction::PromoteBackup {
+                    channel: String::from("backup")
+                },
+                WatchdogAction::Alert,
+            ],
+            actions
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::data::asset::AssetReference;
+use crate::resilience::IntegrationMonitor;
+use crate::state::InstanceState;
+
+pub const WEBHOOKS_FILE_NAME: &str = "webhooks.json";
+
+/// Posts the data changes of an instance to an external system, e.g. a venue scoreboard or a
+/// website widget mirroring what the graphics show.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub name: String,
+    pub url: String,
+    /// Elements whose changes are posted, changes of all elements if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub elements: Vec<String>,
+}
+
+/// Webhooks of an instance.
+pub type Webhooks = Vec<Webhook>;
+
+/// Change of the data of an element with the value before and after the change. A value is
+/// `None` if the element had no such data, e.g. a text which was set for the first time.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DataChange {
+    Text {
+        element: String,
+        old: Option<String>,
+        new: Option<String>,
+    },
+    Class {
+        element: String,
+        class: String,
+        old: Option<bool>,
+        new: Option<bool>,
+    },
+    Image {
+        element: String,
+        old: Option<AssetReference>,
+        new: Option<AssetReference>,
+    },
+}
+
+impl DataChange {
+    pub fn get_element(&self) -> &str {
+        match self {
+            DataChange::Text { element, .. }
+            | DataChange::Class { element, .. }
+            | DataChange::Image { element, .. } => element,
+        }
+    }
+}
+
+/// Body posted to the URL of a webhook.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookPayload {
+    pub instance: String,
+    pub webhook: String,
+    pub timestamp: DateTime<Utc>,
+    pub changes: Vec<DataChange>,
+}
+
+struct WebhookDelivery {
+    url: String,
+    body: serde_json::Value,
+}
+
+/// Delivers webhooks with retries and circuit breaker. Every webhook has its own queue whose
+/// deliveries are posted one after another, so a mirror never receives a change before an
+/// earlier one, while a slow webhook does not delay the others.
+pub struct WebhookQueues {
+    client: reqwest::Client,
+    integrations: Arc<IntegrationMonitor>,
+    queues: Mutex<HashMap<String, UnboundedSender<WebhookDelivery>>>,
+}
+
+impl WebhookQueues {
+    pub fn new(client: reqwest::Client, integrations: Arc<IntegrationMonitor>) -> WebhookQueues {
+        WebhookQueues {
+            client,
+            integrations,
+            queues: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Queues the body for the webhook with the name, e.g. `scoreboard/venue-board`, without
+    /// waiting for the delivery.
+    pub fn enqueue(&self, name: &str, url: &str, body: serde_json::Value) {
+        let mut queues = self
+            .queues
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let delivery = WebhookDelivery {
+            url: url.to_owned(),
+            body,
+        };
+        let delivery = match queues.get(name) {
+            Some(sender) => match sender.send(delivery) {
+                Ok(()) => return,
+                Err(err) => err.0,
+            },
+            None => delivery,
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(deliver_webhooks(
+            name.to_owned(),
+            receiver,
+            self.client.clone(),
+            self.integrations.clone(),
+        ));
+        sender.send(delivery).ok();
+        queues.insert(name.to_owned(), sender);
+    }
+}
+
+async fn deliver_webhooks(
+    name: String,
+    mut receiver: UnboundedReceiver<WebhookDelivery>,
+    client: reqwest::Client,
+    integrations: Arc<IntegrationMonitor>,
+) {
+    while let Some(delivery) = receiver.recv().await {
+        if let Err(err) = post_webhook(&integrations, &client, &delivery.url, &delivery.body).await
+        {
+            error!("Could not call webhook {}: {:#}.", name, err);
+        }
+    }
+}
+
+/// Posts the JSON body to the URL as integration of its host, with retries and circuit breaker.
+pub async fn post_webhook(
+    integrations: &IntegrationMonitor,
+    client: &reqwest::Client,
+    url: &str,
+    body: &serde_json::Value,
+) -> anyhow::Result<()> {
+    let host = reqwest::Url::parse(url)?
+        .host_str()
+        .unwrap_or_default()
+        .to_owned();
+    integrations
+        .execute(&format!("webhook:{host}"), || {
+            crate::outbound::post_json(client, url, body)
+        })
+        .await
+}
+
+impl Webhook {
+    /// Returns the payload of the changes the webhook is interested in, `None` if there are none.
+    pub fn get_payload(&self, instance: &str, changes: &[DataChange]) -> Option<WebhookPayload> {
+        let changes: Vec<DataChange> = changes
+            .iter()
+            .filter(|change| {
+                self.elements.is_empty()
+                    || self
+                        .elements
+                        .iter()
+                        .any(|element| element == change.get_element())
+            })
+            .cloned()
+            .collect();
+        if changes.is_empty() {
+            return None;
+        }
+        Some(WebhookPayload {
+            instance: instance.to_owned(),
+            webhook: self.name.clone(),
+            timestamp: Utc::now(),
+            changes,
+        })
+    }
+}
+
+/// Returns an error message if a webhook is incomplete or its name is not unique.
+pub fn validate_webhooks(webhooks: &[Webhook]) -> Option<String> {
+    let mut names = HashSet::new();
+    for webhook in webhooks {
+        if webhook.name.is_empty() {
+            return Some(String::from("Every webhook needs a name."));
+        }
+        if !names.insert(&webhook.name) {
+            return Some(format!(
+                "Webhook {} is declared more than once.",
+                webhook.name
+            ));
+        }
+        let valid_url = reqwest::Url::parse(&webhook.url)
+            .is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https");
+        if !valid_url {
+            return Some(format!(
+                "Webhook {} has an invalid URL: {}.",
+                webhook.name, webhook.url
+            ));
+        }
+    }
+    None
+}
+
+/// Returns the changes of the texts, classes and images from the previous to the current state.
+pub fn get_changes(previous: &InstanceState, current: &InstanceState) -> Vec<DataChange> {
+    let mut changes: Vec<DataChange> = diff(&previous.texts, &current.texts)
+        .map(|(element, old, new)| DataChange::Text {
+            element: element.clone(),
+            old: old.cloned(),
+            new: new.cloned(),
+        })
+        .collect();
+    let no_classes = BTreeMap::new();
+    let elements: BTreeSet<&String> = previous
+        .classes
+        .keys()
+        .chain(current.classes.keys())
+        .collect();
+    for element in elements {
+        let previous_classes = previous.classes.get(element).unwrap_or(&no_classes);
+        let current_classes = current.classes.get(element).unwrap_or(&no_classes);
+        changes.extend(
+            diff(previous_classes, current_classes).map(|(class, old, new)| DataChange::Class {
+                element: element.clone(),
+                class: class.clone(),
+                old: old.copied(),
+                new: new.copied(),
+            }),
+        );
+    }
+    changes.extend(
+        diff(&previous.images, &current.images).map(|(element, old, new)| DataChange::Image {
+            element: element.clone(),
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+    );
+    changes
+}
+
+/// Returns the keys whose values differ, together with the previous and the current value.
+fn diff<'a, T: PartialEq>(
+    previous: &'a BTreeMap<String, T>,
+    current: &'a BTreeMap<String, T>,
+) -> impl Iterator<Item = (&'a String, Option<&'a T>, Option<&'a T>)> {
+    let keys: BTreeSet<&String> = previous.keys().chain(current.keys()).collect();
+    keys.into_iter().filter_map(|key| {
+        let old = previous.get(key);
+        let new = current.get(key);
+        (old != new).then_some((key, old, new))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::asset::AssetSource;
+
+    #[test]
+    fn test_get_changes() {
+        let previous: InstanceState = serde_json::from_str(
+            r#"{"texts":{"HomeScore":"0","AwayScore":"0"},"classes":{"Clock":{"warning":false}}}"#,
+        )
+        .unwrap();
+        let current: InstanceState = serde_json::from_str(
+            r#"{"texts":{"HomeScore":"1","AwayScore":"0"},"classes":{"Clock":{"warning":true}},
+                "images":{"HomeLogo":{"asset":"home.png","assetSource":"zagreus"}}}"#,
+        )
+        .unwrap();
+        let changes = get_changes(&previous, &current);
+        assert_eq!(
+            vec![
+                DataChange::Text {
+                    element: String::from("HomeScore"),
+                    old: Some(String::from("0")),
+                    new: Some(String::from("1")),
+                },
+                DataChange::Class {
+                    element: String::from("Clock"),
+                    class: String::from("warning"),
+                    old: Some(false),
+                    new: Some(true),
+                },
+                DataChange::Image {
+                    element: String::from("HomeLogo"),
+                    old: None,
+                    new: Some(AssetReference {
+                        asset: String::from("home.png"),
+                        asset_source: AssetSource::Zagreus,
+                    }),
+                },
+            ],
+            changes
+        );
+        assert!(get_changes(&current, &current).is_empty());
+
+        let webhook = Webhook {
+            name: String::from("scoreboard"),
+            url: String::from("http://scoreboard.venue.local/zagreus"),
+            elements: vec![String::from("HomeScore"), String::from("AwayScore")],
+        };
+        assert_eq!(None, validate_webhooks(std::slice::from_ref(&webhook)));
+        let payload = webhook.get_payload("match", &changes).unwrap();
+        assert_eq!(vec![changes[0].clone()], payload.changes);
+        assert_eq!(None, webhook.get_payload("match", &changes[1..]));
+        let all_elements = Webhook {
+            elements: Vec::new(),
+            ..webhook.clone()
+        };
+        assert_eq!(
+            3,
+            all_elements
+                .get_payload("match", &changes)
+                .unwrap()
+                .changes
+                .len()
+        );
+
+        let invalid_url = Webhook {
+            url: String::from("ftp://scoreboard.venue.local"),
+            ..webhook.clone()
+        };
+        assert!(validate_webhooks(&[invalid_url]).is_some());
+        assert!(validate_webhooks(&[webhook.clone(), webhook]).is_some());
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::{BTreeSet, HashMap};
+
+use tokio::sync::RwLock;
+
+/// Animation sequences and element ids of a template as reported by its renderers.
+#[derive(Clone, Default, Debug)]
+pub struct ReportedTemplate {
+    pub animation_sequences: BTreeSet<String>,
+    pub elements: BTreeSet<String>,
+}
+
+/// Templates reported by the renderers of every instance. Reports of all renderers are merged,
+/// since renderers of different output variants can register different animations, and are kept
+/// after the renderers disconnected.
+#[derive(Default)]
+pub struct ReportedTemplates {
+    templates: RwLock<HashMap<String, ReportedTemplate>>,
+}
+
+impl ReportedTemplates {
+    pub async fn report(
+        &self,
+        instance: &str,
+        animation_sequences: Vec<String>,
+        elements: Vec<String>,
+    ) {
+        let mut templates = self.templates.write().await;
+        let template = templates.entry(instance.to_owned()).or_default();
+        template.animation_sequences.extend(animation_sequences);
+        template.elements.extend(elements);
+    }
+
+    pub async fn get(&self, instance: &str) -> ReportedTemplate {
+        self.templates
+            .read()
+            .await
+            .get(instance)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub async fn remove_instance(&self, instance: &str) {
+        self.templates.write().await.remove(instance);
+    }
+}
@@ -1,20 +1,101 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
 use tokio::sync::mpsc::UnboundedSender;
 
-use crate::websocket::message::InstanceMessage;
+use crate::metrics::Metrics;
+use crate::websocket::latency::{ClientLatency, LatencyEstimator};
+use crate::websocket::message::{InstanceMessage, PrecacheProgress};
+use crate::websocket::pair::PairRole;
+use crate::websocket::parameters::{ClientOrigin, ClientParameters};
+use crate::websocket::placeholders;
+use crate::websocket::topics;
 
 pub struct WebsocketConnection {
     message_sender: UnboundedSender<Result<axum::extract::ws::Message, axum::Error>>,
     instance: String,
+    parameters: ClientParameters,
+    origin: ClientOrigin,
+    connected_at: DateTime<Utc>,
+    precache_progress: Option<PrecacheProgress>,
+    latency_estimator: LatencyEstimator,
+    latency: Option<ClientLatency>,
+    /// Role in the redundant pair, if the client belongs to one.
+    pair_role: Option<PairRole>,
+    /// Time of the last answer to a ping, or of connecting if the client did not answer yet.
+    last_heartbeat: Instant,
+    /// Time of the last message of any kind received from the client, including answers to the
+    /// pings of the websocket protocol.
+    last_seen: DateTime<Utc>,
+    /// Filters of the topics the client subscribed to.
+    subscriptions: Vec<String>,
+    /// Bytes queued for sending, decreased once the messages were forwarded to the socket.
+    buffered_bytes: Arc<AtomicUsize>,
+    metrics: Arc<Metrics>,
 }
 
 impl WebsocketConnection {
     pub fn new(
         message_sender: UnboundedSender<Result<axum::extract::ws::Message, axum::Error>>,
         instance: String,
+        parameters: ClientParameters,
+        origin: ClientOrigin,
+        buffered_bytes: Arc<AtomicUsize>,
+        metrics: Arc<Metrics>,
     ) -> WebsocketConnection {
         WebsocketConnection {
             message_sender,
             instance,
+            parameters,
+            origin,
+            connected_at: Utc::now(),
+            precache_progress: None,
+            latency_estimator: LatencyEstimator::default(),
+            latency: None,
+            pair_role: None,
+            last_heartbeat: Instant::now(),
+            last_seen: Utc::now(),
+            subscriptions: Vec::new(),
+            buffered_bytes,
+            metrics,
+        }
+    }
+
+    pub fn get_instance(&self) -> &str {
+        &self.instance
+    }
+
+    pub fn get_parameters(&self) -> &ClientParameters {
+        &self.parameters
+    }
+
+    pub fn get_origin(&self) -> &ClientOrigin {
+        &self.origin
+    }
+
+    pub fn get_connected_at(&self) -> DateTime<Utc> {
+        self.connected_at
+    }
+
+    /// Assigns the client to another channel, e.g. when a backup renderer is switched to program.
+    pub fn set_channel(&mut self, channel: &str) {
+        self.parameters.channel = Some(channel.to_owned());
+    }
+
+    pub fn get_pair_role(&self) -> Option<PairRole> {
+        self.pair_role
+    }
+
+    /// Assigns the role in the pair and notifies the client, e.g. to show a tally.
+    pub fn set_pair_role(&mut self, role: PairRole) {
+        self.pair_role = Some(role);
+        if let Some(pair) = &self.parameters.pair {
+            self.send_message(&InstanceMessage::SetPairRole {
+                pair,
+                active: role == PairRole::Active,
+            });
         }
     }
 
@@ -22,12 +103,94 @@ impl WebsocketConnection {
         self.instance.eq(instance)
     }
 
+    pub fn subscribe(&mut self, filters: &[String]) {
+        for filter in filters {
+            if !self.subscriptions.contains(filter) {
+                self.subscriptions.push(filter.clone());
+            }
+        }
+    }
+
+    pub fn is_subscribed(&self, topic: &str) -> bool {
+        self.subscriptions
+            .iter()
+            .any(|filter| topics::matches_filter(topic, filter))
+    }
+
+    pub fn get_precache_progress(&self) -> Option<&PrecacheProgress> {
+        self.precache_progress.as_ref()
+    }
+
+    pub fn set_precache_progress(&mut self, precache_progress: PrecacheProgress) {
+        self.precache_progress = Some(precache_progress);
+    }
+
+    pub fn get_buffered_bytes(&self) -> usize {
+        self.buffered_bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn get_latency(&self) -> Option<ClientLatency> {
+        self.latency
+    }
+
+    /// Updates the latency with the answer to a ping and returns the new estimation.
+    pub fn add_latency_sample(
+        &mut self,
+        server_sent: i64,
+        client_time: i64,
+        server_received: i64,
+    ) -> ClientLatency {
+        let latency = self
+            .latency_estimator
+            .add_sample(server_sent, client_time, server_received);
+        self.latency = Some(latency);
+        self.last_heartbeat = Instant::now();
+        latency
+    }
+
+    /// Returns the time since the client last answered a ping.
+    pub fn get_silence(&self) -> Duration {
+        self.last_heartbeat.elapsed()
+    }
+
+    pub fn get_last_seen(&self) -> DateTime<Utc> {
+        self.last_seen
+    }
+
+    pub fn mark_seen(&mut self) {
+        self.last_seen = Utc::now();
+    }
+
+    /// Sends a ping of the websocket protocol, which browsers answer without the runtime.
+    pub fn send_ping(&self) {
+        let ping = axum::extract::ws::Message::Ping(Vec::new());
+        if let Err(err) = self.message_sender.send(Ok(ping)) {
+            debug!("Could not send ping on channel: {}.", err);
+        }
+    }
+
+    /// Closes the websocket, after which the client reconnects.
+    pub fn close(&self) {
+        let close_message = axum::extract::ws::Message::Close(None);
+        if let Err(err) = self.message_sender.send(Ok(close_message)) {
+            error!("Could not send close message on channel: {}.", err);
+        }
+    }
+
     pub fn send_message(&self, message: &InstanceMessage) {
-        match serde_json::to_string(message) {
+        let serialized_message = if placeholders::has_placeholders(message) {
+            placeholders::serialize_for_client(message, &self.parameters)
+        } else {
+            serde_json::to_string(message)
+        };
+        match serialized_message {
             Ok(serialized_message) => {
+                self.buffered_bytes
+                    .fetch_add(serialized_message.len(), Ordering::Relaxed);
                 let ws_message = axum::extract::ws::Message::Text(serialized_message);
-                if let Err(err) = self.message_sender.send(Ok(ws_message)) {
-                    error!("Could not send websocket message on channel: {}.", err);
+                match self.message_sender.send(Ok(ws_message)) {
+                    Ok(()) => self.metrics.record_message_sent(),
+                    Err(err) => error!("Could not send websocket message on channel: {}.", err),
                 }
             }
             Err(err) => error!("Could not serialize message: {}.", err),
@@ -0,0 +1,234 @@
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::stream::SplitSink;
+use futures::{SinkExt, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::controller::ServerController;
+use crate::notes::is_valid_note_text;
+use crate::presence::is_valid_operator_name;
+use crate::session::get_acknowledgement_entry;
+use zagreus_client::dto::{
+    ControlAcknowledgement, ControlCommand, ControlNotification, ControlRequest, ControlResult,
+    NoteDto, OperatorNote,
+};
+
+/// Executes the commands of an external controller and acknowledges every command, which avoids
+/// the overhead of HTTP requests for high-frequency updates such as clocks and tickers. Notes of
+/// the operators are sent to every controller, the pinned notes of the show day on connect.
+pub async fn handle_control_commands(
+    socket: WebSocket,
+    controller: Arc<ServerController>,
+    instance: String,
+    may_approve: bool,
+) {
+    info!("Controller connected to instance {}.", instance);
+    let (mut sink, mut stream) = socket.split();
+    let request = format!("WS /ws/control/{instance}");
+    let mut note_changes = controller.subscribe_note_changes();
+
+    let pinned_notes = match controller.get_notes(None).await {
+        Ok(notes) => notes.into_iter().filter(|note| note.pinned).collect(),
+        Err(err) => {
+            error!("Could not load notes: {:#}.", err);
+            Vec::new()
+        }
+    };
+    for note in pinned_notes {
+        if !send_json(&mut sink, &ControlNotification::Note { note }).await {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            message = stream.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let acknowledgement = crate::timing::trace(
+                    request.clone(),
+                    handle_command(&controller, &instance, may_approve, &text),
+                )
+                .await;
+                if controller.is_recording_session().await {
+                    let entry = get_acknowledgement_entry(&instance, &text, &acknowledgement);
+                    controller.record_session_entry(entry).await;
+                }
+                if !send_json(&mut sink, &acknowledgement).await {
+                    break;
+                }
+            }
+            change = note_changes.recv() => match change {
+                Ok(note) => {
+                    if !send_json(&mut sink, &ControlNotification::Note { note }).await {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Controller of instance {} missed {} notes.", instance, skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
+        }
+    }
+
+    info!("Controller disconnected from instance {}.", instance);
+}
+
+/// Sends the message to the controller and returns whether it is still connected.
+async fn send_json<T: serde::Serialize>(
+    sink: &mut SplitSink<WebSocket, Message>,
+    message: &T,
+) -> bool {
+    let serialized_message = match serde_json::to_string(message) {
+        Ok(serialized_message) => serialized_message,
+        Err(err) => {
+            error!("Could not serialize control message: {}.", err);
+            return false;
+        }
+    };
+    if let Err(err) = sink.send(Message::Text(serialized_message)).await {
+        debug!("Could not send control message: {}.", err);
+        return false;
+    }
+    true
+}
+
+async fn handle_command(
+    controller: &ServerController,
+    instance: &str,
+    may_approve: bool,
+    text: &str,
+) -> ControlAcknowledgement {
+    let request = match parse_request(text) {
+        Ok(request) => request,
+        Err((request_id, err)) => {
+            return ControlAcknowledgement {
+                request_id,
+                result: ControlResult::Error {
+                    message: format!("Invalid command: {err}."),
+                },
+            }
+        }
+    };
+
+    let result = match request.command {
+        ControlCommand::Send { operations } if operations.is_empty() => ControlResult::Error {
+            message: String::from("The command contains no operations."),
+        },
+        ControlCommand::Send { operations } => {
+            match controller.submit_operations(instance, operations).await {
+                Some(change) => ControlResult::Pending { change },
+                None => ControlResult::Sent,
+            }
+        }
+        ControlCommand::Approve { .. } | ControlCommand::Reject { .. } if !may_approve => {
+            ControlResult::Error {
+                message: String::from("API key may not approve changes."),
+            }
+        }
+        ControlCommand::Approve { change } => {
+            match controller.approve_change(instance, change).await {
+                Some(change) => ControlResult::Approved { change },
+                None => change_not_found(),
+            }
+        }
+        ControlCommand::Reject { change } => match controller.reject_change(instance, change).await
+        {
+            Some(change) => ControlResult::Rejected { change },
+            None => change_not_found(),
+        },
+        ControlCommand::Note { text, .. } if !is_valid_note_text(&text) => ControlResult::Error {
+            message: String::from("The note is empty or too long."),
+        },
+        ControlCommand::Note {
+            operator: Some(operator),
+            ..
+        } if !is_valid_operator_name(&operator) => ControlResult::Error {
+            message: String::from("Invalid operator name."),
+        },
+        ControlCommand::Note {
+            text,
+            operator,
+            pinned,
+        } => {
+            let note = NoteDto {
+                text,
+                operator,
+                instance: Some(instance.to_owned()),
+                pinned,
+            };
+            note_result(controller.add_note(note).await.map(Some))
+        }
+        ControlCommand::Pin { note, pinned } => {
+            note_result(controller.pin_note(note, pinned).await)
+        }
+    };
+    ControlAcknowledgement {
+        request_id: request.request_id,
+        result,
+    }
+}
+
+/// Parses the command. Returns the request id with the error if the command is invalid, so
+/// controllers can still match the acknowledgement.
+fn parse_request(text: &str) -> Result<ControlRequest, (Option<u64>, serde_json::Error)> {
+    let value: serde_json::Value = serde_json::from_str(text).map_err(|err| (None, err))?;
+    let request_id = value.get("requestId").and_then(serde_json::Value::as_u64);
+    serde_json::from_value(value).map_err(|err| (request_id, err))
+}
+
+fn note_result(result: anyhow::Result<Option<OperatorNote>>) -> ControlResult {
+    match result {
+        Ok(Some(note)) => ControlResult::Noted { note },
+        Ok(None) => ControlResult::Error {
+            message: String::from("Note not found."),
+        },
+        Err(err) => {
+            error!("Could not store note: {:#}.", err);
+            ControlResult::Error {
+                message: String::from("Could not store note."),
+            }
+        }
+    }
+}
+
+fn change_not_found() -> ControlResult {
+    ControlResult::Error {
+        message: String::from("Pending change not found."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let request = parse_request(
+            r#"{"requestId":7,"command":"send","operations":[{"operation":"setText","id":"Clock","text":"12:01"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(Some(7), request.request_id);
+        assert!(matches!(
+            request.command,
+            ControlCommand::Send { operations } if operations.len() == 1
+        ));
+
+        let (request_id, _) = parse_request(r#"{"requestId":8,"command":"take"}"#).unwrap_err();
+        assert_eq!(Some(8), request_id);
+
+        let acknowledgement = ControlAcknowledgement {
+            request_id: Some(7),
+            result: ControlResult::Sent,
+        };
+        assert_eq!(
+            r#"{"requestId":7,"status":"sent"}"#,
+            serde_json::to_string(&acknowledgement).unwrap()
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::controller::ServerController;
+
+/// Sends whether each button of the instance is lit on connect and whenever it changes, so
+/// external control surfaces can light their buttons without polling.
+pub async fn stream_feedback(
+    socket: WebSocket,
+    controller: Arc<ServerController>,
+    instance: String,
+) {
+    info!(
+        "Connected to new button feedback client for instance {}.",
+        instance
+    );
+    let (mut sink, mut stream) = socket.split();
+    let mut state_changes = controller.subscribe_state_changes();
+    let mut feedback_changes = controller.subscribe_feedback_changes();
+    let mut previous_feedback: Option<BTreeMap<String, bool>> = None;
+    let mut changed = true;
+
+    loop {
+        if changed {
+            let feedback = match controller.get_button_feedback(&instance).await {
+                Ok(feedback) => feedback,
+                Err(err) => {
+                    error!(
+                        "Could not get button feedback of instance {}: {}.",
+                        instance, err
+                    );
+                    break;
+                }
+            };
+            if previous_feedback.as_ref() != Some(&feedback) {
+                let serialized_feedback = match serde_json::to_string(&feedback) {
+                    Ok(serialized_feedback) => serialized_feedback,
+                    Err(err) => {
+                        error!("Could not serialize button feedback: {}.", err);
+                        break;
+                    }
+                };
+                if let Err(err) = sink.send(Message::Text(serialized_feedback)).await {
+                    error!("Could not send button feedback: {}.", err);
+                    break;
+                }
+                previous_feedback = Some(feedback);
+            }
+        }
+
+        tokio::select! {
+            change = state_changes.recv() => match change {
+                Ok(changed_instance) => changed = changed_instance == instance,
+                Err(RecvError::Lagged(_)) => changed = true,
+                Err(RecvError::Closed) => break,
+            },
+            change = feedback_changes.recv() => match change {
+                Ok(changed_instance) => changed = changed_instance == instance,
+                Err(RecvError::Lagged(_)) => changed = true,
+                Err(RecvError::Closed) => break,
+            },
+            message = stream.next() => match message {
+                // messages of feedback clients are ignored
+                Some(Ok(_)) => changed = false,
+                Some(Err(_)) | None => break,
+            },
+        }
+    }
+
+    debug!(
+        "Button feedback client of instance {} has disconnected.",
+        instance
+    );
+}
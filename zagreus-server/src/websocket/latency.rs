@@ -0,0 +1,86 @@
+use std::collections::VecDeque;
+
+/// Number of recent measurements from which the clock offset is estimated.
+const MAX_SAMPLES: usize = 8;
+
+/// Round trip time and clock offset of a client, measured with timestamped pings.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientLatency {
+    /// Round trip time of the last ping in milliseconds.
+    pub round_trip_ms: i64,
+    /// Estimated difference of the client clock to the server clock in milliseconds (client time
+    /// minus server time).
+    pub clock_offset_ms: i64,
+}
+
+#[derive(Clone, Copy)]
+struct LatencySample {
+    round_trip_ms: i64,
+    clock_offset_ms: i64,
+}
+
+/// Estimates the clock offset of a client like NTP: the sample with the shortest round trip has
+/// the least asymmetric network delay, so its offset is the most accurate.
+#[derive(Default)]
+pub struct LatencyEstimator {
+    samples: VecDeque<LatencySample>,
+}
+
+impl LatencyEstimator {
+    /// Adds a measurement of a ping sent at `server_sent` and answered at `client_time`, whose
+    /// answer was received at `server_received`. All times are in milliseconds since the epoch.
+    pub fn add_sample(
+        &mut self,
+        server_sent: i64,
+        client_time: i64,
+        server_received: i64,
+    ) -> ClientLatency {
+        let round_trip_ms = (server_received - server_sent).max(0);
+        let clock_offset_ms = client_time - (server_sent + round_trip_ms / 2);
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(LatencySample {
+            round_trip_ms,
+            clock_offset_ms,
+        });
+
+        let best_sample = self
+            .samples
+            .iter()
+            .min_by_key(|sample| sample.round_trip_ms)
+            .copied()
+            .unwrap_or(LatencySample {
+                round_trip_ms,
+                clock_offset_ms,
+            });
+        ClientLatency {
+            round_trip_ms,
+            clock_offset_ms: best_sample.clock_offset_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_offset_of_fastest_round_trip_is_used() {
+        let mut estimator = LatencyEstimator::default();
+        // client clock is 1000 ms ahead, symmetric delay of 10 ms
+        let latency = estimator.add_sample(0, 1010, 20);
+        assert_eq!(20, latency.round_trip_ms);
+        assert_eq!(1000, latency.clock_offset_ms);
+
+        // asymmetric delay on a slow round trip does not change the estimation
+        let latency = estimator.add_sample(100, 1190, 300);
+        assert_eq!(200, latency.round_trip_ms);
+        assert_eq!(1000, latency.clock_offset_ms);
+
+        let latency = estimator.add_sample(400, 1402, 404);
+        assert_eq!(4, latency.round_trip_ms);
+        assert_eq!(1000, latency.clock_offset_ms);
+    }
+}
@@ -0,0 +1,80 @@
+use crate::config::ConnectionLimitsConfig;
+
+/// Websocket close code telling the client to reconnect later.
+pub const TRY_AGAIN_LATER_CLOSE_CODE: u16 = 1013;
+
+/// Current usage of the websocket server, checked against the connection limits.
+pub struct ConnectionUsage {
+    pub connections: usize,
+    /// Connections of the instance the new client connects to.
+    pub instance_connections: usize,
+    /// Bytes queued for sending to all clients.
+    pub buffered_bytes: usize,
+}
+
+/// Returns the reason why a new client of the instance is rejected, if admitting it would exceed
+/// a connection limit.
+pub fn check_admission(
+    limits: &ConnectionLimitsConfig,
+    usage: &ConnectionUsage,
+    instance: &str,
+) -> Option<String> {
+    if let Some(max_connections) = limits.max_connections {
+        if usage.connections >= max_connections {
+            return Some(format!(
+                "Server has reached its maximum of {max_connections} connections."
+            ));
+        }
+    }
+    if let Some(max_instance_connections) = limits.max_connections_per_instance {
+        if usage.instance_connections >= max_instance_connections {
+            return Some(format!(
+                "Instance {instance} has reached its maximum of {max_instance_connections} connections."
+            ));
+        }
+    }
+    if let Some(max_buffered_bytes) = limits.max_buffered_bytes {
+        if usage.buffered_bytes >= max_buffered_bytes {
+            return Some(format!(
+                "Server has {} bytes queued for its clients, which exceeds the maximum of {max_buffered_bytes} bytes.",
+                usage.buffered_bytes
+            ));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_admission() {
+        let limits = ConnectionLimitsConfig {
+            max_connections: Some(10),
+            max_connections_per_instance: Some(2),
+            max_buffered_bytes: Some(1024),
+        };
+        let usage = |connections, instance_connections, buffered_bytes| ConnectionUsage {
+            connections,
+            instance_connections,
+            buffered_bytes,
+        };
+
+        assert!(check_admission(&limits, &usage(9, 1, 0), "scoreboard").is_none());
+        assert!(check_admission(&limits, &usage(10, 1, 0), "scoreboard").is_some());
+        assert_eq!(
+            Some(String::from(
+                "Instance scoreboard has reached its maximum of 2 connections."
+            )),
+            check_admission(&limits, &usage(5, 2, 0), "scoreboard")
+        );
+        assert!(check_admission(&limits, &usage(5, 1, 2048), "scoreboard").is_some());
+        assert!(check_admission(
+            &ConnectionLimitsConfig::default(),
+            &usage(1000, 1000, usize::MAX),
+            "scoreboard"
+        )
+        .is_none());
+    }
+}
@@ -1,50 +1,235 @@
 use std::borrow::Cow;
 
+use schemars::schema::RootSchema;
+use schemars::JsonSchema;
+
+use crate::assets::AssetVersions;
 use crate::data::animation::config::AnimationSequence;
-use crate::data::asset::AssetSource;
+use crate::data::asset::{AssetReference, AssetSource};
 use crate::data::config::TemplateElement;
+use crate::flags::FeatureFlags;
+
+/// Version of the websocket protocol spoken by this server. Must be increased whenever message
+/// types are added or changed in a way that older runtimes cannot handle.
+pub const PROTOCOL_VERSION: u32 = 20;
 
-#[derive(Serialize, Deserialize)]
+/// Messages exchanged between the server and runtime clients. The documentation of every variant
+/// states in which direction it is sent.
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "tag", content = "payload")]
 pub enum InstanceMessage<'a> {
+    /// Server to client: sets the text content of an element. If a tween duration (in
+    /// milliseconds) is set and both the current and the new text are numbers, the client counts
+    /// from the current to the new value instead of snapping.
+    #[serde(rename_all = "camelCase")]
     SetText {
         id: &'a str,
         text: &'a str,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tween_duration: Option<u64>,
     },
-    LogError {
-        message: String,
-        stack: String,
-    },
-    AddClass {
-        id: &'a str,
-        class: &'a str,
-    },
-    RemoveClass {
-        id: &'a str,
-        class: &'a str,
-    },
+    /// Client to server: reports an error that occurred in the template.
+    LogError { message: String, stack: String },
+    /// Server to client: adds a CSS class to an element.
+    AddClass { id: &'a str, class: &'a str },
+    /// Server to client: removes a CSS class from an element.
+    RemoveClass { id: &'a str, class: &'a str },
+    /// Server to client: registers animation sequences (not used by the current runtime).
     LoadAnimations {
         #[serde(borrow)]
-        animations: Cow<'a, Vec<AnimationSequence>>,
+        animations: Cow<'a, [AnimationSequence]>,
     },
+    /// Server to client: registers template elements (not used by the current runtime).
     #[serde(rename_all = "camelCase")]
     LoadElements {
         #[serde(borrow)]
-        elements: Cow<'a, Vec<TemplateElement>>,
+        elements: Cow<'a, [TemplateElement]>,
     },
+    /// Server to client: executes an animation sequence, at the given server time (milliseconds
+    /// since the epoch) if set, so all clients start it on the same frame.
     #[serde(rename_all = "camelCase")]
     ExecuteAnimation {
         animation_sequence: &'a str,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        execute_at: Option<i64>,
     },
+    /// Server to client: animation sequences to execute on load (not used by the current runtime).
     #[serde(rename_all = "camelCase")]
     OnLoad {
         #[serde(borrow)]
-        animation_sequences: Cow<'a, Vec<String>>,
+        animation_sequences: Cow<'a, [String]>,
     },
+    /// Server to client: sets the source of an image element.
     #[serde(rename_all = "camelCase")]
     SetImageSource {
         id: &'a str,
         asset: &'a str,
         asset_source: AssetSource,
     },
+    /// Server to client: the configuration document of the instance.
+    SetConfig {
+        config: Cow<'a, serde_json::Map<String, serde_json::Value>>,
+    },
+    /// Server to client: the effective feature flags of the instance.
+    SetFeatureFlags { flags: Cow<'a, FeatureFlags> },
+    /// Client to server: runtime and protocol version of the client, sent after connecting.
+    #[serde(rename_all = "camelCase")]
+    Handshake {
+        runtime_version: String,
+        protocol_version: u32,
+        required_protocol_version: Option<u32>,
+    },
+    /// Server to client: the client is incompatible with the server or the template.
+    #[serde(rename_all = "camelCase")]
+    IncompatibleVersion {
+        server_protocol_version: u32,
+        message: String,
+    },
+    /// Server to client: pre-fetches assets into the browser cache.
+    PrecacheAssets { assets: Cow<'a, [AssetReference]> },
+    /// Client to server: progress of pre-caching assets.
+    PrecacheProgress(PrecacheProgress),
+    /// Server to client: the current versions of all uploaded assets.
+    SetAssetVersions { versions: Cow<'a, AssetVersions> },
+    /// Server to client: requests a snapshot of the DOM after the client reported an error.
+    #[serde(rename_all = "camelCase")]
+    CaptureDomSnapshot { error_id: u64 },
+    /// Client to server: snapshot of the DOM requested with `CaptureDomSnapshot`.
+    DomSnapshot(DomSnapshot),
+    /// Server to client: measures the latency, answered immediately with `Pong`.
+    #[serde(rename_all = "camelCase")]
+    Ping { server_time: i64 },
+    /// Client to server: answer to `Ping` with the time of the client clock.
+    #[serde(rename_all = "camelCase")]
+    Pong { server_time: i64, client_time: i64 },
+    /// Server to client: the measured clock offset, used to execute animations in sync with other
+    /// clients.
+    #[serde(rename_all = "camelCase")]
+    SetClockOffset {
+        clock_offset_ms: i64,
+        round_trip_ms: i64,
+    },
+    /// Server to client: an emergency override was activated or released. While it is active,
+    /// clients drop pending updates and apply all updates without rate limit.
+    SetEmergencyOverride { active: bool },
+    /// Client to server: an image was loaded and is shown in the element, for the as-run log.
+    ImageDisplayed { id: String, asset: String },
+    /// Server to client: applies the operations in their order at once, so elements which belong
+    /// together (e.g. the texts of a lower third) are updated in the same frame.
+    Batch {
+        #[serde(borrow)]
+        operations: Vec<InstanceMessage<'a>>,
+    },
+    /// Server to client: all data of the instance was deleted. Clients reload the template, so
+    /// they no longer show the deleted data.
+    InstanceDeleted,
+    /// Server to client: an item scheduled with `/api/schedule` fired. Its operations follow
+    /// right after this message.
+    ScheduledItemFired {
+        id: u64,
+        #[serde(borrow, skip_serializing_if = "Option::is_none")]
+        name: Option<&'a str>,
+    },
+    /// Server to client: role of the renderer in its redundant pair, sent when it connects and
+    /// when the standby is promoted because the active renderer disconnected.
+    SetPairRole { pair: &'a str, active: bool },
+    /// Server to client: whether the instance is routed to the channel of the renderer. Renderers
+    /// of other channels hide the template and receive no data until it is routed back.
+    SetRouted { routed: bool },
+    /// Server to client: the server shuts down and closes the connection right after this
+    /// message. Clients reconnect as usual, e.g. to the process taking over the port.
+    ServerShuttingDown,
+    /// Client to server: subscribes to the topics matching the filters (e.g. `scores/+/home`).
+    /// The server answers with the retained value of every matching topic.
+    Subscribe { topics: Vec<String> },
+    /// Server to client: a value was published to a subscribed topic, or is the retained value
+    /// of the topic.
+    Publish {
+        topic: &'a str,
+        value: Cow<'a, serde_json::Value>,
+    },
+    /// Client to server: the registered animation sequences and the ids of the elements of the
+    /// template, sent after connecting so control surfaces can discover them.
+    #[serde(rename_all = "camelCase")]
+    ReportTemplate {
+        animation_sequences: Vec<String>,
+        elements: Vec<String>,
+    },
+    /// Client to server: an event of the template (e.g. `goal`), which the server forwards to the
+    /// renderers of the instances configured in the template event routes.
+    EmitEvent {
+        event: String,
+        #[serde(default)]
+        payload: serde_json::Value,
+    },
+    /// Server to client: an event emitted by a renderer of the source instance.
+    TemplateEvent {
+        source: &'a str,
+        event: &'a str,
+        payload: Cow<'a, serde_json::Value>,
+    },
+}
+
+/// Progress of a client pre-fetching assets, reported after every asset.
+#[derive(Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PrecacheProgress {
+    pub total: usize,
+    pub loaded: usize,
+    pub failed: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DomSnapshot {
+    pub error_id: u64,
+    /// Serialized HTML of the whole document.
+    pub html: String,
+    pub url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl<'a> InstanceMessage<'a> {
+    /// Returns the operations of a batch or the message itself.
+    pub fn operations(&self) -> &[InstanceMessage<'a>] {
+        match self {
+            InstanceMessage::Batch { operations } => operations,
+            message => std::slice::from_ref(message),
+        }
+    }
+
+    /// Returns the tag and the payload of the message.
+    pub fn to_operation(&self) -> serde_json::Result<(String, serde_json::Value)> {
+        let mut value = serde_json::to_value(self)?;
+        Ok((
+            value["tag"].as_str().unwrap_or_default().to_owned(),
+            value["payload"].take(),
+        ))
+    }
+}
+
+/// Returns a JSON schema of all messages of the current protocol version, e.g. to validate
+/// alternative runtime implementations.
+pub fn get_protocol_schema() -> RootSchema {
+    schemars::schema_for!(InstanceMessage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protocol_schema_contains_all_messages() {
+        let schema = serde_json::to_value(get_protocol_schema()).unwrap();
+        let tags: Vec<&str> = schema["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|variant| variant["properties"]["tag"]["enum"][0].as_str())
+            .collect();
+        assert!(tags.contains(&"SetText"));
+        assert!(tags.contains(&"Handshake"));
+        assert!(tags.contains(&"PrecacheProgress"));
+    }
 }
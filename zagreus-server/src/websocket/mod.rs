@@ -1,3 +1,15 @@
+pub mod catalogue;
 pub mod connection;
+pub mod control;
+pub mod feedback;
+pub mod latency;
+pub mod limits;
 pub mod message;
+pub mod pair;
+pub mod parameters;
+pub mod placeholders;
+pub mod presence;
+pub mod relay;
 pub mod server;
+pub mod stream;
+pub mod topics;
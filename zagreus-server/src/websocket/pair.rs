@@ -0,0 +1,47 @@
+/// Maximum number of renderers in a redundant pair: the active one and its standby.
+const MAX_PAIR_MEMBERS: usize = 2;
+
+/// Role of a renderer in a redundant pair (`?pair=<name>`). Both renderers receive all messages,
+/// but only the active one should be taken to air, e.g. by the tally of a vision mixer.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum PairRole {
+    Active,
+    Standby,
+}
+
+/// Returns the role of a renderer joining a pair whose members have the given roles, or `None`
+/// if the pair is complete.
+pub fn get_joining_role(roles: &[PairRole]) -> Option<PairRole> {
+    if roles.len() >= MAX_PAIR_MEMBERS {
+        None
+    } else if roles.contains(&PairRole::Active) {
+        Some(PairRole::Standby)
+    } else {
+        Some(PairRole::Active)
+    }
+}
+
+pub fn get_pair_complete_reason(instance: &str, pair: &str) -> String {
+    format!(
+        "Redundant pair {pair} of instance {instance} already has {MAX_PAIR_MEMBERS} renderers."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_joining_role() {
+        assert_eq!(Some(PairRole::Active), get_joining_role(&[]));
+        assert_eq!(
+            Some(PairRole::Standby),
+            get_joining_role(&[PairRole::Active])
+        );
+        assert_eq!(
+            None,
+            get_joining_role(&[PairRole::Active, PairRole::Standby])
+        );
+    }
+}
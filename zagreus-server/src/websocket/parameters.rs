@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+
+/// Renderer configuration passed as query parameters of the template page (e.g.
+/// `?channel=program&scale=0.5&transparent=1`) and forwarded by the runtime when connecting.
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientParameters {
+    pub channel: Option<String>,
+    pub scale: Option<f64>,
+    pub transparent: Option<bool>,
+    /// Whether the client is the built-in synthetic monitor instead of a renderer, which the
+    /// server determines from the token of the connection, never from the query.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub synthetic: bool,
+    /// Name of the redundant pair the renderer belongs to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pair: Option<String>,
+    /// Output variant of the template the renderer shows, e.g. `vertical`. All variants receive
+    /// the same operations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// All other query parameters.
+    pub custom: BTreeMap<String, String>,
+}
+
+impl ClientParameters {
+    pub fn from_query(mut query: BTreeMap<String, String>, synthetic: bool) -> ClientParameters {
+        let channel = query
+            .remove("channel")
+            .filter(|channel| !channel.is_empty());
+        let scale = query
+            .remove("scale")
+            .and_then(|scale| scale.parse::<f64>().ok())
+            .filter(|scale| scale.is_finite() && *scale > 0.0);
+        let transparent = query
+            .remove("transparent")
+            .map(|transparent| matches!(transparent.as_str(), "1" | "true" | "yes" | ""));
+        // the synthetic monitor never takes the place of a renderer
+        let pair = query
+            .remove("pair")
+            .filter(|pair| !pair.is_empty() && !synthetic);
+        let variant = query
+            .remove("variant")
+            .filter(|variant| !variant.is_empty());
+        ClientParameters {
+            channel,
+            scale,
+            transparent,
+            synthetic,
+            pair,
+            variant,
+            custom: query,
+        }
+    }
+
+    /// Returns the value of a placeholder in texts, which is the channel, pair or variant of the
+    /// renderer or any other parameter.
+    pub fn get_variable(&self, name: &str) -> Option<&str> {
+        match name {
+            "channel" => self.channel.as_deref(),
+            "pair" => self.pair.as_deref(),
+            "variant" => self.variant.as_deref(),
+            name => self.custom.get(name).map(String::as_str),
+        }
+    }
+}
+
+/// Where a client connected from, e.g. to tell apart the renderers of an instance.
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientOrigin {
+    /// Address of the peer, which is the proxy if the server runs behind one.
+    pub remote_address: Option<SocketAddr>,
+    pub user_agent: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_query() {
+        let query = BTreeMap::from([
+            (String::from("channel"), String::from("program")),
+            (String::from("scale"), String::from("0.5")),
+            (String::from("transparent"), String::from("1")),
+            (String::from("layer"), String::from("20")),
+            (String::from("pair"), String::from("main")),
+            (String::from("variant"), String::from("vertical")),
+        ]);
+        let parameters = ClientParameters::from_query(query.clone(), false);
+        assert_eq!(Some(String::from("program")), parameters.channel);
+        assert_eq!(Some(0.5), parameters.scale);
+        assert_eq!(Some(true), parameters.transparent);
+        assert_eq!(Some(String::from("main")), parameters.pair);
+        assert_eq!(Some(String::from("vertical")), parameters.variant);
+        assert_eq!(
+            BTreeMap::from([(String::from("layer"), String::from("20"))]),
+            parameters.custom
+        );
+        assert!(!parameters.synthetic);
+
+        // the synthetic monitor never takes the place of a renderer
+        let parameters = ClientParameters::from_query(query, true);
+        assert!(parameters.synthetic);
+        assert_eq!(None, parameters.pair);
+
+        // clients cannot claim to be the synthetic monitor
+        let query = BTreeMap::from([(String::from("synthetic"), String::from("1"))]);
+        assert!(!ClientParameters::from_query(query, false).synthetic);
+
+        let query = BTreeMap::from([
+            (String::from("scale"), String::from("-1")),
+            (String::from("transparent"), String::from("0")),
+        ]);
+        let parameters = ClientParameters::from_query(query, false);
+        assert_eq!(None, parameters.scale);
+        assert_eq!(Some(false), parameters.transparent);
+    }
+}
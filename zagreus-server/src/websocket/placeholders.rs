@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use crate::websocket::message::InstanceMessage;
+use crate::websocket::parameters::ClientParameters;
+
+/// Whether a text of the message may contain placeholders, which are resolved per client.
+pub fn has_placeholders(message: &InstanceMessage) -> bool {
+    message.operations().iter().any(|operation| {
+        matches!(operation, InstanceMessage::SetText { text, .. } if text.contains('{'))
+    })
+}
+
+/// Serializes the message with the placeholders in its texts replaced by the renderer parameters
+/// of the client.
+pub fn serialize_for_client(
+    message: &InstanceMessage,
+    parameters: &ClientParameters,
+) -> serde_json::Result<String> {
+    let mut value = serde_json::to_value(message)?;
+    resolve_message(&mut value, parameters);
+    serde_json::to_string(&value)
+}
+
+fn resolve_message(message: &mut Value, parameters: &ClientParameters) {
+    match message["tag"].as_str() {
+        Some("SetText") => {
+            if let Some(Value::String(text)) = message["payload"].get_mut("text") {
+                if let Cow::Owned(resolved) = resolve_text(text, parameters) {
+                    *text = resolved;
+                }
+            }
+        }
+        Some("Batch") => {
+            if let Some(Value::Array(operations)) = message["payload"].get_mut("operations") {
+                for operation in operations {
+                    resolve_message(operation, parameters);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Replaces placeholders like `{court}` with the value of the renderer parameter. Placeholders
+/// of parameters the client did not register with are kept, so texts may contain braces.
+pub fn resolve_text<'a>(text: &'a str, parameters: &ClientParameters) -> Cow<'a, str> {
+    let mut resolved = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}').map(|end| start + end) else {
+            break;
+        };
+        match parameters.get_variable(&rest[start + 1..end]) {
+            Some(value) => {
+                resolved.push_str(&rest[..start]);
+                resolved.push_str(value);
+            }
+            None => resolved.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    if rest.len() == text.len() {
+        return Cow::Borrowed(text);
+    }
+    resolved.push_str(rest);
+    Cow::Owned(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    #[test]
+    fn test_resolve_text() {
+        let parameters = ClientParameters::from_query(
+            BTreeMap::from([
+                (String::from("channel"), String::from("court-1")),
+                (String::from("court"), String::from("Court 1")),
+            ]),
+            false,
+        );
+        assert_eq!(
+            "Court 1 - Final",
+            resolve_text("{court} - Final", &parameters)
+        );
+        assert_eq!(
+            "court-1: {language} {}",
+            resolve_text("{channel}: {language} {}", &parameters)
+        );
+        assert!(matches!(
+            resolve_text("No placeholders {", &parameters),
+            Cow::Borrowed(_)
+        ));
+
+        let message = InstanceMessage::Batch {
+            operations: vec![
+                InstanceMessage::SetText {
+                    id: "Title",
+                    text: "{court}",
+                    tween_duration: None,
+                },
+                InstanceMessage::AddClass {
+                    id: "{court}",
+                    class: "visible",
+                },
+            ],
+        };
+        assert!(has_placeholders(&message));
+        let serialized = serialize_for_client(&message, &parameters).unwrap();
+        assert!(serialized.contains(r#""text":"Court 1""#));
+        assert!(serialized.contains(r#""id":"{court}""#));
+    }
+}
@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, StreamExt};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::controller::ServerController;
+use crate::presence::InstancePresence;
+
+/// Marks the operator as present on the instance while the control panel is connected and sends
+/// the operators and the lock of the instance on connect and whenever they change.
+pub async fn stream_presence(
+    socket: WebSocket,
+    controller: Arc<ServerController>,
+    instance: String,
+    operator: String,
+) {
+    info!("Operator {} is operating instance {}.", operator, instance);
+    let (mut sink, mut stream) = socket.split();
+    let mut presence_changes = controller.subscribe_presence_changes();
+    controller.join_presence(&instance, &operator).await;
+    let mut previous_presence: Option<InstancePresence> = None;
+    let mut changed = true;
+
+    loop {
+        if changed {
+            let presence = controller.get_presence(&instance).await;
+            if previous_presence.as_ref() != Some(&presence) {
+                let serialized_presence = match serde_json::to_string(&presence) {
+                    Ok(serialized_presence) => serialized_presence,
+                    Err(err) => {
+                        error!("Could not serialize presence: {}.", err);
+                        break;
+                    }
+                };
+                if let Err(err) = sink.send(Message::Text(serialized_presence)).await {
+                    debug!("Could not send presence: {}.", err);
+                    break;
+                }
+                previous_presence = Some(presence);
+            }
+        }
+
+        tokio::select! {
+            change = presence_changes.recv() => match change {
+                Ok(changed_instance) => changed = changed_instance == instance,
+                Err(RecvError::Lagged(_)) => changed = true,
+                Err(RecvError::Closed) => break,
+            },
+            message = stream.next() => match message {
+                // messages of control panels are ignored
+                Some(Ok(_)) => changed = false,
+                Some(Err(_)) | None => break,
+            },
+        }
+    }
+
+    controller.leave_presence(&instance, &operator).await;
+    info!(
+        "Operator {} stopped operating instance {}.",
+        operator, instance
+    );
+}
@@ -0,0 +1,60 @@
+use std::collections::BTreeSet;
+
+use crate::config::TemplateEventRouteConfig;
+
+/// Longest name of an event emitted by a template, so renderers cannot flood the event log.
+pub const MAX_EVENT_NAME_LENGTH: usize = 128;
+
+/// Returns the instances whose renderers receive the event emitted by a renderer of the source.
+pub fn get_event_targets<'a>(
+    routes: &'a [TemplateEventRouteConfig],
+    source: &str,
+    event: &str,
+) -> BTreeSet<&'a str> {
+    routes
+        .iter()
+        .filter(|route| route.source == source)
+        .filter(|route| match &route.event {
+            Some(route_event) => route_event == event,
+            None => true,
+        })
+        .flat_map(|route| route.targets.iter().map(String::as_str))
+        .collect()
+}
+
+pub fn is_valid_event_name(event: &str) -> bool {
+    !event.is_empty() && event.len() <= MAX_EVENT_NAME_LENGTH && !event.contains(char::is_control)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_event_targets() {
+        let route =
+            |source: &str, event: Option<&str>, targets: &[&str]| TemplateEventRouteConfig {
+                source: source.to_owned(),
+                event: event.map(str::to_owned),
+                targets: targets.iter().map(|target| (*target).to_owned()).collect(),
+            };
+        let routes = [
+            route("scorebug", Some("goal"), &["stats", "ticker"]),
+            route("scorebug", None, &["stats"]),
+            route("ticker", Some("goal"), &["scorebug"]),
+        ];
+        assert_eq!(
+            BTreeSet::from(["stats", "ticker"]),
+            get_event_targets(&routes, "scorebug", "goal")
+        );
+        assert_eq!(
+            BTreeSet::from(["stats"]),
+            get_event_targets(&routes, "scorebug", "card")
+        );
+        assert!(get_event_targets(&routes, "stats", "goal").is_empty());
+
+        assert!(is_valid_event_name("goal"));
+        assert!(!is_valid_event_name(""));
+        assert!(!is_valid_event_name("goal\n"));
+    }
+}
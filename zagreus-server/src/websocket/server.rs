@@ -1,47 +1,251 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use futures::stream::SplitStream;
 use futures::FutureExt;
 use futures::StreamExt;
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedReceiver;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 
+use crate::asrun::{AsRunLog, AsRunRecord};
+use crate::config::{ConnectionLimitsConfig, HeartbeatConfig, TemplateEventRouteConfig};
+use crate::errors::{ClientErrorRecord, ErrorLog};
+use crate::events::{EventBatch, EventKind, EventLog};
+use crate::metrics::Metrics;
+use crate::routing::{Routes, RoutingMatrix};
+use crate::websocket::catalogue::{ReportedTemplate, ReportedTemplates};
 use crate::websocket::connection::WebsocketConnection;
-use crate::websocket::message::InstanceMessage;
+use crate::websocket::latency::ClientLatency;
+use crate::websocket::limits::{ConnectionUsage, TRY_AGAIN_LATER_CLOSE_CODE};
+use crate::websocket::message::{DomSnapshot, InstanceMessage, PrecacheProgress, PROTOCOL_VERSION};
+use crate::websocket::pair::PairRole;
+use crate::websocket::parameters::{ClientOrigin, ClientParameters};
+use crate::websocket::topics::RetainedTopics;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectedClient {
+    pub id: usize,
+    pub instance: String,
+    pub connected_at: DateTime<Utc>,
+    /// Time of the last message of the client, including answers to pings.
+    pub last_seen: DateTime<Utc>,
+    #[serde(flatten)]
+    pub origin: ClientOrigin,
+    #[serde(flatten)]
+    pub parameters: ClientParameters,
+    /// Latency of the client, once it answered a ping.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<ClientLatency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pair_role: Option<PairRole>,
+}
+
 type UserConnections =
     Arc<RwLock<HashMap<usize, crate::websocket::connection::WebsocketConnection>>>;
 
+/// Shared state needed to handle the messages of a client.
+#[derive(Clone)]
+struct MessageContext {
+    connections: UserConnections,
+    events: Arc<EventLog>,
+    errors: Arc<ErrorLog>,
+    as_run: Arc<AsRunLog>,
+    metrics: Arc<Metrics>,
+    topics: Arc<RetainedTopics>,
+    templates: Arc<ReportedTemplates>,
+    event_routes: Arc<Vec<TemplateEventRouteConfig>>,
+}
+
+const MAX_EVENT_LOG_ENTRIES: usize = 10_000;
+const MAX_ERROR_LOG_ENTRIES: usize = 1_000;
+/// Interval in which the latency and clock offset of every client is measured.
+const LATENCY_PING_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct WebsocketServer {
     next_user_id: AtomicUsize,
     connections: UserConnections,
+    events: Arc<EventLog>,
+    errors: Arc<ErrorLog>,
+    as_run: Arc<AsRunLog>,
+    limits: ConnectionLimitsConfig,
+    heartbeat: HeartbeatConfig,
+    metrics: Arc<Metrics>,
+    topics: Arc<RetainedTopics>,
+    templates: Arc<ReportedTemplates>,
+    routing: RoutingMatrix,
+    event_routes: Arc<Vec<TemplateEventRouteConfig>>,
 }
 
 impl WebsocketServer {
-    pub fn new() -> WebsocketServer {
+    pub fn new(
+        data_folder: PathBuf,
+        limits: ConnectionLimitsConfig,
+        heartbeat: HeartbeatConfig,
+        event_routes: Vec<TemplateEventRouteConfig>,
+    ) -> WebsocketServer {
         WebsocketServer {
+            limits,
+            heartbeat,
             connections: Arc::new(RwLock::new(HashMap::new())),
             next_user_id: AtomicUsize::new(0),
+            events: Arc::new(EventLog::new(MAX_EVENT_LOG_ENTRIES)),
+            errors: Arc::new(ErrorLog::new(MAX_ERROR_LOG_ENTRIES)),
+            as_run: Arc::new(AsRunLog::new(data_folder.clone())),
+            metrics: Arc::new(Metrics::default()),
+            topics: Arc::new(RetainedTopics::default()),
+            templates: Arc::new(ReportedTemplates::default()),
+            routing: RoutingMatrix::new(data_folder),
+            event_routes: Arc::new(event_routes),
         }
     }
 
+    /// Returns the reason why a new client of the instance is rejected, if admitting it would
+    /// exceed a connection limit.
+    pub async fn check_admission(
+        &self,
+        instance: &str,
+        parameters: &ClientParameters,
+    ) -> Option<String> {
+        Self::admit(
+            &self.limits,
+            &*self.connections.read().await,
+            instance,
+            parameters,
+        )
+        .err()
+    }
+
+    /// Returns the role of the new client in its redundant pair, or the reason why it is rejected.
+    fn admit(
+        limits: &ConnectionLimitsConfig,
+        connections: &HashMap<usize, WebsocketConnection>,
+        instance: &str,
+        parameters: &ClientParameters,
+    ) -> Result<Option<PairRole>, String> {
+        // the synthetic monitor is identified by the token of this process, so it still reports
+        // the health of the instance when the limits are reached
+        if !parameters.synthetic {
+            let usage = Self::get_usage(connections, instance);
+            if let Some(reason) =
+                crate::websocket::limits::check_admission(limits, &usage, instance)
+            {
+                return Err(reason);
+            }
+        }
+        let Some(pair) = &parameters.pair else {
+            return Ok(None);
+        };
+        let roles: Vec<PairRole> = connections
+            .values()
+            .filter(|connection| is_member_of_pair(connection, instance, pair))
+            .filter_map(|connection| connection.get_pair_role())
+            .collect();
+        match crate::websocket::pair::get_joining_role(&roles) {
+            Some(role) => Ok(Some(role)),
+            None => Err(crate::websocket::pair::get_pair_complete_reason(
+                instance, pair,
+            )),
+        }
+    }
+
+    fn get_usage(
+        connections: &HashMap<usize, WebsocketConnection>,
+        instance: &str,
+    ) -> ConnectionUsage {
+        ConnectionUsage {
+            connections: connections.len(),
+            instance_connections: connections
+                .values()
+                .filter(|connection| connection.is_from_instance(instance))
+                .count(),
+            buffered_bytes: connections
+                .values()
+                .map(|connection| connection.get_buffered_bytes())
+                .sum(),
+        }
+    }
+
+    /// Adds the client and returns its id, or closes the websocket if admitting the client would
+    /// exceed a connection limit.
     pub async fn add_client_socket(
         &self,
-        websocket: axum::extract::ws::WebSocket,
+        mut websocket: axum::extract::ws::WebSocket,
         template_name: &str,
-    ) {
+        parameters: ClientParameters,
+        origin: ClientOrigin,
+    ) -> Option<usize> {
         let id = self.next_user_id.fetch_add(1, Ordering::SeqCst);
+        let pair = parameters.pair.clone();
+        let channel = parameters.channel.clone();
+        let (sender_tx, sender_rx) = tokio::sync::mpsc::unbounded_channel();
+        let buffered_bytes = Arc::new(AtomicUsize::new(0));
+        let mut connection = WebsocketConnection::new(
+            sender_tx,
+            String::from(template_name),
+            parameters,
+            origin,
+            buffered_bytes.clone(),
+            self.metrics.clone(),
+        );
+
+        // the limits are checked again while adding, since clients may connect concurrently
+        let rejection = {
+            let mut connections = self.connections.write().await;
+            match Self::admit(
+                &self.limits,
+                &connections,
+                template_name,
+                connection.get_parameters(),
+            ) {
+                Ok(role) => {
+                    if let Some(role) = role {
+                        connection.set_pair_role(role);
+                    }
+                    connections.insert(id, connection);
+                    None
+                }
+                Err(reason) => Some(reason),
+            }
+        };
+        if let Some(reason) = rejection {
+            warn!(
+                "Rejected websocket client of template {}: {}",
+                template_name, reason
+            );
+            let close_frame = axum::extract::ws::CloseFrame {
+                code: TRY_AGAIN_LATER_CLOSE_CODE,
+                reason: reason.into(),
+            };
+            if let Err(err) = websocket
+                .send(axum::extract::ws::Message::Close(Some(close_frame)))
+                .await
+            {
+                debug!("Could not close rejected websocket: {}.", err);
+            }
+            return None;
+        }
         info!(
-            "Connected to new websocket client with id {} and template {}.",
-            id, template_name
+            "Connected to new websocket client with id {} and template {} (channel: {}).",
+            id,
+            template_name,
+            channel.as_deref().unwrap_or("none")
         );
+        if let Some(pair) = pair {
+            info!("Client {} joined redundant pair {}.", id, pair);
+        }
 
         let (websocket_sink, websocket_stream) = websocket.split();
 
         // sending
-        let (sender_tx, sender_rx) = tokio::sync::mpsc::unbounded_channel();
         let sender_rx = UnboundedReceiverStream::new(sender_rx);
         let sending_stream =
             tokio_stream::StreamExt::take_while(sender_rx, |result| match result {
@@ -50,6 +254,11 @@ impl WebsocketServer {
                     error!("Could not forward message to websocket sink: {}.", err);
                     false
                 }
+            })
+            .inspect(move |result| {
+                if let Ok(axum::extract::ws::Message::Text(text)) = result {
+                    buffered_bytes.fetch_sub(text.len(), Ordering::Relaxed);
+                }
             });
         tokio::task::spawn(sending_stream.forward(websocket_sink).map(|result| {
             if let Err(err) = result {
@@ -57,33 +266,195 @@ impl WebsocketServer {
             }
         }));
 
-        let connection = WebsocketConnection::new(sender_tx, String::from(template_name));
-        self.connections.write().await.insert(id, connection);
+        self.events
+            .record(template_name, EventKind::ClientConnected { client: id })
+            .await;
+
+        tokio::spawn(Self::send_latency_pings(self.connections.clone(), id));
 
         // user messages and disconnect handler
-        tokio::spawn(Self::handle_user_messages(
+        let context = MessageContext {
+            connections: self.connections.clone(),
+            events: self.events.clone(),
+            errors: self.errors.clone(),
+            as_run: self.as_run.clone(),
+            metrics: self.metrics.clone(),
+            topics: self.topics.clone(),
+            templates: self.templates.clone(),
+            event_routes: self.event_routes.clone(),
+        };
+        let receiver = tokio::spawn(Self::handle_user_messages(
             id,
             websocket_stream,
-            self.connections.clone(),
+            context.clone(),
         ));
+        tokio::spawn(Self::send_heartbeats(
+            context,
+            id,
+            self.heartbeat.clone(),
+            receiver,
+        ));
+
+        Some(id)
+    }
+
+    /// Pings the client until it disconnects and closes its connection once the client sent
+    /// nothing for longer than the timeout, since the socket of a client which vanished (e.g.
+    /// after a network outage) may not fail for a long time.
+    async fn send_heartbeats(
+        context: MessageContext,
+        id: usize,
+        heartbeat: HeartbeatConfig,
+        receiver: JoinHandle<()>,
+    ) {
+        let timeout = Duration::from_secs(heartbeat.timeout_seconds);
+        let mut interval = tokio::time::interval(Duration::from_secs(heartbeat.interval_seconds));
+        loop {
+            interval.tick().await;
+            let silence = match context.connections.read().await.get(&id) {
+                Some(connection) => {
+                    connection.send_ping();
+                    (Utc::now() - connection.get_last_seen())
+                        .to_std()
+                        .unwrap_or_default()
+                }
+                None => break,
+            };
+            if silence <= timeout {
+                continue;
+            }
+            warn!(
+                "Closing connection of client {}, which sent nothing for {} seconds.",
+                id,
+                silence.as_secs()
+            );
+            if let Some(connection) = context.connections.read().await.get(&id) {
+                connection.close();
+            }
+            // the receiver would only notice the disconnect once the socket fails
+            receiver.abort();
+            context.as_run.client_disconnected(id).await;
+            Self::user_disconnected(&context.connections, &context.events, id).await;
+            break;
+        }
     }
 
     async fn handle_user_messages(
         id: usize,
         mut stream: SplitStream<axum::extract::ws::WebSocket>,
-        connections: UserConnections,
+        context: MessageContext,
     ) {
+        let MessageContext {
+            connections,
+            events,
+            errors,
+            as_run,
+            metrics,
+            topics,
+            templates,
+            event_routes,
+        } = context;
         loop {
-            match stream.next().await {
+            let next_message = stream.next().await;
+            if let Some(Ok(_)) = next_message {
+                if let Some(connection) = connections.write().await.get_mut(&id) {
+                    connection.mark_seen();
+                }
+            }
+            match next_message {
                 Some(message_result) => match message_result {
+                    Ok(
+                        axum::extract::ws::Message::Ping(_) | axum::extract::ws::Message::Pong(_),
+                    ) => {
+                        // pings are answered by the websocket implementation, e.g. for synthetic
+                        // clients, and answers to heartbeats only mark the client as seen
+                    }
+                    Ok(axum::extract::ws::Message::Close(_)) => break,
                     Ok(message) => {
                         match serde_json::from_slice::<InstanceMessage>(&message.into_data()) {
-                            Ok(parsed_message) => {
-                                if let InstanceMessage::LogError { message, stack } = parsed_message
-                                {
-                                    error!("Template error occurred: {}\n{}", message, stack)
+                            Ok(parsed_message) => match parsed_message {
+                                InstanceMessage::LogError { message, stack } => {
+                                    error!("Template error occurred: {}\n{}", message, stack);
+                                    metrics.record_client_error();
+                                    Self::record_client_error(
+                                        &connections,
+                                        &events,
+                                        &errors,
+                                        id,
+                                        &message,
+                                        &stack,
+                                    )
+                                    .await
                                 }
-                            }
+                                InstanceMessage::Handshake {
+                                    runtime_version,
+                                    protocol_version,
+                                    required_protocol_version,
+                                } => {
+                                    Self::handle_handshake(
+                                        &connections,
+                                        id,
+                                        runtime_version,
+                                        protocol_version,
+                                        required_protocol_version,
+                                    )
+                                    .await
+                                }
+                                InstanceMessage::PrecacheProgress(progress) => {
+                                    Self::handle_precache_progress(&connections, id, progress).await
+                                }
+                                InstanceMessage::DomSnapshot(snapshot) => {
+                                    Self::handle_dom_snapshot(&errors, id, snapshot).await
+                                }
+                                InstanceMessage::Pong {
+                                    server_time,
+                                    client_time,
+                                } => {
+                                    Self::handle_pong(&connections, id, server_time, client_time)
+                                        .await
+                                }
+                                InstanceMessage::ImageDisplayed { id: element, asset } => {
+                                    Self::handle_image_displayed(
+                                        &connections,
+                                        &as_run,
+                                        id,
+                                        &element,
+                                        &asset,
+                                    )
+                                    .await
+                                }
+                                InstanceMessage::Subscribe { topics: filters } => {
+                                    Self::handle_subscribe(&connections, &topics, id, &filters)
+                                        .await
+                                }
+                                InstanceMessage::ReportTemplate {
+                                    animation_sequences,
+                                    elements,
+                                } => {
+                                    let instance = connections
+                                        .read()
+                                        .await
+                                        .get(&id)
+                                        .map(|connection| connection.get_instance().to_owned());
+                                    if let Some(instance) = instance {
+                                        templates
+                                            .report(&instance, animation_sequences, elements)
+                                            .await;
+                                    }
+                                }
+                                InstanceMessage::EmitEvent { event, payload } => {
+                                    Self::handle_emit_event(
+                                        &connections,
+                                        &events,
+                                        &event_routes,
+                                        id,
+                                        &event,
+                                        payload,
+                                    )
+                                    .await
+                                }
+                                _ => {}
+                            },
                             Err(err) => error!("Could not parse message on websocket: {}.", err),
                         }
                     }
@@ -100,12 +471,299 @@ impl WebsocketServer {
         }
 
         // as soon as the loop quits the client has disconnected
-        Self::user_disconnected(&connections, id).await;
+        as_run.client_disconnected(id).await;
+        Self::user_disconnected(&connections, &events, id).await;
     }
 
-    async fn user_disconnected(connections: &UserConnections, id: usize) {
+    /// Subscribes the client to the topics and sends it their retained values.
+    async fn handle_subscribe(
+        connections: &UserConnections,
+        topics: &RetainedTopics,
+        id: usize,
+        filters: &[String],
+    ) {
+        let mut connections = connections.write().await;
+        let Some(connection) = connections.get_mut(&id) else {
+            return;
+        };
+        connection.subscribe(filters);
+        for filter in filters {
+            for (topic, value) in topics.get_matching(connection.get_instance(), filter).await {
+                connection.send_message(&InstanceMessage::Publish {
+                    topic: &topic,
+                    value: Cow::Owned(value),
+                });
+            }
+        }
+    }
+
+    /// Forwards the event emitted by the client to the clients of the instances it is routed to.
+    async fn handle_emit_event(
+        connections: &UserConnections,
+        events: &EventLog,
+        routes: &[TemplateEventRouteConfig],
+        id: usize,
+        event: &str,
+        payload: Value,
+    ) {
+        if !crate::websocket::relay::is_valid_event_name(event) {
+            warn!(
+                "Ignoring template event with invalid name of client {}.",
+                id
+            );
+            return;
+        }
+        let mut clients = 0;
+        let source = {
+            let connections = connections.read().await;
+            let Some(source) = connections.get(&id).map(WebsocketConnection::get_instance) else {
+                return;
+            };
+            let targets = crate::websocket::relay::get_event_targets(routes, source, event);
+            let message = InstanceMessage::TemplateEvent {
+                source,
+                event,
+                payload: Cow::Borrowed(&payload),
+            };
+            for connection in connections.values() {
+                if targets.contains(connection.get_instance()) {
+                    connection.send_message(&message);
+                    clients += 1;
+                }
+            }
+            source.to_owned()
+        };
+        debug!(
+            "Forwarded event {} of instance {} to {} clients.",
+            event, source, clients
+        );
+        events
+            .record(
+                &source,
+                EventKind::TemplateEvent {
+                    client: id,
+                    event: event.to_owned(),
+                    payload,
+                    clients,
+                },
+            )
+            .await;
+    }
+
+    async fn handle_image_displayed(
+        connections: &UserConnections,
+        as_run: &AsRunLog,
+        id: usize,
+        element: &str,
+        asset: &str,
+    ) {
+        let instance = match connections.read().await.get(&id) {
+            // the synthetic monitor does not show anything
+            Some(connection) if !connection.get_parameters().synthetic => {
+                connection.get_instance().to_owned()
+            }
+            _ => return,
+        };
+        as_run.record_display(&instance, id, element, asset).await;
+    }
+
+    /// Records the error and requests a DOM snapshot from the client, so it can be seen what the
+    /// template looked like when the error occurred.
+    async fn record_client_error(
+        connections: &UserConnections,
+        events: &EventLog,
+        errors: &ErrorLog,
+        id: usize,
+        message: &str,
+        stack: &str,
+    ) {
+        let instance = match connections.read().await.get(&id) {
+            Some(connection) => connection.get_instance().to_owned(),
+            None => return,
+        };
+        events
+            .record(
+                &instance,
+                EventKind::ClientError {
+                    client: id,
+                    message: message.to_owned(),
+                },
+            )
+            .await;
+
+        if let Some(error_id) = errors.record(&instance, id, message, stack).await {
+            if let Some(connection) = connections.read().await.get(&id) {
+                connection.send_message(&InstanceMessage::CaptureDomSnapshot { error_id });
+            }
+        }
+    }
+
+    /// Sends timestamped pings to the client until it disconnects.
+    async fn send_latency_pings(connections: UserConnections, id: usize) {
+        let mut interval = tokio::time::interval(LATENCY_PING_INTERVAL);
+        loop {
+            interval.tick().await;
+            match connections.read().await.get(&id) {
+                Some(connection) => connection.send_message(&InstanceMessage::Ping {
+                    server_time: Utc::now().timestamp_millis(),
+                }),
+                None => break,
+            }
+        }
+    }
+
+    /// Updates the latency of the client and sends it the estimated clock offset.
+    async fn handle_pong(
+        connections: &UserConnections,
+        id: usize,
+        server_time: i64,
+        client_time: i64,
+    ) {
+        let received = Utc::now().timestamp_millis();
+        if let Some(connection) = connections.write().await.get_mut(&id) {
+            let latency = connection.add_latency_sample(server_time, client_time, received);
+            connection.send_message(&InstanceMessage::SetClockOffset {
+                clock_offset_ms: latency.clock_offset_ms,
+                round_trip_ms: latency.round_trip_ms,
+            });
+        }
+    }
+
+    async fn handle_dom_snapshot(errors: &ErrorLog, id: usize, snapshot: DomSnapshot) {
+        let error_id = snapshot.error_id;
+        if errors.attach_snapshot(id, snapshot).await {
+            debug!(
+                "Client with id {} captured a DOM snapshot for error {}.",
+                id, error_id
+            );
+        } else {
+            warn!(
+                "Client with id {} sent a DOM snapshot for unknown error {}.",
+                id, error_id
+            );
+        }
+    }
+
+    async fn handle_handshake(
+        connections: &UserConnections,
+        id: usize,
+        runtime_version: String,
+        protocol_version: u32,
+        required_protocol_version: Option<u32>,
+    ) {
+        let locked_connections = connections.read().await;
+        let Some(connection) = locked_connections.get(&id) else {
+            return;
+        };
+        debug!(
+            "Client with id {} uses runtime {} (protocol version {}).",
+            id, runtime_version, protocol_version
+        );
+
+        let incompatibility =
+            check_protocol_compatibility(protocol_version, required_protocol_version);
+        match incompatibility {
+            Some(message) => {
+                error!("Client with id {} is incompatible: {}", id, message);
+                connection.send_message(&InstanceMessage::IncompatibleVersion {
+                    server_protocol_version: PROTOCOL_VERSION,
+                    message,
+                });
+            }
+            None if protocol_version < PROTOCOL_VERSION => warn!(
+                "Client with id {} uses protocol version {} which is older than the server protocol version {}. Newer operations will be ignored by the client.",
+                id, protocol_version, PROTOCOL_VERSION
+            ),
+            None => {}
+        }
+    }
+
+    async fn handle_precache_progress(
+        connections: &UserConnections,
+        id: usize,
+        progress: PrecacheProgress,
+    ) {
+        if progress.loaded + progress.failed.len() == progress.total {
+            if progress.failed.is_empty() {
+                info!(
+                    "Client with id {} has pre-cached {} assets.",
+                    id, progress.total
+                );
+            } else {
+                warn!(
+                    "Client with id {} could not pre-cache assets: {:?}.",
+                    id, progress.failed
+                );
+            }
+        }
+        if let Some(connection) = connections.write().await.get_mut(&id) {
+            connection.set_precache_progress(progress);
+        }
+    }
+
+    async fn user_disconnected(connections: &UserConnections, events: &EventLog, id: usize) {
         debug!("Client with id {} has disconnected.", id);
-        connections.write().await.remove(&id);
+        let (connection, promoted) = {
+            let mut connections = connections.write().await;
+            let connection = connections.remove(&id);
+            let promoted = connection
+                .as_ref()
+                .filter(|connection| connection.get_pair_role() == Some(PairRole::Active))
+                .and_then(|connection| Self::promote_standby(&mut connections, connection));
+            (connection, promoted)
+        };
+        if let Some(connection) = connection {
+            let instance = connection.get_instance();
+            events
+                .record(instance, EventKind::ClientDisconnected { client: id })
+                .await;
+            if let Some((client, pair)) = promoted {
+                warn!(
+                    "Active renderer {} of pair {} of instance {} disconnected, promoted standby {}.",
+                    id, pair, instance, client
+                );
+                events
+                    .record(instance, EventKind::StandbyPromoted { client, pair })
+                    .await;
+            }
+        }
+    }
+
+    /// Makes the standby of the pair of the disconnected active client active. Returns its id and
+    /// the name of the pair.
+    fn promote_standby(
+        connections: &mut HashMap<usize, WebsocketConnection>,
+        disconnected: &WebsocketConnection,
+    ) -> Option<(usize, String)> {
+        let pair = disconnected.get_parameters().pair.as_ref()?;
+        let (id, standby) = connections
+            .iter_mut()
+            .filter(|(_, connection)| {
+                is_member_of_pair(connection, disconnected.get_instance(), pair)
+            })
+            .min_by_key(|(id, _)| **id)?;
+        standby.set_pair_role(PairRole::Active);
+        Some((*id, pair.clone()))
+    }
+
+    /// Adds a client which receives the messages of the instance through the returned channel
+    /// instead of a websocket, e.g. to record the messages of a harness run.
+    pub async fn add_capturing_client(
+        &self,
+        instance: &str,
+    ) -> UnboundedReceiver<Result<axum::extract::ws::Message, axum::Error>> {
+        let id = self.next_user_id.fetch_add(1, Ordering::SeqCst);
+        let (sender_tx, sender_rx) = tokio::sync::mpsc::unbounded_channel();
+        let connection = WebsocketConnection::new(
+            sender_tx,
+            String::from(instance),
+            ClientParameters::default(),
+            ClientOrigin::default(),
+            Arc::new(AtomicUsize::new(0)),
+            self.metrics.clone(),
+        );
+        self.connections.write().await.insert(id, connection);
+        sender_rx
     }
 
     pub async fn send_message_to_instance_clients(
@@ -113,13 +771,459 @@ impl WebsocketServer {
         instance: &str,
         message: &InstanceMessage<'_>,
     ) {
-        let locked_connections = self.connections.read().await;
-        let connection_entries = locked_connections.values();
+        self.send_message_to_clients(instance, None, message).await
+    }
 
-        for connection in connection_entries {
-            if connection.is_from_instance(instance) {
+    /// Sends the data of the instance to the renderers of the channels it is routed to, or to all
+    /// renderers if it has no route. The synthetic monitor always receives it.
+    pub async fn send_message_to_routed_clients(
+        &self,
+        instance: &str,
+        message: &InstanceMessage<'_>,
+    ) {
+        let route = self.get_route(instance).await;
+        self.send_message_to_clients(instance, route.as_ref(), message)
+            .await
+    }
+
+    async fn send_message_to_clients(
+        &self,
+        instance: &str,
+        route: Option<&BTreeSet<String>>,
+        message: &InstanceMessage<'_>,
+    ) {
+        let mut clients = 0;
+        for connection in self.connections.read().await.values() {
+            if connection.is_from_instance(instance) && is_routed_connection(connection, route) {
                 connection.send_message(message);
+                clients += 1;
+            }
+        }
+        crate::timing::mark("websocket");
+
+        match message.to_operation() {
+            Ok((operation, payload)) => {
+                let event = EventKind::Operation {
+                    operation,
+                    payload,
+                    clients,
+                };
+                self.events.record(instance, event).await
+            }
+            Err(err) => error!("Could not serialize message for event log: {}.", err),
+        }
+    }
+
+    pub async fn record_event(&self, instance: &str, kind: EventKind) {
+        self.events.record(instance, kind).await
+    }
+
+    pub async fn get_as_run_records(
+        &self,
+        instance: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<AsRunRecord>> {
+        self.as_run.get_records(instance, from, to).await
+    }
+
+    pub async fn get_client_errors(&self, instance: &str) -> Vec<ClientErrorRecord> {
+        self.errors.get_errors(instance).await
+    }
+
+    pub async fn get_error_snapshot_html(&self, instance: &str, error_id: u64) -> Option<String> {
+        self.errors.get_snapshot_html(instance, error_id).await
+    }
+
+    pub async fn poll_events(
+        &self,
+        instance: &str,
+        cursor: Option<u64>,
+        timeout: Duration,
+    ) -> EventBatch {
+        self.events.poll(instance, cursor, timeout).await
+    }
+
+    pub async fn poll_all_events(&self, cursor: Option<u64>, timeout: Duration) -> EventBatch {
+        self.events.poll_all(cursor, timeout).await
+    }
+
+    /// Sends the value to the clients of the instance which subscribed to the topic and returns
+    /// their number. A retained value is also sent to clients which subscribe later.
+    pub async fn publish(&self, instance: &str, topic: &str, value: Value, retain: bool) -> usize {
+        // retained before sending, so clients subscribing meanwhile do not miss the value
+        if retain {
+            self.topics.retain(instance, topic, value.clone()).await;
+        }
+        let mut clients = 0;
+        {
+            let message = InstanceMessage::Publish {
+                topic,
+                value: Cow::Borrowed(&value),
+            };
+            for connection in self.connections.read().await.values() {
+                if connection.is_from_instance(instance) && connection.is_subscribed(topic) {
+                    connection.send_message(&message);
+                    clients += 1;
+                }
             }
         }
+        clients
+    }
+
+    pub async fn get_retained_topics(&self, instance: &str) -> BTreeMap<String, Value> {
+        self.topics.get_all(instance).await
+    }
+
+    pub async fn remove_retained_topic(&self, instance: &str, topic: &str) -> bool {
+        self.topics.remove(instance, topic).await
+    }
+
+    pub async fn get_routes(&self) -> anyhow::Result<Routes> {
+        self.routing.get_routes().await
+    }
+
+    /// Returns the route of the instance. Routes which cannot be loaded are logged and ignored,
+    /// so the data still reaches the renderers.
+    pub async fn get_route(&self, instance: &str) -> Option<BTreeSet<String>> {
+        self.routing
+            .get_route(instance)
+            .await
+            .unwrap_or_else(|err| {
+                error!("Could not load routes: {:#}.", err);
+                None
+            })
+    }
+
+    pub async fn set_route(
+        &self,
+        instance: &str,
+        channels: BTreeSet<String>,
+    ) -> anyhow::Result<Option<BTreeSet<String>>> {
+        self.routing.set_route(instance, channels).await
+    }
+
+    pub async fn remove_route(&self, instance: &str) -> anyhow::Result<Option<BTreeSet<String>>> {
+        self.routing.remove_route(instance).await
+    }
+
+    /// Returns the ids and channels of the renderers of the instance.
+    pub async fn get_renderer_channels(&self, instance: &str) -> Vec<(usize, Option<String>)> {
+        self.connections
+            .read()
+            .await
+            .iter()
+            .filter(|(_, connection)| {
+                connection.is_from_instance(instance) && !connection.get_parameters().synthetic
+            })
+            .map(|(id, connection)| (*id, connection.get_parameters().channel.clone()))
+            .collect()
+    }
+
+    pub async fn remove_retained_topics(&self, instance: &str) {
+        self.topics.remove_instance(instance).await
+    }
+
+    pub async fn get_reported_template(&self, instance: &str) -> ReportedTemplate {
+        self.templates.get(instance).await
+    }
+
+    pub async fn remove_reported_template(&self, instance: &str) {
+        self.templates.remove_instance(instance).await
+    }
+
+    /// Whether the client with the id is connected to the instance.
+    pub async fn is_instance_client(&self, instance: &str, id: usize) -> bool {
+        self.connections
+            .read()
+            .await
+            .get(&id)
+            .is_some_and(|connection| connection.is_from_instance(instance))
+    }
+
+    pub async fn send_message_to_client(&self, id: usize, message: &InstanceMessage<'_>) {
+        if let Some(connection) = self.connections.read().await.get(&id) {
+            connection.send_message(message);
+        }
+    }
+
+    /// Sends the message to the renderers of the channel only, without recording an event.
+    /// Returns the number of renderers.
+    pub async fn send_message_to_channel_clients(
+        &self,
+        instance: &str,
+        channel: &str,
+        message: &InstanceMessage<'_>,
+    ) -> usize {
+        let mut clients = 0;
+        for connection in self.connections.read().await.values() {
+            if is_renderer_of_channel(connection, instance, channel) {
+                connection.send_message(message);
+                clients += 1;
+            }
+        }
+        clients
+    }
+
+    pub async fn get_connected_instances(&self) -> BTreeSet<String> {
+        self.connections
+            .read()
+            .await
+            .values()
+            .map(|connection| connection.get_instance().to_owned())
+            .collect()
+    }
+
+    /// Resets the pre-caching progress of all clients of the instance to zero loaded assets.
+    pub async fn reset_precache_progress(&self, instance: &str, total: usize) {
+        for connection in self.connections.write().await.values_mut() {
+            if connection.is_from_instance(instance) {
+                connection.set_precache_progress(PrecacheProgress {
+                    total,
+                    ..PrecacheProgress::default()
+                });
+            }
+        }
+    }
+
+    /// Returns the pre-caching progress per client id. Clients that were never asked to
+    /// pre-cache assets have no progress.
+    pub async fn get_precache_progress(
+        &self,
+        instance: &str,
+    ) -> BTreeMap<usize, Option<PrecacheProgress>> {
+        self.connections
+            .read()
+            .await
+            .iter()
+            .filter(|(_, connection)| connection.is_from_instance(instance))
+            .map(|(id, connection)| (*id, connection.get_precache_progress().cloned()))
+            .collect()
+    }
+
+    pub async fn get_instance_clients(&self, instance: &str) -> Vec<ConnectedClient> {
+        let mut clients: Vec<ConnectedClient> = self
+            .connections
+            .read()
+            .await
+            .iter()
+            .filter(|(_, connection)| connection.is_from_instance(instance))
+            .map(|(id, connection)| get_connected_client(*id, connection))
+            .collect();
+        clients.sort_by_key(|client| client.id);
+        clients
+    }
+
+    /// Returns the connected clients of all instances.
+    pub async fn get_clients(&self) -> Vec<ConnectedClient> {
+        let mut clients: Vec<ConnectedClient> = self
+            .connections
+            .read()
+            .await
+            .iter()
+            .map(|(id, connection)| get_connected_client(*id, connection))
+            .collect();
+        clients.sort_by_key(|client| client.id);
+        clients
+    }
+
+    /// Returns the connected clients per instance without waiting, if the connections are not
+    /// locked.
+    pub fn try_get_clients(&self) -> Option<BTreeMap<String, Vec<ConnectedClient>>> {
+        let connections = self.connections.try_read().ok()?;
+        let mut clients: BTreeMap<String, Vec<ConnectedClient>> = BTreeMap::new();
+        for (id, connection) in connections.iter() {
+            clients
+                .entry(connection.get_instance().to_owned())
+                .or_default()
+                .push(get_connected_client(*id, connection));
+        }
+        for instance_clients in clients.values_mut() {
+            instance_clients.sort_by_key(|client| client.id);
+        }
+        Some(clients)
+    }
+
+    /// Returns whether a renderer of the channel of the instance answered a ping within the
+    /// maximum silence.
+    pub async fn has_responsive_client(
+        &self,
+        instance: &str,
+        channel: &str,
+        max_silence: Duration,
+    ) -> bool {
+        self.connections.read().await.values().any(|connection| {
+            is_renderer_of_channel(connection, instance, channel)
+                && connection.get_silence() <= max_silence
+        })
+    }
+
+    /// Switches the responsive renderer of a channel of the instance with the lowest id to
+    /// another channel and returns its id.
+    pub async fn switch_client_channel(
+        &self,
+        instance: &str,
+        from_channel: &str,
+        to_channel: &str,
+        max_silence: Duration,
+    ) -> Option<usize> {
+        let mut connections = self.connections.write().await;
+        let id = connections
+            .iter()
+            .filter(|(_, connection)| {
+                is_renderer_of_channel(connection, instance, from_channel)
+                    && connection.get_silence() <= max_silence
+            })
+            .map(|(id, _)| *id)
+            .min()?;
+        connections.get_mut(&id)?.set_channel(to_channel);
+        Some(id)
+    }
+
+    /// Tells all clients that the server shuts down and closes their connections.
+    pub async fn disconnect_all_clients(&self) {
+        let connections = self.connections.read().await;
+        info!("Disconnecting {} websocket client(s).", connections.len());
+        for connection in connections.values() {
+            connection.send_message(&InstanceMessage::ServerShuttingDown);
+            connection.close();
+        }
+    }
+
+    /// Returns the number of bytes queued for sending to all clients.
+    pub async fn get_buffered_bytes(&self) -> usize {
+        self.connections
+            .read()
+            .await
+            .values()
+            .map(|connection| connection.get_buffered_bytes())
+            .sum()
+    }
+
+    pub async fn get_client_count(&self) -> usize {
+        self.connections.read().await.len()
+    }
+
+    pub fn get_metrics(&self) -> Arc<Metrics> {
+        self.metrics.clone()
+    }
+
+    /// Returns the number of renderers connected to each instance, not counting synthetic clients.
+    pub async fn get_client_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for connection in self.connections.read().await.values() {
+            if !connection.get_parameters().synthetic {
+                *counts
+                    .entry(connection.get_instance().to_owned())
+                    .or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Returns the number of renderers connected to the instance, not counting synthetic clients.
+    pub async fn get_instance_client_count(&self, instance: &str) -> usize {
+        self.connections
+            .read()
+            .await
+            .values()
+            .filter(|connection| {
+                connection.is_from_instance(instance) && !connection.get_parameters().synthetic
+            })
+            .count()
+    }
+}
+
+fn get_connected_client(id: usize, connection: &WebsocketConnection) -> ConnectedClient {
+    ConnectedClient {
+        id,
+        instance: connection.get_instance().to_owned(),
+        connected_at: connection.get_connected_at(),
+        last_seen: connection.get_last_seen(),
+        origin: connection.get_origin().clone(),
+        parameters: connection.get_parameters().clone(),
+        latency: connection.get_latency(),
+        pair_role: connection.get_pair_role(),
+    }
+}
+
+fn is_member_of_pair(connection: &WebsocketConnection, instance: &str, pair: &str) -> bool {
+    connection.is_from_instance(instance)
+        && connection.get_parameters().pair.as_deref() == Some(pair)
+}
+
+fn is_routed_connection(
+    connection: &WebsocketConnection,
+    route: Option<&BTreeSet<String>>,
+) -> bool {
+    let parameters = connection.get_parameters();
+    parameters.synthetic || crate::routing::is_routed(route, parameters.channel.as_deref())
+}
+
+fn is_renderer_of_channel(connection: &WebsocketConnection, instance: &str, channel: &str) -> bool {
+    let parameters = connection.get_parameters();
+    connection.is_from_instance(instance)
+        && !parameters.synthetic
+        && parameters.channel.as_deref() == Some(channel)
+}
+
+fn check_protocol_compatibility(
+    runtime_protocol_version: u32,
+    required_protocol_version: Option<u32>,
+) -> Option<String> {
+    let required_protocol_version = required_protocol_version?;
+    if required_protocol_version > PROTOCOL_VERSION {
+        Some(format!(
+            "Template requires protocol version {required_protocol_version} but the server only supports version {PROTOCOL_VERSION}. Please upgrade the zagreus server."
+        ))
+    } else if required_protocol_version > runtime_protocol_version {
+        Some(format!(
+            "Template requires protocol version {required_protocol_version} but the loaded runtime only supports version {runtime_protocol_version}. Please load a newer zagreus runtime."
+        ))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_protocol_compatibility() {
+        assert!(check_protocol_compatibility(PROTOCOL_VERSION, None).is_none());
+        assert!(check_protocol_compatibility(PROTOCOL_VERSION, Some(PROTOCOL_VERSION)).is_none());
+        assert!(
+            check_protocol_compatibility(PROTOCOL_VERSION, Some(PROTOCOL_VERSION + 1)).is_some()
+        );
+        assert!(check_protocol_compatibility(0, Some(PROTOCOL_VERSION)).is_some());
+    }
+
+    #[test]
+    fn test_admit_counts_synthetic_clients() {
+        let limits = ConnectionLimitsConfig {
+            max_connections: Some(1),
+            max_connections_per_instance: None,
+            max_buffered_bytes: None,
+        };
+        let synthetic = ClientParameters::from_query(BTreeMap::new(), true);
+        let renderer = ClientParameters::from_query(BTreeMap::new(), false);
+        let mut connections = HashMap::new();
+        assert!(WebsocketServer::admit(&limits, &connections, "scoreboard", &renderer).is_ok());
+
+        let (sender, _receiver) = tokio::sync::mpsc::unbounded_channel();
+        connections.insert(
+            0,
+            WebsocketConnection::new(
+                sender,
+                String::from("scoreboard"),
+                synthetic.clone(),
+                ClientOrigin::default(),
+                Arc::new(AtomicUsize::new(0)),
+                Arc::new(Metrics::default()),
+            ),
+        );
+        assert!(WebsocketServer::admit(&limits, &connections, "scoreboard", &renderer).is_err());
+        assert!(WebsocketServer::admit(&limits, &connections, "scoreboard", &synthetic).is_ok());
     }
 }
@@ -0,0 +1,162 @@
+use std::sync::Arc;
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::PublicStateConfig;
+use crate::controller::ServerController;
+
+/// Messages of the state stream, an alternative to the command protocol for renderers which are
+/// not browsers (e.g. game engine overlays or native apps).
+#[derive(Serialize)]
+#[serde(tag = "tag", content = "payload")]
+enum StateStreamMessage {
+    /// The full state of the instance.
+    State(Value),
+    /// A JSON merge patch (RFC 7386) to apply to the previously received state.
+    Patch(Value),
+}
+
+/// Sends the state of the instance on connect and after every change, either as full state or
+/// as patch to the previous state. With an allowlist only the public data is streamed and
+/// changes of other data are not noticeable.
+pub async fn stream_state(
+    socket: WebSocket,
+    controller: Arc<ServerController>,
+    instance: String,
+    diff: bool,
+    allowlist: Option<PublicStateConfig>,
+) {
+    info!(
+        "Connected to new state stream client for instance {}.",
+        instance
+    );
+    let (mut sink, mut stream) = socket.split();
+    let mut changes = controller.subscribe_state_changes();
+    let mut previous_state: Option<Value> = None;
+    let mut changed = true;
+
+    loop {
+        if changed {
+            let state = match controller.get_state(&instance).await {
+                Ok(state) => match &allowlist {
+                    Some(allowlist) => serde_json::to_value(state.to_public(allowlist)),
+                    None => serde_json::to_value(state),
+                }
+                .unwrap_or_default(),
+                Err(err) => {
+                    error!("Could not get state of instance {}: {}.", instance, err);
+                    break;
+                }
+            };
+            let message = match previous_state.as_ref() {
+                Some(previous_state) if *previous_state == state => None,
+                Some(previous_state) if diff => {
+                    let patch = create_merge_patch(previous_state, &state);
+                    if patch.as_object().is_some_and(|patch| patch.is_empty()) {
+                        None
+                    } else {
+                        Some(StateStreamMessage::Patch(patch))
+                    }
+                }
+                _ => Some(StateStreamMessage::State(state.clone())),
+            };
+            previous_state = Some(state);
+
+            if let Some(message) = message {
+                let serialized_message = match serde_json::to_string(&message) {
+                    Ok(serialized_message) => serialized_message,
+                    Err(err) => {
+                        error!("Could not serialize state stream message: {}.", err);
+                        break;
+                    }
+                };
+                if let Err(err) = sink.send(Message::Text(serialized_message)).await {
+                    error!("Could not send message on state stream: {}.", err);
+                    break;
+                }
+            }
+        }
+
+        tokio::select! {
+            change = changes.recv() => match change {
+                Ok(changed_instance) => changed = changed_instance == instance,
+                // notifications were missed, so the state may have changed
+                Err(RecvError::Lagged(_)) => changed = true,
+                Err(RecvError::Closed) => break,
+            },
+            message = stream.next() => match message {
+                // messages of state stream clients are ignored
+                Some(Ok(_)) => changed = false,
+                Some(Err(_)) | None => break,
+            },
+        }
+    }
+
+    debug!(
+        "State stream client of instance {} has disconnected.",
+        instance
+    );
+}
+
+/// Creates a JSON merge patch which transforms the previous into the current value. Removed
+/// object members are set to `null`.
+fn create_merge_patch(previous: &Value, current: &Value) -> Value {
+    match (previous, current) {
+        (Value::Object(previous), Value::Object(current)) => {
+            let mut patch = serde_json::Map::new();
+            for (key, previous_value) in previous {
+                match current.get(key) {
+                    Some(current_value) if current_value == previous_value => {}
+                    Some(current_value) => {
+                        patch.insert(
+                            key.clone(),
+                            create_merge_patch(previous_value, current_value),
+                        );
+                    }
+                    None => {
+                        patch.insert(key.clone(), Value::Null);
+                    }
+                }
+            }
+            for (key, current_value) in current {
+                if !previous.contains_key(key) {
+                    patch.insert(key.clone(), current_value.clone());
+                }
+            }
+            Value::Object(patch)
+        }
+        _ => current.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_create_merge_patch() {
+        let previous = json!({
+            "texts": { "HomeScore": "1", "AwayScore": "0" },
+            "classes": { "Scoreboard": { "active": true } },
+        });
+        let current = json!({
+            "texts": { "HomeScore": "2", "AwayScore": "0" },
+            "images": { "Sponsor": { "asset": "sponsor.png" } },
+        });
+
+        let patch = create_merge_patch(&previous, &current);
+        assert_eq!(
+            json!({
+                "texts": { "HomeScore": "2" },
+                "classes": null,
+                "images": { "Sponsor": { "asset": "sponsor.png" } },
+            }),
+            patch
+        );
+        assert_eq!(json!({}), create_merge_patch(&current, &current));
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+/// Last retained value of every topic per instance, sent to renderers as soon as they subscribe
+/// to the topic, so renderers which connect mid-show do not wait for the next update.
+#[derive(Default)]
+pub struct RetainedTopics {
+    values: RwLock<HashMap<String, BTreeMap<String, Value>>>,
+}
+
+impl RetainedTopics {
+    pub async fn retain(&self, instance: &str, topic: &str, value: Value) {
+        self.values
+            .write()
+            .await
+            .entry(instance.to_owned())
+            .or_default()
+            .insert(topic.to_owned(), value);
+    }
+
+    /// Removes the retained value of the topic and returns whether there was one.
+    pub async fn remove(&self, instance: &str, topic: &str) -> bool {
+        let mut values = self.values.write().await;
+        let Some(topics) = values.get_mut(instance) else {
+            return false;
+        };
+        let removed = topics.remove(topic).is_some();
+        if topics.is_empty() {
+            values.remove(instance);
+        }
+        removed
+    }
+
+    pub async fn remove_instance(&self, instance: &str) {
+        self.values.write().await.remove(instance);
+    }
+
+    pub async fn get_all(&self, instance: &str) -> BTreeMap<String, Value> {
+        self.values
+            .read()
+            .await
+            .get(instance)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the retained values of the topics matching the filter.
+    pub async fn get_matching(&self, instance: &str, filter: &str) -> Vec<(String, Value)> {
+        self.values
+            .read()
+            .await
+            .get(instance)
+            .into_iter()
+            .flatten()
+            .filter(|(topic, _)| matches_filter(topic, filter))
+            .map(|(topic, value)| (topic.clone(), value.clone()))
+            .collect()
+    }
+}
+
+/// Whether a topic can be published to, i.e. is not empty and contains no wildcards.
+pub fn is_valid_topic(topic: &str) -> bool {
+    !topic.is_empty() && !topic.contains(['+', '#']) && !topic.contains(char::is_control)
+}
+
+/// Matches the topic against a filter in which `+` stands for one level and a trailing `#` for
+/// all remaining levels, like in MQTT, e.g. `scores/+/home` or `scores/#`.
+pub fn matches_filter(topic: &str, filter: &str) -> bool {
+    let mut topic_levels = topic.split('/');
+    for filter_level in filter.split('/') {
+        if filter_level == "#" {
+            return true;
+        }
+        match topic_levels.next() {
+            Some(topic_level) if filter_level == "+" || filter_level == topic_level => {}
+            _ => return false,
+        }
+    }
+    topic_levels.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_filter() {
+        assert!(matches_filter("scores/court-1/home", "scores/court-1/home"));
+        assert!(matches_filter("scores/court-1/home", "scores/+/home"));
+        assert!(matches_filter("scores/court-1/home", "scores/#"));
+        assert!(matches_filter("scores", "scores/#"));
+        assert!(!matches_filter("scores/court-1/away", "scores/+/home"));
+        assert!(!matches_filter("scores/court-1", "scores/+/home"));
+        assert!(!matches_filter("scores/court-1/home/sets", "scores/+/home"));
+
+        assert!(is_valid_topic("scores/court-1/home"));
+        assert!(!is_valid_topic("scores/+/home"));
+        assert!(!is_valid_topic(""));
+    }
+}
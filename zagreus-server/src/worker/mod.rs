@@ -0,0 +1,142 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::anyhow;
+use tokio::sync::Semaphore;
+
+#[derive(Serialize, Clone, Default, PartialEq, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkerPoolStats {
+    pub max_workers: usize,
+    /// Tasks which are currently running on a worker.
+    pub busy: usize,
+    /// Tasks which wait for a free worker.
+    pub queued: usize,
+}
+
+/// Bounded pool for CPU-heavy work (e.g. hashing uploaded assets). Tasks run on the blocking
+/// threads of tokio, but at most `max_workers` at the same time, so large uploads cannot take up
+/// all cores and delay the websocket broadcasts of the async runtime.
+pub struct WorkerPool {
+    max_workers: usize,
+    permits: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+impl WorkerPool {
+    pub fn new(max_workers: usize) -> WorkerPool {
+        let max_workers = max_workers.max(1);
+        WorkerPool {
+            max_workers,
+            permits: Arc::new(Semaphore::new(max_workers)),
+            queued: AtomicUsize::new(0),
+        }
+    }
+
+    /// Runs the task once a worker is free and returns its result.
+    pub async fn run<T, F>(&self, task: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let queued = QueuedGuard::new(&self.queued);
+        let permit = self.permits.clone().acquire_owned().await;
+        drop(queued);
+        let permit = permit?;
+
+        tokio::task::spawn_blocking(move || {
+            let result = task();
+            drop(permit);
+            result
+        })
+        .await
+        .map_err(|err| anyhow!("Worker task did not complete: {}", err))
+    }
+
+    pub fn get_stats(&self) -> WorkerPoolStats {
+        WorkerPoolStats {
+            max_workers: self.max_workers,
+            busy: self.max_workers - self.permits.available_permits(),
+            queued: self.queued.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Counts a task as queued while it exists, so tasks whose caller stopped waiting (e.g. because
+/// the request was cancelled) are not counted forever.
+struct QueuedGuard<'a> {
+    queued: &'a AtomicUsize,
+}
+
+impl<'a> QueuedGuard<'a> {
+    fn new(queued: &'a AtomicUsize) -> QueuedGuard<'a> {
+        queued.fetch_add(1, Ordering::Relaxed);
+        QueuedGuard { queued }
+    }
+}
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrency_is_limited() {
+        let pool = Arc::new(WorkerPool::new(1));
+        let (started_sender, started_receiver) = std::sync::mpsc::channel();
+        let (release_sender, release_receiver) = std::sync::mpsc::channel::<()>();
+
+        let first = tokio::spawn({
+            let pool = pool.clone();
+            async move {
+                pool.run(move || {
+                    started_sender.send(()).unwrap();
+                    release_receiver.recv().unwrap();
+                    1
+                })
+                .await
+            }
+        });
+        tokio::task::spawn_blocking(move || started_receiver.recv().unwrap())
+            .await
+            .unwrap();
+        let second = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.run(|| 2).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            WorkerPoolStats {
+                max_workers: 1,
+                busy: 1,
+                queued: 1,
+            },
+            pool.get_stats()
+        );
+
+        release_sender.send(()).unwrap();
+        assert_eq!(1, first.await.unwrap().unwrap());
+        assert_eq!(2, second.await.unwrap().unwrap());
+        assert_eq!(0, pool.get_stats().busy);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_task_is_not_queued() {
+        let pool = WorkerPool::new(1);
+        let permit = pool.permits.clone().acquire_owned().await.unwrap();
+
+        let waiting = tokio::time::timeout(Duration::from_millis(20), pool.run(|| 1)).await;
+        assert!(waiting.is_err());
+        assert_eq!(0, pool.get_stats().queued);
+
+        drop(permit);
+        assert_eq!(1, pool.run(|| 1).await.unwrap());
+    }
+}